@@ -29,15 +29,19 @@ fn main() {
 ///
 /// * `cfg` - The start library builder.
 fn configure_for_aarch64(cfg: &mut cc::Build) {
-  const AARCH64_START_FILES: [&'static str; 8] = [
+  const AARCH64_START_FILES: [&'static str; 12] = [
+    "src/arch/aarch64/start/barrier.s",
+    "src/arch/aarch64/start/cache.s",
     "src/arch/aarch64/start/cpu.s",
     "src/arch/aarch64/start/dtb.s",
     "src/arch/aarch64/start/exceptions.s",
+    "src/arch/aarch64/start/guard.s",
     "src/arch/aarch64/start/interrupts.s",
     "src/arch/aarch64/start/mm.s",
+    "src/arch/aarch64/start/percpu.s",
     "src/arch/aarch64/start/spin_lock.s",
     "src/arch/aarch64/start/start.s",
-    "src/arch/aarch64/start/task.s",
+    "src/arch/aarch64/start/time.s",
   ];
 
   cfg
@@ -57,17 +61,21 @@ fn configure_for_aarch64(cfg: &mut cc::Build) {
 ///
 /// * `cfg` - The start library builder.
 fn configure_for_arm(cfg: &mut cc::Build) {
-  const ARM_START_FILES: [&'static str; 10] = [
+  const ARM_START_FILES: [&'static str; 14] = [
+    "src/arch/arm/start/barrier.s",
+    "src/arch/arm/start/cache.s",
     "src/arch/arm/start/cpu.s",
     "src/arch/arm/start/dtb.s",
     "src/arch/arm/start/exceptions.s",
     "src/arch/arm/start/extensions.s",
+    "src/arch/arm/start/guard.s",
     "src/arch/arm/start/interrupts.s",
     "src/arch/arm/start/layout.s",
     "src/arch/arm/start/mm.s",
+    "src/arch/arm/start/percpu.s",
     "src/arch/arm/start/spin_lock.s",
     "src/arch/arm/start/start.s",
-    "src/arch/arm/start/task.s",
+    "src/arch/arm/start/time.s",
   ];
 
   cfg
@@ -0,0 +1,342 @@
+//! AArch64 Address Space Identifier Management
+//!
+//! An Address Space Identifier (ASID) tags the non-global TLB entries of a user
+//! address space. Switching TTBR0_EL1 between address spaces with different
+//! ASIDs does not require invalidating the TLB. Depending on the processor,
+//! ASIDs are either 8 or 16 bits.
+//!
+//! The ASIDs handed out by the allocator are versioned. The bits above the
+//! hardware ASID store the allocator's generation when the ASID was allocated.
+//! When the allocator runs out of hardware ASIDs, it advances the generation,
+//! invalidates the TLBs on all cores, and starts over. An ASID from an older
+//! generation must be refreshed before it is programmed into TTBR0_EL1.
+//!
+//! The allocator tracks the ASID each core last programmed into TTBR0_EL1.
+//! Other cores may still be running with those ASIDs when one core rolls the
+//! allocator over, so their hardware ASIDs are reserved in the new generation
+//! rather than handed out to new address spaces. A reserved ASID keeps its
+//! hardware ASID when it is refreshed.
+//!
+//! Hardware ASID 0 is reserved for tasks that do not have a user address space
+//! and is never allocated.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::mm;
+use crate::arch::cpu;
+use crate::support::bits;
+use crate::sync::SpinLock;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::ptr;
+
+/// The maximum number of ASID bits supported by the architecture.
+pub const MAX_ASID_BITS: usize = 16;
+
+/// The reserved ASID.
+pub const RESERVED_ASID: usize = 0;
+
+/// The number of bitmap words required to track the maximum number of ASIDs.
+const ASID_MAP_WORDS: usize = (1 << MAX_ASID_BITS) >> bits::WORD_BIT_SHIFT;
+
+/// ASID allocator convenience type sized for the maximum number of ASIDs and
+/// cores.
+pub type SystemAsidAllocator = AsidAllocator<ASID_MAP_WORDS, { cpu::MAX_CORES }>;
+
+/// The system ASID allocator.
+static mut ASID_ALLOCATOR: SpinLock<SystemAsidAllocator> = SpinLock::new(AsidAllocator::new(8));
+
+/// Allocates versioned ASIDs. The allocator uses a bitmap of length MAP_WORDS
+/// to track allocated ASIDs, thus the allocator can track up to
+/// `MAP_WORDS << bits::WORD_BIT_SHIFT` ASIDs. The allocator tracks the active
+/// ASIDs of up to CORES cores.
+pub struct AsidAllocator<const MAP_WORDS: usize, const CORES: usize> {
+  asid_bits: usize,
+  generation: usize,
+  used: bits::Bitmap<MAP_WORDS>,
+
+  /// The ASID each core last activated since the last rollover, or the
+  /// reserved ASID if the core has not activated an ASID since then.
+  active: [usize; CORES],
+
+  /// The ASID each core was using at the last rollover. The hardware ASIDs
+  /// are reserved in the current generation.
+  reserved: [usize; CORES],
+}
+
+impl<const MAP_WORDS: usize, const CORES: usize> AsidAllocator<MAP_WORDS, CORES> {
+  /// Construct a new ASID allocator.
+  ///
+  /// # Parameters
+  ///
+  /// * `asid_bits` - The number of hardware ASID bits.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: The bitmap must be large enough to track all hardware ASIDs.
+  pub const fn new(asid_bits: usize) -> Self {
+    assert!(asid_bits > 0 && asid_bits <= MAX_ASID_BITS);
+    assert!((1 << asid_bits) <= MAP_WORDS << bits::WORD_BIT_SHIFT);
+
+    Self {
+      asid_bits,
+      generation: 1,
+      used: bits::Bitmap::new(usize::MAX),
+      active: [RESERVED_ASID; CORES],
+      reserved: [RESERVED_ASID; CORES],
+    }
+  }
+
+  /// Reset the allocator.
+  ///
+  /// # Parameters
+  ///
+  /// * `asid_bits` - The number of hardware ASID bits.
+  ///
+  /// # Description
+  ///
+  /// Releases all ASIDs and returns to the first generation.
+  pub fn reset(&mut self, asid_bits: usize) {
+    assert!(asid_bits > 0 && asid_bits <= MAX_ASID_BITS);
+    assert!((1 << asid_bits) <= MAP_WORDS << bits::WORD_BIT_SHIFT);
+
+    self.asid_bits = asid_bits;
+    self.generation = 1;
+    self.used.clear_all_bits();
+    self.active = [RESERVED_ASID; CORES];
+    self.reserved = [RESERVED_ASID; CORES];
+  }
+
+  /// Allocate an ASID.
+  ///
+  /// # Description
+  ///
+  /// If all hardware ASIDs in the current generation are allocated, the
+  /// allocator rolls over to a new generation.
+  ///
+  ///   NOTE: Rolling over reserves one hardware ASID per core that is using an
+  ///         ASID, so the processor must support more ASIDs than there are
+  ///         cores.
+  ///
+  /// # Returns
+  ///
+  /// A versioned ASID.
+  pub fn alloc(&mut self) -> usize {
+    let index = match self.find_free() {
+      Some(index) => index,
+      None => {
+        self.rollover();
+        self
+          .find_free()
+          .expect("No ASIDs available after reserving active ASIDs.")
+      }
+    };
+
+    self.used.set_bit(index);
+
+    // Bit 0 of the bitmap represents hardware ASID 1.
+    (self.generation << self.asid_bits) | (index + 1)
+  }
+
+  /// Free an ASID.
+  ///
+  /// # Parameters
+  ///
+  /// * `asid` - The versioned ASID.
+  ///
+  /// # Description
+  ///
+  /// The TLB entries tagged with the ASID are invalidated on all cores before
+  /// the ASID can be recycled. Reserved ASIDs and ASIDs from older generations
+  /// are ignored.
+  pub fn free(&mut self, asid: usize) {
+    if !self.is_current(asid) {
+      return;
+    }
+
+    let hw_asid = self.get_hardware_asid(asid);
+
+    if hw_asid == RESERVED_ASID {
+      return;
+    }
+
+    mm::invalidate_tlb_asid_broadcast(hw_asid);
+    self.used.clear_bit(hw_asid - 1);
+  }
+
+  /// Refresh an ASID.
+  ///
+  /// # Parameters
+  ///
+  /// * `asid` - The versioned ASID.
+  ///
+  /// # Description
+  ///
+  /// An ASID from an older generation that was reserved at the last rollover
+  /// keeps its hardware ASID in the current generation.
+  ///
+  /// # Returns
+  ///
+  /// The ASID if it is reserved or from the current generation, otherwise an
+  /// ASID in the current generation.
+  pub fn refresh(&mut self, asid: usize) -> usize {
+    if asid == RESERVED_ASID || self.is_current(asid) {
+      return asid;
+    }
+
+    if let Some(asid) = self.update_reserved(asid) {
+      return asid;
+    }
+
+    self.alloc()
+  }
+
+  /// Refresh an ASID and make it the active ASID on a core.
+  ///
+  /// # Parameters
+  ///
+  /// * `core_idx` - The core index.
+  /// * `asid` - The versioned ASID.
+  ///
+  /// # Description
+  ///
+  /// Must be called by the core that is about to program the ASID into
+  /// TTBR0_EL1. See `refresh()`.
+  ///
+  /// # Returns
+  ///
+  /// The refreshed ASID.
+  pub fn activate(&mut self, core_idx: usize, asid: usize) -> usize {
+    assert!(core_idx < CORES);
+
+    let asid = self.refresh(asid);
+    self.active[core_idx] = asid;
+    asid
+  }
+
+  /// Check if an ASID is from the current generation.
+  ///
+  /// # Parameters
+  ///
+  /// * `asid` - The versioned ASID.
+  pub fn is_current(&self, asid: usize) -> bool {
+    (asid >> self.asid_bits) == self.generation
+  }
+
+  /// Get the hardware ASID from a versioned ASID.
+  ///
+  /// # Parameters
+  ///
+  /// * `asid` - The versioned ASID.
+  pub fn get_hardware_asid(&self, asid: usize) -> usize {
+    asid & ((1 << self.asid_bits) - 1)
+  }
+
+  /// Get the current generation.
+  pub fn get_generation(&self) -> usize {
+    self.generation
+  }
+
+  /// Get the number of ASIDs allocated in the current generation.
+  pub fn get_asids_allocated(&self) -> usize {
+    self.used.ones()
+  }
+
+  /// Find a free hardware ASID in the current generation.
+  ///
+  /// # Returns
+  ///
+  /// The bitmap index of the free ASID, or None if all ASIDs are allocated.
+  fn find_free(&self) -> Option<usize> {
+    let index = self.used.first_zero()?;
+
+    // Hardware ASID 0 is reserved, so only `2^asid_bits - 1` ASIDs are
+    // available.
+    if index >= (1 << self.asid_bits) - 1 {
+      return None;
+    }
+
+    Some(index)
+  }
+
+  /// Move a reserved ASID to the current generation.
+  ///
+  /// # Parameters
+  ///
+  /// * `asid` - The versioned ASID from an older generation.
+  ///
+  /// # Description
+  ///
+  /// Several cores may have reserved the same ASID. All of the reservations
+  /// are updated.
+  ///
+  /// # Returns
+  ///
+  /// The ASID with the current generation, or None if the ASID is not
+  /// reserved.
+  fn update_reserved(&mut self, asid: usize) -> Option<usize> {
+    let new_asid = (self.generation << self.asid_bits) | self.get_hardware_asid(asid);
+    let mut found = false;
+
+    for reserved in &mut self.reserved {
+      if *reserved == asid {
+        *reserved = new_asid;
+        found = true;
+      }
+    }
+
+    found.then_some(new_asid)
+  }
+
+  /// Advance to a new generation.
+  ///
+  /// # Description
+  ///
+  /// Releases all ASIDs except the ASIDs the cores are using, and invalidates
+  /// the TLBs on all cores so that entries tagged with ASIDs from the previous
+  /// generation cannot be hit by the new generation.
+  ///
+  /// A core that has not activated an ASID since the previous rollover is
+  /// still using the ASID it reserved then.
+  fn rollover(&mut self) {
+    self.generation += 1;
+    self.used.clear_all_bits();
+
+    for (active, reserved) in self.active.iter_mut().zip(self.reserved.iter_mut()) {
+      let asid = match *active {
+        RESERVED_ASID => *reserved,
+        asid => asid,
+      };
+
+      *active = RESERVED_ASID;
+      *reserved = asid;
+
+      if asid != RESERVED_ASID {
+        self.used.set_bit((asid & ((1 << self.asid_bits) - 1)) - 1);
+      }
+    }
+
+    mm::invalidate_tlb_all_broadcast();
+  }
+}
+
+/// Initialize the system ASID allocator with the number of ASID bits supported
+/// by the processor.
+///
+/// # Description
+///
+///   NOTE: Must only be called once while the kernel is single-threaded.
+pub fn init() {
+  get_asid_allocator().lock().reset(mm::get_asid_bits());
+}
+
+/// Get the system ASID allocator.
+pub fn get_asid_allocator() -> &'static SpinLock<SystemAsidAllocator> {
+  unsafe { ptr::addr_of!(ASID_ALLOCATOR).as_ref().unwrap() }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
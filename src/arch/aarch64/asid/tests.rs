@@ -0,0 +1,176 @@
+//! AArch64 ASID Management Tests
+
+use super::{AsidAllocator, RESERVED_ASID};
+use crate::debug_print;
+use crate::test::{self, tlb};
+use crate::{check_eq, check_neq, execute_test};
+
+/// Use 4-bit ASIDs to keep the tests short. There are 15 ASIDs available per
+/// generation.
+const TEST_ASID_BITS: usize = 4;
+
+/// The number of ASIDs available per generation.
+const TEST_ASID_COUNT: usize = (1 << TEST_ASID_BITS) - 1;
+
+/// The number of test cores.
+const TEST_CORES: usize = 2;
+
+/// Test ASID allocator type.
+type TestAsidAllocator = AsidAllocator<1, TEST_CORES>;
+
+/// Run ASID tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_allocation);
+  execute_test!(context, test_recycling);
+  execute_test!(context, test_rollover);
+  execute_test!(context, test_rollover_active);
+}
+
+/// Test allocating all ASIDs in a generation.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Every hardware ASID except the reserved ASID should be allocated exactly
+/// once in the first generation.
+fn test_allocation(context: &mut test::TestContext) {
+  let mut allocator = TestAsidAllocator::new(TEST_ASID_BITS);
+
+  for i in 0..TEST_ASID_COUNT {
+    let asid = allocator.alloc();
+    check_eq!(context, allocator.is_current(asid), true);
+    check_eq!(context, allocator.get_hardware_asid(asid), i + 1);
+    check_neq!(context, allocator.get_hardware_asid(asid), RESERVED_ASID);
+  }
+
+  check_eq!(context, allocator.get_generation(), 1);
+  check_eq!(context, allocator.get_asids_allocated(), TEST_ASID_COUNT);
+}
+
+/// Test recycling freed ASIDs.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_recycling(context: &mut test::TestContext) {
+  let mut allocator = TestAsidAllocator::new(TEST_ASID_BITS);
+
+  let a = allocator.alloc();
+  let b = allocator.alloc();
+  check_neq!(context, a, b);
+
+  // A freed ASID is reused without advancing the generation.
+  allocator.free(a);
+  check_eq!(context, allocator.get_asids_allocated(), 1);
+  check_eq!(context, allocator.alloc(), a);
+  check_eq!(context, allocator.get_generation(), 1);
+
+  // Freeing the reserved ASID does nothing.
+  allocator.free(RESERVED_ASID);
+  check_eq!(context, allocator.get_asids_allocated(), 2);
+  check_eq!(context, allocator.refresh(RESERVED_ASID), RESERVED_ASID);
+
+  // Refreshing a current ASID returns the same ASID.
+  check_eq!(context, allocator.refresh(b), b);
+  check_eq!(context, allocator.get_asids_allocated(), 2);
+}
+
+/// Test rolling over to a new generation.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Exhausting the ASIDs should advance the generation and invalidate all TLB
+/// entries on all cores. ASIDs from the previous generation must be refreshed.
+fn test_rollover(context: &mut test::TestContext) {
+  let mut allocator = TestAsidAllocator::new(TEST_ASID_BITS);
+  let first = allocator.alloc();
+
+  for _ in 1..TEST_ASID_COUNT {
+    _ = allocator.alloc();
+  }
+
+  tlb::clear_invalidations();
+
+  let asid = allocator.alloc();
+  check_eq!(context, allocator.get_generation(), 2);
+  check_eq!(context, allocator.get_hardware_asid(asid), 1);
+  check_eq!(context, allocator.get_asids_allocated(), 1);
+  check_eq!(context, tlb::was_invalidated_broadcast(0, usize::MAX), true);
+
+  // The first ASID has the same hardware ASID as the new ASID, but is stale.
+  check_eq!(context, allocator.get_hardware_asid(first), 1);
+  check_eq!(context, allocator.is_current(first), false);
+
+  // Freeing a stale ASID must not release the new generation's ASID.
+  allocator.free(first);
+  check_eq!(context, allocator.get_asids_allocated(), 1);
+
+  // Refreshing the stale ASID allocates a new ASID in the current generation.
+  let refreshed = allocator.refresh(first);
+  check_eq!(context, allocator.is_current(refreshed), true);
+  check_eq!(context, allocator.get_hardware_asid(refreshed), 2);
+  check_eq!(context, allocator.get_asids_allocated(), 2);
+}
+
+/// Test that rolling over reserves the ASIDs the cores are using.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Both cores are running with ASIDs when the allocator rolls over, so their
+/// hardware ASIDs must not be handed out in the new generation. Refreshing a
+/// reserved ASID keeps its hardware ASID. A core that does not activate an
+/// ASID before the next rollover keeps its reservation.
+fn test_rollover_active(context: &mut test::TestContext) {
+  let mut allocator = TestAsidAllocator::new(TEST_ASID_BITS);
+  let first = allocator.alloc();
+  let second = allocator.alloc();
+
+  check_eq!(context, allocator.activate(0, first), first);
+  check_eq!(context, allocator.activate(1, second), second);
+
+  for _ in 2..TEST_ASID_COUNT {
+    _ = allocator.alloc();
+  }
+
+  let asid = allocator.alloc();
+  check_eq!(context, allocator.get_generation(), 2);
+  check_eq!(context, allocator.get_hardware_asid(asid), 3);
+  check_eq!(context, allocator.get_asids_allocated(), 3);
+
+  // Refreshing a reserved ASID keeps the hardware ASID.
+  let refreshed = allocator.activate(0, first);
+  check_eq!(context, allocator.is_current(refreshed), true);
+  check_eq!(context, allocator.get_hardware_asid(refreshed), 1);
+  check_eq!(context, allocator.get_asids_allocated(), 3);
+
+  for _ in 3..TEST_ASID_COUNT {
+    _ = allocator.alloc();
+  }
+
+  // Core 1 has not activated an ASID since the last rollover, so it is still
+  // using the second ASID.
+  let asid = allocator.alloc();
+  check_eq!(context, allocator.get_generation(), 3);
+  check_eq!(context, allocator.get_hardware_asid(asid), 3);
+  check_eq!(context, allocator.get_asids_allocated(), 3);
+
+  let refreshed = allocator.refresh(second);
+  check_eq!(context, allocator.is_current(refreshed), true);
+  check_eq!(context, allocator.get_hardware_asid(refreshed), 2);
+  check_eq!(context, allocator.get_asids_allocated(), 3);
+}
@@ -13,3 +13,17 @@ use crate::arch;
 extern "C" fn pk_handle_exception(_esr_el1: usize, _far_el1: usize, _cpu_context: usize) {
   arch::cpu::halt();
 }
+
+/// IRQ handler.
+///
+/// # Parameters
+///
+/// * `cpu_context` - Pointer to the saved CPU context structure.
+///
+/// # Description
+///
+/// Dispatches the interrupt to its registered handler. See `irq::dispatch()`.
+#[unsafe(no_mangle)]
+extern "C" fn pk_handle_irq(_cpu_context: usize) {
+  arch::irq::dispatch();
+}
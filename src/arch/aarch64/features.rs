@@ -0,0 +1,68 @@
+//! AArch64 CPU Feature Decoding
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::cpu::CpuFeatures;
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// ID_AA64MMFR0_EL1 fields. See D17.2.64.
+const PA_RANGE_SHIFT: usize = 0;
+const ASID_BITS_SHIFT: usize = 4;
+const TGRAN16_SHIFT: usize = 20;
+const TGRAN64_SHIFT: usize = 24;
+const TGRAN4_SHIFT: usize = 28;
+const FIELD_MASK: usize = 0xf;
+
+/// ASIDBits value for 16-bit ASIDs.
+const ASID_BITS_16: usize = 0b0010;
+
+/// TGran16 value when 16 KiB granules are not supported.
+const TGRAN16_NOT_SUPPORTED: usize = 0b0000;
+
+/// TGran4 and TGran64 value when the granule is not supported.
+const TGRAN_NOT_SUPPORTED: usize = 0b1111;
+
+/// Physical address sizes indexed by PARange.
+const PA_RANGE_BITS: [usize; 7] = [32, 36, 40, 42, 44, 48, 52];
+
+/// Decode ID_AA64MMFR0_EL1.
+///
+/// # Parameters
+///
+/// * `mmfr0` - The register value.
+///
+/// # Description
+///
+/// Newer versions of the architecture only add larger PARange values, so an
+/// unknown PARange value is decoded as the largest known physical address
+/// size.
+///
+/// # Returns
+///
+/// The decoded features. AArch64 always uses long descriptors.
+pub fn decode_mmfr0(mmfr0: usize) -> CpuFeatures {
+  let field = |shift: usize| (mmfr0 >> shift) & FIELD_MASK;
+  let pa_range = field(PA_RANGE_SHIFT);
+
+  CpuFeatures {
+    pa_bits: *PA_RANGE_BITS
+      .get(pa_range)
+      .unwrap_or(PA_RANGE_BITS.last().unwrap()),
+    asid_bits: if field(ASID_BITS_SHIFT) == ASID_BITS_16 {
+      16
+    } else {
+      8
+    },
+    long_descriptors: true,
+    granule_4k: field(TGRAN4_SHIFT) != TGRAN_NOT_SUPPORTED,
+    granule_16k: field(TGRAN16_SHIFT) != TGRAN16_NOT_SUPPORTED,
+    granule_64k: field(TGRAN64_SHIFT) != TGRAN_NOT_SUPPORTED,
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
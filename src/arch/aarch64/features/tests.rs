@@ -0,0 +1,79 @@
+//! AArch64 CPU Feature Decoding Tests
+
+use super::decode_mmfr0;
+use crate::debug_print;
+use crate::{check_eq, execute_test, test};
+
+/// ID_AA64MMFR0_EL1 as reported by a Cortex-A53: 40-bit physical addresses,
+/// 16-bit ASIDs, and 4 KiB and 64 KiB granules.
+const CORTEX_A53_MMFR0: usize = 0x0000_1122;
+
+/// Synthetic ID_AA64MMFR0_EL1 with 48-bit physical addresses, 8-bit ASIDs, and
+/// only 16 KiB granules.
+const ONLY_16K_MMFR0: usize = 0xff10_0005;
+
+/// Run CPU feature decoding tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_cortex_a53);
+  execute_test!(context, test_granules);
+  execute_test!(context, test_pa_range);
+}
+
+/// Test decoding a Cortex-A53's features.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_cortex_a53(context: &mut test::TestContext) {
+  let features = decode_mmfr0(CORTEX_A53_MMFR0);
+
+  check_eq!(context, features.pa_bits, 40);
+  check_eq!(context, features.asid_bits, 16);
+  check_eq!(context, features.long_descriptors, true);
+  check_eq!(context, features.granule_4k, true);
+  check_eq!(context, features.granule_16k, false);
+  check_eq!(context, features.granule_64k, true);
+}
+
+/// Test decoding the granule and ASID fields.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// TGran16 uses 0 for not supported while TGran4 and TGran64 use 0xf, so a
+/// register with all three fields inverted must invert the support flags.
+fn test_granules(context: &mut test::TestContext) {
+  let features = decode_mmfr0(ONLY_16K_MMFR0);
+
+  check_eq!(context, features.pa_bits, 48);
+  check_eq!(context, features.asid_bits, 8);
+  check_eq!(context, features.granule_4k, false);
+  check_eq!(context, features.granule_16k, true);
+  check_eq!(context, features.granule_64k, false);
+}
+
+/// Test decoding every PARange value.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Unknown values decode as the largest known size.
+fn test_pa_range(context: &mut test::TestContext) {
+  const EXPECTED: [usize; 16] = [
+    32, 36, 40, 42, 44, 48, 52, 52, 52, 52, 52, 52, 52, 52, 52, 52,
+  ];
+
+  for (pa_range, exp) in EXPECTED.iter().enumerate() {
+    check_eq!(context, decode_mmfr0(pa_range).pa_bits, *exp);
+  }
+}
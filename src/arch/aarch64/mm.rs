@@ -1,9 +1,28 @@
 //! AArch64 Memory Management
 
-use crate::arch::memory::{MappingStrategy, PageAllocator};
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::arch::memory::{
+  DescriptorInfo, DescriptorKind, MappingCoalescer, MappingStrategy, PageAllocator,
+};
+use crate::support::addr::{PhysAddr, VirtAddr};
+use crate::support::bitfield::BitField;
 use crate::support::bits;
+#[cfg(feature = "module_tests")]
+use crate::test;
 use core::{cmp, ptr, slice};
 
+unsafe extern "C" {
+  fn mmu_invalidate_tlb_range(virt_addr: usize, pages: usize);
+  fn mmu_invalidate_tlb_range_broadcast(virt_addr: usize, pages: usize);
+  fn mmu_invalidate_tlb_all();
+  fn mmu_invalidate_tlb_all_broadcast();
+  fn mmu_invalidate_tlb_asid_broadcast(asid: usize);
+  fn mmu_get_asid_bits() -> usize;
+  fn mmu_set_ttbr0(table_addr: usize, asid: usize);
+}
+
 /// All levels use nine bits of the address for table indices.
 const TABLE_SHIFT: usize = 9;
 const INDEX_MASK: usize = (1 << TABLE_SHIFT) - 1;
@@ -13,6 +32,10 @@ const LEVEL_3_SHIFT: usize = LEVEL_4_SHIFT + TABLE_SHIFT;
 const LEVEL_2_SHIFT: usize = LEVEL_3_SHIFT + TABLE_SHIFT;
 const LEVEL_1_SHIFT: usize = LEVEL_2_SHIFT + TABLE_SHIFT;
 
+/// The tables translate bits [47:0] of a virtual address. The upper bits select
+/// the user or kernel tables.
+const VIRT_ADDR_MASK: usize = (1 << (LEVEL_1_SHIFT + TABLE_SHIFT)) - 1;
+
 /// Tables are a single page at all levels.
 const TABLE_SIZE: usize = super::get_page_size();
 
@@ -28,21 +51,24 @@ const LEVEL_3_BLOCK_MASK: usize = 0x0000_ffff_ffe0_0000;
 /// Levels 1, 2, and 3, and indicates a page entry at Level 4. 0b01 indicates a
 /// block entry at Levels 2 and 3, but is invalid at Levels 1 and 4. 0bn0 always
 /// indicates an invalid entry regardless of `n`.
-const MM_PAGE_TABLE_FLAG: usize = 0b11 << 0;
-const MM_PAGE_FLAG: usize = 0b11 << 0;
-const MM_BLOCK_FLAG: usize = 0b01 << 0;
-const MM_ACCESS_FLAG: usize = 0b1 << 10;
+const DESC_TYPE: BitField = BitField::new(0, 2);
+const MM_PAGE_TABLE_FLAG: usize = 0b11;
+const MM_PAGE_FLAG: usize = 0b11;
+const MM_BLOCK_FLAG: usize = 0b01;
+
+/// Bits [4:2] of a block or page entry are the MAIR index of the memory
+/// attributes, and bit 10 is the access flag.
+const DESC_ATTR_IDX: BitField = BitField::new(2, 3);
+const DESC_ACCESS_FLAG: BitField = BitField::new(10, 1);
 
 /// The start code has already configured the MAIR registers. Only the memory
 /// type indices are needed here. See `mm.s`.
 const MM_NORMAL_MAIR_IDX: usize = 0x0;
 const MM_DEVICE_MAIR_IDX: usize = 0x1;
 
-const TYPE_MASK: usize = 0x3;
-
 /// Translation table level.
 #[derive(Clone, Copy, PartialEq)]
-enum TableLevel {
+pub enum TableLevel {
   Level1,
   Level2,
   Level3,
@@ -74,11 +100,13 @@ pub fn direct_map_memory(
   allocator: &mut impl PageAllocator,
   strategy: MappingStrategy,
 ) {
+  let base = PhysAddr::new(base);
+
   fill_table(
     virtual_base,
     TableLevel::Level1,
-    pages_start,
-    virtual_base + base,
+    PhysAddr::new(pages_start),
+    base.to_virt(virtual_base),
     base,
     size,
     device,
@@ -117,9 +145,9 @@ pub fn map_memory(
   fill_table(
     virtual_base,
     TableLevel::Level1,
-    pages_start,
-    virt,
-    base,
+    PhysAddr::new(pages_start),
+    VirtAddr::new(virt),
+    PhysAddr::new(base),
     size,
     device,
     allocator,
@@ -127,6 +155,311 @@ pub fn map_memory(
   );
 }
 
+/// Unmap a range of virtual addresses.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel segment base address.
+/// * `pages_start` - The address of the task's starting page table.
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Clears the page and section entries covering the range, then invalidates the
+/// TLB for the range. See `invalidate_tlb_mapping()`. Sections must be unmapped
+/// in their entirety. Unmapping part of a section will assert.
+///
+///   NOTE: Tables that become empty are not freed.
+pub fn unmap_memory(virtual_base: usize, pages_start: usize, virt: usize, size: usize) {
+  let page_size = super::get_page_size();
+
+  assert!(bits::is_aligned(virt, page_size));
+  assert!(bits::is_aligned(size, page_size));
+
+  clear_table(
+    virtual_base,
+    TableLevel::Level1,
+    PhysAddr::new(pages_start),
+    VirtAddr::new(virt),
+    size,
+  );
+
+  invalidate_tlb_mapping(virt, size);
+}
+
+/// Count the number of tables a mapping would allocate.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `base` - Base of the physical address range.
+/// * `size` - Size of the physical address range.
+/// * `strategy` - The mapping strategy.
+///
+/// # Description
+///
+/// Walks the range the same way `map_memory()` would without reading or
+/// modifying any tables.
+///
+///   NOTE: The count assumes the starting table exists, but that no lower level
+///         tables exist. If part of the range is already mapped, the count is
+///         an upper bound.
+///
+/// # Returns
+///
+/// The number of table pages the mapping would allocate.
+pub fn count_tables_needed(
+  virt: usize,
+  base: usize,
+  size: usize,
+  strategy: MappingStrategy,
+) -> usize {
+  let page_size = super::get_page_size();
+
+  assert!(bits::is_aligned(virt, page_size));
+  assert!(bits::is_aligned(base, page_size));
+
+  let table_level = TableLevel::Level1;
+
+  match strategy {
+    MappingStrategy::Compact => count_tables_compact(table_level, virt, size),
+    MappingStrategy::Granular => count_tables_granular(table_level, virt, size),
+  }
+}
+
+/// Invalidate the TLB entries for a range of virtual addresses after changing
+/// the mappings.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Mappings in the upper half of the address space are kernel-global and may be
+/// cached by any core, so the invalidation is broadcast. Mappings in the lower
+/// half belong to the task running on the current core, so only the current
+/// core's TLB is invalidated.
+///
+///   NOTE: The range must not span the boundary between local and global
+///         mappings.
+pub fn invalidate_tlb_mapping(virt: usize, size: usize) {
+  if requires_broadcast(virt) {
+    invalidate_tlb_range_broadcast(virt, size);
+  } else {
+    invalidate_tlb_range(virt, size);
+  }
+}
+
+/// Invalidate the current core's TLB entries for a range of virtual addresses.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// The range is expanded to page boundaries. Any translation table changes
+/// made prior to calling are guaranteed to be visible before invalidation.
+pub fn invalidate_tlb_range(virt: usize, size: usize) {
+  let (base, pages) = get_invalidation_pages(virt, size);
+
+  #[cfg(feature = "module_tests")]
+  test::tlb::record_invalidation(base, pages << super::get_page_shift(), false);
+
+  unsafe {
+    mmu_invalidate_tlb_range(base, pages);
+  }
+}
+
+/// Invalidate the TLB entries for a range of virtual addresses on all cores.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// The range is expanded to page boundaries. Any translation table changes
+/// made prior to calling are guaranteed to be visible to all cores before
+/// invalidation.
+pub fn invalidate_tlb_range_broadcast(virt: usize, size: usize) {
+  let (base, pages) = get_invalidation_pages(virt, size);
+
+  #[cfg(feature = "module_tests")]
+  test::tlb::record_invalidation(base, pages << super::get_page_shift(), true);
+
+  unsafe {
+    mmu_invalidate_tlb_range_broadcast(base, pages);
+  }
+}
+
+/// Invalidate all of the current core's TLB entries.
+pub fn invalidate_tlb_all() {
+  #[cfg(feature = "module_tests")]
+  test::tlb::record_invalidation(0, usize::MAX, false);
+
+  unsafe {
+    mmu_invalidate_tlb_all();
+  }
+}
+
+/// Invalidate all TLB entries on all cores.
+pub fn invalidate_tlb_all_broadcast() {
+  #[cfg(feature = "module_tests")]
+  test::tlb::record_invalidation(0, usize::MAX, true);
+
+  unsafe {
+    mmu_invalidate_tlb_all_broadcast();
+  }
+}
+
+/// Invalidate all non-global TLB entries tagged with an ASID on all cores.
+///
+/// # Parameters
+///
+/// * `asid` - The ASID to invalidate.
+pub fn invalidate_tlb_asid_broadcast(asid: usize) {
+  unsafe {
+    mmu_invalidate_tlb_asid_broadcast(asid);
+  }
+}
+
+/// Get the number of ASID bits supported by the processor.
+///
+/// # Returns
+///
+/// Either 8 or 16.
+pub fn get_asid_bits() -> usize {
+  unsafe { mmu_get_asid_bits() }
+}
+
+/// Set the user address space translation table and ASID for the current core.
+///
+/// # Parameters
+///
+/// * `table_addr` - The physical address of the Level 1 table.
+/// * `asid` - The address space's ASID.
+pub fn set_user_table(table_addr: usize, asid: usize) {
+  unsafe {
+    mmu_set_ttbr0(table_addr, asid);
+  }
+}
+
+/// Decode a descriptor for debugging.
+///
+/// # Parameters
+///
+/// * `table_level` - The table level of the descriptor.
+/// * `desc` - The descriptor.
+///
+/// # Description
+///
+/// The memory type and access flag are only decoded for blocks and pages.
+///
+/// # Returns
+///
+/// The decoded descriptor.
+pub fn describe_descriptor(table_level: TableLevel, desc: usize) -> DescriptorInfo {
+  let Some(phys_addr) = get_phys_addr_from_descriptor(table_level, desc) else {
+    return DescriptorInfo::invalid();
+  };
+
+  let kind = if is_pointer_entry(table_level, desc) {
+    DescriptorKind::Table
+  } else if table_level == TableLevel::Level4 {
+    DescriptorKind::Page
+  } else {
+    DescriptorKind::Block
+  };
+
+  if kind == DescriptorKind::Table {
+    return DescriptorInfo {
+      kind,
+      phys_addr,
+      ..DescriptorInfo::invalid()
+    };
+  }
+
+  DescriptorInfo {
+    kind,
+    phys_addr,
+    device: DESC_ATTR_IDX.get(desc) == MM_DEVICE_MAIR_IDX,
+    access_flag: DESC_ACCESS_FLAG.get(desc) != 0,
+  }
+}
+
+/// Report the mappings in a set of kernel translation tables.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel segment base address.
+/// * `pages_start` - The address of the Level 1 table.
+/// * `emit` - Called with the base virtual address, size, and first decoded
+///   descriptor of each mapped range.
+///
+/// # Description
+///
+/// Walks the tables depth-first and reports ranges in ascending address order.
+/// Contiguous blocks or pages with the same attributes that map contiguous
+/// physical memory are reported as a single range.
+///
+///   NOTE: The tables are assumed to translate the kernel segment. Virtual
+///         addresses are reported with the upper bits of the kernel segment
+///         base.
+pub fn dump_tables(
+  virtual_base: usize,
+  pages_start: usize,
+  mut emit: impl FnMut(usize, usize, &DescriptorInfo),
+) {
+  let mut coalescer = MappingCoalescer::new();
+
+  dump_table(
+    virtual_base,
+    TableLevel::Level1,
+    PhysAddr::new(pages_start),
+    virtual_base & !VIRT_ADDR_MASK,
+    &mut coalescer,
+    &mut emit,
+  );
+
+  coalescer.finish(&mut emit);
+}
+
+/// Check if changes to a mapping must be broadcast to all cores.
+///
+/// # Parameters
+///
+/// * `virt` - The virtual address of the mapping.
+///
+/// # Returns
+///
+/// True if the address is in the kernel's address space, false otherwise.
+fn requires_broadcast(virt: usize) -> bool {
+  virt >= super::get_kernel_virtual_base()
+}
+
+/// Get the page range covering a range of virtual addresses.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Returns
+///
+/// The page-aligned base address and the number of pages.
+fn get_invalidation_pages(virt: usize, size: usize) -> (usize, usize) {
+  let page_size = super::get_page_size();
+  let base = bits::align_down(virt, page_size);
+  let pages = (size + (virt - base) + page_size - 1) >> super::get_page_shift();
+  (base, pages)
+}
+
 /// Wrapper for strategy-specific fill functions.
 ///
 /// # Parameters
@@ -143,9 +476,9 @@ pub fn map_memory(
 fn fill_table(
   virtual_base: usize,
   table_level: TableLevel,
-  table_addr: usize,
-  virt: usize,
-  base: usize,
+  table_addr: PhysAddr,
+  virt: VirtAddr,
+  base: PhysAddr,
   size: usize,
   device: bool,
   allocator: &mut impl PageAllocator,
@@ -203,27 +536,27 @@ fn fill_table(
 fn fill_table_compact(
   virtual_base: usize,
   table_level: TableLevel,
-  table_addr: usize,
-  virt: usize,
-  base: usize,
+  table_addr: PhysAddr,
+  virt: VirtAddr,
+  base: PhysAddr,
   size: usize,
   device: bool,
   allocator: &mut impl PageAllocator,
 ) {
   let page_size = super::get_page_size();
 
-  assert!(bits::is_aligned(base, page_size));
-  assert!(bits::is_aligned(virt, page_size));
+  assert!(bits::is_aligned(base.as_usize(), page_size));
+  assert!(bits::is_aligned(virt.as_usize(), page_size));
 
   let entry_size = get_table_entry_size(table_level);
   let mut virt = virt;
   let mut base = base;
   let mut size = size;
-  let table = get_table(virtual_base + table_addr);
+  let table = get_table(table_addr.to_virt(virtual_base).as_usize());
 
   while size >= page_size {
-    let idx = get_descriptor_index(virt, table_level);
-    let aligned = bits::is_aligned(virt, entry_size);
+    let idx = get_descriptor_index(virt.as_usize(), table_level);
+    let aligned = bits::is_aligned(virt.as_usize(), entry_size);
     let mut fill_size = entry_size;
 
     // If the base virtual address is not aligned on the entry size or the size
@@ -249,7 +582,7 @@ fn fill_table_compact(
         MappingStrategy::Compact,
       );
     } else {
-      table[idx] = make_descriptor(table_level, base, device).unwrap();
+      table[idx] = make_descriptor(table_level, base.as_usize(), device).unwrap();
     }
 
     virt += fill_size;
@@ -279,26 +612,30 @@ fn fill_table_compact(
 fn fill_table_granular(
   virtual_base: usize,
   table_level: TableLevel,
-  table_addr: usize,
-  virt: usize,
-  base: usize,
+  table_addr: PhysAddr,
+  virt: VirtAddr,
+  base: PhysAddr,
   size: usize,
   device: bool,
   allocator: &mut impl PageAllocator,
 ) {
   let page_size = super::get_page_size();
 
-  assert!(bits::is_aligned(virt, page_size));
-  assert!(bits::is_aligned(base, page_size));
+  assert!(bits::is_aligned(virt.as_usize(), page_size));
+  assert!(bits::is_aligned(base.as_usize(), page_size));
 
   let entry_size = get_table_entry_size(table_level);
   let mut virt = virt;
   let mut base = base;
   let mut size = size;
-  let table = get_table(virtual_base + table_addr);
+  let table = get_table(table_addr.to_virt(virtual_base).as_usize());
 
   loop {
-    let idx = get_descriptor_index(virt, table_level);
+    let idx = get_descriptor_index(virt.as_usize(), table_level);
+
+    // Only fill up to the end of the entry. The remainder of the range is
+    // covered by the following entries.
+    let fill_size = cmp::min(size, entry_size - (virt.as_usize() & (entry_size - 1)));
 
     // For levels 1, 2, and 3, allocate new tables as necessary and descend to
     // the next level down. At level 4, add individual page entries.
@@ -309,25 +646,214 @@ fn fill_table_granular(
         table[idx],
         virt,
         base,
-        size,
+        fill_size,
         device,
         allocator,
         MappingStrategy::Granular,
       );
     } else {
-      table[idx] = make_descriptor(table_level, base, device).unwrap();
+      table[idx] = make_descriptor(table_level, base.as_usize(), device).unwrap();
+    }
+
+    // If the rest of the range fits in the entry, there is nothing left to do.
+    if size <= fill_size {
+      break;
+    }
+
+    virt += fill_size;
+    base += fill_size;
+    size -= fill_size;
+  }
+}
+
+/// Clears the entries in a page table for the specified range.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel segment base address.
+/// * `table_level` - The current table level.
+/// * `table_addr` - The address of the current page table.
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Descends into lower level tables as necessary. Recursion is bounded by the
+/// table levels.
+fn clear_table(
+  virtual_base: usize,
+  table_level: TableLevel,
+  table_addr: PhysAddr,
+  virt: VirtAddr,
+  size: usize,
+) {
+  let entry_size = get_table_entry_size(table_level);
+  let table = get_table(table_addr.to_virt(virtual_base).as_usize());
+  let mut virt = virt;
+  let mut size = size;
+
+  while size > 0 {
+    let idx = get_descriptor_index(virt.as_usize(), table_level);
+    let offset = virt.as_usize() & (entry_size - 1);
+    let clear_size = cmp::min(entry_size - offset, size);
+
+    if is_pointer_entry(table_level, table[idx]) {
+      clear_table(
+        virtual_base,
+        get_next_table(table_level).unwrap(),
+        PhysAddr::new(get_phys_addr_from_descriptor(table_level, table[idx]).unwrap()),
+        virt,
+        clear_size,
+      );
+    } else {
+      // The entry is a section, page, or invalid entry. A section cannot be
+      // partially unmapped.
+      assert!(DESC_TYPE.get(table[idx]) == 0 || clear_size == entry_size);
+      table[idx] = 0;
+    }
+
+    virt = virt.wrapping_add(clear_size);
+    size -= clear_size;
+  }
+}
+
+/// Report the mappings in a table and its lower level tables.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel segment base address.
+/// * `table_level` - The current table level.
+/// * `table_addr` - The address of the current page table.
+/// * `virt` - The base virtual address translated by the table.
+/// * `coalescer` - The coalescer collecting the mapped ranges.
+/// * `emit` - Called with each coalesced range.
+///
+/// # Description
+///
+/// Recursion is bounded by the table levels.
+fn dump_table(
+  virtual_base: usize,
+  table_level: TableLevel,
+  table_addr: PhysAddr,
+  virt: usize,
+  coalescer: &mut MappingCoalescer,
+  emit: &mut impl FnMut(usize, usize, &DescriptorInfo),
+) {
+  let entry_size = get_table_entry_size(table_level);
+  let table = get_table(table_addr.to_virt(virtual_base).as_usize());
+
+  for (idx, desc) in table.iter().enumerate() {
+    let entry_virt = virt.wrapping_add(idx * entry_size);
+    let info = describe_descriptor(table_level, *desc);
+
+    match info.kind {
+      DescriptorKind::Invalid => {}
+      DescriptorKind::Table => dump_table(
+        virtual_base,
+        get_next_table(table_level).unwrap(),
+        PhysAddr::new(info.phys_addr),
+        entry_virt,
+        coalescer,
+        emit,
+      ),
+      _ => coalescer.add(entry_virt, entry_size, info, emit),
+    }
+  }
+}
+
+/// Count the number of tables `fill_table_compact()` would allocate.
+///
+/// # Parameters
+///
+/// * `table_level` - The current table level.
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Mirrors `fill_table_compact()`. A new table is counted the first time an
+/// entry cannot be a block. Level 4 tables only contain pages.
+///
+/// Recursion is bounded by the table levels.
+fn count_tables_compact(table_level: TableLevel, virt: usize, size: usize) -> usize {
+  let Some(next_level) = get_next_table(table_level) else {
+    return 0;
+  };
+
+  let page_size = super::get_page_size();
+  let entry_size = get_table_entry_size(table_level);
+  let mut virt = virt;
+  let mut size = size;
+  let mut last_entry = None;
+  let mut count = 0;
+
+  while size >= page_size {
+    let aligned = bits::is_aligned(virt, entry_size);
+    let mut fill_size = entry_size;
+
+    if !aligned || size < entry_size {
+      if !aligned {
+        fill_size = size & (entry_size - 1);
+      } else {
+        fill_size = size;
+      }
+
+      // Only the first fill through an entry allocates a table.
+      let entry = bits::align_down(virt, entry_size);
+
+      if last_entry != Some(entry) {
+        count += 1;
+        last_entry = Some(entry);
+      }
+
+      count += count_tables_compact(next_level, virt, fill_size);
     }
 
-    // If the size of the block is smaller than the entry size, there is nothing
-    // left to do.
-    if size <= entry_size {
+    virt += fill_size;
+    size -= fill_size;
+  }
+
+  count
+}
+
+/// Count the number of tables `fill_table_granular()` would allocate.
+///
+/// # Parameters
+///
+/// * `table_level` - The current table level.
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Mirrors `fill_table_granular()`. Every entry above the page level that the
+/// range touches points to a new table, and the range is split at each entry
+/// boundary so that each part is only counted under its own entry.
+///
+/// Recursion is bounded by the table levels.
+fn count_tables_granular(table_level: TableLevel, virt: usize, size: usize) -> usize {
+  let Some(next_level) = get_next_table(table_level) else {
+    return 0;
+  };
+
+  let entry_size = get_table_entry_size(table_level);
+  let mut virt = virt;
+  let mut size = size;
+  let mut count = 0;
+
+  loop {
+    let fill_size = cmp::min(size, entry_size - (virt & (entry_size - 1)));
+    count += 1 + count_tables_granular(next_level, virt, fill_size);
+
+    if size <= fill_size {
       break;
     }
 
-    virt += entry_size;
-    base += entry_size;
-    size -= entry_size;
+    virt += fill_size;
+    size -= fill_size;
   }
+
+  count
 }
 
 /// Given a table level, returns the size covered by a single entry.
@@ -379,7 +905,7 @@ fn get_next_table(table_level: TableLevel) -> Option<TableLevel> {
 ///
 /// The physical address, or None if the descriptor is invalid.
 fn get_phys_addr_from_descriptor(table_level: TableLevel, desc: usize) -> Option<usize> {
-  match desc & TYPE_MASK {
+  match DESC_TYPE.get(desc) {
     MM_PAGE_TABLE_FLAG => Some(desc & TABLE_OR_PAGE_MASK),
     MM_BLOCK_FLAG => match table_level {
       TableLevel::Level2 => Some(desc & LEVEL_2_BLOCK_MASK),
@@ -436,7 +962,7 @@ fn make_descriptor(table_level: TableLevel, phys_addr: usize, device: bool) -> O
 ///
 /// The new block descriptor.
 fn make_block_descriptor(phys_addr: usize, mair_idx: usize) -> usize {
-  phys_addr | (mair_idx << 2) | MM_ACCESS_FLAG | MM_BLOCK_FLAG
+  make_leaf_descriptor(phys_addr, mair_idx, MM_BLOCK_FLAG)
 }
 
 /// Make a Level 4 page descriptor.
@@ -454,7 +980,28 @@ fn make_block_descriptor(phys_addr: usize, mair_idx: usize) -> usize {
 ///
 /// The new page descriptor.
 fn make_page_descriptor(phys_addr: usize, mair_idx: usize) -> usize {
-  phys_addr | (mair_idx << 2) | MM_ACCESS_FLAG | MM_PAGE_FLAG
+  make_leaf_descriptor(phys_addr, mair_idx, MM_PAGE_FLAG)
+}
+
+/// Make a block or page descriptor.
+///
+/// # Parameters
+///
+/// * `phys_addr` - The physical address of the block or page.
+/// * `mair_idx` - The attributes MAIR index.
+/// * `entry_type` - The entry type.
+///
+/// # Description
+///
+/// The access flag is set so that the first access does not fault.
+///
+/// # Returns
+///
+/// The new descriptor.
+fn make_leaf_descriptor(phys_addr: usize, mair_idx: usize, entry_type: usize) -> usize {
+  let desc = DESC_TYPE.set(phys_addr, entry_type);
+  let desc = DESC_ATTR_IDX.set(desc, mair_idx);
+  DESC_ACCESS_FLAG.set(desc, 1)
 }
 
 /// Determine if a descriptor is a table pointer.
@@ -470,7 +1017,7 @@ fn make_page_descriptor(phys_addr: usize, mair_idx: usize) -> usize {
 fn is_pointer_entry(table_level: TableLevel, desc: usize) -> bool {
   match table_level {
     TableLevel::Level4 => false,
-    _ => desc & TYPE_MASK == MM_PAGE_TABLE_FLAG,
+    _ => DESC_TYPE.get(desc) == MM_PAGE_TABLE_FLAG,
   }
 }
 
@@ -492,7 +1039,7 @@ fn make_pointer_entry(table_level: TableLevel, phys_addr: usize) -> Option<usize
         return None;
       }
 
-      Some(phys_addr | MM_PAGE_TABLE_FLAG)
+      Some(DESC_TYPE.set(phys_addr, MM_PAGE_TABLE_FLAG))
     }
   }
 }
@@ -539,6 +1086,36 @@ fn get_table(table_vaddr: usize) -> &'static mut [usize] {
   unsafe { slice::from_raw_parts_mut(table_vaddr as *mut usize, TABLE_SIZE >> 3) }
 }
 
+/// Fills a table with entries equivalent to a block in the level above.
+///
+/// # Parameters
+///
+/// * `table_level` - The table level of the block descriptor.
+/// * `table` - The lower level table.
+/// * `desc` - The block descriptor.
+///
+/// # Description
+///
+/// A Level 2 block is split into Level 3 blocks, and a Level 3 block is split
+/// into Level 4 pages. Each entry keeps the block's lower attributes in bits
+/// [11:2] and upper attributes in bits [63:52]. The table entry pointing to the
+/// lower level table may then replace the block entry without changing the
+/// mappings.
+fn split_block(table_level: TableLevel, table: &mut [usize], desc: usize) {
+  let next_level = get_next_table(table_level).unwrap();
+  let block_addr = get_phys_addr_from_descriptor(table_level, desc).unwrap();
+  let attrs = desc & !(TABLE_OR_PAGE_MASK | DESC_TYPE.mask());
+  let entry_size = get_table_entry_size(next_level);
+  let entry_type = match next_level {
+    TableLevel::Level4 => MM_PAGE_FLAG,
+    _ => MM_BLOCK_FLAG,
+  };
+
+  for (idx, entry) in table.iter_mut().enumerate() {
+    *entry = DESC_TYPE.set((block_addr + idx * entry_size) | attrs, entry_type);
+  }
+}
+
 /// Allocates a new page table if necessary, then fills the table with entries
 /// for the specified range of memory.
 ///
@@ -559,6 +1136,13 @@ fn get_table(table_vaddr: usize) -> &'static mut [usize] {
 /// The current table must be Level 1, 2, or 3. Level 4 tables can only point to
 /// pages.
 ///
+/// If the current descriptor is a block, the new table is filled with entries
+/// equivalent to the block before the range is mapped, so only the range
+/// changes. See `split_block()`.
+///
+///   NOTE: The caller must invalidate the TLB for the range if the block was
+///         live.
+///
 /// # Returns
 ///
 /// The new descriptor.
@@ -566,8 +1150,8 @@ fn alloc_table_and_fill(
   virtual_base: usize,
   table_level: TableLevel,
   desc: usize,
-  virt: usize,
-  base: usize,
+  virt: VirtAddr,
+  base: PhysAddr,
   size: usize,
   device: bool,
   allocator: &mut impl PageAllocator,
@@ -575,26 +1159,28 @@ fn alloc_table_and_fill(
 ) -> usize {
   let mut desc = desc;
 
-  // TODO: It is probably fine to overwrite a section descriptor. If the memory
-  //       configuration is overwriting itself, then we probably have something
-  //       wrong and an exception is the right outcome if the configuration is
-  //       invalid.
   if !is_pointer_entry(table_level, desc) {
     // Let an assert occur if we cannot allocate a table from linear memory.
     let (next_addr, _) = allocator.alloc(1).unwrap();
+    let next_addr = PhysAddr::new(next_addr);
+    let table_vaddr = next_addr.to_virt(virtual_base).as_usize();
 
-    unsafe {
-      // Zero out the table. Any entry in the table with 0 in bit 0 is invalid.
-      ptr::write_bytes((virtual_base + next_addr) as *mut u8, 0, TABLE_SIZE);
+    if DESC_TYPE.get(desc) == MM_BLOCK_FLAG {
+      split_block(table_level, get_table(table_vaddr), desc);
+    } else {
+      unsafe {
+        // Zero out the table. Any entry in the table with 0 in bit 0 is invalid.
+        ptr::write_bytes(table_vaddr as *mut u8, 0, TABLE_SIZE);
+      }
     }
 
-    desc = make_pointer_entry(table_level, next_addr).unwrap();
+    desc = make_pointer_entry(table_level, next_addr.as_usize()).unwrap();
   }
 
   fill_table(
     virtual_base,
     get_next_table(table_level).unwrap(),
-    get_phys_addr_from_descriptor(table_level, desc).unwrap(),
+    PhysAddr::new(get_phys_addr_from_descriptor(table_level, desc).unwrap()),
     virt,
     base,
     size,
@@ -605,3 +1191,8 @@ fn alloc_table_and_fill(
 
   desc
 }
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
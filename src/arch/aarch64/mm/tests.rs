@@ -0,0 +1,576 @@
+//! AArch64 Memory Management Tests
+
+use super::{
+  TableLevel, describe_descriptor, get_descriptor_index, get_phys_addr_from_descriptor, get_table,
+  make_descriptor, make_pointer_entry,
+};
+use crate::arch::memory::{
+  BufferedPageAllocator, DescriptorInfo, DescriptorKind, MappingStrategy, PageAllocator,
+};
+use crate::debug_print;
+use crate::support::{bits, print};
+use crate::test::{self, memory, tlb};
+use crate::{check_eq, check_neq, check_optional, execute_test};
+use core::fmt::Write;
+use core::ptr;
+
+/// Run memory management tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_unmap_pages);
+  execute_test!(context, test_unmap_section);
+  execute_test!(context, test_invalidation_scope);
+  execute_test!(context, test_count_tables);
+  execute_test!(context, test_descriptor_layout);
+  execute_test!(context, test_describe_descriptor);
+  execute_test!(context, test_dump_tables);
+  execute_test!(context, test_split_section);
+  execute_test!(context, test_map_device_window);
+}
+
+/// Test unmapping individual pages.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps four pages into a detached set of tables, unmaps the middle two, and
+/// verifies the entries are cleared and the TLB was invalidated for the range.
+fn test_unmap_pages(context: &mut test::TestContext) {
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+  let virt = virt_base;
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    virt,
+    phys_addr,
+    page_size * 4,
+    false,
+    &mut allocator,
+    MappingStrategy::Granular,
+  );
+
+  tlb::clear_invalidations();
+  super::unmap_memory(virt_base, root_addr, virt + page_size, page_size * 2);
+  check_eq!(context, tlb::was_invalidated(virt + page_size, page_size * 2), true);
+
+  let table = get_table_at_level(virt_base, root_addr, virt, TableLevel::Level4);
+  check_neq!(context, table[get_descriptor_index(virt, TableLevel::Level4)], 0);
+  check_eq!(context, table[get_descriptor_index(virt + page_size, TableLevel::Level4)], 0);
+  check_eq!(context, table[get_descriptor_index(virt + page_size * 2, TableLevel::Level4)], 0);
+  check_neq!(context, table[get_descriptor_index(virt + page_size * 3, TableLevel::Level4)], 0);
+}
+
+/// Test unmapping a whole section.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_unmap_section(context: &mut test::TestContext) {
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let section_size = crate::arch::get_section_size();
+  let (mut allocator, root_addr, _) = make_test_tables();
+  let virt = virt_base;
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    virt,
+    0,
+    section_size,
+    false,
+    &mut allocator,
+    MappingStrategy::Compact,
+  );
+
+  let level3 = get_table_at_level(virt_base, root_addr, virt, TableLevel::Level3);
+  let idx = get_descriptor_index(virt, TableLevel::Level3);
+  check_neq!(context, level3[idx], 0);
+
+  tlb::clear_invalidations();
+  super::unmap_memory(virt_base, root_addr, virt, section_size);
+  check_eq!(context, tlb::was_invalidated(virt, section_size), true);
+  check_eq!(context, level3[idx], 0);
+}
+
+/// Test selecting local or broadcast TLB invalidation.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Kernel-global mappings must be invalidated on all cores while task mappings
+/// in the lower half of the address space only need to be invalidated on the
+/// current core.
+fn test_invalidation_scope(context: &mut test::TestContext) {
+  let page_size = crate::arch::get_page_size();
+  let global_virt = crate::arch::get_kernel_virtual_base();
+  let local_virt = 0;
+
+  tlb::clear_invalidations();
+  super::invalidate_tlb_mapping(global_virt, page_size);
+  check_eq!(context, tlb::was_invalidated_broadcast(global_virt, page_size), true);
+  check_eq!(context, tlb::was_invalidated_local(global_virt, page_size), false);
+
+  tlb::clear_invalidations();
+  super::invalidate_tlb_mapping(local_virt, page_size);
+  check_eq!(context, tlb::was_invalidated_local(local_virt, page_size), true);
+  check_eq!(context, tlb::was_invalidated_broadcast(local_virt, page_size), false);
+}
+
+/// Test predicting the number of tables a mapping allocates.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Compares the predicted table count against the number of pages actually
+/// allocated from the table allocator when mapping into a detached set of
+/// tables. Unaligned ranges that cross entry boundaries need one table for
+/// each entry they touch.
+fn test_count_tables(context: &mut test::TestContext) {
+  let page_size = crate::arch::get_page_size();
+  let section_size = crate::arch::get_section_size();
+
+  check_table_count(context, 0, page_size * 4, MappingStrategy::Granular);
+  check_table_count(context, 0, section_size * 2 + page_size, MappingStrategy::Granular);
+  check_table_count(context, section_size, page_size, MappingStrategy::Granular);
+  check_table_count(context, section_size - page_size, page_size * 2, MappingStrategy::Granular);
+  check_table_count(context, section_size + page_size, section_size * 2, MappingStrategy::Granular);
+  check_table_count(context, 0, section_size, MappingStrategy::Compact);
+  check_table_count(context, 0, section_size * 3, MappingStrategy::Compact);
+  check_table_count(context, 0, section_size + page_size * 3, MappingStrategy::Compact);
+}
+
+/// Map a range into a detached set of tables and verify the predicted table
+/// count.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `offset` - Offset of the range from the kernel virtual base.
+/// * `size` - Size of the range.
+/// * `strategy` - The mapping strategy.
+fn check_table_count(
+  context: &mut test::TestContext,
+  offset: usize,
+  size: usize,
+  strategy: MappingStrategy,
+) {
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+  let phys_addr = bits::align_up(phys_addr, crate::arch::get_section_size());
+  let virt = virt_base + offset;
+  let expected = super::count_tables_needed(virt, phys_addr, size, strategy);
+  let before = allocator.get_alloc_mem();
+
+  super::map_memory(virt_base, root_addr, virt, phys_addr, size, false, &mut allocator, strategy);
+
+  let allocated = (allocator.get_alloc_mem() - before) >> crate::arch::get_page_shift();
+  check_eq!(context, allocated, expected);
+}
+
+/// Construct a table allocator and a detached root table in test memory.
+///
+/// # Description
+///
+/// The tables are not attached to the MMU, so mapping into them does not
+/// affect the kernel's address space.
+///
+/// # Returns
+///
+/// A tuple with the table allocator, the physical address of the root table,
+/// and the physical address of a page-aligned test area after the tables.
+fn make_test_tables() -> (BufferedPageAllocator<1>, usize, usize) {
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let phys_addr = memory::get_test_memory_mut().as_ptr() as usize - virt_base;
+  let table_area_size = page_size * 16;
+
+  memory::reset_test_memory();
+
+  let mut allocator =
+    BufferedPageAllocator::<1>::new(phys_addr, phys_addr + table_area_size, page_size);
+  let (root_addr, _) = allocator.alloc(1).unwrap();
+
+  unsafe {
+    ptr::write_bytes((virt_base + root_addr) as *mut u8, 0, page_size);
+  }
+
+  (allocator, root_addr, phys_addr + table_area_size)
+}
+
+/// Walk the tables to the table at a given level covering a virtual address.
+///
+/// # Parameters
+///
+/// * `virt_base` - The kernel segment base address.
+/// * `root_addr` - The physical address of the Level 1 table.
+/// * `virt` - The virtual address.
+/// * `level` - The table level to find.
+fn get_table_at_level(
+  virt_base: usize,
+  root_addr: usize,
+  virt: usize,
+  level: TableLevel,
+) -> &'static mut [usize] {
+  let mut table_level = TableLevel::Level1;
+  let mut table = get_table(virt_base + root_addr);
+
+  while table_level != level {
+    let idx = get_descriptor_index(virt, table_level);
+    let addr = get_phys_addr_from_descriptor(table_level, table[idx]).unwrap();
+    table = get_table(virt_base + addr);
+    table_level = super::get_next_table(table_level).unwrap();
+  }
+
+  table
+}
+
+/// Test descriptors against hand-computed values.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Block and page descriptors have the entry type in bits [1:0], the MAIR
+/// index in bits [4:2], and the access flag in bit 10. Pointer descriptors
+/// only have the entry type.
+fn test_descriptor_layout(context: &mut test::TestContext) {
+  const PAGE_ADDR: usize = 0x1234_5000;
+  const PAGE_DESC: usize = 0x1234_5403;
+  const BLOCK_ADDR: usize = 0x4020_0000;
+  const DEVICE_BLOCK_DESC: usize = 0x4020_0405;
+  const TABLE_ADDR: usize = 0x8000_1000;
+  const POINTER_DESC: usize = 0x8000_1003;
+
+  check_optional!(context, make_descriptor(TableLevel::Level4, PAGE_ADDR, false), PAGE_DESC);
+  check_optional!(
+    context,
+    make_descriptor(TableLevel::Level3, BLOCK_ADDR + 0x123, true),
+    DEVICE_BLOCK_DESC
+  );
+  check_optional!(context, make_pointer_entry(TableLevel::Level1, TABLE_ADDR), POINTER_DESC);
+}
+
+/// Test decoding known descriptors at each level.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Blocks are invalid at Levels 1 and 4, and an entry type with bit 0 clear is
+/// invalid at every level. Descriptors made by the kernel must decode to the
+/// values used to make them.
+fn test_describe_descriptor(context: &mut test::TestContext) {
+  const CASES: [(TableLevel, usize, DescriptorKind, usize, bool, bool); 9] = [
+    (TableLevel::Level1, 0x8000_1003, DescriptorKind::Table, 0x8000_1000, false, false),
+    (TableLevel::Level1, 0x4000_0401, DescriptorKind::Invalid, 0, false, false),
+    (TableLevel::Level2, 0x4000_0405, DescriptorKind::Block, 0x4000_0000, true, true),
+    (TableLevel::Level2, 0x4000_0000, DescriptorKind::Invalid, 0, false, false),
+    (TableLevel::Level3, 0x4020_0001, DescriptorKind::Block, 0x4020_0000, false, false),
+    (TableLevel::Level3, 0x8000_3003, DescriptorKind::Table, 0x8000_3000, false, false),
+    (TableLevel::Level4, 0x1234_5403, DescriptorKind::Page, 0x1234_5000, false, true),
+    (TableLevel::Level4, 0x1234_5401, DescriptorKind::Invalid, 0, false, false),
+    (TableLevel::Level4, 0x1234_5406, DescriptorKind::Invalid, 0, false, false),
+  ];
+
+  for (level, desc, kind, phys_addr, device, access_flag) in CASES {
+    let info = describe_descriptor(level, desc);
+    let expected = DescriptorInfo {
+      kind,
+      phys_addr,
+      device,
+      access_flag,
+    };
+
+    let matches = info == expected;
+    check_eq!(context, matches, true);
+  }
+
+  let desc = make_descriptor(TableLevel::Level2, 0x4000_0000, true).unwrap();
+  check_description(
+    context,
+    describe_descriptor(TableLevel::Level2, desc),
+    "block 0x40000000 device AF",
+  );
+
+  let desc = make_descriptor(TableLevel::Level4, 0x1234_5000, false).unwrap();
+  check_description(
+    context,
+    describe_descriptor(TableLevel::Level4, desc),
+    "page 0x12345000 normal AF",
+  );
+
+  let desc = make_pointer_entry(TableLevel::Level3, 0x8000_3000).unwrap();
+  check_description(context, describe_descriptor(TableLevel::Level3, desc), "table 0x80003000");
+
+  check_description(context, describe_descriptor(TableLevel::Level1, 0), "invalid");
+}
+
+/// Check the formatted description of a descriptor.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `info` - The decoded descriptor.
+/// * `expected` - The expected description.
+fn check_description(context: &mut test::TestContext, info: DescriptorInfo, expected: &str) {
+  let mut buf = [0u8; 64];
+  let mut stream = print::WriteBuffer::new(&mut buf);
+
+  _ = write!(stream, "{}", info);
+  let matches = stream.as_bytes() == expected.as_bytes();
+  check_eq!(context, matches, true);
+}
+
+/// Test reporting the mappings in a set of tables.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps a range of normal memory pages and a range of device memory sections
+/// into a detached set of tables. The dump must report exactly the two mapped
+/// ranges, each coalesced into a single range.
+fn test_dump_tables(context: &mut test::TestContext) {
+  const MAX_RANGES: usize = 4;
+
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let section_size = crate::arch::get_section_size();
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+  let page_virt = virt_base + section_size;
+  let section_virt = virt_base + section_size * 4;
+  let section_phys = bits::align_up(phys_addr, section_size);
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    page_virt,
+    phys_addr,
+    page_size * 4,
+    false,
+    &mut allocator,
+    MappingStrategy::Granular,
+  );
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    section_virt,
+    section_phys,
+    section_size * 2,
+    true,
+    &mut allocator,
+    MappingStrategy::Compact,
+  );
+
+  let mut ranges = [(0, 0, DescriptorInfo::invalid()); MAX_RANGES];
+  let mut count = 0;
+
+  super::dump_tables(virt_base, root_addr, |virt, size, info| {
+    if count < MAX_RANGES {
+      ranges[count] = (virt, size, *info);
+    }
+
+    count += 1;
+  });
+
+  check_eq!(context, count, 2);
+
+  let expected = [
+    (
+      page_virt,
+      page_size * 4,
+      DescriptorInfo {
+        kind: DescriptorKind::Page,
+        phys_addr,
+        device: false,
+        access_flag: true,
+      },
+    ),
+    (
+      section_virt,
+      section_size * 2,
+      DescriptorInfo {
+        kind: DescriptorKind::Block,
+        phys_addr: section_phys,
+        device: true,
+        access_flag: true,
+      },
+    ),
+  ];
+
+  for (range, expected) in ranges.iter().zip(expected.iter()) {
+    check_eq!(context, range.0, expected.0);
+    check_eq!(context, range.1, expected.1);
+    let matches = range.2 == expected.2;
+    check_eq!(context, matches, true);
+  }
+}
+
+/// Test mapping a page inside a section.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps a section of normal memory into a detached set of tables, then maps a
+/// device page over the fourth page of the section. The section must be split
+/// into pages so that only the fourth page changes.
+fn test_split_section(context: &mut test::TestContext) {
+  const MAX_RANGES: usize = 4;
+
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let section_size = crate::arch::get_section_size();
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+  let section_virt = virt_base + section_size * 2;
+  let section_phys = bits::align_up(phys_addr, section_size);
+  let page_virt = section_virt + page_size * 3;
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    section_virt,
+    section_phys,
+    section_size,
+    false,
+    &mut allocator,
+    MappingStrategy::Compact,
+  );
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    page_virt,
+    phys_addr,
+    page_size,
+    true,
+    &mut allocator,
+    MappingStrategy::Granular,
+  );
+
+  let mut ranges = [(0, 0, DescriptorInfo::invalid()); MAX_RANGES];
+  let mut count = 0;
+
+  super::dump_tables(virt_base, root_addr, |virt, size, info| {
+    if count < MAX_RANGES {
+      ranges[count] = (virt, size, *info);
+    }
+
+    count += 1;
+  });
+
+  check_eq!(context, count, 3);
+
+  let normal_page = DescriptorInfo {
+    kind: DescriptorKind::Page,
+    phys_addr: section_phys,
+    device: false,
+    access_flag: true,
+  };
+  let expected = [
+    (section_virt, page_size * 3, normal_page),
+    (
+      page_virt,
+      page_size,
+      DescriptorInfo {
+        phys_addr,
+        device: true,
+        ..normal_page
+      },
+    ),
+    (
+      page_virt + page_size,
+      section_size - page_size * 4,
+      DescriptorInfo {
+        phys_addr: section_phys + page_size * 4,
+        ..normal_page
+      },
+    ),
+  ];
+
+  for (range, expected) in ranges.iter().zip(expected.iter()) {
+    check_eq!(context, range.0, expected.0);
+    check_eq!(context, range.1, expected.1);
+    let matches = range.2 == expected.2;
+    check_eq!(context, matches, true);
+  }
+}
+
+/// Test mapping a device range at a fixed high virtual address.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps device pages into the last section of the address space in a detached
+/// set of tables. The virtual address is unrelated to the linear mapping of the
+/// physical range, so the dump must report only the high range.
+fn test_map_device_window(context: &mut test::TestContext) {
+  const MAX_RANGES: usize = 2;
+
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let window_virt = 0usize.wrapping_sub(crate::arch::get_section_size());
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    window_virt,
+    phys_addr,
+    page_size * 2,
+    true,
+    &mut allocator,
+    MappingStrategy::Granular,
+  );
+
+  let mut ranges = [(0, 0, DescriptorInfo::invalid()); MAX_RANGES];
+  let mut count = 0;
+
+  super::dump_tables(virt_base, root_addr, |virt, size, info| {
+    if count < MAX_RANGES {
+      ranges[count] = (virt, size, *info);
+    }
+
+    count += 1;
+  });
+
+  check_eq!(context, count, 1);
+  check_eq!(context, ranges[0].0, window_virt);
+  check_eq!(context, ranges[0].1, page_size * 2);
+
+  let expected = DescriptorInfo {
+    kind: DescriptorKind::Page,
+    phys_addr,
+    device: true,
+    access_flag: true,
+  };
+  let matches = ranges[0].2 == expected;
+  check_eq!(context, matches, true);
+}
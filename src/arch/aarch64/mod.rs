@@ -1,16 +1,22 @@
 //! AArch64 Architecture
 
 mod exceptions;
-mod mm;
+#[cfg(feature = "module_tests")]
+mod tests;
 
+pub mod asid;
+pub mod features;
+pub mod mm;
 pub mod task;
 
 #[cfg(feature = "serial_debug_output")]
 pub use super::arm_common::debug;
-pub use super::arm_common::{cpu, interrupts, sync};
+#[cfg(feature = "module_tests")]
+pub use super::arm_common::panic_guard;
+pub use super::arm_common::{cache, cpu, dtb_mmio, gic, interrupts, irq, percpu, sync, time};
 pub use super::common::{device_tree, memory};
 
-use super::arm_common::{dtb_cpu, dtb_memory};
+use super::arm_common::{dtb_chosen, dtb_cpu, dtb_memory};
 use crate::arch::memory::PageAllocator;
 use crate::debug_print;
 use crate::support::{bits, dtb, range};
@@ -46,8 +52,14 @@ const PAGE_TABLE_ENTRY_SHIFT: usize = 3;
 /// The size of the virtual area reserved for the page directory (2 TiB).
 const PAGE_DATABASE_SIZE: usize = 0x200_0000_0000;
 
-/// The base virtual address of the page directory.
-const PAGE_DATABASE_VIRTUAL_BASE: usize = 0xffff_fe00_0000_0000;
+/// The base virtual address of the page directory. The page directory is
+/// placed at the top of the address space.
+const PAGE_DATABASE_VIRTUAL_BASE: usize = 0usize.wrapping_sub(PAGE_DATABASE_SIZE);
+
+const _: () = assert!(
+  PAGE_DATABASE_VIRTUAL_BASE & SECTION_MASK == 0,
+  "The page directory is not section-aligned."
+);
 
 /// Basic kernel configuration provided by the start code. All address are
 /// physical.
@@ -118,7 +130,7 @@ impl MemoryRangeHandler for RangeZoneTagger {
 ///
 ///   NOTE: Must only be called once while the kernel is single-threaded.
 ///
-///   NOTE: Requires 4 KiB pages.
+///   NOTE: Requires 4 KiB pages and a core that supports 4 KiB granules.
 ///
 ///   NOTE: Requires the kernel stack page count to be a power of two.
 ///
@@ -156,8 +168,10 @@ pub fn init(config_addr: usize) {
   debug_print!("=== Propeller (AArch64) ===\n");
   debug_print!("Booting on core {:x}.\n", cpu::get_id());
 
-  // Require 4 KiB pages.
+  // Require 4 KiB pages and a core that supports 4 KiB granules. The MMU code
+  // only builds 4 KiB granule translation tables.
   assert_eq!(kconfig.page_size, PAGE_SIZE);
+  assert!(cpu::features().granule_4k);
 
   // Require a power-of-2 page count for the kernel stack size.
   assert!(bits::is_power_of_2(kconfig.kernel_stack_pages));
@@ -173,6 +187,15 @@ pub fn init(config_addr: usize) {
   init_core_config(blob_vaddr);
   init_memory_config(blob_vaddr, blob_size);
   init_direct_map(&mut allocator);
+  asid::init();
+
+  dump_vm_layout(|name, base, size| {
+    debug_print!("{}: {:#x} - {:#x}\n", name, base, base + size - 1);
+  });
+
+  if cfg!(debug_assertions) {
+    check_vm_layout();
+  }
 
   debug_print!("arch init complete.\n");
 }
@@ -243,6 +266,43 @@ pub fn get_kernel_base() -> usize {
   unsafe { KERNEL_CONFIG.kernel_base }
 }
 
+/// Get the physical range occupied by the kernel image.
+///
+/// # Description
+///
+///   NOTE: The interface guarantees read-only access outside of the module and
+///         one-time initialization is assumed.
+pub fn get_kernel_range() -> MemoryRange {
+  let kconfig = get_kernel_config();
+
+  MemoryRange {
+    tag: MemoryZone::LinearMemoryZone,
+    base: kconfig.kernel_base,
+    size: kconfig.kernel_size,
+  }
+}
+
+/// Get the physical range occupied by the kernel image aligned outward to
+/// section boundaries.
+///
+/// # Description
+///
+/// The base is aligned down and the end is aligned up to the section size.
+///
+///   NOTE: The interface guarantees read-only access outside of the module and
+///         one-time initialization is assumed.
+pub fn get_kernel_range_section_aligned() -> MemoryRange {
+  let range = get_kernel_range();
+  let base = bits::align_down(range.base, SECTION_SIZE);
+  let end = bits::align_up(range.base + range.size, SECTION_SIZE);
+
+  MemoryRange {
+    tag: range.tag,
+    base,
+    size: end - base,
+  }
+}
+
 /// Get the maximum physical address.
 ///
 /// # Description
@@ -275,17 +335,45 @@ pub fn get_device_tree() -> &'static device_tree::DeviceTree {
   unsafe { ptr::addr_of!(DEVICE_TREE).as_ref().unwrap() }
 }
 
+/// Get the system memory configuration.
+///
+/// # Description
+///
+/// Convenience interface to access the memory configuration in the system
+/// device tree.
+pub fn get_memory_config() -> &'static MemoryConfig {
+  get_device_tree().get_memory_config()
+}
+
 /// Get the core index of the current core.
 ///
 /// # Description
 ///
 /// For any non-trivial use of the core index, interrupts must be disabled prior
 /// to calling to prevent the task from moving to another core.
+///
+/// The index is cached in the core's data area after the first lookup. See
+/// `percpu::get_current_core_index()`.
 pub fn get_current_core_index() -> usize {
-  get_device_tree()
-    .get_core_config()
-    .get_core_index(cpu::get_id())
-    .unwrap()
+  percpu::get_current_core_index(lookup_current_core_index)
+}
+
+/// Look up the core index of the current core in the core configuration.
+///
+/// # Description
+///
+/// Prefer `get_current_core_index()`, which caches the result.
+///
+///   NOTE: Panics if the current core's ID is not in the core configuration,
+///         e.g. a secondary core the DTB did not describe.
+pub fn lookup_current_core_index() -> usize {
+  let id = cpu::get_id();
+
+  let Some(core_idx) = get_device_tree().get_core_config().get_core_index(id) else {
+    panic!("Core ID {:#x} is not in the core configuration.", id);
+  };
+
+  core_idx
 }
 
 /// Get the page database virtual base address.
@@ -298,6 +386,76 @@ pub fn get_page_database_size() -> usize {
   PAGE_DATABASE_SIZE
 }
 
+/// Report the layout of the kernel's virtual address space.
+///
+/// # Parameters
+///
+/// * `emit` - Called with the name, base virtual address, and size of each
+///   region.
+///
+/// # Description
+///
+/// Regions are reported in ascending address order. The linear mappings extend
+/// from the kernel segment base to the ISR stack area.
+///
+///   NOTE: Only valid after the memory configuration has been initialized.
+pub fn dump_vm_layout(mut emit: impl FnMut(&'static str, usize, usize)) {
+  let virtual_base = get_kernel_virtual_base();
+  let isr_base = get_isr_stack_area_virtual_base();
+
+  emit("Linear Mappings", virtual_base, isr_base - virtual_base);
+  emit("ISR Stacks", isr_base, get_isr_stack_size());
+  emit("Page Database", PAGE_DATABASE_VIRTUAL_BASE, PAGE_DATABASE_SIZE);
+}
+
+/// Maximum number of regions reported by `dump_vm_layout()`.
+const MAX_VM_REGIONS: usize = 8;
+
+/// Find a conflict in the kernel's virtual address space layout.
+///
+/// # Description
+///
+/// Collects the regions reported by `dump_vm_layout()` and checks that they are
+/// mutually non-overlapping and within the kernel segment.
+///
+///   NOTE: Only valid after the memory configuration has been initialized.
+///
+/// # Returns
+///
+/// The name of the first conflicting region, or None if the layout is valid.
+fn find_vm_layout_conflict() -> Option<&'static str> {
+  let mut regions = [memory::VirtualRegion {
+    tag: "",
+    base: 0,
+    size: 0,
+  }; MAX_VM_REGIONS];
+  let mut count = 0;
+
+  dump_vm_layout(|name, base, size| {
+    assert!(count < MAX_VM_REGIONS);
+    regions[count] = memory::VirtualRegion {
+      tag: name,
+      base,
+      size,
+    };
+    count += 1;
+  });
+
+  memory::find_vm_layout_conflict(get_kernel_virtual_base(), &regions[..count])
+}
+
+/// Check the kernel's virtual address space layout.
+///
+/// # Description
+///
+/// Panics if any region overlaps another region or is outside of the kernel
+/// segment. Only called in debug builds.
+fn check_vm_layout() {
+  if let Some(name) = find_vm_layout_conflict() {
+    panic!("Virtual region \"{}\" conflicts with the kernel layout.", name);
+  }
+}
+
 /// Get the base virtual address of the ISR stack area.
 ///
 /// # Description
@@ -375,9 +533,11 @@ fn init_core_config(blob_vaddr: usize) {
   assert!(dtb_cpu::get_core_config(core_config, blob_vaddr));
 
   for core in core_config.get_cores() {
-    let s = core::str::from_utf8(&core.get_core_type()).unwrap_or("Unknown");
+    let s = core::str::from_utf8(core.get_primary_core_type()).unwrap_or("Unknown");
     debug_print!("Core {:x}: {}\n", core.get_id(), s)
   }
+
+  percpu::init(core_config.get_core_count());
 }
 
 /// Initialize the memory layout configuration.
@@ -391,8 +551,9 @@ fn init_core_config(blob_vaddr: usize) {
 ///
 /// Reads the ranges covered by memory devices from the DTB, then excludes any
 /// physical memory beyond the virtual base address, excludes 0 to the end of
-/// the section-aligned kernel, and excludes the section-aligned DTB area. The
-/// remaining physical memory is available for use.
+/// the section-aligned kernel, excludes the section-aligned DTB area, and
+/// excludes the section-aligned initial ramdisk, if any. The remaining physical
+/// memory is available for use.
 ///
 /// # Assumptions
 ///
@@ -405,6 +566,7 @@ fn init_memory_config(blob_vaddr: usize, blob_size: usize) {
   let core_count = device_tree.get_core_config().get_core_count();
   let page_shift = get_page_shift();
   let section_size = get_section_size();
+  let kernel_range = get_kernel_range_section_aligned();
   let blob_start = bits::align_down(kconfig.blob, section_size);
   let blob_size = bits::align_up(kconfig.blob + blob_size, section_size) - blob_start;
 
@@ -415,7 +577,11 @@ fn init_memory_config(blob_vaddr: usize, blob_size: usize) {
 
   let tagger = RangeZoneTagger {};
   let mem_config = device_tree.get_memory_config_mut();
-  assert!(dtb_memory::get_memory_layout(mem_config, &tagger, blob_vaddr));
+  match dtb_memory::get_memory_layout(mem_config, &tagger, blob_vaddr) {
+    Ok(_) => {}
+    Err(dtb_memory::MemoryLayoutError::TooManyRanges) => panic!("Too many memory ranges."),
+    Err(_) => panic!("Failed to read the memory layout."),
+  }
 
   let excl = &[
     // Exclude the kernel area.
@@ -428,7 +594,7 @@ fn init_memory_config(blob_vaddr: usize, blob_size: usize) {
     MemoryRange {
       tag: MemoryZone::InvalidZone,
       base: 0,
-      size: bits::align_up(kconfig.kernel_base + kconfig.kernel_size, section_size),
+      size: kernel_range.base + kernel_range.size,
     },
     // Exclude the DTB blob.
     MemoryRange {
@@ -439,12 +605,26 @@ fn init_memory_config(blob_vaddr: usize, blob_size: usize) {
   ];
 
   for range in excl {
-    mem_config.exclude_range(range);
+    mem_config
+      .exclude_reserved_range(range)
+      .expect("Failed to exclude the kernel and DTB from the memory layout.");
+  }
+
+  // Exclude the section-aligned initial ramdisk, if the bootloader provided
+  // one, so that it is not overwritten.
+  if let Some(initrd) = dtb_chosen::get_chosen_info(blob_vaddr)
+    .and_then(|chosen| chosen.get_initrd_exclusion(section_size))
+  {
+    mem_config
+      .exclude_reserved_range(&initrd)
+      .expect("Failed to exclude the initial ramdisk from the memory layout.");
   }
 
   for range in mem_config.get_ranges() {
     debug_print!("Memory: {:#x} - {:#x}\n", range.base, range.base + range.size - 1);
   }
+
+  debug_print!("Total memory: {:#x} bytes\n", mem_config.total_size());
 }
 
 /// Initialize the linear memory map.
@@ -549,9 +729,25 @@ fn init_isr_stacks(allocator: &mut impl PageAllocator) {
 }
 
 #[cfg(feature = "module_tests")]
-pub fn run_tests() {
-  let mut context = test::TestContext::new();
-  debug_print!(" arch:\n");
-  crate::arch::task::run_tests(&mut context);
-  debug_print!("  {} pass, {} fail\n", context.pass_count, context.fail_count);
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+  super::common::cpu::run_tests(context);
+  super::common::memory::run_tests(context);
+  super::arm_common::cache::run_tests(context);
+  super::arm_common::cpu::run_tests(context);
+  #[cfg(feature = "bcm2835_mini_uart_debug")]
+  super::arm_common::debug::run_tests(context);
+  super::arm_common::dtb_chosen::run_tests(context);
+  super::arm_common::dtb_cpu::run_tests(context);
+  super::arm_common::dtb_device_tree::run_tests(context);
+  super::arm_common::dtb_memory::run_tests(context);
+  super::arm_common::dtb_mmio::run_tests(context);
+  super::arm_common::gic::run_tests(context);
+  super::arm_common::irq::run_tests(context);
+  super::arm_common::percpu::run_tests(context);
+  super::arm_common::time::run_tests(context);
+  features::run_tests(context);
+  mm::run_tests(context);
+  asid::run_tests(context);
+  crate::arch::task::run_tests(context);
 }
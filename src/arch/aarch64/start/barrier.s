@@ -0,0 +1,27 @@
+//! AArch64 Memory Barriers
+
+///-----------------------------------------------------------------------------
+///
+/// Data memory barrier. See B2.3.5.
+///
+/// # Description
+///
+/// Orders memory accesses before the barrier before memory accesses after the
+/// barrier, including accesses to device memory.
+.global sync_dmb
+sync_dmb:
+  dmb     sy
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Data synchronization barrier. See B2.3.5.
+///
+/// # Description
+///
+/// Waits for memory accesses before the barrier to complete.
+.global sync_dsb
+sync_dsb:
+  dsb     sy
+  ret
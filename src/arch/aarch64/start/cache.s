@@ -0,0 +1,135 @@
+//! AArch64 Low-Level Cache Maintenance
+
+.equ CTR_EL0_DMINLINE_SHIFT,    16
+.equ CLIDR_EL1_LOC_SHIFT,       24
+.equ CCSIDR_EL1_ASSOC_SHIFT,    3
+.equ CCSIDR_EL1_NUMSETS_SHIFT,  13
+.equ CACHE_TYPE_DATA,           2
+
+///-----------------------------------------------------------------------------
+///
+/// Get the size of the smallest data cache line. See D17.2.34.
+///
+/// # Returns
+///
+/// The line size in bytes. CTR_EL0.DminLine is the log2 of the number of
+/// words in the line.
+.global cache_get_dcache_line_size
+cache_get_dcache_line_size:
+  mrs     x9, ctr_el0
+  ubfx    x9, x9, #CTR_EL0_DMINLINE_SHIFT, #4
+  mov     x0, #4
+  lsl     x0, x0, x9
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Clean a data cache line by virtual address to the Point of Coherency.
+///
+/// # Parameters
+///
+/// * x0 - The virtual address.
+.global cache_clean_line
+cache_clean_line:
+  dc      cvac, x0
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate a data cache line by virtual address to the Point of Coherency.
+///
+/// # Parameters
+///
+/// * x0 - The virtual address.
+.global cache_invalidate_line
+cache_invalidate_line:
+  dc      ivac, x0
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Clean and invalidate a data cache line by virtual address to the Point of
+/// Coherency.
+///
+/// # Parameters
+///
+/// * x0 - The virtual address.
+.global cache_clean_invalidate_line
+cache_clean_invalidate_line:
+  dc      civac, x0
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Wait for outstanding cache maintenance to complete.
+.global cache_sync
+cache_sync:
+  dsb     sy
+  isb
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Clean every data cache level up to the Level of Coherency by set/way. See
+/// D7.5.2 and D17.2.29.
+///
+/// # Description
+///
+/// Set/way operations only affect the current core's caches.
+.global cache_clean_all_dcache
+cache_clean_all_dcache:
+  dsb     sy                // Complete outstanding stores.
+
+  mrs     x0, clidr_el1
+  ubfx    w3, w0, #CLIDR_EL1_LOC_SHIFT, #3
+  lsl     w3, w3, #1        // w3 = Level of Coherency * 2
+  cbz     w3, 5f
+  mov     w10, #0           // w10 = cache level * 2, i.e. the CSSELR value
+
+1:
+  add     w2, w10, w10, lsr #1
+  lsr     w1, w0, w2        // Cache type for the level is at CLIDR[3 * level]
+  and     w1, w1, #0x7
+  cmp     w1, #CACHE_TYPE_DATA
+  b.lt    4f                // Skip levels without a data cache.
+
+  msr     csselr_el1, x10   // Select the level's data cache.
+  isb
+  mrs     x1, ccsidr_el1
+  and     w2, w1, #0x7
+  add     w2, w2, #4        // w2 = log2(line size in bytes)
+  ubfx    w4, w1, #CCSIDR_EL1_ASSOC_SHIFT, #10    // w4 = ways - 1
+  clz     w5, w4            // w5 = bit position of the way
+  ubfx    w6, w1, #CCSIDR_EL1_NUMSETS_SHIFT, #15  // w6 = sets - 1
+  mov     w9, w4            // w9 = way
+
+2:
+  mov     w7, w6            // w7 = set
+
+3:
+  lsl     w11, w9, w5
+  lsl     w12, w7, w2
+  orr     w11, w11, w12
+  orr     w11, w11, w10     // w11 = way | set | level
+  dc      csw, x11
+  subs    w7, w7, #1
+  b.ge    3b
+  subs    w9, w9, #1
+  b.ge    2b
+
+4:
+  add     w10, w10, #2
+  cmp     w3, w10
+  b.gt    1b
+
+  msr     csselr_el1, xzr   // Restore the level 1 selection.
+  dsb     sy
+  isb
+
+5:
+  ret
@@ -16,6 +16,29 @@ cpu_halt:
   b       1b                // Infinite loop.
 
 
+///-----------------------------------------------------------------------------
+///
+/// Idle the caller.
+///
+/// # Description
+///
+/// Puts the current core into a low-power state until it is woken, then
+/// returns to the caller.
+.global cpu_idle
+cpu_idle:
+  wfi                       // Wait for interrupt.
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Hint to the core that the caller is spinning.
+.global cpu_relax
+cpu_relax:
+  yield
+  ret
+
+
 ///-----------------------------------------------------------------------------
 ///
 /// Get the current core ID.
@@ -25,3 +48,16 @@ cpu_get_id:
   ldr     x1, =CPU_AFFINITY_MASK
   and     x0, x0, x1
   ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Get the memory model feature register. See D17.2.64.
+///
+/// # Returns
+///
+/// The value of ID_AA64MMFR0_EL1.
+.global cpu_get_mmfr0
+cpu_get_mmfr0:
+  mrs     x0, id_aa64mmfr0_el1
+  ret
@@ -139,19 +139,19 @@
 el1_vectors:
 // Exception taken from EL1 with SP_EL0
   ventry  _trap_exception_el1   // Synchronous
-  ventry  _trap_exception_el1   // IRQ
+  ventry  _trap_irq_el1         // IRQ
   ventry  _trap_exception_el1   // FIQ
   ventry  _trap_exception_el1   // Error
 
 // Exception taken from EL1 with SP_EL1
   ventry  _trap_exception_el1
-  ventry  _trap_exception_el1
+  ventry  _trap_irq_el1
   ventry  _trap_exception_el1
   ventry  _trap_exception_el1
 
 // Exception taken from EL0 in AArch64
   ventry  _trap_exception_el0
-  ventry  _trap_exception_el0
+  ventry  _trap_irq_el0
   ventry  _trap_exception_el0
   ventry  _trap_exception_el0
 
@@ -190,3 +190,23 @@ _trap_exception_el1:
   mov     x2, sp
   bl      pk_handle_exception // Transfer to Rustland
   kernel_exit 1
+
+
+///-----------------------------------------------------------------------------
+///
+/// IRQ trap stub for EL0.
+_trap_irq_el0:
+  kernel_entry 0
+  mov     x0, sp
+  bl      pk_handle_irq       // Transfer to Rustland
+  kernel_exit 0
+
+
+///-----------------------------------------------------------------------------
+///
+/// IRQ trap stub for EL1.
+_trap_irq_el1:
+  kernel_entry 1
+  mov     x0, sp
+  bl      pk_handle_irq       // Transfer to Rustland
+  kernel_exit 1
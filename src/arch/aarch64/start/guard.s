@@ -0,0 +1,68 @@
+//! AArch64 Low-Level Panic Guard
+
+.include "abi.h"
+
+///-----------------------------------------------------------------------------
+///
+/// Call a function with a resumable context.
+///
+/// # Parameters
+///
+/// * x0 - The guard context address.
+/// * x1 - The function to call.
+/// * x2 - The argument to pass to the function.
+///
+/// # Description
+///
+/// Saves the callee-saved registers and the frame pointer to the guard context
+/// before calling the function. The context layout is x19-x28 followed by x29.
+///
+/// # Returns
+///
+/// 0 if the function returned normally, or 1 if `guard_resume` resumed the
+/// context.
+.global guard_call
+guard_call:
+  fn_entry
+
+  stp     x19, x20, [x0, #0]
+  stp     x21, x22, [x0, #16]
+  stp     x23, x24, [x0, #32]
+  stp     x25, x26, [x0, #48]
+  stp     x27, x28, [x0, #64]
+  str     x29, [x0, #80]
+
+  mov     x9, x1
+  mov     x0, x2
+  blr     x9
+
+  mov     x0, #0
+  fn_exit
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Resume a guard context.
+///
+/// # Parameters
+///
+/// * x0 - The guard context address.
+///
+/// # Description
+///
+/// Restores the registers saved by `guard_call` and returns from `guard_call`
+/// as if the function had returned. Restoring the frame pointer discards all
+/// frames above `guard_call`'s frame.
+.global guard_resume
+guard_resume:
+  ldp     x19, x20, [x0, #0]
+  ldp     x21, x22, [x0, #16]
+  ldp     x23, x24, [x0, #32]
+  ldp     x25, x26, [x0, #48]
+  ldp     x27, x28, [x0, #64]
+  ldr     x29, [x0, #80]
+
+  mov     x0, #1
+  fn_exit
+  ret
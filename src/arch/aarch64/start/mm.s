@@ -66,6 +66,13 @@
 .equ TCR_EL1_CACHE,  (TCR_EL1_IRGN1 | TCR_EL1_IRGN0 | TCR_EL1_ORGN1 | TCR_EL1_ORGN0)
 .equ TCR_EL1_VALUE,  (TCR_EL1_T0SZ | TCR_EL1_T1SZ | TCR_EL1_TG0_4K | TCR_EL1_TG1_4K | TCR_EL1_CACHE)
 
+// TCR_EL1.AS selects 16-bit ASIDs when the processor supports them. Support is
+// reported by ID_AA64MMFR0_EL1.ASIDBits. See D17.2.64.
+.equ TCR_EL1_AS,                  (1 << 36)
+.equ ID_AA64MMFR0_EL1_ASID_SHIFT, 4
+.equ ID_AA64MMFR0_EL1_ASID_16,    0b0010
+.equ TTBR_ASID_SHIFT,             48
+
 // EL1 memory attribute indirection register configuration. See D17.2.97.
 //
 //   * Configure attribute 0 to tag pages as normal memory. Inner and outer
@@ -330,6 +337,14 @@ mmu_setup_and_enable:
   msr     ttbr1_el1, x1
 
   ldr     x9, =TCR_EL1_VALUE
+
+// Enable 16-bit ASIDs if supported.
+  mrs     x10, id_aa64mmfr0_el1
+  ubfx    x10, x10, #ID_AA64MMFR0_EL1_ASID_SHIFT, #4
+  cmp     x10, #ID_AA64MMFR0_EL1_ASID_16
+  b.ne    1f
+  orr     x9, x9, #TCR_EL1_AS
+1:
   msr     tcr_el1, x9
 
   ldr     x9, =MAIR_EL1_VALUE
@@ -363,6 +378,181 @@ mmu_cleanup_ttbr:
   ret
 
 
+///-----------------------------------------------------------------------------
+///
+/// Get the number of ASID bits in use.
+///
+/// # Returns
+///
+/// 16 if the processor supports 16-bit ASIDs, otherwise 8.
+/// `mmu_setup_and_enable` enables 16-bit ASIDs if supported.
+.global mmu_get_asid_bits
+mmu_get_asid_bits:
+  mrs     x9, id_aa64mmfr0_el1
+  ubfx    x9, x9, #ID_AA64MMFR0_EL1_ASID_SHIFT, #4
+  mov     x0, #8
+  cmp     x9, #ID_AA64MMFR0_EL1_ASID_16
+  b.ne    1f
+  mov     x0, #16
+1:
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Set the user address space translation table and ASID.
+///
+/// # Parameters
+///
+/// * x0 - The physical address of the Level 1 translation table.
+/// * x1 - The ASID.
+///
+/// # Description
+///
+/// TTBR0_EL1.ASID is bits [63:48] of the register. See D17.2.143. TCR_EL1.A1 is
+/// zero, so the ASID in TTBR0_EL1 is the current ASID.
+.global mmu_set_ttbr0
+mmu_set_ttbr0:
+  orr     x0, x0, x1, lsl #TTBR_ASID_SHIFT
+  msr     ttbr0_el1, x0
+  isb
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate the EL1 TLB entries for an ASID on all cores in the Inner
+/// Shareable domain.
+///
+/// # Parameters
+///
+/// * x0 - The ASID.
+///
+/// # Description
+///
+/// Global entries are not affected.
+.global mmu_invalidate_tlb_asid_broadcast
+mmu_invalidate_tlb_asid_broadcast:
+  dsb     ishst
+  lsl     x0, x0, #TTBR_ASID_SHIFT
+  tlbi    aside1is, x0
+  dsb     ish
+  isb
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate the current core's TLB entries for a range of pages.
+///
+/// # Parameters
+///
+/// * x0 - The page-aligned base virtual address.
+/// * x1 - The number of pages.
+///
+/// # Description
+///
+/// The TLB will be invalidated for each page in the range at all translation
+/// levels. The caller is responsible for any translation table updates prior to
+/// calling. A barrier ensures the table updates are visible before
+/// invalidating.
+.global mmu_invalidate_tlb_range
+mmu_invalidate_tlb_range:
+  dsb     nshst
+
+  cbz     x1, 2f
+
+// TLBI VAE1 takes VA[55:12] in bits [43:0] of the operand. Leave the ASID
+// field in bits [63:48] zero; kernel entries are global.
+  ubfx    x0, x0, #PAGE_SHIFT, #44
+1:
+  tlbi    vae1, x0
+  add     x0, x0, #1
+  subs    x1, x1, #1
+  b.ne    1b
+
+// Ensure completion.
+2:
+  dsb     nsh
+  isb
+
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate all of the current core's EL1 TLB entries.
+///
+/// # Description
+///
+/// A barrier ensures any translation table updates are visible before
+/// invalidating.
+.global mmu_invalidate_tlb_all
+mmu_invalidate_tlb_all:
+  dsb     nshst
+  tlbi    vmalle1
+  dsb     nsh
+  isb
+
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate the EL1 TLB entries for a range of pages on all cores in the
+/// Inner Shareable domain.
+///
+/// # Parameters
+///
+/// * x0 - The page-aligned base virtual address.
+/// * x1 - The number of pages.
+///
+/// # Description
+///
+/// The TLBs will be invalidated for each page in the range at all translation
+/// levels on all cores in the Inner Shareable domain. The caller is responsible
+/// for any translation table updates prior to calling. A barrier ensures the
+/// table updates are visible to all cores before invalidating.
+.global mmu_invalidate_tlb_range_broadcast
+mmu_invalidate_tlb_range_broadcast:
+  dsb     ishst
+
+  cbz     x1, 2f
+
+// See `mmu_invalidate_tlb_range` for the operand format.
+  ubfx    x0, x0, #PAGE_SHIFT, #44
+1:
+  tlbi    vae1is, x0
+  add     x0, x0, #1
+  subs    x1, x1, #1
+  b.ne    1b
+
+// Ensure completion on all cores.
+2:
+  dsb     ish
+  isb
+
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate all EL1 TLB entries on all cores in the Inner Shareable domain.
+///
+/// # Description
+///
+/// A barrier ensures any translation table updates are visible to all cores
+/// before invalidating.
+.global mmu_invalidate_tlb_all_broadcast
+mmu_invalidate_tlb_all_broadcast:
+  dsb     ishst
+  tlbi    vmalle1is
+  dsb     ish
+  isb
+
+  ret
+
+
 ///-----------------------------------------------------------------------------
 ///
 /// Section-align a memory block.
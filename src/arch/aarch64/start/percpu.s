@@ -0,0 +1,23 @@
+//! AArch64 Low-Level Per-Core Data Management
+
+///-----------------------------------------------------------------------------
+///
+/// Get the current core's data area address from the TPIDR_EL1 register. See
+/// D17.2.140.
+.global percpu_get_area_addr
+percpu_get_area_addr:
+  mrs     x0, tpidr_el1
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Set the current core's data area address. See D17.2.140.
+///
+/// # Parameters
+///
+/// x0 - The data area address.
+.global percpu_set_area_addr
+percpu_set_area_addr:
+  msr     tpidr_el1, x0
+  ret
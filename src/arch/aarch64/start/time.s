@@ -0,0 +1,69 @@
+//! AArch64 Low-Level Generic Timer Utilities
+
+// Timer control register enable bit. The interrupt mask bit is left clear.
+.equ TIMER_CTL_ENABLE, 1
+
+///-----------------------------------------------------------------------------
+///
+/// Get the current physical count from CNTPCT_EL0.
+///
+/// # Description
+///
+/// The instruction barrier ensures the count is not read early.
+///
+/// # Returns
+///
+/// x0 - The 64-bit count.
+.global time_get_ticks
+time_get_ticks:
+  isb
+  mrs     x0, cntpct_el0
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Get the counter frequency from CNTFRQ_EL0.
+///
+/// # Returns
+///
+/// x0 - The frequency in Hz.
+.global time_get_frequency
+time_get_frequency:
+  mrs     x0, cntfrq_el0
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Start the physical timer.
+///
+/// # Parameters
+///
+/// x0 - The number of ticks until the timer fires.
+///
+/// # Description
+///
+/// Writes the interval to CNTP_TVAL_EL0, then enables the timer with the
+/// interrupt unmasked in CNTP_CTL_EL0. Writing CNTP_TVAL_EL0 clears a pending
+/// timer condition.
+.global time_set_timer
+time_set_timer:
+  msr     cntp_tval_el0, x0
+  mov     x0, #TIMER_CTL_ENABLE
+  msr     cntp_ctl_el0, x0
+  isb
+  ret
+
+
+///-----------------------------------------------------------------------------
+///
+/// Get the physical timer control register, CNTP_CTL_EL0.
+///
+/// # Returns
+///
+/// x0 - The control register value.
+.global time_get_timer_control
+time_get_timer_control:
+  mrs     x0, cntp_ctl_el0
+  ret
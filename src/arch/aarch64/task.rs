@@ -3,20 +3,24 @@
 #[cfg(feature = "module_tests")]
 mod tests;
 
+use super::{asid, mm};
 use crate::arch::cpu;
 use crate::support::bits;
 #[cfg(feature = "module_tests")]
 use crate::test;
 
-unsafe extern "C" {
-  fn task_get_current_task_addr() -> usize;
-  fn task_set_current_task_addr(task: usize);
-}
-
 const CPU_MASK_WORDS: usize = (cpu::MAX_CORES + usize::BITS as usize - 1) / usize::BITS as usize;
 
+const _: () = assert!(
+  CPU_MASK_WORDS * bits::WORD_BITS >= cpu::MAX_CORES,
+  "The affinity mask cannot represent every core."
+);
+
 pub type AffinityMask = bits::Bitmap<CPU_MASK_WORDS>;
 
+/// Required kernel thread stack alignment.
+const KERNEL_STACK_ALIGN: usize = 16;
+
 /// Re-initialization guard.
 static mut INITIALIZED: bool = false;
 
@@ -37,6 +41,7 @@ pub struct TaskContext {
   x29: usize, // the frame pointer
   x30: usize, // the link register
   sp: usize,  // the stack pointer
+  asid: usize,
 }
 
 impl TaskContext {
@@ -56,12 +61,94 @@ impl TaskContext {
       x29: 0,
       x30: 0,
       sp: 0,
+      asid: asid::RESERVED_ASID,
     }
   }
 
-  /// Construct a new task context.
+  /// Construct a new task context and allocate an ASID.
   pub fn new() -> Self {
-    Self::default()
+    let mut context = Self::default();
+    context.asid = asid::get_asid_allocator().lock().alloc();
+    context
+  }
+
+  /// Construct a task context for a new kernel thread.
+  ///
+  /// # Parameters
+  ///
+  /// * `entry` - The thread entry point.
+  /// * `stack_top` - The virtual address of the top of the thread's stack.
+  /// * `table_addr` - Unused.
+  ///
+  /// # Description
+  ///
+  /// The link register is set to the entry point so that the first switch to
+  /// the context returns into the thread. The frame pointer is zero to
+  /// terminate the thread's frame chain.
+  ///
+  /// Kernel threads do not have a user address space, so the context uses the
+  /// reserved ASID.
+  ///
+  ///   NOTE: The table address exists to satisfy the TaskContext interface
+  ///         requirements. Kernel threads only use the kernel's TTBR1_EL1
+  ///         tables.
+  ///
+  ///   NOTE: The stack must be 16-byte aligned.
+  pub fn new_kernel_thread(entry: fn() -> !, stack_top: usize, _table_addr: usize) -> Self {
+    assert_eq!(stack_top & (KERNEL_STACK_ALIGN - 1), 0);
+
+    let mut context = Self::default();
+    context.x30 = entry as usize;
+    context.sp = stack_top;
+    context
+  }
+
+  /// Get the context's versioned ASID.
+  pub fn get_asid(&self) -> usize {
+    self.asid
+  }
+
+  /// Program TTBR0_EL1 with the context's user address space.
+  ///
+  /// # Parameters
+  ///
+  /// * `table_addr` - The physical address of the context's Level 1 table.
+  ///
+  /// # Description
+  ///
+  /// If the context's ASID is from an older generation, the ASID is refreshed
+  /// before programming TTBR0_EL1. The ASID becomes the current core's active
+  /// ASID. See `AsidAllocator::activate()`.
+  pub fn set_user_table(&mut self, table_addr: usize) {
+    let hw_asid = {
+      let mut allocator = asid::get_asid_allocator().lock();
+      self.asid = allocator.activate(super::get_current_core_index(), self.asid);
+      allocator.get_hardware_asid(self.asid)
+    };
+
+    mm::set_user_table(table_addr, hw_asid);
+  }
+
+  /// See `Task::mapped_page_count()`.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: This function exists to satisfy the TaskContext interface
+  ///         requirements. Local mappings are never created, so the count is
+  ///         always zero.
+  pub fn mapped_page_count(&self) -> usize {
+    0
+  }
+
+  /// See `Task::free_mapping_count()`.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: This function exists to satisfy the TaskContext interface
+  ///         requirements. Local mappings are never created, so there is no
+  ///         limit.
+  pub fn free_mapping_count(&self) -> usize {
+    usize::MAX
   }
 
   /// Get the current pin mask.
@@ -74,6 +161,7 @@ impl TaskContext {
   /// # Parameters
   ///
   /// * `page_addr` - The physical address of the page to map.
+  /// * `device` - Whether the page maps to device memory.
   ///
   /// # Description
   ///
@@ -82,11 +170,15 @@ impl TaskContext {
   ///         virtual base under the assumption that all physical memory has
   ///         been linearly mapped into the kernel's virtual address space.
   ///
+  ///   NOTE: The linear map only covers normal memory, and there is no local
+  ///         mapping table to map device memory. Panics if `device` is true.
+  ///
   /// # Returns
   ///
-  /// The virtual address of the mapped page.
-  pub fn map_page(&mut self, page_addr: usize) -> usize {
-    super::get_kernel_virtual_base() + page_addr
+  /// The virtual address of the mapped page. Never None.
+  pub fn map_page(&mut self, page_addr: usize, device: bool) -> Option<usize> {
+    assert!(!device);
+    Some(super::get_kernel_virtual_base() + page_addr)
   }
 
   /// See `Task::unmap_page()`.
@@ -98,33 +190,27 @@ impl TaskContext {
   pub fn unmap_page(&mut self) {}
 }
 
+impl Drop for TaskContext {
+  /// Release the context's ASID.
+  fn drop(&mut self) {
+    asid::get_asid_allocator().lock().free(self.asid);
+  }
+}
+
 /// Initialize the bootstrap task context.
 ///
 /// # Description
 ///
 ///   NOTE: This function exists to satisfy the TaskContext interface
-///         requirements and simply returns an empty task context.
+///         requirements and simply returns an empty task context. The bootstrap
+///         task does not have a user address space and uses the reserved ASID.
 pub fn init_bootstrap_context() -> TaskContext {
   unsafe {
     assert!(!INITIALIZED);
     INITIALIZED = true;
   }
 
-  TaskContext::new()
-}
-
-/// Get the current task address from the task register.
-pub fn get_current_task_addr() -> usize {
-  unsafe { task_get_current_task_addr() }
-}
-
-/// Set the task register to a new task object address.
-///
-/// # Parameters
-///
-/// * `addr` - The new task address.
-pub fn set_current_task_addr(addr: usize) {
-  unsafe { task_set_current_task_addr(addr) }
+  TaskContext::default()
 }
 
 #[cfg(feature = "module_tests")]
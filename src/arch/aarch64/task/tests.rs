@@ -1,8 +1,9 @@
 //! AArch64 Task Tests
 
+use crate::arch::{asid, cpu};
 use crate::debug_print;
-use crate::task::Task;
-use crate::{check_eq, execute_test, test};
+use crate::task::{Task, TaskContext};
+use crate::{check_eq, check_none, execute_test, test};
 use core::slice;
 
 /// Run task tests.
@@ -12,6 +13,8 @@ use core::slice;
 /// * `context` - The test context.
 pub fn run_tests(context: &mut test::TestContext) {
   execute_test!(context, test_local_mappings);
+  execute_test!(context, test_new_kernel_thread);
+  execute_test!(context, test_mapped_page_count);
 }
 
 /// Test local mappings.
@@ -29,7 +32,7 @@ fn test_local_mappings(context: &mut test::TestContext) {
   let virt_base = crate::arch::get_kernel_virtual_base();
 
   // Map an address beyond 896 MiB. This address should be linearly mapped.
-  let lcl_address = task.map_page(0x3900_0000);
+  let lcl_address = task.map_page(0x3900_0000).unwrap();
   check_eq!(context, lcl_address, 0x3900_0000 + virt_base);
 
   // Write to the page. This will cause an exception if the mapping failed.
@@ -39,11 +42,11 @@ fn test_local_mappings(context: &mut test::TestContext) {
   check_eq!(context, lcl_page[0], 42);
 
   // Remap the same page. The address should be the same.
-  let lcl_address2 = task.map_page(0x3900_0000);
+  let lcl_address2 = task.map_page(0x3900_0000).unwrap();
   check_eq!(context, lcl_address2, lcl_address);
 
   // Map an address below 896 MiB. This address should be linearly mapped.
-  let lcl_address3 = task.map_page(0x3700_0000);
+  let lcl_address3 = task.map_page(0x3700_0000).unwrap();
   check_eq!(context, lcl_address3, 0x3700_0000 + virt_base);
 
   // Write to the page. This will cause an exception if the mapping failed.
@@ -52,3 +55,62 @@ fn test_local_mappings(context: &mut test::TestContext) {
   lcl_page3[0] = 42;
   check_eq!(context, lcl_page3[0], 42);
 }
+
+/// Kernel thread entry point for context construction tests. Never executed.
+fn test_thread_entry() -> ! {
+  loop {
+    cpu::relax();
+  }
+}
+
+/// Test constructing a kernel thread context.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// For AArch64, this verifies that the link register and stack pointer are set,
+/// the remaining registers are zero, and the context uses the reserved ASID.
+fn test_new_kernel_thread(context: &mut test::TestContext) {
+  const TEST_STACK_TOP: usize = 0x10_0000;
+  const TEST_TABLE_ADDR: usize = 0x20_0000;
+
+  let ctx = TaskContext::new_kernel_thread(test_thread_entry, TEST_STACK_TOP, TEST_TABLE_ADDR);
+  let context_regs = [
+    ctx.x19, ctx.x20, ctx.x21, ctx.x22, ctx.x23, ctx.x24, ctx.x25, ctx.x26, ctx.x27, ctx.x28,
+    ctx.x29,
+  ];
+
+  check_eq!(context, ctx.x30, test_thread_entry as *const () as usize);
+  check_eq!(context, ctx.sp, TEST_STACK_TOP);
+  check_eq!(context, ctx.get_asid(), asid::RESERVED_ASID);
+
+  for reg in context_regs {
+    check_eq!(context, reg, 0);
+  }
+}
+
+/// Test counting local mappings through the public interface.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// For AArch64, mapping a page never creates a local mapping, so the count is
+/// always zero and the task is never pinned.
+fn test_mapped_page_count(context: &mut test::TestContext) {
+  let task = Task::get_current_task_mut();
+  check_eq!(context, task.mapped_page_count(), 0);
+
+  _ = task.map_page(0x3900_0000);
+  check_eq!(context, task.mapped_page_count(), 0);
+  check_eq!(context, task.get_context().mapped_page_count(), 0);
+  check_none!(context, task.get_context().get_pin_mask());
+
+  task.unmap_page();
+  check_eq!(context, task.mapped_page_count(), 0);
+}
@@ -0,0 +1,127 @@
+//! AArch64 Architecture Tests
+
+use crate::arch::memory::{self, VirtualRegion};
+use crate::debug_print;
+use crate::support::range::RangeOrdering;
+use crate::{check_eq, check_none, check_optional, execute_test, test};
+
+/// Run architecture tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_kernel_range);
+  execute_test!(context, test_vm_layout);
+  execute_test!(context, test_vm_layout_conflict);
+}
+
+/// Test the section-aligned kernel range.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_kernel_range(context: &mut test::TestContext) {
+  let raw = super::get_kernel_range();
+  let aligned = super::get_kernel_range_section_aligned();
+  let section_mask = super::get_section_size() - 1;
+
+  check_eq!(context, aligned.base & section_mask, 0);
+  check_eq!(context, aligned.size & section_mask, 0);
+
+  let contains =
+    matches!(aligned.cmp(&raw), Some(RangeOrdering::Superset) | Some(RangeOrdering::Equal));
+  check_eq!(context, contains, true);
+}
+
+/// Test that the reported virtual address space regions do not overlap.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_vm_layout(context: &mut test::TestContext) {
+  const MAX_REGIONS: usize = 8;
+  const EXPECTED_REGIONS: usize = 3;
+
+  let mut regions = [(0, 0); MAX_REGIONS];
+  let mut count = 0;
+
+  super::dump_vm_layout(|_, base, size| {
+    if count < MAX_REGIONS {
+      regions[count] = (base, size);
+    }
+
+    count += 1;
+  });
+
+  check_eq!(context, count, EXPECTED_REGIONS);
+
+  let regions = &regions[..EXPECTED_REGIONS.min(count)];
+
+  for (base, size) in regions {
+    let valid = *size > 0 && base.checked_add(size - 1).is_some();
+    check_eq!(context, valid, true);
+  }
+
+  // The regions are reported in ascending order, so each region must end
+  // before the next begins.
+  for pair in regions.windows(2) {
+    let ((base, size), (next_base, _)) = (pair[0], pair[1]);
+    let disjoint = base.saturating_add(size.saturating_sub(1)) < next_base;
+    check_eq!(context, disjoint, true);
+  }
+}
+
+/// Test detecting conflicts in a virtual address space layout.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A kernel panic cannot be caught, so the test checks the conflict search that
+/// triggers the debug assertion in `init()` rather than the assertion itself.
+fn test_vm_layout_conflict(context: &mut test::TestContext) {
+  const PAGE_SIZE: usize = 4096;
+
+  let virtual_base = super::get_kernel_virtual_base();
+  let region = |tag, offset, size| VirtualRegion {
+    tag,
+    base: virtual_base + offset,
+    size,
+  };
+
+  let valid = [
+    region("Vectors", 0, PAGE_SIZE),
+    region("Page Directory", PAGE_SIZE, PAGE_SIZE),
+    region("Thread Local", 4 * PAGE_SIZE, 2 * PAGE_SIZE),
+  ];
+  check_none!(context, memory::find_vm_layout_conflict(virtual_base, &valid));
+
+  // The thread-local area overlaps the end of the page directory.
+  let overlapping = [
+    region("Vectors", 0, PAGE_SIZE),
+    region("Page Directory", PAGE_SIZE, 2 * PAGE_SIZE),
+    region("Thread Local", 2 * PAGE_SIZE, 2 * PAGE_SIZE),
+  ];
+  check_optional!(
+    context,
+    memory::find_vm_layout_conflict(virtual_base, &overlapping),
+    "Page Directory"
+  );
+
+  // The vectors start below the kernel segment.
+  let outside = [VirtualRegion {
+    tag: "Vectors",
+    base: virtual_base - PAGE_SIZE,
+    size: 2 * PAGE_SIZE,
+  }];
+  check_optional!(context, memory::find_vm_layout_conflict(virtual_base, &outside), "Vectors");
+
+  // An empty region is never valid.
+  let empty = [region("Recursive Map", 0, 0)];
+  check_optional!(context, memory::find_vm_layout_conflict(virtual_base, &empty), "Recursive Map");
+
+  check_none!(context, super::find_vm_layout_conflict());
+}
@@ -2,6 +2,9 @@
 
 use crate::arch;
 
+/// IRQ exception type. Must match the low-level exception handlers.
+const IRQ_EXCEPTION: usize = 5;
+
 /// ARM exception handler.
 ///
 /// # Parameters
@@ -9,6 +12,11 @@ use crate::arch;
 /// * `exception` - The exception type.
 /// * `cpu_context` - Pointer to the saved CPU context structure.
 #[unsafe(no_mangle)]
-extern "C" fn pk_handle_exception(_exception: usize, _cpu_context: usize) {
+extern "C" fn pk_handle_exception(exception: usize, _cpu_context: usize) {
+  if exception == IRQ_EXCEPTION {
+    arch::irq::dispatch();
+    return;
+  }
+
   arch::cpu::halt();
 }
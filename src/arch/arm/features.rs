@@ -0,0 +1,76 @@
+//! ARM CPU Feature Decoding
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::cpu::CpuFeatures;
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// ID_MMFR0 VMSA support field. See B4.1.89.
+const VMSA_MASK: usize = 0xf;
+
+/// VMSA support value for VMSAv7 with long descriptors, i.e. LPAE.
+const VMSA_V7_LONG_DESCRIPTORS: usize = 0b0101;
+
+/// Physical address sizes with and without LPAE.
+const LPAE_PA_BITS: usize = 40;
+const PA_BITS: usize = 32;
+
+/// ARMv7 ASIDs are always 8 bits.
+const ASID_BITS: usize = 8;
+
+/// Decode ID_MMFR0.
+///
+/// # Parameters
+///
+/// * `mmfr0` - The register value.
+///
+/// # Description
+///
+/// Later VMSA support values include the features of earlier values, so any
+/// value at or above VMSAv7 with long descriptors supports LPAE.
+///
+/// # Returns
+///
+/// The decoded features. ARMv7 only supports 4 KiB granules.
+pub fn decode_mmfr0(mmfr0: usize) -> CpuFeatures {
+  let long_descriptors = (mmfr0 & VMSA_MASK) >= VMSA_V7_LONG_DESCRIPTORS;
+
+  CpuFeatures {
+    pa_bits: if long_descriptors {
+      LPAE_PA_BITS
+    } else {
+      PA_BITS
+    },
+    asid_bits: ASID_BITS,
+    long_descriptors,
+    granule_4k: true,
+    granule_16k: false,
+    granule_64k: false,
+  }
+}
+
+/// Require a core that supports LPAE.
+///
+/// # Parameters
+///
+/// * `features` - The core's features.
+///
+/// # Description
+///
+/// The kernel only builds long-descriptor translation tables with 4 KiB
+/// granules.
+///
+///   NOTE: Panics if the core does not support LPAE.
+pub fn require_lpae(features: &CpuFeatures) {
+  assert!(
+    features.long_descriptors && features.granule_4k,
+    "The core does not support LPAE. Propeller requires long-descriptor translation tables."
+  );
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,74 @@
+//! ARM CPU Feature Decoding Tests
+
+use super::{decode_mmfr0, require_lpae};
+use crate::debug_print;
+use crate::{check_eq, check_no_panic, check_panics, execute_test, test};
+
+/// ID_MMFR0 as reported by a Cortex-A7: VMSAv7 with long descriptors.
+const CORTEX_A7_MMFR0: usize = 0x1010_1105;
+
+/// ID_MMFR0 as reported by a Cortex-A9: VMSAv7 without long descriptors.
+const CORTEX_A9_MMFR0: usize = 0x0010_0103;
+
+/// Run CPU feature decoding tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_lpae);
+  execute_test!(context, test_no_lpae);
+  execute_test!(context, test_require_lpae);
+}
+
+/// Test decoding a core with LPAE.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_lpae(context: &mut test::TestContext) {
+  let features = decode_mmfr0(CORTEX_A7_MMFR0);
+
+  check_eq!(context, features.pa_bits, 40);
+  check_eq!(context, features.asid_bits, 8);
+  check_eq!(context, features.long_descriptors, true);
+  check_eq!(context, features.granule_4k, true);
+  check_eq!(context, features.granule_16k, false);
+  check_eq!(context, features.granule_64k, false);
+
+  // A later VMSA version still supports long descriptors.
+  let features = decode_mmfr0((CORTEX_A7_MMFR0 & !0xf) | 0x6);
+  check_eq!(context, features.long_descriptors, true);
+}
+
+/// Test decoding a core without LPAE.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_no_lpae(context: &mut test::TestContext) {
+  let features = decode_mmfr0(CORTEX_A9_MMFR0);
+
+  check_eq!(context, features.pa_bits, 32);
+  check_eq!(context, features.asid_bits, 8);
+  check_eq!(context, features.long_descriptors, false);
+  check_eq!(context, features.granule_4k, true);
+}
+
+/// Test requiring LPAE.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Clearing the VMSA support field's long descriptor value must cause the
+/// requirement to panic.
+fn test_require_lpae(context: &mut test::TestContext) {
+  let lpae = decode_mmfr0(CORTEX_A7_MMFR0);
+  let no_lpae = decode_mmfr0((CORTEX_A7_MMFR0 & !0xf) | 0x3);
+
+  check_no_panic!(context, || require_lpae(&lpae));
+  check_panics!(context, || require_lpae(&no_lpae));
+}
@@ -1,7 +1,16 @@
 //! ARM Memory Management
 
-use crate::arch::memory::{MappingStrategy, PageAllocator};
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::arch::memory::{
+  DescriptorInfo, DescriptorKind, MappingCoalescer, MappingStrategy, PageAllocator,
+};
+use crate::support::addr::{PhysAddr, VirtAddr};
+use crate::support::bitfield::BitField;
 use crate::support::bits;
+#[cfg(feature = "module_tests")]
+use crate::test;
 use core::{cmp, ptr, slice};
 
 unsafe extern "C" {
@@ -11,6 +20,10 @@ unsafe extern "C" {
     desc: usize,
     desc_high: usize,
   );
+  fn mmu_invalidate_tlb_range(virt_addr: usize, pages: usize);
+  fn mmu_invalidate_tlb_range_broadcast(virt_addr: usize, pages: usize);
+  fn mmu_invalidate_tlb_all();
+  fn mmu_invalidate_tlb_all_broadcast();
 }
 
 const LEVEL_1_TABLE_SHIFT_LONG: usize = 2;
@@ -42,24 +55,39 @@ const LEVEL_2_BLOCK_LOW_MASK_LONG: usize = 0xffe0_0000;
 /// Levels 1 and 2, and indicates a page entry at Level 3. 0b01 indicates a
 /// block entry at Levels 1 and 2, but is invalid at Level 3. 0bn0 always
 /// indicates an invalid entry regardless of `n`.
-const MM_PAGE_TABLE_FLAG_LONG: usize = 0b11 << 0;
-const MM_PAGE_FLAG_LONG: usize = 0b11 << 0;
-const MM_BLOCK_FLAG_LONG: usize = 0b01 << 0;
-const MM_ACCESS_FLAG_LONG: usize = 0b1 << 10;
+const DESC_TYPE_LONG: BitField = BitField::new(0, 2);
+const MM_PAGE_TABLE_FLAG_LONG: usize = 0b11;
+const MM_PAGE_FLAG_LONG: usize = 0b11;
+const MM_BLOCK_FLAG_LONG: usize = 0b01;
+
+/// Bits [4:2] of a block or page entry are the MAIR index of the memory
+/// attributes, and bit 10 is the access flag.
+const DESC_ATTR_IDX_LONG: BitField = BitField::new(2, 3);
+const DESC_ACCESS_FLAG_LONG: BitField = BitField::new(10, 1);
 
 /// The start code has already configured the MAIR registers. Only the memory
 /// type indices are needed here. See `mm.s`.
 const MM_NORMAL_MAIR_IDX_LONG: usize = 0x0;
 const MM_DEVICE_MAIR_IDX_LONG: usize = 0x1;
 
-const TYPE_MASK: usize = 0x3;
+/// Bit 0 is set for all valid entries.
+const MM_VALID_FLAG_LONG: usize = 0b1 << 0;
+
+/// The base of the upper 1 GiB of the address space served by the recursive
+/// map. The start code installs the recursive entry in the Level 2 table that
+/// covers this region regardless of the virtual memory split.
+const RECURSIVE_MAP_COVERAGE_BASE: usize = 0usize.wrapping_sub(1 << LEVEL_1_SHIFT_LONG);
 
 /// The maximum number of local mappings a task can maintain.
-const MAX_LOCAL_MAPPINGS: usize = super::get_page_size() >> super::get_page_table_entry_shift();
+pub const MAX_LOCAL_MAPPINGS: usize = super::get_page_size() >> super::get_page_table_entry_shift();
+
+/// The number of words in a task's local mapping table. Each LPAE descriptor
+/// is two words.
+pub const LOCAL_TABLE_WORDS: usize = MAX_LOCAL_MAPPINGS * 2;
 
 /// Translation table level. LPAE supports up to 3 levels of translation.
 #[derive(Copy, Clone, PartialEq)]
-enum TableLevel {
+pub enum TableLevel {
   Level1,
   Level2,
   Level3,
@@ -96,12 +124,13 @@ pub fn direct_map_memory(
   allocator: &mut impl PageAllocator,
   strategy: MappingStrategy,
 ) {
-  let virt = virtual_base + base;
+  let base = PhysAddr::new(base);
+  let virt = base.to_virt(virtual_base);
 
   fill_table(
     virtual_base,
-    get_first_table_level(virtual_base, virt),
-    pages_start,
+    get_first_table_level(virtual_base, virt.as_usize()),
+    PhysAddr::new(pages_start),
     virt,
     base,
     size,
@@ -146,9 +175,9 @@ pub fn map_memory(
   fill_table(
     virtual_base,
     get_first_table_level(virtual_base, virt),
-    pages_start,
-    virt,
-    base,
+    PhysAddr::new(pages_start),
+    VirtAddr::new(virt),
+    PhysAddr::new(base),
     size,
     device,
     allocator,
@@ -156,6 +185,206 @@ pub fn map_memory(
   );
 }
 
+/// Unmap a range of virtual addresses.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel segment base address.
+/// * `pages_start` - The physical address of the task's starting page table.
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Clears the page and section entries covering the range, then invalidates the
+/// TLB for the range. See `invalidate_tlb_mapping()`. Sections must be unmapped
+/// in their entirety. Unmapping part of a section will assert.
+///
+///   NOTE: Tables that become empty are not freed.
+///
+/// # Assumptions
+///
+/// The physical address of the starting page table is in linear memory.
+pub fn unmap_memory(virtual_base: usize, pages_start: usize, virt: usize, size: usize) {
+  let page_size = super::get_page_size();
+
+  assert!(bits::is_aligned(virt, page_size));
+  assert!(bits::is_aligned(size, page_size));
+
+  clear_table(
+    virtual_base,
+    get_first_table_level(virtual_base, virt),
+    PhysAddr::new(pages_start),
+    VirtAddr::new(virt),
+    size,
+  );
+
+  invalidate_tlb_mapping(virt, size);
+}
+
+/// Count the number of tables a mapping would allocate.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `base` - Base of the physical address range.
+/// * `size` - Size of the physical address range.
+/// * `strategy` - The mapping strategy.
+///
+/// # Description
+///
+/// Walks the range the same way `map_memory()` would without reading or
+/// modifying any tables.
+///
+///   NOTE: The count assumes the starting table exists, but that no lower level
+///         tables exist. If part of the range is already mapped, the count is
+///         an upper bound.
+///
+/// # Returns
+///
+/// The number of table pages the mapping would allocate.
+pub fn count_tables_needed(
+  virt: usize,
+  base: usize,
+  size: usize,
+  strategy: MappingStrategy,
+) -> usize {
+  let page_size = super::get_page_size();
+
+  assert!(bits::is_aligned(virt, page_size));
+  assert!(bits::is_aligned(base, page_size));
+
+  let table_level = get_first_table_level(super::get_kernel_virtual_base(), virt);
+
+  match strategy {
+    MappingStrategy::Compact => count_tables_compact(table_level, virt, size),
+    MappingStrategy::Granular => count_tables_granular(table_level, virt, size),
+  }
+}
+
+/// Invalidate the TLB entries for a range of virtual addresses after changing
+/// the mappings.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Mappings in the thread-local area are only ever used by the core that owns
+/// the mapping's slot, so only the current core's TLB is invalidated. All other
+/// mappings are kernel-global and may be cached by any core, so the
+/// invalidation is broadcast.
+///
+///   NOTE: The range must not span the boundary between local and global
+///         mappings.
+pub fn invalidate_tlb_mapping(virt: usize, size: usize) {
+  if requires_broadcast(virt) {
+    invalidate_tlb_range_broadcast(virt, size);
+  } else {
+    invalidate_tlb_range(virt, size);
+  }
+}
+
+/// Invalidate the current core's TLB entries for a range of virtual addresses.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// The range is expanded to page boundaries. Any translation table changes
+/// made prior to calling are guaranteed to be visible before invalidation.
+pub fn invalidate_tlb_range(virt: usize, size: usize) {
+  let (base, pages) = get_invalidation_pages(virt, size);
+
+  #[cfg(feature = "module_tests")]
+  test::tlb::record_invalidation(base, pages << super::get_page_shift(), false);
+
+  unsafe {
+    mmu_invalidate_tlb_range(base, pages);
+  }
+}
+
+/// Invalidate the TLB entries for a range of virtual addresses on all cores.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// The range is expanded to page boundaries. Any translation table changes
+/// made prior to calling are guaranteed to be visible to all cores before
+/// invalidation.
+pub fn invalidate_tlb_range_broadcast(virt: usize, size: usize) {
+  let (base, pages) = get_invalidation_pages(virt, size);
+
+  #[cfg(feature = "module_tests")]
+  test::tlb::record_invalidation(base, pages << super::get_page_shift(), true);
+
+  unsafe {
+    mmu_invalidate_tlb_range_broadcast(base, pages);
+  }
+}
+
+/// Invalidate all of the current core's TLB entries.
+pub fn invalidate_tlb_all() {
+  #[cfg(feature = "module_tests")]
+  test::tlb::record_invalidation(0, usize::MAX, false);
+
+  unsafe {
+    mmu_invalidate_tlb_all();
+  }
+}
+
+/// Invalidate all TLB entries on all cores.
+pub fn invalidate_tlb_all_broadcast() {
+  #[cfg(feature = "module_tests")]
+  test::tlb::record_invalidation(0, usize::MAX, true);
+
+  unsafe {
+    mmu_invalidate_tlb_all_broadcast();
+  }
+}
+
+/// Check if changes to a mapping must be broadcast to all cores.
+///
+/// # Parameters
+///
+/// * `virt` - The virtual address of the mapping.
+///
+/// # Returns
+///
+/// False if the address is in the thread-local area, true otherwise.
+fn requires_broadcast(virt: usize) -> bool {
+  let local_base = super::get_thread_local_area_virtual_base();
+  let local_size = super::get_thread_local_area_size();
+
+  virt < local_base || virt - local_base >= local_size
+}
+
+/// Get the page range covering a range of virtual addresses.
+///
+/// # Parameters
+///
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Returns
+///
+/// The page-aligned base address and the number of pages.
+fn get_invalidation_pages(virt: usize, size: usize) -> (usize, usize) {
+  let page_size = super::get_page_size();
+  let base = bits::align_down(virt, page_size);
+  let pages = (size + (virt - base) + page_size - 1) >> super::get_page_shift();
+  (base, pages)
+}
+
 /// Maps a thread-local table into the kernel's address space.
 ///
 /// # Parameters
@@ -166,31 +395,62 @@ pub fn map_memory(
 ///
 /// # Description
 ///
-/// If using a 2/2 split, the kernel has a Level 1 table, and we need to get the
-/// address of the Level 2 table that covers the thread local area. Otherwise,
-/// if using a 3/1 split, the kernel starts at a Level 2 table.
+/// See `map_thread_local_table_for_split()`. The split is the split configured
+/// by the start code.
+pub fn map_thread_local_table(pages_start: usize, local_virt: usize, table_addr: usize) {
+  map_thread_local_table_for_split(
+    super::get_kernel_virtual_base(),
+    super::get_kernel_config().vm_split,
+    pages_start,
+    local_virt,
+    table_addr,
+  );
+}
+
+/// Maps a thread-local table into the kernel's address space for a given
+/// virtual memory split.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel virtual base address.
+/// * `vm_split` - The virtual memory split.
+/// * `pages_start` - The physical address of the starting kernel page table.
+/// * `local_virt` - The virtual address of the core's thread local area.
+/// * `table_addr` - The physical address of the task's local mappings table.
+///
+/// # Description
 ///
 /// The thread-local mapping table is mapped by adding a table entry to the
-/// Level 2 table.
+/// Level 2 table that covers the thread local area. See
+/// `get_kernel_level_2_table()`.
+///
+/// If the Level 2 entry is a block, the thread-local mapping table becomes the
+/// Level 3 table for the block. The table is back-filled with page entries
+/// equivalent to the block before the table entry replaces the block entry, so
+/// the block's mappings are not lost.
+///
+///   NOTE: Local mappings added to the table replace the back-filled entries.
 ///
 /// # Assumptions
 ///
-/// The Level 1 and Level 2 page tables are in linear memory.
-pub fn map_thread_local_table(pages_start: usize, local_virt: usize, table_addr: usize) {
-  let virtual_base = super::get_kernel_virtual_base();
-  let start_level = get_first_table_level(virtual_base, local_virt);
-  let l2_addr: usize;
-
-  if start_level == TableLevel::Level1 {
-    let table = get_table(virtual_base + pages_start);
-    let idx = get_descriptor_index(local_virt, start_level);
-    l2_addr = get_phys_addr_from_descriptor(start_level, table[idx], table[idx + 1]).unwrap();
-  } else {
-    l2_addr = pages_start;
-  }
-
+/// The Level 1 and Level 2 page tables and the thread-local mapping table are
+/// in linear memory.
+fn map_thread_local_table_for_split(
+  virtual_base: usize,
+  vm_split: usize,
+  pages_start: usize,
+  local_virt: usize,
+  table_addr: usize,
+) {
+  let l2_addr = get_kernel_level_2_table(virtual_base, vm_split, pages_start, local_virt).unwrap();
   let l2_vaddr = virtual_base + l2_addr;
   let idx = get_descriptor_index(local_virt, TableLevel::Level2);
+  let (desc, desc_high) = read_table_entry(l2_vaddr, idx);
+
+  if desc & MM_VALID_FLAG_LONG != 0 && !is_pointer_entry(TableLevel::Level2, desc, desc_high) {
+    split_block(TableLevel::Level2, get_table(virtual_base + table_addr), desc, desc_high);
+  }
+
   let desc_vaddr = l2_vaddr + (idx << bits::WORD_SHIFT);
   let (desc, desc_high) = make_pointer_descriptor(TableLevel::Level2, table_addr).unwrap();
 
@@ -199,6 +459,55 @@ pub fn map_thread_local_table(pages_start: usize, local_virt: usize, table_addr:
   }
 }
 
+/// Get the Level 2 table that covers a virtual address in the kernel segment.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel virtual base address.
+/// * `vm_split` - The virtual memory split.
+/// * `pages_start` - The physical address of the starting kernel page table.
+/// * `virt` - A virtual address in the kernel segment.
+///
+/// # Description
+///
+/// If using a 3/1 split, the kernel starts at a Level 2 table. Otherwise, if
+/// using a 2/2 split, the kernel has a Level 1 table, and the Level 2 table is
+/// read from the Level 1 entry covering the address.
+///
+///   NOTE: With a 2/2 split, the input address size of the kernel segment is 31
+///         bits, so only bit 30 indexes the Level 1 table. The index is
+///         relative to the kernel segment base, which is derived from
+///         `vm_split` rather than `virtual_base`. `virtual_base` is only used
+///         to access the tables in linear memory. See `init_table` in
+///         `start/mm.s`.
+///
+/// # Returns
+///
+/// The physical address of the Level 2 table, or None if the Level 1 entry is
+/// not a table pointer.
+fn get_kernel_level_2_table(
+  virtual_base: usize,
+  vm_split: usize,
+  pages_start: usize,
+  virt: usize,
+) -> Option<usize> {
+  if vm_split == 3 {
+    return Some(pages_start);
+  }
+
+  let segment_base = vm_split << LEVEL_1_SHIFT_LONG;
+  debug_assert!(virt >= segment_base);
+
+  let idx = get_descriptor_index(virt - segment_base, TableLevel::Level1);
+  let (desc, desc_high) = read_table_entry(virtual_base + pages_start, idx);
+
+  if !is_pointer_entry(TableLevel::Level1, desc, desc_high) {
+    return None;
+  }
+
+  get_phys_addr_from_descriptor(TableLevel::Level1, desc, desc_high)
+}
+
 /// Map a page in the task's local mappings.
 ///
 /// # Parameters
@@ -224,6 +533,7 @@ pub fn map_page_local(
   count: usize,
   device: bool,
 ) -> usize {
+  debug_assert_eq!(table.len(), LOCAL_TABLE_WORDS);
   assert!(count < MAX_LOCAL_MAPPINGS);
 
   let idx = count << 1;
@@ -252,6 +562,7 @@ pub fn map_page_local(
 /// and invalidates the current core's TLB for the virtual address assigned to
 /// the page.
 pub fn unmap_page_local(table: &mut [usize], section_vaddr: usize, count: usize) {
+  debug_assert_eq!(table.len(), LOCAL_TABLE_WORDS);
   assert!(count > 0);
 
   let idx = (count - 1) << 1;
@@ -269,6 +580,201 @@ pub fn unmap_page_local(table: &mut [usize], section_vaddr: usize, count: usize)
   }
 }
 
+/// Read the descriptor that maps a virtual address through the recursive map.
+///
+/// # Parameters
+///
+/// * `virt` - The virtual address.
+///
+/// # Description
+///
+/// Reads the Level 2 entry covering the address through the recursive map. If
+/// the entry is a block, the block descriptor is returned. Otherwise, the
+/// Level 3 entry is read through the recursive map. See the Recursive Map Area
+/// section of the architecture documentation.
+///
+///   NOTE: Only the upper 1 GiB of the kernel's address space is served by the
+///         recursive map.
+///
+/// # Returns
+///
+/// A tuple with the low and high 32-bits of the Level 3 page descriptor or
+/// Level 2 block descriptor, or None if the address is not mapped or is not
+/// served by the recursive map.
+pub fn read_descriptor(virt: usize) -> Option<(usize, usize)> {
+  if virt < RECURSIVE_MAP_COVERAGE_BASE {
+    return None;
+  }
+
+  // The recursive entry maps the Level 2 table itself into the recursive map
+  // area, so the table that maps the recursive map area is the Level 2 table.
+  let l2_vaddr = get_recursive_table_address(super::RECURSIVE_MAP_AREA)?;
+  let idx = get_descriptor_index(virt, TableLevel::Level2);
+  let (desc, desc_high) = read_table_entry(l2_vaddr, idx);
+
+  if desc & MM_VALID_FLAG_LONG == 0 {
+    return None;
+  }
+
+  if !is_pointer_entry(TableLevel::Level2, desc, desc_high) {
+    return Some((desc, desc_high));
+  }
+
+  let l3_vaddr = get_recursive_table_address(virt)?;
+  let idx = get_descriptor_index(virt, TableLevel::Level3);
+  let (desc, desc_high) = read_table_entry(l3_vaddr, idx);
+
+  if desc & MM_VALID_FLAG_LONG == 0 {
+    return None;
+  }
+
+  Some((desc, desc_high))
+}
+
+/// Get the base virtual address of the recursive map area.
+///
+/// # Parameters
+///
+/// * `entry` - The index of the recursive entry in the Level 2 table that
+///   covers the upper 1 GiB of the address space.
+///
+/// # Returns
+///
+/// The base virtual address of the 2 MiB block selected by the recursive entry.
+pub const fn get_recursive_map_area(entry: usize) -> usize {
+  RECURSIVE_MAP_COVERAGE_BASE + (entry << LEVEL_2_SHIFT_LONG)
+}
+
+/// Get the virtual address of the table that maps a given virtual address
+/// through the recursive map.
+///
+/// # Parameters
+///
+/// * `virt` - The virtual address.
+///
+/// # Description
+///
+/// Each 2 MiB section of the upper 1 GiB of the address space is served by one
+/// page in the recursive map area. With a 3/1 split, the kernel segment starts
+/// at the coverage base and all of the kernel's tables are served. With a 2/2
+/// split, the lower 1 GiB of the kernel segment is mapped by a separate Level 2
+/// table without a recursive entry and is not served.
+///
+///   NOTE: The address of the Level 3 table is only meaningful if the Level 2
+///         entry covering the address is a table pointer.
+///
+/// # Returns
+///
+/// The virtual address of the table, or None if the address is not served by
+/// the recursive map.
+pub fn get_recursive_table_address(virt: usize) -> Option<usize> {
+  if virt < RECURSIVE_MAP_COVERAGE_BASE {
+    return None;
+  }
+
+  let section_idx = (virt - RECURSIVE_MAP_COVERAGE_BASE) >> LEVEL_2_SHIFT_LONG;
+  Some(super::RECURSIVE_MAP_AREA + (section_idx << LEVEL_3_SHIFT_LONG))
+}
+
+/// Decode a descriptor for debugging.
+///
+/// # Parameters
+///
+/// * `table_level` - The table level of the descriptor.
+/// * `desc` - The lower 32-bits of the descriptor.
+/// * `desc_high` - The upper 32-bits of the descriptor.
+///
+/// # Description
+///
+/// The memory type and access flag are only decoded for blocks and pages.
+///
+///   NOTE: A descriptor with a physical address above 4 GiB is reported as
+///         invalid since the kernel cannot address it.
+///
+/// # Returns
+///
+/// The decoded descriptor.
+pub fn describe_descriptor(
+  table_level: TableLevel,
+  desc: usize,
+  desc_high: usize,
+) -> DescriptorInfo {
+  let Some(phys_addr) = get_phys_addr_from_descriptor(table_level, desc, desc_high) else {
+    return DescriptorInfo::invalid();
+  };
+
+  let kind = if is_pointer_entry(table_level, desc, desc_high) {
+    DescriptorKind::Table
+  } else if table_level == TableLevel::Level3 {
+    DescriptorKind::Page
+  } else {
+    DescriptorKind::Block
+  };
+
+  if kind == DescriptorKind::Table {
+    return DescriptorInfo {
+      kind,
+      phys_addr,
+      ..DescriptorInfo::invalid()
+    };
+  }
+
+  DescriptorInfo {
+    kind,
+    phys_addr,
+    device: DESC_ATTR_IDX_LONG.get(desc) == MM_DEVICE_MAIR_IDX_LONG,
+    access_flag: DESC_ACCESS_FLAG_LONG.get(desc) != 0,
+  }
+}
+
+/// Report the mappings in a set of kernel translation tables.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel segment base address.
+/// * `pages_start` - The physical address of the kernel's starting page table.
+/// * `emit` - Called with the base virtual address, size, and first decoded
+///   descriptor of each mapped range.
+///
+/// # Description
+///
+/// Walks the tables depth-first and reports ranges in ascending address order.
+/// Contiguous blocks or pages with the same attributes that map contiguous
+/// physical memory are reported as a single range.
+///
+/// With a 3/1 split, the starting table is the Level 2 table for the kernel
+/// segment. Otherwise, it is a Level 1 table for the whole address space.
+///
+///   NOTE: The recursive map area maps the Level 3 tables covering the upper
+///         1 GiB of the address space, so it is reported as normal pages.
+///
+/// # Assumptions
+///
+/// The physical address of the starting page table is in linear memory.
+pub fn dump_tables(
+  virtual_base: usize,
+  pages_start: usize,
+  mut emit: impl FnMut(usize, usize, &DescriptorInfo),
+) {
+  let table_level = get_first_table_level(virtual_base, virtual_base);
+  let virt = match table_level {
+    TableLevel::Level1 => 0,
+    _ => bits::align_down(virtual_base, get_table_entry_size(TableLevel::Level1)),
+  };
+  let mut coalescer = MappingCoalescer::new();
+
+  dump_table(
+    virtual_base,
+    table_level,
+    PhysAddr::new(pages_start),
+    virt,
+    &mut coalescer,
+    &mut emit,
+  );
+
+  coalescer.finish(&mut emit);
+}
+
 /// Get the first table level to translate a given virtual address.
 ///
 /// # Parameters
@@ -312,9 +818,9 @@ fn get_first_table_level(virtual_base: usize, virt_addr: usize) -> TableLevel {
 fn fill_table(
   virtual_base: usize,
   table_level: TableLevel,
-  table_addr: usize,
-  virt: usize,
-  base: usize,
+  table_addr: PhysAddr,
+  virt: VirtAddr,
+  base: PhysAddr,
   size: usize,
   device: bool,
   allocator: &mut impl PageAllocator,
@@ -392,9 +898,9 @@ fn fill_table(
 fn fill_table_compact(
   virtual_base: usize,
   table_level: TableLevel,
-  table_addr: usize,
-  virt: usize,
-  base: usize,
+  table_addr: PhysAddr,
+  virt: VirtAddr,
+  base: PhysAddr,
   size: usize,
   device: bool,
   allocator: &mut impl PageAllocator,
@@ -402,18 +908,18 @@ fn fill_table_compact(
   let page_size = super::get_page_size();
   let section_size = super::get_section_size();
 
-  assert!(bits::is_aligned(virt, page_size));
-  assert!(bits::is_aligned(base, page_size));
+  assert!(bits::is_aligned(virt.as_usize(), page_size));
+  assert!(bits::is_aligned(base.as_usize(), page_size));
 
   let entry_size = get_table_entry_size(table_level);
   let mut virt = virt;
   let mut base = base;
   let mut size = size;
-  let table = get_table(virtual_base + table_addr);
+  let table = get_table(table_addr.to_virt(virtual_base).as_usize());
 
   while size >= page_size {
-    let idx = get_descriptor_index(virt, table_level);
-    let aligned = bits::is_aligned(virt, section_size);
+    let idx = get_descriptor_index(virt.as_usize(), table_level);
+    let aligned = bits::is_aligned(virt.as_usize(), section_size);
     let mut fill_size = entry_size;
     let desc: usize;
     let desc_high: usize;
@@ -442,7 +948,7 @@ fn fill_table_compact(
         MappingStrategy::Compact,
       );
     } else {
-      (desc, desc_high) = make_descriptor(table_level, base, device).unwrap();
+      (desc, desc_high) = make_descriptor(table_level, base.as_usize(), device).unwrap();
     }
 
     table[idx] = desc;
@@ -475,26 +981,30 @@ fn fill_table_compact(
 fn fill_table_granular(
   virtual_base: usize,
   table_level: TableLevel,
-  table_addr: usize,
-  virt: usize,
-  base: usize,
+  table_addr: PhysAddr,
+  virt: VirtAddr,
+  base: PhysAddr,
   size: usize,
   device: bool,
   allocator: &mut impl PageAllocator,
 ) {
   let page_size = super::get_page_size();
 
-  assert!(bits::is_aligned(virt, page_size));
-  assert!(bits::is_aligned(base, page_size));
+  assert!(bits::is_aligned(virt.as_usize(), page_size));
+  assert!(bits::is_aligned(base.as_usize(), page_size));
 
   let entry_size = get_table_entry_size(table_level);
   let mut virt = virt;
   let mut base = base;
   let mut size = size;
-  let table = get_table(virtual_base + table_addr);
+  let table = get_table(table_addr.to_virt(virtual_base).as_usize());
 
   loop {
-    let idx = get_descriptor_index(virt, table_level);
+    let idx = get_descriptor_index(virt.as_usize(), table_level);
+
+    // Only fill up to the end of the entry. The remainder of the range is
+    // covered by the following entries.
+    let fill_size = cmp::min(size, entry_size - (virt.as_usize() & (entry_size - 1)));
     let desc: usize;
     let desc_high: usize;
 
@@ -508,30 +1018,228 @@ fn fill_table_granular(
         table[idx + 1],
         virt,
         base,
-        size,
+        fill_size,
         device,
         allocator,
         MappingStrategy::Granular,
       );
     } else {
-      (desc, desc_high) = make_descriptor(table_level, base, device).unwrap();
+      (desc, desc_high) = make_descriptor(table_level, base.as_usize(), device).unwrap();
     }
 
     table[idx] = desc;
     table[idx + 1] = desc_high;
 
-    // If the size of the block is smaller than the entry size, there is nothing
-    // left to do.
-    if size <= entry_size {
+    // If the rest of the range fits in the entry, there is nothing left to do.
+    if size <= fill_size {
       break;
     }
 
-    virt += entry_size;
-    base += entry_size;
-    size -= entry_size;
+    virt += fill_size;
+    base += fill_size;
+    size -= fill_size;
   }
 }
 
+/// Clears the entries in a page table for the specified range.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel segment base address.
+/// * `table_level` - The current table level.
+/// * `table_addr` - The physical address of the current page table.
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Descends into lower level tables as necessary. Recursion is bounded by the
+/// table levels.
+fn clear_table(
+  virtual_base: usize,
+  table_level: TableLevel,
+  table_addr: PhysAddr,
+  virt: VirtAddr,
+  size: usize,
+) {
+  let entry_size = get_table_entry_size(table_level);
+  let table = get_table(table_addr.to_virt(virtual_base).as_usize());
+  let mut virt = virt;
+  let mut size = size;
+
+  while size > 0 {
+    let idx = get_descriptor_index(virt.as_usize(), table_level);
+    let offset = virt.as_usize() & (entry_size - 1);
+    let clear_size = cmp::min(entry_size - offset, size);
+
+    if is_pointer_entry(table_level, table[idx], table[idx + 1]) {
+      clear_table(
+        virtual_base,
+        get_next_table(table_level).unwrap(),
+        PhysAddr::new(
+          get_phys_addr_from_descriptor(table_level, table[idx], table[idx + 1]).unwrap(),
+        ),
+        virt,
+        clear_size,
+      );
+    } else {
+      // The entry is a section, page, or invalid entry. A section cannot be
+      // partially unmapped.
+      assert!(DESC_TYPE_LONG.get(table[idx]) == 0 || clear_size == entry_size);
+      table[idx] = 0;
+      table[idx + 1] = 0;
+    }
+
+    virt = virt.wrapping_add(clear_size);
+    size -= clear_size;
+  }
+}
+
+/// Report the mappings in a table and its lower level tables.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel segment base address.
+/// * `table_level` - The current table level.
+/// * `table_addr` - The physical address of the current page table.
+/// * `virt` - The base virtual address translated by the table.
+/// * `coalescer` - The coalescer collecting the mapped ranges.
+/// * `emit` - Called with each coalesced range.
+///
+/// # Description
+///
+/// A Level 1 table only has four entries. Recursion is bounded by the table
+/// levels.
+fn dump_table(
+  virtual_base: usize,
+  table_level: TableLevel,
+  table_addr: PhysAddr,
+  virt: usize,
+  coalescer: &mut MappingCoalescer,
+  emit: &mut impl FnMut(usize, usize, &DescriptorInfo),
+) {
+  let entry_size = get_table_entry_size(table_level);
+  let table = get_table(table_addr.to_virt(virtual_base).as_usize());
+  let entries = match table_level {
+    TableLevel::Level1 => LEVEL_1_INDEX_MASK_LONG + 1,
+    _ => table.len() >> 1,
+  };
+
+  for idx in 0..entries {
+    let entry_virt = virt.wrapping_add(idx * entry_size);
+    let info = describe_descriptor(table_level, table[idx << 1], table[(idx << 1) + 1]);
+
+    match info.kind {
+      DescriptorKind::Invalid => {}
+      DescriptorKind::Table => dump_table(
+        virtual_base,
+        get_next_table(table_level).unwrap(),
+        PhysAddr::new(info.phys_addr),
+        entry_virt,
+        coalescer,
+        emit,
+      ),
+      _ => coalescer.add(entry_virt, entry_size, info, emit),
+    }
+  }
+}
+
+/// Count the number of tables `fill_table_compact()` would allocate.
+///
+/// # Parameters
+///
+/// * `table_level` - The current table level.
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Mirrors `fill_table_compact()`. A new table is counted the first time an
+/// entry cannot be a block. Level 3 tables only contain pages.
+///
+/// Recursion is bounded by the table levels.
+fn count_tables_compact(table_level: TableLevel, virt: usize, size: usize) -> usize {
+  let Some(next_level) = get_next_table(table_level) else {
+    return 0;
+  };
+
+  let page_size = super::get_page_size();
+  let section_size = super::get_section_size();
+  let entry_size = get_table_entry_size(table_level);
+  let mut virt = virt;
+  let mut size = size;
+  let mut last_entry = None;
+  let mut count = 0;
+
+  while size >= page_size {
+    let aligned = bits::is_aligned(virt, section_size);
+    let mut fill_size = entry_size;
+
+    if !aligned || size < entry_size {
+      if !aligned {
+        fill_size = size & (entry_size - 1);
+      } else {
+        fill_size = size;
+      }
+
+      // Only the first fill through an entry allocates a table.
+      let entry = bits::align_down(virt, entry_size);
+
+      if last_entry != Some(entry) {
+        count += 1;
+        last_entry = Some(entry);
+      }
+
+      count += count_tables_compact(next_level, virt, fill_size);
+    }
+
+    virt += fill_size;
+    size -= fill_size;
+  }
+
+  count
+}
+
+/// Count the number of tables `fill_table_granular()` would allocate.
+///
+/// # Parameters
+///
+/// * `table_level` - The current table level.
+/// * `virt` - Base of the virtual address range.
+/// * `size` - Size of the virtual address range.
+///
+/// # Description
+///
+/// Mirrors `fill_table_granular()`. Every entry above the page level that the
+/// range touches points to a new table, and the range is split at each entry
+/// boundary so that each part is only counted under its own entry.
+///
+/// Recursion is bounded by the table levels.
+fn count_tables_granular(table_level: TableLevel, virt: usize, size: usize) -> usize {
+  let Some(next_level) = get_next_table(table_level) else {
+    return 0;
+  };
+
+  let entry_size = get_table_entry_size(table_level);
+  let mut virt = virt;
+  let mut size = size;
+  let mut count = 0;
+
+  loop {
+    let fill_size = cmp::min(size, entry_size - (virt & (entry_size - 1)));
+    count += 1 + count_tables_granular(next_level, virt, fill_size);
+
+    if size <= fill_size {
+      break;
+    }
+
+    virt += fill_size;
+    size -= fill_size;
+  }
+
+  count
+}
+
 /// Given a table level, returns the size covered by a single entry.
 ///
 /// # Parameters
@@ -592,7 +1300,7 @@ fn get_phys_addr_from_descriptor(
     return None;
   }
 
-  match desc & TYPE_MASK {
+  match DESC_TYPE_LONG.get(desc) {
     MM_PAGE_TABLE_FLAG_LONG => Some(desc & TABLE_OR_PAGE_LOW_MASK_LONG),
     MM_BLOCK_FLAG_LONG => match table_level {
       TableLevel::Level1 => Some(desc & LEVEL_1_BLOCK_LOW_MASK_LONG),
@@ -659,7 +1367,7 @@ fn make_descriptor(
 ///
 /// A tuple with the low and high 32-bits of the descriptor.
 fn make_block_descriptor(phys_addr: usize, mair_idx: usize) -> (usize, usize) {
-  (phys_addr | (mair_idx << 2) | MM_ACCESS_FLAG_LONG | MM_BLOCK_FLAG_LONG, 0)
+  (make_descriptor_low(phys_addr, mair_idx, MM_BLOCK_FLAG_LONG), 0)
 }
 
 /// Make a Level 3 page descriptor.
@@ -677,7 +1385,28 @@ fn make_block_descriptor(phys_addr: usize, mair_idx: usize) -> (usize, usize) {
 ///
 /// A tuple with the low and high 32-bits of the descriptor.
 fn make_page_descriptor(phys_addr: usize, mair_idx: usize) -> (usize, usize) {
-  (phys_addr | (mair_idx << 2) | MM_ACCESS_FLAG_LONG | MM_PAGE_FLAG_LONG, 0)
+  (make_descriptor_low(phys_addr, mair_idx, MM_PAGE_FLAG_LONG), 0)
+}
+
+/// Make the low 32-bits of a block, page, or pointer descriptor.
+///
+/// # Parameters
+///
+/// * `phys_addr` - The physical address of the block, page, or table.
+/// * `mair_idx` - The attributes MAIR index.
+/// * `entry_type` - The entry type.
+///
+/// # Description
+///
+/// The access flag is set so that the first access does not fault.
+///
+/// # Returns
+///
+/// The low 32-bits of the new descriptor.
+fn make_descriptor_low(phys_addr: usize, mair_idx: usize, entry_type: usize) -> usize {
+  let desc = DESC_TYPE_LONG.set(phys_addr, entry_type);
+  let desc = DESC_ATTR_IDX_LONG.set(desc, mair_idx);
+  DESC_ACCESS_FLAG_LONG.set(desc, 1)
 }
 
 /// Determine if a descriptor is a table pointer.
@@ -694,7 +1423,7 @@ fn make_page_descriptor(phys_addr: usize, mair_idx: usize) -> (usize, usize) {
 fn is_pointer_entry(table_level: TableLevel, desc: usize, _desc_high: usize) -> bool {
   match table_level {
     TableLevel::Level3 => false,
-    _ => desc & TYPE_MASK == MM_PAGE_TABLE_FLAG_LONG,
+    _ => DESC_TYPE_LONG.get(desc) == MM_PAGE_TABLE_FLAG_LONG,
   }
 }
 
@@ -721,10 +1450,7 @@ fn make_pointer_descriptor(table_level: TableLevel, phys_addr: usize) -> Option<
       // to read/write the table through the recursive map. So, fill in the MAIR
       // index in bits [4:2] and the access flag in bit 10. Leaving bits [7:6]
       // as zero makes the page read/write for the kernel.
-      Some((
-        phys_addr | (MM_NORMAL_MAIR_IDX_LONG << 2) | MM_ACCESS_FLAG_LONG | MM_PAGE_TABLE_FLAG_LONG,
-        0,
-      ))
+      Some((make_descriptor_low(phys_addr, MM_NORMAL_MAIR_IDX_LONG, MM_PAGE_TABLE_FLAG_LONG), 0))
     }
   }
 }
@@ -778,6 +1504,60 @@ fn get_table(table_vaddr: usize) -> &'static mut [usize] {
   }
 }
 
+/// Read an entry from a live table.
+///
+/// # Parameters
+///
+/// * `table_vaddr` - The table virtual address.
+/// * `idx` - The index of the low word of the descriptor.
+///
+/// # Description
+///
+/// Live tables may be updated by other cores, so the descriptor is read with
+/// volatile reads.
+///
+/// # Returns
+///
+/// A tuple with the low and high 32-bits of the descriptor.
+fn read_table_entry(table_vaddr: usize, idx: usize) -> (usize, usize) {
+  let desc_ptr = (table_vaddr as *const usize).wrapping_add(idx);
+
+  unsafe { (ptr::read_volatile(desc_ptr), ptr::read_volatile(desc_ptr.add(1))) }
+}
+
+/// Fills a table with entries equivalent to a block in the level above.
+///
+/// # Parameters
+///
+/// * `table_level` - The table level of the block descriptor.
+/// * `table` - The lower level table.
+/// * `desc` - The low 32-bits of the block descriptor.
+/// * `desc_high` - The high 32-bits of the block descriptor.
+///
+/// # Description
+///
+/// A Level 1 block is split into Level 2 blocks, and a Level 2 block is split
+/// into Level 3 pages. Each entry keeps the block's lower attributes in bits
+/// [11:2] and the block's upper attributes in the high word. The table entry
+/// pointing to the lower level table may then replace the block entry without
+/// changing the mappings.
+fn split_block(table_level: TableLevel, table: &mut [usize], desc: usize, desc_high: usize) {
+  let next_level = get_next_table(table_level).unwrap();
+  let block_addr = get_phys_addr_from_descriptor(table_level, desc, desc_high).unwrap();
+  let attrs = desc & !(TABLE_OR_PAGE_LOW_MASK_LONG | DESC_TYPE_LONG.mask());
+  let entry_size = get_table_entry_size(next_level);
+  let entry_type = match next_level {
+    TableLevel::Level3 => MM_PAGE_FLAG_LONG,
+    _ => MM_BLOCK_FLAG_LONG,
+  };
+
+  for entry in 0..(TABLE_SIZE_LONG >> super::get_page_table_entry_shift()) {
+    let idx = entry << 1;
+    table[idx] = DESC_TYPE_LONG.set((block_addr + entry * entry_size) | attrs, entry_type);
+    table[idx + 1] = desc_high;
+  }
+}
+
 /// Allocates a new page table if necessary, then fills the table with entries
 /// for the specified range of memory.
 ///
@@ -799,8 +1579,15 @@ fn get_table(table_vaddr: usize) -> &'static mut [usize] {
 /// The current table must be Level 1 or 2. Level 3 tables can only point to
 /// pages.
 ///
+/// If the current descriptor is a block, the new table is filled with entries
+/// equivalent to the block before the range is mapped, so only the range
+/// changes. See `split_block()`.
+///
 /// Recursion is bounded by the table levels.
 ///
+///   NOTE: The caller must invalidate the TLB for the range if the block was
+///         live.
+///
 /// # Returns
 ///
 /// A tuple with the low and high 32-bits of the descriptor.
@@ -809,8 +1596,8 @@ fn alloc_table_and_fill(
   table_level: TableLevel,
   desc: usize,
   desc_high: usize,
-  virt: usize,
-  base: usize,
+  virt: VirtAddr,
+  base: PhysAddr,
   size: usize,
   device: bool,
   allocator: &mut impl PageAllocator,
@@ -819,27 +1606,29 @@ fn alloc_table_and_fill(
   let mut desc = desc;
   let mut desc_high = desc_high;
 
-  // TODO: It is probably fine to overwrite a section descriptor. If the memory
-  //       configuration is overwriting itself, then we probably have something
-  //       wrong and an exception is the right outcome if the configuration is
-  //       invalid.
   if !is_pointer_entry(table_level, desc, desc_high) {
     // Let an assert occur if we cannot allocate a table from linear memory.
     let (next_addr, _) = allocator.alloc(1).unwrap();
+    let next_addr = PhysAddr::new(next_addr);
+    let table_vaddr = next_addr.to_virt(virtual_base).as_usize();
 
-    unsafe {
-      // Zero out the table. Any entry in the table with bits 0 and 1 set to 0
-      // is invalid.
-      ptr::write_bytes((virtual_base + next_addr) as *mut u8, 0, TABLE_SIZE_LONG);
+    if DESC_TYPE_LONG.get(desc) == MM_BLOCK_FLAG_LONG {
+      split_block(table_level, get_table(table_vaddr), desc, desc_high);
+    } else {
+      unsafe {
+        // Zero out the table. Any entry in the table with bits 0 and 1 set to 0
+        // is invalid.
+        ptr::write_bytes(table_vaddr as *mut u8, 0, TABLE_SIZE_LONG);
+      }
     }
 
-    (desc, desc_high) = make_pointer_descriptor(table_level, next_addr).unwrap();
+    (desc, desc_high) = make_pointer_descriptor(table_level, next_addr.as_usize()).unwrap();
   }
 
   fill_table(
     virtual_base,
     get_next_table(table_level).unwrap(),
-    get_phys_addr_from_descriptor(table_level, desc, desc_high).unwrap(),
+    PhysAddr::new(get_phys_addr_from_descriptor(table_level, desc, desc_high).unwrap()),
     virt,
     base,
     size,
@@ -850,3 +1639,8 @@ fn alloc_table_and_fill(
 
   (desc, desc_high)
 }
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
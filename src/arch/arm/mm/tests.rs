@@ -0,0 +1,955 @@
+//! ARM Memory Management Tests
+
+use super::{
+  LOCAL_TABLE_WORDS, MAX_LOCAL_MAPPINGS, TableLevel, describe_descriptor, get_descriptor_index,
+  get_phys_addr_from_descriptor, get_table, make_descriptor, make_pointer_descriptor,
+};
+use crate::arch::memory::{
+  BufferedPageAllocator, DescriptorInfo, DescriptorKind, MappingStrategy, PageAllocator,
+};
+use crate::debug_print;
+use crate::support::{bits, print};
+use crate::test::{self, memory, tlb};
+use crate::{check_eq, check_neq, check_none, check_optional, execute_test};
+use core::fmt::Write;
+use core::ptr;
+
+/// Run memory management tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_unmap_pages);
+  execute_test!(context, test_unmap_section);
+  execute_test!(context, test_invalidation_scope);
+  execute_test!(context, test_count_tables);
+  execute_test!(context, test_read_page_descriptor);
+  execute_test!(context, test_read_block_descriptor);
+  execute_test!(context, test_thread_local_block_split);
+  execute_test!(context, test_thread_local_splits);
+  execute_test!(context, test_recursive_map_area);
+  execute_test!(context, test_recursive_table_address);
+  execute_test!(context, test_local_table_size);
+  execute_test!(context, test_descriptor_layout);
+  execute_test!(context, test_describe_descriptor);
+  execute_test!(context, test_dump_tables);
+  execute_test!(context, test_split_section);
+  execute_test!(context, test_map_device_window);
+}
+
+/// Test unmapping individual pages.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps four pages into a detached set of tables, unmaps the middle two, and
+/// verifies the entries are cleared and the TLB was invalidated for the range.
+fn test_unmap_pages(context: &mut test::TestContext) {
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+  let virt = virt_base;
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    virt,
+    phys_addr,
+    page_size * 4,
+    false,
+    &mut allocator,
+    MappingStrategy::Granular,
+  );
+
+  tlb::clear_invalidations();
+  super::unmap_memory(virt_base, root_addr, virt + page_size, page_size * 2);
+  check_eq!(context, tlb::was_invalidated(virt + page_size, page_size * 2), true);
+
+  let table = get_level_3_table(virt_base, root_addr, virt);
+  check_neq!(context, table[get_descriptor_index(virt, TableLevel::Level3)], 0);
+  check_eq!(context, table[get_descriptor_index(virt + page_size, TableLevel::Level3)], 0);
+  check_eq!(context, table[get_descriptor_index(virt + page_size * 2, TableLevel::Level3)], 0);
+  check_neq!(context, table[get_descriptor_index(virt + page_size * 3, TableLevel::Level3)], 0);
+}
+
+/// Test unmapping a whole section.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_unmap_section(context: &mut test::TestContext) {
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let section_size = crate::arch::get_section_size();
+  let (mut allocator, root_addr, _) = make_test_tables();
+  let virt = virt_base;
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    virt,
+    0,
+    section_size,
+    false,
+    &mut allocator,
+    MappingStrategy::Compact,
+  );
+
+  let level2 = get_level_2_table(virt_base, root_addr, virt);
+  let idx = get_descriptor_index(virt, TableLevel::Level2);
+  check_neq!(context, level2[idx], 0);
+
+  tlb::clear_invalidations();
+  super::unmap_memory(virt_base, root_addr, virt, section_size);
+  check_eq!(context, tlb::was_invalidated(virt, section_size), true);
+  check_eq!(context, level2[idx], 0);
+  check_eq!(context, level2[idx + 1], 0);
+}
+
+/// Test selecting local or broadcast TLB invalidation.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Kernel-global mappings must be invalidated on all cores while thread-local
+/// mappings only need to be invalidated on the current core.
+fn test_invalidation_scope(context: &mut test::TestContext) {
+  let page_size = crate::arch::get_page_size();
+  let global_virt = crate::arch::get_kernel_virtual_base();
+  let local_virt = super::super::get_thread_local_area_virtual_base();
+  let local_size = super::super::get_thread_local_area_size();
+
+  tlb::clear_invalidations();
+  super::invalidate_tlb_mapping(global_virt, page_size);
+  check_eq!(context, tlb::was_invalidated_broadcast(global_virt, page_size), true);
+  check_eq!(context, tlb::was_invalidated_local(global_virt, page_size), false);
+
+  tlb::clear_invalidations();
+  super::invalidate_tlb_mapping(local_virt, page_size);
+  check_eq!(context, tlb::was_invalidated_local(local_virt, page_size), true);
+  check_eq!(context, tlb::was_invalidated_broadcast(local_virt, page_size), false);
+
+  // The first page after the thread-local area is global.
+  let after_virt = local_virt + local_size;
+
+  tlb::clear_invalidations();
+  super::invalidate_tlb_mapping(after_virt, page_size);
+  check_eq!(context, tlb::was_invalidated_broadcast(after_virt, page_size), true);
+}
+
+/// Test reading a live page descriptor through the recursive map.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps a page into the current core's thread-local table, which is always a
+/// Level 3 table, then verifies the descriptor read through the recursive map.
+/// Assumes the tests are running on the primary core.
+fn test_read_page_descriptor(context: &mut test::TestContext) {
+  let page_size = crate::arch::get_page_size();
+  let local_virt = super::super::get_thread_local_area_virtual_base();
+  let section_idx =
+    (local_virt - super::RECURSIVE_MAP_COVERAGE_BASE) >> crate::arch::get_section_shift();
+  let table =
+    get_table(super::super::RECURSIVE_MAP_AREA + (section_idx << crate::arch::get_page_shift()));
+  let phys_addr = 0x3900_0000;
+  let (desc, desc_high) = make_descriptor(TableLevel::Level3, phys_addr, false).unwrap();
+
+  check_none!(context, super::read_descriptor(local_virt));
+
+  let page_virt = super::map_page_local(table, local_virt, phys_addr, 0, false);
+  check_eq!(context, page_virt, local_virt);
+  check_optional!(context, super::read_descriptor(page_virt).map(|d| d.0), desc);
+  check_optional!(context, super::read_descriptor(page_virt).map(|d| d.1), desc_high);
+
+  // Any address in the page reads the same descriptor, but the next page is
+  // unmapped.
+  check_optional!(context, super::read_descriptor(page_virt + 0x123).map(|d| d.0), desc);
+  check_none!(context, super::read_descriptor(page_virt + page_size));
+
+  super::unmap_page_local(table, local_virt, 1);
+  check_none!(context, super::read_descriptor(page_virt));
+
+  // Addresses below the recursive map's coverage are never served.
+  check_none!(context, super::read_descriptor(super::RECURSIVE_MAP_COVERAGE_BASE - page_size));
+}
+
+/// Test reading a live block descriptor through the recursive map.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps a section at the top of the hardware area, below the thread-local area,
+/// then verifies the descriptor read through the recursive map. The section is
+/// unmapped before returning.
+fn test_read_block_descriptor(context: &mut test::TestContext) {
+  let kconfig = super::super::get_kernel_config();
+  let section_size = crate::arch::get_section_size();
+  let virt = super::super::get_thread_local_area_virtual_base() - section_size;
+  let (mut allocator, _, _) = make_test_tables();
+
+  // Use the section-aligned test memory as the block. The memory is never
+  // accessed through the mapping.
+  let phys_addr = memory::get_test_memory_mut().as_ptr() as usize - kconfig.virtual_base;
+  let (desc, desc_high) = make_descriptor(TableLevel::Level2, phys_addr, false).unwrap();
+
+  check_none!(context, super::read_descriptor(virt));
+
+  super::map_memory(
+    kconfig.virtual_base,
+    kconfig.kernel_pages_start,
+    virt,
+    phys_addr,
+    section_size,
+    false,
+    &mut allocator,
+    MappingStrategy::Compact,
+  );
+
+  check_optional!(context, super::read_descriptor(virt).map(|d| d.0), desc);
+  check_optional!(context, super::read_descriptor(virt).map(|d| d.1), desc_high);
+  check_optional!(context, super::read_descriptor(virt + section_size - 1).map(|d| d.0), desc);
+
+  super::unmap_memory(kconfig.virtual_base, kconfig.kernel_pages_start, virt, section_size);
+  check_none!(context, super::read_descriptor(virt));
+}
+
+/// Test inserting a thread-local table over a section.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps a section compactly into a detached set of tables, then inserts a
+/// thread-local table for the section. The thread-local table must replace the
+/// block entry and carry page entries equivalent to the section.
+fn test_thread_local_block_split(context: &mut test::TestContext) {
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_shift = crate::arch::get_page_shift();
+  let section_size = crate::arch::get_section_size();
+  let virt = super::super::get_thread_local_area_virtual_base() - section_size;
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+  let phys_addr = bits::align_up(phys_addr, section_size);
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    virt,
+    phys_addr,
+    section_size,
+    false,
+    &mut allocator,
+    MappingStrategy::Compact,
+  );
+
+  let level2 = get_level_2_table(virt_base, root_addr, virt);
+  let idx = get_descriptor_index(virt, TableLevel::Level2);
+  let is_block = !super::is_pointer_entry(TableLevel::Level2, level2[idx], level2[idx + 1]);
+  check_eq!(context, is_block, true);
+
+  let (table_addr, _) = allocator.alloc(1).unwrap();
+  super::map_thread_local_table(root_addr, virt, table_addr);
+
+  check_optional!(
+    context,
+    get_phys_addr_from_descriptor(TableLevel::Level2, level2[idx], level2[idx + 1]),
+    table_addr
+  );
+
+  let table = get_level_3_table(virt_base, root_addr, virt);
+  let last_page = (section_size >> page_shift) - 1;
+
+  for page in [0, 1, last_page] {
+    let page_addr = phys_addr + (page << page_shift);
+    let (desc, desc_high) = make_descriptor(TableLevel::Level3, page_addr, false).unwrap();
+    check_eq!(context, table[page << 1], desc);
+    check_eq!(context, table[(page << 1) + 1], desc_high);
+  }
+}
+
+/// Test inserting a thread-local table with a simulated 3/1 or 2/2 split.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Builds a detached set of kernel tables laid out the way the start code lays
+/// them out for each split. With a 2/2 split, the Level 1 table only uses
+/// entries 0 and 1 for the lower and upper 1 GiB of the kernel segment. A
+/// section is installed over the thread-local section before inserting the
+/// thread-local table. The table must replace the block in the upper Level 2
+/// table, and no other table may be modified.
+fn test_thread_local_splits(context: &mut test::TestContext) {
+  const SPLITS: [(usize, usize); 2] = [(3, 0xc000_0000), (2, 0x8000_0000)];
+
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let page_shift = crate::arch::get_page_shift();
+  let section_size = crate::arch::get_section_size();
+  let local_virt = super::super::get_thread_local_area_virtual_base();
+
+  for (split, segment_base) in SPLITS {
+    let (mut allocator, root_addr, phys_addr) = make_test_tables();
+    let phys_addr = bits::align_up(phys_addr, section_size);
+    let (lower_addr, _) = allocator.alloc(1).unwrap();
+    let (upper_addr, _) = allocator.alloc(1).unwrap();
+    let (table_addr, _) = allocator.alloc(1).unwrap();
+
+    for addr in [lower_addr, upper_addr] {
+      unsafe {
+        ptr::write_bytes((virt_base + addr) as *mut u8, 0, page_size);
+      }
+    }
+
+    // With a 3/1 split, the root table is the upper Level 2 table.
+    let upper_addr = if split == 2 {
+      let root = get_table(virt_base + root_addr);
+
+      for (entry, addr) in [(0, lower_addr), (1, upper_addr)] {
+        let (desc, desc_high) = make_pointer_descriptor(TableLevel::Level1, addr).unwrap();
+        root[entry << 1] = desc;
+        root[(entry << 1) + 1] = desc_high;
+      }
+
+      upper_addr
+    } else {
+      root_addr
+    };
+
+    check_optional!(
+      context,
+      super::get_kernel_level_2_table(virt_base, split, root_addr, local_virt),
+      upper_addr
+    );
+
+    let upper = get_table(virt_base + upper_addr);
+    let idx = get_descriptor_index(local_virt, TableLevel::Level2);
+    let (desc, desc_high) = make_descriptor(TableLevel::Level2, phys_addr, false).unwrap();
+    upper[idx] = desc;
+    upper[idx + 1] = desc_high;
+
+    super::map_thread_local_table_for_split(virt_base, split, root_addr, local_virt, table_addr);
+
+    check_optional!(
+      context,
+      get_phys_addr_from_descriptor(TableLevel::Level2, upper[idx], upper[idx + 1]),
+      table_addr
+    );
+
+    let table = get_table(virt_base + table_addr);
+    let last_page = (section_size >> page_shift) - 1;
+
+    for page in [0, last_page] {
+      let page_addr = phys_addr + (page << page_shift);
+      let (desc, desc_high) = make_descriptor(TableLevel::Level3, page_addr, false).unwrap();
+      check_eq!(context, table[page << 1], desc);
+      check_eq!(context, table[(page << 1) + 1], desc_high);
+    }
+
+    let other_entries = upper
+      .iter()
+      .enumerate()
+      .filter(|&(word, _)| word != idx && word != idx + 1)
+      .all(|(_, &desc)| desc == 0);
+    check_eq!(context, other_entries, true);
+
+    if split != 2 {
+      continue;
+    }
+
+    // The lower 1 GiB of the kernel segment is served by the lower Level 2
+    // table, and the Level 1 entries above entry 1 are unused.
+    check_optional!(
+      context,
+      super::get_kernel_level_2_table(virt_base, split, root_addr, segment_base),
+      lower_addr
+    );
+
+    let lower_clear = get_table(virt_base + lower_addr)
+      .iter()
+      .all(|&desc| desc == 0);
+    check_eq!(context, lower_clear, true);
+
+    let root_clear = get_table(virt_base + root_addr)[4..]
+      .iter()
+      .all(|&desc| desc == 0);
+    check_eq!(context, root_clear, true);
+  }
+}
+
+/// Test predicting the number of tables a mapping allocates.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Compares the predicted table count against the number of pages actually
+/// allocated from the table allocator when mapping into a detached set of
+/// tables. Unaligned ranges that cross entry boundaries need one table for
+/// each entry they touch.
+fn test_count_tables(context: &mut test::TestContext) {
+  let page_size = crate::arch::get_page_size();
+  let section_size = crate::arch::get_section_size();
+
+  check_table_count(context, 0, page_size * 4, MappingStrategy::Granular);
+  check_table_count(context, 0, section_size * 2 + page_size, MappingStrategy::Granular);
+  check_table_count(context, section_size, page_size, MappingStrategy::Granular);
+  check_table_count(context, section_size - page_size, page_size * 2, MappingStrategy::Granular);
+  check_table_count(context, section_size + page_size, section_size * 2, MappingStrategy::Granular);
+  check_table_count(context, 0, section_size, MappingStrategy::Compact);
+  check_table_count(context, 0, section_size * 3, MappingStrategy::Compact);
+  check_table_count(context, 0, section_size + page_size * 3, MappingStrategy::Compact);
+}
+
+/// Map a range into a detached set of tables and verify the predicted table
+/// count.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `offset` - Offset of the range from the kernel virtual base.
+/// * `size` - Size of the range.
+/// * `strategy` - The mapping strategy.
+fn check_table_count(
+  context: &mut test::TestContext,
+  offset: usize,
+  size: usize,
+  strategy: MappingStrategy,
+) {
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+  let phys_addr = bits::align_up(phys_addr, crate::arch::get_section_size());
+  let virt = virt_base + offset;
+  let expected = super::count_tables_needed(virt, phys_addr, size, strategy);
+  let before = allocator.get_alloc_mem();
+
+  super::map_memory(virt_base, root_addr, virt, phys_addr, size, false, &mut allocator, strategy);
+
+  let allocated = (allocator.get_alloc_mem() - before) >> crate::arch::get_page_shift();
+  check_eq!(context, allocated, expected);
+}
+
+/// Construct a table allocator and a detached root table in test memory.
+///
+/// # Description
+///
+/// The tables are not attached to the MMU, so mapping into them does not
+/// affect the kernel's address space.
+///
+/// # Returns
+///
+/// A tuple with the table allocator, the physical address of the root table,
+/// and the physical address of a page-aligned test area after the tables.
+fn make_test_tables() -> (BufferedPageAllocator<1>, usize, usize) {
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let phys_addr = memory::get_test_memory_mut().as_ptr() as usize - virt_base;
+  let table_area_size = page_size * 16;
+
+  memory::reset_test_memory();
+
+  let mut allocator =
+    BufferedPageAllocator::<1>::new(phys_addr, phys_addr + table_area_size, page_size);
+  let (root_addr, _) = allocator.alloc(1).unwrap();
+
+  unsafe {
+    ptr::write_bytes((virt_base + root_addr) as *mut u8, 0, page_size);
+  }
+
+  (allocator, root_addr, phys_addr + table_area_size)
+}
+
+/// Get the Level 2 table covering a virtual address.
+///
+/// # Parameters
+///
+/// * `virt_base` - The kernel segment base address.
+/// * `root_addr` - The physical address of the root table.
+/// * `virt` - The virtual address.
+fn get_level_2_table(virt_base: usize, root_addr: usize, virt: usize) -> &'static mut [usize] {
+  let first_level = super::get_first_table_level(virt_base, virt);
+
+  if first_level == TableLevel::Level2 {
+    return get_table(virt_base + root_addr);
+  }
+
+  let root = get_table(virt_base + root_addr);
+  let idx = get_descriptor_index(virt, TableLevel::Level1);
+  let addr = get_phys_addr_from_descriptor(TableLevel::Level1, root[idx], root[idx + 1]).unwrap();
+  get_table(virt_base + addr)
+}
+
+/// Get the Level 3 table covering a virtual address.
+///
+/// # Parameters
+///
+/// * `virt_base` - The kernel segment base address.
+/// * `root_addr` - The physical address of the root table.
+/// * `virt` - The virtual address.
+fn get_level_3_table(virt_base: usize, root_addr: usize, virt: usize) -> &'static mut [usize] {
+  let level2 = get_level_2_table(virt_base, root_addr, virt);
+  let idx = get_descriptor_index(virt, TableLevel::Level2);
+  let addr =
+    get_phys_addr_from_descriptor(TableLevel::Level2, level2[idx], level2[idx + 1]).unwrap();
+  get_table(virt_base + addr)
+}
+
+/// Test that the recursive map area matches the start code.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The start code installs the recursive entry at `RECURSIVE_L2_OFFSET` bytes
+/// into the Level 2 table. See `start/mm.s`.
+fn test_recursive_map_area(context: &mut test::TestContext) {
+  const RECURSIVE_L2_OFFSET: usize = 0xff0;
+
+  let entry = RECURSIVE_L2_OFFSET >> crate::arch::get_page_table_entry_shift();
+  check_eq!(context, super::get_recursive_map_area(entry), super::super::RECURSIVE_MAP_AREA);
+  check_eq!(context, super::super::RECURSIVE_MAP_AREA, 0xffc0_0000usize);
+}
+
+/// Test locating tables through the recursive map for both splits.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The upper 1 GiB of the address space is served with either split. With a
+/// 2/2 split, the lower 1 GiB of the kernel segment is not served.
+fn test_recursive_table_address(context: &mut test::TestContext) {
+  const SPLITS: [(usize, usize); 2] = [(3, 0xc000_0000), (2, 0x8000_0000)];
+
+  let page_shift = crate::arch::get_page_shift();
+  let section_size = crate::arch::get_section_size();
+  let area = super::super::RECURSIVE_MAP_AREA;
+  let coverage_base = super::RECURSIVE_MAP_COVERAGE_BASE;
+
+  for (split, virtual_base) in SPLITS {
+    // User addresses are never served.
+    check_none!(context, super::get_recursive_table_address(virtual_base - 1));
+
+    if split == 2 {
+      check_none!(context, super::get_recursive_table_address(virtual_base));
+      check_none!(context, super::get_recursive_table_address(coverage_base - 1));
+    } else {
+      check_eq!(context, virtual_base, coverage_base);
+    }
+
+    // Every section in the upper 1 GiB is served by its own page, and the
+    // recursive map area itself is served by the Level 2 table.
+    check_optional!(context, super::get_recursive_table_address(coverage_base), area);
+    check_optional!(
+      context,
+      super::get_recursive_table_address(coverage_base + section_size),
+      area + (1 << page_shift)
+    );
+    check_optional!(
+      context,
+      super::get_recursive_table_address(area),
+      area + (super::super::RECURSIVE_MAP_ENTRY << page_shift)
+    );
+    check_optional!(
+      context,
+      super::get_recursive_table_address(usize::MAX),
+      area + (511 << page_shift)
+    );
+  }
+}
+
+/// Test the size of a task's local mapping table.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// With 4 KiB pages, the table holds 512 two-word descriptors and fills exactly
+/// one page.
+fn test_local_table_size(context: &mut test::TestContext) {
+  check_eq!(context, MAX_LOCAL_MAPPINGS, 512);
+  check_eq!(context, LOCAL_TABLE_WORDS, 1024);
+  check_eq!(context, LOCAL_TABLE_WORDS << bits::WORD_SHIFT, crate::arch::get_page_size());
+}
+
+/// Test descriptors against hand-computed values.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Descriptors have the entry type in bits [1:0], the MAIR index in bits [4:2],
+/// and the access flag in bit 10. The high word is always zero.
+fn test_descriptor_layout(context: &mut test::TestContext) {
+  const PAGE_ADDR: usize = 0x3900_0000;
+  const PAGE_DESC: usize = 0x3900_0403;
+  const BLOCK_ADDR: usize = 0x3920_0000;
+  const DEVICE_BLOCK_DESC: usize = 0x3920_0405;
+  const TABLE_ADDR: usize = 0x8000_1000;
+  const POINTER_DESC: usize = 0x8000_1403;
+
+  let page = make_descriptor(TableLevel::Level3, PAGE_ADDR, false);
+  check_optional!(context, page.map(|d| d.0), PAGE_DESC);
+  check_optional!(context, page.map(|d| d.1), 0);
+
+  let block = make_descriptor(TableLevel::Level2, BLOCK_ADDR + 0x123, true);
+  check_optional!(context, block.map(|d| d.0), DEVICE_BLOCK_DESC);
+  check_optional!(context, block.map(|d| d.1), 0);
+
+  let pointer = make_pointer_descriptor(TableLevel::Level1, TABLE_ADDR);
+  check_optional!(context, pointer.map(|d| d.0), POINTER_DESC);
+  check_optional!(context, pointer.map(|d| d.1), 0);
+}
+
+/// Test decoding known descriptors at each level.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Blocks are invalid at Level 3, and an entry type with bit 0 clear is invalid
+/// at every level. Address bits in the high word put the descriptor out of the
+/// kernel's reach. Descriptors made by the kernel must decode to the values
+/// used to make them.
+fn test_describe_descriptor(context: &mut test::TestContext) {
+  const CASES: [(TableLevel, usize, usize, DescriptorKind, usize, bool, bool); 9] = [
+    (TableLevel::Level1, 0x8000_1403, 0, DescriptorKind::Table, 0x8000_1000, false, false),
+    (TableLevel::Level1, 0x4000_0405, 0, DescriptorKind::Block, 0x4000_0000, true, true),
+    (TableLevel::Level1, 0x4000_0401, 0x1, DescriptorKind::Invalid, 0, false, false),
+    (TableLevel::Level2, 0x3920_0001, 0, DescriptorKind::Block, 0x3920_0000, false, false),
+    (TableLevel::Level2, 0x3920_0000, 0, DescriptorKind::Invalid, 0, false, false),
+    (TableLevel::Level2, 0x8000_3403, 0, DescriptorKind::Table, 0x8000_3000, false, false),
+    (TableLevel::Level3, 0x3900_0407, 0, DescriptorKind::Page, 0x3900_0000, true, true),
+    (TableLevel::Level3, 0x3900_0401, 0, DescriptorKind::Invalid, 0, false, false),
+    (TableLevel::Level3, 0x3900_0402, 0, DescriptorKind::Invalid, 0, false, false),
+  ];
+
+  for (level, desc, desc_high, kind, phys_addr, device, access_flag) in CASES {
+    let info = describe_descriptor(level, desc, desc_high);
+    let expected = DescriptorInfo {
+      kind,
+      phys_addr,
+      device,
+      access_flag,
+    };
+
+    let matches = info == expected;
+    check_eq!(context, matches, true);
+  }
+
+  let (desc, desc_high) = make_descriptor(TableLevel::Level2, 0x3920_0000, true).unwrap();
+  check_description(
+    context,
+    describe_descriptor(TableLevel::Level2, desc, desc_high),
+    "block 0x39200000 device AF",
+  );
+
+  let (desc, desc_high) = make_descriptor(TableLevel::Level3, 0x3900_0000, false).unwrap();
+  check_description(
+    context,
+    describe_descriptor(TableLevel::Level3, desc, desc_high),
+    "page 0x39000000 normal AF",
+  );
+
+  let (desc, desc_high) = make_pointer_descriptor(TableLevel::Level2, 0x8000_3000).unwrap();
+  check_description(
+    context,
+    describe_descriptor(TableLevel::Level2, desc, desc_high),
+    "table 0x80003000",
+  );
+
+  check_description(context, describe_descriptor(TableLevel::Level1, 0, 0), "invalid");
+}
+
+/// Check the formatted description of a descriptor.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `info` - The decoded descriptor.
+/// * `expected` - The expected description.
+fn check_description(context: &mut test::TestContext, info: DescriptorInfo, expected: &str) {
+  let mut buf = [0u8; 64];
+  let mut stream = print::WriteBuffer::new(&mut buf);
+
+  _ = write!(stream, "{}", info);
+  let matches = stream.as_bytes() == expected.as_bytes();
+  check_eq!(context, matches, true);
+}
+
+/// Test reporting the mappings in a set of tables.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps a range of normal memory pages and a range of device memory sections
+/// into a detached set of tables. The dump must report exactly the two mapped
+/// ranges, each coalesced into a single range.
+fn test_dump_tables(context: &mut test::TestContext) {
+  const MAX_RANGES: usize = 4;
+
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let section_size = crate::arch::get_section_size();
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+  let page_virt = virt_base + section_size;
+  let section_virt = virt_base + section_size * 4;
+  let section_phys = bits::align_up(phys_addr, section_size);
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    page_virt,
+    phys_addr,
+    page_size * 4,
+    false,
+    &mut allocator,
+    MappingStrategy::Granular,
+  );
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    section_virt,
+    section_phys,
+    section_size * 2,
+    true,
+    &mut allocator,
+    MappingStrategy::Compact,
+  );
+
+  let mut ranges = [(0, 0, DescriptorInfo::invalid()); MAX_RANGES];
+  let mut count = 0;
+
+  super::dump_tables(virt_base, root_addr, |virt, size, info| {
+    if count < MAX_RANGES {
+      ranges[count] = (virt, size, *info);
+    }
+
+    count += 1;
+  });
+
+  check_eq!(context, count, 2);
+
+  let expected = [
+    (
+      page_virt,
+      page_size * 4,
+      DescriptorInfo {
+        kind: DescriptorKind::Page,
+        phys_addr,
+        device: false,
+        access_flag: true,
+      },
+    ),
+    (
+      section_virt,
+      section_size * 2,
+      DescriptorInfo {
+        kind: DescriptorKind::Block,
+        phys_addr: section_phys,
+        device: true,
+        access_flag: true,
+      },
+    ),
+  ];
+
+  for (range, expected) in ranges.iter().zip(expected.iter()) {
+    check_eq!(context, range.0, expected.0);
+    check_eq!(context, range.1, expected.1);
+    let matches = range.2 == expected.2;
+    check_eq!(context, matches, true);
+  }
+}
+
+/// Test mapping a page inside a section.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps a section of normal memory into a detached set of tables, then maps a
+/// device page over the fourth page of the section. The section must be split
+/// into pages so that only the fourth page changes.
+fn test_split_section(context: &mut test::TestContext) {
+  const MAX_RANGES: usize = 4;
+
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let section_size = crate::arch::get_section_size();
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+  let section_virt = virt_base + section_size * 2;
+  let section_phys = bits::align_up(phys_addr, section_size);
+  let page_virt = section_virt + page_size * 3;
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    section_virt,
+    section_phys,
+    section_size,
+    false,
+    &mut allocator,
+    MappingStrategy::Compact,
+  );
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    page_virt,
+    phys_addr,
+    page_size,
+    true,
+    &mut allocator,
+    MappingStrategy::Granular,
+  );
+
+  let mut ranges = [(0, 0, DescriptorInfo::invalid()); MAX_RANGES];
+  let mut count = 0;
+
+  super::dump_tables(virt_base, root_addr, |virt, size, info| {
+    if count < MAX_RANGES {
+      ranges[count] = (virt, size, *info);
+    }
+
+    count += 1;
+  });
+
+  check_eq!(context, count, 3);
+
+  let normal_page = DescriptorInfo {
+    kind: DescriptorKind::Page,
+    phys_addr: section_phys,
+    device: false,
+    access_flag: true,
+  };
+  let expected = [
+    (section_virt, page_size * 3, normal_page),
+    (
+      page_virt,
+      page_size,
+      DescriptorInfo {
+        phys_addr,
+        device: true,
+        ..normal_page
+      },
+    ),
+    (
+      page_virt + page_size,
+      section_size - page_size * 4,
+      DescriptorInfo {
+        phys_addr: section_phys + page_size * 4,
+        ..normal_page
+      },
+    ),
+  ];
+
+  for (range, expected) in ranges.iter().zip(expected.iter()) {
+    check_eq!(context, range.0, expected.0);
+    check_eq!(context, range.1, expected.1);
+    let matches = range.2 == expected.2;
+    check_eq!(context, matches, true);
+  }
+}
+
+/// Test mapping a device range into the driver area.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps device pages at the base of the driver area in a detached set of
+/// tables. The virtual address is unrelated to the linear mapping of the
+/// physical range, so the dump must report only the driver area range.
+///
+/// The driver area is in the kernel segment, so translation must start at
+/// Level 2 in a 3/1 split. User addresses always start at Level 1.
+fn test_map_device_window(context: &mut test::TestContext) {
+  const MAX_RANGES: usize = 2;
+
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let page_size = crate::arch::get_page_size();
+  let driver_virt = super::super::DRIVER_VIRTUAL_BASE;
+  let (mut allocator, root_addr, phys_addr) = make_test_tables();
+
+  let expected_level = if super::super::get_kernel_config().vm_split == 3 {
+    TableLevel::Level2
+  } else {
+    TableLevel::Level1
+  };
+
+  let driver_level_matches = super::get_first_table_level(virt_base, driver_virt) == expected_level;
+  check_eq!(context, driver_level_matches, true);
+  let user_level_matches = super::get_first_table_level(virt_base, 0) == TableLevel::Level1;
+  check_eq!(context, user_level_matches, true);
+
+  super::map_memory(
+    virt_base,
+    root_addr,
+    driver_virt,
+    phys_addr,
+    page_size * 2,
+    true,
+    &mut allocator,
+    MappingStrategy::Granular,
+  );
+
+  let mut ranges = [(0, 0, DescriptorInfo::invalid()); MAX_RANGES];
+  let mut count = 0;
+
+  super::dump_tables(virt_base, root_addr, |virt, size, info| {
+    if count < MAX_RANGES {
+      ranges[count] = (virt, size, *info);
+    }
+
+    count += 1;
+  });
+
+  check_eq!(context, count, 1);
+  check_eq!(context, ranges[0].0, driver_virt);
+  check_eq!(context, ranges[0].1, page_size * 2);
+
+  let expected = DescriptorInfo {
+    kind: DescriptorKind::Page,
+    phys_addr,
+    device: true,
+    access_flag: true,
+  };
+  let matches = ranges[0].2 == expected;
+  check_eq!(context, matches, true);
+}
@@ -1,22 +1,27 @@
 //! ARM Architecture
 
 mod exceptions;
-mod mm;
+#[cfg(feature = "module_tests")]
+mod tests;
 
+pub mod features;
+pub mod mm;
 pub mod task;
 
 #[cfg(feature = "serial_debug_output")]
 pub use super::arm_common::debug;
-pub use super::arm_common::{cpu, interrupts, sync};
+#[cfg(feature = "module_tests")]
+pub use super::arm_common::panic_guard;
+pub use super::arm_common::{cache, cpu, dtb_mmio, gic, interrupts, irq, percpu, sync, time};
 pub use super::common::{device_tree, memory};
 
-use super::arm_common::{dtb_cpu, dtb_memory};
+use super::arm_common::{dtb_chosen, dtb_cpu, dtb_memory};
 use crate::arch::memory::PageAllocator;
 use crate::debug_print;
 use crate::support::{bits, dtb, range};
 #[cfg(feature = "module_tests")]
 use crate::test;
-use core::{ptr, slice};
+use core::{cmp, ptr, slice};
 use memory::{
   BufferedPageAllocator, MappingStrategy, MemoryConfig, MemoryRange, MemoryRangeHandler, MemoryZone,
 };
@@ -44,22 +49,57 @@ const PAGE_TABLE_ENTRY_SIZE: usize = 8;
 const PAGE_TABLE_ENTRY_SHIFT: usize = 3;
 
 /// Reserve the upper 128 MiB of the kernel segment for the high memory area.
+/// The fixed areas, the page database, the ISR stack area, and the thread-local
+/// area must all fit in the high memory area. See `get_high_mem_layout()`.
 const HIGH_MEM_SIZE: usize = 128 * 1024 * 1024;
 
 /// The base virtual address of the exception vectors.
 const VECTORS_VIRTUAL_BASE: usize = 0xffff_0000;
 
-/// The base virtual address of the recursive map area.
-const RECURSIVE_MAP_AREA: usize = 0xffc0_0000;
+/// The size of the exception vectors and the stub pointers page that follows.
+const VECTORS_SIZE: usize = 2 * PAGE_SIZE;
 
-/// The base virtual address of the driver area.
-const DRIVER_VIRTUAL_BASE: usize = 0xf800_0000;
+/// The index of the recursive entry in the Level 2 table that covers the upper
+/// 1 GiB of the address space. See `RECURSIVE_L2_OFFSET` in `start/mm.s`.
+const RECURSIVE_MAP_ENTRY: usize = 510;
 
-/// The size of the virtual area reserved for the page directory.
-const PAGE_DATABASE_SIZE: usize = 24 * 1024 * 1024;
-
-/// The base virtual address of the page directory.
-const PAGE_DATABASE_VIRTUAL_BASE: usize = RECURSIVE_MAP_AREA - PAGE_DATABASE_SIZE;
+/// The base virtual address of the recursive map area.
+const RECURSIVE_MAP_AREA: usize = mm::get_recursive_map_area(RECURSIVE_MAP_ENTRY);
+
+/// The base virtual address of the driver area at the bottom of the high
+/// memory area.
+const DRIVER_VIRTUAL_BASE: usize = 0usize.wrapping_sub(HIGH_MEM_SIZE);
+
+/// The size of the virtual area reserved for the page database for each 1 GiB
+/// of the kernel segment. See `get_page_database_size_for_split()`.
+const PAGE_DATABASE_SIZE_PER_GIB: usize = 24 * 1024 * 1024;
+
+// The 2/2 split has the largest kernel segment and, therefore, the largest page
+// database.
+const _: () = assert!(
+  RECURSIVE_MAP_AREA - get_page_database_size_for_split(2) > DRIVER_VIRTUAL_BASE,
+  "The high memory area is too small for the fixed areas."
+);
+
+// The thread-local area is placed in the high memory area. The local mapping
+// tables are found through the recursive map, which only serves the upper
+// 1 GiB of the address space regardless of the virtual memory split.
+const _: () = assert!(
+  DRIVER_VIRTUAL_BASE >= mm::get_recursive_map_area(0),
+  "The high memory area is not served by the recursive map."
+);
+
+/// Virtual layout of the areas in the high memory area that depend on the
+/// virtual memory split or the number of cores.
+#[derive(Copy, Clone)]
+struct HighMemLayout {
+  page_database_base: usize,
+  page_database_size: usize,
+  isr_stack_area_base: usize,
+  isr_stack_area_size: usize,
+  thread_local_area_base: usize,
+  thread_local_area_size: usize,
+}
 
 /// Basic kernel configuration provided by the start code. All address are
 /// physical.
@@ -98,6 +138,11 @@ static mut KERNEL_CONFIG: KernelConfig = KernelConfig {
 /// System device tree.
 static mut DEVICE_TREE: device_tree::DeviceTree = device_tree::DeviceTree::new();
 
+/// The base virtual address and size of the page database.
+static mut PAGE_DATABASE_VIRTUAL_BASE: usize = 0;
+
+static mut PAGE_DATABASE_SIZE: usize = 0;
+
 /// The base virtual address and size of the thread local mapping area.
 static mut THREAD_LOCAL_AREA_VIRTUAL_BASE: usize = 0;
 
@@ -171,6 +216,8 @@ impl MemoryRangeHandler for RangeZoneTagger {
 ///
 ///   NOTE: Requires 4 KiB pages.
 ///
+///   NOTE: Requires a core that supports long descriptors, i.e. LPAE.
+///
 ///   NOTE: Requires the kernel stack page count to be a power of two.
 ///
 ///   NOTE: Requires the blob to be a DTB.
@@ -207,6 +254,9 @@ pub fn init(config_addr: usize) {
   // Require 4 KiB pages.
   assert_eq!(kconfig.page_size, PAGE_SIZE);
 
+  // Require LPAE. The MMU code only builds long-descriptor translation tables.
+  features::require_lpae(&cpu::features());
+
   // Require a power-of-2 page count for the kernel stack size.
   assert!(bits::is_power_of_2(kconfig.kernel_stack_pages));
 
@@ -228,6 +278,14 @@ pub fn init(config_addr: usize) {
   init_memory_config(blob_vaddr, blob_size);
   init_direct_map(&mut allocator);
 
+  dump_vm_layout(|name, base, size| {
+    debug_print!("{}: {:#x} - {:#x}\n", name, base, base + size - 1);
+  });
+
+  if cfg!(debug_assertions) {
+    check_vm_layout();
+  }
+
   debug_print!("arch init complete.\n");
 }
 
@@ -297,6 +355,43 @@ pub fn get_kernel_base() -> usize {
   unsafe { KERNEL_CONFIG.kernel_base }
 }
 
+/// Get the physical range occupied by the kernel image.
+///
+/// # Description
+///
+///   NOTE: The interface guarantees read-only access outside of the module and
+///         one-time initialization is assumed.
+pub fn get_kernel_range() -> MemoryRange {
+  let kconfig = get_kernel_config();
+
+  MemoryRange {
+    tag: MemoryZone::LinearMemoryZone,
+    base: kconfig.kernel_base,
+    size: kconfig.kernel_size,
+  }
+}
+
+/// Get the physical range occupied by the kernel image aligned outward to
+/// section boundaries.
+///
+/// # Description
+///
+/// The base is aligned down and the end is aligned up to the section size.
+///
+///   NOTE: The interface guarantees read-only access outside of the module and
+///         one-time initialization is assumed.
+pub fn get_kernel_range_section_aligned() -> MemoryRange {
+  let range = get_kernel_range();
+  let base = bits::align_down(range.base, SECTION_SIZE);
+  let end = bits::align_up(range.base + range.size, SECTION_SIZE);
+
+  MemoryRange {
+    tag: range.tag,
+    base,
+    size: end - base,
+  }
+}
+
 /// Get the maximum physical address.
 ///
 /// # Description
@@ -328,27 +423,139 @@ pub fn get_device_tree() -> &'static device_tree::DeviceTree {
   unsafe { ptr::addr_of!(DEVICE_TREE).as_ref().unwrap() }
 }
 
+/// Get the system memory configuration.
+///
+/// # Description
+///
+/// Convenience interface to access the memory configuration in the system
+/// device tree.
+pub fn get_memory_config() -> &'static MemoryConfig {
+  get_device_tree().get_memory_config()
+}
+
 /// Get the core index of the current core.
 ///
 /// # Description
 ///
 /// For any non-trivial use of the core index, interrupts must be disabled prior
 /// to calling to prevent the task from moving to another core.
+///
+/// The index is cached in the core's data area after the first lookup. See
+/// `percpu::get_current_core_index()`.
 pub fn get_current_core_index() -> usize {
-  get_device_tree()
-    .get_core_config()
-    .get_core_index(cpu::get_id())
-    .unwrap()
+  percpu::get_current_core_index(lookup_current_core_index)
+}
+
+/// Look up the core index of the current core in the core configuration.
+///
+/// # Description
+///
+/// Prefer `get_current_core_index()`, which caches the result.
+///
+///   NOTE: Panics if the current core's ID is not in the core configuration,
+///         e.g. a secondary core the DTB did not describe.
+pub fn lookup_current_core_index() -> usize {
+  let id = cpu::get_id();
+
+  let Some(core_idx) = get_device_tree().get_core_config().get_core_index(id) else {
+    panic!("Core ID {:#x} is not in the core configuration.", id);
+  };
+
+  core_idx
 }
 
 /// Get the page database virtual base address.
+///
+/// # Description
+///
+///   NOTE: The interface guarantees read-only access outside of the module and
+///         one-time initialization is assumed.
 pub fn get_page_database_virtual_base() -> usize {
   unsafe { PAGE_DATABASE_VIRTUAL_BASE }
 }
 
 /// Get the size of the page database.
+///
+/// # Description
+///
+///   NOTE: The interface guarantees read-only access outside of the module and
+///         one-time initialization is assumed.
 pub fn get_page_database_size() -> usize {
-  PAGE_DATABASE_SIZE
+  unsafe { PAGE_DATABASE_SIZE }
+}
+
+/// Report the layout of the kernel's virtual address space.
+///
+/// # Parameters
+///
+/// * `emit` - Called with the name, base virtual address, and size of each
+///   region.
+///
+/// # Description
+///
+/// Regions are reported in ascending address order. The hardware area is the
+/// space remaining between the base of the high memory area and the
+/// thread-local area.
+///
+///   NOTE: Only valid after the memory configuration has been initialized.
+pub fn dump_vm_layout(mut emit: impl FnMut(&'static str, usize, usize)) {
+  let local_base = get_thread_local_area_virtual_base();
+
+  emit("Linear Mappings", get_kernel_virtual_base(), get_direct_map_limit());
+  emit("Hardware Area", DRIVER_VIRTUAL_BASE, local_base - DRIVER_VIRTUAL_BASE);
+  emit("Thread Local", local_base, get_thread_local_area_size());
+  emit("ISR Stacks", get_isr_stack_area_virtual_base(), get_isr_stack_size());
+  emit("Page Database", get_page_database_virtual_base(), get_page_database_size());
+  emit("Recursive Map", RECURSIVE_MAP_AREA, SECTION_SIZE);
+  emit("Exception Vectors", VECTORS_VIRTUAL_BASE, VECTORS_SIZE);
+}
+
+/// Maximum number of regions reported by `dump_vm_layout()`.
+const MAX_VM_REGIONS: usize = 8;
+
+/// Find a conflict in the kernel's virtual address space layout.
+///
+/// # Description
+///
+/// Collects the regions reported by `dump_vm_layout()` and checks that they are
+/// mutually non-overlapping and within the kernel segment.
+///
+///   NOTE: Only valid after the memory configuration has been initialized.
+///
+/// # Returns
+///
+/// The name of the first conflicting region, or None if the layout is valid.
+fn find_vm_layout_conflict() -> Option<&'static str> {
+  let mut regions = [memory::VirtualRegion {
+    tag: "",
+    base: 0,
+    size: 0,
+  }; MAX_VM_REGIONS];
+  let mut count = 0;
+
+  dump_vm_layout(|name, base, size| {
+    assert!(count < MAX_VM_REGIONS);
+    regions[count] = memory::VirtualRegion {
+      tag: name,
+      base,
+      size,
+    };
+    count += 1;
+  });
+
+  memory::find_vm_layout_conflict(get_kernel_virtual_base(), &regions[..count])
+}
+
+/// Check the kernel's virtual address space layout.
+///
+/// # Description
+///
+/// Panics if any region overlaps another region or is outside of the kernel
+/// segment. Only called in debug builds.
+fn check_vm_layout() {
+  if let Some(name) = find_vm_layout_conflict() {
+    panic!("Virtual region \"{}\" conflicts with the kernel layout.", name);
+  }
 }
 
 /// Get the base physical address of the high memory area.
@@ -360,6 +567,24 @@ fn get_high_mem_base() -> usize {
   usize::MAX - get_kernel_virtual_base() - HIGH_MEM_SIZE + 1
 }
 
+/// Get the physical address at which the direct map ends.
+///
+/// # Description
+///
+/// The direct map never extends into the thread-local area or the high memory
+/// area. This guarantees that `mm::map_thread_local_table()` always finds an
+/// empty Level 2 entry for a core's thread-local section rather than a section
+/// installed by the direct map.
+///
+///   NOTE: Private to the ARM architecture
+///
+///   NOTE: Only valid after the thread-local area has been laid out by
+///         `init_memory_config()`.
+fn get_direct_map_limit() -> usize {
+  let local_base = get_thread_local_area_virtual_base() - get_kernel_virtual_base();
+  cmp::min(get_high_mem_base(), local_base)
+}
+
 /// Get the base virtual address of the thread local mapping area.
 ///
 /// # Description
@@ -461,9 +686,11 @@ fn init_core_config(blob_vaddr: usize) {
   assert!(dtb_cpu::get_core_config(core_config, blob_vaddr));
 
   for core in core_config.get_cores() {
-    let s = core::str::from_utf8(&core.get_core_type()).unwrap_or("Unknown");
+    let s = core::str::from_utf8(core.get_primary_core_type()).unwrap_or("Unknown");
     debug_print!("Core {:x}: {}\n", core.get_id(), s)
   }
+
+  percpu::init(core_config.get_core_count());
 }
 
 /// Initialize the memory layout configuration.
@@ -477,8 +704,9 @@ fn init_core_config(blob_vaddr: usize) {
 ///
 /// Reads the ranges covered by memory devices from the DTB, then excludes any
 /// physical memory beyond the virtual base address, excludes 0 to the end of
-/// the section-aligned kernel, and excludes the section-aligned DTB area. The
-/// remaining physical memory is available for use.
+/// the section-aligned kernel, excludes the section-aligned DTB area, and
+/// excludes the section-aligned initial ramdisk, if any. The remaining physical
+/// memory is available for use.
 ///
 /// # Assumptions
 ///
@@ -489,29 +717,39 @@ fn init_memory_config(blob_vaddr: usize, blob_size: usize) {
 
   let kconfig = get_kernel_config();
   let core_count = device_tree.get_core_config().get_core_count();
-  let page_shift = get_page_shift();
   let section_size = get_section_size();
+  let kernel_range = get_kernel_range_section_aligned();
   let blob_start = bits::align_down(kconfig.blob, section_size);
   let blob_size = bits::align_up(kconfig.blob + blob_size, section_size) - blob_start;
 
+  let Some(layout) = get_high_mem_layout(kconfig.vm_split, core_count, kconfig.kernel_stack_pages)
+  else {
+    panic!("The page database, ISR stack, and thread-local areas do not fit in high memory.");
+  };
+
   unsafe {
-    ISR_STACK_AREA_SIZE = ((kconfig.kernel_stack_pages + 1) << page_shift) * 4 * core_count;
-    ISR_STACK_AREA_VIRTUAL_BASE = PAGE_DATABASE_VIRTUAL_BASE - ISR_STACK_AREA_SIZE;
-    THREAD_LOCAL_AREA_SIZE = section_size * core_count;
-    THREAD_LOCAL_AREA_VIRTUAL_BASE =
-      bits::align_down(ISR_STACK_AREA_VIRTUAL_BASE - THREAD_LOCAL_AREA_SIZE, section_size);
+    PAGE_DATABASE_SIZE = layout.page_database_size;
+    PAGE_DATABASE_VIRTUAL_BASE = layout.page_database_base;
+    ISR_STACK_AREA_SIZE = layout.isr_stack_area_size;
+    ISR_STACK_AREA_VIRTUAL_BASE = layout.isr_stack_area_base;
+    THREAD_LOCAL_AREA_SIZE = layout.thread_local_area_size;
+    THREAD_LOCAL_AREA_VIRTUAL_BASE = layout.thread_local_area_base;
   }
 
-  let tagger = RangeZoneTagger::new(get_high_mem_base());
+  let tagger = RangeZoneTagger::new(get_direct_map_limit());
   let mem_config = device_tree.get_memory_config_mut();
-  assert!(dtb_memory::get_memory_layout(mem_config, &tagger, blob_vaddr));
+  match dtb_memory::get_memory_layout(mem_config, &tagger, blob_vaddr) {
+    Ok(_) => {}
+    Err(dtb_memory::MemoryLayoutError::TooManyRanges) => panic!("Too many memory ranges."),
+    Err(_) => panic!("Failed to read the memory layout."),
+  }
 
   let excl = &[
     // Exclude the page database.
     MemoryRange {
       tag: MemoryZone::InvalidZone,
-      base: PAGE_DATABASE_VIRTUAL_BASE - kconfig.virtual_base,
-      size: PAGE_DATABASE_SIZE,
+      base: get_page_database_virtual_base() - kconfig.virtual_base,
+      size: get_page_database_size(),
     },
     // Exclude the kernel area.
     MemoryRange {
@@ -523,7 +761,7 @@ fn init_memory_config(blob_vaddr: usize, blob_size: usize) {
     MemoryRange {
       tag: MemoryZone::InvalidZone,
       base: 0,
-      size: bits::align_up(kconfig.kernel_base + kconfig.kernel_size, section_size),
+      size: kernel_range.base + kernel_range.size,
     },
     // Exclude the DTB blob.
     MemoryRange {
@@ -534,12 +772,97 @@ fn init_memory_config(blob_vaddr: usize, blob_size: usize) {
   ];
 
   for range in excl {
-    mem_config.exclude_range(range);
+    mem_config
+      .exclude_reserved_range(range)
+      .expect("Failed to exclude the kernel and DTB from the memory layout.");
+  }
+
+  // Exclude the section-aligned initial ramdisk, if the bootloader provided
+  // one, so that it is not overwritten.
+  if let Some(initrd) = dtb_chosen::get_chosen_info(blob_vaddr)
+    .and_then(|chosen| chosen.get_initrd_exclusion(section_size))
+  {
+    mem_config
+      .exclude_reserved_range(&initrd)
+      .expect("Failed to exclude the initial ramdisk from the memory layout.");
   }
 
   for range in mem_config.get_ranges() {
     debug_print!("Memory: {:#x} - {:#x}\n", range.base, range.base + range.size - 1);
   }
+
+  debug_print!("Total memory: {:#x} bytes\n", mem_config.total_size());
+}
+
+/// Get the size of the page database for a virtual memory split.
+///
+/// # Parameters
+///
+/// * `vm_split` - The virtual memory split.
+///
+/// # Description
+///
+/// The split is the number of GiB of the 4 GiB address space given to user
+/// space. The page database grows with the size of the kernel segment, so a 2/2
+/// split reserves twice the area reserved by a 3/1 split.
+///
+/// # Returns
+///
+/// The size of the virtual area reserved for the page database.
+const fn get_page_database_size_for_split(vm_split: usize) -> usize {
+  (4 - vm_split) * PAGE_DATABASE_SIZE_PER_GIB
+}
+
+/// Lay out the page database, ISR stack, and thread-local areas in the high
+/// memory area.
+///
+/// # Parameters
+///
+/// * `vm_split` - The virtual memory split.
+/// * `core_count` - The number of cores.
+/// * `kernel_stack_pages` - The number of pages in each ISR stack.
+///
+/// # Description
+///
+/// The page database is placed directly below the recursive map area and is
+/// sized for the split. See `get_page_database_size_for_split()`. The ISR
+/// stack area is placed directly below the page database, and the
+/// section-aligned thread-local area is placed below the ISR stack area. The
+/// space remaining between the base of the high memory area and the
+/// thread-local area is the hardware area.
+///
+///   NOTE: The whole high memory area is served by the recursive map with
+///         either split, so the thread-local area is always served if it fits.
+///
+/// # Returns
+///
+/// The layout, or None if the areas do not fit in the high memory area.
+fn get_high_mem_layout(
+  vm_split: usize,
+  core_count: usize,
+  kernel_stack_pages: usize,
+) -> Option<HighMemLayout> {
+  let page_database_size = get_page_database_size_for_split(vm_split);
+  let page_database_base = RECURSIVE_MAP_AREA.checked_sub(page_database_size)?;
+  let step_size = kernel_stack_pages.checked_add(1)?.checked_mul(PAGE_SIZE)?;
+  let isr_stack_area_size = step_size.checked_mul(4)?.checked_mul(core_count)?;
+  let isr_stack_area_base = page_database_base.checked_sub(isr_stack_area_size)?;
+  let thread_local_area_size = SECTION_SIZE.checked_mul(core_count)?;
+  let thread_local_area_base =
+    bits::align_down(isr_stack_area_base.checked_sub(thread_local_area_size)?, SECTION_SIZE);
+
+  if thread_local_area_base < DRIVER_VIRTUAL_BASE {
+    return None;
+  }
+
+  Some(HighMemLayout {
+    page_database_base,
+    page_database_size,
+    isr_stack_area_base,
+    isr_stack_area_size,
+    thread_local_area_base,
+    thread_local_area_size,
+  })
 }
 
 /// Initialize the linear memory map.
@@ -548,45 +871,34 @@ fn init_memory_config(blob_vaddr: usize, blob_size: usize) {
 ///
 /// Linearly maps physical memory into the kernel page tables. Invalidating the
 /// TLB is not required here. We are only adding new entries at this point.
+///
+/// The direct map stops below the thread-local area so that the thread-local
+/// sections are never covered by section entries. See `get_direct_map_limit()`.
 fn init_direct_map(allocator: &mut impl PageAllocator) {
   let kconfig = get_kernel_config();
 
   // The memory layout already excludes any physical memory beyond the kernel /
   // user split. However, we still need to mask off physical memory that cannot
-  // be linearly mapped.
-  let high_mem_base = get_high_mem_base();
-  let excl = MemoryRange {
-    tag: MemoryZone::InvalidZone,
-    base: high_mem_base,
-    size: usize::MAX - high_mem_base + 1,
-  };
-
-  // Linearly map each memory range using 2 MiB sections. For each range in the
-  // memory configuration, exclude the high memory area. This adds roughly the
-  // same amount of time overhead as copying the memory configuration and
-  // excluding the high memory area from the set but does not incur the stack
-  // space or time cost of copying the configuration.
-  for range in get_device_tree().get_memory_config().get_ranges() {
-    let (left, _) = range.exclude(&excl).unwrap();
-
-    if let Some(left) = left {
-      mm::direct_map_memory(
-        kconfig.virtual_base,
-        kconfig.kernel_pages_start,
-        left.base,
-        left.size,
-        false,
-        allocator,
-        MappingStrategy::Compact,
-      );
+  // be linearly mapped. Linearly map each remaining range using 2 MiB sections.
+  let limit = get_direct_map_limit();
+
+  for range in get_device_tree().get_memory_config().linear_ranges(limit) {
+    mm::direct_map_memory(
+      kconfig.virtual_base,
+      kconfig.kernel_pages_start,
+      range.base,
+      range.size,
+      false,
+      allocator,
+      MappingStrategy::Compact,
+    );
 
-      debug_print!(
-        "Map: {:#x} - {:#x} => {:#x}\n",
-        left.base,
-        left.base + left.size - 1,
-        kconfig.virtual_base + left.base
-      );
-    }
+    debug_print!(
+      "Map: {:#x} - {:#x} => {:#x}\n",
+      range.base,
+      range.base + range.size - 1,
+      kconfig.virtual_base + range.base
+    );
   }
 }
 
@@ -675,9 +987,24 @@ fn init_isr_stacks(allocator: &mut impl PageAllocator) {
 }
 
 #[cfg(feature = "module_tests")]
-pub fn run_tests() {
-  let mut context = test::TestContext::new();
-  debug_print!(" arch:\n");
-  task::run_tests(&mut context);
-  debug_print!("  {} pass, {} fail\n", context.pass_count, context.fail_count);
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+  super::common::cpu::run_tests(context);
+  super::common::memory::run_tests(context);
+  super::arm_common::cache::run_tests(context);
+  super::arm_common::cpu::run_tests(context);
+  #[cfg(feature = "bcm2835_mini_uart_debug")]
+  super::arm_common::debug::run_tests(context);
+  super::arm_common::dtb_chosen::run_tests(context);
+  super::arm_common::dtb_cpu::run_tests(context);
+  super::arm_common::dtb_device_tree::run_tests(context);
+  super::arm_common::dtb_memory::run_tests(context);
+  super::arm_common::dtb_mmio::run_tests(context);
+  super::arm_common::gic::run_tests(context);
+  super::arm_common::irq::run_tests(context);
+  super::arm_common::percpu::run_tests(context);
+  super::arm_common::time::run_tests(context);
+  features::run_tests(context);
+  mm::run_tests(context);
+  task::run_tests(context);
 }
@@ -0,0 +1,27 @@
+//! ARM Memory Barriers
+
+///-----------------------------------------------------------------------------
+///
+/// Data memory barrier. See A3.8.3.
+///
+/// # Description
+///
+/// Orders memory accesses before the barrier before memory accesses after the
+/// barrier, including accesses to device memory.
+.global sync_dmb
+sync_dmb:
+  dmb
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Data synchronization barrier. See A3.8.3.
+///
+/// # Description
+///
+/// Waits for memory accesses before the barrier to complete.
+.global sync_dsb
+sync_dsb:
+  dsb
+  mov     pc, lr
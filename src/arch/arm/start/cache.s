@@ -0,0 +1,137 @@
+//! ARM Low-Level Cache Maintenance
+
+.equ CTR_DMINLINE_SHIFT,    16
+.equ CLIDR_LOC_SHIFT,       24
+.equ CCSIDR_ASSOC_SHIFT,    3
+.equ CCSIDR_NUMSETS_SHIFT,  13
+.equ CACHE_TYPE_DATA,       2
+
+///-----------------------------------------------------------------------------
+///
+/// Get the size of the smallest data cache line. See B4.1.42.
+///
+/// # Returns
+///
+/// The line size in bytes. CTR.DminLine is the log2 of the number of words in
+/// the line.
+.global cache_get_dcache_line_size
+cache_get_dcache_line_size:
+  mrc     p15, 0, r1, c0, c0, 1
+  ubfx    r1, r1, #CTR_DMINLINE_SHIFT, #4
+  mov     r0, #4
+  lsl     r0, r0, r1
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Clean a data cache line by MVA to the Point of Coherency (DCCMVAC).
+///
+/// # Parameters
+///
+/// * r0 - The virtual address.
+.global cache_clean_line
+cache_clean_line:
+  mcr     p15, 0, r0, c7, c10, 1
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate a data cache line by MVA to the Point of Coherency (DCIMVAC).
+///
+/// # Parameters
+///
+/// * r0 - The virtual address.
+.global cache_invalidate_line
+cache_invalidate_line:
+  mcr     p15, 0, r0, c7, c6, 1
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Clean and invalidate a data cache line by MVA to the Point of Coherency
+/// (DCCIMVAC).
+///
+/// # Parameters
+///
+/// * r0 - The virtual address.
+.global cache_clean_invalidate_line
+cache_clean_invalidate_line:
+  mcr     p15, 0, r0, c7, c14, 1
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Wait for outstanding cache maintenance to complete.
+.global cache_sync
+cache_sync:
+  dsb
+  isb
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Clean every data cache level up to the Level of Coherency by set/way
+/// (DCCSW). See B4.2.1, B4.1.19, and B4.1.20.
+///
+/// # Description
+///
+/// Set/way operations only affect the current core's caches.
+.global cache_clean_all_dcache
+cache_clean_all_dcache:
+  push    {r4-r10, lr}
+  dsb                       // Complete outstanding stores.
+
+  mrc     p15, 1, r0, c0, c0, 1
+  ubfx    r3, r0, #CLIDR_LOC_SHIFT, #3
+  lsl     r3, r3, #1        // r3 = Level of Coherency * 2
+  cmp     r3, #0
+  beq     5f
+  mov     r10, #0           // r10 = cache level * 2, i.e. the CSSELR value
+
+1:
+  add     r2, r10, r10, lsr #1
+  lsr     r1, r0, r2        // Cache type for the level is at CLIDR[3 * level]
+  and     r1, r1, #0x7
+  cmp     r1, #CACHE_TYPE_DATA
+  blt     4f                // Skip levels without a data cache.
+
+  mcr     p15, 2, r10, c0, c0, 0  // Select the level's data cache.
+  isb
+  mrc     p15, 1, r1, c0, c0, 0
+  and     r2, r1, #0x7
+  add     r2, r2, #4        // r2 = log2(line size in bytes)
+  ubfx    r4, r1, #CCSIDR_ASSOC_SHIFT, #10    // r4 = ways - 1
+  clz     r5, r4            // r5 = bit position of the way
+  ubfx    r6, r1, #CCSIDR_NUMSETS_SHIFT, #15  // r6 = sets - 1
+  mov     r9, r4            // r9 = way
+
+2:
+  mov     r7, r6            // r7 = set
+
+3:
+  lsl     r8, r9, r5
+  orr     r8, r8, r7, lsl r2
+  orr     r8, r8, r10       // r8 = way | set | level
+  mcr     p15, 0, r8, c7, c10, 2
+  subs    r7, r7, #1
+  bge     3b
+  subs    r9, r9, #1
+  bge     2b
+
+4:
+  add     r10, r10, #2
+  cmp     r3, r10
+  bgt     1b
+
+  mov     r10, #0
+  mcr     p15, 2, r10, c0, c0, 0  // Restore the level 1 selection.
+  dsb
+  isb
+
+5:
+  pop     {r4-r10, pc}
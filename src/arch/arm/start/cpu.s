@@ -16,6 +16,29 @@ cpu_halt:
   b       1b                // Infinite loop.
 
 
+///-----------------------------------------------------------------------------
+///
+/// Idle the caller.
+///
+/// # Description
+///
+/// Puts the current core into a low-power state until it is woken, then
+/// returns to the caller.
+.global cpu_idle
+cpu_idle:
+  wfe                       // Wait for event.
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Hint to the core that the caller is spinning.
+.global cpu_relax
+cpu_relax:
+  yield
+  mov     pc, lr
+
+
 ///-----------------------------------------------------------------------------
 ///
 /// Get the current core ID.
@@ -25,3 +48,16 @@ cpu_get_id:
   ldr     r1, =CPU_AFFINITY_MASK
   and     r0, r0, r1
   mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Get the memory model feature register. See B4.1.89.
+///
+/// # Returns
+///
+/// The value of ID_MMFR0.
+.global cpu_get_mmfr0
+cpu_get_mmfr0:
+  mrc     p15, 0, r0, c0, c1, 4
+  mov     pc, lr
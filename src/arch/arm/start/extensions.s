@@ -7,6 +7,11 @@
 ///
 /// Check for long page table descriptor support.
 ///
+/// # Description
+///
+/// Later VMSA support values include the features of earlier values, so any
+/// value at or above VMSAv7 with long descriptors supports LPAE. See B4.1.89.
+///
 /// # Returns
 ///
 /// 0 if the CPU supports long page table descriptors, non-zero otherwise.
@@ -14,6 +19,7 @@
 ext_has_long_descriptor_support:
   mrc     p15, 0, r0, c0, c1, 4
   and     r0, r0, #ID_MMFR0_VMSA_MASK
-  sub     r0, r0, #VMSAv7_WITH_LONG_DESCRIPTORS
-  
+  subs    r0, r0, #VMSAv7_WITH_LONG_DESCRIPTORS
+  movge   r0, #0            // r0 is negative if LPAE is not supported.
+
   mov     pc, lr
@@ -0,0 +1,56 @@
+//! ARM Low-Level Panic Guard
+
+.include "abi.h"
+
+///-----------------------------------------------------------------------------
+///
+/// Call a function with a resumable context.
+///
+/// # Parameters
+///
+/// * r0 - The guard context address.
+/// * r1 - The function to call.
+/// * r2 - The argument to pass to the function.
+///
+/// # Description
+///
+/// Saves the callee-saved registers and the frame pointer to the guard context
+/// before calling the function. The context layout is r4-r11, where r11 is the
+/// frame pointer.
+///
+/// # Returns
+///
+/// 0 if the function returned normally, or 1 if `guard_resume` resumed the
+/// context.
+.global guard_call
+guard_call:
+  fn_entry
+
+  stmia   r0, {r4-r11}
+
+  mov     r0, r2
+  blx     r1
+
+  mov     r0, #0
+  fn_exit
+
+
+///-----------------------------------------------------------------------------
+///
+/// Resume a guard context.
+///
+/// # Parameters
+///
+/// * r0 - The guard context address.
+///
+/// # Description
+///
+/// Restores the registers saved by `guard_call` and returns from `guard_call`
+/// as if the function had returned. Restoring the frame pointer discards all
+/// frames above `guard_call`'s frame.
+.global guard_resume
+guard_resume:
+  ldmia   r0, {r4-r11}
+
+  mov     r0, #1
+  fn_exit
@@ -503,6 +503,137 @@ mmu_update_table_entry_local:
   mov     pc, lr
 
 
+///-----------------------------------------------------------------------------
+///
+/// Invalidate the current core's TLB entries for a range of pages.
+///
+/// # Parameters
+///
+/// * r0 - The page-aligned base virtual address.
+/// * r1 - The number of pages.
+///
+/// # Description
+///
+/// The unified TLB and Branch Predictors will be invalidated for each page in
+/// the range. The caller is responsible for any translation table updates
+/// prior to calling. A barrier ensures the table updates are visible before
+/// invalidating.
+.global mmu_invalidate_tlb_range
+mmu_invalidate_tlb_range:
+  dsb
+
+  cmp     r1, #0
+  beq     2f
+
+// Invalidate unified TLB and Branch Predictor by virtual address (See TLBIMVA
+// in B3.18.7 and BPIMVA in B3.18.6).
+1:
+  mcr     p15, 0, r0, c8, c7, 1
+  mcr     p15, 0, r0, c7, c5, 7
+  add     r0, r0, #(1 << PAGE_SHIFT)
+  subs    r1, r1, #1
+  bne     1b
+
+// Ensure completion.
+2:
+  dsb
+  isb
+
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate all of the current core's TLB entries.
+///
+/// # Description
+///
+/// The entire unified TLB and Branch Predictor array will be invalidated. A
+/// barrier ensures any translation table updates are visible before
+/// invalidating.
+.global mmu_invalidate_tlb_all
+mmu_invalidate_tlb_all:
+  dsb
+
+// Invalidate entire unified TLB and Branch Predictor array (See TLBIALL in
+// B3.18.7 and BPIALL in B3.18.6), and ensure completion.
+  mov     r0, #0
+  mcr     p15, 0, r0, c8, c7, 0
+  mcr     p15, 0, r0, c7, c5, 6
+  dsb
+  isb
+
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate the TLB entries for a range of pages on all cores in the Inner
+/// Shareable domain.
+///
+/// # Parameters
+///
+/// * r0 - The page-aligned base virtual address.
+/// * r1 - The number of pages.
+///
+/// # Description
+///
+/// The unified TLBs will be invalidated for each page in the range, and the
+/// Branch Predictor arrays will be invalidated, on all cores in the Inner
+/// Shareable domain. The caller is responsible for any translation table
+/// updates prior to calling. A barrier ensures the table updates are visible to
+/// all cores before invalidating.
+.global mmu_invalidate_tlb_range_broadcast
+mmu_invalidate_tlb_range_broadcast:
+  dsb     ish
+
+  cmp     r1, #0
+  beq     2f
+
+// Invalidate unified TLB by virtual address, Inner Shareable (See TLBIMVAIS in
+// B3.18.7).
+1:
+  mcr     p15, 0, r0, c8, c3, 1
+  add     r0, r0, #(1 << PAGE_SHIFT)
+  subs    r1, r1, #1
+  bne     1b
+
+// There is no Inner Shareable variant of BPIMVA. Invalidate the entire Branch
+// Predictor array, Inner Shareable (See BPIALLIS in B3.18.6).
+  mcr     p15, 0, r0, c7, c1, 6
+
+// Ensure completion.
+2:
+  dsb     ish
+  isb
+
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Invalidate all TLB entries on all cores in the Inner Shareable domain.
+///
+/// # Description
+///
+/// The entire unified TLB and Branch Predictor array will be invalidated on all
+/// cores in the Inner Shareable domain. A barrier ensures any translation table
+/// updates are visible to all cores before invalidating.
+.global mmu_invalidate_tlb_all_broadcast
+mmu_invalidate_tlb_all_broadcast:
+  dsb     ish
+
+// Invalidate entire unified TLB and Branch Predictor array, Inner Shareable
+// (See TLBIALLIS in B3.18.7 and BPIALLIS in B3.18.6), and ensure completion.
+  mov     r0, #0
+  mcr     p15, 0, r0, c8, c3, 0
+  mcr     p15, 0, r0, c7, c1, 6
+  dsb     ish
+  isb
+
+  mov     pc, lr
+
+
 ///-----------------------------------------------------------------------------
 ///
 /// Section-align a memory block.
@@ -0,0 +1,23 @@
+//! ARM Low-Level Per-Core Data Management
+
+///-----------------------------------------------------------------------------
+///
+/// Get the current core's data area address from the TPIDRPRW register. See
+/// B3.17.
+.global percpu_get_area_addr
+percpu_get_area_addr:
+  mrc     p15, 0, r0, c13, c0, 4
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Set the current core's data area address. See B3.17.
+///
+/// # Parameters
+///
+/// r0 - The data area address.
+.global percpu_set_area_addr
+percpu_set_area_addr:
+  mcr     p15, 0, r0, c13, c0, 4
+  mov     pc, lr
@@ -275,6 +275,11 @@ secondary_hyp_entry:
 ///-----------------------------------------------------------------------------
 ///
 /// Boot a secondary core.
+///
+/// # Description
+///
+///   TODO: Set up the core's stack from the stack list, enable the MMU, and
+///         jump to `pk_secondary_init`.
 secondary_core_boot:
   b       cpu_halt
 
@@ -0,0 +1,68 @@
+//! ARM Low-Level Generic Timer Utilities
+
+// Timer control register enable bit. The interrupt mask bit is left clear.
+.equ TIMER_CTL_ENABLE, 1
+
+///-----------------------------------------------------------------------------
+///
+/// Get the current virtual count from CNTVCT.
+///
+/// # Description
+///
+/// The instruction barrier ensures the count is not read early.
+///
+/// # Returns
+///
+/// r0:r1 - The 64-bit count.
+.global time_get_ticks
+time_get_ticks:
+  isb
+  mrrc    p15, 1, r0, r1, c14
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Get the counter frequency from CNTFRQ.
+///
+/// # Returns
+///
+/// r0 - The frequency in Hz.
+.global time_get_frequency
+time_get_frequency:
+  mrc     p15, 0, r0, c14, c0, 0
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Start the physical timer.
+///
+/// # Parameters
+///
+/// r0 - The number of ticks until the timer fires.
+///
+/// # Description
+///
+/// Writes the interval to CNTP_TVAL, then enables the timer with the interrupt
+/// unmasked in CNTP_CTL. Writing CNTP_TVAL clears a pending timer condition.
+.global time_set_timer
+time_set_timer:
+  mcr     p15, 0, r0, c14, c2, 0
+  mov     r0, #TIMER_CTL_ENABLE
+  mcr     p15, 0, r0, c14, c2, 1
+  isb
+  mov     pc, lr
+
+
+///-----------------------------------------------------------------------------
+///
+/// Get the physical timer control register, CNTP_CTL.
+///
+/// # Returns
+///
+/// r0 - The control register value.
+.global time_get_timer_control
+time_get_timer_control:
+  mrc     p15, 0, r0, c14, c2, 1
+  mov     pc, lr
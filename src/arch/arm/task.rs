@@ -11,15 +11,18 @@ use crate::support::bits;
 use crate::{execute_test, test};
 use core::{ptr, slice};
 
-unsafe extern "C" {
-  fn task_get_current_task_addr() -> usize;
-  fn task_set_current_task_addr(task: usize);
-}
-
 const CPU_MASK_WORDS: usize = (cpu::MAX_CORES + bits::WORD_BITS - 1) >> bits::WORD_BIT_SHIFT;
 
+const _: () = assert!(
+  CPU_MASK_WORDS * bits::WORD_BITS >= cpu::MAX_CORES,
+  "The affinity mask cannot represent every core."
+);
+
 pub type AffinityMask = bits::Bitmap<CPU_MASK_WORDS>;
 
+/// Required kernel thread stack alignment.
+const KERNEL_STACK_ALIGN: usize = 8;
+
 /// Re-initialization guard.
 static mut INITIALIZED: bool = false;
 
@@ -27,10 +30,10 @@ static mut INITIALIZED: bool = false;
 /// ensure the local mapping table is aligned to a page boundary and rearrange
 /// the remaining fields of the task structure accordingly.
 #[repr(C, align(4096))]
-struct AlignedTable([usize; 1024]);
+struct AlignedTable([usize; mm::LOCAL_TABLE_WORDS]);
 
 /// The bootstrap task's local mapping table.
-static mut BOOTSTRAP_LOCAL_TABLE: AlignedTable = AlignedTable([0; 1024]);
+static mut BOOTSTRAP_LOCAL_TABLE: AlignedTable = AlignedTable([0; mm::LOCAL_TABLE_WORDS]);
 
 /// ARM task context.
 ///
@@ -76,14 +79,7 @@ impl TaskContext {
   /// None if the given virtual address is not in the upper 1 GiB of the kernel's
   /// address space.
   fn get_page_virtual_address_for_virtual_address(virt_addr: usize) -> Option<usize> {
-    // Only the upper 1 GiB of the kernel address space is served by the recursive
-    // map area.
-    if virt_addr < 0xc000_0000 {
-      return None;
-    }
-
-    let index = (virt_addr - 0xc000_0000) >> super::get_section_shift();
-    Some(super::RECURSIVE_MAP_AREA + (index << super::get_page_shift()))
+    mm::get_recursive_table_address(virt_addr)
   }
 
   /// Get the base virtual address of the thread local area for the current core.
@@ -94,6 +90,11 @@ impl TaskContext {
   ///
   /// # Description
   ///
+  /// Each core has one section in the thread local area. The area is in the
+  /// high memory area at the top of the address space, so the base is the same
+  /// with a 3/1 or 2/2 split and the core's local mapping table is always
+  /// served by the recursive map.
+  ///
   ///   NOTE: The interface guarantees read-only access outside of the module and
   ///         one-time initialization is assumed.
   ///
@@ -104,6 +105,25 @@ impl TaskContext {
     unsafe { super::THREAD_LOCAL_AREA_VIRTUAL_BASE + offset }
   }
 
+  /// Get the local mapping table for a core's thread local area.
+  ///
+  /// # Parameters
+  ///
+  /// * `local_base` - The base virtual address of the core's thread local area.
+  ///
+  /// # Description
+  ///
+  /// The table is the Level 3 table that maps the thread local area and fills
+  /// exactly one page.
+  ///
+  ///   NOTE: Private to the ARM architecture.
+  fn get_local_table(local_base: usize) -> &'static mut [usize] {
+    debug_assert_eq!(mm::LOCAL_TABLE_WORDS << bits::WORD_SHIFT, super::get_page_size());
+
+    let table_vaddr = Self::get_page_virtual_address_for_virtual_address(local_base);
+    unsafe { slice::from_raw_parts_mut(table_vaddr.unwrap() as *mut usize, mm::LOCAL_TABLE_WORDS) }
+  }
+
   /// Construct an empty task context.
   pub const fn default() -> Self {
     TaskContext {
@@ -128,11 +148,56 @@ impl TaskContext {
     Self::default()
   }
 
+  /// Construct a task context for a new kernel thread.
+  ///
+  /// # Parameters
+  ///
+  /// * `entry` - The thread entry point.
+  /// * `stack_top` - The virtual address of the top of the thread's stack.
+  /// * `table_addr` - The physical address of the thread's local mapping table.
+  ///
+  /// # Description
+  ///
+  /// The link register is set to the entry point so that the first switch to
+  /// the context returns into the thread. The frame pointer is zero to
+  /// terminate the thread's frame chain.
+  ///
+  ///   NOTE: The stack must be 8-byte aligned per the AAPCS.
+  pub fn new_kernel_thread(entry: fn() -> !, stack_top: usize, table_addr: usize) -> Self {
+    assert_eq!(stack_top & (KERNEL_STACK_ALIGN - 1), 0);
+
+    let mut context = Self::default();
+    context.pc = entry as usize;
+    context.sp = stack_top;
+    context.table_addr = table_addr;
+    context
+  }
+
   /// Get the context's local mapping table physical address.
   pub fn get_table_addr(&self) -> usize {
     self.table_addr
   }
 
+  /// Get the number of pages mapped into the task's local mappings.
+  ///
+  /// # Description
+  ///
+  /// Pages in linear memory count toward the number of local mappings. See
+  /// `map_page()`.
+  pub fn mapped_page_count(&self) -> usize {
+    self.map_count
+  }
+
+  /// Get the number of pages that can still be mapped into the task's local
+  /// mappings.
+  ///
+  /// # Description
+  ///
+  /// See `mapped_page_count()`.
+  pub fn free_mapping_count(&self) -> usize {
+    mm::MAX_LOCAL_MAPPINGS - self.map_count
+  }
+
   /// Get the current pin mask.
   pub fn get_pin_mask(&self) -> Option<&AffinityMask> {
     self.pin_mask.as_ref()
@@ -144,29 +209,29 @@ impl TaskContext {
   /// # Parameters
   ///
   /// * `page_addr` - The physical address of the page to map.
+  /// * `device` - Whether the page maps to device memory.
   ///
   /// # Description
   ///
-  /// See `Task::map_page()`.
+  /// See `Task::map_page()` and `Task::map_device_page()`.
   ///
-  /// If the page is in linear memory, the function adds a null entry to the
-  /// local mapping table and returns the virtual address of the linearly mapped
-  /// page. This means that pages in linear memory still count toward the number
-  /// of local mappings a task is maintaining.
+  /// If the page is normal memory in linear memory, the function adds a null
+  /// entry to the local mapping table and returns the virtual address of the
+  /// linearly mapped page. This means that pages in linear memory still count
+  /// toward the number of local mappings a task is maintaining.
   ///
   ///   NOTE: This is done to simplify the unmapping logic which does not take
   ///         an address parameter.
   ///
-  /// Otherwise, if the page is in high memory, the function maps the page to
-  /// the next available virtual address in the task's local mappings. The
-  /// mappings are thread-local, so the function is thread safe.
+  /// Otherwise, if the page is in high memory or is device memory, the function
+  /// maps the page to the next available virtual address in the task's local
+  /// mappings. Device pages are always mapped locally because the linear map
+  /// only covers normal memory. The mappings are thread-local, so the function
+  /// is thread safe.
   ///
-  /// The function will panic if no more pages can be mapped into the thread's
-  /// local mappings.
-  ///
-  /// When at least one local mapping exists to a high memory page, the task
-  /// will be pinned to the current core. If the task is swapped out while local
-  /// mappings to high memory exist, it must be swapped back to the same core
+  /// When at least one local mapping exists to a high memory or device page,
+  /// the task will be pinned to the current core. If the task is swapped out
+  /// while such local mappings exist, it must be swapped back to the same core
   /// for pointers to remain valid.
   ///
   /// This process is slow, but as pointed out in the announcement of local
@@ -176,14 +241,19 @@ impl TaskContext {
   ///
   /// # Returns
   ///
-  /// The virtual address of the mapped page.
-  pub fn map_page(&mut self, page_addr: usize) -> usize {
-    // If mapping a page in linear memory, return the linearly mapped address
-    // and increment the map count. We do not need to pin the process to the
-    // current core.
-    if page_addr < super::get_high_mem_base() {
+  /// The virtual address of the mapped page, or None if the local mapping
+  /// table is full.
+  pub fn map_page(&mut self, page_addr: usize, device: bool) -> Option<usize> {
+    if self.free_mapping_count() == 0 {
+      return None;
+    }
+
+    // If mapping a normal page in linear memory, return the linearly mapped
+    // address and increment the map count. We do not need to pin the process to
+    // the current core.
+    if !device && page_addr < super::get_high_mem_base() {
       self.map_count += 1;
-      return super::get_kernel_virtual_base() + page_addr;
+      return Some(super::get_kernel_virtual_base() + page_addr);
     }
 
     // TODO: Interrupts need to be disabled before proceeding to ensure a
@@ -202,12 +272,11 @@ impl TaskContext {
     // TODO: Interrupts may be re-enabled here; the rest is thread-safe.
 
     let local_base = Self::get_thread_local_virtual_base(core_idx);
-    let table_vaddr = Self::get_page_virtual_address_for_virtual_address(local_base);
-    let table = unsafe { slice::from_raw_parts_mut(table_vaddr.unwrap() as *mut usize, 1024) };
-    let page_vaddr = mm::map_page_local(table, local_base, page_addr, self.map_count, false);
+    let table = Self::get_local_table(local_base);
+    let page_vaddr = mm::map_page_local(table, local_base, page_addr, self.map_count, device);
 
     self.map_count += 1;
-    page_vaddr
+    Some(page_vaddr)
   }
 
   /// Unmaps the last mapped page in the current task's local mapping table.
@@ -222,8 +291,7 @@ impl TaskContext {
     }
 
     let local_base = Self::get_thread_local_virtual_base(super::get_current_core_index());
-    let table_vaddr = Self::get_page_virtual_address_for_virtual_address(local_base);
-    let table = unsafe { slice::from_raw_parts_mut(table_vaddr.unwrap() as *mut usize, 1024) };
+    let table = Self::get_local_table(local_base);
 
     mm::unmap_page_local(table, local_base, self.map_count);
 
@@ -269,20 +337,6 @@ pub fn init_bootstrap_context() -> TaskContext {
   context
 }
 
-/// Get the current task address from the task register.
-pub fn get_current_task_addr() -> usize {
-  unsafe { task_get_current_task_addr() }
-}
-
-/// Set the task register to a new task object address.
-///
-/// # Parameters
-///
-/// * `addr` - The new task address.
-pub fn set_current_task_addr(addr: usize) {
-  unsafe { task_set_current_task_addr(addr) }
-}
-
 #[cfg(feature = "module_tests")]
 pub fn run_tests(context: &mut test::TestContext) {
   tests::run_tests(context);
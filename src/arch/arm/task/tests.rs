@@ -1,8 +1,10 @@
 //! ARM Task Tests
 
+use crate::arch::cpu::{self, MAX_CORES};
+use crate::arch::mm::MAX_LOCAL_MAPPINGS;
 use crate::debug_print;
-use crate::task::{Task, TaskContext};
-use crate::{check_eq, execute_test, test};
+use crate::task::{AffinityMask, Task, TaskContext};
+use crate::{check_eq, check_none, check_not_none, execute_test, test};
 use core::slice;
 
 /// Run task tests.
@@ -12,6 +14,11 @@ use core::slice;
 /// * `context` - The test context.
 pub fn run_tests(context: &mut test::TestContext) {
   execute_test!(context, test_local_mappings);
+  execute_test!(context, test_new_kernel_thread);
+  execute_test!(context, test_mapped_page_count);
+  execute_test!(context, test_device_mappings);
+  execute_test!(context, test_mapping_exhaustion);
+  execute_test!(context, test_pinned_task);
 }
 
 /// Test local mappings.
@@ -30,11 +37,10 @@ fn test_local_mappings(context: &mut test::TestContext) {
   let page_size = crate::arch::get_page_size();
   let page_mask = crate::arch::get_page_mask();
   let local_vbase = TaskContext::get_thread_local_virtual_base(0);
-  let table_vaddr = TaskContext::get_page_virtual_address_for_virtual_address(local_vbase);
-  let table = unsafe { slice::from_raw_parts_mut(table_vaddr.unwrap() as *mut usize, 1024) };
+  let table = TaskContext::get_local_table(local_vbase);
 
   // Map an address beyond 896 MiB; assuming we are running on the primary core.
-  let lcl_address = task.map_page(0x3900_0000);
+  let lcl_address = task.map_page(0x3900_0000).unwrap();
   check_eq!(context, lcl_address, local_vbase);
   check_eq!(context, task.get_context().map_count, 1);
   check_eq!(context, table[0] & !page_mask, 0x3900_0000);
@@ -46,7 +52,7 @@ fn test_local_mappings(context: &mut test::TestContext) {
   check_eq!(context, lcl_page[0], 42);
 
   // Remap the same page; verify the address increments by a page.
-  let lcl_address2 = task.map_page(0x3900_0000);
+  let lcl_address2 = task.map_page(0x3900_0000).unwrap();
   check_eq!(context, lcl_address2, lcl_address + page_size);
   check_eq!(context, task.get_context().map_count, 2);
   check_eq!(context, table[2] & !page_mask, 0x3900_0000);
@@ -59,7 +65,7 @@ fn test_local_mappings(context: &mut test::TestContext) {
   check_eq!(context, lcl_page2[0], lcl_page[0]);
 
   // Map an address below 896 MiB. This address should be linearly mapped.
-  let lcl_address3 = task.map_page(0x3700_0000);
+  let lcl_address3 = task.map_page(0x3700_0000).unwrap();
   check_eq!(context, lcl_address3, 0x3700_0000 + virt_base);
   check_eq!(context, task.get_context().map_count, 3);
   check_eq!(context, table[4], 0);
@@ -77,3 +83,198 @@ fn test_local_mappings(context: &mut test::TestContext) {
   check_eq!(context, task.get_context().map_count, 0);
   check_eq!(context, table[0], 0);
 }
+
+/// Kernel thread entry point for context construction tests. Never executed.
+fn test_thread_entry() -> ! {
+  loop {
+    cpu::relax();
+  }
+}
+
+/// Test constructing a kernel thread context.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// For ARM, this verifies that the link register, stack pointer, and local
+/// mapping table address are set and that the remaining registers are zero.
+fn test_new_kernel_thread(context: &mut test::TestContext) {
+  const TEST_STACK_TOP: usize = 0x10_0000;
+  const TEST_TABLE_ADDR: usize = 0x20_0000;
+
+  let ctx = TaskContext::new_kernel_thread(test_thread_entry, TEST_STACK_TOP, TEST_TABLE_ADDR);
+  let context_regs = [ctx.r4, ctx.r5, ctx.r6, ctx.r7, ctx.r8, ctx.r10, ctx.fp];
+
+  check_eq!(context, ctx.pc, test_thread_entry as *const () as usize);
+  check_eq!(context, ctx.sp, TEST_STACK_TOP);
+  check_eq!(context, ctx.get_table_addr(), TEST_TABLE_ADDR);
+  check_eq!(context, ctx.map_count, 0);
+  check_none!(context, ctx.get_pin_mask());
+
+  for reg in context_regs {
+    check_eq!(context, reg, 0);
+  }
+}
+
+/// Test counting local mappings through the public interface.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// For ARM, pages in both high memory and linear memory count toward the local
+/// mappings. The task is pinned while any mappings remain.
+fn test_mapped_page_count(context: &mut test::TestContext) {
+  let task = Task::get_current_task_mut();
+  check_eq!(context, task.mapped_page_count(), 0);
+
+  // Map an address beyond 896 MiB; assuming we are running on the primary core.
+  _ = task.map_page(0x3900_0000);
+  check_eq!(context, task.mapped_page_count(), 1);
+  check_eq!(context, task.get_context().mapped_page_count(), 1);
+  check_not_none!(context, task.get_context().get_pin_mask());
+
+  // Map an address below 896 MiB.
+  _ = task.map_page(0x3700_0000);
+  check_eq!(context, task.mapped_page_count(), 2);
+
+  task.unmap_page();
+  check_eq!(context, task.mapped_page_count(), 1);
+  check_not_none!(context, task.get_context().get_pin_mask());
+
+  task.unmap_page();
+  check_eq!(context, task.mapped_page_count(), 0);
+  check_none!(context, task.get_context().get_pin_mask());
+
+  // Unmapping without any mappings does nothing.
+  task.unmap_page();
+  check_eq!(context, task.mapped_page_count(), 0);
+}
+
+/// Test mapping device pages.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Device pages use the device MAIR index and are always mapped into the local
+/// mapping table, even when they are below 896 MiB. The pages are not accessed.
+fn test_device_mappings(context: &mut test::TestContext) {
+  // See the MAIR indices in `mm`. The index is in bits [4:2] of the
+  // descriptor.
+  const MAIR_IDX_SHIFT: usize = 2;
+  const MAIR_IDX_MASK: usize = 0x7;
+  const NORMAL_MAIR_IDX: usize = 0x0;
+  const DEVICE_MAIR_IDX: usize = 0x1;
+
+  let task = Task::get_current_task_mut();
+  let page_size = crate::arch::get_page_size();
+  let page_mask = crate::arch::get_page_mask();
+  let local_vbase = TaskContext::get_thread_local_virtual_base(0);
+  let table = TaskContext::get_local_table(local_vbase);
+
+  // Map a device page beyond 896 MiB; assuming we are running on the primary
+  // core.
+  let lcl_address = task.map_device_page(0x3900_0000).unwrap();
+  check_eq!(context, lcl_address, local_vbase);
+  check_eq!(context, table[0] & !page_mask, 0x3900_0000);
+  check_eq!(context, (table[0] >> MAIR_IDX_SHIFT) & MAIR_IDX_MASK, DEVICE_MAIR_IDX);
+  check_eq!(context, task.is_pinned(), true);
+
+  // Map the same page as normal memory.
+  let lcl_address2 = task.map_page(0x3900_0000).unwrap();
+  check_eq!(context, lcl_address2, local_vbase + page_size);
+  check_eq!(context, (table[2] >> MAIR_IDX_SHIFT) & MAIR_IDX_MASK, NORMAL_MAIR_IDX);
+
+  // Map a device page below 896 MiB. This page must not be linearly mapped.
+  let lcl_address3 = task.map_device_page(0x0900_0000).unwrap();
+  check_eq!(context, lcl_address3, local_vbase + (page_size * 2));
+  check_eq!(context, table[4] & !page_mask, 0x0900_0000);
+  check_eq!(context, (table[4] >> MAIR_IDX_SHIFT) & MAIR_IDX_MASK, DEVICE_MAIR_IDX);
+  check_eq!(context, task.mapped_page_count(), 3);
+
+  task.unmap_page();
+  task.unmap_page();
+  task.unmap_page();
+  check_eq!(context, table[0], 0);
+  check_eq!(context, table[4], 0);
+  check_eq!(context, task.mapped_page_count(), 0);
+  check_eq!(context, task.is_pinned(), false);
+}
+
+/// Test filling the local mapping table.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Linear pages still take a slot in the table. Once the table is full, both
+/// normal and device mappings fail without changing the mapping count.
+fn test_mapping_exhaustion(context: &mut test::TestContext) {
+  let task = Task::get_current_task_mut();
+  check_eq!(context, task.free_mapping_count(), MAX_LOCAL_MAPPINGS);
+
+  for _ in 0..MAX_LOCAL_MAPPINGS {
+    check_not_none!(context, task.map_page(0x3700_0000));
+  }
+
+  check_eq!(context, task.free_mapping_count(), 0);
+  check_none!(context, task.map_page(0x3700_0000));
+  check_none!(context, task.map_device_page(0x3900_0000));
+  check_eq!(context, task.mapped_page_count(), MAX_LOCAL_MAPPINGS);
+
+  for _ in 0..MAX_LOCAL_MAPPINGS {
+    task.unmap_page();
+  }
+
+  check_eq!(context, task.mapped_page_count(), 0);
+  check_eq!(context, task.free_mapping_count(), MAX_LOCAL_MAPPINGS);
+}
+
+/// Test that a pinned task may only run on the core it is pinned to.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The pin takes precedence over an affinity mask that permits every core.
+fn test_pinned_task(context: &mut test::TestContext) {
+  let task = Task::get_current_task_mut();
+  let core_idx = crate::arch::get_current_core_index();
+  let mut affinity = AffinityMask::new(MAX_CORES);
+  affinity.set_all_bits();
+  task.set_affinity(Some(&affinity));
+
+  // Mapping a page in linear memory does not pin the task.
+  _ = task.map_page(0x3700_0000);
+  check_eq!(context, task.is_pinned(), false);
+  task.unmap_page();
+
+  // Map an address beyond 896 MiB to pin the task to the current core.
+  _ = task.map_page(0x3900_0000);
+  check_eq!(context, task.is_pinned(), true);
+
+  for other_idx in 0..MAX_CORES {
+    check_eq!(context, task.can_run_on(other_idx), other_idx == core_idx);
+  }
+
+  task.unmap_page();
+  check_eq!(context, task.is_pinned(), false);
+
+  for other_idx in 0..MAX_CORES {
+    check_eq!(context, task.can_run_on(other_idx), true);
+  }
+
+  task.set_affinity(None);
+}
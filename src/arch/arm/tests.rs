@@ -0,0 +1,258 @@
+//! ARM Architecture Tests
+
+use crate::arch::cpu;
+use crate::arch::memory::{self, VirtualRegion};
+use crate::debug_print;
+use crate::support::range::RangeOrdering;
+use crate::{check_eq, check_none, check_optional, execute_test, mark_fail, test};
+
+/// Run architecture tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_kernel_range);
+  execute_test!(context, test_thread_local_unmapped);
+  execute_test!(context, test_high_mem_layout);
+  execute_test!(context, test_high_mem_layout_splits);
+  execute_test!(context, test_vm_layout);
+  execute_test!(context, test_vm_layout_conflict);
+}
+
+/// Test the section-aligned kernel range.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_kernel_range(context: &mut test::TestContext) {
+  let raw = super::get_kernel_range();
+  let aligned = super::get_kernel_range_section_aligned();
+  let section_mask = super::get_section_size() - 1;
+
+  check_eq!(context, aligned.base & section_mask, 0);
+  check_eq!(context, aligned.size & section_mask, 0);
+
+  let contains =
+    matches!(aligned.cmp(&raw), Some(RangeOrdering::Superset) | Some(RangeOrdering::Equal));
+  check_eq!(context, contains, true);
+}
+
+/// Test that the direct map does not cover the thread-local area.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Each core's thread-local section must either be unmapped or be served by a
+/// thread-local table, never by a section installed by the direct map. Assumes
+/// no local mappings exist when the architecture tests run.
+fn test_thread_local_unmapped(context: &mut test::TestContext) {
+  let section_size = super::get_section_size();
+  let local_base = super::get_thread_local_area_virtual_base();
+  let local_size = super::get_thread_local_area_size();
+
+  let limit = super::get_direct_map_limit() + super::get_kernel_virtual_base();
+  let below = limit <= local_base;
+  check_eq!(context, below, true);
+
+  for section in (local_base..local_base + local_size).step_by(section_size) {
+    check_none!(context, super::mm::read_descriptor(section));
+    check_none!(context, super::mm::read_descriptor(section + section_size - 1));
+  }
+}
+
+/// Test laying out the high memory area.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The layout must fit for the maximum core count with the configured stack
+/// size, and must match the layout computed at boot for the actual core count.
+fn test_high_mem_layout(context: &mut test::TestContext) {
+  let kconfig = super::get_kernel_config();
+  let section_mask = super::SECTION_SIZE - 1;
+
+  let Some(layout) =
+    super::get_high_mem_layout(kconfig.vm_split, cpu::MAX_CORES, kconfig.kernel_stack_pages)
+  else {
+    mark_fail!(context, "The layout does not fit for the maximum core count.");
+    return;
+  };
+
+  check_eq!(context, layout.thread_local_area_size, super::SECTION_SIZE * cpu::MAX_CORES);
+  check_eq!(context, layout.thread_local_area_base & section_mask, 0);
+
+  let local_end = layout.thread_local_area_base + layout.thread_local_area_size;
+  let isr_end = layout.isr_stack_area_base + layout.isr_stack_area_size;
+  let ordered = super::DRIVER_VIRTUAL_BASE <= layout.thread_local_area_base
+    && local_end <= layout.isr_stack_area_base
+    && isr_end == layout.page_database_base;
+  check_eq!(context, ordered, true);
+
+  let core_count = super::get_device_tree().get_core_config().get_core_count();
+
+  let Some(layout) =
+    super::get_high_mem_layout(kconfig.vm_split, core_count, kconfig.kernel_stack_pages)
+  else {
+    mark_fail!(context, "The layout does not fit for the actual core count.");
+    return;
+  };
+
+  check_eq!(context, layout.page_database_base, super::get_page_database_virtual_base());
+  check_eq!(context, layout.page_database_size, super::get_page_database_size());
+  check_eq!(context, layout.isr_stack_area_base, super::get_isr_stack_area_virtual_base());
+  check_eq!(context, layout.isr_stack_area_size, super::get_isr_stack_size());
+  check_eq!(context, layout.thread_local_area_base, super::get_thread_local_area_virtual_base());
+  check_eq!(context, layout.thread_local_area_size, super::get_thread_local_area_size());
+
+  // ISR stacks as large as the high memory area can never fit.
+  let stack_pages = super::HIGH_MEM_SIZE >> super::PAGE_SHIFT;
+  let fits = super::get_high_mem_layout(kconfig.vm_split, 1, stack_pages).is_some();
+  check_eq!(context, fits, false);
+}
+
+/// Test laying out the high memory area for both splits.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// For each split, the layout must fit for the maximum core count with the
+/// configured stack size. The page database must end at the recursive map area
+/// and be sized for the split, and the thread-local area must be served by the
+/// recursive map. A core count whose thread-local area alone fills the high
+/// memory area can never fit.
+fn test_high_mem_layout_splits(context: &mut test::TestContext) {
+  const SPLITS: [(usize, usize); 2] = [(3, 24 * 1024 * 1024), (2, 48 * 1024 * 1024)];
+
+  let kconfig = super::get_kernel_config();
+  let coverage_base = super::mm::get_recursive_map_area(0);
+
+  for (split, page_database_size) in SPLITS {
+    let Some(layout) =
+      super::get_high_mem_layout(split, cpu::MAX_CORES, kconfig.kernel_stack_pages)
+    else {
+      mark_fail!(context, "The layout does not fit for the maximum core count.");
+      continue;
+    };
+
+    check_eq!(context, layout.page_database_size, page_database_size);
+    check_eq!(
+      context,
+      layout.page_database_base + layout.page_database_size,
+      super::RECURSIVE_MAP_AREA
+    );
+
+    let local_end = layout.thread_local_area_base + layout.thread_local_area_size;
+    let isr_end = layout.isr_stack_area_base + layout.isr_stack_area_size;
+    let ordered = coverage_base <= super::DRIVER_VIRTUAL_BASE
+      && super::DRIVER_VIRTUAL_BASE <= layout.thread_local_area_base
+      && local_end <= layout.isr_stack_area_base
+      && isr_end == layout.page_database_base;
+    check_eq!(context, ordered, true);
+
+    let core_count = super::HIGH_MEM_SIZE / super::SECTION_SIZE;
+    let fits = super::get_high_mem_layout(split, core_count, kconfig.kernel_stack_pages).is_some();
+    check_eq!(context, fits, false);
+  }
+}
+
+/// Test that the reported virtual address space regions do not overlap.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_vm_layout(context: &mut test::TestContext) {
+  const MAX_REGIONS: usize = 8;
+  const EXPECTED_REGIONS: usize = 7;
+
+  let mut regions = [(0, 0); MAX_REGIONS];
+  let mut count = 0;
+
+  super::dump_vm_layout(|_, base, size| {
+    if count < MAX_REGIONS {
+      regions[count] = (base, size);
+    }
+
+    count += 1;
+  });
+
+  check_eq!(context, count, EXPECTED_REGIONS);
+
+  let regions = &regions[..EXPECTED_REGIONS.min(count)];
+
+  for (base, size) in regions {
+    let valid = *size > 0 && base.checked_add(size - 1).is_some();
+    check_eq!(context, valid, true);
+  }
+
+  // The regions are reported in ascending order, so each region must end
+  // before the next begins.
+  for pair in regions.windows(2) {
+    let ((base, size), (next_base, _)) = (pair[0], pair[1]);
+    let disjoint = base.saturating_add(size.saturating_sub(1)) < next_base;
+    check_eq!(context, disjoint, true);
+  }
+}
+
+/// Test detecting conflicts in a virtual address space layout.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A kernel panic cannot be caught, so the test checks the conflict search that
+/// triggers the debug assertion in `init()` rather than the assertion itself.
+fn test_vm_layout_conflict(context: &mut test::TestContext) {
+  const PAGE_SIZE: usize = 4096;
+
+  let virtual_base = super::get_kernel_virtual_base();
+  let region = |tag, offset, size| VirtualRegion {
+    tag,
+    base: virtual_base + offset,
+    size,
+  };
+
+  let valid = [
+    region("Vectors", 0, PAGE_SIZE),
+    region("Page Directory", PAGE_SIZE, PAGE_SIZE),
+    region("Thread Local", 4 * PAGE_SIZE, 2 * PAGE_SIZE),
+  ];
+  check_none!(context, memory::find_vm_layout_conflict(virtual_base, &valid));
+
+  // The thread-local area overlaps the end of the page directory.
+  let overlapping = [
+    region("Vectors", 0, PAGE_SIZE),
+    region("Page Directory", PAGE_SIZE, 2 * PAGE_SIZE),
+    region("Thread Local", 2 * PAGE_SIZE, 2 * PAGE_SIZE),
+  ];
+  check_optional!(
+    context,
+    memory::find_vm_layout_conflict(virtual_base, &overlapping),
+    "Page Directory"
+  );
+
+  // The vectors start below the kernel segment.
+  let outside = [VirtualRegion {
+    tag: "Vectors",
+    base: virtual_base - PAGE_SIZE,
+    size: 2 * PAGE_SIZE,
+  }];
+  check_optional!(context, memory::find_vm_layout_conflict(virtual_base, &outside), "Vectors");
+
+  // An empty region is never valid.
+  let empty = [region("Recursive Map", 0, 0)];
+  check_optional!(context, memory::find_vm_layout_conflict(virtual_base, &empty), "Recursive Map");
+
+  check_none!(context, super::find_vm_layout_conflict());
+}
@@ -0,0 +1,123 @@
+//! ARM Cache Maintenance
+//!
+//! Range operations work on every data cache line that overlaps the range and
+//! complete before returning. Data written for an observer that does not snoop
+//! the caches, e.g. a core that has not enabled its caches yet or a table walk
+//! that is not coherent, must be cleaned to the Point of Coherency first.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::support::bits;
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+unsafe extern "C" {
+  fn cache_get_dcache_line_size() -> usize;
+  fn cache_clean_line(va: usize);
+  fn cache_invalidate_line(va: usize);
+  fn cache_clean_invalidate_line(va: usize);
+  fn cache_sync();
+  fn cache_clean_all_dcache();
+}
+
+/// Clean a range of virtual addresses to the Point of Coherency.
+///
+/// # Parameters
+///
+/// * `va` - The base virtual address.
+/// * `size` - The size of the range in bytes.
+pub fn clean_range(va: usize, size: usize) {
+  maintain_range(va, size, |line| unsafe { cache_clean_line(line) });
+}
+
+/// Invalidate a range of virtual addresses to the Point of Coherency.
+///
+/// # Parameters
+///
+/// * `va` - The base virtual address.
+/// * `size` - The size of the range in bytes.
+///
+/// # Description
+///
+///   NOTE: Invalidating discards any dirty data in the lines that overlap the
+///         range, including data outside of the range if the range is not
+///         aligned on cache lines.
+pub fn invalidate_range(va: usize, size: usize) {
+  maintain_range(va, size, |line| unsafe { cache_invalidate_line(line) });
+}
+
+/// Clean and invalidate a range of virtual addresses to the Point of
+/// Coherency.
+///
+/// # Parameters
+///
+/// * `va` - The base virtual address.
+/// * `size` - The size of the range in bytes.
+pub fn clean_invalidate_range(va: usize, size: usize) {
+  maintain_range(va, size, |line| unsafe { cache_clean_invalidate_line(line) });
+}
+
+/// Clean the current core's data caches up to the Level of Coherency.
+///
+/// # Description
+///
+/// Intended for use before releasing a secondary core that starts with its
+/// caches disabled.
+///
+///   NOTE: Set/way maintenance only affects the current core's caches and is
+///         not safe to use while other cores may write the same memory. Use
+///         the range operations for shared data.
+pub fn clean_all_dcache() {
+  unsafe { cache_clean_all_dcache() };
+}
+
+/// Perform a cache maintenance operation on a range and wait for the
+/// operation to complete.
+///
+/// # Parameters
+///
+/// * `va` - The base virtual address.
+/// * `size` - The size of the range in bytes.
+/// * `op` - The per-line maintenance operation.
+fn maintain_range(va: usize, size: usize, op: impl FnMut(usize)) {
+  let line_size = unsafe { cache_get_dcache_line_size() };
+
+  for_each_line(va, size, line_size, op);
+  unsafe { cache_sync() };
+}
+
+/// Call a function for every cache line that overlaps a range.
+///
+/// # Parameters
+///
+/// * `va` - The base virtual address.
+/// * `size` - The size of the range in bytes.
+/// * `line_size` - The cache line size. Must be a power of 2.
+/// * `op` - The function to call with the address of each line.
+///
+/// # Description
+///
+/// The range is clamped to the end of the address space.
+fn for_each_line(va: usize, size: usize, line_size: usize, mut op: impl FnMut(usize)) {
+  if size == 0 {
+    return;
+  }
+
+  let last = va.saturating_add(size - 1);
+  let mut line = bits::align_down(va, line_size);
+
+  loop {
+    op(line);
+
+    match line.checked_add(line_size) {
+      Some(next) if next <= last => line = next,
+      _ => break,
+    }
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,140 @@
+//! ARM Cache Maintenance Tests
+
+use super::for_each_line;
+use crate::debug_print;
+use crate::{check_eq, execute_test, test};
+
+/// Test cache line size.
+const TEST_LINE_SIZE: usize = 64;
+
+/// Maximum number of lines recorded by a test.
+const MAX_RECORDED_LINES: usize = 8;
+
+/// Lines passed to a mocked maintenance operation.
+struct RecordedLines {
+  lines: [usize; MAX_RECORDED_LINES],
+  count: usize,
+}
+
+impl RecordedLines {
+  /// Record the lines visited for a range.
+  ///
+  /// # Parameters
+  ///
+  /// * `va` - The base virtual address.
+  /// * `size` - The size of the range in bytes.
+  ///
+  /// # Description
+  ///
+  /// Lines beyond `MAX_RECORDED_LINES` are counted, but not recorded.
+  fn record(va: usize, size: usize) -> Self {
+    let mut recorded = RecordedLines {
+      lines: [0; MAX_RECORDED_LINES],
+      count: 0,
+    };
+
+    for_each_line(va, size, TEST_LINE_SIZE, |line| {
+      if recorded.count < MAX_RECORDED_LINES {
+        recorded.lines[recorded.count] = line;
+      }
+
+      recorded.count += 1;
+    });
+
+    recorded
+  }
+}
+
+/// Run cache maintenance tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_aligned_range);
+  execute_test!(context, test_unaligned_range);
+  execute_test!(context, test_empty_range);
+  execute_test!(context, test_end_of_address_space);
+}
+
+/// Check the recorded lines against a list of expected lines.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `recorded` - The recorded lines.
+/// * `expected` - The expected lines.
+fn check_lines(context: &mut test::TestContext, recorded: &RecordedLines, expected: &[usize]) {
+  check_eq!(context, recorded.count, expected.len());
+
+  for (line, exp) in recorded.lines.iter().zip(expected) {
+    check_eq!(context, *line, *exp);
+  }
+}
+
+/// Test a range aligned on cache lines.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_aligned_range(context: &mut test::TestContext) {
+  let recorded = RecordedLines::record(0x1000, TEST_LINE_SIZE * 3);
+  check_lines(context, &recorded, &[0x1000, 0x1040, 0x1080]);
+
+  let recorded = RecordedLines::record(0x1000, TEST_LINE_SIZE);
+  check_lines(context, &recorded, &[0x1000]);
+}
+
+/// Test ranges that are not aligned on cache lines.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Partial lines at both ends of a range must be included, and a single byte
+/// must only touch its own line.
+fn test_unaligned_range(context: &mut test::TestContext) {
+  let recorded = RecordedLines::record(0x1030, TEST_LINE_SIZE);
+  check_lines(context, &recorded, &[0x1000, 0x1040]);
+
+  let recorded = RecordedLines::record(0x103f, 2);
+  check_lines(context, &recorded, &[0x1000, 0x1040]);
+
+  let recorded = RecordedLines::record(0x107f, 1);
+  check_lines(context, &recorded, &[0x1040]);
+
+  let recorded = RecordedLines::record(0x1001, (TEST_LINE_SIZE * 2) - 1);
+  check_lines(context, &recorded, &[0x1000, 0x1040]);
+}
+
+/// Test an empty range.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_empty_range(context: &mut test::TestContext) {
+  let recorded = RecordedLines::record(0x1000, 0);
+  check_lines(context, &recorded, &[]);
+}
+
+/// Test ranges at the end of the address space.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The last line must be visited once without the address wrapping, and a
+/// range that extends past the end is clamped.
+fn test_end_of_address_space(context: &mut test::TestContext) {
+  let last_line = usize::MAX - (TEST_LINE_SIZE - 1);
+
+  let recorded = RecordedLines::record(last_line, TEST_LINE_SIZE);
+  check_lines(context, &recorded, &[last_line]);
+
+  let recorded = RecordedLines::record(last_line - TEST_LINE_SIZE, usize::MAX);
+  check_lines(context, &recorded, &[last_line - TEST_LINE_SIZE, last_line]);
+}
@@ -2,9 +2,36 @@
 
 pub use crate::arch::common::cpu::*;
 
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::{gic, irq, percpu};
+use crate::arch;
+use crate::sync::SpinLock;
+use crate::task::Task;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::ptr;
+
 unsafe extern "C" {
   fn cpu_halt() -> !;
+  fn cpu_idle();
+  fn cpu_relax();
   fn cpu_get_id() -> usize;
+  fn cpu_get_mmfr0() -> usize;
+}
+
+/// Set once a core begins halting the system so that only the first core to
+/// halt broadcasts the halt request.
+static mut HALTING: SpinLock<bool> = SpinLock::new(false);
+
+/// Inter-processor interrupt kinds. Each kind is sent as the software-generated
+/// interrupt with the same ID.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum IpiKind {
+  Reschedule = 0,
+  TlbFlush = 1,
+  Halt = 2,
 }
 
 /// Halt the caller.
@@ -12,7 +39,159 @@ pub fn halt() -> ! {
   unsafe { cpu_halt() };
 }
 
+/// Halt all online cores.
+///
+/// # Description
+///
+/// Sends a Halt IPI to every other online core, then halts the caller. Only the
+/// first core to call `halt_all()` broadcasts, so a core that is already
+/// halting, e.g. because it panicked while broadcasting, simply halts. No IPIs
+/// are sent if the interrupt controller has not been initialized.
+pub fn halt_all() -> ! {
+  if gic::is_initialized() {
+    let online = percpu::cores()
+      .filter(|core| core.is_online())
+      .map(|core| core.get_core_index());
+
+    broadcast_halt(get_halting(), arch::get_current_core_index(), online, send_ipi);
+  }
+
+  halt();
+}
+
+/// Put the caller into a low-power state until it is woken by an interrupt or
+/// an event.
+pub fn idle() {
+  unsafe { cpu_idle() };
+}
+
+/// Hint to the core that the caller is in a spin-wait loop.
+pub fn relax() {
+  unsafe { cpu_relax() };
+}
+
 /// Get the current core ID.
 pub fn get_id() -> usize {
   unsafe { cpu_get_id() }
 }
+
+/// Get the current core's memory management features.
+///
+/// # Description
+///
+/// Decodes the core's memory model feature register. See the architecture's
+/// `features::decode_mmfr0()`.
+pub fn features() -> CpuFeatures {
+  arch::features::decode_mmfr0(unsafe { cpu_get_mmfr0() })
+}
+
+/// Send an inter-processor interrupt to a core.
+///
+/// # Parameters
+///
+/// * `target_core` - The target core's index.
+/// * `ipi` - The kind of interrupt.
+pub fn send_ipi(target_core: usize, ipi: IpiKind) {
+  gic::send_sgi(target_core, ipi as u32);
+}
+
+/// Register and enable the inter-processor interrupt handlers.
+///
+/// # Description
+///
+///   NOTE: Must only be called once, on the primary core, after the interrupt
+///         controller has been initialized.
+pub fn init_ipi() {
+  let handlers: [(IpiKind, fn()); 3] = [
+    (IpiKind::Reschedule, handle_reschedule_ipi),
+    (IpiKind::TlbFlush, handle_tlb_flush_ipi),
+    (IpiKind::Halt, handle_halt_ipi),
+  ];
+
+  for (ipi, handler) in handlers {
+    irq::register(ipi as u32, handler);
+    gic::enable_irq(ipi as u32);
+  }
+}
+
+/// Handle an inter-processor interrupt on the current core.
+///
+/// # Parameters
+///
+/// * `ipi` - The kind of interrupt.
+///
+/// # Description
+///
+/// A reschedule request is recorded on the current task, a TLB flush
+/// invalidates all of the current core's TLB entries, and a halt request halts
+/// the core.
+pub fn handle_ipi(ipi: IpiKind) {
+  match ipi {
+    IpiKind::Reschedule => Task::get_current_task_mut().request_reschedule(),
+    IpiKind::TlbFlush => arch::mm::invalidate_tlb_all(),
+    IpiKind::Halt => halt(),
+  }
+}
+
+/// Reschedule IPI handler.
+fn handle_reschedule_ipi() {
+  handle_ipi(IpiKind::Reschedule);
+}
+
+/// TLB flush IPI handler.
+fn handle_tlb_flush_ipi() {
+  handle_ipi(IpiKind::TlbFlush);
+}
+
+/// Halt IPI handler.
+fn handle_halt_ipi() {
+  handle_ipi(IpiKind::Halt);
+}
+
+/// Send a Halt IPI to every other online core.
+///
+/// # Parameters
+///
+/// * `halting` - The halting flag. See `HALTING`.
+/// * `core_idx` - The calling core's index.
+/// * `online` - The indices of the online cores.
+/// * `send` - Sends an IPI to a core.
+///
+/// # Returns
+///
+/// The number of IPIs sent. No IPIs are sent if the system is already halting.
+fn broadcast_halt(
+  halting: &SpinLock<bool>,
+  core_idx: usize,
+  online: impl Iterator<Item = usize>,
+  mut send: impl FnMut(usize, IpiKind),
+) -> usize {
+  {
+    let mut halting = halting.lock();
+
+    if *halting {
+      return 0;
+    }
+
+    *halting = true;
+  }
+
+  let mut sent = 0;
+
+  for target in online.filter(|&target| target != core_idx) {
+    send(target, IpiKind::Halt);
+    sent += 1;
+  }
+
+  sent
+}
+
+/// Get the halting flag.
+fn get_halting() -> &'static SpinLock<bool> {
+  unsafe { ptr::addr_of!(HALTING).as_ref().unwrap() }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,58 @@
+//! ARM Common CPU Utility Tests
+
+use super::{IpiKind, broadcast_halt};
+use crate::debug_print;
+use crate::sync::SpinLock;
+use crate::{check_eq, execute_test, test};
+
+/// Number of simulated cores.
+const TEST_CORES: usize = 4;
+
+/// Run CPU utility tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_halt_all);
+}
+
+/// Test broadcasting a halt request to the other online cores.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Core 2 is offline and core 1 is halting the system. Cores 0 and 3 must each
+/// receive exactly one Halt IPI. A second broadcast, e.g. from a core that
+/// panics while halting, must not send any IPIs.
+fn test_halt_all(context: &mut test::TestContext) {
+  let halting = SpinLock::new(false);
+  let online = [0, 1, 3];
+  let mut received = [0; TEST_CORES];
+  let mut all_halt = true;
+
+  let sent = broadcast_halt(&halting, 1, online.into_iter(), |target, ipi| {
+    received[target] += 1;
+    all_halt &= ipi == IpiKind::Halt;
+  });
+
+  check_eq!(context, sent, 2);
+  check_eq!(context, all_halt, true);
+  check_eq!(context, received[0], 1);
+  check_eq!(context, received[1], 0);
+  check_eq!(context, received[2], 0);
+  check_eq!(context, received[3], 1);
+  let is_halting = *halting.lock();
+  check_eq!(context, is_halting, true);
+
+  let sent = broadcast_halt(&halting, 3, online.into_iter(), |target, _| {
+    received[target] += 1;
+  });
+
+  check_eq!(context, sent, 0);
+  let matches = received == [1, 0, 0, 1];
+  check_eq!(context, matches, true);
+}
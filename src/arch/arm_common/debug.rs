@@ -9,6 +9,8 @@ mod bcm2835_mini_uart_debug;
 pub use bcm2835_mini_uart_debug::*;
 
 use crate::support::print;
+#[cfg(feature = "module_tests")]
+use crate::test;
 use core::fmt::{self, Write};
 use core::ptr;
 
@@ -28,3 +30,8 @@ pub fn debug_print(args: fmt::Arguments) {
     _ => put_string("Error: debug_print Failed to format string.\n"),
   };
 }
+
+#[cfg(all(feature = "bcm2835_mini_uart_debug", feature = "module_tests"))]
+pub fn run_tests(context: &mut test::TestContext) {
+  bcm2835_mini_uart_debug::run_tests(context);
+}
@@ -12,7 +12,13 @@
 //!   [all]
 //!   enable_uart=1
 
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::support::mmio::Mmio;
 use crate::sync::SpinLock;
+#[cfg(feature = "module_tests")]
+use crate::test;
 use core::ptr;
 
 /// BCM2835 mini-UART registers.
@@ -28,8 +34,8 @@ const PHYSICAL_SIZE: usize = 0x1000;
 /// Re-initialization guard.
 static mut INITIALIZED: bool = false;
 
-/// The base virtual address chosen by the kernel for the registers.
-static mut VIRTUAL_BASE: usize = 0;
+/// The registers at the base virtual address chosen by the kernel.
+static mut REGS: Mmio = Mmio::new(0);
 
 /// Serial port guard.
 static mut DRIVER_LOCK: SpinLock<()> = SpinLock::new(());
@@ -48,7 +54,7 @@ pub fn init(virt_base: usize) {
   unsafe {
     assert!(!INITIALIZED);
     INITIALIZED = true;
-    VIRTUAL_BASE = virt_base;
+    REGS = Mmio::new(virt_base);
   }
 }
 
@@ -68,40 +74,35 @@ pub fn put_string(s: &str) {
 /// * `s` - The bytes to write.
 pub fn put_bytes(s: &[u8]) {
   let guard = unsafe { ptr::addr_of_mut!(DRIVER_LOCK).as_mut().unwrap() }.lock();
+  write_bytes(&unsafe { REGS }, s);
+}
 
+/// Write bytes to the mini-UART's data register.
+///
+/// # Parameters
+///
+/// * `regs` - The mini-UART registers.
+/// * `s` - The bytes to write.
+///
+/// # Description
+///
+/// Polls the line status register until the transmitter can accept a byte,
+/// then writes the byte to the data register. The register block's barriers
+/// keep each data write from passing the preceding status read.
+fn write_bytes(regs: &Mmio, s: &[u8]) {
   for c in s {
     loop {
-      let c = reg_get(AUX_MU_LSR_REG);
+      let c = regs.read32(AUX_MU_LSR_REG);
       if c & 0x20 != 0 {
         break;
       }
     }
 
-    reg_put(AUX_MU_IO_REG, *c as u32);
+    regs.write32(AUX_MU_IO_REG, *c as u32);
   }
 }
 
-/// Read a device register.
-///
-/// # Parameter
-///
-/// * `reg` - The device register to read.
-///
-/// # Returns
-///
-/// The value of the register.
-fn reg_get(reg: usize) -> u32 {
-  unsafe { ptr::read_volatile((VIRTUAL_BASE + reg) as *const u32) }
-}
-
-/// Write to a device register.
-///
-/// # Parameters
-///
-/// * `reg` - The device register to modify.
-/// * `val` - The value to write.
-fn reg_put(reg: usize, val: u32) {
-  unsafe {
-    ptr::write_volatile((VIRTUAL_BASE + reg) as *mut u32, val);
-  }
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
 }
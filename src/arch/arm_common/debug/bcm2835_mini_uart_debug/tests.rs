@@ -0,0 +1,98 @@
+//! BCM2835 Mini-UART Serial Debug Output Driver Tests
+
+use super::{AUX_MU_IO_REG, AUX_MU_LSR_REG, write_bytes};
+use crate::debug_print;
+use crate::support::mmio::Mmio;
+use crate::{check_eq, execute_test, test};
+use core::ptr;
+
+/// Number of registers backing the mock device.
+const REGISTER_COUNT: usize = AUX_MU_LSR_REG / 4 + 1;
+
+/// Line status with the transmitter empty.
+const LSR_TX_EMPTY: u32 = 0x20;
+
+/// Maximum number of recorded barriers.
+const MAX_BARRIERS: usize = 16;
+
+/// Registers backing the mock device.
+static mut REGISTERS: [u32; REGISTER_COUNT] = [0; REGISTER_COUNT];
+
+/// The line status and data registers at each barrier.
+static mut BARRIERS: [(u32, u32); MAX_BARRIERS] = [(0, 0); MAX_BARRIERS];
+static mut BARRIER_COUNT: usize = 0;
+
+/// Run mini-UART driver tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_barriers);
+}
+
+/// Record the registers at a barrier.
+///
+/// # Description
+///
+/// The transmitter becomes empty at the first barrier, so the first status read
+/// reports the transmitter as busy.
+fn record_barrier() {
+  unsafe {
+    let lsr = REGISTERS[AUX_MU_LSR_REG / 4];
+    let io = REGISTERS[AUX_MU_IO_REG / 4];
+
+    if BARRIER_COUNT < MAX_BARRIERS {
+      BARRIERS[BARRIER_COUNT] = (lsr, io);
+    }
+
+    BARRIER_COUNT += 1;
+    REGISTERS[AUX_MU_LSR_REG / 4] = LSR_TX_EMPTY;
+  }
+}
+
+/// Test the barriers around the register sequence.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A barrier must follow every status read, and a barrier must precede every
+/// data write. The data register still holds the previous byte at both, so
+/// each write is separated from the status read before it.
+fn test_barriers(context: &mut test::TestContext) {
+  let expected = [
+    (0, 0),
+    (LSR_TX_EMPTY, 0),
+    (LSR_TX_EMPTY, 0),
+    (LSR_TX_EMPTY, b'p' as u32),
+    (LSR_TX_EMPTY, b'p' as u32),
+  ];
+
+  unsafe {
+    REGISTERS = [0; REGISTER_COUNT];
+    BARRIER_COUNT = 0;
+  }
+
+  let regs = Mmio::with_barrier(ptr::addr_of_mut!(REGISTERS) as usize, record_barrier);
+  write_bytes(&regs, b"pk");
+
+  let count = unsafe { BARRIER_COUNT };
+  check_eq!(context, count, expected.len());
+
+  for (idx, regs) in expected.iter().enumerate() {
+    let matches = unsafe { BARRIERS[idx] } == *regs;
+    check_eq!(context, matches, true);
+  }
+
+  let io = unsafe { REGISTERS[AUX_MU_IO_REG / 4] };
+  check_eq!(context, io, b'k' as u32);
+
+  unsafe { BARRIER_COUNT = 0 };
+  write_bytes(&regs, b"");
+
+  let count = unsafe { BARRIER_COUNT };
+  check_eq!(context, count, 0);
+}
@@ -0,0 +1,210 @@
+//! ARM Common DTB Chosen Scanner
+//!
+//! https://devicetree-specification.readthedocs.io/en/stable/devicenodes.html#chosen-node
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::arch::memory::{MemoryRange, MemoryZone};
+use crate::support::{bits, dtb};
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::cmp;
+
+/// Boot information passed to the kernel by the bootloader in `/chosen`.
+pub struct ChosenInfo<'blob> {
+  bootargs: Option<&'blob [u8]>,
+  initrd_start: usize,
+  initrd_end: usize,
+}
+
+impl<'blob> ChosenInfo<'blob> {
+  /// Construct an empty chosen information structure.
+  pub const fn new() -> Self {
+    ChosenInfo {
+      bootargs: None,
+      initrd_start: 0,
+      initrd_end: 0,
+    }
+  }
+
+  /// Get the kernel command line, excluding the null-terminator.
+  pub fn get_bootargs(&self) -> Option<&'blob [u8]> {
+    self.bootargs
+  }
+
+  /// Get the physical address range of the initial ramdisk.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the base address and size of the initial ramdisk, or None if
+  /// the bootloader did not provide a valid initial ramdisk.
+  pub fn get_initrd(&self) -> Option<(usize, usize)> {
+    if self.initrd_end <= self.initrd_start {
+      return None;
+    }
+
+    Some((self.initrd_start, self.initrd_end - self.initrd_start))
+  }
+
+  /// Get the memory range to exclude from available memory to protect the
+  /// initial ramdisk.
+  ///
+  /// # Parameters
+  ///
+  /// * `boundary` - The boundary on which to align the range.
+  ///
+  /// # Description
+  ///
+  /// The base is aligned down and the end is aligned up to the boundary.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes the boundary is a power of 2 and that aligning the end of the
+  /// initial ramdisk up will not overflow.
+  ///
+  /// # Returns
+  ///
+  /// The exclusion range, or None if there is no initial ramdisk.
+  pub fn get_initrd_exclusion(&self, boundary: usize) -> Option<MemoryRange> {
+    let (base, size) = self.get_initrd()?;
+    let start = bits::align_down(base, boundary);
+    let end = bits::align_up(base + size, boundary);
+
+    Some(MemoryRange {
+      tag: MemoryZone::InvalidZone,
+      base: start,
+      size: end - start,
+    })
+  }
+}
+
+/// Scans for the `/chosen` node.
+struct DtbChosenScanner<'blob> {
+  info: ChosenInfo<'blob>,
+}
+
+impl<'blob> DtbChosenScanner<'blob> {
+  /// Construct a new DTB chosen scanner.
+  pub fn new() -> Self {
+    DtbChosenScanner {
+      info: ChosenInfo::new(),
+    }
+  }
+
+  /// Scan the `chosen` node.
+  ///
+  /// # Parameters
+  ///
+  /// * `reader` - The DTB reader.
+  /// * `cursor` - The current position in the DTB.
+  ///
+  /// # Returns
+  ///
+  /// Returns Ok if able to read the node, otherwise a DTB error.
+  fn scan_chosen_node(
+    &mut self,
+    reader: &dtb::DtbReader<'blob>,
+    cursor: &dtb::DtbCursor,
+  ) -> Result<(), dtb::DtbError> {
+    if let Some((mut str_cursor, _)) = reader.get_property(cursor, b"bootargs") {
+      self.info.bootargs = Some(
+        reader
+          .get_null_terminated_u8_slice(&mut str_cursor)
+          .ok_or(dtb::DtbError::InvalidDtb)?,
+      );
+    }
+
+    if let Some((mut addr_cursor, size)) = reader.get_property(cursor, b"linux,initrd-start") {
+      self.info.initrd_start = Self::read_addr(size, reader, &mut addr_cursor)?;
+    }
+
+    if let Some((mut addr_cursor, size)) = reader.get_property(cursor, b"linux,initrd-end") {
+      self.info.initrd_end = Self::read_addr(size, reader, &mut addr_cursor)?;
+    }
+
+    Ok(())
+  }
+
+  /// Read an initial ramdisk address property.
+  ///
+  /// # Parameters
+  ///
+  /// * `size` - The size of the property's value.
+  /// * `reader` - The DTB reader.
+  /// * `cursor` - The current position in the DTB.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: Bootloaders write the initial ramdisk addresses as either 32-bit
+  ///         or 64-bit values.
+  ///
+  /// # Returns
+  ///
+  /// Returns Ok with the address if valid, otherwise a DTB error.
+  fn read_addr(
+    size: usize,
+    reader: &dtb::DtbReader,
+    cursor: &mut dtb::DtbCursor,
+  ) -> Result<usize, dtb::DtbError> {
+    match size {
+      4 => Ok(reader.get_u32(cursor).ok_or(dtb::DtbError::InvalidDtb)? as usize),
+
+      8 => {
+        let addr = reader.get_u64(cursor).ok_or(dtb::DtbError::InvalidDtb)?;
+        usize::try_from(addr).or(Err(dtb::DtbError::InvalidDtb))
+      }
+
+      _ => Err(dtb::DtbError::UnsupportedValue),
+    }
+  }
+}
+
+impl<'blob> dtb::DtbScanner<'blob> for DtbChosenScanner<'blob> {
+  /// See `dtb::DtbScanner::scan_node()`.
+  fn scan_node(
+    &mut self,
+    reader: &dtb::DtbReader<'blob>,
+    name: &[u8],
+    cursor: &dtb::DtbCursor,
+  ) -> Result<bool, dtb::DtbError> {
+    if name.cmp(b"chosen") != cmp::Ordering::Equal {
+      return Ok(true);
+    }
+
+    _ = self.scan_chosen_node(reader, cursor)?;
+
+    // There is only one `/chosen` node; stop scanning.
+    Ok(false)
+  }
+}
+
+/// Get the boot information from the `/chosen` node.
+///
+/// # Parameters
+///
+/// * `blob_vaddr` - The DTB virtual address.
+///
+/// # Description
+///
+/// The slices returned in the boot information reference the DTB directly.
+///
+/// # Returns
+///
+/// The boot information, or None if the DTB could not be read. If the DTB does
+/// not have a `/chosen` node, the boot information will be empty.
+pub fn get_chosen_info<'blob>(blob_vaddr: usize) -> Option<ChosenInfo<'blob>> {
+  let reader = dtb::DtbReader::new(blob_vaddr).ok()?;
+  let mut scanner = DtbChosenScanner::new();
+
+  if !reader.scan(&mut scanner).is_ok() {
+    return None;
+  }
+
+  Some(scanner.info)
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,183 @@
+//! ARM Common DTB Chosen Scanner Tests
+
+use super::get_chosen_info;
+use crate::arch::memory::{MemoryConfig, MemoryRange, MemoryZone};
+use crate::debug_print;
+use crate::test::{self, dtb};
+use crate::{check_eq, check_none, check_not_none, execute_test, mark_fail};
+use core::ptr;
+
+/// Test command line.
+const TEST_BOOTARGS: &str = "console=ttyAMA0,115200 root=/dev/ram0";
+
+/// Test initial ramdisk range. The range is deliberately not page-aligned.
+const TEST_INITRD_START: u32 = 0x0200_0800;
+const TEST_INITRD_END: u32 = 0x0240_0100;
+
+/// Test exclusion boundary.
+const TEST_BOUNDARY: usize = 0x1000;
+
+/// The memory configuration is too large to build on the kernel stack.
+static mut TEST_MEMORY_CONFIG: MemoryConfig = MemoryConfig::new(MemoryZone::InvalidZone);
+
+/// Run chosen scanner tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_chosen_info);
+  execute_test!(context, test_chosen_info_64bit);
+  execute_test!(context, test_no_chosen_node);
+  execute_test!(context, test_initrd_exclusion);
+}
+
+/// Build a DTB with a `/chosen` node.
+///
+/// # Parameters
+///
+/// * `builder` - The DTB builder.
+/// * `initrd_64bit` - Write the initial ramdisk addresses as 64-bit values.
+///
+/// # Returns
+///
+/// The address of the DTB.
+fn build_chosen_blob(builder: &mut dtb::DtbBuilder, initrd_64bit: bool) -> usize {
+  builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("chosen")
+    .prop_str("bootargs", TEST_BOOTARGS);
+
+  if initrd_64bit {
+    builder
+      .prop_cells("linux,initrd-start", &[0, TEST_INITRD_START])
+      .prop_cells("linux,initrd-end", &[0, TEST_INITRD_END]);
+  } else {
+    builder
+      .prop_u32("linux,initrd-start", TEST_INITRD_START)
+      .prop_u32("linux,initrd-end", TEST_INITRD_END);
+  }
+
+  builder.end_node().end_node().finish()
+}
+
+/// Check the information read from a DTB built by `build_chosen_blob()`.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `blob` - The DTB address.
+fn check_chosen_info(context: &mut test::TestContext, blob: usize) {
+  let Some(info) = get_chosen_info(blob) else {
+    mark_fail!(context, "Failed to read the chosen node.");
+    return;
+  };
+
+  let bootargs = info.get_bootargs();
+  check_not_none!(context, bootargs);
+
+  let matches = bootargs == Some(TEST_BOOTARGS.as_bytes());
+  check_eq!(context, matches, true);
+
+  let Some((base, size)) = info.get_initrd() else {
+    mark_fail!(context, "Initial ramdisk not found.");
+    return;
+  };
+
+  check_eq!(context, base, TEST_INITRD_START as usize);
+  check_eq!(context, size, (TEST_INITRD_END - TEST_INITRD_START) as usize);
+}
+
+/// Test reading 32-bit initial ramdisk addresses.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_chosen_info(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = build_chosen_blob(&mut builder, false);
+  check_chosen_info(context, blob);
+}
+
+/// Test reading 64-bit initial ramdisk addresses.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_chosen_info_64bit(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = build_chosen_blob(&mut builder, true);
+  check_chosen_info(context, blob);
+}
+
+/// Test a DTB without a `/chosen` node.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_no_chosen_node(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .end_node()
+    .finish();
+
+  let Some(info) = get_chosen_info(blob) else {
+    mark_fail!(context, "Failed to read the DTB.");
+    return;
+  };
+
+  check_none!(context, info.get_bootargs());
+  check_none!(context, info.get_initrd());
+  check_none!(context, info.get_initrd_exclusion(TEST_BOUNDARY));
+}
+
+/// Test excluding the initial ramdisk from available memory.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The exclusion range is aligned outward, so the memory on either side of the
+/// initial ramdisk must remain available up to the aligned boundaries.
+fn test_initrd_exclusion(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = build_chosen_blob(&mut builder, false);
+
+  let Some(info) = get_chosen_info(blob) else {
+    mark_fail!(context, "Failed to read the chosen node.");
+    return;
+  };
+
+  let Some(excl) = info.get_initrd_exclusion(TEST_BOUNDARY) else {
+    mark_fail!(context, "Initial ramdisk exclusion not found.");
+    return;
+  };
+
+  let excl_start = TEST_INITRD_START as usize & !(TEST_BOUNDARY - 1);
+  let excl_end = (TEST_INITRD_END as usize + TEST_BOUNDARY - 1) & !(TEST_BOUNDARY - 1);
+  check_eq!(context, excl.base, excl_start);
+  check_eq!(context, excl.size, excl_end - excl_start);
+
+  let config = unsafe { ptr::addr_of_mut!(TEST_MEMORY_CONFIG).as_mut().unwrap() };
+  config.clear();
+  config.insert_range(MemoryRange {
+    tag: MemoryZone::LinearMemoryZone,
+    base: 0,
+    size: 0x1000_0000,
+  });
+
+  check_eq!(context, config.exclude_range(&excl).is_ok(), true);
+
+  let ranges = config.get_ranges();
+  check_eq!(context, ranges.len(), 2);
+  check_eq!(context, ranges[0].base, 0);
+  check_eq!(context, ranges[0].size, excl_start);
+  check_eq!(context, ranges[1].base, excl_end);
+  check_eq!(context, ranges[1].size, 0x1000_0000 - excl_end);
+}
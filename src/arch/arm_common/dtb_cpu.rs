@@ -1,8 +1,32 @@
 //! ARM Common DTB CPU Scanner
 
+#[cfg(feature = "module_tests")]
+mod tests;
+
 use super::cpu::{self, Core, CoreConfig, CoreEnableMethod};
-use crate::support::{dtb, hash, hash_map};
-use core::cmp;
+use crate::support::{dtb, hash, hash_map, print};
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::fmt::Write;
+use core::{cmp, str};
+
+/// Maximum nesting depth of clusters in the `cpu-map` node.
+const MAX_CLUSTER_DEPTH: usize = 8;
+
+/// Maximum length of a `cpu-map` child node name, e.g. `cluster12`.
+const MAP_NODE_NAME_LEN: usize = 32;
+
+/// Approximate capacities of known cores derived from ARM's published DMIPS/MHz
+/// figures and normalized so that the Cortex-A72 has a capacity of 1024. Used
+/// when a core does not have a `capacity-dmips-mhz` property.
+const KNOWN_CORE_CAPACITIES: [(&[u8], u32); 6] = [
+  (b"arm,cortex-a7", 414),
+  (b"arm,cortex-a15", 763),
+  (b"arm,cortex-a53", 501),
+  (b"arm,cortex-a55", 588),
+  (b"arm,cortex-a57", 893),
+  (b"arm,cortex-a72", 1024),
+];
 
 /// Tags for CPU properties and string values.
 enum DtbStringTag {
@@ -12,6 +36,8 @@ enum DtbStringTag {
   DtbPropEnableMethod,
   DtbPropCpuReleaseAddr,
   DtbPropReg,
+  DtbPropPhandle,
+  DtbPropCapacity,
 
   DtbValueSpinTable,
   DtbValueBcm2836,
@@ -43,6 +69,9 @@ impl<'config> DtbCoreScanner<'config> {
     map.insert("enable-method".as_bytes(), DtbStringTag::DtbPropEnableMethod);
     map.insert("cpu-release-addr".as_bytes(), DtbStringTag::DtbPropCpuReleaseAddr);
     map.insert("reg".as_bytes(), DtbStringTag::DtbPropReg);
+    map.insert("phandle".as_bytes(), DtbStringTag::DtbPropPhandle);
+    map.insert("linux,phandle".as_bytes(), DtbStringTag::DtbPropPhandle);
+    map.insert("capacity-dmips-mhz".as_bytes(), DtbStringTag::DtbPropCapacity);
 
     map.insert("spin-table".as_bytes(), DtbStringTag::DtbValueSpinTable);
     map.insert("brcm,bcm2836-smp".as_bytes(), DtbStringTag::DtbValueBcm2836);
@@ -127,6 +156,10 @@ impl<'config> DtbCoreScanner<'config> {
   /// * `reader` - The DTB reader.
   /// * `cursor` - The current position in the DTB.
   ///
+  /// # Description
+  ///
+  /// Disabled cores are ignored so that they are never counted as online.
+  ///
   /// # Returns
   ///
   /// Returns Ok if able to read the node, otherwise a DTB error.
@@ -135,13 +168,18 @@ impl<'config> DtbCoreScanner<'config> {
     reader: &dtb::DtbReader,
     cursor: &dtb::DtbCursor,
   ) -> Result<(), dtb::DtbError> {
+    if !reader.node_is_enabled(cursor) {
+      return Ok(());
+    }
+
     let mut tmp_cursor = *cursor;
     let mut core = Core::new();
+    let mut capacity = None;
 
     while let Some(header) = reader.get_next_property(&mut tmp_cursor) {
       match self.string_map.find(header.name) {
         Some(DtbStringTag::DtbPropCompatible) => {
-          Self::read_compatible(&mut core.core_type, reader, &mut tmp_cursor)?;
+          Self::read_compatible(&mut core.core_type, header.size, reader, &mut tmp_cursor)?;
         }
 
         Some(DtbStringTag::DtbPropEnableMethod) => {
@@ -161,6 +199,21 @@ impl<'config> DtbCoreScanner<'config> {
             Self::read_thread_id(header.size, self.addr_cells, reader, &mut tmp_cursor)? as usize;
         }
 
+        Some(DtbStringTag::DtbPropPhandle) => {
+          // Keep the phandle to match the core with its `cpu-map` entry.
+          core.phandle = reader
+            .get_u32(&mut tmp_cursor)
+            .ok_or(dtb::DtbError::InvalidDtb)?;
+        }
+
+        Some(DtbStringTag::DtbPropCapacity) => {
+          capacity = Some(
+            reader
+              .get_u32(&mut tmp_cursor)
+              .ok_or(dtb::DtbError::InvalidDtb)?,
+          );
+        }
+
         _ => reader.skip_and_align(header.size, &mut tmp_cursor),
       }
     }
@@ -179,6 +232,12 @@ impl<'config> DtbCoreScanner<'config> {
       _ => {}
     }
 
+    // Classify the core by its type if it does not specify its capacity.
+    core.capacity = match capacity {
+      Some(capacity) => capacity,
+      None => Self::classify_capacity(&core.core_type),
+    };
+
     // Do not worry if we were unable to add the core. If there are too many
     // cores, we will just ignore it.
     _ = self.config.add_core(core, is_primary);
@@ -190,29 +249,74 @@ impl<'config> DtbCoreScanner<'config> {
   ///
   /// # Parameters
   ///
-  /// * `core_type` - The slice to receive the string.
+  /// * `core_type` - The slice to receive the null-separated list of strings.
+  /// * `prop_size` - The size of the compatible property.
   /// * `reader` - The DTB reader.
   /// * `cursor` - The current position in the DTB.
   ///
+  /// # Description
+  ///
+  /// The compatible property is a list of null-terminated strings. The strings
+  /// are copied in order until the next string does not fit. A string is never
+  /// truncated so that a partial string cannot match a shorter core type. See
+  /// `cpu::compatible_contains()`.
+  ///
   /// # Returns
   ///
   /// Returns Ok if able to read the property, otherwise a DTB error.
   fn read_compatible(
     core_type: &mut [u8],
+    prop_size: usize,
     reader: &dtb::DtbReader,
     cursor: &mut dtb::DtbCursor,
   ) -> Result<(), dtb::DtbError> {
-    let compatible = reader
-      .get_null_terminated_u8_slice(cursor)
-      .ok_or(dtb::DtbError::InvalidDtb)?;
-    reader.skip_and_align(1, cursor);
+    let mut tmp_cursor = *cursor;
+    let mut remaining = prop_size;
+    let mut len = 0;
 
-    let len = cmp::min(compatible.len(), core_type.len());
-    core_type[..len].clone_from_slice(&compatible[..len]);
+    while remaining > 0 {
+      let compatible = reader
+        .get_null_terminated_u8_slice(&mut tmp_cursor)
+        .ok_or(dtb::DtbError::InvalidDtb)?;
+
+      // Leave room for the null-terminator.
+      if len + compatible.len() + 1 > core_type.len() {
+        break;
+      }
+
+      core_type[len..len + compatible.len()].clone_from_slice(compatible);
+      len += compatible.len() + 1;
+
+      // Step over the null-terminator to the next string.
+      reader.skip(1, &mut tmp_cursor);
+      remaining = remaining.saturating_sub(compatible.len() + 1);
+    }
+
+    reader.skip_and_align(prop_size, cursor);
 
     Ok(())
   }
 
+  /// Look up the capacity of a known core type.
+  ///
+  /// # Parameters
+  ///
+  /// * `core_type` - The null-padded core type.
+  ///
+  /// # Returns
+  ///
+  /// The capacity of the core type, or the default capacity if the core type
+  /// is unknown.
+  fn classify_capacity(core_type: &[u8]) -> u32 {
+    for (known_type, capacity) in KNOWN_CORE_CAPACITIES {
+      if cpu::compatible_contains(core_type, known_type) {
+        return capacity;
+      }
+    }
+
+    cpu::DEFAULT_CORE_CAPACITY
+  }
+
   /// Read the `enable-method` property.
   ///
   /// # Parameters
@@ -315,19 +419,22 @@ impl<'config> DtbCoreScanner<'config> {
     cursor: &mut dtb::DtbCursor,
   ) -> Result<u64, dtb::DtbError> {
     let mut tmp_cursor = *cursor;
-    let count = size / dtb::DtbReader::get_reg_pair_size(addr_cells, 0);
     let pair = reader
       .get_reg_pair(addr_cells, 0, &mut tmp_cursor)
       .ok_or(dtb::DtbError::InvalidDtb)?;
+
+    // Only the first thread is used. Skip the remaining thread identifiers so
+    // the cursor is positioned at the next property.
+    reader.skip_and_align(size, cursor);
     Ok(pair.0)
   }
 }
 
-impl<'config> dtb::DtbScanner for DtbCoreScanner<'config> {
+impl<'config, 'blob> dtb::DtbScanner<'blob> for DtbCoreScanner<'config> {
   /// See `dtb::DtbScanner::scan_node()`.
   fn scan_node(
     &mut self,
-    reader: &dtb::DtbReader,
+    reader: &dtb::DtbReader<'blob>,
     name: &[u8],
     cursor: &dtb::DtbCursor,
   ) -> Result<bool, dtb::DtbError> {
@@ -341,6 +448,186 @@ impl<'config> dtb::DtbScanner for DtbCoreScanner<'config> {
   }
 }
 
+/// Find a numbered child of a `cpu-map` node, e.g. `cluster0` or `core1`.
+///
+/// # Parameters
+///
+/// * `reader` - The DTB reader.
+/// * `cursor` - The cursor pointing to the parent node.
+/// * `prefix` - The child name prefix.
+/// * `index` - The child index.
+///
+/// # Returns
+///
+/// A cursor pointing to the child, or None if the child does not exist.
+fn find_map_child(
+  reader: &dtb::DtbReader,
+  cursor: &dtb::DtbCursor,
+  prefix: &str,
+  index: usize,
+) -> Option<dtb::DtbCursor> {
+  let mut buf = [0u8; MAP_NODE_NAME_LEN];
+  let mut name = print::WriteBuffer::new(&mut buf);
+  _ = write!(name, "{}{}", prefix, index);
+
+  reader.find_child_node(cursor, str::from_utf8(name.as_bytes()).ok()?)
+}
+
+/// Read the phandle of the core referenced by a `cpu-map` core or thread
+/// node.
+///
+/// # Parameters
+///
+/// * `reader` - The DTB reader.
+/// * `cursor` - The cursor pointing to the core or thread node.
+///
+/// # Returns
+///
+/// Returns Ok with the phandle, Ok with None if the node does not reference a
+/// core, or a DTB error.
+fn read_map_phandle(
+  reader: &dtb::DtbReader,
+  cursor: &dtb::DtbCursor,
+) -> Result<Option<u32>, dtb::DtbError> {
+  let Some((mut tmp_cursor, _)) = reader.get_property(cursor, b"cpu") else {
+    return Ok(None);
+  };
+
+  let phandle = reader
+    .get_u32(&mut tmp_cursor)
+    .ok_or(dtb::DtbError::InvalidDtb)?;
+  Ok(Some(phandle))
+}
+
+/// Assign a cluster ID to each core listed in a leaf cluster node.
+///
+/// # Parameters
+///
+/// * `config` - The core configuration.
+/// * `reader` - The DTB reader.
+/// * `cursor` - The cursor pointing to the cluster node.
+/// * `cluster_id` - The cluster ID to assign.
+///
+/// # Description
+///
+/// A core node either references a core directly, or contains `threadN` nodes
+/// for multi-threaded cores. Only the first thread is used.
+///
+/// # Returns
+///
+/// Returns Ok if able to read the cluster, otherwise a DTB error.
+fn read_cluster_cores(
+  config: &mut CoreConfig,
+  reader: &dtb::DtbReader,
+  cursor: &dtb::DtbCursor,
+  cluster_id: usize,
+) -> Result<(), dtb::DtbError> {
+  let mut index = 0;
+
+  while let Some(core_cursor) = find_map_child(reader, cursor, "core", index) {
+    let phandle = match read_map_phandle(reader, &core_cursor)? {
+      Some(phandle) => Some(phandle),
+      None => match find_map_child(reader, &core_cursor, "thread", 0) {
+        Some(thread_cursor) => read_map_phandle(reader, &thread_cursor)?,
+        None => None,
+      },
+    };
+
+    // Zero is not a valid phandle; do not match cores without one.
+    if let Some(phandle) = phandle
+      && phandle != 0
+    {
+      for core in config.get_cores_mut() {
+        if core.phandle == phandle {
+          core.cluster_id = cluster_id;
+        }
+      }
+    }
+
+    index += 1;
+  }
+
+  Ok(())
+}
+
+/// Walk the `clusterN` children of a `cpu-map` or cluster node.
+///
+/// # Parameters
+///
+/// * `config` - The core configuration.
+/// * `reader` - The DTB reader.
+/// * `cursor` - The cursor pointing to the parent node.
+/// * `next_cluster` - The next cluster ID to assign.
+/// * `depth` - The current nesting depth.
+///
+/// # Description
+///
+/// Clusters may be nested. Only leaf clusters, those that contain `coreN`
+/// nodes, are assigned IDs. IDs are assigned in the order the leaf clusters
+/// appear in the DTB.
+///
+/// # Returns
+///
+/// Returns Ok if able to read the clusters, otherwise a DTB error.
+fn read_clusters(
+  config: &mut CoreConfig,
+  reader: &dtb::DtbReader,
+  cursor: &dtb::DtbCursor,
+  next_cluster: &mut usize,
+  depth: usize,
+) -> Result<(), dtb::DtbError> {
+  if depth >= MAX_CLUSTER_DEPTH {
+    return Err(dtb::DtbError::InvalidDtb);
+  }
+
+  let mut index = 0;
+
+  while let Some(cluster_cursor) = find_map_child(reader, cursor, "cluster", index) {
+    if find_map_child(reader, &cluster_cursor, "core", 0).is_some() {
+      read_cluster_cores(config, reader, &cluster_cursor, *next_cluster)?;
+      *next_cluster += 1;
+    } else {
+      read_clusters(config, reader, &cluster_cursor, next_cluster, depth + 1)?;
+    }
+
+    index += 1;
+  }
+
+  Ok(())
+}
+
+/// Read the `/cpus/cpu-map` topology and assign cluster IDs to the cores.
+///
+/// # Parameters
+///
+/// * `config` - The core configuration.
+/// * `reader` - The DTB reader.
+///
+/// # Description
+///
+/// https://www.kernel.org/doc/Documentation/devicetree/bindings/cpu/cpu-topology.txt
+///
+/// Cores not listed in the map, or all cores if there is no map, remain in
+/// cluster 0.
+///
+/// # Returns
+///
+/// Returns Ok if able to read the topology, otherwise a DTB error.
+fn read_cpu_map(config: &mut CoreConfig, reader: &dtb::DtbReader) -> Result<(), dtb::DtbError> {
+  let root = reader.get_root_node().ok_or(dtb::DtbError::InvalidDtb)?;
+
+  let Some(cpus) = reader.find_child_node(&root, "cpus") else {
+    return Ok(());
+  };
+
+  let Some(map) = reader.find_child_node(&cpus, "cpu-map") else {
+    return Ok(());
+  };
+
+  let mut next_cluster = 0;
+  read_clusters(config, reader, &map, &mut next_cluster, 0)
+}
+
 /// Get the core configuration.
 ///
 /// # Parameters
@@ -348,6 +635,11 @@ impl<'config> dtb::DtbScanner for DtbCoreScanner<'config> {
 /// * `config` - The core configuration.
 /// * `blob_vaddr` - The DTB virtual address.
 ///
+/// # Description
+///
+/// Reads the `cpu@N` nodes, then assigns cluster IDs from the `cpu-map` node if
+/// present.
+///
 /// # Assumptions
 ///
 /// Assumes the caller is on the primary core.
@@ -370,6 +662,10 @@ pub fn get_core_config(config: &mut CoreConfig, blob_vaddr: usize) -> bool {
     return false;
   }
 
+  if !read_cpu_map(config, &reader).is_ok() {
+    return false;
+  }
+
   // Validate that we have at least one core.
   if config.get_core_count() == 0 {
     return false;
@@ -385,3 +681,8 @@ pub fn get_core_config(config: &mut CoreConfig, blob_vaddr: usize) -> bool {
 
   true
 }
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,320 @@
+//! ARM Common DTB CPU Scanner Tests
+
+use super::get_core_config;
+use crate::arch::cpu::{self, CoreConfig};
+use crate::debug_print;
+use crate::test::{self, dtb};
+use crate::{check_eq, check_none, check_not_none, execute_test, mark_fail};
+use core::ptr;
+
+/// Number of cores in each test cluster.
+const CLUSTER_SIZE: usize = 2;
+
+/// Number of test clusters.
+const CLUSTER_COUNT: usize = 2;
+
+/// Test node names.
+const CLUSTER_NAMES: [&str; CLUSTER_COUNT] = ["cluster0", "cluster1"];
+const CORE_NAMES: [&str; CLUSTER_SIZE] = ["core0", "core1"];
+const CPU_NAMES: [&str; CLUSTER_COUNT * CLUSTER_SIZE] = ["cpu@0", "cpu@1", "cpu@2", "cpu@3"];
+
+/// The core configuration is too large to build on the kernel stack on 64-bit
+/// platforms.
+static mut TEST_CORE_CONFIG: CoreConfig = CoreConfig::new();
+
+/// Run CPU scanner tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_cluster_topology);
+  execute_test!(context, test_no_cpu_map);
+  execute_test!(context, test_core_capacity);
+  execute_test!(context, test_multi_compatible);
+  execute_test!(context, test_disabled_core);
+}
+
+/// Build a DTB with two clusters of two cores each.
+///
+/// # Parameters
+///
+/// * `builder` - The DTB builder.
+/// * `with_map` - Include the `cpu-map` node.
+///
+/// # Description
+///
+/// The core IDs start at the current core's ID so that the scanner finds the
+/// primary core. Core N has phandle N + 1. The `cpu-map` node is placed before
+/// the `cpu@N` nodes to verify that the topology does not depend on the order
+/// of the nodes.
+///
+/// # Returns
+///
+/// The address of the DTB.
+fn build_cluster_blob(builder: &mut dtb::DtbBuilder, with_map: bool) -> usize {
+  let primary_id = cpu::get_id() as u32;
+
+  builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("cpus")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 0)
+    .prop_str("enable-method", "spin-table");
+
+  if with_map {
+    builder.begin_node("cpu-map");
+
+    for (cluster, cluster_name) in CLUSTER_NAMES.iter().enumerate() {
+      builder.begin_node(cluster_name);
+
+      for (core, core_name) in CORE_NAMES.iter().enumerate() {
+        let phandle = (cluster * CLUSTER_SIZE + core + 1) as u32;
+
+        builder
+          .begin_node(core_name)
+          .prop_u32("cpu", phandle)
+          .end_node();
+      }
+
+      builder.end_node();
+    }
+
+    builder.end_node();
+  }
+
+  for (core, cpu_name) in CPU_NAMES.iter().enumerate() {
+    builder
+      .begin_node(cpu_name)
+      .prop_u32("reg", primary_id + core as u32)
+      .prop_u32("phandle", core as u32 + 1)
+      .end_node();
+  }
+
+  builder.end_node().end_node().finish()
+}
+
+/// Test assigning cluster IDs from the `cpu-map` node.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_cluster_topology(context: &mut test::TestContext) {
+  let config = unsafe { ptr::addr_of_mut!(TEST_CORE_CONFIG).as_mut().unwrap() };
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = build_cluster_blob(&mut builder, true);
+
+  check_eq!(context, get_core_config(config, blob), true);
+  check_eq!(context, config.get_core_count(), CLUSTER_COUNT * CLUSTER_SIZE);
+
+  let primary_id = cpu::get_id();
+
+  for core in 0..(CLUSTER_COUNT * CLUSTER_SIZE) {
+    let Some(index) = config.get_core_index(primary_id + core) else {
+      mark_fail!(context, "Core not found.");
+      continue;
+    };
+
+    let cores = config.get_cores();
+    check_eq!(context, cores[index].get_cluster_id(), core / CLUSTER_SIZE);
+  }
+}
+
+/// Test that all cores are in cluster 0 without a `cpu-map` node.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_no_cpu_map(context: &mut test::TestContext) {
+  let config = unsafe { ptr::addr_of_mut!(TEST_CORE_CONFIG).as_mut().unwrap() };
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = build_cluster_blob(&mut builder, false);
+
+  check_eq!(context, get_core_config(config, blob), true);
+  check_eq!(context, config.get_core_count(), CLUSTER_COUNT * CLUSTER_SIZE);
+
+  for core in config.get_cores() {
+    check_eq!(context, core.get_cluster_id(), 0);
+  }
+}
+
+/// Test reading core capacities.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first two cores specify their capacities. The third core is classified
+/// by its compatible string, and the fourth is an unknown core type that falls
+/// back to the default capacity.
+fn test_core_capacity(context: &mut test::TestContext) {
+  const CORES: [(&str, Option<u32>, u32); 4] = [
+    ("arm,cortex-a72", Some(1024), 1024),
+    ("arm,cortex-a53", Some(485), 485),
+    ("arm,cortex-a53", None, 501),
+    ("vendor,unknown-core", None, cpu::DEFAULT_CORE_CAPACITY),
+  ];
+
+  let config = unsafe { ptr::addr_of_mut!(TEST_CORE_CONFIG).as_mut().unwrap() };
+  let primary_id = cpu::get_id();
+  let mut builder = dtb::DtbBuilder::new();
+
+  builder
+    .begin_node("")
+    .begin_node("cpus")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 0)
+    .prop_str("enable-method", "spin-table");
+
+  for (core, (compatible, capacity, _)) in CORES.iter().enumerate() {
+    builder
+      .begin_node(CPU_NAMES[core])
+      .prop_str("compatible", compatible)
+      .prop_u32("reg", (primary_id + core) as u32);
+
+    if let Some(capacity) = capacity {
+      builder.prop_u32("capacity-dmips-mhz", *capacity);
+    }
+
+    builder.end_node();
+  }
+
+  let blob = builder.end_node().end_node().finish();
+
+  check_eq!(context, get_core_config(config, blob), true);
+  check_eq!(context, config.get_core_count(), CORES.len());
+
+  for (core, (_, _, expected)) in CORES.iter().enumerate() {
+    let Some(index) = config.get_core_index(primary_id + core) else {
+      mark_fail!(context, "Core not found.");
+      continue;
+    };
+
+    let cores = config.get_cores();
+    check_eq!(context, cores[index].get_capacity(), *expected);
+  }
+}
+
+/// Test cores with multiple compatible strings.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The known core type is the second string of each list. The properties
+/// following the compatible property must still be read. The second core's
+/// list is too long for the core type, so its last string is dropped rather
+/// than truncated to a shorter known core type.
+fn test_multi_compatible(context: &mut test::TestContext) {
+  const CORES: [(&str, u32); 2] = [
+    ("vendor,custom-a72\0arm,cortex-a72\0arm,armv8", 1024),
+    (
+      "vendor,long-custom-core-name-that-fills-the-buffer\0arm,cortex-a72",
+      cpu::DEFAULT_CORE_CAPACITY,
+    ),
+  ];
+
+  let config = unsafe { ptr::addr_of_mut!(TEST_CORE_CONFIG).as_mut().unwrap() };
+  let primary_id = cpu::get_id();
+  let mut builder = dtb::DtbBuilder::new();
+
+  builder
+    .begin_node("")
+    .begin_node("cpus")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 0)
+    .prop_str("enable-method", "spin-table");
+
+  for (core, (compatible, _)) in CORES.iter().enumerate() {
+    builder
+      .begin_node(CPU_NAMES[core])
+      .prop_str("compatible", compatible)
+      .prop_u32("reg", (primary_id + core) as u32)
+      .prop_u32("phandle", core as u32 + 1)
+      .end_node();
+  }
+
+  let blob = builder.end_node().end_node().finish();
+
+  check_eq!(context, get_core_config(config, blob), true);
+  check_eq!(context, config.get_core_count(), CORES.len());
+
+  for (core, (_, expected)) in CORES.iter().enumerate() {
+    let Some(index) = config.get_core_index(primary_id + core) else {
+      mark_fail!(context, "Core not found.");
+      continue;
+    };
+
+    let cores = config.get_cores();
+    check_eq!(context, cores[index].phandle, core as u32 + 1);
+    check_eq!(context, cores[index].get_capacity(), *expected);
+  }
+
+  let Some(index) = config.get_core_index(primary_id) else {
+    return;
+  };
+
+  let core = &config.get_cores()[index];
+  let is_primary_type = core.get_primary_core_type() == b"vendor,custom-a72";
+  check_eq!(context, is_primary_type, true);
+  check_eq!(context, cpu::compatible_contains(core.get_core_type(), b"arm,armv8"), true);
+
+  let Some(index) = config.get_core_index(primary_id + 1) else {
+    return;
+  };
+
+  let core_type = config.get_cores()[index].get_core_type();
+  check_eq!(context, cpu::compatible_contains(core_type, b"arm,cortex-a72"), false);
+  check_eq!(context, cpu::compatible_contains(core_type, b"arm,cortex-a7"), false);
+}
+
+/// Test that disabled cores are ignored.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The third core is disabled and the fourth core explicitly enabled. The
+/// disabled core must not be counted or mapped to an index, and the topology
+/// of the remaining cores must still be read.
+fn test_disabled_core(context: &mut test::TestContext) {
+  const STATUS: [Option<&str>; 4] = [None, None, Some("disabled"), Some("okay")];
+
+  let config = unsafe { ptr::addr_of_mut!(TEST_CORE_CONFIG).as_mut().unwrap() };
+  let primary_id = cpu::get_id();
+  let mut builder = dtb::DtbBuilder::new();
+
+  builder
+    .begin_node("")
+    .begin_node("cpus")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 0)
+    .prop_str("enable-method", "spin-table");
+
+  for (core, status) in STATUS.iter().enumerate() {
+    builder
+      .begin_node(CPU_NAMES[core])
+      .prop_u32("reg", (primary_id + core) as u32);
+
+    if let Some(status) = status {
+      builder.prop_str("status", status);
+    }
+
+    builder.end_node();
+  }
+
+  let blob = builder.end_node().end_node().finish();
+
+  check_eq!(context, get_core_config(config, blob), true);
+  check_eq!(context, config.get_core_count(), STATUS.len() - 1);
+  check_none!(context, config.get_core_index(primary_id + 2));
+  check_not_none!(context, config.get_core_index(primary_id + 3));
+}
@@ -0,0 +1,77 @@
+//! ARM Common DTB Device Tree Builder
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::{dtb_cpu, dtb_memory};
+use crate::arch::device_tree::DeviceTree;
+use crate::arch::memory::MemoryRangeHandler;
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+impl DeviceTree {
+  /// Build a system device tree from a DTB.
+  ///
+  /// # Parameters
+  ///
+  /// * `blob_vaddr` - The DTB virtual address.
+  /// * `handler` - The memory range handler.
+  ///
+  /// # Description
+  ///
+  /// Convenience wrapper around `DeviceTree::read_blob()`.
+  ///
+  ///   NOTE: A device tree is large on 64-bit platforms. Prefer
+  ///         `read_blob()` on a static device tree when stack space is
+  ///         limited.
+  ///
+  /// # Returns
+  ///
+  /// A fully-populated device tree, or None if either the core configuration or
+  /// the memory layout could not be read.
+  pub fn from_blob(blob_vaddr: usize, handler: &dyn MemoryRangeHandler) -> Option<Self> {
+    let mut tree = Self::new();
+
+    if !tree.read_blob(blob_vaddr, handler) {
+      return None;
+    }
+
+    Some(tree)
+  }
+
+  /// Populate the device tree from a DTB.
+  ///
+  /// # Parameters
+  ///
+  /// * `blob_vaddr` - The DTB virtual address.
+  /// * `handler` - The memory range handler.
+  ///
+  /// # Description
+  ///
+  /// Runs the CPU scanner and the memory scanner against the DTB. Any existing
+  /// core and memory configuration is discarded.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes the caller is on the primary core.
+  ///
+  /// # Returns
+  ///
+  /// True if both the core configuration and the memory layout were read,
+  /// false otherwise.
+  pub fn read_blob(&mut self, blob_vaddr: usize, handler: &dyn MemoryRangeHandler) -> bool {
+    if !dtb_cpu::get_core_config(self.get_core_config_mut(), blob_vaddr) {
+      return false;
+    }
+
+    let mem_config = self.get_memory_config_mut();
+    mem_config.clear();
+
+    dtb_memory::get_memory_layout(mem_config, handler, blob_vaddr).is_ok()
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
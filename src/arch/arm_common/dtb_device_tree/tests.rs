@@ -0,0 +1,136 @@
+//! ARM Common DTB Device Tree Tests
+
+use crate::arch::cpu;
+use crate::arch::device_tree::DeviceTree;
+use crate::arch::memory::{MemoryConfig, MemoryRange, MemoryRangeHandler, MemoryZone};
+use crate::debug_print;
+use crate::test::{self, dtb};
+use crate::{check_eq, check_optional, execute_test};
+use core::ptr;
+
+/// Test memory base and size.
+const TEST_MEM_BASE: u32 = 0x0;
+const TEST_MEM_SIZE: u32 = 0x1000_0000;
+
+/// Test release address for the spin table enable method.
+const TEST_RELEASE_ADDR: u32 = 0xd8;
+
+/// The device tree is too large to build on the kernel stack on 64-bit
+/// platforms.
+static mut TEST_DEVICE_TREE: DeviceTree = DeviceTree::new();
+
+/// Tags all ranges as linear memory.
+struct TestRangeHandler {}
+
+impl MemoryRangeHandler for TestRangeHandler {
+  /// See `MemoryRangeHandler::handle_range()`.
+  fn handle_range(&self, config: &mut MemoryConfig, base: usize, size: usize) {
+    config.insert_range(MemoryRange {
+      tag: MemoryZone::LinearMemoryZone,
+      base,
+      size,
+    });
+  }
+}
+
+/// Run device tree tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_read_blob);
+  execute_test!(context, test_read_invalid_blob);
+}
+
+/// Build a sample DTB with two cores and one memory node.
+///
+/// # Parameters
+///
+/// * `builder` - The DTB builder.
+///
+/// # Description
+///
+/// The first core uses the current core's ID so that the CPU scanner finds the
+/// primary core. The second core's ID follows the first.
+///
+/// # Returns
+///
+/// The address of the DTB.
+fn build_sample_blob(builder: &mut dtb::DtbBuilder) -> usize {
+  let primary_id = cpu::get_id() as u32;
+
+  builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("cpus")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 0)
+    .prop_str("enable-method", "spin-table")
+    .begin_node("cpu@0")
+    .prop_str("compatible", "arm,cortex-a53")
+    .prop_u32("reg", primary_id)
+    .prop_u32("cpu-release-addr", TEST_RELEASE_ADDR)
+    .end_node()
+    .begin_node("cpu@1")
+    .prop_str("compatible", "arm,cortex-a53")
+    .prop_u32("reg", primary_id + 1)
+    .prop_u32("cpu-release-addr", TEST_RELEASE_ADDR)
+    .end_node()
+    .end_node()
+    .begin_node("memory@0")
+    .prop_str("device_type", "memory")
+    .prop_cells("reg", &[TEST_MEM_BASE, TEST_MEM_SIZE])
+    .end_node()
+    .end_node()
+    .finish()
+}
+
+/// Test building a device tree from a sample DTB.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_read_blob(context: &mut test::TestContext) {
+  let tree = unsafe { ptr::addr_of_mut!(TEST_DEVICE_TREE).as_mut().unwrap() };
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = build_sample_blob(&mut builder);
+
+  check_eq!(context, tree.read_blob(blob, &TestRangeHandler {}), true);
+
+  let cores = tree.get_core_config();
+  let primary_id = cpu::get_id();
+  check_eq!(context, cores.get_core_count(), 2);
+  check_optional!(context, cores.get_core_index(primary_id), 0);
+  check_optional!(context, cores.get_core_index(primary_id + 1), 1);
+
+  for core in cores.get_cores() {
+    check_eq!(context, core.get_release_addr(), TEST_RELEASE_ADDR as usize);
+  }
+
+  let ranges = tree.get_memory_config().get_ranges();
+  check_eq!(context, ranges.len(), 1);
+  check_eq!(context, ranges[0].base, TEST_MEM_BASE as usize);
+  check_eq!(context, ranges[0].size, TEST_MEM_SIZE as usize);
+
+  // Reading the blob again must replace, not append to, the configuration.
+  check_eq!(context, tree.read_blob(blob, &TestRangeHandler {}), true);
+  check_eq!(context, tree.get_core_config().get_core_count(), 2);
+  check_eq!(context, tree.get_memory_config().get_ranges().len(), 1);
+}
+
+/// Test that an invalid DTB does not produce a device tree.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_read_invalid_blob(context: &mut test::TestContext) {
+  let tree = unsafe { ptr::addr_of_mut!(TEST_DEVICE_TREE).as_mut().unwrap() };
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = build_sample_blob(&mut builder);
+  builder.set_header_field(0, 0);
+
+  check_eq!(context, tree.read_blob(blob, &TestRangeHandler {}), false);
+  check_eq!(context, tree.read_blob(0, &TestRangeHandler {}), false);
+}
@@ -1,9 +1,24 @@
 //! ARM Common DTB Memory Scanner
 
+#[cfg(feature = "module_tests")]
+mod tests;
+
 use crate::arch::memory::{MemoryConfig, MemoryRange, MemoryRangeHandler, MemoryZone};
 use crate::support::{dtb, hash, hash_map, range, range_set};
+#[cfg(feature = "module_tests")]
+use crate::test;
 use core::cmp::{self, Ordering};
 
+/// Error value for reading the memory layout.
+pub enum MemoryLayoutError {
+  /// The DTB could not be read.
+  InvalidDtb,
+  /// The DTB does not describe any usable memory.
+  NoMemory,
+  /// The DTB describes more memory ranges than the configuration can hold.
+  TooManyRanges,
+}
+
 /// Tags for expected properties and values.
 enum StringTag {
   DtbPropAddressCells,
@@ -21,6 +36,7 @@ struct DtbMemoryScanner<'mem> {
   string_map: StringMap<'mem>,
   addr_cells: u32,
   size_cells: u32,
+  overflow: bool,
 }
 
 impl<'mem> DtbMemoryScanner<'mem> {
@@ -41,6 +57,7 @@ impl<'mem> DtbMemoryScanner<'mem> {
       string_map: Self::build_string_map(),
       addr_cells: 0,
       size_cells: 0,
+      overflow: false,
     }
   }
 
@@ -106,14 +123,23 @@ impl<'mem> DtbMemoryScanner<'mem> {
   /// # Parameters
   ///
   /// * `reader` - The DTB reader.
+  /// * `name` - The name of the device node.
   /// * `cursor` - The cursor pointing to the device node.
   ///
+  /// # Description
+  ///
+  /// A node is a memory device if its `device_type` property is "memory". If
+  /// the node does not have a `device_type` property, the node is a memory
+  /// device if its name is `memory@<unit-address>`. Disabled memory devices are
+  /// ignored.
+  ///
   /// # Returns
   ///
   /// Returns Ok if able to read the device node, otherwise a DTB error.
   fn scan_device_node(
     &mut self,
     reader: &dtb::DtbReader,
+    name: &[u8],
     cursor: &dtb::DtbCursor,
   ) -> Result<(), dtb::DtbError> {
     let mut tmp_cursor = *cursor;
@@ -156,14 +182,13 @@ impl<'mem> DtbMemoryScanner<'mem> {
       reader.skip_and_align(header.size, &mut tmp_cursor);
     }
 
-    match dev_type {
-      Some((pos, size)) => {
-        if !self.check_device_type(size, reader, &pos) {
-          return Ok(());
-        }
-      }
+    let is_memory = match dev_type {
+      Some((pos, size)) => self.check_device_type(size, reader, &pos),
+      _ => name.starts_with(b"memory@"),
+    };
 
-      _ => return Ok(()),
+    if !is_memory || !reader.node_is_enabled(cursor) {
+      return Ok(());
     }
 
     match reg {
@@ -272,27 +297,36 @@ impl<'mem> DtbMemoryScanner<'mem> {
       self
         .handler
         .handle_range(self.config, base as usize, size as usize);
+
+      // The handler may insert any number of ranges. If the configuration
+      // dropped any of them, flag the overflow rather than silently losing
+      // memory.
+      if self.config.get_dropped_count() > 0 {
+        self.overflow = true;
+        break;
+      }
     }
 
     Ok(())
   }
 }
 
-impl<'mem> dtb::DtbScanner for DtbMemoryScanner<'mem> {
+impl<'mem, 'blob> dtb::DtbScanner<'blob> for DtbMemoryScanner<'mem> {
   /// See `dtb::DtbScanner::scan_node()`
   fn scan_node(
     &mut self,
-    reader: &dtb::DtbReader,
+    reader: &dtb::DtbReader<'blob>,
     name: &[u8],
     cursor: &dtb::DtbCursor,
   ) -> Result<bool, dtb::DtbError> {
     if name.len() == 0 {
       _ = self.scan_root_node(reader, cursor)?;
     } else {
-      _ = self.scan_device_node(reader, cursor)?;
+      _ = self.scan_device_node(reader, name, cursor)?;
     }
 
-    Ok(true)
+    // Stop scanning if the configuration overflowed.
+    Ok(!self.overflow)
   }
 }
 
@@ -304,37 +338,53 @@ impl<'mem> dtb::DtbScanner for DtbMemoryScanner<'mem> {
 /// * `handler` - The memory range handler.
 /// * `blob` - The DTB address.
 ///
+/// # Description
+///
+/// If the configuration overflows, the configuration's dropped range count
+/// reports the number of ranges lost before scanning stopped.
+///
 /// # Assumptions
 ///
 /// Assumes the configuration is empty.
 ///
 /// # Returns
 ///
-/// True if able to read the memory configuration and at least one valid memory
-/// range is provided by the SoC, false otherwise.
+/// Ok if able to read the memory configuration and at least one valid memory
+/// range is provided by the SoC. Otherwise, TooManyRanges if the configuration
+/// cannot hold all of the ranges, NoMemory if there are no valid ranges, or
+/// InvalidDtb if the DTB could not be read.
 pub fn get_memory_layout(
   config: &mut MemoryConfig,
   handler: &dyn MemoryRangeHandler,
   blob: usize,
-) -> bool {
+) -> Result<(), MemoryLayoutError> {
   debug_assert!(config.is_empty());
 
   let mut scanner = DtbMemoryScanner::new(config, handler);
 
   let reader = match dtb::DtbReader::new(blob) {
     Ok(r) => r,
-    _ => return false,
+    _ => return Err(MemoryLayoutError::InvalidDtb),
   };
 
   if !reader.scan(&mut scanner).is_ok() {
-    return false;
+    return Err(MemoryLayoutError::InvalidDtb);
+  }
+
+  if scanner.overflow {
+    return Err(MemoryLayoutError::TooManyRanges);
   }
 
   config.trim_ranges();
 
   if config.is_empty() {
-    return false;
+    return Err(MemoryLayoutError::NoMemory);
   }
 
-  true
+  Ok(())
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
 }
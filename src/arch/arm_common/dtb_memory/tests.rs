@@ -0,0 +1,330 @@
+//! ARM Common DTB Memory Scanner Tests
+
+use super::{MemoryLayoutError, get_memory_layout};
+use crate::arch::memory::{
+  MAX_MEM_RANGES, MemoryConfig, MemoryRange, MemoryRangeHandler, MemoryZone,
+};
+use crate::debug_print;
+use crate::test::{self, dtb};
+use crate::{check_eq, check_no_panic, execute_test};
+use core::ptr;
+
+/// Test memory ranges. The ranges are deliberately not adjacent.
+const TEST_RANGES: [(u32, u32); 2] = [(0x0, 0x1000_0000), (0x8000_0000, 0x2000_0000)];
+
+/// Test SRAM range.
+const TEST_SRAM_BASE: u32 = 0x4000_0000;
+const TEST_SRAM_SIZE: u32 = 0x10_0000;
+
+/// Number of pieces `SplittingRangeHandler` splits each range into.
+const SPLIT_COUNT: usize = 4;
+
+/// The memory configuration is too large to build on the kernel stack.
+static mut TEST_MEMORY_CONFIG: MemoryConfig = MemoryConfig::new(MemoryZone::InvalidZone);
+
+/// Tags all ranges as linear memory.
+struct TestRangeHandler {}
+
+impl MemoryRangeHandler for TestRangeHandler {
+  /// See `MemoryRangeHandler::handle_range()`.
+  fn handle_range(&self, config: &mut MemoryConfig, base: usize, size: usize) {
+    config.insert_range(MemoryRange {
+      tag: MemoryZone::LinearMemoryZone,
+      base,
+      size,
+    });
+  }
+}
+
+/// Splits each range into equal pieces to fill the configuration quickly.
+struct SplittingRangeHandler {}
+
+impl MemoryRangeHandler for SplittingRangeHandler {
+  /// See `MemoryRangeHandler::handle_range()`.
+  fn handle_range(&self, config: &mut MemoryConfig, base: usize, size: usize) {
+    let piece = size / SPLIT_COUNT;
+
+    for i in 0..SPLIT_COUNT {
+      config.insert_range(MemoryRange {
+        tag: MemoryZone::LinearMemoryZone,
+        base: base + i * piece,
+        size: piece,
+      });
+    }
+  }
+}
+
+/// Get the test memory configuration.
+///
+/// # Returns
+///
+/// The cleared test memory configuration.
+fn get_test_config() -> &'static mut MemoryConfig {
+  let config = unsafe { ptr::addr_of_mut!(TEST_MEMORY_CONFIG).as_mut().unwrap() };
+  config.clear();
+  config
+}
+
+/// Run memory scanner tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_unit_address_nodes);
+  execute_test!(context, test_device_type_mismatch);
+  execute_test!(context, test_no_memory);
+  execute_test!(context, test_too_many_ranges);
+  execute_test!(context, test_disabled_memory);
+  execute_test!(context, test_zero_size_ranges);
+}
+
+/// Check that the memory configuration matches the test ranges.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `config` - The memory configuration.
+fn check_test_ranges(context: &mut test::TestContext, config: &MemoryConfig) {
+  let ranges = config.get_ranges();
+  check_eq!(context, ranges.len(), TEST_RANGES.len());
+
+  for (range, (base, size)) in ranges.iter().zip(TEST_RANGES) {
+    check_eq!(context, range.base, base as usize);
+    check_eq!(context, range.size, size as usize);
+  }
+}
+
+/// Test collecting `memory@<unit-address>` nodes without `device_type`.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The SRAM node has a `reg` property, but is neither named as a memory node
+/// nor has a `device_type` property, so it must be ignored.
+fn test_unit_address_nodes(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("memory@0")
+    .prop_cells("reg", &[TEST_RANGES[0].0, TEST_RANGES[0].1])
+    .end_node()
+    .begin_node("sram@40000000")
+    .prop_cells("reg", &[TEST_SRAM_BASE, TEST_SRAM_SIZE])
+    .end_node()
+    .begin_node("memory@80000000")
+    .prop_cells("reg", &[TEST_RANGES[1].0, TEST_RANGES[1].1])
+    .end_node()
+    .end_node()
+    .finish();
+
+  let config = get_test_config();
+  let ok = get_memory_layout(config, &TestRangeHandler {}, blob).is_ok();
+  check_eq!(context, ok, true);
+  check_test_ranges(context, config);
+}
+
+/// Test that `device_type` takes precedence over the node name.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A `memory@<unit-address>` node with a `device_type` other than "memory" is
+/// ignored, and a node with a `device_type` of "memory" is collected regardless
+/// of its name.
+fn test_device_type_mismatch(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("ram")
+    .prop_str("device_type", "memory")
+    .prop_cells("reg", &[TEST_RANGES[0].0, TEST_RANGES[0].1])
+    .end_node()
+    .begin_node("memory@40000000")
+    .prop_str("device_type", "sram")
+    .prop_cells("reg", &[TEST_SRAM_BASE, TEST_SRAM_SIZE])
+    .end_node()
+    .begin_node("memory@80000000")
+    .prop_str("device_type", "memory")
+    .prop_cells("reg", &[TEST_RANGES[1].0, TEST_RANGES[1].1])
+    .end_node()
+    .end_node()
+    .finish();
+
+  let config = get_test_config();
+  let ok = get_memory_layout(config, &TestRangeHandler {}, blob).is_ok();
+  check_eq!(context, ok, true);
+  check_test_ranges(context, config);
+}
+
+/// Test that a DTB without memory nodes reports no memory.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_no_memory(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("sram@40000000")
+    .prop_cells("reg", &[TEST_SRAM_BASE, TEST_SRAM_SIZE])
+    .end_node()
+    .end_node()
+    .finish();
+
+  let config = get_test_config();
+  let result = get_memory_layout(config, &TestRangeHandler {}, blob);
+  check_eq!(context, matches!(result, Err(MemoryLayoutError::NoMemory)), true);
+}
+
+/// Test that overflowing the configuration reports too many ranges.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The memory node has one more range than fits in the configuration once each
+/// range is split by the handler.
+fn test_too_many_ranges(context: &mut test::TestContext) {
+  const RANGE_SIZE: u32 = 0x10_0000;
+  const RANGE_COUNT: usize = (MAX_MEM_RANGES / SPLIT_COUNT) + 1;
+
+  let config = get_test_config();
+  check_eq!(context, config.capacity(), MAX_MEM_RANGES);
+
+  let mut cells = [0u32; RANGE_COUNT * 2];
+  let mut builder = dtb::DtbBuilder::new();
+
+  // Leave a gap between ranges so that they cannot be merged.
+  for i in 0..RANGE_COUNT {
+    cells[i * 2] = i as u32 * RANGE_SIZE * 2;
+    cells[i * 2 + 1] = RANGE_SIZE;
+  }
+
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("memory@0")
+    .prop_str("device_type", "memory")
+    .prop_cells("reg", &cells)
+    .end_node()
+    .end_node()
+    .finish();
+
+  let result = get_memory_layout(config, &SplittingRangeHandler {}, blob);
+  check_eq!(context, matches!(result, Err(MemoryLayoutError::TooManyRanges)), true);
+  check_eq!(context, config.is_full(), true);
+
+  // Scanning stops at the first range that does not fit.
+  check_eq!(context, config.get_dropped_count(), SPLIT_COUNT);
+}
+
+/// Test that disabled memory nodes are ignored.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The SRAM node is a memory node by both name and device type, but is
+/// disabled.
+fn test_disabled_memory(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("memory@0")
+    .prop_cells("reg", &[TEST_RANGES[0].0, TEST_RANGES[0].1])
+    .prop_str("status", "okay")
+    .end_node()
+    .begin_node("memory@40000000")
+    .prop_str("device_type", "memory")
+    .prop_cells("reg", &[TEST_SRAM_BASE, TEST_SRAM_SIZE])
+    .prop_str("status", "disabled")
+    .end_node()
+    .begin_node("memory@80000000")
+    .prop_cells("reg", &[TEST_RANGES[1].0, TEST_RANGES[1].1])
+    .end_node()
+    .end_node()
+    .finish();
+
+  let config = get_test_config();
+  let ok = get_memory_layout(config, &TestRangeHandler {}, blob).is_ok();
+  check_eq!(context, ok, true);
+  check_test_ranges(context, config);
+}
+
+/// Test that zero-size ranges are dropped.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first memory node has a zero-size pair between its valid pairs, and the
+/// SRAM node only has a zero-size pair. The zero-size pairs must be dropped
+/// without panicking while scanning, trimming, or excluding ranges, including
+/// excluding a zero-size range such as an empty initial ramdisk.
+fn test_zero_size_ranges(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("memory@0")
+    .prop_cells(
+      "reg",
+      &[
+        TEST_RANGES[0].0,
+        TEST_RANGES[0].1,
+        TEST_SRAM_BASE,
+        0,
+        TEST_RANGES[1].0,
+        TEST_RANGES[1].1,
+      ],
+    )
+    .end_node()
+    .begin_node("memory@40000000")
+    .prop_str("device_type", "memory")
+    .prop_cells("reg", &[TEST_SRAM_BASE, 0])
+    .end_node()
+    .end_node()
+    .finish();
+
+  let config = get_test_config();
+  let mut ok = false;
+
+  check_no_panic!(context, || {
+    ok = get_memory_layout(config, &TestRangeHandler {}, blob).is_ok();
+  });
+  check_eq!(context, ok, true);
+  check_test_ranges(context, config);
+
+  let empty = MemoryRange {
+    tag: MemoryZone::InvalidZone,
+    base: TEST_RANGES[0].0 as usize,
+    size: 0,
+  };
+
+  check_no_panic!(context, || ok = config.exclude_range(&empty).is_ok());
+  check_eq!(context, ok, true);
+  check_test_ranges(context, config);
+}
@@ -0,0 +1,254 @@
+//! ARM Common DTB MMIO Scanner
+//!
+//! Drivers locate their physical register ranges by compatible string rather
+//! than hard-coding platform-specific base addresses. The kernel must still map
+//! a range into the kernel's address space before a driver can access it.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::support::dtb;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::cmp;
+
+/// Maximum nesting depth of nodes searched below the root node.
+const MAX_NODE_DEPTH: usize = 16;
+
+/// Cell counts assumed when a node's parent does not specify them.
+///
+/// https://devicetree-specification.readthedocs.io/en/stable/devicetree-basics.html#address-cells-and-size-cells
+const DEFAULT_ADDR_CELLS: u32 = 2;
+const DEFAULT_SIZE_CELLS: u32 = 1;
+
+/// The properties of a node used to find a device.
+struct NodeProperties {
+  addr_cells: Option<u32>,
+  size_cells: Option<u32>,
+  compatible: Option<(dtb::DtbCursor, usize)>,
+  reg: Option<(dtb::DtbCursor, usize)>,
+}
+
+/// Read the properties of a node used to find a device.
+///
+/// # Parameters
+///
+/// * `reader` - The DTB reader.
+/// * `cursor` - The cursor pointing to the node.
+///
+/// # Description
+///
+/// The compatible and reg properties are saved as the position and size of
+/// their values to read once the node is known to match.
+///
+/// # Returns
+///
+/// The node properties, or None if the cell properties are invalid.
+fn read_node_properties(
+  reader: &dtb::DtbReader,
+  cursor: &dtb::DtbCursor,
+) -> Option<NodeProperties> {
+  let mut tmp_cursor = *cursor;
+  let mut props = NodeProperties {
+    addr_cells: None,
+    size_cells: None,
+    compatible: None,
+    reg: None,
+  };
+
+  while let Some(header) = reader.get_next_property(&mut tmp_cursor) {
+    let mut value_cursor = tmp_cursor;
+
+    if header.name.cmp(b"#address-cells") == cmp::Ordering::Equal {
+      props.addr_cells = Some(reader.get_u32(&mut value_cursor)?);
+    } else if header.name.cmp(b"#size-cells") == cmp::Ordering::Equal {
+      props.size_cells = Some(reader.get_u32(&mut value_cursor)?);
+    } else if header.name.cmp(b"compatible") == cmp::Ordering::Equal {
+      props.compatible = Some((tmp_cursor, header.size));
+    } else if header.name.cmp(b"reg") == cmp::Ordering::Equal {
+      props.reg = Some((tmp_cursor, header.size));
+    }
+
+    reader.skip_and_align(header.size, &mut tmp_cursor);
+  }
+
+  Some(props)
+}
+
+/// Check a compatible property for a compatible string.
+///
+/// # Parameters
+///
+/// * `reader` - The DTB reader.
+/// * `prop` - The position and size of the compatible property.
+/// * `compatible` - The compatible string, excluding the null-terminator.
+///
+/// # Description
+///
+/// The compatible property is a list of null-terminated strings. Any string in
+/// the list may match, but only exactly.
+///
+/// # Returns
+///
+/// True if the list contains the compatible string, false otherwise.
+fn check_compatible(
+  reader: &dtb::DtbReader,
+  prop: &(dtb::DtbCursor, usize),
+  compatible: &[u8],
+) -> bool {
+  let (mut tmp_cursor, mut remaining) = *prop;
+
+  while remaining > 0 {
+    let Some(entry) = reader.get_null_terminated_u8_slice(&mut tmp_cursor) else {
+      return false;
+    };
+
+    if entry.cmp(compatible) == cmp::Ordering::Equal {
+      return true;
+    }
+
+    // Step over the null-terminator to the next string.
+    reader.skip(1, &mut tmp_cursor);
+    remaining = remaining.saturating_sub(entry.len() + 1);
+  }
+
+  false
+}
+
+/// Read the first range from a reg property.
+///
+/// # Parameters
+///
+/// * `reader` - The DTB reader.
+/// * `prop` - The position and size of the reg property.
+/// * `addr_cells` - The parent's address cell count.
+/// * `size_cells` - The parent's size cell count.
+///
+/// # Returns
+///
+/// A tuple with the base and size, or None if the range is invalid or not
+/// addressable on the platform.
+fn read_first_range(
+  reader: &dtb::DtbReader,
+  prop: &(dtb::DtbCursor, usize),
+  addr_cells: u32,
+  size_cells: u32,
+) -> Option<(usize, usize)> {
+  let (mut tmp_cursor, prop_size) = *prop;
+  let pair_size = dtb::DtbReader::get_reg_pair_size(addr_cells, size_cells);
+
+  if pair_size == 0 || prop_size < pair_size {
+    return None;
+  }
+
+  let (base, size) = reader.get_reg_pair(addr_cells, size_cells, &mut tmp_cursor)?;
+  let base = usize::try_from(base).ok()?;
+  let size = usize::try_from(size).ok()?;
+
+  if size == 0 || base.checked_add(size - 1).is_none() {
+    return None;
+  }
+
+  Some((base, size))
+}
+
+/// Search a node and its descendants for a compatible device.
+///
+/// # Parameters
+///
+/// * `reader` - The DTB reader.
+/// * `cursor` - The cursor pointing to the node.
+/// * `compatible` - The compatible string.
+/// * `addr_cells` - The address cell count inherited from the node's ancestors.
+/// * `size_cells` - The size cell count inherited from the node's ancestors.
+/// * `depth` - The depth of the node below the root node.
+///
+/// # Description
+///
+/// The node's children use the node's cell counts, or the inherited cell
+/// counts if the node does not specify them. Nodes are searched depth-first
+/// in DTB order.
+///
+/// # Returns
+///
+/// The first range of the first enabled, compatible device with a valid reg
+/// property, or None if there is no such device.
+fn find_in_node(
+  reader: &dtb::DtbReader,
+  cursor: &dtb::DtbCursor,
+  compatible: &[u8],
+  addr_cells: u32,
+  size_cells: u32,
+  depth: usize,
+) -> Option<(usize, usize)> {
+  let props = read_node_properties(reader, cursor)?;
+
+  if let Some(prop) = &props.compatible
+    && check_compatible(reader, prop, compatible)
+    && reader.node_is_enabled(cursor)
+    && let Some(reg) = &props.reg
+    && let Some(range) = read_first_range(reader, reg, addr_cells, size_cells)
+  {
+    return Some(range);
+  }
+
+  if depth >= MAX_NODE_DEPTH {
+    return None;
+  }
+
+  let child_addr_cells = props.addr_cells.unwrap_or(addr_cells);
+  let child_size_cells = props.size_cells.unwrap_or(size_cells);
+  let mut child = reader.get_first_child_node(cursor);
+
+  while let Some(child_cursor) = child {
+    let range = find_in_node(
+      reader,
+      &child_cursor,
+      compatible,
+      child_addr_cells,
+      child_size_cells,
+      depth + 1,
+    );
+
+    if range.is_some() {
+      return range;
+    }
+
+    child = reader.get_next_sibling_node(&child_cursor);
+  }
+
+  None
+}
+
+/// Find the physical register range of a memory-mapped device.
+///
+/// # Parameters
+///
+/// * `blob_vaddr` - The DTB blob virtual address.
+/// * `compatible` - The compatible string, excluding the null-terminator, e.g.
+///   `b"arm,pl011"`.
+///
+/// # Description
+///
+/// Searches for the first enabled node whose compatible list contains the
+/// compatible string and reads the first range from its reg property. A node that does
+/// not specify its cell counts inherits them from its nearest ancestor that
+/// does.
+///
+///   NOTE: The addresses are not translated through any parent bus ranges.
+///
+/// # Returns
+///
+/// A tuple with the base address and size, or None if the DTB could not be
+/// read or there is no enabled, compatible device with a valid reg property.
+pub fn find_mmio(blob_vaddr: usize, compatible: &[u8]) -> Option<(usize, usize)> {
+  let reader = dtb::DtbReader::new(blob_vaddr).ok()?;
+  let root = reader.get_root_node()?;
+
+  find_in_node(&reader, &root, compatible, DEFAULT_ADDR_CELLS, DEFAULT_SIZE_CELLS, 0)
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,218 @@
+//! ARM Common DTB MMIO Scanner Tests
+
+use super::find_mmio;
+use crate::debug_print;
+use crate::test::{self, dtb};
+use crate::{check_eq, check_none, check_not_none, execute_test};
+
+/// Test UART register range.
+const TEST_UART_BASE: u32 = 0x0900_0000;
+const TEST_UART_SIZE: u32 = 0x1000;
+
+/// Test timer register range. The base is above 4 GiB.
+const TEST_TIMER_BASE_HI: u32 = 0x1;
+const TEST_TIMER_BASE_LO: u32 = 0x2000_0000;
+const TEST_TIMER_SIZE: u32 = 0x100;
+
+/// Test watchdog register range.
+const TEST_WDT_BASE: u32 = 0x4000_0000;
+const TEST_WDT_SIZE: u32 = 0x20;
+
+/// Run MMIO scanner tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_compatible_mid_list);
+  execute_test!(context, test_inherited_cells);
+  execute_test!(context, test_no_match);
+}
+
+/// Check a found register range.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `range` - The range found.
+/// * `base` - The expected base address.
+/// * `size` - The expected size.
+fn check_range(
+  context: &mut test::TestContext,
+  range: Option<(usize, usize)>,
+  base: usize,
+  size: usize,
+) {
+  check_not_none!(context, range);
+
+  let Some((found_base, found_size)) = range else {
+    return;
+  };
+
+  check_eq!(context, found_base, base);
+  check_eq!(context, found_size, size);
+}
+
+/// Test finding a device by a compatible string in the middle of its
+/// compatible list.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The device is preceded by a device with a different compatible string.
+/// Prefixes of a compatible string must not match.
+fn test_compatible_mid_list(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("watchdog@40000000")
+    .prop_str("compatible", "arm,sp805")
+    .prop_cells("reg", &[TEST_WDT_BASE, TEST_WDT_SIZE])
+    .end_node()
+    .begin_node("pl011@9000000")
+    .prop_cells("reg", &[TEST_UART_BASE, TEST_UART_SIZE])
+    .prop_bytes("compatible", b"vendor,uart\0arm,pl011\0arm,primecell\0")
+    .end_node()
+    .end_node()
+    .finish();
+
+  let uart_base = TEST_UART_BASE as usize;
+  let uart_size = TEST_UART_SIZE as usize;
+
+  check_range(context, find_mmio(blob, b"arm,pl011"), uart_base, uart_size);
+  check_range(context, find_mmio(blob, b"arm,primecell"), uart_base, uart_size);
+  check_range(
+    context,
+    find_mmio(blob, b"arm,sp805"),
+    TEST_WDT_BASE as usize,
+    TEST_WDT_SIZE as usize,
+  );
+
+  check_none!(context, find_mmio(blob, b"arm,pl01"));
+  check_none!(context, find_mmio(blob, b"arm"));
+}
+
+/// Test cell counts inherited by nested nodes.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The root node uses two address cells and two size cells. The bus node
+/// switches to one cell each, and the bridge below it does not specify cell
+/// counts, so the device under the bridge inherits the bus's cell counts. The
+/// device following the bus is back under the root's cell counts.
+///
+/// The timer is above 4 GiB, so it is only addressable on 64-bit platforms.
+fn test_inherited_cells(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 2)
+    .prop_u32("#size-cells", 2)
+    .begin_node("soc")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("watchdog@40000000")
+    .prop_str("compatible", "arm,sp805")
+    .prop_cells("reg", &[TEST_WDT_BASE, TEST_WDT_SIZE])
+    .end_node()
+    .begin_node("bridge")
+    .begin_node("pl011@9000000")
+    .prop_str("compatible", "arm,pl011")
+    .prop_cells("reg", &[TEST_UART_BASE, TEST_UART_SIZE])
+    .end_node()
+    .end_node()
+    .end_node()
+    .begin_node("timer@120000000")
+    .prop_str("compatible", "vendor,timer")
+    .prop_cells("reg", &[TEST_TIMER_BASE_HI, TEST_TIMER_BASE_LO, 0, TEST_TIMER_SIZE])
+    .end_node()
+    .end_node()
+    .finish();
+
+  check_range(
+    context,
+    find_mmio(blob, b"arm,sp805"),
+    TEST_WDT_BASE as usize,
+    TEST_WDT_SIZE as usize,
+  );
+  check_range(
+    context,
+    find_mmio(blob, b"arm,pl011"),
+    TEST_UART_BASE as usize,
+    TEST_UART_SIZE as usize,
+  );
+
+  let timer = find_mmio(blob, b"vendor,timer");
+  let timer_base = ((TEST_TIMER_BASE_HI as u64) << 32) | (TEST_TIMER_BASE_LO as u64);
+
+  match usize::try_from(timer_base) {
+    Ok(base) => check_range(context, timer, base, TEST_TIMER_SIZE as usize),
+    Err(_) => check_none!(context, timer),
+  }
+}
+
+/// Test DTBs without a usable compatible device.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A compatible device without a reg property, with an empty range, or that is
+/// disabled is skipped in favor of the next compatible device.
+fn test_no_match(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("pl011@9000000")
+    .prop_str("compatible", "arm,pl011")
+    .end_node()
+    .begin_node("pl011@9001000")
+    .prop_str("compatible", "arm,pl011")
+    .prop_cells("reg", &[TEST_UART_BASE + TEST_UART_SIZE, 0])
+    .end_node()
+    .end_node()
+    .finish();
+
+  check_none!(context, find_mmio(blob, b"arm,pl011"));
+  check_none!(context, find_mmio(blob, b"arm,sp805"));
+
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("pl011@9000000")
+    .prop_str("compatible", "arm,pl011")
+    .end_node()
+    .begin_node("pl011@9001000")
+    .prop_str("compatible", "arm,pl011")
+    .prop_cells("reg", &[TEST_UART_BASE, TEST_UART_SIZE])
+    .prop_str("status", "disabled")
+    .end_node()
+    .begin_node("pl011@9002000")
+    .prop_str("compatible", "arm,pl011")
+    .prop_cells("reg", &[TEST_UART_BASE + TEST_UART_SIZE, TEST_UART_SIZE])
+    .end_node()
+    .end_node()
+    .finish();
+
+  check_range(
+    context,
+    find_mmio(blob, b"arm,pl011"),
+    (TEST_UART_BASE + TEST_UART_SIZE) as usize,
+    TEST_UART_SIZE as usize,
+  );
+}
@@ -0,0 +1,613 @@
+//! ARM Generic Interrupt Controller (GICv2) Driver
+//!
+//! https://developer.arm.com/documentation/ihi0048/latest/
+//!
+//! A GICv2 is split into a distributor, shared by all cores, and a banked CPU
+//! interface per core. The physical register ranges are discovered from the
+//! DTB with `get_physical_ranges()`. The kernel must map both ranges into the
+//! kernel's address space and provide the base virtual addresses to `init()`.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::support::{dtb, hash, hash_map};
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::{cmp, ptr};
+
+/// Distributor registers.
+const GICD_CTLR: usize = 0x000;
+const GICD_ISENABLER: usize = 0x100;
+const GICD_ICENABLER: usize = 0x180;
+const GICD_SGIR: usize = 0xf00;
+
+/// CPU interface registers.
+const GICC_CTLR: usize = 0x000;
+const GICC_PMR: usize = 0x004;
+const GICC_IAR: usize = 0x00c;
+const GICC_EOIR: usize = 0x010;
+
+/// Distributor and CPU interface enable bits.
+const CTLR_ENABLE: u32 = 1;
+
+/// Lowest priority mask. Allows interrupts of any priority to be signaled.
+const PMR_ALLOW_ALL: u32 = 0xff;
+
+/// The interrupt ID field of GICC_IAR.
+const IAR_ID_MASK: u32 = 0x3ff;
+
+/// The number of interrupt enable bits per GICD_ISENABLER / GICD_ICENABLER
+/// register.
+const IRQS_PER_ENABLE_REG: u32 = 32;
+
+/// The CPU target list field of GICD_SGIR. The target list filter field is left
+/// as 0 so that the interrupt is only forwarded to the listed CPU interfaces.
+const SGIR_TARGET_LIST_SHIFT: u32 = 16;
+
+/// The number of software-generated interrupts. SGIs use interrupt IDs 0-15.
+pub const MAX_SGIS: u32 = 16;
+
+/// The number of CPU interfaces a GICv2 can target with an SGI.
+pub const MAX_SGI_TARGETS: usize = 8;
+
+/// Interrupt IDs 1020 and above are special or reserved.
+pub const MAX_IRQS: u32 = 1020;
+
+/// The interrupt ID returned by `ack()` when there is no pending interrupt.
+pub const SPURIOUS_IRQ: u32 = 1023;
+
+/// Compatible strings for supported GICv2 implementations.
+const COMPATIBLE_GICS: [&[u8]; 2] = [b"arm,gic-400", b"arm,cortex-a15-gic"];
+
+/// Re-initialization guard.
+static mut INITIALIZED: bool = false;
+
+/// The system interrupt controller.
+static mut GIC: Gic = Gic::new(0, 0);
+
+/// Tags for expected properties.
+enum StringTag {
+  DtbPropAddressCells,
+  DtbPropSizeCells,
+  DtbPropCompatible,
+  DtbPropReg,
+}
+
+type StringMap = hash_map::HashMap<&'static [u8], StringTag, hash::BuildFnv1aHasher, 7>;
+
+/// The physical register ranges of a GICv2. Each range is a tuple with the
+/// base address and size.
+pub struct GicPhysicalRanges {
+  pub distributor: (usize, usize),
+  pub cpu_interface: (usize, usize),
+}
+
+/// Scans for a GICv2 node.
+struct DtbGicScanner {
+  ranges: Option<GicPhysicalRanges>,
+  string_map: StringMap,
+  addr_cells: u32,
+  size_cells: u32,
+}
+
+impl DtbGicScanner {
+  /// Construct a new DTB GIC scanner.
+  pub fn new() -> Self {
+    DtbGicScanner {
+      ranges: None,
+      string_map: Self::build_string_map(),
+      addr_cells: 0,
+      size_cells: 0,
+    }
+  }
+
+  /// Build a string map for the scanner.
+  ///
+  /// # Returns
+  ///
+  /// A new string map for the expected properties.
+  fn build_string_map() -> StringMap {
+    let mut map = StringMap::new(hash::BuildFnv1aHasher {});
+
+    map.insert("#address-cells".as_bytes(), StringTag::DtbPropAddressCells);
+    map.insert("#size-cells".as_bytes(), StringTag::DtbPropSizeCells);
+    map.insert("compatible".as_bytes(), StringTag::DtbPropCompatible);
+    map.insert("reg".as_bytes(), StringTag::DtbPropReg);
+
+    map
+  }
+
+  /// Reads the root cell configuration.
+  ///
+  /// # Parameters
+  ///
+  /// * `reader` - The DTB reader.
+  /// * `cursor` - The cursor pointing to the root node.
+  ///
+  /// # Returns
+  ///
+  /// Returns Ok if able to read the cell configuration, otherwise a DTB error.
+  fn scan_root_node(
+    &mut self,
+    reader: &dtb::DtbReader,
+    cursor: &dtb::DtbCursor,
+  ) -> Result<(), dtb::DtbError> {
+    let mut tmp_cursor = *cursor;
+
+    while let Some(header) = reader.get_next_property(&mut tmp_cursor) {
+      match self.string_map.find(header.name) {
+        Some(StringTag::DtbPropAddressCells) => {
+          self.addr_cells = reader
+            .get_u32(&mut tmp_cursor)
+            .ok_or(dtb::DtbError::InvalidDtb)?;
+        }
+
+        Some(StringTag::DtbPropSizeCells) => {
+          self.size_cells = reader
+            .get_u32(&mut tmp_cursor)
+            .ok_or(dtb::DtbError::InvalidDtb)?;
+        }
+
+        _ => reader.skip_and_align(header.size, &mut tmp_cursor),
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Scans a device node. If the device is a supported GIC, the function reads
+  /// the register ranges.
+  ///
+  /// # Parameters
+  ///
+  /// * `reader` - The DTB reader.
+  /// * `cursor` - The cursor pointing to the device node.
+  ///
+  /// # Returns
+  ///
+  /// Returns Ok if able to read the device node, otherwise a DTB error.
+  fn scan_device_node(
+    &mut self,
+    reader: &dtb::DtbReader,
+    cursor: &dtb::DtbCursor,
+  ) -> Result<(), dtb::DtbError> {
+    let mut tmp_cursor = *cursor;
+    let mut is_gic = false;
+
+    // Save the position and size of the reg property to read after checking
+    // the compatible property.
+    let mut reg: Option<(dtb::DtbCursor, usize)> = None;
+
+    while let Some(header) = reader.get_next_property(&mut tmp_cursor) {
+      match self.string_map.find(header.name) {
+        Some(StringTag::DtbPropCompatible) => {
+          is_gic = Self::check_compatible(header.size, reader, &tmp_cursor);
+        }
+
+        Some(StringTag::DtbPropReg) => reg = Some((tmp_cursor, header.size)),
+
+        _ => {}
+      }
+
+      reader.skip_and_align(header.size, &mut tmp_cursor);
+    }
+
+    if !is_gic {
+      return Ok(());
+    }
+
+    let Some((pos, size)) = reg else {
+      return Err(dtb::DtbError::InvalidDtb);
+    };
+
+    self.ranges = Some(self.read_ranges(size, reader, &pos)?);
+    Ok(())
+  }
+
+  /// Check a compatible property for a supported GIC.
+  ///
+  /// # Parameters
+  ///
+  /// * `prop_size` - The size of the compatible property.
+  /// * `reader` - The DTB reader.
+  /// * `cursor` - The current position in the DTB.
+  ///
+  /// # Description
+  ///
+  /// The compatible property is a list of null-terminated strings. The device
+  /// is a supported GIC if any of the strings is a supported GIC.
+  ///
+  /// # Returns
+  ///
+  /// True if the device is a supported GIC, false otherwise.
+  fn check_compatible(prop_size: usize, reader: &dtb::DtbReader, cursor: &dtb::DtbCursor) -> bool {
+    let mut tmp_cursor = *cursor;
+    let mut remaining = prop_size;
+
+    while remaining > 0 {
+      let Some(compatible) = reader.get_null_terminated_u8_slice(&mut tmp_cursor) else {
+        return false;
+      };
+
+      if COMPATIBLE_GICS
+        .iter()
+        .any(|gic| compatible.cmp(gic) == cmp::Ordering::Equal)
+      {
+        return true;
+      }
+
+      // Step over the null-terminator to the next string.
+      reader.skip(1, &mut tmp_cursor);
+      remaining = remaining.saturating_sub(compatible.len() + 1);
+    }
+
+    false
+  }
+
+  /// Read the distributor and CPU interface ranges from a GIC reg property.
+  ///
+  /// # Parameters
+  ///
+  /// * `prop_size` - The size of the reg property.
+  /// * `reader` - The DTB reader.
+  /// * `cursor` - The current position in the DTB.
+  ///
+  /// # Description
+  ///
+  /// The first pair is the distributor and the second pair is the CPU
+  /// interface. Any further pairs describe the virtualization extensions and
+  /// are ignored.
+  ///
+  ///   NOTE: The root cell configuration is used and the addresses are not
+  ///         translated through any parent bus ranges.
+  ///
+  /// # Returns
+  ///
+  /// Returns Ok with the ranges if valid, otherwise a DTB error.
+  fn read_ranges(
+    &self,
+    prop_size: usize,
+    reader: &dtb::DtbReader,
+    cursor: &dtb::DtbCursor,
+  ) -> Result<GicPhysicalRanges, dtb::DtbError> {
+    let pair_size = dtb::DtbReader::get_reg_pair_size(self.addr_cells, self.size_cells);
+    let mut tmp_cursor = *cursor;
+
+    if (pair_size == 0) || (prop_size < pair_size * 2) || (prop_size % pair_size != 0) {
+      return Err(dtb::DtbError::InvalidDtb);
+    }
+
+    let distributor = self.read_range(reader, &mut tmp_cursor)?;
+    let cpu_interface = self.read_range(reader, &mut tmp_cursor)?;
+
+    Ok(GicPhysicalRanges {
+      distributor,
+      cpu_interface,
+    })
+  }
+
+  /// Read a single register range.
+  ///
+  /// # Parameters
+  ///
+  /// * `reader` - The DTB reader.
+  /// * `cursor` - The current position in the DTB.
+  ///
+  /// # Returns
+  ///
+  /// Returns Ok with the base and size if the range is addressable on the
+  /// platform, otherwise a DTB error.
+  fn read_range(
+    &self,
+    reader: &dtb::DtbReader,
+    cursor: &mut dtb::DtbCursor,
+  ) -> Result<(usize, usize), dtb::DtbError> {
+    let (base, size) = reader
+      .get_reg_pair(self.addr_cells, self.size_cells, cursor)
+      .ok_or(dtb::DtbError::InvalidDtb)?;
+
+    let base = usize::try_from(base).or(Err(dtb::DtbError::UnsupportedValue))?;
+    let size = usize::try_from(size).or(Err(dtb::DtbError::UnsupportedValue))?;
+
+    if size == 0 || base.checked_add(size - 1).is_none() {
+      return Err(dtb::DtbError::InvalidDtb);
+    }
+
+    Ok((base, size))
+  }
+}
+
+impl<'blob> dtb::DtbScanner<'blob> for DtbGicScanner {
+  /// See `dtb::DtbScanner::scan_node()`.
+  fn scan_node(
+    &mut self,
+    reader: &dtb::DtbReader<'blob>,
+    name: &[u8],
+    cursor: &dtb::DtbCursor,
+  ) -> Result<bool, dtb::DtbError> {
+    if name.len() == 0 {
+      _ = self.scan_root_node(reader, cursor)?;
+    } else {
+      _ = self.scan_device_node(reader, cursor)?;
+    }
+
+    // Stop scanning once the first GIC is found.
+    Ok(self.ranges.is_none())
+  }
+}
+
+/// A GICv2 distributor and CPU interface.
+struct Gic {
+  dist_base: usize,
+  cpu_base: usize,
+}
+
+impl Gic {
+  /// Construct a new GIC.
+  ///
+  /// # Parameters
+  ///
+  /// * `dist_base` - The base virtual address of the distributor registers.
+  /// * `cpu_base` - The base virtual address of the CPU interface registers.
+  const fn new(dist_base: usize, cpu_base: usize) -> Self {
+    Gic {
+      dist_base,
+      cpu_base,
+    }
+  }
+
+  /// Enable the distributor and the current core's CPU interface.
+  ///
+  /// # Description
+  ///
+  /// Interrupts of any priority are signaled to the core. Individual interrupts
+  /// must still be enabled with `enable_irq()`.
+  fn enable(&self) {
+    self.dist_put(GICD_CTLR, CTLR_ENABLE);
+    self.cpu_put(GICC_PMR, PMR_ALLOW_ALL);
+    self.cpu_put(GICC_CTLR, CTLR_ENABLE);
+  }
+
+  /// Enable forwarding of an interrupt to the CPU interfaces.
+  ///
+  /// # Parameters
+  ///
+  /// * `id` - The interrupt ID.
+  fn enable_irq(&self, id: u32) {
+    let (reg, bit) = Self::get_enable_reg(GICD_ISENABLER, id);
+    self.dist_put(reg, bit);
+  }
+
+  /// Disable forwarding of an interrupt to the CPU interfaces.
+  ///
+  /// # Parameters
+  ///
+  /// * `id` - The interrupt ID.
+  fn disable_irq(&self, id: u32) {
+    let (reg, bit) = Self::get_enable_reg(GICD_ICENABLER, id);
+    self.dist_put(reg, bit);
+  }
+
+  /// Acknowledge the highest priority pending interrupt.
+  ///
+  /// # Returns
+  ///
+  /// The interrupt ID, or `SPURIOUS_IRQ` if there is no pending interrupt.
+  fn ack(&self) -> u32 {
+    self.cpu_get(GICC_IAR) & IAR_ID_MASK
+  }
+
+  /// Signal the end of processing for an interrupt.
+  ///
+  /// # Parameters
+  ///
+  /// * `id` - The interrupt ID returned by `ack()`.
+  fn eoi(&self, id: u32) {
+    self.cpu_put(GICC_EOIR, id & IAR_ID_MASK);
+  }
+
+  /// Send a software-generated interrupt to a single CPU interface.
+  ///
+  /// # Parameters
+  ///
+  /// * `target` - The target CPU interface.
+  /// * `id` - The SGI interrupt ID.
+  fn send_sgi(&self, target: usize, id: u32) {
+    self.dist_put(GICD_SGIR, Self::get_sgir_value(target, id));
+  }
+
+  /// Get the GICD_SGIR value that sends an SGI to a single CPU interface.
+  ///
+  /// # Parameters
+  ///
+  /// * `target` - The target CPU interface.
+  /// * `id` - The SGI interrupt ID.
+  ///
+  /// # Returns
+  ///
+  /// The register value.
+  fn get_sgir_value(target: usize, id: u32) -> u32 {
+    assert!(target < MAX_SGI_TARGETS);
+    assert!(id < MAX_SGIS);
+
+    (1 << (SGIR_TARGET_LIST_SHIFT + target as u32)) | id
+  }
+
+  /// Get the set-enable or clear-enable register and bit for an interrupt.
+  ///
+  /// # Parameters
+  ///
+  /// * `base_reg` - Either GICD_ISENABLER or GICD_ICENABLER.
+  /// * `id` - The interrupt ID.
+  ///
+  /// # Description
+  ///
+  /// Both the set-enable and clear-enable registers are write-one, so only the
+  /// interrupt's bit is written and the other interrupts are unaffected.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the register offset and the interrupt's bit.
+  fn get_enable_reg(base_reg: usize, id: u32) -> (usize, u32) {
+    assert!(id < MAX_IRQS);
+
+    let reg = base_reg + ((id / IRQS_PER_ENABLE_REG) as usize) * 4;
+    let bit = 1 << (id % IRQS_PER_ENABLE_REG);
+
+    (reg, bit)
+  }
+
+  /// Write to a distributor register.
+  ///
+  /// # Parameters
+  ///
+  /// * `reg` - The register offset.
+  /// * `val` - The value to write.
+  fn dist_put(&self, reg: usize, val: u32) {
+    unsafe { ptr::write_volatile((self.dist_base + reg) as *mut u32, val) };
+  }
+
+  /// Read a CPU interface register.
+  ///
+  /// # Parameters
+  ///
+  /// * `reg` - The register offset.
+  ///
+  /// # Returns
+  ///
+  /// The value of the register.
+  fn cpu_get(&self, reg: usize) -> u32 {
+    unsafe { ptr::read_volatile((self.cpu_base + reg) as *const u32) }
+  }
+
+  /// Write to a CPU interface register.
+  ///
+  /// # Parameters
+  ///
+  /// * `reg` - The register offset.
+  /// * `val` - The value to write.
+  fn cpu_put(&self, reg: usize, val: u32) {
+    unsafe { ptr::write_volatile((self.cpu_base + reg) as *mut u32, val) };
+  }
+}
+
+/// Get the physical register ranges of the GIC.
+///
+/// # Parameters
+///
+/// * `blob_vaddr` - The DTB virtual address.
+///
+/// # Description
+///
+/// Finds the first node compatible with `arm,gic-400` or `arm,cortex-a15-gic`.
+///
+/// # Returns
+///
+/// The distributor and CPU interface ranges, or None if the DTB could not be
+/// read or does not have a supported GIC.
+pub fn get_physical_ranges(blob_vaddr: usize) -> Option<GicPhysicalRanges> {
+  let reader = dtb::DtbReader::new(blob_vaddr).ok()?;
+  let mut scanner = DtbGicScanner::new();
+
+  if !reader.scan(&mut scanner).is_ok() {
+    return None;
+  }
+
+  scanner.ranges
+}
+
+/// Initialize the GIC driver.
+///
+/// # Parameters
+///
+/// * `dist_base` - The base virtual address of the distributor registers.
+/// * `cpu_base` - The base virtual address of the CPU interface registers.
+///
+/// # Description
+///
+/// Enables the distributor and the CPU interface of the calling core.
+///
+/// # Assumptions
+///
+/// Assumes the caller is on the primary core.
+pub fn init(dist_base: usize, cpu_base: usize) {
+  unsafe {
+    assert!(!INITIALIZED);
+    INITIALIZED = true;
+    GIC = Gic::new(dist_base, cpu_base);
+  }
+
+  get_gic().enable();
+}
+
+/// Check if the GIC driver has been initialized.
+pub fn is_initialized() -> bool {
+  unsafe { INITIALIZED }
+}
+
+/// Enable an interrupt.
+///
+/// # Parameters
+///
+/// * `id` - The interrupt ID.
+pub fn enable_irq(id: u32) {
+  get_gic().enable_irq(id);
+}
+
+/// Disable an interrupt.
+///
+/// # Parameters
+///
+/// * `id` - The interrupt ID.
+pub fn disable_irq(id: u32) {
+  get_gic().disable_irq(id);
+}
+
+/// Send a software-generated interrupt to a core.
+///
+/// # Parameters
+///
+/// * `target` - The target core's index.
+/// * `id` - The SGI interrupt ID.
+///
+/// # Description
+///
+///   NOTE: Assumes core indices match the GIC's CPU interface numbers.
+pub fn send_sgi(target: usize, id: u32) {
+  get_gic().send_sgi(target, id);
+}
+
+/// Acknowledge the highest priority pending interrupt on the current core.
+///
+/// # Description
+///
+///   NOTE: The source core of a software-generated interrupt is discarded.
+///
+/// # Returns
+///
+/// The interrupt ID, or `SPURIOUS_IRQ` if there is no pending interrupt.
+pub fn ack() -> u32 {
+  get_gic().ack()
+}
+
+/// Signal the end of processing for an interrupt on the current core.
+///
+/// # Parameters
+///
+/// * `id` - The interrupt ID returned by `ack()`.
+pub fn eoi(id: u32) {
+  get_gic().eoi(id);
+}
+
+/// Get the system interrupt controller.
+fn get_gic() -> &'static Gic {
+  unsafe {
+    assert!(INITIALIZED);
+    ptr::addr_of!(GIC).as_ref().unwrap()
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,276 @@
+//! ARM Generic Interrupt Controller (GICv2) Tests
+
+use super::super::cpu::IpiKind;
+use super::{
+  GICC_CTLR, GICC_EOIR, GICC_IAR, GICC_PMR, GICD_CTLR, GICD_ICENABLER, GICD_ISENABLER, GICD_SGIR,
+  Gic, MAX_SGI_TARGETS, SPURIOUS_IRQ, get_physical_ranges,
+};
+use crate::debug_print;
+use crate::test::{self, dtb};
+use crate::{check_eq, check_none, check_not_none, check_panics, execute_test};
+use core::ptr;
+
+/// Size of the mocked distributor register range in 32-bit words. Covers the
+/// control, enable, and software-generated interrupt registers.
+const TEST_DIST_WORDS: usize = 0xf04 / 4;
+
+/// Size of the mocked CPU interface register range in 32-bit words.
+const TEST_CPU_WORDS: usize = 0x20 / 4;
+
+/// Test register ranges.
+const TEST_DIST_BASE: u32 = 0x0800_0000;
+const TEST_DIST_SIZE: u32 = 0x1_0000;
+const TEST_CPU_BASE: u32 = 0x0801_0000;
+const TEST_CPU_SIZE: u32 = 0x1_0000;
+
+/// Mocked distributor registers.
+static mut TEST_DIST: [u32; TEST_DIST_WORDS] = [0; TEST_DIST_WORDS];
+
+/// Mocked CPU interface registers.
+static mut TEST_CPU: [u32; TEST_CPU_WORDS] = [0; TEST_CPU_WORDS];
+
+/// Run GIC tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_gic_discovery);
+  execute_test!(context, test_no_gic);
+  execute_test!(context, test_enable);
+  execute_test!(context, test_irq_enable_offsets);
+  execute_test!(context, test_ack_eoi);
+  execute_test!(context, test_send_sgi);
+}
+
+/// Construct a GIC over cleared, mocked register ranges.
+///
+/// # Returns
+///
+/// The mocked GIC.
+fn get_test_gic() -> Gic {
+  let dist = unsafe { ptr::addr_of_mut!(TEST_DIST).as_mut().unwrap() };
+  let cpu = unsafe { ptr::addr_of_mut!(TEST_CPU).as_mut().unwrap() };
+  dist.fill(0);
+  cpu.fill(0);
+  Gic::new(dist.as_ptr() as usize, cpu.as_ptr() as usize)
+}
+
+/// Read a mocked distributor register.
+///
+/// # Parameters
+///
+/// * `reg` - The register offset.
+fn get_dist_reg(reg: usize) -> u32 {
+  unsafe { ptr::addr_of!(TEST_DIST).as_ref().unwrap()[reg / 4] }
+}
+
+/// Read a mocked CPU interface register.
+///
+/// # Parameters
+///
+/// * `reg` - The register offset.
+fn get_cpu_reg(reg: usize) -> u32 {
+  unsafe { ptr::addr_of!(TEST_CPU).as_ref().unwrap()[reg / 4] }
+}
+
+/// Write a mocked CPU interface register.
+///
+/// # Parameters
+///
+/// * `reg` - The register offset.
+/// * `val` - The value to write.
+fn set_cpu_reg(reg: usize, val: u32) {
+  unsafe { ptr::addr_of_mut!(TEST_CPU).as_mut().unwrap()[reg / 4] = val };
+}
+
+/// Test discovering the GIC register ranges.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The GIC is not the first string in its compatible list, is preceded by a
+/// device with a reg property, and has the virtualization extension ranges,
+/// which must be ignored.
+fn test_gic_discovery(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("uart@9000000")
+    .prop_str("compatible", "arm,pl011")
+    .prop_cells("reg", &[0x0900_0000, 0x1000])
+    .end_node()
+    .begin_node("intc@8000000")
+    .prop_cells(
+      "reg",
+      &[
+        TEST_DIST_BASE,
+        TEST_DIST_SIZE,
+        TEST_CPU_BASE,
+        TEST_CPU_SIZE,
+        0x0803_0000,
+        0x1_0000,
+        0x0804_0000,
+        0x1_0000,
+      ],
+    )
+    .prop_bytes("compatible", b"vendor,intc\0arm,cortex-a15-gic\0")
+    .end_node()
+    .end_node()
+    .finish();
+
+  let ranges = get_physical_ranges(blob);
+  check_not_none!(context, ranges);
+
+  let Some(ranges) = ranges else {
+    return;
+  };
+
+  check_eq!(context, ranges.distributor.0, TEST_DIST_BASE as usize);
+  check_eq!(context, ranges.distributor.1, TEST_DIST_SIZE as usize);
+  check_eq!(context, ranges.cpu_interface.0, TEST_CPU_BASE as usize);
+  check_eq!(context, ranges.cpu_interface.1, TEST_CPU_SIZE as usize);
+}
+
+/// Test DTBs without a usable GIC.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// An unsupported interrupt controller is ignored, and a GIC that only
+/// describes its distributor is invalid.
+fn test_no_gic(context: &mut test::TestContext) {
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("interrupt-controller@40000000")
+    .prop_str("compatible", "arm,gic-v3")
+    .prop_cells("reg", &[TEST_DIST_BASE, TEST_DIST_SIZE, TEST_CPU_BASE, TEST_CPU_SIZE])
+    .end_node()
+    .end_node()
+    .finish();
+
+  check_none!(context, get_physical_ranges(blob));
+
+  let mut builder = dtb::DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("interrupt-controller@40000000")
+    .prop_str("compatible", "arm,gic-400")
+    .prop_cells("reg", &[TEST_DIST_BASE, TEST_DIST_SIZE])
+    .end_node()
+    .end_node()
+    .finish();
+
+  check_none!(context, get_physical_ranges(blob));
+}
+
+/// Test enabling the distributor and CPU interface.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_enable(context: &mut test::TestContext) {
+  let gic = get_test_gic();
+  gic.enable();
+
+  check_eq!(context, get_dist_reg(GICD_CTLR), 1);
+  check_eq!(context, get_cpu_reg(GICC_CTLR), 1);
+  check_eq!(context, get_cpu_reg(GICC_PMR), 0xff);
+}
+
+/// Test the set-enable and clear-enable register offsets and bits.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Each enable register covers 32 interrupts. Only the interrupt's bit is
+/// written, and enabling or disabling an interrupt does not write to the
+/// opposite register.
+fn test_irq_enable_offsets(context: &mut test::TestContext) {
+  const TEST_IRQS: [(u32, usize, u32); 4] = [
+    (0, 0x0, 1 << 0),
+    (27, 0x0, 1 << 27),
+    (33, 0x4, 1 << 1),
+    (1019, 0x7c, 1 << 27),
+  ];
+
+  for (id, offset, bit) in TEST_IRQS {
+    let gic = get_test_gic();
+    gic.enable_irq(id);
+    check_eq!(context, get_dist_reg(GICD_ISENABLER + offset), bit);
+    check_eq!(context, get_dist_reg(GICD_ICENABLER + offset), 0);
+
+    let gic = get_test_gic();
+    gic.disable_irq(id);
+    check_eq!(context, get_dist_reg(GICD_ICENABLER + offset), bit);
+    check_eq!(context, get_dist_reg(GICD_ISENABLER + offset), 0);
+  }
+}
+
+/// Test acknowledging and ending an interrupt.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The source core bits of the acknowledge register are discarded.
+fn test_ack_eoi(context: &mut test::TestContext) {
+  let gic = get_test_gic();
+
+  set_cpu_reg(GICC_IAR, (3 << 10) | 30);
+  let id = gic.ack();
+  check_eq!(context, id, 30);
+
+  gic.eoi(id);
+  check_eq!(context, get_cpu_reg(GICC_EOIR), 30);
+
+  set_cpu_reg(GICC_IAR, SPURIOUS_IRQ);
+  check_eq!(context, gic.ack(), SPURIOUS_IRQ);
+}
+
+/// Test the software-generated interrupt register value for each IPI kind.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Each SGI must only target the requested CPU interface with the target list
+/// filter cleared. Targets beyond the GICv2's CPU interfaces must be rejected
+/// without writing the register.
+fn test_send_sgi(context: &mut test::TestContext) {
+  let gic = get_test_gic();
+  let kinds = [IpiKind::Reschedule, IpiKind::TlbFlush, IpiKind::Halt];
+
+  for target in [0, 1, MAX_SGI_TARGETS - 1] {
+    for kind in kinds {
+      gic.send_sgi(target, kind as u32);
+      check_eq!(context, get_dist_reg(GICD_SGIR), (1 << (16 + target)) | (kind as u32));
+    }
+  }
+
+  check_eq!(context, get_dist_reg(GICD_SGIR), 0x80_0002);
+
+  let gic = get_test_gic();
+  check_panics!(context, || gic.send_sgi(MAX_SGI_TARGETS, IpiKind::Halt as u32));
+  check_eq!(context, get_dist_reg(GICD_SGIR), 0);
+}
@@ -0,0 +1,177 @@
+//! ARM Interrupt Dispatch
+//!
+//! Drivers register a handler for each interrupt ID they own. The low-level
+//! IRQ vector calls `dispatch()`, which acknowledges the interrupt with the
+//! interrupt controller, runs the registered handler, and signals the end of
+//! the interrupt.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::{gic, interrupts};
+use crate::debug_print;
+use crate::sync::SpinLock;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::ptr;
+
+/// The number of interrupt IDs that may have a handler.
+pub const MAX_IRQ_HANDLERS: usize = gic::MAX_IRQS as usize;
+
+/// Interrupt handler table convenience type.
+type SystemIrqTable = IrqTable<MAX_IRQ_HANDLERS>;
+
+/// The system interrupt handler table.
+static mut IRQ_TABLE: SpinLock<SystemIrqTable> = SpinLock::new(IrqTable::new());
+
+/// A fixed-size table of interrupt handlers indexed by interrupt ID.
+struct IrqTable<const SIZE: usize> {
+  handlers: [Option<fn()>; SIZE],
+}
+
+impl<const SIZE: usize> IrqTable<SIZE> {
+  /// Construct an empty handler table.
+  const fn new() -> Self {
+    IrqTable {
+      handlers: [None; SIZE],
+    }
+  }
+
+  /// Set the handler for an interrupt ID.
+  ///
+  /// # Parameters
+  ///
+  /// * `id` - The interrupt ID.
+  /// * `handler` - The handler to run when the interrupt is signaled.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: Panics if the interrupt ID is out of range or already has a
+  ///         handler.
+  fn register(&mut self, id: u32, handler: fn()) {
+    let slot = &mut self.handlers[id as usize];
+    assert!(slot.is_none());
+    *slot = Some(handler);
+  }
+
+  /// Get the handler for an interrupt ID.
+  ///
+  /// # Parameters
+  ///
+  /// * `id` - The interrupt ID.
+  ///
+  /// # Returns
+  ///
+  /// The handler, or None if the interrupt ID is out of range or does not have
+  /// a handler.
+  fn get_handler(&self, id: u32) -> Option<fn()> {
+    *self.handlers.get(id as usize)?
+  }
+}
+
+/// Register an interrupt handler.
+///
+/// # Parameters
+///
+/// * `id` - The interrupt ID.
+/// * `handler` - The handler to run when the interrupt is signaled.
+///
+/// # Description
+///
+/// The handler runs in interrupt context with interrupts masked. The interrupt
+/// must still be enabled in the interrupt controller.
+///
+///   NOTE: Panics if the interrupt ID is out of range or already has a
+///         handler.
+pub fn register(id: u32, handler: fn()) {
+  with_table(get_irq_table(), |table| table.register(id, handler));
+}
+
+/// Dispatch the pending interrupt on the current core.
+///
+/// # Description
+///
+/// Called from the low-level IRQ vector. See `dispatch_irq()`.
+pub fn dispatch() {
+  dispatch_irq(get_irq_table(), gic::ack, gic::eoi);
+}
+
+/// Dispatch an interrupt using the provided interrupt controller operations.
+///
+/// # Parameters
+///
+/// * `table` - The interrupt handler table.
+/// * `ack` - Acknowledges the pending interrupt and returns its ID.
+/// * `eoi` - Signals the end of the interrupt.
+///
+/// # Description
+///
+/// Runs the handler registered for the acknowledged interrupt, or logs the
+/// interrupt as spurious if there is no handler. The table lock is not held
+/// while the handler runs so that handlers may register other handlers.
+///
+/// The interrupt controller's spurious interrupt ID means there was no pending
+/// interrupt to acknowledge, so no handler is run and the end of the interrupt
+/// is not signaled.
+fn dispatch_irq<const SIZE: usize>(
+  table: &SpinLock<IrqTable<SIZE>>,
+  ack: impl FnOnce() -> u32,
+  eoi: impl FnOnce(u32),
+) {
+  let id = ack();
+
+  if id == gic::SPURIOUS_IRQ {
+    return;
+  }
+
+  match with_table(table, |table| table.get_handler(id)) {
+    Some(handler) => handler(),
+    None => handle_spurious_irq(id),
+  }
+
+  eoi(id);
+}
+
+/// Default handler for interrupts without a registered handler.
+///
+/// # Parameters
+///
+/// * `id` - The interrupt ID.
+fn handle_spurious_irq(id: u32) {
+  debug_print!("Spurious IRQ {}\n", id);
+}
+
+/// Access a handler table with the lock held.
+///
+/// # Parameters
+///
+/// * `table` - The interrupt handler table.
+/// * `f` - The function to call with the table.
+///
+/// # Description
+///
+/// Interrupts are masked while the lock is held so that the dispatcher on the
+/// same core cannot deadlock trying to look up a handler.
+///
+/// # Returns
+///
+/// The result of `f`.
+fn with_table<const SIZE: usize, R>(
+  table: &SpinLock<IrqTable<SIZE>>,
+  f: impl FnOnce(&mut IrqTable<SIZE>) -> R,
+) -> R {
+  let irq_state = interrupts::save_and_mask_all_interrupts();
+  let result = f(&mut table.lock());
+  interrupts::restore_interrupt_state(irq_state);
+  result
+}
+
+/// Get the system interrupt handler table.
+fn get_irq_table() -> &'static SpinLock<SystemIrqTable> {
+  unsafe { ptr::addr_of!(IRQ_TABLE).as_ref().unwrap() }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,174 @@
+//! ARM Interrupt Dispatch Tests
+
+use super::{IrqTable, dispatch_irq};
+use crate::arch::gic::SPURIOUS_IRQ;
+use crate::debug_print;
+use crate::sync::SpinLock;
+use crate::{check_eq, check_none, check_not_none, check_optional, execute_test, test};
+use core::cell::Cell;
+
+/// Test handler table size.
+const TEST_TABLE_SIZE: usize = 64;
+
+/// Test interrupt ID with a registered handler.
+const TEST_IRQ: u32 = 30;
+
+/// Test interrupt ID without a registered handler.
+const TEST_UNREGISTERED_IRQ: u32 = 33;
+
+/// Test handler table type.
+type TestIrqTable = IrqTable<TEST_TABLE_SIZE>;
+
+/// Number of times `count_handler()` has run.
+static mut HANDLER_COUNT: usize = 0;
+
+/// A mocked interrupt controller that signals a single interrupt.
+struct MockGic {
+  pending: u32,
+  acked: Cell<bool>,
+  eoi: Cell<Option<u32>>,
+  handler_count_at_eoi: Cell<usize>,
+}
+
+impl MockGic {
+  /// Construct a mocked interrupt controller.
+  ///
+  /// # Parameters
+  ///
+  /// * `pending` - The interrupt ID to return when acknowledged.
+  fn new(pending: u32) -> Self {
+    MockGic {
+      pending,
+      acked: Cell::new(false),
+      eoi: Cell::new(None),
+      handler_count_at_eoi: Cell::new(0),
+    }
+  }
+
+  /// Dispatch the pending interrupt through the mocked interrupt controller.
+  ///
+  /// # Parameters
+  ///
+  /// * `table` - The interrupt handler table.
+  fn dispatch(&self, table: &SpinLock<TestIrqTable>) {
+    dispatch_irq(
+      table,
+      || {
+        self.acked.set(true);
+        self.pending
+      },
+      |id| {
+        self.eoi.set(Some(id));
+        self.handler_count_at_eoi.set(get_handler_count());
+      },
+    );
+  }
+}
+
+/// Run interrupt dispatch tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_register);
+  execute_test!(context, test_dispatch);
+  execute_test!(context, test_dispatch_unregistered);
+  execute_test!(context, test_dispatch_spurious);
+}
+
+/// Count the number of times the handler runs.
+fn count_handler() {
+  unsafe { HANDLER_COUNT += 1 };
+}
+
+/// Get the number of times the handler has run.
+fn get_handler_count() -> usize {
+  unsafe { HANDLER_COUNT }
+}
+
+/// Test registering a handler.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_register(context: &mut test::TestContext) {
+  let mut table = TestIrqTable::new();
+
+  check_none!(context, table.get_handler(TEST_IRQ));
+  table.register(TEST_IRQ, count_handler);
+  check_not_none!(context, table.get_handler(TEST_IRQ));
+  check_none!(context, table.get_handler(TEST_UNREGISTERED_IRQ));
+
+  // Out of range interrupt IDs never have a handler.
+  check_none!(context, table.get_handler(TEST_TABLE_SIZE as u32));
+  check_none!(context, table.get_handler(SPURIOUS_IRQ));
+}
+
+/// Test dispatching an interrupt with a registered handler.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The handler must run exactly once between acknowledging the interrupt and
+/// signaling the end of the interrupt.
+fn test_dispatch(context: &mut test::TestContext) {
+  unsafe { HANDLER_COUNT = 0 };
+
+  let table = SpinLock::new(TestIrqTable::new());
+  table.lock().register(TEST_IRQ, count_handler);
+
+  let gic = MockGic::new(TEST_IRQ);
+  gic.dispatch(&table);
+
+  check_eq!(context, gic.acked.get(), true);
+  check_optional!(context, gic.eoi.get(), TEST_IRQ);
+  check_eq!(context, gic.handler_count_at_eoi.get(), 1);
+  check_eq!(context, get_handler_count(), 1);
+}
+
+/// Test dispatching an interrupt without a registered handler.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The default handler runs instead, and the end of the interrupt is still
+/// signaled so that the interrupt controller does not stall.
+fn test_dispatch_unregistered(context: &mut test::TestContext) {
+  unsafe { HANDLER_COUNT = 0 };
+
+  let table = SpinLock::new(TestIrqTable::new());
+  table.lock().register(TEST_IRQ, count_handler);
+
+  let gic = MockGic::new(TEST_UNREGISTERED_IRQ);
+  gic.dispatch(&table);
+
+  check_eq!(context, gic.acked.get(), true);
+  check_optional!(context, gic.eoi.get(), TEST_UNREGISTERED_IRQ);
+  check_eq!(context, get_handler_count(), 0);
+}
+
+/// Test dispatching when the interrupt controller has no pending interrupt.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_dispatch_spurious(context: &mut test::TestContext) {
+  unsafe { HANDLER_COUNT = 0 };
+
+  let table = SpinLock::new(TestIrqTable::new());
+  table.lock().register(TEST_IRQ, count_handler);
+
+  let gic = MockGic::new(SPURIOUS_IRQ);
+  gic.dispatch(&table);
+
+  check_eq!(context, gic.acked.get(), true);
+  check_none!(context, gic.eoi.get());
+  check_eq!(context, get_handler_count(), 0);
+}
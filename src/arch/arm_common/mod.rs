@@ -3,10 +3,20 @@
 //! The ARM common module houses architecture-independent, but ARM platform-
 //! specific utilities.
 
+pub mod cache;
 pub mod cpu;
 #[cfg(feature = "serial_debug_output")]
 pub mod debug;
+pub mod dtb_chosen;
 pub mod dtb_cpu;
+pub mod dtb_device_tree;
 pub mod dtb_memory;
+pub mod dtb_mmio;
+pub mod gic;
 pub mod interrupts;
+pub mod irq;
+#[cfg(feature = "module_tests")]
+pub mod panic_guard;
+pub mod percpu;
 pub mod sync;
+pub mod time;
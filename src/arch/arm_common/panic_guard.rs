@@ -0,0 +1,83 @@
+//! ARM Panic Guard
+//!
+//! The kernel aborts on panic, so module tests cannot rely on unwinding to
+//! verify that an operation panics. Instead, a test calls the operation through
+//! a guard that saves a resumable context. If the operation panics, the panic
+//! handler resumes the context rather than halting, and the guard reports the
+//! panic to the test.
+
+unsafe extern "C" {
+  fn guard_call(ctx: *mut GuardContext, f: extern "C" fn(usize), arg: usize) -> usize;
+  fn guard_resume(ctx: *const GuardContext) -> !;
+}
+
+/// The number of registers saved by `guard_call`. AArch64 saves x19-x29. ARM
+/// only uses the first eight slots for r4-r11.
+const GUARD_REGS: usize = 11;
+
+/// The address of the innermost active guard context, or 0 if there is none.
+static mut ACTIVE_GUARD: usize = 0;
+
+/// Saved registers used to resume after a panic.
+#[repr(C)]
+struct GuardContext {
+  regs: [usize; GUARD_REGS],
+}
+
+/// Call a function and report whether it panicked.
+///
+/// # Parameters
+///
+/// * `f` - The function to call.
+///
+/// # Description
+///
+/// Guards may be nested. A panic resumes the innermost guard.
+///
+///   NOTE: Resuming a guard abandons the frames above it without running any
+///         destructors. Locks held and interrupts masked when the function
+///         panicked remain held and masked, so guarded functions should panic
+///         before changing any shared state.
+///
+///   NOTE: The active guard is not tracked per-core. Guards are only intended
+///         for single-threaded module tests.
+///
+/// # Returns
+///
+/// True if the function panicked, false if it returned normally.
+pub fn call_guarded(mut f: &mut dyn FnMut()) -> bool {
+  let mut ctx = GuardContext {
+    regs: [0; GUARD_REGS],
+  };
+  let prev_guard = unsafe { ACTIVE_GUARD };
+  let f_addr = &mut f as *mut &mut dyn FnMut() as usize;
+
+  unsafe { ACTIVE_GUARD = &mut ctx as *mut GuardContext as usize };
+  let panicked = unsafe { guard_call(&mut ctx, call_trampoline, f_addr) } != 0;
+  unsafe { ACTIVE_GUARD = prev_guard };
+
+  panicked
+}
+
+/// Resume the innermost active guard.
+///
+/// # Description
+///
+/// Called by the panic handler. Does not return if there is an active guard.
+pub fn resume() {
+  let guard = unsafe { ACTIVE_GUARD };
+
+  if guard != 0 {
+    unsafe { guard_resume(guard as *const GuardContext) };
+  }
+}
+
+/// Call a guarded function.
+///
+/// # Parameters
+///
+/// * `f_addr` - The address of a `&mut dyn FnMut()` reference.
+extern "C" fn call_trampoline(f_addr: usize) {
+  let f = unsafe { (f_addr as *mut &mut dyn FnMut()).as_mut().unwrap() };
+  f();
+}
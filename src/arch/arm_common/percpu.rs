@@ -0,0 +1,238 @@
+//! ARM Per-Core Data
+//!
+//! Each core has a private data area for state that is only ever modified by
+//! that core, e.g. scheduler state and the current task. The areas are kept in
+//! a static array indexed by core index so that subsystems do not need to
+//! compute per-core offsets themselves. Each core caches the address of its
+//! area in a thread ID register that is only accessible to the kernel, which
+//! also serves as a cache of the core's index.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::cpu::MAX_CORES;
+use crate::arch;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::{mem, ptr};
+
+unsafe extern "C" {
+  fn percpu_get_area_addr() -> usize;
+  fn percpu_set_area_addr(addr: usize);
+}
+
+/// Per-core data areas.
+static mut CORE_DATA: [CoreData; MAX_CORES] = [const { CoreData::new() }; MAX_CORES];
+
+/// The number of initialized per-core data areas.
+static mut CORE_COUNT: usize = 0;
+
+/// A core's private data area.
+///
+/// # Description
+///
+/// Only the owning core may modify its area, and only with interrupts disabled.
+/// Other cores may read an area for debugging, but must not rely on the values
+/// being current.
+pub struct CoreData {
+  core_idx: usize,
+  current_task: usize,
+  online: bool,
+}
+
+impl CoreData {
+  /// Construct an empty per-core data area.
+  const fn new() -> Self {
+    CoreData {
+      core_idx: 0,
+      current_task: 0,
+      online: false,
+    }
+  }
+
+  /// Get the index of the core that owns the area.
+  pub fn get_core_index(&self) -> usize {
+    self.core_idx
+  }
+
+  /// Get the address of the task running on the core.
+  ///
+  /// # Returns
+  ///
+  /// The task address, or 0 if the core has not started running a task.
+  pub fn get_current_task_addr(&self) -> usize {
+    self.current_task
+  }
+
+  /// Set the address of the task running on the core.
+  ///
+  /// # Parameters
+  ///
+  /// * `addr` - The task address.
+  pub fn set_current_task_addr(&mut self, addr: usize) {
+    self.current_task = addr;
+  }
+
+  /// Check if the core has finished initialization and entered the scheduler.
+  pub fn is_online(&self) -> bool {
+    self.online
+  }
+
+  /// Mark the core online or offline.
+  ///
+  /// # Parameters
+  ///
+  /// * `online` - Whether the core is online.
+  pub fn set_online(&mut self, online: bool) {
+    self.online = online;
+  }
+}
+
+/// Initialize the per-core data areas.
+///
+/// # Parameters
+///
+/// * `core_count` - The number of cores.
+///
+/// # Description
+///
+/// Also caches the primary core's data area and marks the primary core online.
+/// See `init_core()`.
+///
+/// # Assumptions
+///
+/// Assumes the caller is on the primary core and that the secondary cores have
+/// not been started.
+pub fn init(core_count: usize) {
+  assert!(core_count > 0 && core_count <= MAX_CORES);
+
+  for core_idx in 0..core_count {
+    let area = get_core_data(core_idx);
+    *area = CoreData::new();
+    area.core_idx = core_idx;
+  }
+
+  unsafe { CORE_COUNT = core_count };
+
+  init_core();
+  this_core().set_online(true);
+}
+
+/// Cache the current core's data area.
+///
+/// # Description
+///
+/// Each core must call this function before calling `this_core()`. It is safe
+/// to call more than once.
+///
+///   NOTE: `init()` must have been called first.
+pub fn init_core() {
+  let core_idx = arch::lookup_current_core_index();
+  assert!(core_idx < unsafe { CORE_COUNT });
+
+  let area = get_core_data(core_idx);
+  unsafe { percpu_set_area_addr(area as *mut _ as usize) };
+}
+
+/// Get the current core's index.
+///
+/// # Parameters
+///
+/// * `lookup` - Computes the current core's index.
+///
+/// # Description
+///
+/// If the current core's data area is cached, returns the index stored in the
+/// area. Otherwise, calls `lookup` and caches the data area for the computed
+/// index so that later calls skip the lookup. See `init_core()`.
+///
+/// The cache belongs to the core rather than the running task, so it never
+/// needs to be invalidated when a task moves to another core. Before `init()`,
+/// the index is always computed.
+///
+/// # Returns
+///
+/// The current core's index.
+pub fn get_current_core_index(lookup: impl FnOnce() -> usize) -> usize {
+  if let Some(area) = get_cached_core_data() {
+    return area.core_idx;
+  }
+
+  let core_idx = lookup();
+
+  if core_idx < unsafe { CORE_COUNT } {
+    let area = get_core_data(core_idx);
+    unsafe { percpu_set_area_addr(area as *mut _ as usize) };
+  }
+
+  core_idx
+}
+
+/// Get the current core's data area.
+///
+/// # Description
+///
+///   NOTE: Interrupts must be disabled prior to calling to prevent the task
+///         from moving to another core while the area is in use.
+///
+///   NOTE: `init_core()` must have been called on the current core first.
+pub fn this_core() -> &'static mut CoreData {
+  unsafe { (percpu_get_area_addr() as *mut CoreData).as_mut().unwrap() }
+}
+
+/// Get the data areas of all cores.
+///
+/// # Description
+///
+/// Intended for debugging. See `CoreData`.
+///
+/// # Returns
+///
+/// An iterator over the initialized data areas in core index order.
+pub fn cores() -> impl Iterator<Item = &'static CoreData> {
+  let areas = unsafe { ptr::addr_of!(CORE_DATA).as_ref().unwrap() };
+  areas[..unsafe { CORE_COUNT }].iter()
+}
+
+/// Get the current core's cached data area.
+///
+/// # Description
+///
+/// The cached address is only trusted if it points to an initialized data
+/// area.
+///
+/// # Returns
+///
+/// The data area, or None if the core has not cached a valid area.
+fn get_cached_core_data() -> Option<&'static CoreData> {
+  let addr = unsafe { percpu_get_area_addr() };
+  let base = ptr::addr_of!(CORE_DATA) as usize;
+  let offset = addr.checked_sub(base)?;
+  let core_idx = offset / mem::size_of::<CoreData>();
+
+  if offset % mem::size_of::<CoreData>() != 0 || core_idx >= unsafe { CORE_COUNT } {
+    return None;
+  }
+
+  Some(get_core_data(core_idx))
+}
+
+/// Get a core's data area.
+///
+/// # Parameters
+///
+/// * `core_idx` - The core index.
+///
+/// # Description
+///
+/// Intended for bringing up a core before it has cached its own data area.
+/// See `CoreData`.
+pub fn get_core_data(core_idx: usize) -> &'static mut CoreData {
+  let areas = unsafe { ptr::addr_of_mut!(CORE_DATA).as_mut().unwrap() };
+  &mut areas[core_idx]
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,110 @@
+//! ARM Per-Core Data Tests
+
+use super::{get_core_data, get_current_core_index, percpu_get_area_addr, percpu_set_area_addr};
+use crate::debug_print;
+use crate::{check_eq, check_neq, execute_test, test};
+use core::ptr;
+
+/// Number of times `count_lookup()` has run.
+static mut LOOKUP_COUNT: usize = 0;
+
+/// Run per-core data tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_distinct_areas);
+  execute_test!(context, test_current_task);
+  execute_test!(context, test_core_index_cache);
+}
+
+/// Count the number of core index lookups.
+///
+/// # Returns
+///
+/// Always core 0.
+fn count_lookup() -> usize {
+  unsafe { LOOKUP_COUNT += 1 };
+  0
+}
+
+/// Test that two simulated cores get distinct data areas.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Modifying one core's area must not affect the other core's area. The
+/// original values are restored afterward.
+fn test_distinct_areas(context: &mut test::TestContext) {
+  let saved = (get_core_data(0).core_idx, get_core_data(1).core_idx);
+
+  let same_area = ptr::eq(get_core_data(0), get_core_data(1));
+  check_eq!(context, same_area, false);
+
+  get_core_data(0).core_idx = 0x1234;
+  get_core_data(1).core_idx = 0x5678;
+  check_eq!(context, get_core_data(0).get_core_index(), 0x1234);
+  check_eq!(context, get_core_data(1).get_core_index(), 0x5678);
+
+  get_core_data(1).core_idx = 0x9abc;
+  check_neq!(context, get_core_data(0).get_core_index(), 0x9abc);
+
+  get_core_data(0).core_idx = saved.0;
+  get_core_data(1).core_idx = saved.1;
+}
+
+/// Test that setting the current task on one simulated core does not change
+/// another core's current task.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_current_task(context: &mut test::TestContext) {
+  let saved = (get_core_data(0).get_current_task_addr(), get_core_data(1).get_current_task_addr());
+
+  get_core_data(0).set_current_task_addr(0x1000);
+  get_core_data(1).set_current_task_addr(0);
+  check_eq!(context, get_core_data(0).get_current_task_addr(), 0x1000);
+  check_eq!(context, get_core_data(1).get_current_task_addr(), 0);
+
+  get_core_data(1).set_current_task_addr(0x2000);
+  check_eq!(context, get_core_data(0).get_current_task_addr(), 0x1000);
+  check_eq!(context, get_core_data(1).get_current_task_addr(), 0x2000);
+
+  get_core_data(0).set_current_task_addr(saved.0);
+  get_core_data(1).set_current_task_addr(saved.1);
+}
+
+/// Test caching the current core index.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Clearing the cached data area simulates a core that has not looked up its
+/// index. The first call must compute the index and cache the core's data
+/// area, and the second call must use the cache. The original cached area is
+/// restored afterward.
+fn test_core_index_cache(context: &mut test::TestContext) {
+  let saved = unsafe { percpu_get_area_addr() };
+
+  unsafe {
+    LOOKUP_COUNT = 0;
+    percpu_set_area_addr(0);
+  }
+
+  check_eq!(context, get_current_core_index(count_lookup), 0);
+  check_eq!(context, unsafe { LOOKUP_COUNT }, 1);
+  check_eq!(context, unsafe { percpu_get_area_addr() }, get_core_data(0) as *mut _ as usize);
+
+  check_eq!(context, get_current_core_index(count_lookup), 0);
+  check_eq!(context, unsafe { LOOKUP_COUNT }, 1);
+
+  unsafe { percpu_set_area_addr(saved) };
+}
@@ -4,6 +4,28 @@ unsafe extern "C" {
   fn sync_spin_lock(lock_addr: usize);
   fn sync_spin_try_lock(lock_addr: usize) -> u32;
   fn sync_spin_unlock(lock_addr: usize);
+  fn sync_dmb();
+  fn sync_dsb();
+}
+
+/// Data memory barrier.
+///
+/// # Description
+///
+/// Memory accesses before the barrier, including device register accesses, are
+/// observed before memory accesses after the barrier.
+pub fn dmb() {
+  unsafe { sync_dmb() };
+}
+
+/// Data synchronization barrier.
+///
+/// # Description
+///
+/// Memory accesses before the barrier complete before any instruction after the
+/// barrier runs.
+pub fn dsb() {
+  unsafe { sync_dsb() };
 }
 
 /// Spin lock.
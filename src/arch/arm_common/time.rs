@@ -0,0 +1,201 @@
+//! ARM Generic Timer
+//!
+//! The generic timer provides a monotonic system count that increments at a
+//! fixed frequency and is synchronized across all cores.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::arch::cpu;
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+unsafe extern "C" {
+  fn time_get_ticks() -> u64;
+  fn time_get_frequency() -> usize;
+  fn time_set_timer(ticks: usize);
+  fn time_get_timer_control() -> usize;
+}
+
+/// Microseconds per second.
+const MICROS_PER_SECOND: u64 = 1_000_000;
+
+/// Timer control register enable bit.
+const TIMER_CTL_ENABLE: usize = 1 << 0;
+
+/// Timer control register status bit. Set when the timer condition is met.
+const TIMER_CTL_ISTATUS: usize = 1 << 2;
+
+/// The timer value register is a signed 32-bit count.
+const MAX_TIMER_TICKS: u64 = i32::MAX as u64;
+
+/// The interrupt ID of the EL1 physical timer's private peripheral interrupt.
+pub const TIMER_IRQ: u32 = 30;
+
+/// The periodic timer interval in ticks.
+static mut TIMER_INTERVAL_TICKS: u64 = 0;
+
+/// Get the current system count.
+pub fn now_ticks() -> u64 {
+  unsafe { time_get_ticks() }
+}
+
+/// Get the frequency of the system count in Hz.
+///
+/// # Description
+///
+///   NOTE: The frequency is programmed by the firmware. If the firmware did not
+///         program the frequency, it will be zero.
+pub fn ticks_per_second() -> u64 {
+  unsafe { time_get_frequency() as u64 }
+}
+
+/// Get the current system time in microseconds.
+pub fn now_micros() -> u64 {
+  ticks_to_micros(now_ticks(), ticks_per_second())
+}
+
+/// Convert a system count to microseconds.
+///
+/// # Parameters
+///
+/// * `ticks` - The system count.
+/// * `frequency` - The frequency of the system count in Hz.
+///
+/// # Description
+///
+/// The whole seconds and the remainder are converted separately so that the
+/// intermediate values do not overflow. The result saturates if the number of
+/// microseconds does not fit in 64 bits.
+///
+///   NOTE: The frequency is at most 32 bits.
+///
+/// # Returns
+///
+/// The number of microseconds, truncated, or zero if the frequency is zero.
+pub fn ticks_to_micros(ticks: u64, frequency: u64) -> u64 {
+  if frequency == 0 {
+    return 0;
+  }
+
+  let secs = ticks / frequency;
+  let rem = ticks % frequency;
+  secs
+    .saturating_mul(MICROS_PER_SECOND)
+    .saturating_add((rem * MICROS_PER_SECOND) / frequency)
+}
+
+/// Convert microseconds to a system count.
+///
+/// # Parameters
+///
+/// * `micros` - The number of microseconds.
+/// * `frequency` - The frequency of the system count in Hz.
+///
+/// # Description
+///
+/// The conversion rounds up so that waiting for the resulting number of ticks
+/// waits at least the requested time. The result saturates if the number of
+/// ticks does not fit in 64 bits.
+///
+///   NOTE: The frequency is at most 32 bits.
+///
+/// # Returns
+///
+/// The number of ticks.
+pub fn micros_to_ticks(micros: u64, frequency: u64) -> u64 {
+  let secs = micros / MICROS_PER_SECOND;
+  let rem = micros % MICROS_PER_SECOND;
+  secs
+    .saturating_mul(frequency)
+    .saturating_add((rem * frequency).div_ceil(MICROS_PER_SECOND))
+}
+
+/// Busy-wait for a number of microseconds.
+///
+/// # Parameters
+///
+/// * `micros` - The number of microseconds to wait.
+///
+/// # Description
+///
+/// Intended for short delays, such as device initialization sequences, where
+/// the caller cannot sleep.
+///
+///   NOTE: Panics if the firmware did not program the counter frequency.
+pub fn spin_wait_micros(micros: u64) {
+  let frequency = ticks_per_second();
+  assert_ne!(frequency, 0);
+  spin_wait_ticks(micros_to_ticks(micros, frequency), now_ticks);
+}
+
+/// Busy-wait until a counter has advanced by a number of ticks.
+///
+/// # Parameters
+///
+/// * `ticks` - The number of ticks to wait.
+/// * `read_ticks` - Reads the counter.
+///
+/// # Description
+///
+/// The elapsed count is computed with wrapping arithmetic, so the wait is
+/// correct even if the counter wraps around during the wait.
+fn spin_wait_ticks(ticks: u64, mut read_ticks: impl FnMut() -> u64) {
+  let start = read_ticks();
+
+  while read_ticks().wrapping_sub(start) < ticks {
+    cpu::relax();
+  }
+}
+
+/// Start the periodic timer on the current core.
+///
+/// # Parameters
+///
+/// * `interval_micros` - The timer period in microseconds.
+///
+/// # Description
+///
+/// Programs the physical timer to fire after the interval and unmasks the timer
+/// interrupt. The timer interrupt handler must call `rearm()` to schedule the
+/// next tick. All cores share the same interval.
+///
+///   NOTE: A handler for `TIMER_IRQ` must be registered and the interrupt must
+///         be enabled in the interrupt controller.
+///
+///   NOTE: Panics if the firmware did not program the counter frequency or if
+///         the interval does not fit in the timer value register.
+pub fn arm_timer(interval_micros: u64) {
+  let ticks = micros_to_ticks(interval_micros, ticks_per_second());
+  assert!(ticks > 0 && ticks <= MAX_TIMER_TICKS);
+
+  unsafe {
+    TIMER_INTERVAL_TICKS = ticks;
+    time_set_timer(ticks as usize);
+  }
+}
+
+/// Schedule the next periodic timer tick on the current core.
+///
+/// # Description
+///
+/// Called from the timer interrupt handler. Reprogramming the timer clears the
+/// pending interrupt.
+///
+///   NOTE: `arm_timer()` must have been called first.
+pub fn rearm() {
+  let ticks = unsafe { TIMER_INTERVAL_TICKS };
+  assert_ne!(ticks, 0);
+  unsafe { time_set_timer(ticks as usize) };
+}
+
+/// Check if the current core's timer has fired.
+pub fn is_timer_pending() -> bool {
+  let ctl = unsafe { time_get_timer_control() };
+  (ctl & TIMER_CTL_ENABLE) != 0 && (ctl & TIMER_CTL_ISTATUS) != 0
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,206 @@
+//! ARM Generic Timer Tests
+
+use super::{micros_to_ticks, spin_wait_ticks, ticks_to_micros};
+use crate::debug_print;
+use crate::{check_eq, execute_test, test};
+
+/// Common generic timer frequencies in Hz.
+const TEST_FREQUENCIES: [u64; 4] = [1_000_000, 19_200_000, 54_000_000, 62_500_000];
+
+/// A mocked tick source that advances by a fixed number of ticks per read.
+struct MockTickSource {
+  ticks: u64,
+  step: u64,
+  frequency: u64,
+}
+
+impl MockTickSource {
+  /// Read the mocked system count and advance it. The count wraps around like
+  /// the hardware counter.
+  fn read(&mut self) -> u64 {
+    let ticks = self.ticks;
+    self.ticks = self.ticks.wrapping_add(self.step);
+    ticks
+  }
+}
+
+/// Run generic timer tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_micros_conversion);
+  execute_test!(context, test_micros_truncation);
+  execute_test!(context, test_micros_large_counts);
+  execute_test!(context, test_mock_tick_source);
+  execute_test!(context, test_ticks_conversion);
+  execute_test!(context, test_spin_wait);
+  execute_test!(context, test_spin_wait_wrap);
+}
+
+/// Test converting whole seconds to microseconds.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_micros_conversion(context: &mut test::TestContext) {
+  for frequency in TEST_FREQUENCIES {
+    check_eq!(context, ticks_to_micros(0, frequency), 0);
+    check_eq!(context, ticks_to_micros(frequency, frequency), 1_000_000);
+    check_eq!(context, ticks_to_micros(frequency * 3 / 2, frequency), 1_500_000);
+  }
+
+  // An unprogrammed frequency reports no time.
+  check_eq!(context, ticks_to_micros(12345, 0), 0);
+}
+
+/// Test that partial microseconds are truncated.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// At 19.2 MHz, a microsecond is 19.2 ticks.
+fn test_micros_truncation(context: &mut test::TestContext) {
+  check_eq!(context, ticks_to_micros(19, 19_200_000), 0);
+  check_eq!(context, ticks_to_micros(20, 19_200_000), 1);
+  check_eq!(context, ticks_to_micros(192, 19_200_000), 10);
+  check_eq!(context, ticks_to_micros(19_200_000 + 191, 19_200_000), 1_000_009);
+}
+
+/// Test counts where multiplying the count by one million would overflow.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_micros_large_counts(context: &mut test::TestContext) {
+  const FREQUENCY: u64 = 54_000_000;
+  const DAYS: u64 = 10_000;
+  const SECONDS: u64 = DAYS * 24 * 60 * 60;
+
+  check_eq!(context, ticks_to_micros(SECONDS * FREQUENCY, FREQUENCY), SECONDS * 1_000_000);
+
+  // One tick per second runs out of microseconds before it runs out of ticks.
+  check_eq!(context, ticks_to_micros(u64::MAX, 1), u64::MAX);
+}
+
+/// Test measuring elapsed time with a mocked tick source.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The mocked source starts near the point where a naive conversion would
+/// overflow and advances 2.5 ms per read.
+fn test_mock_tick_source(context: &mut test::TestContext) {
+  for frequency in TEST_FREQUENCIES {
+    let mut source = MockTickSource {
+      ticks: u64::MAX / 1_000_000,
+      step: frequency / 400,
+      frequency,
+    };
+
+    let start = ticks_to_micros(source.read(), source.frequency);
+    let end = ticks_to_micros(source.read(), source.frequency);
+    let elapsed = end - start;
+
+    // Truncating both ends may lose up to a microsecond.
+    let in_range = (2_499..=2_500).contains(&elapsed);
+    check_eq!(context, in_range, true);
+  }
+}
+
+/// Test converting microseconds to ticks.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_ticks_conversion(context: &mut test::TestContext) {
+  for frequency in TEST_FREQUENCIES {
+    check_eq!(context, micros_to_ticks(0, frequency), 0);
+    check_eq!(context, micros_to_ticks(1_000_000, frequency), frequency);
+    check_eq!(context, micros_to_ticks(2_500_000, frequency), frequency * 5 / 2);
+  }
+
+  // At 19.2 MHz, a microsecond is 19.2 ticks. Partial ticks round up.
+  check_eq!(context, micros_to_ticks(1, 19_200_000), 20);
+  check_eq!(context, micros_to_ticks(10, 19_200_000), 192);
+
+  check_eq!(context, micros_to_ticks(u64::MAX, 62_500_000), u64::MAX);
+}
+
+/// Spin-wait on a mocked tick source.
+///
+/// # Parameters
+///
+/// * `source` - The mocked tick source.
+/// * `ticks` - The number of ticks to wait.
+///
+/// # Returns
+///
+/// The number of times the counter was read.
+fn count_spin_wait_reads(source: &mut MockTickSource, ticks: u64) -> u64 {
+  let mut reads = 0;
+
+  spin_wait_ticks(ticks, || {
+    reads += 1;
+    source.read()
+  });
+
+  reads
+}
+
+/// Test that the spin-wait returns after the requested number of ticks.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first read records the start of the wait. With a counter that advances
+/// by one tick per read, waiting N ticks takes N more reads.
+fn test_spin_wait(context: &mut test::TestContext) {
+  let mut source = MockTickSource {
+    ticks: 1000,
+    step: 1,
+    frequency: 1_000_000,
+  };
+
+  check_eq!(context, count_spin_wait_reads(&mut source, 0), 2);
+  check_eq!(context, count_spin_wait_reads(&mut source, 1), 2);
+  check_eq!(context, count_spin_wait_reads(&mut source, 100), 101);
+
+  // A counter that advances faster than the wait still waits at least the
+  // requested number of ticks.
+  let mut source = MockTickSource {
+    ticks: 0,
+    step: 7,
+    frequency: 1_000_000,
+  };
+
+  let ticks = micros_to_ticks(100, source.frequency);
+  let reads = count_spin_wait_reads(&mut source, ticks);
+  check_eq!(context, reads, ticks.div_ceil(7) + 1);
+}
+
+/// Test that the spin-wait handles the counter wrapping around.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_spin_wait_wrap(context: &mut test::TestContext) {
+  let mut source = MockTickSource {
+    ticks: u64::MAX - 10,
+    step: 1,
+    frequency: 1_000_000,
+  };
+
+  check_eq!(context, count_spin_wait_reads(&mut source, 50), 51);
+  check_eq!(context, source.ticks, 40);
+}
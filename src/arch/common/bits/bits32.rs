@@ -50,7 +50,7 @@ pub const fn floor_log2(n: usize) -> usize {
   ones(n >> 1)
 }
 
-/// Fast 32-bit ceiling base-2 log of an n.
+/// Fast 32-bit ceiling base-2 log of a number.
 ///
 /// # Parameters
 ///
@@ -59,6 +59,9 @@ pub const fn floor_log2(n: usize) -> usize {
 /// # Returns
 ///
 /// ceiling( log2( n ) ) when n > 0, 0 otherwise.
+///
+///   NOTE: Any number greater than 2^31 returns 32, so the result cannot be
+///         used as a shift amount without checking it first.
 pub const fn ceil_log2(n: usize) -> usize {
   let mut m = n & (n.wrapping_sub(1));
   m |= !m.wrapping_sub(1);
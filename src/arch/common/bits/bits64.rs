@@ -61,6 +61,9 @@ pub const fn floor_log2(n: usize) -> usize {
 /// # Returns
 ///
 /// ceiling( log2( n ) ) when n > 0, 0 otherwise.
+///
+///   NOTE: Any number greater than 2^63 returns 64, so the result cannot be
+///         used as a shift amount without checking it first.
 pub const fn ceil_log2(n: usize) -> usize {
   let mut m = n & (n.wrapping_sub(1));
   m |= !m.wrapping_sub(1);
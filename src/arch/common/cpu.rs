@@ -1,6 +1,12 @@
 //! Common CPU Core Configuration Utilities
 
+#[cfg(feature = "module_tests")]
+mod tests;
+
 use crate::support::{hash, hash_map};
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::cmp;
 
 /// 32-bit builds are limited to 16 cores. Thread-local page mapping requires
 /// each core to reserve a 2 MiB block in the kernel's address space. Limiting
@@ -13,6 +19,11 @@ pub const MAX_CORES: usize = 16;
 /// Length of a core type name.
 pub const CORE_TYPE_LEN: usize = 64;
 
+/// Capacity assumed for a core when the platform does not describe it. Core
+/// capacities are normalized so that the most capable core has a capacity of
+/// 1024.
+pub const DEFAULT_CORE_CAPACITY: u32 = 1024;
+
 /// Size of the core ID to core index map, the smallest prime larger than 1.5x
 /// the AArch64 max core count.
 #[cfg(target_pointer_width = "64")]
@@ -20,6 +31,23 @@ pub const CORE_MAP_SIZE: usize = 389;
 #[cfg(target_pointer_width = "32")]
 pub const CORE_MAP_SIZE: usize = 29;
 
+/// Memory management features reported by a core's ID registers.
+#[derive(Copy, Clone)]
+pub struct CpuFeatures {
+  /// The number of physical address bits the core supports.
+  pub pa_bits: usize,
+  /// The number of ASID bits the core supports.
+  pub asid_bits: usize,
+  /// The core supports the long translation table descriptor format.
+  pub long_descriptors: bool,
+  /// The core supports 4 KiB translation granules.
+  pub granule_4k: bool,
+  /// The core supports 16 KiB translation granules.
+  pub granule_16k: bool,
+  /// The core supports 64 KiB translation granules.
+  pub granule_64k: bool,
+}
+
 /// Method used to enable a core.
 #[derive(Copy, Clone)]
 pub enum CoreEnableMethod {
@@ -34,6 +62,32 @@ pub enum CoreEnableMethod {
   Bcm2836,
 }
 
+/// Check if a core type contains a compatible string.
+///
+/// # Parameters
+///
+/// * `core_type` - The null-padded core type.
+/// * `needle` - The compatible string, excluding the null-terminator.
+///
+/// # Description
+///
+/// A core type is a copy of the core's DTB compatible property, which is a
+/// list of null-separated strings ordered from most to least specific. Any
+/// string in the list may match, but only exactly.
+///
+/// # Returns
+///
+/// True if the list contains the compatible string, false otherwise.
+pub fn compatible_contains(core_type: &[u8], needle: &[u8]) -> bool {
+  if needle.is_empty() {
+    return false;
+  }
+
+  core_type
+    .split(|&c| c == 0)
+    .any(|entry| entry.cmp(needle) == cmp::Ordering::Equal)
+}
+
 /// Logical core information.
 ///
 ///   TODO: The members should be private.
@@ -42,6 +96,9 @@ pub struct Core {
   pub core_type: [u8; CORE_TYPE_LEN],
   pub enable_method: CoreEnableMethod,
   pub release_addr: usize,
+  pub phandle: u32,
+  pub cluster_id: usize,
+  pub capacity: u32,
 }
 
 impl Core {
@@ -52,6 +109,9 @@ impl Core {
       core_type: [0; CORE_TYPE_LEN],
       enable_method: CoreEnableMethod::Invalid,
       release_addr: 0,
+      phandle: 0,
+      cluster_id: 0,
+      capacity: DEFAULT_CORE_CAPACITY,
     }
   }
 
@@ -60,11 +120,16 @@ impl Core {
     self.id
   }
 
-  /// Get the core type byte string.
+  /// Get the core type byte string. See `compatible_contains()`.
   pub fn get_core_type(&self) -> &[u8] {
     &self.core_type
   }
 
+  /// Get the most specific core type in the core type byte string.
+  pub fn get_primary_core_type(&self) -> &[u8] {
+    self.core_type.split(|&c| c == 0).next().unwrap_or(&[])
+  }
+
   /// Get the method to enable the core.
   pub fn get_enable_method(&self) -> CoreEnableMethod {
     self.enable_method
@@ -75,6 +140,19 @@ impl Core {
   pub fn get_release_addr(&self) -> usize {
     self.release_addr
   }
+
+  /// Get the ID of the cluster containing the core. Clusters are numbered from
+  /// zero. All cores are in cluster 0 if the platform does not describe its
+  /// topology.
+  pub fn get_cluster_id(&self) -> usize {
+    self.cluster_id
+  }
+
+  /// Get the core's relative performance. A core with a larger capacity can do
+  /// more work per clock than a core with a smaller capacity.
+  pub fn get_capacity(&self) -> u32 {
+    self.capacity
+  }
 }
 
 /// Convenience type for mapping from a hardware core ID to an core index.
@@ -166,7 +244,8 @@ impl CoreConfig {
   ///
   /// # Returns
   ///
-  /// The index of the specified core.
+  /// The index of the specified core, or None if the core is not in the
+  /// configuration.
   pub fn get_core_index(&self, id: usize) -> Option<usize> {
     // A linear search can be faster with a small number of cores.
     //
@@ -209,4 +288,14 @@ impl CoreConfig {
   pub fn get_cores(&self) -> &[Core] {
     &self.cores[..self.core_count]
   }
+
+  /// Get the mutable list of cores.
+  pub fn get_cores_mut(&mut self) -> &mut [Core] {
+    &mut self.cores[..self.core_count]
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
 }
@@ -0,0 +1,188 @@
+//! Common CPU Core Configuration Tests
+
+use super::{CORE_TYPE_LEN, Core, CoreConfig, MAX_CORES, compatible_contains};
+use crate::debug_print;
+use crate::test;
+use crate::{check_eq, check_none, check_optional, execute_test};
+use core::ptr;
+
+/// Base physical identifier for test cores. Physical identifiers do not need to
+/// start at zero.
+const TEST_ID_BASE: usize = 0x8000_0100;
+
+/// The core configuration is too large to build on the kernel stack on 64-bit
+/// platforms.
+static mut TEST_CORE_CONFIG: CoreConfig = CoreConfig::new();
+
+/// Run core configuration tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_primary_core);
+  execute_test!(context, test_unknown_id);
+  execute_test!(context, test_full_config);
+  execute_test!(context, test_reset);
+  execute_test!(context, test_compatible_contains);
+}
+
+/// Get the test core configuration after resetting it.
+fn get_test_config() -> &'static mut CoreConfig {
+  let config = unsafe { ptr::addr_of_mut!(TEST_CORE_CONFIG).as_mut().unwrap() };
+  config.reset();
+  config
+}
+
+/// Construct a test core.
+///
+/// # Parameters
+///
+/// * `id` - The physical core identifier.
+fn make_core(id: usize) -> Core {
+  let mut core = Core::new();
+  core.id = id;
+  core
+}
+
+/// Test that the primary core is always at index 0.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The primary core is added after a secondary core and must swap places with
+/// the core that was at index 0. Looking up an unknown identifier must not
+/// panic.
+fn test_primary_core(context: &mut test::TestContext) {
+  let config = get_test_config();
+
+  check_eq!(context, config.add_core(make_core(TEST_ID_BASE + 1), false), true);
+  check_eq!(context, config.add_core(make_core(TEST_ID_BASE), true), true);
+  check_eq!(context, config.add_core(make_core(TEST_ID_BASE + 2), false), true);
+  check_eq!(context, config.get_core_count(), 3);
+
+  check_optional!(context, config.get_core_index(TEST_ID_BASE), 0);
+  check_optional!(context, config.get_core_index(TEST_ID_BASE + 1), 1);
+  check_optional!(context, config.get_core_index(TEST_ID_BASE + 2), 2);
+  check_none!(context, config.get_core_index(TEST_ID_BASE + 3));
+
+  for (index, core) in config.get_cores().iter().enumerate() {
+    check_optional!(context, config.get_core_index(core.get_id()), index);
+  }
+}
+
+/// Test looking up identifiers that are not in the configuration.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// An empty configuration has no identifiers, not even 0, and an identifier
+/// must match exactly.
+fn test_unknown_id(context: &mut test::TestContext) {
+  let config = get_test_config();
+
+  check_none!(context, config.get_core_index(0));
+  check_none!(context, config.get_core_index(TEST_ID_BASE));
+
+  check_eq!(context, config.add_core(make_core(TEST_ID_BASE), true), true);
+  check_optional!(context, config.get_core_index(TEST_ID_BASE), 0);
+  check_none!(context, config.get_core_index(0));
+  check_none!(context, config.get_core_index(TEST_ID_BASE & 0xff));
+  check_none!(context, config.get_core_index(usize::MAX));
+}
+
+/// Test a configuration with the maximum number of cores.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// On 64-bit platforms, lookups switch from a linear search to the identifier
+/// map once there are enough cores. The primary core is added last so that the
+/// swap must also update the map.
+fn test_full_config(context: &mut test::TestContext) {
+  let config = get_test_config();
+
+  for i in 1..MAX_CORES {
+    check_eq!(context, config.add_core(make_core(TEST_ID_BASE + i), false), true);
+  }
+
+  check_eq!(context, config.add_core(make_core(TEST_ID_BASE), true), true);
+  check_eq!(context, config.get_core_count(), MAX_CORES);
+  check_eq!(context, config.add_core(make_core(TEST_ID_BASE + MAX_CORES), false), false);
+  check_eq!(context, config.get_core_count(), MAX_CORES);
+
+  check_optional!(context, config.get_core_index(TEST_ID_BASE), 0);
+  check_optional!(context, config.get_core_index(TEST_ID_BASE + 1), MAX_CORES - 1);
+  check_none!(context, config.get_core_index(TEST_ID_BASE + MAX_CORES));
+
+  for (index, core) in config.get_cores().iter().enumerate() {
+    check_optional!(context, config.get_core_index(core.get_id()), index);
+  }
+}
+
+/// Test resetting the configuration.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Identifiers from before the reset must not resolve to an index.
+fn test_reset(context: &mut test::TestContext) {
+  let config = get_test_config();
+
+  for i in 0..MAX_CORES {
+    _ = config.add_core(make_core(TEST_ID_BASE + i), i == 0);
+  }
+
+  config.reset();
+  check_eq!(context, config.get_core_count(), 0);
+  check_eq!(context, config.get_cores().len(), 0);
+  check_none!(context, config.get_core_index(TEST_ID_BASE));
+  check_none!(context, config.get_core_index(TEST_ID_BASE + MAX_CORES - 1));
+
+  check_eq!(context, config.add_core(make_core(TEST_ID_BASE + 1), true), true);
+  check_optional!(context, config.get_core_index(TEST_ID_BASE + 1), 0);
+  check_none!(context, config.get_core_index(TEST_ID_BASE));
+}
+
+/// Test searching a null-separated core type list.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Any entry may match, but prefixes, suffixes, and the null padding must not.
+fn test_compatible_contains(context: &mut test::TestContext) {
+  const LIST: &[u8] = b"vendor,custom-a53\0arm,cortex-a53\0arm,armv8\0";
+
+  let mut core = Core::new();
+  core.core_type[..LIST.len()].clone_from_slice(LIST);
+  let core_type = core.get_core_type();
+
+  check_eq!(context, core_type.len(), CORE_TYPE_LEN);
+  check_eq!(context, compatible_contains(core_type, b"vendor,custom-a53"), true);
+  check_eq!(context, compatible_contains(core_type, b"arm,cortex-a53"), true);
+  check_eq!(context, compatible_contains(core_type, b"arm,armv8"), true);
+
+  check_eq!(context, compatible_contains(core_type, b"arm,cortex-a5"), false);
+  check_eq!(context, compatible_contains(core_type, b"cortex-a53"), false);
+  check_eq!(context, compatible_contains(core_type, b"arm,cortex-a53\0arm,armv8"), false);
+  check_eq!(context, compatible_contains(core_type, b""), false);
+
+  let is_primary_type = core.get_primary_core_type() == b"vendor,custom-a53";
+  check_eq!(context, is_primary_type, true);
+  check_eq!(context, compatible_contains(Core::new().get_core_type(), b"arm,armv8"), false);
+}
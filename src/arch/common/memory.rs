@@ -1,7 +1,13 @@
 //! Common Memory Configuration Utilities
 
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::debug_print;
 use crate::support::{bits, range, range_set};
-use core::cmp;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::{cmp, fmt};
 
 /// Memory zone tags.
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -17,8 +23,9 @@ pub enum MemoryZone {
   HighMemoryZone,
 }
 
-/// Maximum number of memory ranges that can be stored in a configuration.
-pub const MAX_MEM_RANGES: usize = 64;
+/// Maximum number of memory ranges that can be stored in a configuration. The
+/// DTB may describe many ranges, and each exclusion may split a range.
+pub const MAX_MEM_RANGES: usize = 128;
 
 /// Convenience range type.
 pub type MemoryRange = range::Range<MemoryZone>;
@@ -26,6 +33,69 @@ pub type MemoryRange = range::Range<MemoryZone>;
 /// Convenience range set type.
 pub type MemoryConfig = range_set::RangeSet<MAX_MEM_RANGES, MemoryZone>;
 
+/// Convenience range type for named regions of the kernel's virtual address
+/// space.
+pub type VirtualRegion = range::Range<&'static str>;
+
+impl MemoryConfig {
+  /// Iterate over the memory ranges that can be linearly mapped.
+  ///
+  /// # Parameters
+  ///
+  /// * `high_mem_base` - The physical address at which high memory begins.
+  ///
+  /// # Description
+  ///
+  /// Ranges entirely below the high memory base are yielded as-is. A range that
+  /// straddles the high memory base is clipped to the part below the base, and
+  /// ranges entirely at or above the base are skipped. The configuration is not
+  /// modified or copied.
+  ///
+  /// # Returns
+  ///
+  /// An iterator over the linear memory ranges in ascending order.
+  pub fn linear_ranges(&self, high_mem_base: usize) -> impl Iterator<Item = MemoryRange> {
+    self
+      .get_ranges()
+      .iter()
+      .filter_map(move |range| range.split(high_mem_base).ok()?.0)
+  }
+
+  /// Exclude a range that must not be handed to the allocators.
+  ///
+  /// # Parameters
+  ///
+  /// * `excl` - The range to exclude.
+  ///
+  /// # Description
+  ///
+  /// Wraps `exclude_range()`. If the range cannot be excluded, the range and how
+  /// full the configuration is are printed before the error is returned so that
+  /// the caller's panic can be diagnosed.
+  ///
+  /// # Returns
+  ///
+  /// The result of `exclude_range()`.
+  pub fn exclude_reserved_range(
+    &mut self,
+    excl: &MemoryRange,
+  ) -> Result<(), range_set::RangeSetError> {
+    let result = self.exclude_range(excl);
+
+    if result.is_err() {
+      debug_print!(
+        "Failed to exclude {:#x} - {:#x}: {} of {} ranges in use.\n",
+        excl.base,
+        excl.base + (excl.size - 1),
+        self.len(),
+        self.capacity()
+      );
+    }
+
+    result
+  }
+}
+
 /// Handles memory ranges as they are discovered.
 pub trait MemoryRangeHandler {
   /// Performs any architecture-dependent processing on a range.
@@ -44,7 +114,54 @@ pub trait MemoryRangeHandler {
   fn handle_range(&self, config: &mut MemoryConfig, base: usize, size: usize);
 }
 
+/// Find a conflict in a kernel virtual address space layout.
+///
+/// # Parameters
+///
+/// * `virtual_base` - The kernel segment base address.
+/// * `regions` - The regions in the layout.
+///
+/// # Description
+///
+/// A region conflicts with the layout if it is empty, extends beyond the end of
+/// the address space, is not fully contained by the kernel segment, or overlaps
+/// any other region. The regions do not need to be sorted.
+///
+/// # Returns
+///
+/// The name of the first conflicting region, or None if the layout is valid.
+pub fn find_vm_layout_conflict(
+  virtual_base: usize,
+  regions: &[VirtualRegion],
+) -> Option<&'static str> {
+  let segment = VirtualRegion {
+    tag: "Kernel Segment",
+    base: virtual_base,
+    size: 0usize.wrapping_sub(virtual_base),
+  };
+
+  for (i, region) in regions.iter().enumerate() {
+    if region.is_empty() || region.base.checked_add(region.size - 1).is_none() {
+      return Some(region.tag);
+    }
+
+    if !matches!(
+      region.cmp(&segment),
+      Some(range::RangeOrdering::Subset) | Some(range::RangeOrdering::Equal)
+    ) {
+      return Some(region.tag);
+    }
+
+    if regions[i + 1..].iter().any(|other| region.overlaps(other)) {
+      return Some(region.tag);
+    }
+  }
+
+  None
+}
+
 /// Mapping strategies to use when mapping blocks of memory.
+#[derive(Copy, Clone)]
 pub enum MappingStrategy {
   /// A strategy that uses architecture-specific techniques, such as ARM
   /// sections, to map a block of memory using the fewest table entries.
@@ -53,8 +170,133 @@ pub enum MappingStrategy {
   Granular,
 }
 
+/// The kind of entry a translation table descriptor describes.
+#[derive(Copy, Clone, PartialEq)]
+pub enum DescriptorKind {
+  /// The descriptor does not translate any addresses.
+  Invalid,
+  /// The descriptor points to a lower level table.
+  Table,
+  /// The descriptor maps a block of memory larger than a page.
+  Block,
+  /// The descriptor maps a single page.
+  Page,
+}
+
+/// A decoded translation table descriptor.
+#[derive(Copy, Clone, PartialEq)]
+pub struct DescriptorInfo {
+  /// The kind of entry.
+  pub kind: DescriptorKind,
+  /// The physical address of the table, block, or page. Zero if the descriptor
+  /// is invalid.
+  pub phys_addr: usize,
+  /// The block or page is mapped as device memory.
+  pub device: bool,
+  /// The block or page access flag is set.
+  pub access_flag: bool,
+}
+
+impl DescriptorInfo {
+  /// Construct the description of an invalid descriptor.
+  pub const fn invalid() -> Self {
+    DescriptorInfo {
+      kind: DescriptorKind::Invalid,
+      phys_addr: 0,
+      device: false,
+      access_flag: false,
+    }
+  }
+}
+
+impl fmt::Display for DescriptorInfo {
+  /// Format a decoded descriptor for debug output, e.g. `block 0x40200000
+  /// device AF`.
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let kind = match self.kind {
+      DescriptorKind::Invalid => return write!(f, "invalid"),
+      DescriptorKind::Table => return write!(f, "table {:#x}", self.phys_addr),
+      DescriptorKind::Block => "block",
+      DescriptorKind::Page => "page",
+    };
+
+    let mem_type = if self.device { "device" } else { "normal" };
+    write!(f, "{} {:#x} {}", kind, self.phys_addr, mem_type)?;
+
+    if self.access_flag {
+      write!(f, " AF")?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Coalesces the mappings reported by a translation table walk.
+///
+/// A mapping extends the pending range if it is the same kind of entry with the
+/// same attributes and both its virtual and physical addresses continue the
+/// range.
+pub struct MappingCoalescer {
+  pending: Option<(usize, usize, DescriptorInfo)>,
+}
+
+impl MappingCoalescer {
+  /// Construct a coalescer without a pending range.
+  pub const fn new() -> Self {
+    MappingCoalescer { pending: None }
+  }
+
+  /// Add a mapped block or page.
+  ///
+  /// # Parameters
+  ///
+  /// * `virt` - The base virtual address of the mapping.
+  /// * `size` - The size of the mapping.
+  /// * `info` - The decoded descriptor.
+  /// * `emit` - Called with the base virtual address, size, and first decoded
+  ///   descriptor of the pending range if the mapping does not extend it.
+  pub fn add(
+    &mut self,
+    virt: usize,
+    size: usize,
+    info: DescriptorInfo,
+    emit: &mut impl FnMut(usize, usize, &DescriptorInfo),
+  ) {
+    if let Some((pending_virt, pending_size, pending_info)) = &mut self.pending {
+      let extends = info.kind == pending_info.kind
+        && info.device == pending_info.device
+        && info.access_flag == pending_info.access_flag
+        && pending_virt.wrapping_add(*pending_size) == virt
+        && pending_info.phys_addr.wrapping_add(*pending_size) == info.phys_addr;
+
+      if extends {
+        *pending_size += size;
+        return;
+      }
+
+      emit(*pending_virt, *pending_size, pending_info);
+    }
+
+    self.pending = Some((virt, size, info));
+  }
+
+  /// Emit the pending range, if any.
+  ///
+  /// # Parameters
+  ///
+  /// * `emit` - Called with the base virtual address, size, and first decoded
+  ///   descriptor of the pending range.
+  pub fn finish(self, emit: &mut impl FnMut(usize, usize, &DescriptorInfo)) {
+    if let Some((virt, size, info)) = &self.pending {
+      emit(*virt, *size, info);
+    }
+  }
+}
+
 /// Physically-contiguous page block allocator interface.
 pub trait PageAllocator {
+  /// The number of pages in the largest block the allocator can allocate.
+  /// Requests for more pages always fail.
   const MAX_BLOCK_PAGES: usize;
 
   /// Allocate a physically-contiguous block of pages from memory.
@@ -85,7 +327,12 @@ pub trait PageAllocator {
   ///
   /// * `addr` - The physical base address of the block.
   /// * `pages` - The number of pages to free.
-  fn free(&mut self, addr: usize, pages: usize);
+  ///
+  /// # Returns
+  ///
+  /// False if the allocator could not free the block and leaked it, true
+  /// otherwise.
+  fn free(&mut self, addr: usize, pages: usize) -> bool;
 
   /// Get the amount of memory currently allocated by this allocator in bytes.
   fn get_alloc_mem(&self) -> usize;
@@ -167,12 +414,13 @@ impl<const BUFFER_SIZE: usize> PageAllocator for BufferedPageAllocator<BUFFER_SI
   }
 
   /// See `PageAllocator::free`.
-  fn free(&mut self, addr: usize, pages: usize) {
+  fn free(&mut self, addr: usize, pages: usize) -> bool {
     assert_eq!(pages, 1);
     assert!(addr >= self.start_addr && addr < self.end_addr);
     assert!(bits::is_aligned(addr, self.page_size));
     let z = addr >> self.page_shift;
     self.bitmap.clear_bit(z);
+    true
   }
 
   /// Get the amount of memory currently allocated by this allocator in bytes.
@@ -187,3 +435,8 @@ impl<const BUFFER_SIZE: usize> PageAllocator for BufferedPageAllocator<BUFFER_SI
     pages << self.page_shift
   }
 }
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
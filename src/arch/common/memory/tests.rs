@@ -0,0 +1,97 @@
+//! Common Memory Configuration Tests
+
+use super::{MemoryConfig, MemoryRange, MemoryZone};
+use crate::debug_print;
+use crate::test;
+use crate::{check_eq, execute_test};
+use core::ptr;
+
+/// The memory configuration is too large to build on the kernel stack on 64-bit
+/// platforms.
+static mut TEST_MEM_CONFIG: MemoryConfig = MemoryConfig::new(MemoryZone::InvalidZone);
+
+/// Test memory ranges as (base, size) tuples.
+const TEST_RANGES: [(usize, usize); 3] = [
+  (0x0010_0000, 0x0ff0_0000),
+  (0x2000_0000, 0x1000_0000),
+  (0x3800_0000, 0x1000_0000),
+];
+
+/// Run memory configuration tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_linear_ranges);
+}
+
+/// Get the test memory configuration with the test ranges.
+fn get_test_config() -> &'static mut MemoryConfig {
+  let config = unsafe { ptr::addr_of_mut!(TEST_MEM_CONFIG).as_mut().unwrap() };
+  config.clear();
+
+  for (base, size) in TEST_RANGES {
+    config.insert_range(MemoryRange {
+      tag: MemoryZone::LinearMemoryZone,
+      base,
+      size,
+    });
+  }
+
+  config
+}
+
+/// Check the linear ranges yielded for a high memory base.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `config` - The memory configuration.
+/// * `high_mem_base` - The high memory base.
+/// * `expected` - The expected ranges as (base, size) tuples.
+fn check_linear_ranges(
+  context: &mut test::TestContext,
+  config: &MemoryConfig,
+  high_mem_base: usize,
+  expected: &[(usize, usize)],
+) {
+  let mut count = 0;
+
+  for range in config.linear_ranges(high_mem_base) {
+    let matches = expected
+      .get(count)
+      .is_some_and(|&(base, size)| range.base == base && range.size == size);
+    check_eq!(context, matches, true);
+    let matches = range.tag == MemoryZone::LinearMemoryZone;
+    check_eq!(context, matches, true);
+    count += 1;
+  }
+
+  check_eq!(context, count, expected.len());
+}
+
+/// Test iterating over the linear memory ranges.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Ranges entirely below the high memory base, including a range that ends
+/// exactly at the base, are yielded as-is. A range that straddles the base is
+/// clipped, and ranges entirely at or above the base are skipped. The
+/// configuration must not be modified.
+fn test_linear_ranges(context: &mut test::TestContext) {
+  let config = get_test_config();
+
+  check_linear_ranges(context, config, usize::MAX, &TEST_RANGES);
+  check_linear_ranges(context, config, 0x3000_0000, &TEST_RANGES[..2]);
+  check_linear_ranges(context, config, 0x2800_0000, &[TEST_RANGES[0], (0x2000_0000, 0x0800_0000)]);
+  check_linear_ranges(context, config, 0x2000_0000, &TEST_RANGES[..1]);
+  check_linear_ranges(context, config, 0, &[]);
+
+  check_eq!(context, config.len(), TEST_RANGES.len());
+  check_eq!(context, config.get_ranges()[1].size, TEST_RANGES[1].1);
+}
@@ -6,15 +6,18 @@
 // variable and code.
 #![cfg_attr(debug_assertions, allow(unused))]
 
+#[cfg(feature = "kernel_heap")]
+extern crate alloc;
+
 mod arch;
 mod mm;
+mod sched;
 mod support;
 mod sync;
 mod task;
 #[cfg(feature = "module_tests")]
 mod test;
 
-use arch::memory::MemoryZone;
 use core::ops::DerefMut;
 use core::panic::PanicInfo;
 
@@ -25,8 +28,12 @@ use core::panic::PanicInfo;
 /// * `info` - Information about the panic.
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+  // Let a module test catch an expected panic.
+  #[cfg(feature = "module_tests")]
+  arch::panic_guard::resume();
+
   debug_print!("Kernel panic! {} {}\n", info.message(), info.location().unwrap());
-  arch::cpu::halt();
+  arch::cpu::halt_all();
 }
 
 /// Single-threaded kernel initialization.
@@ -40,28 +47,62 @@ extern "C" fn pk_init(config: usize) {
   arch::init(config);
   task::init();
   mm::init();
+  sched::init();
 
   // Run module tests single-threaded.
   #[cfg(feature = "module_tests")]
-  run_module_tests();
+  pk_run_tests();
 
   // Bring up any secondary cores.
-  let mut alloc = mm::get_zone_allocator(MemoryZone::LinearMemoryZone)
-    .as_mut()
-    .unwrap();
-  arch::init_smp(alloc.lock().deref_mut());
+  //
+  //   TODO: Release the secondary cores to `_secondary_start` and join the boot
+  //         barrier once the secondary cores can reach `pk_secondary_init()`.
+  arch::init_smp(mm::get_page_allocator().lock().deref_mut());
+}
+
+/// Secondary core entry point.
+///
+/// # Description
+///
+/// `_secondary_start` jumps here after enabling the MMU on a secondary core.
+/// Caches the core's per-core data area, then brings the core online and
+/// enters the scheduler once all cores have reached the boot barrier. See
+/// `sched::start_secondary_core()`.
+#[unsafe(no_mangle)]
+extern "C" fn pk_secondary_init() -> ! {
+  arch::percpu::init_core();
+
+  sched::start_secondary_core(
+    arch::get_current_core_index(),
+    sched::get_boot_barrier(),
+    pk_scheduler,
+  );
 }
 
 /// Scheduler entry point.
 #[unsafe(no_mangle)]
 extern "C" fn pk_scheduler() -> ! {
-  arch::cpu::halt();
+  // Secondary cores enter the scheduler directly, so cache their per-core data
+  // areas here.
+  arch::percpu::init_core();
+
+  loop {
+    sched::idle();
+  }
 }
 
+/// Module test entry point.
+///
+/// # Description
+///
+/// Runs every module's tests single-threaded and prints the total counts. See
+/// `test::run_all()`.
 #[cfg(feature = "module_tests")]
-fn run_module_tests() {
+#[unsafe(no_mangle)]
+extern "C" fn pk_run_tests() {
+  let mut context = test::TestContext::new();
+
   debug_print!("--- Running Module Tests ---\n");
-  arch::run_tests();
-  mm::run_tests();
-  support::bits::run_tests();
+  test::run_all(&mut context);
+  debug_print!("--- {} pass, {} fail ---\n", context.pass_count, context.fail_count);
 }
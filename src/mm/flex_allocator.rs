@@ -0,0 +1,227 @@
+//! Dynamic Flex Allocator
+//!
+//! The Dynamic Flex Allocator is a per-core page allocator that sits in front
+//! of a global Buddy Page Allocator. Single-page requests are the most common
+//! page allocation, so the flex allocator maintains a small, per-core buffer of
+//! single pages to avoid contending for the global allocator's lock on every
+//! allocation.
+//!
+//! When the buffer drops to a low watermark, the allocator refills the buffer up
+//! to a high watermark from the global allocator while holding the lock once.
+//! When the buffer is full, the allocator returns half of the buffer to the
+//! global allocator. Multi-page requests are passed directly through to the
+//! global allocator.
+//!
+//!   NOTE: A flex allocator is NOT thread-safe. Each core must use its own
+//!         instance with interrupts masked.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::page_allocator::BuddyPageAllocator;
+use crate::arch;
+use crate::arch::memory::PageAllocator;
+use crate::sync::SpinLock;
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// Callback used to retrieve the global allocator backing a flex allocator.
+pub type AllocatorCallback = fn() -> &'static SpinLock<BuddyPageAllocator<'static>>;
+
+/// The Dynamic Flex Allocator buffers up to BUFFER_SIZE single pages allocated
+/// from a global allocator.
+pub struct DynamicFlexAllocator<const BUFFER_SIZE: usize> {
+  get_allocator_cb: AllocatorCallback,
+  pages: [usize; BUFFER_SIZE],
+  count: usize,
+  low_watermark: usize,
+  high_watermark: usize,
+}
+
+impl<const BUFFER_SIZE: usize> DynamicFlexAllocator<BUFFER_SIZE> {
+  /// The number of pages to return to the global allocator when the buffer is
+  /// full.
+  const SPILL_COUNT: usize = (BUFFER_SIZE + 1) >> 1;
+
+  /// Construct a new, empty flex allocator.
+  ///
+  /// # Parameters
+  ///
+  /// * `get_allocator_cb` - Callback to retrieve the global allocator.
+  /// * `low_watermark` - Refill the buffer when a single-page allocation finds
+  ///   this many pages or fewer in the buffer.
+  /// * `high_watermark` - The number of pages in the buffer after a refill.
+  ///
+  /// # Description
+  ///
+  /// A low watermark of 0 only refills an empty buffer. A higher low watermark
+  /// keeps pages on hand so that a burst of allocations does not immediately
+  /// stall on the global allocator's lock.
+  ///
+  ///   NOTE: Panics if the low watermark is not less than the high watermark or
+  ///         the high watermark is larger than the buffer.
+  pub const fn new(
+    get_allocator_cb: AllocatorCallback,
+    low_watermark: usize,
+    high_watermark: usize,
+  ) -> Self {
+    assert!(BUFFER_SIZE > 0);
+    assert!(low_watermark < high_watermark && high_watermark <= BUFFER_SIZE);
+
+    Self {
+      get_allocator_cb,
+      pages: [0; BUFFER_SIZE],
+      count: 0,
+      low_watermark,
+      high_watermark,
+    }
+  }
+
+  /// Get the number of pages currently held in the buffer.
+  pub fn get_buffered_pages(&self) -> usize {
+    self.count
+  }
+
+  /// Return all buffered pages to the global allocator.
+  pub fn drain(&mut self) {
+    self.spill(0);
+  }
+
+  /// Free a batch of blocks.
+  ///
+  /// # Parameters
+  ///
+  /// * `blocks` - A list of tuples with the base address and number of pages
+  ///   of each block.
+  ///
+  /// # Description
+  ///
+  /// Single pages are returned to the buffer while it has room. Larger blocks,
+  /// and single pages that do not fit in the buffer, are returned to the global
+  /// allocator while holding the lock once for the whole batch. Unlike
+  /// `free()`, a full buffer is not spilled.
+  pub fn free_batch(&mut self, blocks: &[(usize, usize)]) {
+    let get_allocator = self.get_allocator_cb;
+    let mut allocator = None;
+
+    for &(addr, pages) in blocks {
+      if pages == 1 && self.count < BUFFER_SIZE {
+        self.pages[self.count] = addr;
+        self.count += 1;
+        continue;
+      }
+
+      allocator
+        .get_or_insert_with(|| get_allocator().lock())
+        .free(addr, pages);
+    }
+  }
+
+  /// Refill the buffer from the global allocator.
+  ///
+  /// # Description
+  ///
+  /// Attempts to allocate enough single pages to bring the buffer up to the
+  /// high watermark. The refill stops early if the global allocator runs out
+  /// of pages.
+  fn refill(&mut self) {
+    let mut allocator = (self.get_allocator_cb)().lock();
+
+    while self.count < self.high_watermark {
+      let Some((addr, _)) = allocator.alloc(1) else {
+        break;
+      };
+
+      self.pages[self.count] = addr;
+      self.count += 1;
+    }
+  }
+
+  /// Return buffered pages to the global allocator.
+  ///
+  /// # Parameters
+  ///
+  /// * `keep` - The number of pages to keep in the buffer.
+  fn spill(&mut self, keep: usize) {
+    if self.count <= keep {
+      return;
+    }
+
+    let mut allocator = (self.get_allocator_cb)().lock();
+
+    while self.count > keep {
+      self.count -= 1;
+      allocator.free(self.pages[self.count], 1);
+    }
+  }
+}
+
+impl<const BUFFER_SIZE: usize> PageAllocator for DynamicFlexAllocator<BUFFER_SIZE> {
+  const MAX_BLOCK_PAGES: usize = BuddyPageAllocator::MAX_BLOCK_PAGES;
+
+  /// See `PageAllocator::alloc`.
+  ///
+  /// # Description
+  ///
+  /// Single pages are served from the buffer. The buffer is refilled first if
+  /// it is at or below the low watermark. Larger blocks are allocated directly
+  /// from the global allocator.
+  fn alloc(&mut self, pages: usize) -> Option<(usize, usize)> {
+    if pages != 1 {
+      return (self.get_allocator_cb)().lock().alloc(pages);
+    }
+
+    if self.count <= self.low_watermark {
+      self.refill();
+    }
+
+    if self.count == 0 {
+      return None;
+    }
+
+    self.count -= 1;
+    Some((self.pages[self.count], 1))
+  }
+
+  /// See `PageAllocator::free`.
+  ///
+  /// # Description
+  ///
+  /// Single pages are returned to the buffer. Larger blocks are returned
+  /// directly to the global allocator.
+  fn free(&mut self, addr: usize, pages: usize) -> bool {
+    if pages != 1 {
+      return (self.get_allocator_cb)().lock().free(addr, pages);
+    }
+
+    if self.count == BUFFER_SIZE {
+      self.spill(BUFFER_SIZE - Self::SPILL_COUNT);
+    }
+
+    self.pages[self.count] = addr;
+    self.count += 1;
+    true
+  }
+
+  /// See `PageAllocator::get_alloc_mem`.
+  ///
+  /// # Description
+  ///
+  /// Buffered pages are allocated from the global allocator's perspective, but
+  /// are still available to the flex allocator's clients.
+  fn get_alloc_mem(&self) -> usize {
+    let buffered = self.count << arch::get_page_shift();
+    (self.get_allocator_cb)().lock().get_alloc_mem() - buffered
+  }
+
+  /// See `PageAllocator::get_free_mem`.
+  fn get_free_mem(&self) -> usize {
+    let buffered = self.count << arch::get_page_shift();
+    (self.get_allocator_cb)().lock().get_free_mem() + buffered
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,352 @@
+//! Dynamic Flex Allocator Tests
+
+use super::DynamicFlexAllocator;
+use crate::arch;
+use crate::arch::memory::{MemoryRange, MemoryZone, PageAllocator};
+use crate::debug_print;
+use crate::mm::page_allocator::BuddyPageAllocator;
+use crate::support::bits;
+use crate::sync::SpinLock;
+use crate::test::{self, memory};
+use crate::{check_eq, check_neq, check_none, check_not_none, execute_test};
+use core::ptr;
+
+/// Size of the buddy page allocator metadata.
+const META_SIZE: usize = BuddyPageAllocator::calc_metadata_size(memory::MEMORY_SIZE);
+
+/// Use the whole test buffer minus the metadata for the page allocator.
+const TEST_MEM_SIZE: usize =
+  bits::align_down(memory::MEMORY_SIZE - META_SIZE, arch::get_page_size());
+
+/// Use a small buffer to exercise refills and spills.
+const TEST_BUFFER_SIZE: usize = 8;
+
+/// Only refill an empty buffer, and refill half of the buffer.
+const TEST_LOW_WATERMARK: usize = 0;
+const TEST_HIGH_WATERMARK: usize = TEST_BUFFER_SIZE / 2;
+
+/// Flex allocator convenience type.
+type TestFlexAllocator = DynamicFlexAllocator<TEST_BUFFER_SIZE>;
+
+/// The global allocator backing the flex allocators under test.
+static mut TEST_ALLOCATOR: Option<SpinLock<BuddyPageAllocator>> = None;
+
+/// The number of times `get_counting_test_allocator()` has been called.
+static mut CALLBACK_COUNT: usize = 0;
+
+/// Test entry-point.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_single_page_refill);
+  execute_test!(context, test_single_page_spill);
+  execute_test!(context, test_multi_page_pass_through);
+  execute_test!(context, test_exhaustion);
+  execute_test!(context, test_free_batch);
+  execute_test!(context, test_free_batch_overflow);
+  execute_test!(context, test_watermarks);
+}
+
+/// Test allocating single pages through the buffer.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first single-page allocation should refill half of the buffer from the
+/// global allocator. Freeing the page and draining the buffer should return
+/// everything to the global allocator.
+fn test_single_page_refill(context: &mut test::TestContext) {
+  init_test_allocator();
+
+  let page_size = arch::get_page_size();
+  let refill = TEST_HIGH_WATERMARK;
+  let mut flex =
+    TestFlexAllocator::new(get_test_allocator, TEST_LOW_WATERMARK, TEST_HIGH_WATERMARK);
+
+  let result = flex.alloc(1);
+  check_not_none!(context, result);
+  check_eq!(context, flex.get_buffered_pages(), refill - 1);
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), refill * page_size);
+  check_eq!(context, flex.get_alloc_mem(), page_size);
+  check_eq!(context, flex.get_free_mem(), TEST_MEM_SIZE - page_size);
+
+  // Allocating the rest of the buffered pages should not touch the global
+  // allocator.
+  for _ in 1..refill {
+    check_not_none!(context, flex.alloc(1));
+  }
+
+  check_eq!(context, flex.get_buffered_pages(), 0);
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), refill * page_size);
+
+  let (addr, pages) = result.unwrap_or((0, 0));
+  check_eq!(context, pages, 1);
+  flex.free(addr, 1);
+  check_eq!(context, flex.get_buffered_pages(), 1);
+
+  flex.drain();
+  check_eq!(context, flex.get_buffered_pages(), 0);
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), (refill - 1) * page_size);
+}
+
+/// Test freeing single pages into a full buffer.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Freeing a page into a full buffer should return half of the buffer to the
+/// global allocator before buffering the freed page.
+fn test_single_page_spill(context: &mut test::TestContext) {
+  init_test_allocator();
+
+  let spill = TestFlexAllocator::SPILL_COUNT;
+  let mut flex =
+    TestFlexAllocator::new(get_test_allocator, TEST_LOW_WATERMARK, TEST_HIGH_WATERMARK);
+  let mut addrs = [0usize; TEST_BUFFER_SIZE + 1];
+
+  // Allocate more pages than the buffer can hold.
+  for addr in &mut addrs {
+    *addr = flex.alloc(1).unwrap_or((0, 0)).0;
+    check_neq!(context, *addr, 0);
+  }
+
+  // Free them all. The last free should spill.
+  for addr in &addrs[..TEST_BUFFER_SIZE] {
+    flex.free(*addr, 1);
+  }
+
+  check_eq!(context, flex.get_buffered_pages(), TEST_BUFFER_SIZE);
+
+  flex.free(addrs[TEST_BUFFER_SIZE], 1);
+  check_eq!(context, flex.get_buffered_pages(), TEST_BUFFER_SIZE - spill + 1);
+
+  flex.drain();
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), 0);
+  check_eq!(context, get_test_allocator().lock().get_free_mem(), TEST_MEM_SIZE);
+  check_eq!(context, flex.get_free_mem(), TEST_MEM_SIZE);
+}
+
+/// Test multi-page allocations bypass the buffer.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_multi_page_pass_through(context: &mut test::TestContext) {
+  init_test_allocator();
+
+  let page_size = arch::get_page_size();
+  let mut flex =
+    TestFlexAllocator::new(get_test_allocator, TEST_LOW_WATERMARK, TEST_HIGH_WATERMARK);
+
+  let result = flex.alloc(4);
+  check_not_none!(context, result);
+  check_eq!(context, flex.get_buffered_pages(), 0);
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), 4 * page_size);
+
+  let (addr, pages) = result.unwrap_or((0, 0));
+  check_eq!(context, pages, 4);
+  flex.free(addr, pages);
+  check_eq!(context, flex.get_buffered_pages(), 0);
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), 0);
+}
+
+/// Test allocating every page through the flex allocator.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_exhaustion(context: &mut test::TestContext) {
+  init_test_allocator();
+
+  let page_size = arch::get_page_size();
+  let mut flex =
+    TestFlexAllocator::new(get_test_allocator, TEST_LOW_WATERMARK, TEST_HIGH_WATERMARK);
+  let mut count = 0;
+
+  while flex.alloc(1).is_some() {
+    count += 1;
+  }
+
+  check_eq!(context, count, TEST_MEM_SIZE / page_size);
+  check_none!(context, flex.alloc(1));
+  check_eq!(context, flex.get_free_mem(), 0);
+}
+
+/// Test freeing a batch of blocks.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The multi-page blocks in the batch are freed while holding the global
+/// allocator's lock once, and the single page is returned to the buffer. A
+/// batch of single pages that fit in the buffer never locks the global
+/// allocator.
+fn test_free_batch(context: &mut test::TestContext) {
+  init_test_allocator();
+
+  let page_size = arch::get_page_size();
+  let refill = TEST_HIGH_WATERMARK;
+  let mut flex =
+    TestFlexAllocator::new(get_counting_test_allocator, TEST_LOW_WATERMARK, TEST_HIGH_WATERMARK);
+  let mut blocks = [(0, 0); 4];
+
+  for (block, pages) in blocks.iter_mut().zip([2, 4, 8, 1]) {
+    *block = flex.alloc(pages).unwrap_or((0, 0));
+    check_eq!(context, block.1, pages);
+  }
+
+  reset_callback_count();
+  flex.free_batch(&blocks);
+  check_eq!(context, get_callback_count(), 1);
+  check_eq!(context, flex.get_buffered_pages(), refill);
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), refill * page_size);
+
+  let single = flex.alloc(1).unwrap_or((0, 0));
+  check_eq!(context, single.1, 1);
+
+  reset_callback_count();
+  flex.free_batch(&[single]);
+  check_eq!(context, get_callback_count(), 0);
+  check_eq!(context, flex.get_buffered_pages(), refill);
+
+  flex.drain();
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), 0);
+}
+
+/// Test freeing a batch of single pages that does not fit in the buffer.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The buffer is filled, and the remaining pages are returned to the global
+/// allocator while holding the lock once.
+fn test_free_batch_overflow(context: &mut test::TestContext) {
+  init_test_allocator();
+
+  let mut flex =
+    TestFlexAllocator::new(get_counting_test_allocator, TEST_LOW_WATERMARK, TEST_HIGH_WATERMARK);
+  let mut blocks = [(0, 0); TEST_BUFFER_SIZE * 2];
+
+  for block in &mut blocks {
+    *block = flex.alloc(1).unwrap_or((0, 0));
+    check_eq!(context, block.1, 1);
+  }
+
+  reset_callback_count();
+  flex.free_batch(&blocks);
+  check_eq!(context, get_callback_count(), 1);
+  check_eq!(context, flex.get_buffered_pages(), TEST_BUFFER_SIZE);
+
+  flex.drain();
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), 0);
+  check_eq!(context, get_test_allocator().lock().get_free_mem(), TEST_MEM_SIZE);
+}
+
+/// Test refilling the buffer at the low watermark.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The buffer is refilled to the high watermark on the first allocation. The
+/// global allocator is not touched again until an allocation finds the buffer
+/// at the low watermark.
+fn test_watermarks(context: &mut test::TestContext) {
+  const LOW_WATERMARK: usize = 2;
+  const HIGH_WATERMARK: usize = 6;
+
+  init_test_allocator();
+  reset_callback_count();
+
+  let mut flex = TestFlexAllocator::new(get_counting_test_allocator, LOW_WATERMARK, HIGH_WATERMARK);
+
+  check_not_none!(context, flex.alloc(1));
+  check_eq!(context, get_callback_count(), 1);
+  check_eq!(context, flex.get_buffered_pages(), HIGH_WATERMARK - 1);
+
+  // Allocate down to the low watermark.
+  for _ in LOW_WATERMARK..HIGH_WATERMARK - 1 {
+    check_not_none!(context, flex.alloc(1));
+  }
+
+  check_eq!(context, get_callback_count(), 1);
+  check_eq!(context, flex.get_buffered_pages(), LOW_WATERMARK);
+
+  check_not_none!(context, flex.alloc(1));
+  check_eq!(context, get_callback_count(), 2);
+  check_eq!(context, flex.get_buffered_pages(), HIGH_WATERMARK - 1);
+}
+
+/// Construct the global test allocator.
+///
+/// # Description
+///
+///   NOTE: Any flex allocator using the previous test allocator must be drained
+///         or discarded before calling.
+fn init_test_allocator() {
+  let virt_base = arch::get_kernel_virtual_base();
+  let phys_addr = memory::get_test_memory_mut().as_ptr() as usize - virt_base;
+  let meta_addr = virt_base + phys_addr + TEST_MEM_SIZE;
+
+  memory::reset_test_memory();
+
+  let avail = &[MemoryRange {
+    tag: MemoryZone::InvalidZone,
+    base: phys_addr,
+    size: TEST_MEM_SIZE,
+  }];
+
+  // Assume this will never fail. If it does, something is wrong with the test
+  // setup.
+  let allocator =
+    BuddyPageAllocator::new(phys_addr, TEST_MEM_SIZE, meta_addr as *mut u8, avail).unwrap();
+
+  unsafe {
+    TEST_ALLOCATOR = Some(SpinLock::new(allocator));
+  }
+}
+
+/// Flex allocator callback for the global test allocator.
+fn get_test_allocator() -> &'static SpinLock<BuddyPageAllocator<'static>> {
+  unsafe {
+    ptr::addr_of!(TEST_ALLOCATOR)
+      .as_ref()
+      .unwrap()
+      .as_ref()
+      .unwrap()
+  }
+}
+
+/// Flex allocator callback that counts the number of times the global test
+/// allocator is retrieved. The flex allocator retrieves the global allocator
+/// once for each lock acquisition.
+fn get_counting_test_allocator() -> &'static SpinLock<BuddyPageAllocator<'static>> {
+  unsafe { CALLBACK_COUNT += 1 };
+  get_test_allocator()
+}
+
+/// Reset the callback count.
+fn reset_callback_count() {
+  unsafe { CALLBACK_COUNT = 0 };
+}
+
+/// Get the callback count.
+fn get_callback_count() -> usize {
+  unsafe { CALLBACK_COUNT }
+}
@@ -0,0 +1,273 @@
+//! Kernel Heap
+//!
+//! The kernel heap implements `GlobalAlloc` to make the `alloc` crate available
+//! to the kernel. Small allocations are served from size-classed slabs managed
+//! by Slab Managers. Large allocations, and allocations requiring an alignment
+//! larger than a slab object guarantees, are served directly by the page
+//! allocator in power-of-2 page blocks.
+//!
+//! Size classes are powers of 2 from `2^MIN_CLASS_SHIFT` bytes up to
+//! `2^MAX_CLASS_SHIFT` bytes:
+//!
+//!     Class    Size (Bytes)
+//!     ---------------------
+//!     0        16
+//!     1        32
+//!     2        64
+//!     3        128
+//!     4        256
+//!     5        512
+//!     6        1024
+//!     7        2048
+//!
+//! Slab objects are only guaranteed to be aligned on a machine word boundary.
+//!
+//!   NOTE: The heap does not use per-core caching. Each size class is protected
+//!         by its own lock.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::flex_allocator::AllocatorCallback;
+use super::page_allocator::BuddyPageAllocator;
+use super::slab_allocator::SlabManager;
+use crate::arch;
+use crate::arch::memory::PageAllocator;
+use crate::support::bits;
+use crate::sync::SpinLock;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::alloc::{GlobalAlloc, Layout};
+use core::{cmp, ptr};
+
+/// The smallest size class shift.
+const MIN_CLASS_SHIFT: usize = 4;
+
+/// The largest size class shift.
+const MAX_CLASS_SHIFT: usize = 11;
+
+/// The largest size served by a slab.
+const MAX_CLASS_SIZE: usize = 1 << MAX_CLASS_SHIFT;
+
+/// Placeholder object type for a size class.
+struct HeapBlock<const SIZE: usize>([u8; SIZE]);
+
+/// Slab Manager convenience type for a size class.
+type HeapSlabManager<const SIZE: usize> =
+  SpinLock<SlabManager<BuddyPageAllocator<'static>, HeapBlock<SIZE>>>;
+
+/// The kernel heap.
+pub struct KernelHeap {
+  get_allocator_cb: AllocatorCallback,
+  class_16: HeapSlabManager<16>,
+  class_32: HeapSlabManager<32>,
+  class_64: HeapSlabManager<64>,
+  class_128: HeapSlabManager<128>,
+  class_256: HeapSlabManager<256>,
+  class_512: HeapSlabManager<512>,
+  class_1024: HeapSlabManager<1024>,
+  class_2048: HeapSlabManager<2048>,
+}
+
+/// The kernel heap only accesses its Slab Managers and the page allocator
+/// through spin locks.
+unsafe impl Sync for KernelHeap {}
+
+impl KernelHeap {
+  /// Construct a new kernel heap.
+  ///
+  /// # Parameters
+  ///
+  /// * `get_allocator_cb` - Callback to retrieve the page allocator.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: The page allocator MUST allocate from linear memory.
+  pub const fn new(get_allocator_cb: AllocatorCallback) -> Self {
+    Self {
+      get_allocator_cb,
+      class_16: SpinLock::new(SlabManager::new()),
+      class_32: SpinLock::new(SlabManager::new()),
+      class_64: SpinLock::new(SlabManager::new()),
+      class_128: SpinLock::new(SlabManager::new()),
+      class_256: SpinLock::new(SlabManager::new()),
+      class_512: SpinLock::new(SlabManager::new()),
+      class_1024: SpinLock::new(SlabManager::new()),
+      class_2048: SpinLock::new(SlabManager::new()),
+    }
+  }
+
+  /// Get the size class for a layout.
+  ///
+  /// # Parameters
+  ///
+  /// * `layout` - The allocation layout.
+  ///
+  /// # Returns
+  ///
+  /// The size class index, or None if the layout must be served by the page
+  /// allocator.
+  fn get_size_class(layout: &Layout) -> Option<usize> {
+    if layout.size() > MAX_CLASS_SIZE || layout.align() > bits::WORD_BYTES {
+      return None;
+    }
+
+    let shift = cmp::max(bits::ceil_log2(layout.size()), MIN_CLASS_SHIFT);
+    Some(shift - MIN_CLASS_SHIFT)
+  }
+
+  /// Get the number of pages required for a layout.
+  ///
+  /// # Parameters
+  ///
+  /// * `layout` - The allocation layout.
+  ///
+  /// # Description
+  ///
+  /// Page blocks are aligned to their size, so the block must be at least as
+  /// large as the requested alignment.
+  ///
+  /// # Returns
+  ///
+  /// The number of pages rounded up to the nearest power of 2.
+  fn get_block_pages(layout: &Layout) -> usize {
+    let size = cmp::max(layout.size(), layout.align());
+    let pages = (size + arch::get_page_size() - 1) >> arch::get_page_shift();
+    1 << bits::ceil_log2(pages)
+  }
+
+  /// Allocate an object from a size class.
+  ///
+  /// # Parameters
+  ///
+  /// * `class` - The size class's Slab Manager.
+  ///
+  /// # Returns
+  ///
+  /// The virtual address of the object, or null if unable to allocate.
+  fn alloc_from_class<const SIZE: usize>(&self, class: &HeapSlabManager<SIZE>) -> *mut u8 {
+    match class.lock().alloc((self.get_allocator_cb)()) {
+      Some(addr) => addr as *mut u8,
+      None => ptr::null_mut(),
+    }
+  }
+
+  /// Allocate a page block.
+  ///
+  /// # Parameters
+  ///
+  /// * `layout` - The allocation layout.
+  ///
+  /// # Returns
+  ///
+  /// The linear virtual address of the block, or null if unable to allocate.
+  fn alloc_pages(&self, layout: &Layout) -> *mut u8 {
+    let pages = Self::get_block_pages(layout);
+
+    if pages > BuddyPageAllocator::MAX_BLOCK_PAGES {
+      return ptr::null_mut();
+    }
+
+    match (self.get_allocator_cb)().lock().alloc(pages) {
+      Some((addr, _)) => (arch::get_kernel_virtual_base() + addr) as *mut u8,
+      None => ptr::null_mut(),
+    }
+  }
+
+  /// Free a page block.
+  ///
+  /// # Parameters
+  ///
+  /// * `addr` - The linear virtual address of the block.
+  /// * `layout` - The allocation layout.
+  fn free_pages(&self, addr: usize, layout: &Layout) {
+    let pages = Self::get_block_pages(layout);
+    let phys_addr = addr - arch::get_kernel_virtual_base();
+    (self.get_allocator_cb)().lock().free(phys_addr, pages);
+  }
+
+  /// Get the number of objects allocated from a size class.
+  ///
+  /// # Parameters
+  ///
+  /// * `class` - The size class index.
+  ///
+  /// # Returns
+  ///
+  /// The number of objects allocated.
+  pub fn get_objects_allocated(&self, class: usize) -> usize {
+    match class {
+      0 => self.class_16.lock().get_objects_allocated(),
+      1 => self.class_32.lock().get_objects_allocated(),
+      2 => self.class_64.lock().get_objects_allocated(),
+      3 => self.class_128.lock().get_objects_allocated(),
+      4 => self.class_256.lock().get_objects_allocated(),
+      5 => self.class_512.lock().get_objects_allocated(),
+      6 => self.class_1024.lock().get_objects_allocated(),
+      7 => self.class_2048.lock().get_objects_allocated(),
+      _ => 0,
+    }
+  }
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+  /// See `GlobalAlloc::alloc()`.
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    // Mask interrupts for the duration of the allocation. An interrupt handler
+    // allocating on the same core would otherwise deadlock on a held lock.
+    let irq_state = arch::interrupts::save_and_mask_all_interrupts();
+
+    let ptr = match Self::get_size_class(&layout) {
+      Some(0) => self.alloc_from_class(&self.class_16),
+      Some(1) => self.alloc_from_class(&self.class_32),
+      Some(2) => self.alloc_from_class(&self.class_64),
+      Some(3) => self.alloc_from_class(&self.class_128),
+      Some(4) => self.alloc_from_class(&self.class_256),
+      Some(5) => self.alloc_from_class(&self.class_512),
+      Some(6) => self.alloc_from_class(&self.class_1024),
+      Some(7) => self.alloc_from_class(&self.class_2048),
+      _ => self.alloc_pages(&layout),
+    };
+
+    arch::interrupts::restore_interrupt_state(irq_state);
+
+    ptr
+  }
+
+  /// See `GlobalAlloc::dealloc()`.
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    let addr = ptr as usize;
+    let irq_state = arch::interrupts::save_and_mask_all_interrupts();
+
+    match Self::get_size_class(&layout) {
+      Some(0) => self.class_16.lock().free(addr),
+      Some(1) => self.class_32.lock().free(addr),
+      Some(2) => self.class_64.lock().free(addr),
+      Some(3) => self.class_128.lock().free(addr),
+      Some(4) => self.class_256.lock().free(addr),
+      Some(5) => self.class_512.lock().free(addr),
+      Some(6) => self.class_1024.lock().free(addr),
+      Some(7) => self.class_2048.lock().free(addr),
+      _ => self.free_pages(addr, &layout),
+    }
+
+    arch::interrupts::restore_interrupt_state(irq_state);
+  }
+}
+
+/// The global kernel heap.
+///
+///   NOTE: The heap must not be used before the memory management module is
+///         initialized.
+#[global_allocator]
+static KERNEL_HEAP: KernelHeap = KernelHeap::new(super::get_page_allocator);
+
+/// Get the global kernel heap.
+pub fn get_kernel_heap() -> &'static KernelHeap {
+  &KERNEL_HEAP
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,215 @@
+//! Kernel Heap Tests
+
+use super::{KernelHeap, MAX_CLASS_SIZE};
+use crate::arch;
+use crate::arch::memory::{MemoryRange, MemoryZone, PageAllocator};
+use crate::debug_print;
+use crate::mm::page_allocator::BuddyPageAllocator;
+use crate::support::bits;
+use crate::sync::SpinLock;
+use crate::test::{self, memory};
+use crate::{check_eq, check_neq, check_none, check_optional, execute_test};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+
+/// Size of the buddy page allocator metadata.
+const META_SIZE: usize = BuddyPageAllocator::calc_metadata_size(memory::MEMORY_SIZE);
+
+/// Use the whole test buffer minus the metadata for the page allocator.
+const TEST_MEM_SIZE: usize =
+  bits::align_down(memory::MEMORY_SIZE - META_SIZE, arch::get_page_size());
+
+/// The page allocator backing the heaps under test.
+static mut TEST_ALLOCATOR: Option<SpinLock<BuddyPageAllocator>> = None;
+
+/// Test entry-point.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_size_classes);
+  execute_test!(context, test_slab_allocation);
+  execute_test!(context, test_page_allocation);
+  execute_test!(context, test_aligned_allocation);
+  execute_test!(context, test_global_heap);
+}
+
+/// Test mapping layouts to size classes.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_size_classes(context: &mut test::TestContext) {
+  let class = |size, align| {
+    let layout = Layout::from_size_align(size, align).unwrap();
+    KernelHeap::get_size_class(&layout)
+  };
+
+  check_optional!(context, class(1, 1), 0);
+  check_optional!(context, class(16, 8), 0);
+  check_optional!(context, class(17, 8), 1);
+  check_optional!(context, class(100, 4), 3);
+  check_optional!(context, class(MAX_CLASS_SIZE, 1), 7);
+  check_none!(context, class(MAX_CLASS_SIZE + 1, 1));
+  check_none!(context, class(16, bits::WORD_BYTES << 1));
+}
+
+/// Test small allocations are served by the slabs.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_slab_allocation(context: &mut test::TestContext) {
+  init_test_allocator();
+
+  let heap = KernelHeap::new(get_test_allocator);
+  let layout = Layout::from_size_align(24, 8).unwrap();
+
+  let a = unsafe { heap.alloc(layout) };
+  let b = unsafe { heap.alloc(layout) };
+  check_neq!(context, a as usize, 0);
+  check_neq!(context, b as usize, 0);
+  check_neq!(context, a as usize, b as usize);
+  check_eq!(context, bits::is_aligned(a as usize, bits::WORD_BYTES), true);
+  check_eq!(context, heap.get_objects_allocated(1), 2);
+
+  // Writing to the objects will cause an exception if the memory is invalid.
+  unsafe {
+    a.write_bytes(0xab, layout.size());
+    b.write_bytes(0xcd, layout.size());
+    check_eq!(context, *a, 0xab);
+    check_eq!(context, *b, 0xcd);
+  }
+
+  unsafe {
+    heap.dealloc(a, layout);
+    heap.dealloc(b, layout);
+  }
+
+  check_eq!(context, heap.get_objects_allocated(1), 0);
+}
+
+/// Test large allocations are served by the page allocator.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_page_allocation(context: &mut test::TestContext) {
+  init_test_allocator();
+
+  let page_size = arch::get_page_size();
+  let heap = KernelHeap::new(get_test_allocator);
+  let layout = Layout::from_size_align(page_size * 3, 8).unwrap();
+
+  let a = unsafe { heap.alloc(layout) };
+  check_neq!(context, a as usize, 0);
+  check_eq!(context, bits::is_aligned(a as usize, page_size), true);
+
+  // Three pages should round up to a four page block.
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), page_size * 4);
+
+  unsafe {
+    heap.dealloc(a, layout);
+  }
+
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), 0);
+
+  // An allocation larger than the page allocator's largest block should fail.
+  let max_size = BuddyPageAllocator::MAX_BLOCK_PAGES * page_size;
+  let layout = Layout::from_size_align(max_size + 1, 8).unwrap();
+  let a = unsafe { heap.alloc(layout) };
+  check_eq!(context, a as usize, 0);
+}
+
+/// Test over-aligned allocations fall back to page blocks.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_aligned_allocation(context: &mut test::TestContext) {
+  init_test_allocator();
+
+  let page_size = arch::get_page_size();
+  let heap = KernelHeap::new(get_test_allocator);
+  let align = page_size << 2;
+  let layout = Layout::from_size_align(32, align).unwrap();
+
+  let a = unsafe { heap.alloc(layout) };
+  check_neq!(context, a as usize, 0);
+  check_eq!(context, bits::is_aligned(a as usize, align), true);
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), align);
+
+  unsafe {
+    heap.dealloc(a, layout);
+  }
+
+  check_eq!(context, get_test_allocator().lock().get_alloc_mem(), 0);
+}
+
+/// Test the `alloc` crate through the global heap.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_global_heap(context: &mut test::TestContext) {
+  let heap = super::get_kernel_heap();
+  let objs = heap.get_objects_allocated(0);
+
+  let b = Box::new(42usize);
+  check_eq!(context, *b, 42);
+  check_eq!(context, heap.get_objects_allocated(0), objs + 1);
+
+  drop(b);
+  check_eq!(context, heap.get_objects_allocated(0), objs);
+
+  let alloc_mem = crate::mm::get_page_allocator().lock().get_alloc_mem();
+  let mut v: Vec<usize> = Vec::with_capacity(4096);
+
+  for i in 0..4096 {
+    v.push(i);
+  }
+
+  check_eq!(context, v.len(), 4096);
+  check_eq!(context, v[4095], 4095);
+
+  drop(v);
+  check_eq!(context, crate::mm::get_page_allocator().lock().get_alloc_mem(), alloc_mem);
+}
+
+/// Construct the test page allocator.
+fn init_test_allocator() {
+  let virt_base = arch::get_kernel_virtual_base();
+  let phys_addr = memory::get_test_memory_mut().as_ptr() as usize - virt_base;
+  let meta_addr = virt_base + phys_addr + TEST_MEM_SIZE;
+
+  memory::reset_test_memory();
+
+  let avail = &[MemoryRange {
+    tag: MemoryZone::InvalidZone,
+    base: phys_addr,
+    size: TEST_MEM_SIZE,
+  }];
+
+  // Assume this will never fail. If it does, something is wrong with the test
+  // setup.
+  let allocator =
+    BuddyPageAllocator::new(phys_addr, TEST_MEM_SIZE, meta_addr as *mut u8, avail).unwrap();
+
+  unsafe {
+    TEST_ALLOCATOR = Some(SpinLock::new(allocator));
+  }
+}
+
+/// Heap callback for the test page allocator.
+fn get_test_allocator() -> &'static SpinLock<BuddyPageAllocator<'static>> {
+  unsafe {
+    ptr::addr_of!(TEST_ALLOCATOR)
+      .as_ref()
+      .unwrap()
+      .as_ref()
+      .unwrap()
+  }
+}
@@ -1,16 +1,23 @@
 //! Memory Management
 
+mod flex_allocator;
+#[cfg(feature = "kernel_heap")]
+mod heap;
 mod page_allocator;
 mod slab_allocator;
+#[cfg(feature = "module_tests")]
+mod tests;
 
 use crate::arch;
-use crate::arch::memory::{MemoryConfig, MemoryRange, MemoryZone};
+use crate::arch::memory::{MappingStrategy, MemoryConfig, MemoryRange, MemoryZone, PageAllocator};
 use crate::debug_print;
-use crate::support::bits;
+use crate::support::addr::PhysAddr;
+use crate::support::{bits, range};
 use crate::sync::{SpinLock, SpinLockGuard};
 #[cfg(feature = "module_tests")]
 use crate::test;
-use core::ptr;
+use core::{mem, ptr, slice};
+use flex_allocator::DynamicFlexAllocator;
 use page_allocator::BuddyPageAllocator;
 
 /// Tracks the overall memory ranges covered by each zone and the required
@@ -23,9 +30,26 @@ struct ZoneInfo {
   end_index: usize,
 }
 
+/// A range of virtual addresses.
+pub type VirtualRange = range::Range<()>;
+
 /// Per-core page buffer size.
 const PER_CORE_PAGE_BUFFER_SIZE: usize = 256;
 
+/// Per-core page buffer watermarks. See `DynamicFlexAllocator::new()`.
+const PER_CORE_PAGE_BUFFER_LOW_WATERMARK: usize = PER_CORE_PAGE_BUFFER_SIZE / 8;
+const PER_CORE_PAGE_BUFFER_HIGH_WATERMARK: usize = PER_CORE_PAGE_BUFFER_SIZE / 2;
+
+/// Per-core flex allocator convenience type.
+type CoreFlexAllocator = DynamicFlexAllocator<PER_CORE_PAGE_BUFFER_SIZE>;
+
+/// Convenience initializer for the per-core flex allocator array.
+const FLEX_ALLOCATOR_INITIALIZER: CoreFlexAllocator = CoreFlexAllocator::new(
+  get_page_allocator,
+  PER_CORE_PAGE_BUFFER_LOW_WATERMARK,
+  PER_CORE_PAGE_BUFFER_HIGH_WATERMARK,
+);
+
 /// Total number of zone allocators and their indices.
 const ZONE_ALLOCATOR_COUNT: usize = 2;
 
@@ -46,6 +70,10 @@ static mut ZONE_ALLOCATOR_MEMORY_CONFIG: MemoryConfig = MemoryConfig::new(Memory
 static mut ZONE_ALLOCATORS: [Option<SpinLock<BuddyPageAllocator>>; ZONE_ALLOCATOR_COUNT] =
   [ZONE_ALLOCATOR_INITIALIZER; ZONE_ALLOCATOR_COUNT];
 
+/// The per-core flex allocators. Each core has its own page buffer. See
+/// `init_flex_allocators()`.
+static mut FLEX_ALLOCATORS: &mut [CoreFlexAllocator] = &mut [];
+
 /// Initialize the memory management module.
 ///
 /// # Description
@@ -58,6 +86,7 @@ pub fn init() {
   }
 
   init_allocators();
+  init_flex_allocators();
 
   debug_print!("mm init complete.\n");
 }
@@ -75,8 +104,170 @@ pub fn get_zone_allocator(
   &mut allocators[index]
 }
 
+/// Get the global page allocator.
+///
+/// # Description
+///
+/// The global page allocator is the linear memory zone allocator. Pages
+/// allocated from the linear memory zone are always linearly mapped into the
+/// kernel's address space.
+///
+///   NOTE: Panics if called before `init()`.
+pub fn get_page_allocator() -> &'static SpinLock<BuddyPageAllocator<'static>> {
+  get_zone_allocator(MemoryZone::LinearMemoryZone)
+    .as_ref()
+    .unwrap()
+}
+
+/// Get the flex allocator for the current core.
+///
+/// # Description
+///
+/// The flex allocator buffers single pages allocated from the global page
+/// allocator. See `get_page_allocator()`.
+///
+///   NOTE: Interrupts must be masked for as long as the caller holds the
+///         reference to prevent the task from moving to another core.
+///
+///   NOTE: Panics if called before `init()`.
+pub fn global_flex_allocator() -> &'static mut DynamicFlexAllocator<PER_CORE_PAGE_BUFFER_SIZE> {
+  let core_idx = arch::get_current_core_index();
+  let allocators = unsafe { ptr::addr_of_mut!(FLEX_ALLOCATORS).as_mut().unwrap() };
+  &mut allocators[core_idx]
+}
+
+/// Map a range of virtual addresses in the kernel segment to a range of
+/// physical addresses.
+///
+/// # Parameters
+///
+/// * `pages_start` - The physical address of the starting page table.
+/// * `region` - The virtual address range.
+/// * `phys_base` - Base of the physical address range.
+/// * `device` - Whether the range maps to device memory.
+/// * `allocator` - The allocator that will provide new table pages.
+/// * `strategy` - The mapping strategy.
+///
+/// # Description
+///
+/// Maps the physical range `[phys_base, phys_base + region.size)` to the
+/// virtual range using the architecture's page tables. The virtual range does
+/// not need to be the linear mapping of the physical range.
+///
+/// # Assumptions
+///
+/// * The starting page table is in linear memory.
+/// * The allocator *must* allocate pages in linear memory.
+pub fn map(
+  pages_start: PhysAddr,
+  region: &VirtualRange,
+  phys_base: PhysAddr,
+  device: bool,
+  allocator: &mut impl PageAllocator,
+  strategy: MappingStrategy,
+) {
+  arch::mm::map_memory(
+    arch::get_kernel_virtual_base(),
+    pages_start.as_usize(),
+    region.base,
+    phys_base.as_usize(),
+    region.size,
+    device,
+    allocator,
+    strategy,
+  );
+}
+
+/// Unmap a range of virtual addresses in the kernel segment.
+///
+/// # Parameters
+///
+/// * `pages_start` - The physical address of the starting page table.
+/// * `region` - The virtual address range.
+///
+/// # Description
+///
+/// Clears the mapping and invalidates the TLB for the range. Sections must be
+/// unmapped in their entirety.
+///
+///   NOTE: Tables that become empty are not freed.
+///
+/// # Assumptions
+///
+/// The starting page table is in linear memory.
+pub fn unmap(pages_start: PhysAddr, region: &VirtualRange) {
+  arch::mm::unmap_memory(
+    arch::get_kernel_virtual_base(),
+    pages_start.as_usize(),
+    region.base,
+    region.size,
+  );
+}
+
 /// Initialize the allocators.
 fn init_allocators() {
+  let allocators = unsafe { ptr::addr_of_mut!(ZONE_ALLOCATORS).as_mut().unwrap() };
+  let alloc_config = unsafe {
+    ptr::addr_of_mut!(ZONE_ALLOCATOR_MEMORY_CONFIG)
+      .as_mut()
+      .unwrap()
+  };
+
+  init_zone_allocators(allocators, alloc_config, arch::get_memory_config());
+}
+
+/// Initialize the per-core flex allocators.
+///
+/// # Description
+///
+/// Allocates one flex allocator for each core in the device tree from the
+/// global page allocator rather than reserving space for the maximum number of
+/// cores. The allocators are in linear memory, so they are accessed through the
+/// linear mapping.
+fn init_flex_allocators() {
+  let core_count = arch::get_device_tree().get_core_config().get_core_count();
+  let size = core_count * mem::size_of::<CoreFlexAllocator>();
+  let pages = bits::align_up(size, arch::get_page_size()) >> arch::get_page_shift();
+
+  let (base, _) = get_page_allocator()
+    .lock()
+    .allocate(pages)
+    .expect("Failed to allocate the per-core flex allocators.");
+
+  let addr = (arch::get_kernel_virtual_base() + base) as *mut CoreFlexAllocator;
+
+  let allocators = unsafe {
+    for core_idx in 0..core_count {
+      addr.add(core_idx).write(FLEX_ALLOCATOR_INITIALIZER);
+    }
+
+    slice::from_raw_parts_mut(addr, core_count)
+  };
+
+  unsafe { FLEX_ALLOCATORS = allocators };
+
+  debug_print!("Flex allocators @ {:#x}\n", addr as usize);
+}
+
+/// Construct the zone allocators for a memory configuration.
+///
+/// # Parameters
+///
+/// * `allocators` - The zone allocators to construct.
+/// * `alloc_config` - Receives the memory ranges served by the allocators.
+/// * `mem_config` - The memory configuration.
+///
+/// # Description
+///
+/// Computes the metadata size required for each zone, carves the metadata out
+/// of the end of a linear memory range, then constructs an allocator for each
+/// zone present in the memory configuration. The metadata is excluded from the
+/// ranges available to the allocators.
+fn init_zone_allocators<'alloc>(
+  allocators: &mut [Option<SpinLock<BuddyPageAllocator<'alloc>>>; ZONE_ALLOCATOR_COUNT],
+  alloc_config: &mut MemoryConfig,
+  mem_config: &MemoryConfig,
+) {
   const ZONE_INFO_INITIALIZER: ZoneInfo = ZoneInfo {
     range: MemoryRange {
       tag: MemoryZone::InvalidZone,
@@ -89,21 +280,14 @@ fn init_allocators() {
     end_index: 0,
   };
 
-  // Scan the system memory configuration and aggregate the physical memory
-  // ranges into the zones.
-  let mem_config = arch::get_device_tree().get_memory_config();
+  // Scan the memory configuration and aggregate the physical memory ranges into
+  // the zones.
   let mut zone_info: [ZoneInfo; ZONE_ALLOCATOR_COUNT] =
     [ZONE_INFO_INITIALIZER; ZONE_ALLOCATOR_COUNT];
   init_zone_info(&mut zone_info, mem_config);
 
   // Compute the total metadata size and find a base address in a linear memory
   // range for the metadata.
-  let allocators = unsafe { ptr::addr_of_mut!(ZONE_ALLOCATORS).as_mut().unwrap() };
-  let alloc_config = unsafe {
-    ptr::addr_of_mut!(ZONE_ALLOCATOR_MEMORY_CONFIG)
-      .as_mut()
-      .unwrap()
-  };
   let meta_base = init_allocator_memory_config(alloc_config, mem_config, &zone_info);
 
   // The metadata for all zones is guaranteed to be in linear memory, so we can
@@ -226,7 +410,9 @@ fn init_allocator_memory_config(
     size: meta_total,
   };
 
-  alloc_config.exclude_range(&excl);
+  alloc_config
+    .exclude_reserved_range(&excl)
+    .expect("Failed to exclude the allocator metadata from the memory layout.");
 
   meta_base
 }
@@ -250,10 +436,11 @@ fn get_zone_index(zone: MemoryZone) -> Option<usize> {
 
 /// Run the memory management tests.
 #[cfg(feature = "module_tests")]
-pub fn run_tests() {
-  let mut context = test::TestContext::new();
-  debug_print!(" mm:\n");
-  page_allocator::run_tests(&mut context);
-  slab_allocator::run_tests(&mut context);
-  debug_print!("  {} pass, {} fail\n", context.pass_count, context.fail_count);
+pub fn run_tests(context: &mut test::TestContext) {
+  page_allocator::run_tests(context);
+  flex_allocator::run_tests(context);
+  slab_allocator::run_tests(context);
+  #[cfg(feature = "kernel_heap")]
+  heap::run_tests(context);
+  tests::run_tests(context);
 }
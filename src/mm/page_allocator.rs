@@ -4,17 +4,24 @@
 mod tests;
 
 use crate::arch;
-use crate::arch::memory::{MemoryRange, PageAllocator};
+use crate::arch::memory::{MemoryRange, MemoryZone, PageAllocator};
+use crate::debug_print;
 use crate::support::bits;
-use crate::task::Task;
+use crate::sync::{SpinLock, SpinLockGuard};
+use crate::task::{LocalMapping, Task};
 #[cfg(feature = "module_tests")]
 use crate::test;
+use core::ops::{Deref, DerefMut};
 use core::{cmp, ptr, slice};
 
 /// Support blocks that are up to Page Size * 2^10 bytes. For example, with a
 /// 4 KiB page size, the largest block size is 4 MiB.
 const BLOCK_LEVELS: usize = 11;
 
+/// The maximum number of block nodes the allocator maps at once. See
+/// `add_to_list()` and `remove_from_list()`.
+const MAX_NODE_MAPPINGS: usize = 3;
+
 /// Linked-list node placed at the beginning of each unallocated block.
 #[repr(C)]
 struct BlockNode {
@@ -53,6 +60,27 @@ impl BlockNode {
   }
 }
 
+/// A block's linked-list node mapped into the kernel's address space. The node
+/// is unmapped when the reference is dropped.
+struct MappedBlockNode<'task> {
+  node: &'task mut BlockNode,
+  _mapping: LocalMapping<'task>,
+}
+
+impl Deref for MappedBlockNode<'_> {
+  type Target = BlockNode;
+
+  fn deref(&self) -> &BlockNode {
+    self.node
+  }
+}
+
+impl DerefMut for MappedBlockNode<'_> {
+  fn deref_mut(&mut self) -> &mut BlockNode {
+    self.node
+  }
+}
+
 /// Block level metadata
 #[derive(Default)]
 struct BlockLevel {
@@ -187,14 +215,15 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   /// Verifies that the pointer is page-aligned and that the node's checksum is
   /// correct.
   ///
-  ///   NOTE: `get_block_node()` and `unget_block_node()` are wrappers for
-  ///         `Task::map_page()` and `Task::unmap_page()`. Calls must follow the
-  ///         same stack semantics.
+  ///   NOTE: The node is mapped with `Task::map_page_scoped()` and unmapped when
+  ///         the reference is dropped. References must be dropped in reverse
+  ///         order.
   ///
   /// # Returns
   ///
-  /// A node reference.
-  fn get_block_node(addr: usize) -> &'alloc BlockNode {
+  /// A mapped node reference, or None if the current task's local mapping
+  /// table is full.
+  fn get_block_node(addr: usize) -> Option<MappedBlockNode<'alloc>> {
     Self::get_block_node_mut(addr)
   }
 
@@ -209,17 +238,16 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   /// Verifies that the pointer is page-aligned and that the node's checksum is
   /// correct.
   ///
-  ///   NOTE: `get_block_node_mut()` and `unget_block_node()` are wrappers for
-  ///         `Task::map_page()` and `Task::unmap_page()`. Calls must follow the
-  ///         same stack semantics.
+  ///   NOTE: See `get_block_node()`.
   ///
   /// # Returns
   ///
-  /// A mutable node reference.
-  fn get_block_node_mut(addr: usize) -> &'alloc mut BlockNode {
-    let node = Self::get_block_node_unchecked_mut(addr);
+  /// A mapped, mutable node reference, or None if the current task's local
+  /// mapping table is full.
+  fn get_block_node_mut(addr: usize) -> Option<MappedBlockNode<'alloc>> {
+    let node = Self::get_block_node_unchecked_mut(addr)?;
     assert!(node.verify_checksum());
-    node
+    Some(node)
   }
 
   /// Get an unchecked, mutable reference to a block's linked-list node.
@@ -233,30 +261,39 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   /// Verifies that the pointer is page-aligned, but does not verify the check-
   /// sum. Used when the node is not expected to be initialized.
   ///
-  ///   NOTE: `get_block_node_unchecked_mut()` and `unget_block_node()` are
-  ///         wrappers for `Task::map_page()` and `Task::unmap_page()`. Calls
-  ///         must follow the same stack semantics.
+  ///   NOTE: See `get_block_node()`.
   ///
   /// # Returns
   ///
-  /// A mutable node reference assumed to be uninitialized.
-  fn get_block_node_unchecked_mut(addr: usize) -> &'alloc mut BlockNode {
+  /// A mapped, mutable node reference assumed to be uninitialized, or None if
+  /// the current task's local mapping table is full.
+  fn get_block_node_unchecked_mut(addr: usize) -> Option<MappedBlockNode<'alloc>> {
     let page_size = arch::get_page_size();
     assert_eq!(bits::align_down(addr, page_size), addr);
 
-    let page = Task::get_current_task_mut().map_page(addr);
-    unsafe { (page as *mut BlockNode).as_mut().unwrap() }
+    let (page, mapping) = Task::get_current_task_mut().map_page_scoped(addr)?;
+
+    Some(MappedBlockNode {
+      node: unsafe { (page as *mut BlockNode).as_mut().unwrap() },
+      _mapping: mapping,
+    })
   }
 
-  /// Release a block node.
+  /// Check if the allocator can map the block nodes needed to update its free
+  /// lists.
   ///
   /// # Description
   ///
-  ///   NOTE: `get_block_node_*()` and `unget_block_node()` are wrappers for
-  ///         `Task::map_page()` and `Task::unmap_page()`. Calls must follow the
-  ///         same stack semantics.
-  fn unget_block_node() {
-    Task::get_current_task_mut().unmap_page();
+  /// Each list operation maps its nodes before modifying any of them, and
+  /// operations run one at a time. If this check passes at the start of an
+  /// allocator operation, no list operation within it will run out of local
+  /// mappings part way through.
+  ///
+  /// # Returns
+  ///
+  /// True if the current task can map at least `MAX_NODE_MAPPINGS` pages.
+  fn can_map_nodes() -> bool {
+    Task::get_current_task().free_mapping_count() >= MAX_NODE_MAPPINGS
   }
 
   /// Construct a new page allocator for a given contiguous memory area.
@@ -297,33 +334,12 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   /// * `base + size` would overflow a pointer after alignment.
   /// * `metadata` is null.
   /// * `avail` is empty.
+  /// * `avail` overlaps the metadata area (debug builds only).
+  /// * The current task's local mapping table is full.
   pub fn new(base: usize, size: usize, metadata: *mut u8, avail: &[MemoryRange]) -> Option<Self> {
-    let page_size = arch::get_page_size();
-    let max_physical = arch::get_maximum_physical_address();
+    let (base, size, end) = Self::align_area(base, size)?;
     let mut free_mem = 0;
 
-    // Sanity check the inputs so that we can calculate an initial end address.
-    if base > max_physical {
-      return None;
-    }
-
-    if max_physical - base < (size - 1) {
-      return None;
-    }
-
-    let end = base + size - 1;
-
-    // Now update the base address for page-alignment.
-    let base = bits::align_up(base, page_size);
-
-    // Now update the new size for page-alignment.
-    let size = bits::align_down(end - base + 1, page_size);
-
-    // At least one page is required.
-    if size < page_size {
-      return None;
-    }
-
     // A metadata area is required.
     if metadata == ptr::null_mut() {
       return None;
@@ -349,6 +365,21 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
     // Make the allocator.
     let (levels, meta_size) = Self::make_levels(size);
 
+    // In debug builds, verify that the metadata area has been excluded from the
+    // available regions. Free list nodes placed in the metadata area would
+    // corrupt the flags and vice versa.
+    if cfg!(debug_assertions) {
+      let meta_range = MemoryRange {
+        tag: MemoryZone::InvalidZone,
+        base: metadata as usize - arch::get_kernel_virtual_base(),
+        size: meta_size,
+      };
+
+      if avail.iter().any(|range| range.overlaps(&meta_range)) {
+        return None;
+      }
+    }
+
     let mut allocator = Self {
       base,
       size,
@@ -360,11 +391,124 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
       free_mem,
     };
 
-    allocator.init_metadata(&avail);
+    if !Self::can_map_nodes() {
+      return None;
+    }
+
+    allocator.init_metadata(&avail)?;
 
     Some(allocator)
   }
 
+  /// Reconstruct a page allocator from an existing metadata area.
+  ///
+  /// # Parameters
+  ///
+  /// * `base` - Base physical address of the memory area served.
+  /// * `size` - Size of the memory area.
+  /// * `metadata` - The metadata area of an existing allocator.
+  /// * `alloc_mem` - The amount of memory allocated by the existing allocator.
+  ///
+  /// # Description
+  ///
+  /// Adopts the metadata and free lists of an allocator constructed with the
+  /// same `base` and `size`, e.g. by an earlier boot stage, without walking the
+  /// available regions again. The free list heads and the amount of free memory
+  /// are rebuilt from the flags. See `init_levels_from_flags()`.
+  ///
+  /// The flags do not distinguish allocated blocks from unavailable blocks, so
+  /// the caller must provide the existing allocator's allocated memory.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes the existing allocator is no longer in use and that nothing has
+  /// modified its free blocks since.
+  ///
+  /// # Returns
+  ///
+  /// The reconstructed allocator, or None if:
+  ///
+  /// * `base` and `size` are invalid as described by `new()`.
+  /// * `metadata` is null.
+  /// * The free lists are inconsistent with the flags.
+  /// * The current task's local mapping table is full.
+  pub fn from_existing(
+    base: usize,
+    size: usize,
+    metadata: *mut u8,
+    alloc_mem: usize,
+  ) -> Option<Self> {
+    let (base, size, _) = Self::align_area(base, size)?;
+
+    // A metadata area is required.
+    if metadata == ptr::null_mut() {
+      return None;
+    }
+
+    let (levels, meta_size) = Self::make_levels(size);
+
+    let mut allocator = Self {
+      base,
+      size,
+      levels,
+      flags: unsafe {
+        slice::from_raw_parts_mut(metadata as *mut usize, meta_size >> bits::WORD_SHIFT)
+      },
+      alloc_mem,
+      free_mem: 0,
+    };
+
+    if !allocator.init_levels_from_flags() {
+      return None;
+    }
+
+    Some(allocator)
+  }
+
+  /// Page-align a memory area.
+  ///
+  /// # Parameters
+  ///
+  /// * `base` - Base physical address of the memory area.
+  /// * `size` - Size of the memory area.
+  ///
+  /// # Description
+  ///
+  /// See `new()`.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the aligned base, the aligned size, and the original end
+  /// address of the area, or None if the area is invalid.
+  fn align_area(base: usize, size: usize) -> Option<(usize, usize, usize)> {
+    let page_size = arch::get_page_size();
+    let max_physical = arch::get_maximum_physical_address();
+
+    // Sanity check the inputs so that we can calculate an initial end address.
+    if base > max_physical {
+      return None;
+    }
+
+    if max_physical - base < (size - 1) {
+      return None;
+    }
+
+    let end = base + size - 1;
+
+    // Now update the base address for page-alignment.
+    let base = bits::align_up(base, page_size);
+
+    // Now update the new size for page-alignment.
+    let size = bits::align_down(end - base + 1, page_size);
+
+    // At least one page is required.
+    if size < page_size {
+      return None;
+    }
+
+    Some((base, size, end))
+  }
+
   /// Attempts to allocate a contiguous block of pages.
   ///
   /// # Parameters
@@ -376,37 +520,145 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   /// If `pages` is not a power of 2, the size of the block returned will be the
   /// smallest power of 2 pages larger than the requested number of pages.
   ///
+  /// The largest block the allocator can return is `MAX_BLOCK_PAGES` pages,
+  /// e.g. 4 MiB with a 4 KiB page size. Larger requests always fail. Callers
+  /// that need to tell an oversized request from an out-of-memory condition
+  /// must compare the request to `MAX_BLOCK_PAGES`.
+  ///
+  /// The allocator uses a best-fit policy. It takes a block from the smallest
+  /// non-empty level that satisfies the request and only splits a block if no
+  /// block of exactly the required size is available. Because `free()`
+  /// coalesces buddies immediately, there are never free buddy pairs that could
+  /// be merged on demand to avoid a split.
+  ///
   /// # Returns
   ///
   /// A tuple with the base physical address of the contiguous block and the
-  /// actual number of pages allocated, or None if the allocator could not find
-  /// an available contiguous block of the requested size.
+  /// actual number of pages allocated, or None if the request is larger than
+  /// the largest block, the allocator could not find an available contiguous
+  /// block of the requested size, or the current task's local mapping table is
+  /// full.
   pub fn allocate(&mut self, pages: usize) -> Option<(usize, usize)> {
-    if pages == 0 {
+    if pages == 0 || !Self::can_map_nodes() {
       return None;
     }
 
-    // Calculate the level with the minimum block size.
+    // Calculate the level with the minimum block size. Skip the scan if the
+    // request rounds up past the largest block.
     let min_level = bits::ceil_log2(pages);
 
+    if min_level >= BLOCK_LEVELS {
+      return None;
+    }
+
     for level in min_level..BLOCK_LEVELS {
       if self.levels[level].head == 0 {
         continue;
       }
 
-      let block = self.split_free_block(level, min_level);
-      let pages = 1 << min_level;
-      let block_size = pages << arch::get_page_shift();
-      self.free_mem -= block_size;
-      self.alloc_mem += block_size;
-
-      return Some((block, pages));
+      return self.take_free_block(level, min_level);
     }
 
     // No blocks available.
     None
   }
 
+  /// Attempts to allocate a contiguous block of pages without splitting.
+  ///
+  /// # Parameters
+  ///
+  /// * `pages` - The requested number of pages.
+  ///
+  /// # Description
+  ///
+  /// Rounds `pages` up to a power of 2 like `allocate()`, but only succeeds if
+  /// a block of exactly that size is already available. Larger blocks are
+  /// never split.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the base physical address of the contiguous block and the
+  /// actual number of pages allocated, or None if the request is larger than
+  /// the largest block, there are no available blocks of exactly the requested
+  /// size, or the current task's local mapping table is full.
+  pub fn allocate_exact(&mut self, pages: usize) -> Option<(usize, usize)> {
+    if pages == 0 || !Self::can_map_nodes() {
+      return None;
+    }
+
+    let level = bits::ceil_log2(pages);
+
+    if level >= BLOCK_LEVELS || self.levels[level].head == 0 {
+      return None;
+    }
+
+    self.take_free_block(level, level)
+  }
+
+  /// Reserves a specific range of pages.
+  ///
+  /// # Parameters
+  ///
+  /// * `base` - The base physical address of the range.
+  /// * `pages` - The number of pages in the range.
+  ///
+  /// # Description
+  ///
+  /// Unlike `allocate()`, the caller chooses the pages, e.g. for a buffer
+  /// shared with firmware at a fixed address. The base address must be page
+  /// aligned, but the range does not need to be a power of 2 pages. Free blocks
+  /// that extend past the range are split, and the parts outside of the range
+  /// are returned to the free lists.
+  ///
+  /// The range is released with `free()` one aligned, power of 2 block at a
+  /// time.
+  ///
+  ///   NOTE: Checking the range walks every free list, so reservations are
+  ///         much slower than allocations.
+  ///
+  /// # Returns
+  ///
+  /// True if the range was reserved. False if the range is empty, unaligned,
+  /// outside of the allocator's area, any page in the range is not available,
+  /// or the current task's local mapping table is full. The allocator is
+  /// unchanged on failure.
+  pub fn reserve(&mut self, base: usize, pages: usize) -> bool {
+    let page_size = arch::get_page_size();
+
+    let Some(size) = pages.checked_mul(page_size) else {
+      return false;
+    };
+
+    if size == 0 || size > self.size || !bits::is_aligned(base, page_size) {
+      return false;
+    }
+
+    if base < self.base || base - self.base > self.size - size {
+      return false;
+    }
+
+    if !Self::can_map_nodes() {
+      return false;
+    }
+
+    let end = base + size;
+
+    if self.count_free_pages(base, end) != Some(pages) {
+      return false;
+    }
+
+    // See `can_map_nodes()`. Reserving the range cannot fail part way
+    // through.
+    if self.reserve_range(base, end).is_none() {
+      return false;
+    }
+
+    self.free_mem -= size;
+    self.alloc_mem += size;
+
+    true
+  }
+
   /// Frees a block of memory.
   ///
   /// # Parameters
@@ -419,9 +671,16 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   /// The number of pages must be a power of 2. The base address of the block
   /// must be aligned on an address that is a multiple of the block size. The
   /// function ignores a base address of 0 or a page count of 0.
-  pub fn free(&mut self, base: usize, pages: usize) {
+  ///
+  /// If the current task's local mapping table is full, the block is leaked
+  /// rather than leaving the free lists partially updated.
+  ///
+  /// # Returns
+  ///
+  /// False if the block was leaked, true otherwise.
+  pub fn free(&mut self, base: usize, pages: usize) -> bool {
     if (base == 0) || (pages == 0) {
-      return;
+      return true;
     }
 
     assert!(bits::is_power_of_2(pages));
@@ -437,6 +696,29 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
     let alloc_end = self.base + (self.size - 1);
     assert!(base >= self.base && range_end <= alloc_end);
 
+    if !Self::can_map_nodes() || self.release_block(base, min_level).is_none() {
+      debug_print!("Leaked {} pages at {:#x}. No local mappings available.\n", pages, base);
+      return false;
+    }
+
+    self.free_mem += block_size;
+    self.alloc_mem -= block_size;
+
+    true
+  }
+
+  /// Returns a block to the free lists, coalescing it with free buddies.
+  ///
+  /// # Parameters
+  ///
+  /// * `base` - The base physical address of the block.
+  /// * `min_level` - The block level.
+  ///
+  /// # Returns
+  ///
+  /// None if a block node could not be mapped. See `can_map_nodes()`.
+  fn release_block(&mut self, base: usize, min_level: usize) -> Option<()> {
+    let page_shift = arch::get_page_shift();
     let mut base = base;
 
     for level in min_level..BLOCK_LEVELS {
@@ -446,7 +728,7 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
       // here is that the buddy block is in use if the bit is zero, and we
       // cannot coalesce the two.
       if self.flags[index] & (1 << bit_idx) == 0 {
-        self.add_to_list(level, base);
+        self.add_to_list(level, base)?;
         break;
       }
 
@@ -457,12 +739,166 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
       //   NOTE: The buddy address is calculated relative to the beginning of
       //         the allocator's memory region.
       let buddy_addr = ((base - self.base) ^ ((1 << level) << page_shift)) + self.base;
-      self.remove_from_list(level, buddy_addr);
+      self.remove_from_list(level, buddy_addr)?;
       base = cmp::min(base, buddy_addr);
     }
 
-    self.free_mem += block_size;
-    self.alloc_mem -= block_size;
+    Some(())
+  }
+
+  /// Count the free pages within a range.
+  ///
+  /// # Parameters
+  ///
+  /// * `start` - The base physical address of the range.
+  /// * `end` - The physical address just past the end of the range.
+  ///
+  /// # Returns
+  ///
+  /// The number of free pages in the range, or None if a block node could not
+  /// be mapped.
+  fn count_free_pages(&self, start: usize, end: usize) -> Option<usize> {
+    let page_shift = arch::get_page_shift();
+    let mut count = 0;
+
+    for level in 0..BLOCK_LEVELS {
+      let head = self.levels[level].head;
+
+      if head == 0 {
+        continue;
+      }
+
+      let block_size = (1 << level) << page_shift;
+      let mut block_addr = head;
+
+      // Free blocks never overlap, so the sum of the overlaps is the number of
+      // free pages in the range.
+      loop {
+        let overlap_start = cmp::max(start, block_addr);
+        let overlap_end = cmp::min(end, block_addr + block_size);
+        count += overlap_end.saturating_sub(overlap_start) >> page_shift;
+
+        block_addr = Self::get_block_node(block_addr)?.next;
+
+        if block_addr == head {
+          break;
+        }
+      }
+    }
+
+    Some(count)
+  }
+
+  /// Removes the free blocks covering a range from the free lists.
+  ///
+  /// # Parameters
+  ///
+  /// * `start` - The base physical address of the range.
+  /// * `end` - The physical address just past the end of the range.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes every page in the range is free. See `count_free_pages()`.
+  ///
+  /// # Returns
+  ///
+  /// None if a block node could not be mapped. See `can_map_nodes()`.
+  fn reserve_range(&mut self, start: usize, end: usize) -> Option<()> {
+    let page_shift = arch::get_page_shift();
+    let mut addr = start;
+
+    while addr < end {
+      let level = self.get_free_block_level(addr);
+      let block_size = (1 << level) << page_shift;
+      let block_addr = self.base + bits::align_down(addr - self.base, block_size);
+
+      self.remove_from_list(level, block_addr)?;
+      self.release_outside_range(level, block_addr, start, end)?;
+
+      addr = block_addr + block_size;
+    }
+
+    Some(())
+  }
+
+  /// Find the level of the free block containing a free page.
+  ///
+  /// # Parameters
+  ///
+  /// * `addr` - The physical address of the page.
+  ///
+  /// # Description
+  ///
+  /// A pair's flag bit is set when exactly one block in the pair is free. The
+  /// pairs within a free block have no free blocks, so their bits are clear.
+  /// Below the top level, free buddies are always coalesced, so the bit for
+  /// the free block's pair is set. The free block's level is the lowest level
+  /// with a set bit, or the top level if no bits are set.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes the page is free.
+  ///
+  /// # Returns
+  ///
+  /// The level of the free block.
+  fn get_free_block_level(&self, addr: usize) -> usize {
+    let page_shift = arch::get_page_shift();
+
+    for level in 0..BLOCK_LEVELS - 1 {
+      let block_size = (1 << level) << page_shift;
+      let block_addr = self.base + bits::align_down(addr - self.base, block_size);
+      let (index, bit_idx) = self.get_flag_index_and_bit(block_addr, level);
+
+      if self.flags[index] & (1 << bit_idx) != 0 {
+        return level;
+      }
+    }
+
+    BLOCK_LEVELS - 1
+  }
+
+  /// Returns the parts of a block outside of a range to the free lists.
+  ///
+  /// # Parameters
+  ///
+  /// * `level` - The block level.
+  /// * `block_addr` - The physical block address.
+  /// * `start` - The base physical address of the range.
+  /// * `end` - The physical address just past the end of the range.
+  ///
+  /// # Description
+  ///
+  /// Blocks entirely outside of the range are added to the free list for their
+  /// level, and blocks entirely inside of the range are dropped. Blocks that
+  /// overlap the range are split in half and each half is handled at the next
+  /// level down.
+  ///
+  /// # Returns
+  ///
+  /// None if a block node could not be mapped.
+  fn release_outside_range(
+    &mut self,
+    level: usize,
+    block_addr: usize,
+    start: usize,
+    end: usize,
+  ) -> Option<()> {
+    let block_size = (1 << level) << arch::get_page_shift();
+    let block_end = block_addr + block_size;
+
+    if block_end <= start || block_addr >= end {
+      return self.add_to_list(level, block_addr);
+    }
+
+    if block_addr >= start && block_end <= end {
+      return Some(());
+    }
+
+    // The range is page aligned, so a single page is never partially covered.
+    let half_size = block_size >> 1;
+    self.release_outside_range(level - 1, block_addr, start, end)?;
+    self.release_outside_range(level - 1, block_addr + half_size, start, end)
   }
 
   /// Initializes the allocator's linked list and accounting metadata.
@@ -474,7 +910,11 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   /// # Assumptions
   ///
   /// The available regions have already been validated by the caller.
-  fn init_metadata(&mut self, avail: &[MemoryRange]) {
+  ///
+  /// # Returns
+  ///
+  /// None if a block node could not be mapped. See `can_map_nodes()`.
+  fn init_metadata(&mut self, avail: &[MemoryRange]) -> Option<()> {
     let page_shift = arch::get_page_shift();
     let page_size = arch::get_page_size();
 
@@ -513,12 +953,181 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
         let size = blocks << page_shift;
 
         // Add the block to the level's available list.
-        self.add_to_list(level, addr);
+        self.add_to_list(level, addr)?;
 
         addr += size;
         remaining -= size;
       }
     }
+
+    Some(())
+  }
+
+  /// Rebuilds the free list heads and free memory from the metadata flags.
+  ///
+  /// # Description
+  ///
+  /// Each set flag bit means exactly one block in the pair is free. The free
+  /// block is the one with a valid node that its neighbors link back to.
+  /// `remove_from_list()` clears the nodes of allocated blocks, so they never
+  /// appear linked. The first free block found at each level becomes the head
+  /// of that level's list.
+  ///
+  /// # Returns
+  ///
+  /// True if every level's list has exactly one block per set flag bit, false
+  /// otherwise.
+  fn init_levels_from_flags(&mut self) -> bool {
+    let page_shift = arch::get_page_shift();
+
+    for level in 0..BLOCK_LEVELS {
+      let start = self.levels[level].offset;
+      let end = match self.levels.get(level + 1) {
+        Some(next_level) => next_level.offset,
+        None => self.flags.len(),
+      };
+
+      let words = &self.flags[start..end];
+      let expected: usize = words.iter().map(|word| word.count_ones() as usize).sum();
+
+      let Some(index) = words.iter().position(|word| *word != 0) else {
+        self.levels[level].head = 0;
+        continue;
+      };
+
+      // Find the free block in the first pair with its bit set.
+      let block_pair = (index << bits::WORD_BIT_SHIFT) + words[index].trailing_zeros() as usize;
+      let block_addr = self.base + (((block_pair << 1) << level) << page_shift);
+      let buddy_addr = block_addr + ((1 << level) << page_shift);
+
+      let Some(head) = [block_addr, buddy_addr]
+        .into_iter()
+        .find(|addr| self.is_free_block(*addr, level))
+      else {
+        return false;
+      };
+
+      if self.count_free_blocks(level, head, expected) != Some(expected) {
+        return false;
+      }
+
+      self.levels[level].head = head;
+      self.free_mem += (expected << level) << page_shift;
+    }
+
+    true
+  }
+
+  /// Check if a block is linked into a free list.
+  ///
+  /// # Parameters
+  ///
+  /// * `block_addr` - The physical block address.
+  /// * `level` - The block level.
+  ///
+  /// # Returns
+  ///
+  /// True if the block has a valid node and its neighbors link back to it,
+  /// false otherwise.
+  fn is_free_block(&self, block_addr: usize, level: usize) -> bool {
+    if !self.is_block_in_area(block_addr, level) {
+      return false;
+    }
+
+    let Some((next, prev)) = Self::read_block_node(block_addr) else {
+      return false;
+    };
+
+    if !self.is_block_in_area(next, level) || !self.is_block_in_area(prev, level) {
+      return false;
+    }
+
+    let next_links = matches!(Self::read_block_node(next), Some((_, p)) if p == block_addr);
+    let prev_links = matches!(Self::read_block_node(prev), Some((n, _)) if n == block_addr);
+
+    next_links && prev_links
+  }
+
+  /// Count the blocks in a free list.
+  ///
+  /// # Parameters
+  ///
+  /// * `level` - The block level.
+  /// * `head` - The physical address of the first block in the list.
+  /// * `limit` - The maximum number of blocks expected in the list.
+  ///
+  /// # Returns
+  ///
+  /// The number of blocks in the list, or None if the list has more than
+  /// `limit` blocks, a block does not have its flag bit set, or a node is
+  /// invalid.
+  fn count_free_blocks(&self, level: usize, head: usize, limit: usize) -> Option<usize> {
+    let mut block_addr = head;
+    let mut count = 0;
+
+    while count < limit {
+      let (index, bit_idx) = self.get_flag_index_and_bit(block_addr, level);
+
+      if self.flags[index] & (1 << bit_idx) == 0 {
+        return None;
+      }
+
+      let (next, _) = Self::read_block_node(block_addr)?;
+      count += 1;
+
+      if next == head {
+        return Some(count);
+      }
+
+      if !self.is_block_in_area(next, level) {
+        return None;
+      }
+
+      block_addr = next;
+    }
+
+    None
+  }
+
+  /// Check if a block at a given level lies within the allocator's area.
+  ///
+  /// # Parameters
+  ///
+  /// * `block_addr` - The physical block address.
+  /// * `level` - The block level.
+  ///
+  /// # Returns
+  ///
+  /// True if the block is aligned on a multiple of its size and fits within
+  /// the area, false otherwise.
+  fn is_block_in_area(&self, block_addr: usize, level: usize) -> bool {
+    let block_size = (1 << level) << arch::get_page_shift();
+
+    if !bits::is_aligned(block_addr, block_size) || block_addr < self.base {
+      return false;
+    }
+
+    block_addr - self.base <= self.size - block_size
+  }
+
+  /// Read a block's linked-list node without asserting its checksum.
+  ///
+  /// # Parameters
+  ///
+  /// * `block_addr` - The physical block address.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the physical addresses of the next and previous nodes, or
+  /// None if the node's checksum is invalid or the node could not be mapped.
+  fn read_block_node(block_addr: usize) -> Option<(usize, usize)> {
+    let node = Self::get_block_node_unchecked_mut(block_addr)?;
+
+    if !node.verify_checksum() {
+      return None;
+    }
+
+    Some((node.next, node.prev))
   }
 
   /// Get the flag index and bit for a given physical address at a given level.
@@ -548,6 +1157,33 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
     (index, bit)
   }
 
+  /// Remove a free block and account for it as allocated.
+  ///
+  /// # Parameters
+  ///
+  /// * `level` - The level from which to take the block.
+  /// * `min_level` - The level of the block to return.
+  ///
+  /// # Description
+  ///
+  /// Assumes at least one block is available at `level`. See
+  /// `split_free_block()`.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the base physical address of the block and the number of
+  /// pages in the block, or None if a block node could not be mapped. See
+  /// `can_map_nodes()`.
+  fn take_free_block(&mut self, level: usize, min_level: usize) -> Option<(usize, usize)> {
+    let block = self.split_free_block(level, min_level)?;
+    let pages = 1 << min_level;
+    let block_size = pages << arch::get_page_shift();
+    self.free_mem -= block_size;
+    self.alloc_mem += block_size;
+
+    Some((block, pages))
+  }
+
   /// Split a free block until it is the required size.
   ///
   /// # Parameters
@@ -563,10 +1199,11 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   ///
   /// # Returns
   ///
-  /// The block address of the block removed from `level`.
-  fn split_free_block(&mut self, level: usize, min_level: usize) -> usize {
+  /// The block address of the block removed from `level`, or None if a block
+  /// node could not be mapped.
+  fn split_free_block(&mut self, level: usize, min_level: usize) -> Option<usize> {
     let page_size = arch::get_page_size();
-    let block_addr = self.pop_from_list(level);
+    let block_addr = self.pop_from_list(level)?;
 
     // For this example, just assume 1 byte pages starting at 0 for simplicity.
     //
@@ -600,10 +1237,10 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
     // [32, 34).
     for l in (min_level..level).rev() {
       let buddy_addr = block_addr | (page_size << l);
-      self.add_to_list(l, buddy_addr);
+      self.add_to_list(l, buddy_addr)?;
     }
 
-    block_addr
+    Some(block_addr)
   }
 
   /// Adds a block to the tail of a level's list of available blocks.
@@ -612,10 +1249,19 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   ///
   /// * `level` - The level to which the block will be added.
   /// * `block_addr` - The virtual block address to add to the list.
-  fn add_to_list(&mut self, level: usize, block_addr: usize) {
+  ///
+  /// # Description
+  ///
+  /// Maps up to `MAX_NODE_MAPPINGS` nodes. All nodes are mapped before any are
+  /// modified, so the list is unchanged if a node cannot be mapped.
+  ///
+  /// # Returns
+  ///
+  /// None if a block node could not be mapped.
+  fn add_to_list(&mut self, level: usize, block_addr: usize) -> Option<()> {
     let (index, bit_idx) = self.get_flag_index_and_bit(block_addr, level);
     let head_addr = self.levels[level].head;
-    let block = Self::get_block_node_unchecked_mut(block_addr);
+    let mut block = Self::get_block_node_unchecked_mut(block_addr)?;
 
     // If the list is empty, initialize a new node that points only to itself
     // and return the block address as the new head address. Otherwise, add the
@@ -626,8 +1272,8 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
       block.next = block_addr;
       block.update_checksum();
     } else {
-      let head = Self::get_block_node_mut(head_addr);
-      let prev = Self::get_block_node_mut(head.prev);
+      let mut head = Self::get_block_node_mut(head_addr)?;
+      let mut prev = Self::get_block_node_mut(head.prev)?;
 
       block.prev = head.prev;
       block.next = head_addr;
@@ -638,14 +1284,11 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
 
       prev.next = block_addr;
       prev.update_checksum();
-
-      Self::unget_block_node();
-      Self::unget_block_node();
     }
 
-    Self::unget_block_node();
-
     self.flags[index] ^= 1 << bit_idx;
+
+    Some(())
   }
 
   /// Pop the head of a level's free list.
@@ -660,11 +1303,12 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   ///
   /// # Returns
   ///
-  /// The block address popped from the list.
-  fn pop_from_list(&mut self, level: usize) -> usize {
+  /// The block address popped from the list, or None if a block node could not
+  /// be mapped.
+  fn pop_from_list(&mut self, level: usize) -> Option<usize> {
     let head_addr = self.levels[level].head;
-    self.remove_from_list(level, head_addr);
-    head_addr
+    self.remove_from_list(level, head_addr)?;
+    Some(head_addr)
   }
 
   /// Removes a specific block from a level's free list.
@@ -673,10 +1317,19 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
   ///
   /// * `level` - The level from which to remove a free block.
   /// * `block_addr` - The virtual block address to remove from the list.
-  fn remove_from_list(&mut self, level: usize, block_addr: usize) {
+  ///
+  /// # Description
+  ///
+  /// Maps up to `MAX_NODE_MAPPINGS` nodes. All nodes are mapped before any are
+  /// modified, so the list is unchanged if a node cannot be mapped.
+  ///
+  /// # Returns
+  ///
+  /// None if a block node could not be mapped.
+  fn remove_from_list(&mut self, level: usize, block_addr: usize) -> Option<()> {
     let (index, bit_idx) = self.get_flag_index_and_bit(block_addr, level);
     let head_addr = self.levels[level].head;
-    let block = Self::get_block_node_mut(block_addr);
+    let mut block = Self::get_block_node_mut(block_addr)?;
 
     // If the block points to itself, sanity check the block and list, then
     // set the head to zero. Otherwise, remove the block.
@@ -685,8 +1338,8 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
       assert_eq!(head_addr, block_addr);
       self.levels[level].head = 0;
     } else {
-      let prev = Self::get_block_node_mut(block.prev);
-      let next = Self::get_block_node_mut(block.next);
+      let mut prev = Self::get_block_node_mut(block.prev)?;
+      let mut next = Self::get_block_node_mut(block.next)?;
 
       next.prev = block.prev;
       next.update_checksum();
@@ -694,22 +1347,21 @@ impl<'alloc> BuddyPageAllocator<'alloc> {
       prev.next = block.next;
       prev.update_checksum();
 
-      block.next = 0;
-      block.prev = 0;
-      block.checksum = 0;
-
-      Self::unget_block_node();
-      Self::unget_block_node();
-
       // If this block is the head block, move the head to the next block.
       if block_addr == head_addr {
         self.levels[level].head = block.next;
       }
     }
 
-    Self::unget_block_node();
+    // Clear the node so that an allocated block is never mistaken for a free
+    // block. See `init_levels_from_flags()`.
+    block.next = 0;
+    block.prev = 0;
+    block.checksum = 0;
 
     self.flags[index] ^= 1 << bit_idx;
+
+    Some(())
   }
 }
 
@@ -722,8 +1374,8 @@ impl<'memory> PageAllocator for BuddyPageAllocator<'memory> {
   }
 
   /// See `PageAllocator::free`.
-  fn free(&mut self, addr: usize, pages: usize) {
-    self.free(addr, pages);
+  fn free(&mut self, addr: usize, pages: usize) -> bool {
+    self.free(addr, pages)
   }
 
   /// See `PageAllocator::get_alloc_mem`.
@@ -737,6 +1389,87 @@ impl<'memory> PageAllocator for BuddyPageAllocator<'memory> {
   }
 }
 
+/// A Buddy Page Allocator protected by a spin lock.
+///
+/// # Description
+///
+/// Each operation acquires the lock for its duration, so callers may share the
+/// allocator without locking it themselves. Use `lock()` to perform several
+/// operations while holding the lock once.
+///
+///   NOTE: The lock does not mask interrupts. Callers that may be interrupted
+///         by code using the same allocator must mask interrupts first.
+pub struct SyncBuddyAllocator<'alloc> {
+  allocator: SpinLock<BuddyPageAllocator<'alloc>>,
+}
+
+impl<'alloc> SyncBuddyAllocator<'alloc> {
+  /// Construct a new thread-safe allocator.
+  ///
+  /// # Parameters
+  ///
+  /// * `allocator` - The allocator to protect.
+  pub const fn new(allocator: BuddyPageAllocator<'alloc>) -> Self {
+    SyncBuddyAllocator {
+      allocator: SpinLock::new(allocator),
+    }
+  }
+
+  /// See `BuddyPageAllocator::allocate()`.
+  pub fn allocate(&self, pages: usize) -> Option<(usize, usize)> {
+    self.allocator.lock().allocate(pages)
+  }
+
+  /// See `BuddyPageAllocator::free()`.
+  pub fn free(&self, base: usize, pages: usize) -> bool {
+    self.allocator.lock().free(base, pages)
+  }
+
+  /// Get the allocator's memory accounting.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the amount of memory allocated and the amount of memory
+  /// available in bytes. Both values are read while holding the lock once.
+  pub fn stats(&self) -> (usize, usize) {
+    let allocator = self.allocator.lock();
+    (allocator.alloc_mem, allocator.free_mem)
+  }
+
+  /// Acquire the lock.
+  ///
+  /// # Returns
+  ///
+  /// A guard object that provides access to the protected allocator.
+  pub fn lock(&self) -> SpinLockGuard<'_, BuddyPageAllocator<'alloc>> {
+    self.allocator.lock()
+  }
+}
+
+impl PageAllocator for SyncBuddyAllocator<'_> {
+  const MAX_BLOCK_PAGES: usize = BuddyPageAllocator::MAX_BLOCK_PAGES;
+
+  /// See `PageAllocator::alloc`.
+  fn alloc(&mut self, pages: usize) -> Option<(usize, usize)> {
+    self.allocate(pages)
+  }
+
+  /// See `PageAllocator::free`.
+  fn free(&mut self, addr: usize, pages: usize) -> bool {
+    SyncBuddyAllocator::free(self, addr, pages)
+  }
+
+  /// See `PageAllocator::get_alloc_mem`.
+  fn get_alloc_mem(&self) -> usize {
+    self.stats().0
+  }
+
+  /// See `PageAllocator::get_free_mem`.
+  fn get_free_mem(&self) -> usize {
+    self.stats().1
+  }
+}
+
 #[cfg(feature = "module_tests")]
 pub fn run_tests(context: &mut test::TestContext) {
   tests::run_tests(context);
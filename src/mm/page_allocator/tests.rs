@@ -1,13 +1,18 @@
 //! Buddy Page Allocator Tests
 
-use super::{BlockLevel, BuddyPageAllocator};
+use super::{BlockLevel, BuddyPageAllocator, MAX_NODE_MAPPINGS, SyncBuddyAllocator};
 use crate::arch;
 use crate::arch::memory::{MemoryConfig, MemoryRange, MemoryZone};
 use crate::debug_print;
 use crate::support::bits;
+use crate::support::rand::Xorshift64;
+use crate::task::Task;
 use crate::test::{self, memory};
-use crate::{check_eq, check_neq, check_none, check_not_none, execute_test, mark_fail};
-use core::{iter, ptr, slice};
+use crate::{
+  check_eq, check_neq, check_no_panic, check_none, check_not_none, check_panics, execute_test,
+  mark_fail,
+};
+use core::{array, iter, mem, ptr, slice};
 
 /// Test with 2047 pages. The non-power of 2 tests proper setup and accounting.
 const TEST_PAGE_COUNT: usize = 2047;
@@ -45,12 +50,26 @@ const EXPECTED_METADATA_SIZE: usize = 78 << bits::WORD_SHIFT;
 #[cfg(target_pointer_width = "64")]
 const EXPECTED_METADATA_SIZE: usize = 43 << bits::WORD_SHIFT;
 
+const _: () = assert!(
+  BuddyPageAllocator::calc_metadata_size(TEST_BUFFER_SIZE) == EXPECTED_METADATA_SIZE,
+  "The tabulated metadata size does not match the compile-time calculation."
+);
+
 /// The total size of the test memory buffer.
 const TOTAL_MEM_SIZE: usize = TEST_BUFFER_SIZE + EXPECTED_METADATA_SIZE;
 
 /// The allocator should serve up blocks of 2^0 up to 2^10 pages.
 const EXPECTED_BLOCK_LEVELS: usize = 11;
 
+/// The number of randomized allocate and free operations.
+const RANDOM_OP_COUNT: usize = 4096;
+
+/// The maximum number of blocks held by the randomized test at once.
+const RANDOM_MAX_LIVE_BLOCKS: usize = 128;
+
+/// Constant seed so that a failing randomized sequence is reproducible.
+const RANDOM_SEED: u64 = 0x0123_4567_89ab_cdef;
+
 /// Test memory configuration.
 ///
 ///   NOTE: This is static to save stack space.
@@ -65,13 +84,24 @@ struct AllocatorState<'a> {
 pub fn run_tests(context: &mut test::TestContext) {
   debug_assert!(memory::MEMORY_SIZE >= TOTAL_MEM_SIZE);
   execute_test!(context, test_size_calculation);
+  execute_test!(context, test_const_size_calculation);
   execute_test!(context, test_level_construction);
   execute_test!(context, test_metadata_front_load);
   execute_test!(context, test_metadata_end_load);
   execute_test!(context, test_available_regions);
   execute_test!(context, test_construction_errors);
   execute_test!(context, test_allocation);
+  execute_test!(context, test_oversized_allocation);
+  execute_test!(context, test_exact_allocation);
   execute_test!(context, test_free);
+  execute_test!(context, test_misaligned_free);
+  execute_test!(context, test_reserve);
+  execute_test!(context, test_reserve_conflicts);
+  execute_test!(context, test_reconstruction);
+  execute_test!(context, test_reconstruction_errors);
+  execute_test!(context, test_mapping_exhaustion);
+  execute_test!(context, test_sync_allocator);
+  execute_test!(context, test_random_alloc_free);
 }
 
 /// Test calculating the size required for the allocator metadata.
@@ -87,6 +117,34 @@ fn test_size_calculation(context: &mut test::TestContext) {
   check_eq!(context, size, 0);
 }
 
+/// Test that the compile-time and run-time metadata size calculations agree.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The constant is evaluated at compile time, and must match calling the same
+/// function outside of a constant context. `make_levels()` computes the size
+/// with its own loop, so it must also agree with the constant function,
+/// including for sizes that are not a multiple of the page size.
+fn test_const_size_calculation(context: &mut test::TestContext) {
+  const CONST_SIZE: usize = BuddyPageAllocator::calc_metadata_size(TEST_MEM_SIZE);
+
+  check_eq!(context, BuddyPageAllocator::calc_metadata_size(TEST_MEM_SIZE), CONST_SIZE);
+
+  let odd_sizes = (0..EXPECTED_BLOCK_LEVELS).map(|level| (memory::PAGE_SIZE << level) + 0x800);
+
+  for size in [0, TEST_MEM_SIZE, TEST_BUFFER_SIZE]
+    .into_iter()
+    .chain(odd_sizes)
+  {
+    let (_, levels_size) = BuddyPageAllocator::make_levels(size);
+    check_eq!(context, BuddyPageAllocator::calc_metadata_size(size), levels_size);
+  }
+}
+
 /// Test initializing the head pointers and bit array offsets.
 ///
 /// # Parameters
@@ -351,6 +409,22 @@ fn test_construction_errors(context: &mut test::TestContext) {
   let allocator = BuddyPageAllocator::new(base_addr, TOTAL_MEM_SIZE, meta, bad_avail);
   check_none!(context, allocator);
 
+  // Place the metadata inside of, then straddling the end of, the available
+  // region. The overlap check only runs in debug builds.
+  if cfg!(debug_assertions) {
+    let virt_base = arch::get_kernel_virtual_base();
+    let overlap_addrs = [
+      base_addr + memory::PAGE_SIZE,
+      base_addr + TEST_MEM_SIZE - mem::size_of::<usize>(),
+    ];
+
+    for addr in overlap_addrs {
+      let overlap_meta = (virt_base + addr) as *mut u8;
+      let allocator = BuddyPageAllocator::new(base_addr, TOTAL_MEM_SIZE, overlap_meta, good_avail);
+      check_none!(context, allocator);
+    }
+  }
+
   // TODO: Error check providing virtual addresses and invalid available ranges.
 }
 
@@ -396,6 +470,96 @@ fn test_allocation(context: &mut test::TestContext) {
   }
 }
 
+/// Test requesting more pages than the largest block.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Oversized requests fail without affecting the allocator's accounting, and
+/// the largest block can still be allocated afterward.
+fn test_oversized_allocation(context: &mut test::TestContext) {
+  const MAX_BLOCK_PAGES: usize = 1 << (EXPECTED_BLOCK_LEVELS - 1);
+
+  let mut allocator = make_allocator(0);
+  let free_mem = allocator.free_mem;
+
+  for pages in [MAX_BLOCK_PAGES + 1, 1 << EXPECTED_BLOCK_LEVELS, usize::MAX] {
+    check_none!(context, allocator.allocate(pages));
+  }
+
+  check_eq!(context, allocator.free_mem, free_mem);
+  check_eq!(context, allocator.alloc_mem, 0);
+
+  let result = allocator.allocate(MAX_BLOCK_PAGES);
+  check_not_none!(context, result);
+
+  if let Some((_, pages)) = result {
+    check_eq!(context, pages, MAX_BLOCK_PAGES);
+  }
+}
+
+/// Test allocating without splitting.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The test allocator starts with exactly one block at each level. Once the
+/// 2-page block is taken, `allocate_exact()` fails while `allocate()` splits
+/// the 4-page block. The split leaves a 2-page buddy that `allocate_exact()`
+/// can then take.
+fn test_exact_allocation(context: &mut test::TestContext) {
+  let mut allocator = make_allocator(0);
+  let free_mem = allocator.free_mem;
+
+  check_none!(context, allocator.allocate_exact(0));
+  check_none!(context, allocator.allocate_exact(1 << EXPECTED_BLOCK_LEVELS));
+
+  let result = allocator.allocate_exact(2);
+  check_not_none!(context, result);
+
+  if let Some((_, pages)) = result {
+    check_eq!(context, pages, 2);
+  }
+
+  // The 4-page block is not split.
+  check_none!(context, allocator.allocate_exact(2));
+  check_neq!(context, allocator.levels[2].head, 0);
+  check_eq!(context, allocator.free_mem, free_mem - (memory::PAGE_SIZE * 2));
+
+  // `allocate()` splits the 4-page block, leaving its odd 2-page buddy.
+  let result = allocator.allocate(2);
+  check_not_none!(context, result);
+
+  let Some((split_addr, pages)) = result else {
+    return;
+  };
+
+  let buddy_addr = split_addr + (memory::PAGE_SIZE * 2);
+  check_eq!(context, pages, 2);
+  check_eq!(context, allocator.levels[2].head, 0);
+  check_eq!(context, allocator.levels[1].head, buddy_addr);
+
+  // Three pages round up to the now empty 4-page level.
+  check_none!(context, allocator.allocate_exact(3));
+
+  let result = allocator.allocate_exact(2);
+  check_not_none!(context, result);
+
+  if let Some((addr, pages)) = result {
+    check_eq!(context, addr, buddy_addr);
+    check_eq!(context, pages, 2);
+  }
+
+  check_eq!(context, allocator.levels[1].head, 0);
+  check_eq!(context, allocator.alloc_mem, memory::PAGE_SIZE * 6);
+}
+
 /// Test freeing blocks.
 ///
 /// # Parameters
@@ -437,6 +601,483 @@ fn test_free(context: &mut test::TestContext) {
   }
 }
 
+/// Test freeing a misaligned block.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The base address of a 2-page block must be aligned on a 2-page boundary.
+/// The alignment assertion must fail before the allocator is modified, so
+/// freeing the aligned block afterward coalesces the free lists back into their
+/// original state.
+fn test_misaligned_free(context: &mut test::TestContext) {
+  let mut allocator = make_allocator(0);
+  let heads: [usize; EXPECTED_BLOCK_LEVELS] = array::from_fn(|i| allocator.levels[i].head);
+
+  let Some((block_addr, _)) = allocator.allocate(2) else {
+    mark_fail!(context, "Failed to allocate a 2-page block.");
+    return;
+  };
+
+  check_panics!(context, || _ = allocator.free(block_addr + memory::PAGE_SIZE, 2));
+  check_no_panic!(context, || _ = allocator.free(block_addr, 2));
+
+  for (level, head) in allocator.levels.iter().zip(heads) {
+    check_eq!(context, level.head, head);
+  }
+}
+
+/// Test reserving specific ranges.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Reserving pages [4, 8) splits the 1024-page block and returns every part
+/// outside of the range to the free lists. Freeing the range coalesces the
+/// blocks back into the original state. A range that is not a power of 2
+/// pages is reserved the same way.
+fn test_reserve(context: &mut test::TestContext) {
+  let mut allocator = make_allocator(0);
+  let (base_addr, _) = get_addrs();
+  let free_mem = allocator.free_mem;
+
+  let initial_state = AllocatorState {
+    levels: [
+      &[make_block_addr(base_addr, 2047, 0)],
+      &[make_block_addr(base_addr, 1023, 1)],
+      &[make_block_addr(base_addr, 511, 2)],
+      &[make_block_addr(base_addr, 255, 3)],
+      &[make_block_addr(base_addr, 127, 4)],
+      &[make_block_addr(base_addr, 63, 5)],
+      &[make_block_addr(base_addr, 31, 6)],
+      &[make_block_addr(base_addr, 15, 7)],
+      &[make_block_addr(base_addr, 7, 8)],
+      &[make_block_addr(base_addr, 3, 9)],
+      &[make_block_addr(base_addr, 1, 10)],
+    ],
+  };
+
+  let reserved_addr = base_addr + (memory::PAGE_SIZE * 4);
+  check_eq!(context, allocator.reserve(reserved_addr, 4), true);
+  check_eq!(context, allocator.free_mem, free_mem - (memory::PAGE_SIZE * 4));
+  check_eq!(context, allocator.alloc_mem, memory::PAGE_SIZE * 4);
+
+  // The parts of the 1024-page block outside of the range are added after the
+  // existing block at each level, so the lists are not in address order.
+  // Level 2 gains pages [0, 4), and each level from 3 through 9 gains its
+  // second block, e.g. pages [8, 16) at level 3.
+  for level in 0..EXPECTED_BLOCK_LEVELS {
+    let (exp_count, exp_sum) = match level {
+      0 | 1 => (1, initial_state.levels[level][0]),
+      2 => (2, initial_state.levels[2][0] + make_block_addr(base_addr, 1, 2)),
+      10 => (0, 0),
+      _ => (2, initial_state.levels[level][0] + make_block_addr(base_addr, 2, level)),
+    };
+
+    let (count, addr_sum) = get_level_summary(&allocator, level);
+    check_eq!(context, count, exp_count);
+    check_eq!(context, addr_sum, exp_sum);
+  }
+
+  allocator.free(reserved_addr, 4);
+  check_eq!(context, allocator.free_mem, free_mem);
+  check_eq!(context, allocator.alloc_mem, 0);
+  verify_allocator(context, &allocator, &initial_state);
+
+  // Reserve pages [9, 12).
+  let reserved_addr = base_addr + (memory::PAGE_SIZE * 9);
+  check_eq!(context, allocator.reserve(reserved_addr, 3), true);
+  check_eq!(context, allocator.alloc_mem, memory::PAGE_SIZE * 3);
+
+  allocator.free(reserved_addr, 1);
+  allocator.free(reserved_addr + memory::PAGE_SIZE, 2);
+  check_eq!(context, allocator.free_mem, free_mem);
+  check_eq!(context, allocator.alloc_mem, 0);
+  verify_allocator(context, &allocator, &initial_state);
+}
+
+/// Test reserving ranges that are not available.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The allocator's area extends past the available region, so the pages after
+/// the region are never free. Failed reservations must not change the
+/// allocator.
+fn test_reserve_conflicts(context: &mut test::TestContext) {
+  let mut allocator = make_allocator(0);
+  let (base_addr, _) = get_addrs();
+
+  // The single page block is the last available page.
+  let result = allocator.allocate(1);
+  check_not_none!(context, result);
+
+  let Some((alloc_addr, _)) = result else {
+    return;
+  };
+
+  check_eq!(context, alloc_addr, make_block_addr(base_addr, 2047, 0));
+
+  let free_mem = allocator.free_mem;
+  let alloc_mem = allocator.alloc_mem;
+  let mut summaries = [(0, 0); EXPECTED_BLOCK_LEVELS];
+
+  for (level, summary) in summaries.iter_mut().enumerate() {
+    *summary = get_level_summary(&allocator, level);
+  }
+
+  let tests = [
+    // The allocated page.
+    (alloc_addr, 1),
+    // A range ending with the allocated page.
+    (alloc_addr - (memory::PAGE_SIZE * 6), 7),
+    // A range after the available region.
+    (alloc_addr + memory::PAGE_SIZE, 1),
+    // A range starting before the allocator's area.
+    (base_addr - memory::PAGE_SIZE, 2),
+    // A range extending past the allocator's area.
+    (base_addr, (TEST_BUFFER_SIZE / memory::PAGE_SIZE) + 1),
+    // An unaligned range.
+    (base_addr + 1, 1),
+    // An empty range.
+    (base_addr, 0),
+    // A range too large to represent.
+    (base_addr, usize::MAX),
+  ];
+
+  for (addr, pages) in tests {
+    check_eq!(context, allocator.reserve(addr, pages), false);
+  }
+
+  check_eq!(context, allocator.free_mem, free_mem);
+  check_eq!(context, allocator.alloc_mem, alloc_mem);
+
+  for (level, summary) in summaries.iter().enumerate() {
+    let (count, addr_sum) = get_level_summary(&allocator, level);
+    check_eq!(context, count, summary.0);
+    check_eq!(context, addr_sum, summary.1);
+  }
+}
+
+/// Test reconstructing an allocator from its metadata.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The test mixes allocations and frees so that some levels have several free
+/// blocks, then drops the allocator and reconstructs it from the metadata. The
+/// reconstructed allocator must have the same free blocks and accounting, and
+/// freeing the remaining blocks must restore the initial end-loaded state.
+fn test_reconstruction(context: &mut test::TestContext) {
+  let (base_addr, meta_addr) = get_addrs();
+  let mut summaries = [(0, 0); EXPECTED_BLOCK_LEVELS];
+  let free_mem;
+  let alloc_mem;
+  let live;
+
+  {
+    let mut allocator = make_allocator(0);
+
+    let a = allocator.allocate(1).unwrap();
+    let b = allocator.allocate(1).unwrap();
+    let c = allocator.allocate(4).unwrap();
+    let d = allocator.allocate(4).unwrap();
+    allocator.free(a.0, a.1);
+    let e = allocator.allocate(1).unwrap();
+    let f = allocator.allocate(16).unwrap();
+    allocator.free(c.0, c.1);
+
+    for (level, summary) in summaries.iter_mut().enumerate() {
+      *summary = get_level_summary(&allocator, level);
+    }
+
+    free_mem = allocator.free_mem;
+    alloc_mem = allocator.alloc_mem;
+    live = [b, d, e, f];
+  }
+
+  let allocator =
+    BuddyPageAllocator::from_existing(base_addr, TEST_BUFFER_SIZE, meta_addr as *mut u8, alloc_mem);
+  check_not_none!(context, allocator);
+
+  let Some(mut allocator) = allocator else {
+    return;
+  };
+
+  check_eq!(context, allocator.free_mem, free_mem);
+  check_eq!(context, allocator.alloc_mem, alloc_mem);
+
+  for (level, summary) in summaries.iter().enumerate() {
+    let (count, addr_sum) = get_level_summary(&allocator, level);
+    check_eq!(context, count, summary.0);
+    check_eq!(context, addr_sum, summary.1);
+  }
+
+  for (addr, pages) in live {
+    allocator.free(addr, pages);
+  }
+
+  check_eq!(context, allocator.alloc_mem, 0);
+  check_eq!(context, allocator.free_mem, TEST_MEM_SIZE);
+
+  verify_allocator(
+    context,
+    &allocator,
+    &AllocatorState {
+      levels: [
+        &[make_block_addr(base_addr, 2047, 0)],
+        &[make_block_addr(base_addr, 1023, 1)],
+        &[make_block_addr(base_addr, 511, 2)],
+        &[make_block_addr(base_addr, 255, 3)],
+        &[make_block_addr(base_addr, 127, 4)],
+        &[make_block_addr(base_addr, 63, 5)],
+        &[make_block_addr(base_addr, 31, 6)],
+        &[make_block_addr(base_addr, 15, 7)],
+        &[make_block_addr(base_addr, 7, 8)],
+        &[make_block_addr(base_addr, 3, 9)],
+        &[make_block_addr(base_addr, 1, 10)],
+      ],
+    },
+  );
+}
+
+/// Test that reconstruction rejects invalid parameters and metadata.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Clearing the free blocks leaves flag bits set without any linked nodes.
+fn test_reconstruction_errors(context: &mut test::TestContext) {
+  let (base_addr, meta_addr) = get_addrs();
+  let meta = meta_addr as *mut u8;
+
+  _ = make_allocator(0);
+
+  let allocator = BuddyPageAllocator::from_existing(base_addr, TEST_BUFFER_SIZE, meta, 0);
+  check_not_none!(context, allocator);
+
+  let allocator =
+    BuddyPageAllocator::from_existing(base_addr, TEST_BUFFER_SIZE, ptr::null_mut(), 0);
+  check_none!(context, allocator);
+
+  let allocator = BuddyPageAllocator::from_existing(base_addr, memory::PAGE_SIZE - 1, meta, 0);
+  check_none!(context, allocator);
+
+  memory::get_test_memory_mut()[..TEST_MEM_SIZE].fill(0);
+
+  let allocator = BuddyPageAllocator::from_existing(base_addr, TEST_BUFFER_SIZE, meta, 0);
+  check_none!(context, allocator);
+}
+
+/// Test running out of local mappings.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// With fewer than `MAX_NODE_MAPPINGS` local mappings left, allocations fail
+/// cleanly and frees leak the block instead of corrupting the free lists. Once
+/// the mappings are released, the allocator works normally. Skipped if the
+/// architecture does not limit local mappings.
+fn test_mapping_exhaustion(context: &mut test::TestContext) {
+  let task = Task::get_current_task_mut();
+
+  if task.free_mapping_count() == usize::MAX {
+    return;
+  }
+
+  let mut allocator = make_allocator(0);
+  let (base_addr, _) = get_addrs();
+
+  let result = allocator.allocate(1);
+  check_not_none!(context, result);
+
+  let Some((addr, _)) = result else {
+    return;
+  };
+
+  let free_mem = allocator.free_mem;
+  let alloc_mem = allocator.alloc_mem;
+  let mut mapped = 0;
+
+  while task.free_mapping_count() >= MAX_NODE_MAPPINGS {
+    _ = task.map_page(base_addr);
+    mapped += 1;
+  }
+
+  check_none!(context, allocator.allocate(1));
+  check_none!(context, allocator.allocate_exact(1));
+  check_eq!(context, allocator.free_mem, free_mem);
+
+  check_eq!(context, allocator.free(addr, 1), false);
+  check_eq!(context, allocator.alloc_mem, alloc_mem);
+
+  while task.free_mapping_count() > 0 {
+    _ = task.map_page(base_addr);
+    mapped += 1;
+  }
+
+  check_none!(context, task.map_page(base_addr));
+
+  for _ in 0..mapped {
+    task.unmap_page();
+  }
+
+  check_not_none!(context, allocator.allocate(1));
+  check_eq!(context, allocator.alloc_mem, alloc_mem + memory::PAGE_SIZE);
+}
+
+/// Test sharing an allocator through the thread-safe wrapper.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Two clients share the wrapper through immutable references and interleave
+/// their allocations and frees. Every operation must release the lock, and the
+/// accounting must match the operations from both clients.
+fn test_sync_allocator(context: &mut test::TestContext) {
+  let allocator = SyncBuddyAllocator::new(make_allocator(0));
+  let clients = [&allocator, &allocator];
+  let mut blocks = [[(0, 0); 4]; 2];
+
+  for i in 0..blocks[0].len() {
+    for (client, client_blocks) in iter::zip(clients, &mut blocks) {
+      client_blocks[i] = client.allocate(1 << i).unwrap_or((0, 0));
+      check_eq!(context, client_blocks[i].1, 1 << i);
+      check_eq!(context, allocator.allocator.try_lock().is_some(), true);
+    }
+  }
+
+  // Each client allocated 1 + 2 + 4 + 8 pages.
+  let (alloc_mem, free_mem) = allocator.stats();
+  check_eq!(context, alloc_mem, memory::PAGE_SIZE * 30);
+  check_eq!(context, free_mem, TEST_MEM_SIZE - alloc_mem);
+
+  // A held lock blocks other clients.
+  {
+    let guard = allocator.lock();
+    check_eq!(context, guard.alloc_mem, alloc_mem);
+    check_eq!(context, allocator.allocator.try_lock().is_none(), true);
+  }
+
+  for i in (0..blocks[0].len()).rev() {
+    for (client, client_blocks) in iter::zip(clients, &blocks) {
+      client.free(client_blocks[i].0, client_blocks[i].1);
+      check_eq!(context, allocator.allocator.try_lock().is_some(), true);
+    }
+  }
+
+  check_eq!(context, allocator.stats().0, 0);
+  check_eq!(context, allocator.stats().1, TEST_MEM_SIZE);
+}
+
+/// Test randomized allocations and frees against a page ownership model.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Each operation either allocates a block of a random size or frees a random
+/// block the test holds. The model tracks the pages the test owns. Every
+/// allocation must be aligned and must not overlap an owned page, and an
+/// allocation may only fail if there is no free block large enough. After
+/// every operation, the allocator must be consistent with the model. See
+/// `is_consistent()`.
+///
+/// Freeing the remaining blocks at the end must coalesce the free lists back
+/// into their original state.
+fn test_random_alloc_free(context: &mut test::TestContext) {
+  let mut allocator = make_allocator(0);
+  let (base_addr, _) = get_addrs();
+  let heads: [usize; EXPECTED_BLOCK_LEVELS] = array::from_fn(|i| allocator.levels[i].head);
+  let mut rng = Xorshift64::new(RANDOM_SEED);
+  let mut owned = [false; TEST_PAGE_COUNT];
+  let mut live = [(0, 0); RANDOM_MAX_LIVE_BLOCKS];
+  let mut live_count = 0;
+
+  for op in 0..RANDOM_OP_COUNT {
+    let free_block =
+      live_count == RANDOM_MAX_LIVE_BLOCKS || (live_count > 0 && rng.next_below(2) == 0);
+
+    if free_block {
+      let idx = rng.next_below(live_count);
+      let (addr, pages) = live[idx];
+      let start_page = (addr - base_addr) >> memory::PAGE_SHIFT;
+
+      live_count -= 1;
+      live[idx] = live[live_count];
+
+      allocator.free(addr, pages);
+      owned[start_page..start_page + pages].fill(false);
+    } else {
+      let level = rng.next_below(EXPECTED_BLOCK_LEVELS);
+      let pages = 1 + rng.next_below(1 << level);
+      let min_level = bits::ceil_log2(pages);
+      let available = allocator.levels[min_level..]
+        .iter()
+        .any(|level| level.head != 0);
+      let result = allocator.allocate(pages);
+
+      check_eq!(context, result.is_some(), available);
+
+      if let Some((addr, act_count)) = result {
+        let start_page = (addr - base_addr) >> memory::PAGE_SHIFT;
+        let end_page = start_page + act_count;
+
+        check_eq!(context, act_count, 1 << min_level);
+        check_eq!(context, addr & ((act_count << memory::PAGE_SHIFT) - 1), 0);
+
+        if end_page > TEST_PAGE_COUNT || owned[start_page..end_page].contains(&true) {
+          mark_fail!(context, "Allocated an unavailable or owned page.");
+          return;
+        }
+
+        owned[start_page..end_page].fill(true);
+        live[live_count] = (addr, act_count);
+        live_count += 1;
+      }
+    }
+
+    if !is_consistent(&allocator, &owned) {
+      mark_fail!(context, "Allocator is inconsistent with the model.");
+      debug_print!("    after operation {}\n", op);
+      return;
+    }
+  }
+
+  for &(addr, pages) in &live[..live_count] {
+    allocator.free(addr, pages);
+  }
+
+  for (level, head) in allocator.levels.iter().zip(heads) {
+    check_eq!(context, level.head, head);
+  }
+
+  check_eq!(context, allocator.free_mem, TEST_MEM_SIZE);
+}
+
 #[cfg(target_pointer_width = "32")]
 fn make_expected_levels() -> [BlockLevel; EXPECTED_BLOCK_LEVELS] {
   [
@@ -612,6 +1253,120 @@ fn make_allocator<'alloc>(base_offset: usize) -> BuddyPageAllocator<'alloc> {
   BuddyPageAllocator::new(base_addr, TEST_BUFFER_SIZE, meta_addr as *mut u8, avail).unwrap()
 }
 
+/// Summarize a level's free list.
+///
+/// # Parameters
+///
+/// * `allocator` - The allocator to summarize.
+/// * `level` - The level to summarize.
+///
+/// # Returns
+///
+/// A tuple with the number of blocks in the list and the wrapping sum of their
+/// addresses. The summary does not depend on the list's head or order.
+fn get_level_summary(allocator: &BuddyPageAllocator, level: usize) -> (usize, usize) {
+  let head = allocator.levels[level].head;
+  let mut ptr = head;
+  let mut count = 0;
+  let mut addr_sum: usize = 0;
+
+  if head == 0 {
+    return (0, 0);
+  }
+
+  loop {
+    count += 1;
+    addr_sum = addr_sum.wrapping_add(ptr);
+    ptr = BuddyPageAllocator::get_block_node(ptr).unwrap().next;
+
+    if ptr == head {
+      break;
+    }
+  }
+
+  (count, addr_sum)
+}
+
+/// Check that an allocator is consistent with a page ownership model.
+///
+/// # Parameters
+///
+/// * `allocator` - The allocator to check.
+/// * `owned` - The pages allocated from the allocator.
+///
+/// # Description
+///
+/// Every free block must be aligned on its size, must lie within the available
+/// region, and must not overlap an owned page or another free block. Every
+/// page in the region must be either owned or free.
+///
+/// A block's pair flag is set when exactly one block of the pair is free, so
+/// every free block's pair flag must be set, and a level must have as many set
+/// flags as free blocks. A free block whose buddy is also free leaves the pair
+/// flag clear, so the flags also catch buddies that failed to coalesce.
+///
+/// # Returns
+///
+/// True if the allocator is consistent, false otherwise.
+fn is_consistent(allocator: &BuddyPageAllocator, owned: &[bool; TEST_PAGE_COUNT]) -> bool {
+  let mut free = [false; TEST_PAGE_COUNT];
+  let mut free_pages = 0;
+
+  for (level_idx, level) in allocator.levels.iter().enumerate() {
+    let block_pages = 1 << level_idx;
+    let mut ptr = level.head;
+    let mut blocks = 0;
+
+    while ptr != 0 {
+      let start_page = (ptr - allocator.base) >> memory::PAGE_SHIFT;
+      let end_page = start_page + block_pages;
+
+      if start_page & (block_pages - 1) != 0 || end_page > TEST_PAGE_COUNT {
+        return false;
+      }
+
+      for page in start_page..end_page {
+        if owned[page] || free[page] {
+          return false;
+        }
+
+        free[page] = true;
+      }
+
+      let block_pair = (start_page >> level_idx) >> 1;
+      let word = allocator.flags[level.offset + (block_pair >> bits::WORD_BIT_SHIFT)];
+
+      if word & (1 << (block_pair & bits::WORD_BIT_MASK)) == 0 {
+        return false;
+      }
+
+      blocks += 1;
+      free_pages += block_pages;
+      ptr = BuddyPageAllocator::get_block_node(ptr).unwrap().next;
+
+      if ptr == level.head {
+        break;
+      }
+    }
+
+    let flags_end = allocator
+      .levels
+      .get(level_idx + 1)
+      .map_or(allocator.flags.len(), |next| next.offset);
+    let set_flags: u32 = allocator.flags[level.offset..flags_end]
+      .iter()
+      .map(|word| word.count_ones())
+      .sum();
+
+    if set_flags as usize != blocks {
+      return false;
+    }
+  }
+
+  iter::zip(owned, &free).all(|(owned, free)| owned != free)
+    && allocator.free_mem == free_pages << memory::PAGE_SHIFT
+}
+
 /// Verifies the state of an allocator.
 ///
 /// # Parameters
@@ -647,7 +1402,7 @@ fn verify_allocator(
     blocks >>= 1;
 
     for block in *exp_blocks {
-      let node = BuddyPageAllocator::get_block_node(ptr);
+      let node = BuddyPageAllocator::get_block_node(ptr).unwrap();
       check_eq!(context, ptr, *block);
       ptr = node.next;
 
@@ -676,7 +1431,7 @@ fn verify_allocator(
     check_eq!(context, ptr, exp_blocks[0]);
 
     for block in exp_blocks.iter().rev() {
-      let node = BuddyPageAllocator::get_block_node(ptr);
+      let node = BuddyPageAllocator::get_block_node(ptr).unwrap();
       ptr = node.prev;
       check_eq!(context, ptr, *block);
     }
@@ -151,7 +151,7 @@ struct ObjectWrapper<T> {
 /// the unused list.
 ///
 ///   NOTE: The slab manager is NOT thread-safe.
-struct SlabManager<A, T> {
+pub struct SlabManager<A, T> {
   slab_pages: usize,
   objs_per_slab: usize,
   unused: usize,
@@ -110,8 +110,8 @@ where
   }
 
   /// See `PageAllocator::free()`.
-  fn free(&mut self, addr: usize, pages: usize) {
-    self.allocator.free(addr, pages);
+  fn free(&mut self, addr: usize, pages: usize) -> bool {
+    self.allocator.free(addr, pages)
   }
 
   /// See `PageAllocator::get_alloc_mem()`.
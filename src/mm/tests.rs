@@ -0,0 +1,207 @@
+//! Memory Management Tests
+
+use super::{BuddyPageAllocator, VirtualRange, ZONE_ALLOCATOR_COUNT, ZONE_ALLOCATOR_INITIALIZER};
+use crate::arch;
+use crate::arch::memory::{
+  BufferedPageAllocator, DescriptorInfo, DescriptorKind, MappingStrategy, MemoryConfig,
+  MemoryRange, MemoryZone, PageAllocator,
+};
+use crate::debug_print;
+use crate::support::addr::PhysAddr;
+use crate::support::bits;
+use crate::sync::SpinLock;
+use crate::test::{self, memory, tlb};
+use crate::{check_eq, check_lt, check_none, check_not_none, execute_test};
+use core::ptr;
+
+/// Test memory configuration.
+///
+///   NOTE: This is static to save stack space.
+static mut TEST_MEM_CONFIG: MemoryConfig = MemoryConfig::new(MemoryZone::InvalidZone);
+
+/// Test allocator memory configuration.
+///
+///   NOTE: This is static to save stack space.
+static mut TEST_ALLOC_CONFIG: MemoryConfig = MemoryConfig::new(MemoryZone::InvalidZone);
+
+/// Run the memory management tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_zone_allocator_init);
+  execute_test!(context, test_map_unmap);
+}
+
+/// Test constructing the zone allocators from a memory configuration.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Treats the test memory as a single linear memory range and verifies the
+/// metadata is carved out of the end of the range, the allocator never hands
+/// out the metadata pages, and freeing every page restores the free memory
+/// count.
+fn test_zone_allocator_init(context: &mut test::TestContext) {
+  let virt_base = arch::get_kernel_virtual_base();
+  let page_size = arch::get_page_size();
+  let base_addr = memory::get_test_memory_mut().as_ptr() as usize - virt_base;
+  let meta_size =
+    bits::align_up(BuddyPageAllocator::calc_metadata_size(memory::MEMORY_SIZE), page_size);
+  let meta_base = base_addr + memory::MEMORY_SIZE - meta_size;
+
+  let mem_config = unsafe { ptr::addr_of_mut!(TEST_MEM_CONFIG).as_mut().unwrap() };
+  let alloc_config = unsafe { ptr::addr_of_mut!(TEST_ALLOC_CONFIG).as_mut().unwrap() };
+
+  memory::reset_test_memory();
+  mem_config.clear();
+  mem_config.insert_range(MemoryRange {
+    tag: MemoryZone::LinearMemoryZone,
+    base: base_addr,
+    size: memory::MEMORY_SIZE,
+  });
+
+  let mut allocators: [Option<SpinLock<BuddyPageAllocator>>; ZONE_ALLOCATOR_COUNT] =
+    [ZONE_ALLOCATOR_INITIALIZER; ZONE_ALLOCATOR_COUNT];
+  super::init_zone_allocators(&mut allocators, alloc_config, mem_config);
+
+  // The metadata should be excluded from the end of the linear range.
+  check_eq!(context, alloc_config.len(), 1);
+  check_eq!(context, alloc_config.get_ranges()[0].base, base_addr);
+  check_eq!(context, alloc_config.get_ranges()[0].size, memory::MEMORY_SIZE - meta_size);
+
+  // There is no high memory in the configuration.
+  check_none!(context, allocators[super::HIGH_MEMORY_ALLOCATOR]);
+  check_not_none!(context, allocators[super::LINEAR_MEMORY_ALLOCATOR]);
+
+  let Some(lock) = &allocators[super::LINEAR_MEMORY_ALLOCATOR] else {
+    return;
+  };
+
+  let mut allocator = lock.lock();
+  check_eq!(context, allocator.get_free_mem(), memory::MEMORY_SIZE - meta_size);
+  check_eq!(context, allocator.get_alloc_mem(), 0);
+
+  // Allocate every available page and verify none come from the metadata.
+  let mut count = 0;
+  while let Some((addr, _)) = allocator.alloc(1) {
+    check_lt!(context, addr, meta_base);
+    count += 1;
+  }
+
+  check_eq!(context, count, (memory::MEMORY_SIZE - meta_size) / page_size);
+  check_eq!(context, allocator.get_free_mem(), 0);
+
+  // Return every page.
+  let mut addr = base_addr;
+  while addr < meta_base {
+    allocator.free(addr, 1);
+    addr += page_size;
+  }
+
+  check_eq!(context, allocator.get_free_mem(), memory::MEMORY_SIZE - meta_size);
+  check_eq!(context, allocator.get_alloc_mem(), 0);
+}
+
+/// Test mapping and unmapping through the architecture-neutral interface.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Maps four device pages into a detached set of tables, then unmaps the
+/// middle two. The tables are not attached to the MMU, so mapping into them
+/// does not affect the kernel's address space.
+fn test_map_unmap(context: &mut test::TestContext) {
+  const MAX_RANGES: usize = 4;
+
+  let virt_base = arch::get_kernel_virtual_base();
+  let page_size = arch::get_page_size();
+  let section_size = arch::get_section_size();
+  let base_addr = memory::get_test_memory_mut().as_ptr() as usize - virt_base;
+  let table_area_size = page_size * 16;
+  let phys_addr = base_addr + table_area_size;
+
+  memory::reset_test_memory();
+
+  let mut allocator =
+    BufferedPageAllocator::<1>::new(base_addr, base_addr + table_area_size, page_size);
+  let (root_addr, _) = allocator.alloc(1).unwrap();
+
+  unsafe {
+    ptr::write_bytes((virt_base + root_addr) as *mut u8, 0, page_size);
+  }
+
+  let pages_start = PhysAddr::new(root_addr);
+  let region = VirtualRange {
+    tag: (),
+    base: virt_base + section_size,
+    size: page_size * 4,
+  };
+
+  super::map(
+    pages_start,
+    &region,
+    PhysAddr::new(phys_addr),
+    true,
+    &mut allocator,
+    MappingStrategy::Granular,
+  );
+
+  let mut ranges = [(0, 0, DescriptorInfo::invalid()); MAX_RANGES];
+  let mut count = 0;
+
+  arch::mm::dump_tables(virt_base, root_addr, |virt, size, info| {
+    if count < MAX_RANGES {
+      ranges[count] = (virt, size, *info);
+    }
+
+    count += 1;
+  });
+
+  let expected = DescriptorInfo {
+    kind: DescriptorKind::Page,
+    phys_addr,
+    device: true,
+    access_flag: true,
+  };
+
+  check_eq!(context, count, 1);
+  check_eq!(context, ranges[0].0, region.base);
+  check_eq!(context, ranges[0].1, region.size);
+  let matches = ranges[0].2 == expected;
+  check_eq!(context, matches, true);
+
+  let hole = VirtualRange {
+    tag: (),
+    base: region.base + page_size,
+    size: page_size * 2,
+  };
+
+  tlb::clear_invalidations();
+  super::unmap(pages_start, &hole);
+  check_eq!(context, tlb::was_invalidated(hole.base, hole.size), true);
+
+  count = 0;
+
+  arch::mm::dump_tables(virt_base, root_addr, |virt, size, info| {
+    if count < MAX_RANGES {
+      ranges[count] = (virt, size, *info);
+    }
+
+    count += 1;
+  });
+
+  check_eq!(context, count, 2);
+  check_eq!(context, ranges[0].0, region.base);
+  check_eq!(context, ranges[0].1, page_size);
+  check_eq!(context, ranges[1].0, region.base + page_size * 3);
+  check_eq!(context, ranges[1].1, page_size);
+  check_eq!(context, ranges[1].2.phys_addr, phys_addr + page_size * 3);
+}
@@ -0,0 +1,165 @@
+//! Deferred Work
+//!
+//! Interrupt handlers must do as little work as possible. Work that does not
+//! need to happen immediately is pushed to a deferred queue and run later from
+//! a core's idle loop. Deferred callbacks run once, in the order they were
+//! pushed.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::arch::interrupts;
+use crate::sync::SpinLock;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::ptr;
+
+/// The maximum number of callbacks that may be pending in the system queue.
+pub const MAX_DEFERRED_CALLBACKS: usize = 32;
+
+/// Deferred queue convenience type.
+pub type SystemDeferredQueue = DeferredQueue<MAX_DEFERRED_CALLBACKS>;
+
+/// The system deferred queue.
+static mut DEFERRED_QUEUE: SystemDeferredQueue = DeferredQueue::new();
+
+/// A ring buffer of pending callbacks.
+struct CallbackRing<const CAPACITY: usize> {
+  callbacks: [Option<fn()>; CAPACITY],
+  head: usize,
+  len: usize,
+  overflow_count: usize,
+}
+
+/// A bounded queue of deferred callbacks. Any number of producers, including
+/// interrupt handlers, may push callbacks, and any number of consumers may run
+/// them.
+pub struct DeferredQueue<const CAPACITY: usize> {
+  ring: SpinLock<CallbackRing<CAPACITY>>,
+}
+
+impl<const CAPACITY: usize> DeferredQueue<CAPACITY> {
+  /// Construct an empty deferred queue.
+  pub const fn new() -> Self {
+    assert!(CAPACITY > 0);
+
+    DeferredQueue {
+      ring: SpinLock::new(CallbackRing {
+        callbacks: [None; CAPACITY],
+        head: 0,
+        len: 0,
+        overflow_count: 0,
+      }),
+    }
+  }
+
+  /// Push a callback to the back of the queue.
+  ///
+  /// # Parameters
+  ///
+  /// * `callback` - The callback to run.
+  ///
+  /// # Description
+  ///
+  /// Safe to call from interrupt context. If the queue is full, the callback is
+  /// dropped and the overflow is counted.
+  ///
+  /// # Returns
+  ///
+  /// True if the callback was queued, or false if the queue is full.
+  pub fn push(&self, callback: fn()) -> bool {
+    self.with_ring(|ring| {
+      if ring.len == CAPACITY {
+        ring.overflow_count += 1;
+        return false;
+      }
+
+      let tail = (ring.head + ring.len) % CAPACITY;
+      ring.callbacks[tail] = Some(callback);
+      ring.len += 1;
+      true
+    })
+  }
+
+  /// Run all pending callbacks.
+  ///
+  /// # Description
+  ///
+  /// Each callback is removed from the queue before it is run, and the lock is
+  /// not held while it runs, so a callback or an interrupt handler may push
+  /// more work. At most the callbacks pending when the function is called are
+  /// run. Work pushed while the callbacks run is left pending for the next call.
+  ///
+  /// Any number of cores may drain the queue concurrently. Each callback is
+  /// removed by exactly one of them, and a core stops early if the others have
+  /// already emptied the queue.
+  ///
+  /// # Returns
+  ///
+  /// The number of callbacks run by this call.
+  pub fn run_all(&self) -> usize {
+    let pending = self.get_pending_count();
+    let mut run = 0;
+
+    while run < pending {
+      let Some(callback) = self.with_ring(|ring| {
+        if ring.len == 0 {
+          return None;
+        }
+
+        let callback = ring.callbacks[ring.head].take();
+        ring.head = (ring.head + 1) % CAPACITY;
+        ring.len -= 1;
+        callback
+      }) else {
+        break;
+      };
+
+      callback();
+      run += 1;
+    }
+
+    run
+  }
+
+  /// Get the number of pending callbacks.
+  pub fn get_pending_count(&self) -> usize {
+    self.with_ring(|ring| ring.len)
+  }
+
+  /// Get the number of callbacks dropped because the queue was full.
+  pub fn get_overflow_count(&self) -> usize {
+    self.with_ring(|ring| ring.overflow_count)
+  }
+
+  /// Access the ring buffer with the lock held.
+  ///
+  /// # Parameters
+  ///
+  /// * `f` - The function to call with the ring buffer.
+  ///
+  /// # Description
+  ///
+  /// Interrupts are masked while the lock is held so that an interrupt handler
+  /// on the same core cannot deadlock trying to push a callback.
+  ///
+  /// # Returns
+  ///
+  /// The result of `f`.
+  fn with_ring<R>(&self, f: impl FnOnce(&mut CallbackRing<CAPACITY>) -> R) -> R {
+    let irq_state = interrupts::save_and_mask_all_interrupts();
+    let result = f(&mut self.ring.lock());
+    interrupts::restore_interrupt_state(irq_state);
+    result
+  }
+}
+
+/// Get the system deferred queue.
+pub fn get_deferred_queue() -> &'static SystemDeferredQueue {
+  unsafe { ptr::addr_of!(DEFERRED_QUEUE).as_ref().unwrap() }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
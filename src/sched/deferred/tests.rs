@@ -0,0 +1,211 @@
+//! Deferred Work Tests
+
+use super::DeferredQueue;
+use crate::debug_print;
+use crate::{check_eq, execute_test, test};
+use core::ptr;
+
+/// Test queue capacity.
+const TEST_CAPACITY: usize = 4;
+
+/// Test deferred queue type.
+type TestDeferredQueue = DeferredQueue<TEST_CAPACITY>;
+
+/// The queue used by the simulated interrupt handler.
+static mut TEST_QUEUE: TestDeferredQueue = DeferredQueue::new();
+
+/// Record of the callbacks run, in order.
+static mut CALL_LOG: [usize; TEST_CAPACITY * 2] = [0; TEST_CAPACITY * 2];
+
+/// Number of entries in the call record.
+static mut CALL_COUNT: usize = 0;
+
+/// Run deferred work tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_run_order);
+  execute_test!(context, test_overflow);
+  execute_test!(context, test_push_from_interrupt);
+  execute_test!(context, test_concurrent_drain);
+}
+
+/// Get the queue used by the simulated interrupt handler.
+fn get_test_queue() -> &'static TestDeferredQueue {
+  unsafe { ptr::addr_of!(TEST_QUEUE).as_ref().unwrap() }
+}
+
+/// Clear the call record.
+fn reset_call_log() {
+  unsafe { CALL_COUNT = 0 };
+}
+
+/// Get the call record.
+fn get_call_log() -> &'static [usize] {
+  let log = unsafe { ptr::addr_of!(CALL_LOG).as_ref().unwrap() };
+  &log[..unsafe { CALL_COUNT }]
+}
+
+/// Append an identifier to the call record.
+///
+/// # Parameters
+///
+/// * `id` - The callback identifier.
+fn record_call(id: usize) {
+  let log = unsafe { ptr::addr_of_mut!(CALL_LOG).as_mut().unwrap() };
+  let count = unsafe { CALL_COUNT };
+  log[count] = id;
+  unsafe { CALL_COUNT = count + 1 };
+}
+
+/// Test callback 1.
+fn callback_1() {
+  record_call(1);
+}
+
+/// Test callback 2.
+fn callback_2() {
+  record_call(2);
+}
+
+/// Test callback 3.
+fn callback_3() {
+  record_call(3);
+}
+
+/// Simulated interrupt handler. Does the minimum and defers the rest.
+fn simulated_irq() {
+  record_call(0);
+  _ = get_test_queue().push(callback_3);
+}
+
+/// Simulated consumer on another core. Drains the queue out from under the
+/// consumer that ran this callback.
+fn concurrent_drain() {
+  record_call(4);
+  _ = get_test_queue().run_all();
+}
+
+/// Test that callbacks run once in the order they were pushed.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_run_order(context: &mut test::TestContext) {
+  let queue = TestDeferredQueue::new();
+  reset_call_log();
+
+  check_eq!(context, queue.push(callback_2), true);
+  check_eq!(context, queue.push(callback_1), true);
+  check_eq!(context, queue.push(callback_3), true);
+  check_eq!(context, queue.get_pending_count(), 3);
+
+  check_eq!(context, queue.run_all(), 3);
+  check_eq!(context, queue.get_pending_count(), 0);
+
+  let matches = get_call_log() == [2, 1, 3];
+  check_eq!(context, matches, true);
+
+  // Nothing left to run.
+  check_eq!(context, queue.run_all(), 0);
+  check_eq!(context, get_call_log().len(), 3);
+}
+
+/// Test that pushing to a full queue drops the callback and counts the
+/// overflow.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_overflow(context: &mut test::TestContext) {
+  let queue = TestDeferredQueue::new();
+  reset_call_log();
+
+  for _ in 0..TEST_CAPACITY {
+    check_eq!(context, queue.push(callback_1), true);
+  }
+
+  check_eq!(context, queue.push(callback_2), false);
+  check_eq!(context, queue.push(callback_2), false);
+  check_eq!(context, queue.get_overflow_count(), 2);
+
+  check_eq!(context, queue.run_all(), TEST_CAPACITY);
+
+  let matches = get_call_log().iter().all(|id| *id == 1);
+  check_eq!(context, matches, true);
+  check_eq!(context, get_call_log().len(), TEST_CAPACITY);
+
+  // The ring buffer wraps around after draining.
+  check_eq!(context, queue.push(callback_2), true);
+  check_eq!(context, queue.run_all(), 1);
+  check_eq!(context, queue.get_overflow_count(), 2);
+}
+
+/// Test pushing work from a simulated interrupt handler.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The handler is first invoked directly, as if an interrupt arrived before
+/// draining, then again from a deferred callback, as if an interrupt arrived
+/// while draining. Work pushed while draining must be left for the next drain.
+fn test_push_from_interrupt(context: &mut test::TestContext) {
+  let queue = get_test_queue();
+  _ = queue.run_all();
+  reset_call_log();
+
+  simulated_irq();
+  check_eq!(context, queue.get_pending_count(), 1);
+
+  check_eq!(context, queue.push(simulated_irq), true);
+  check_eq!(context, queue.run_all(), 2);
+
+  let matches = get_call_log() == [0, 3, 0];
+  check_eq!(context, matches, true);
+
+  // The callback deferred by the second interrupt is still pending.
+  check_eq!(context, queue.get_pending_count(), 1);
+  check_eq!(context, queue.run_all(), 1);
+
+  let matches = get_call_log() == [0, 3, 0, 3];
+  check_eq!(context, matches, true);
+  check_eq!(context, queue.get_overflow_count(), 0);
+}
+
+/// Test draining the queue while another consumer is also draining it.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first callback drains the rest of the queue as if another core's idle
+/// loop got to it first. The outer drain must stop once the queue is empty
+/// rather than popping the count it saw on entry.
+fn test_concurrent_drain(context: &mut test::TestContext) {
+  let queue = get_test_queue();
+  _ = queue.run_all();
+  reset_call_log();
+
+  check_eq!(context, queue.push(concurrent_drain), true);
+  check_eq!(context, queue.push(callback_1), true);
+  check_eq!(context, queue.push(callback_2), true);
+
+  // Only the first callback is run by the outer drain.
+  check_eq!(context, queue.run_all(), 1);
+  check_eq!(context, queue.get_pending_count(), 0);
+
+  let matches = get_call_log() == [4, 1, 2];
+  check_eq!(context, matches, true);
+
+  // The ring buffer is still consistent.
+  check_eq!(context, queue.push(callback_3), true);
+  check_eq!(context, queue.run_all(), 1);
+  check_eq!(context, queue.get_pending_count(), 0);
+}
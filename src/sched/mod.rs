@@ -0,0 +1,661 @@
+//! Scheduler
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+pub mod deferred;
+pub mod wait;
+
+pub use wait::WaitQueue;
+
+use crate::arch::{self, cpu, gic, interrupts, irq, percpu, time};
+use crate::sync::{Barrier, SpinLock};
+use crate::task::{self, MAX_PRIORITY, Task, TaskState};
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::{cmp, ptr};
+
+/// The maximum number of tasks that may be queued on each core.
+pub const MAX_QUEUED_TASKS: usize = 32;
+
+/// The number of timer ticks between balancing the run queue.
+const BALANCE_INTERVAL_TICKS: usize = 16;
+
+/// The number of timer ticks between raising the priority of waiting tasks.
+const AGING_INTERVAL_TICKS: usize = 8;
+
+/// Run queue convenience type.
+pub type SystemRunQueue = RunQueue<'static, { cpu::MAX_CORES }, MAX_QUEUED_TASKS>;
+
+/// Re-initialization guard.
+static mut INITIALIZED: bool = false;
+
+/// The system run queue. The core count is set by `init()`.
+///
+///   NOTE: The run queue is constructed statically rather than by `init()` to
+///         avoid building it on the stack.
+static mut RUN_QUEUE: SpinLock<SystemRunQueue> = SpinLock::new(SystemRunQueue::new(1));
+
+/// The barrier every core joins before entering the scheduler. The core count
+/// is set by `init()`.
+static mut BOOT_BARRIER: Barrier = Barrier::new(1);
+
+/// The number of timer ticks on the primary core.
+static mut PRIMARY_TICKS: usize = 0;
+
+/// A fixed-capacity queue of tasks ordered by priority. Tasks with the same
+/// priority are first-in, first-out.
+///
+/// The queue tracks the priority each task was queued with separately from the
+/// task so that a waiting task's priority can be boosted without changing the
+/// task's own priority.
+struct TaskQueue<'task, const CAPACITY: usize> {
+  tasks: [Option<&'task mut Task>; CAPACITY],
+  priorities: [u8; CAPACITY],
+  head: usize,
+  len: usize,
+}
+
+impl<'task, const CAPACITY: usize> TaskQueue<'task, CAPACITY> {
+  /// Construct an empty task queue.
+  const fn new() -> Self {
+    TaskQueue {
+      tasks: [const { None }; CAPACITY],
+      priorities: [0; CAPACITY],
+      head: 0,
+      len: 0,
+    }
+  }
+
+  /// Check if the queue is full.
+  fn is_full(&self) -> bool {
+    self.len == CAPACITY
+  }
+
+  /// Get the ring index of a position in the queue.
+  ///
+  /// # Parameters
+  ///
+  /// * `pos` - The position relative to the front of the queue.
+  fn ring_index(&self, pos: usize) -> usize {
+    (self.head + pos) % CAPACITY
+  }
+
+  /// Add a task behind all queued tasks with the same or higher priority.
+  ///
+  /// # Parameters
+  ///
+  /// * `task` - The task to add.
+  /// * `priority` - The priority to queue the task with.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes the queue is not full.
+  fn push(&mut self, task: &'task mut Task, priority: u8) {
+    let mut pos = self.len;
+
+    while pos > 0 && self.priorities[self.ring_index(pos - 1)] < priority {
+      let (from, to) = (self.ring_index(pos - 1), self.ring_index(pos));
+      self.tasks[to] = self.tasks[from].take();
+      self.priorities[to] = self.priorities[from];
+      pos -= 1;
+    }
+
+    let idx = self.ring_index(pos);
+    self.tasks[idx] = Some(task);
+    self.priorities[idx] = priority;
+    self.len += 1;
+  }
+
+  /// Remove the first ready task from the queue.
+  ///
+  /// # Description
+  ///
+  /// Blocked tasks are skipped and stay in the queue. Dead tasks found before
+  /// the first ready task are reaped by removing them from the queue.
+  ///
+  /// # Returns
+  ///
+  /// The highest-priority ready task that has been queued the longest, or None
+  /// if there is no ready task in the queue.
+  fn pop_ready(&mut self) -> Option<&'task mut Task> {
+    let mut pos = 0;
+
+    while pos < self.len {
+      match self.tasks[self.ring_index(pos)].as_deref().map(Task::state) {
+        Some(TaskState::Ready) => return self.remove(pos).map(|(task, _)| task),
+        Some(TaskState::Dead) => _ = self.remove(pos),
+        _ => pos += 1,
+      }
+    }
+
+    None
+  }
+
+  /// Get the priority of the first ready task in the queue.
+  ///
+  /// # Returns
+  ///
+  /// The highest priority of the ready tasks, or None if there is no ready task
+  /// in the queue.
+  fn peek_priority(&self) -> Option<u8> {
+    (0..self.len)
+      .map(|pos| self.ring_index(pos))
+      .find(|&idx| {
+        self.tasks[idx]
+          .as_deref()
+          .is_some_and(|task| task.state() == TaskState::Ready)
+      })
+      .map(|idx| self.priorities[idx])
+  }
+
+  /// Find the task closest to the back of the queue that matches a predicate.
+  ///
+  /// # Parameters
+  ///
+  /// * `pred` - The predicate.
+  ///
+  /// # Returns
+  ///
+  /// The position of the task relative to the front of the queue, or None if
+  /// no task matches.
+  fn rposition(&self, pred: impl Fn(&Task) -> bool) -> Option<usize> {
+    (0..self.len).rev().find(|&pos| {
+      self.tasks[self.ring_index(pos)]
+        .as_deref()
+        .is_some_and(&pred)
+    })
+  }
+
+  /// Remove a task from the queue.
+  ///
+  /// # Parameters
+  ///
+  /// * `pos` - The position of the task relative to the front of the queue.
+  ///
+  /// # Description
+  ///
+  /// The tasks behind the removed task keep their order.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the task and the priority it was queued with, or None if the
+  /// position is beyond the back of the queue.
+  fn remove(&mut self, pos: usize) -> Option<(&'task mut Task, u8)> {
+    if pos >= self.len {
+      return None;
+    }
+
+    let idx = self.ring_index(pos);
+    let task = self.tasks[idx].take()?;
+    let priority = self.priorities[idx];
+
+    // Removing the front task only needs to advance the front of the queue.
+    if pos == 0 {
+      self.head = (self.head + 1) % CAPACITY;
+      self.len -= 1;
+      return Some((task, priority));
+    }
+
+    for i in pos..self.len - 1 {
+      let (from, to) = (self.ring_index(i + 1), self.ring_index(i));
+      self.tasks[to] = self.tasks[from].take();
+      self.priorities[to] = self.priorities[from];
+    }
+
+    self.len -= 1;
+    Some((task, priority))
+  }
+
+  /// Raise the priority of every queued task below `MAX_PRIORITY` by one.
+  ///
+  /// # Description
+  ///
+  /// Raising every priority by the same amount keeps the queue ordered. Tasks
+  /// that reach `MAX_PRIORITY` are behind the tasks already queued at
+  /// `MAX_PRIORITY`.
+  fn age(&mut self) {
+    for pos in 0..self.len {
+      let idx = self.ring_index(pos);
+      self.priorities[idx] = cmp::min(self.priorities[idx] + 1, MAX_PRIORITY);
+    }
+  }
+}
+
+/// Per-core run queues. The run queue can track up to CORES cores with up to
+/// CAPACITY tasks queued on each core.
+pub struct RunQueue<'task, const CORES: usize, const CAPACITY: usize> {
+  core_count: usize,
+  queues: [TaskQueue<'task, CAPACITY>; CORES],
+}
+
+impl<'task, const CORES: usize, const CAPACITY: usize> RunQueue<'task, CORES, CAPACITY> {
+  /// Construct a new run queue.
+  ///
+  /// # Parameters
+  ///
+  /// * `core_count` - The number of cores available to the scheduler.
+  pub const fn new(core_count: usize) -> Self {
+    assert!(core_count > 0 && core_count <= CORES);
+    assert!(CAPACITY > 0);
+
+    RunQueue {
+      core_count,
+      queues: [const { TaskQueue::new() }; CORES],
+    }
+  }
+
+  /// Get the number of cores available to the scheduler.
+  pub fn get_core_count(&self) -> usize {
+    self.core_count
+  }
+
+  /// Set the number of cores available to the scheduler.
+  ///
+  /// # Parameters
+  ///
+  /// * `core_count` - The number of cores available to the scheduler.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes no tasks are queued.
+  pub fn set_core_count(&mut self, core_count: usize) {
+    assert!(core_count > 0 && core_count <= CORES);
+    self.core_count = core_count;
+  }
+
+  /// Get the number of tasks queued on a core.
+  ///
+  /// # Parameters
+  ///
+  /// * `core_idx` - The core index.
+  pub fn get_load(&self, core_idx: usize) -> usize {
+    assert!(core_idx < self.core_count);
+    self.queues[core_idx].len
+  }
+
+  /// Queue a task on the least-loaded core it may run on.
+  ///
+  /// # Parameters
+  ///
+  /// * `task` - The task to queue.
+  ///
+  /// # Description
+  ///
+  /// The cores a task may run on are determined by `Task::can_run_on()`, so a
+  /// pinned task is always queued on the core it is pinned to. The load of a
+  /// core is the number of tasks in its queue. If multiple permitted cores have
+  /// the same load, the task is queued on the lowest core index. Cores with full
+  /// queues are skipped.
+  ///
+  /// The task is queued with its current priority behind any tasks on the core
+  /// with the same or higher priority. See `Task::get_priority()`.
+  ///
+  /// # Returns
+  ///
+  /// The index of the core the task was queued on, or the task if there are no
+  /// permitted cores with space in their queues.
+  pub fn enqueue(&mut self, task: &'task mut Task) -> Result<usize, &'task mut Task> {
+    let mut target: Option<usize> = None;
+
+    for core_idx in 0..self.core_count {
+      let queue = &self.queues[core_idx];
+
+      if queue.is_full() || !task.can_run_on(core_idx) {
+        continue;
+      }
+
+      match target {
+        Some(t) if self.queues[t].len <= queue.len => {}
+        _ => target = Some(core_idx),
+      }
+    }
+
+    let Some(core_idx) = target else {
+      return Err(task);
+    };
+
+    let priority = task.get_priority();
+    self.queues[core_idx].push(task, priority);
+    Ok(core_idx)
+  }
+
+  /// Remove the next ready task queued on a core.
+  ///
+  /// # Parameters
+  ///
+  /// * `core_idx` - The core index.
+  ///
+  /// # Description
+  ///
+  /// Blocked tasks stay queued until they are ready. Dead tasks are removed
+  /// from the queue as they are found. See `Task::state()`.
+  ///
+  /// # Returns
+  ///
+  /// The highest-priority ready task that has been queued the longest, or None
+  /// if the core has no ready tasks.
+  pub fn dequeue(&mut self, core_idx: usize) -> Option<&'task mut Task> {
+    assert!(core_idx < self.core_count);
+    self.queues[core_idx].pop_ready()
+  }
+
+  /// Check if a task queued on a core should preempt the core's current task.
+  ///
+  /// # Parameters
+  ///
+  /// * `core_idx` - The core index.
+  /// * `current` - The task running on the core.
+  ///
+  /// # Returns
+  ///
+  /// True if a ready task with a higher priority than the current task is
+  /// queued on the core, false otherwise.
+  pub fn should_preempt(&self, core_idx: usize, current: &Task) -> bool {
+    assert!(core_idx < self.core_count);
+    self.queues[core_idx]
+      .peek_priority()
+      .is_some_and(|priority| priority > current.get_priority())
+  }
+
+  /// Raise the priority of every waiting task below `MAX_PRIORITY` by one.
+  ///
+  /// # Description
+  ///
+  /// Called periodically so that a low-priority task waiting behind a steady
+  /// stream of higher-priority tasks eventually runs. A task's boost only
+  /// lasts until it is dequeued.
+  pub fn age(&mut self) {
+    for queue in &mut self.queues[..self.core_count] {
+      queue.age();
+    }
+  }
+
+  /// Migrate tasks from longer queues to shorter queues.
+  ///
+  /// # Description
+  ///
+  /// Repeatedly moves a task from a queue to a queue that is shorter by at
+  /// least two tasks. The pair of queues with the largest difference in load
+  /// that has a movable task is balanced first. A task is only moved if it is
+  /// not pinned and it may run on the target core. See `Task::can_run_on()`.
+  /// The task moved is the task closest to the back of the source queue, which
+  /// is also the lowest-priority task that may move. It keeps the priority it
+  /// was queued with.
+  ///
+  /// Balancing stops when no pair of queues differs in load by two or more, or
+  /// when none of the tasks on longer queues may move to the shorter queues.
+  ///
+  /// # Returns
+  ///
+  /// The number of tasks migrated.
+  pub fn balance(&mut self) -> usize {
+    let mut migrated = 0;
+
+    while let Some((src, dst, pos)) = self.find_migration() {
+      let Some((task, priority)) = self.queues[src].remove(pos) else {
+        break;
+      };
+
+      self.queues[dst].push(task, priority);
+      migrated += 1;
+    }
+
+    migrated
+  }
+
+  /// Find the next task to migrate.
+  ///
+  /// # Description
+  ///
+  /// Every migration reduces the difference in load between two queues by at
+  /// least two, so `balance()` always terminates.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the source core index, target core index, and the task's
+  /// position in the source queue, or None if there is no task to migrate.
+  fn find_migration(&self) -> Option<(usize, usize, usize)> {
+    let mut migration = None;
+    let mut max_diff = 1;
+
+    for src in 0..self.core_count {
+      for dst in 0..self.core_count {
+        let src_len = self.queues[src].len;
+        let dst_queue = &self.queues[dst];
+
+        if dst_queue.is_full() || src_len <= dst_queue.len + max_diff {
+          continue;
+        }
+
+        let pos = self.queues[src].rposition(|task| !task.is_pinned() && task.can_run_on(dst));
+
+        if let Some(pos) = pos {
+          migration = Some((src, dst, pos));
+          max_diff = src_len - dst_queue.len;
+        }
+      }
+    }
+
+    migration
+  }
+}
+
+/// Initialize the scheduler.
+///
+/// # Description
+///
+/// Sets the system run queue's and the boot barrier's core counts to the
+/// number of cores in the device tree.
+///
+///   NOTE: Must only be called once while the kernel is single-threaded.
+pub fn init() {
+  unsafe {
+    assert!(!INITIALIZED);
+    INITIALIZED = true;
+  }
+
+  let core_count = arch::get_device_tree().get_core_config().get_core_count();
+  get_run_queue().lock().set_core_count(core_count);
+  get_boot_barrier().set_count(core_count);
+}
+
+/// Bring a secondary core online and enter the scheduler.
+///
+/// # Parameters
+///
+/// * `core_idx` - The secondary core's index.
+/// * `barrier` - The barrier to join before entering the scheduler.
+/// * `enter` - The scheduler entry point.
+///
+/// # Description
+///
+/// Sets the core's idle task as its current task, marks the core online, and
+/// waits at the barrier until all cores arrive. See `task::init_idle_task()`.
+///
+///   NOTE: Interrupts must be disabled.
+pub fn start_secondary_core(core_idx: usize, barrier: &Barrier, enter: extern "C" fn() -> !) -> ! {
+  let core = percpu::get_core_data(core_idx);
+  let task = task::init_idle_task(core_idx);
+
+  core.set_current_task_addr(task as *const _ as usize);
+  core.set_online(true);
+  barrier.wait();
+
+  enter()
+}
+
+/// Get the barrier every core joins before entering the scheduler.
+pub fn get_boot_barrier() -> &'static Barrier {
+  unsafe { ptr::addr_of!(BOOT_BARRIER).as_ref().unwrap() }
+}
+
+/// Get the system run queue.
+pub fn get_run_queue() -> &'static SpinLock<SystemRunQueue> {
+  unsafe { ptr::addr_of!(RUN_QUEUE).as_ref().unwrap() }
+}
+
+/// Access the system run queue with the lock held.
+///
+/// # Parameters
+///
+/// * `f` - The function to call with the run queue.
+///
+/// # Description
+///
+/// Interrupts are masked while the lock is held so that the timer interrupt
+/// handler on the same core cannot deadlock trying to access the run queue.
+///
+/// # Returns
+///
+/// The result of `f`.
+fn with_run_queue<R>(f: impl FnOnce(&mut SystemRunQueue) -> R) -> R {
+  let irq_state = interrupts::save_and_mask_all_interrupts();
+  let result = f(&mut get_run_queue().lock());
+  interrupts::restore_interrupt_state(irq_state);
+  result
+}
+
+/// Balance the system run queue.
+///
+/// # Description
+///
+/// See `RunQueue::balance()`.
+///
+/// # Returns
+///
+/// The number of tasks migrated.
+pub fn balance() -> usize {
+  with_run_queue(|run_queue| run_queue.balance())
+}
+
+/// Raise the priority of every task waiting in the system run queue.
+///
+/// # Description
+///
+/// See `RunQueue::age()`.
+pub fn age() {
+  with_run_queue(|run_queue| run_queue.age());
+}
+
+/// Handle a periodic timer tick on the current core.
+///
+/// # Description
+///
+/// Called from the timer interrupt handler. Charges the tick to the current
+/// task's quantum. See `Task::tick()`. If the quantum has not run out, but a
+/// higher-priority task is queued on the current core, requests a reschedule
+/// for the current task.
+///
+/// # Returns
+///
+/// True if the current task should be switched out because its quantum has
+/// run out or a higher-priority task is queued on the current core.
+pub fn tick() -> bool {
+  let task = Task::get_current_task_mut();
+  let core_idx = arch::get_current_core_index();
+
+  if task.tick() {
+    return true;
+  }
+
+  if !with_run_queue(|run_queue| run_queue.should_preempt(core_idx, task)) {
+    return false;
+  }
+
+  task.request_reschedule();
+  true
+}
+
+/// Give up the current core to another task.
+///
+/// # Description
+///
+/// Requests a reschedule for the current task. See
+/// `Task::request_reschedule()`.
+///
+///   TODO: Switch to the next ready task once context switching is
+///         implemented. Until then, the core idles until the next interrupt or
+///         event, then the current task resumes.
+pub fn yield_now() {
+  Task::get_current_task_mut().request_reschedule();
+  cpu::idle();
+}
+
+/// Start the periodic timer tick.
+///
+/// # Parameters
+///
+/// * `interval_micros` - The tick period in microseconds.
+///
+/// # Description
+///
+/// Registers the timer interrupt handler, enables the timer interrupt, and
+/// starts the timer on the current core.
+///
+///   NOTE: Must only be called once, on the primary core, after the interrupt
+///         controller has been initialized.
+pub fn start_tick(interval_micros: u64) {
+  irq::register(time::TIMER_IRQ, handle_timer_irq);
+  gic::enable_irq(time::TIMER_IRQ);
+  time::arm_timer(interval_micros);
+}
+
+/// Timer interrupt handler.
+///
+/// # Description
+///
+/// Schedules the next tick and charges the tick to the current task. The
+/// primary core also ages the run queue every `AGING_INTERVAL_TICKS` ticks and
+/// balances the run queue every `BALANCE_INTERVAL_TICKS` ticks.
+fn handle_timer_irq() {
+  if !time::is_timer_pending() {
+    return;
+  }
+
+  time::rearm();
+  _ = tick();
+
+  if arch::get_current_core_index() != 0 {
+    return;
+  }
+
+  let ticks = unsafe { ptr::addr_of_mut!(PRIMARY_TICKS).as_mut().unwrap() };
+  *ticks = ticks.wrapping_add(1);
+
+  if *ticks % AGING_INTERVAL_TICKS == 0 {
+    age();
+  }
+
+  if *ticks % BALANCE_INTERVAL_TICKS == 0 {
+    _ = balance();
+  }
+}
+
+/// Idle the current core.
+///
+/// # Description
+///
+/// Runs any work that was waiting for the core to be idle, then puts the core
+/// into a low-power state until it is woken by an interrupt or an event.
+pub fn idle() {
+  run_idle_work();
+  cpu::idle();
+}
+
+/// Run the work that waits for a core to be idle.
+///
+/// # Description
+///
+/// Runs all pending deferred callbacks. See `DeferredQueue::run_all()`.
+pub fn run_idle_work() {
+  deferred::get_deferred_queue().run_all();
+}
+
+/// Run the scheduler tests.
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+  deferred::run_tests(context);
+  wait::run_tests(context);
+}
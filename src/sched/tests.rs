@@ -0,0 +1,568 @@
+//! Scheduler Tests
+
+use super::{RunQueue, deferred};
+use crate::arch::cpu::MAX_CORES;
+use crate::arch::percpu;
+use crate::debug_print;
+use crate::sync::Barrier;
+use crate::task::{
+  self, AffinityMask, DEFAULT_QUANTUM, MAX_PRIORITY, Task, TaskContext, TaskState, affinity,
+};
+use crate::{check_eq, check_none, check_optional, check_panics, execute_test, mark_fail, test};
+use core::{array, ptr};
+
+/// Number of times `count_callback()` has run.
+static mut CALLBACK_COUNT: usize = 0;
+
+/// Whether `enter_test_scheduler()` has run.
+static mut ENTERED_SCHEDULER: bool = false;
+
+/// A task that preempts the current task in `test_tick_preemption()`. The task
+/// must be static to be queued on the system run queue.
+static mut PREEMPT_TASK: Task = Task::new(TEST_TASKS, TaskContext::default());
+
+/// Number of test cores.
+const TEST_CORES: usize = 4;
+
+/// Test queue capacity per core.
+const TEST_CAPACITY: usize = 4;
+
+/// Number of test tasks.
+const TEST_TASKS: usize = 8;
+
+/// Test run queue type.
+type TestRunQueue<'task> = RunQueue<'task, TEST_CORES, TEST_CAPACITY>;
+
+/// Run scheduler tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_unaffined_balance);
+  execute_test!(context, test_affined_placement);
+  execute_test!(context, test_no_permitted_core);
+  execute_test!(context, test_dequeue_order);
+  execute_test!(context, test_balance);
+  execute_test!(context, test_balance_restricted);
+  execute_test!(context, test_priority_preemption);
+  execute_test!(context, test_aging);
+  execute_test!(context, test_task_states);
+  execute_test!(context, test_idle_work);
+  execute_test!(context, test_tick);
+  execute_test!(context, test_tick_preemption);
+  execute_test!(context, test_secondary_start);
+}
+
+/// Construct the test tasks. Task N has task identifier N.
+///
+/// # Returns
+///
+/// An array of unqueued tasks.
+fn make_tasks() -> [Task; TEST_TASKS] {
+  array::from_fn(|task_id| Task::new(task_id, TaskContext::default()))
+}
+
+/// Test that unaffined tasks are queued on the least-loaded core.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// With all cores equally loaded, ties go to the lowest core index.
+fn test_unaffined_balance(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(TEST_CORES);
+  let mut tasks = tasks.iter_mut();
+
+  for core_idx in 0..TEST_CORES {
+    let Some(task) = tasks.next() else {
+      mark_fail!(context, "Out of test tasks.");
+      return;
+    };
+
+    check_optional!(context, queue.enqueue(task).ok(), core_idx);
+  }
+
+  // Drain core 2. It is now the least-loaded core.
+  let dequeued = queue.dequeue(2).is_some();
+  check_eq!(context, dequeued, true);
+  check_eq!(context, queue.get_load(2), 0);
+
+  let Some(task) = tasks.next() else {
+    mark_fail!(context, "Out of test tasks.");
+    return;
+  };
+
+  check_optional!(context, queue.enqueue(task).ok(), 2);
+
+  // All cores have a load of one again, so the next task goes to core 0.
+  let Some(task) = tasks.next() else {
+    mark_fail!(context, "Out of test tasks.");
+    return;
+  };
+
+  check_optional!(context, queue.enqueue(task).ok(), 0);
+  check_eq!(context, queue.get_load(0), 2);
+}
+
+/// Test that affined tasks are only queued on permitted cores.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The tasks may run on cores 1 and 3. The tasks alternate between the two
+/// cores until both queues are full.
+fn test_affined_placement(context: &mut test::TestContext) {
+  let mut affinity = AffinityMask::new(MAX_CORES);
+  affinity.set_bit(1);
+  affinity.set_bit(3);
+
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(TEST_CORES);
+
+  for (i, task) in tasks.iter_mut().enumerate() {
+    task.set_affinity(Some(&affinity));
+    let expected = if i % 2 == 0 { 1 } else { 3 };
+    check_optional!(context, queue.enqueue(task).ok(), expected);
+  }
+
+  check_eq!(context, queue.get_load(0), 0);
+  check_eq!(context, queue.get_load(1), TEST_CAPACITY);
+  check_eq!(context, queue.get_load(2), 0);
+  check_eq!(context, queue.get_load(3), TEST_CAPACITY);
+}
+
+/// Test a task that may not run on any core with space in its queue.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first task may only run on a core beyond the core count. The remaining
+/// tasks may only run on core 0, so the task after core 0's queue is full is
+/// rejected.
+fn test_no_permitted_core(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(TEST_CORES - 1);
+  let (first, rest) = tasks.split_at_mut(1);
+
+  let mut affinity = AffinityMask::new(MAX_CORES);
+  affinity.set_bit(TEST_CORES - 1);
+  first[0].set_affinity(Some(&affinity));
+
+  let rejected = queue
+    .enqueue(&mut first[0])
+    .err()
+    .map(|task| task.get_task_id());
+  check_optional!(context, rejected, 0);
+
+  let mut affinity = AffinityMask::new(MAX_CORES);
+  affinity.set_bit(0);
+
+  for (i, task) in rest.iter_mut().take(TEST_CAPACITY + 1).enumerate() {
+    task.set_affinity(Some(&affinity));
+    let core_idx = queue.enqueue(task).ok();
+
+    if i < TEST_CAPACITY {
+      check_optional!(context, core_idx, 0);
+    } else {
+      check_none!(context, core_idx);
+    }
+  }
+}
+
+/// Test that tasks are dequeued in the order they were queued.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_dequeue_order(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(1);
+
+  for task in tasks.iter_mut().take(TEST_CAPACITY) {
+    check_optional!(context, queue.enqueue(task).ok(), 0);
+  }
+
+  for task_id in 0..TEST_CAPACITY {
+    let dequeued = queue.dequeue(0).map(|task| task.get_task_id());
+    check_optional!(context, dequeued, task_id);
+  }
+
+  check_none!(context, queue.dequeue(0));
+}
+
+/// Construct an imbalanced two-core run queue.
+///
+/// # Parameters
+///
+/// * `queue` - The two-core run queue.
+/// * `tasks` - The test tasks.
+/// * `movable` - Whether each task queued on core 0 may also run on core 1.
+///
+/// # Description
+///
+/// Core 1's queue is filled with tasks affined to core 1 so that the remaining
+/// tasks are all queued on core 0. Core 1's queue is then drained. The first
+/// `TEST_CAPACITY` tasks are used to fill core 1.
+fn make_imbalanced_queue<'task>(
+  queue: &mut TestRunQueue<'task>,
+  tasks: &'task mut [Task; TEST_TASKS],
+  movable: [bool; TEST_CAPACITY],
+) {
+  let (fill, rest) = tasks.split_at_mut(TEST_CAPACITY);
+  let core_0 = affinity::single(0);
+  let both = affinity::from_cores(&[0, 1]);
+
+  for task in fill {
+    task.set_affinity(Some(&affinity::single(1)));
+    _ = queue.enqueue(task);
+  }
+
+  for (task, movable) in rest.iter_mut().zip(movable) {
+    task.set_affinity(Some(if movable { &both } else { &core_0 }));
+    _ = queue.enqueue(task);
+  }
+
+  while queue.dequeue(1).is_some() {}
+}
+
+/// Test that balancing only migrates tasks permitted on the target core.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Core 0 has four tasks and core 1 has none. Only the second and fourth tasks
+/// may run on core 1. Both must move, starting with the task closest to the
+/// back of the queue, and the tasks left on core 0 must keep their order.
+fn test_balance(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(2);
+
+  make_imbalanced_queue(&mut queue, &mut tasks, [false, true, false, true]);
+  check_eq!(context, queue.get_load(0), TEST_CAPACITY);
+  check_eq!(context, queue.get_load(1), 0);
+
+  check_eq!(context, queue.balance(), 2);
+  check_eq!(context, queue.get_load(0), 2);
+  check_eq!(context, queue.get_load(1), 2);
+
+  // The queues are balanced, so there is nothing left to do.
+  check_eq!(context, queue.balance(), 0);
+
+  let expected = [
+    (0, TEST_CAPACITY),
+    (0, TEST_CAPACITY + 2),
+    (1, TEST_CAPACITY + 3),
+  ];
+
+  for (core_idx, task_id) in expected {
+    let dequeued = queue.dequeue(core_idx).map(|task| task.get_task_id());
+    check_optional!(context, dequeued, task_id);
+  }
+
+  let dequeued = queue.dequeue(1).map(|task| task.get_task_id());
+  check_optional!(context, dequeued, TEST_CAPACITY + 1);
+}
+
+/// Test that balancing does not migrate tasks restricted to their core.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Core 0 has four tasks that may only run on core 0, so the queues must stay
+/// imbalanced. With only the last task permitted on core 1, exactly one task
+/// moves even though the queues remain imbalanced.
+fn test_balance_restricted(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(2);
+
+  make_imbalanced_queue(&mut queue, &mut tasks, [false; TEST_CAPACITY]);
+  check_eq!(context, queue.balance(), 0);
+  check_eq!(context, queue.get_load(0), TEST_CAPACITY);
+  check_eq!(context, queue.get_load(1), 0);
+
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(2);
+
+  make_imbalanced_queue(&mut queue, &mut tasks, [false, false, false, true]);
+  check_eq!(context, queue.balance(), 1);
+  check_eq!(context, queue.get_load(0), TEST_CAPACITY - 1);
+
+  let dequeued = queue.dequeue(1).map(|task| task.get_task_id());
+  check_optional!(context, dequeued, TEST_TASKS - 1);
+}
+
+/// Test that a high-priority task runs before a low-priority task.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The low-priority task is queued first, but the high-priority task must be
+/// dequeued first. While the high-priority task is queued, it must preempt a
+/// running task with a lower priority, but not a running task with the same
+/// priority.
+fn test_priority_preemption(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(1);
+  let (queued, running) = tasks.split_at_mut(2);
+  let (low, high) = queued.split_at_mut(1);
+
+  low[0].set_priority(0);
+  high[0].set_priority(MAX_PRIORITY);
+  running[0].set_priority(0);
+  running[1].set_priority(MAX_PRIORITY);
+
+  let enqueued = queue.enqueue(&mut low[0]).ok();
+  check_optional!(context, enqueued, 0);
+  check_eq!(context, queue.should_preempt(0, &running[0]), false);
+
+  let enqueued = queue.enqueue(&mut high[0]).ok();
+  check_optional!(context, enqueued, 0);
+  check_eq!(context, queue.should_preempt(0, &running[0]), true);
+  check_eq!(context, queue.should_preempt(0, &running[1]), false);
+
+  check_optional!(context, queue.dequeue(0).map(|task| task.get_task_id()), 1);
+  check_eq!(context, queue.should_preempt(0, &running[0]), false);
+  check_optional!(context, queue.dequeue(0).map(|task| task.get_task_id()), 0);
+}
+
+/// Test that aging eventually runs a starved task.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A high-priority task is requeued every time it runs, so without aging the
+/// low-priority task never runs. Each round of aging raises the low-priority
+/// task by one level. Once it reaches `MAX_PRIORITY`, the requeued
+/// high-priority task is queued behind it, so the low-priority task runs next.
+fn test_aging(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(1);
+  let (low, rest) = tasks.split_at_mut(1);
+
+  low[0].set_priority(0);
+  rest[0].set_priority(MAX_PRIORITY);
+
+  check_optional!(context, queue.enqueue(&mut low[0]).ok(), 0);
+  check_optional!(context, queue.enqueue(&mut rest[0]).ok(), 0);
+
+  for round in 0..(MAX_PRIORITY as usize + 1) * 2 {
+    let Some(task) = queue.dequeue(0) else {
+      mark_fail!(context, "Run queue is empty.");
+      return;
+    };
+
+    check_eq!(context, task.get_task_id(), 1);
+    _ = queue.enqueue(task);
+
+    // Start aging halfway through. The low-priority task only catches up once
+    // it has been aged to the high-priority task's level.
+    if round > MAX_PRIORITY as usize {
+      queue.age();
+    }
+  }
+
+  check_optional!(context, queue.dequeue(0).map(|task| task.get_task_id()), 0);
+  check_optional!(context, queue.dequeue(0).map(|task| task.get_task_id()), 1);
+  check_none!(context, queue.dequeue(0));
+}
+
+/// Test that blocked tasks are skipped and dead tasks are reaped.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first task is blocked and the second task is dead. Dequeuing must skip
+/// the blocked task and remove the dead task, leaving only the blocked task in
+/// the queue once the ready tasks have been dequeued. A blocked task queued
+/// ahead of the ready tasks must not trigger preemption.
+fn test_task_states(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let mut queue = TestRunQueue::new(1);
+  let (queued, running) = tasks.split_at_mut(TEST_CAPACITY);
+
+  queued[0].set_priority(MAX_PRIORITY);
+  queued[0].set_state(TaskState::Blocked);
+  queued[1].set_state(TaskState::Dead);
+
+  for task in queued.iter_mut() {
+    check_optional!(context, queue.enqueue(task).ok(), 0);
+  }
+
+  check_eq!(context, queue.should_preempt(0, &running[0]), false);
+
+  check_optional!(context, queue.dequeue(0).map(|task| task.get_task_id()), 2);
+  check_eq!(context, queue.get_load(0), 2);
+  check_optional!(context, queue.dequeue(0).map(|task| task.get_task_id()), 3);
+  check_none!(context, queue.dequeue(0));
+  check_eq!(context, queue.get_load(0), 1);
+}
+
+/// Count the number of times the callback runs.
+fn count_callback() {
+  unsafe { CALLBACK_COUNT += 1 };
+}
+
+/// Test that the idle work runs deferred callbacks exactly once.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_idle_work(context: &mut test::TestContext) {
+  unsafe { CALLBACK_COUNT = 0 };
+
+  let queue = deferred::get_deferred_queue();
+  check_eq!(context, queue.push(count_callback), true);
+  check_eq!(context, queue.push(count_callback), true);
+  check_eq!(context, unsafe { CALLBACK_COUNT }, 0);
+
+  super::run_idle_work();
+  check_eq!(context, unsafe { CALLBACK_COUNT }, 2);
+
+  super::run_idle_work();
+  check_eq!(context, unsafe { CALLBACK_COUNT }, 2);
+}
+
+/// Test that timer ticks trigger a reschedule when the quantum runs out.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Ticks are charged to the current task. The reschedule request remains set
+/// on later ticks until the quantum is reset.
+fn test_tick(context: &mut test::TestContext) {
+  let task = Task::get_current_task_mut();
+  task.reset_quantum();
+
+  for remaining in (1..DEFAULT_QUANTUM).rev() {
+    check_eq!(context, super::tick(), false);
+    check_eq!(context, task.get_quantum(), remaining);
+  }
+
+  check_eq!(context, task.needs_reschedule(), false);
+  check_eq!(context, super::tick(), true);
+  check_eq!(context, task.get_quantum(), 0);
+  check_eq!(context, task.needs_reschedule(), true);
+
+  check_eq!(context, super::tick(), true);
+  check_eq!(context, task.get_quantum(), 0);
+
+  task.reset_quantum();
+  check_eq!(context, task.get_quantum(), DEFAULT_QUANTUM);
+  check_eq!(context, task.needs_reschedule(), false);
+}
+
+/// Test that a timer tick requests a reschedule for a higher-priority task.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The current task has a full quantum, but a higher-priority task is queued
+/// on the current core. The tick must request a reschedule for the current
+/// task so that the higher-priority task runs.
+fn test_tick_preemption(context: &mut test::TestContext) {
+  let task = Task::get_current_task_mut();
+  task.reset_quantum();
+
+  let preempt = unsafe { ptr::addr_of_mut!(PREEMPT_TASK).as_mut().unwrap() };
+  *preempt = Task::new(TEST_TASKS, TaskContext::default());
+  preempt.set_affinity(Some(&affinity::single(0)));
+  preempt.set_priority(MAX_PRIORITY);
+
+  let enqueued = super::get_run_queue().lock().enqueue(preempt).ok();
+  check_optional!(context, enqueued, 0);
+
+  check_eq!(context, super::tick(), true);
+  check_eq!(context, task.needs_reschedule(), true);
+
+  let dequeued = super::get_run_queue()
+    .lock()
+    .dequeue(0)
+    .map(|task| task.get_task_id());
+  check_optional!(context, dequeued, TEST_TASKS);
+
+  task.reset_quantum();
+  check_eq!(context, super::tick(), false);
+  check_eq!(context, task.needs_reschedule(), false);
+
+  task.reset_quantum();
+}
+
+/// Stub scheduler entry point for a simulated secondary core.
+///
+/// # Description
+///
+/// Records that the core reached the scheduler, then panics so that the test's
+/// panic guard resumes the test rather than entering the scheduler loop.
+extern "C" fn enter_test_scheduler() -> ! {
+  unsafe { ENTERED_SCHEDULER = true };
+  panic!("Entered the test scheduler.");
+}
+
+/// Test bringing a simulated secondary core online.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The simulated core is the only core participating in the barrier, so it
+/// must pass the barrier and reach the scheduler. The core must be online and
+/// running its idle task. The core's original data area values are restored
+/// afterward.
+fn test_secondary_start(context: &mut test::TestContext) {
+  const CORE_IDX: usize = 1;
+
+  let core = percpu::get_core_data(CORE_IDX);
+  let saved = (core.get_current_task_addr(), core.is_online());
+  let barrier = Barrier::new(1);
+
+  core.set_online(false);
+  unsafe { ENTERED_SCHEDULER = false };
+
+  check_panics!(context, || super::start_secondary_core(CORE_IDX, &barrier, enter_test_scheduler));
+
+  let core = percpu::get_core_data(CORE_IDX);
+  let idle_task = task::init_idle_task(CORE_IDX);
+
+  check_eq!(context, unsafe { ENTERED_SCHEDULER }, true);
+  check_eq!(context, core.is_online(), true);
+  check_eq!(context, core.get_current_task_addr(), idle_task as *const _ as usize);
+  let matches = idle_task.state() == TaskState::Running;
+  check_eq!(context, matches, true);
+  check_eq!(context, barrier.get_arrived_count(), 0);
+
+  core.set_current_task_addr(saved.0);
+  core.set_online(saved.1);
+}
@@ -0,0 +1,234 @@
+//! Wait Queues
+//!
+//! A wait queue parks blocked tasks until an event wakes them. Waking a task
+//! marks it ready and returns it to a run queue. Tasks are woken in priority
+//! order, and tasks with the same priority are woken in the order they were
+//! parked.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::{RunQueue, TaskQueue};
+use crate::arch::interrupts;
+use crate::sync::SpinLock;
+use crate::task::{Task, TaskState};
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// A bounded queue of blocked tasks waiting for an event. Up to CAPACITY tasks
+/// may wait at the same time.
+pub struct WaitQueue<'task, const CAPACITY: usize> {
+  tasks: SpinLock<TaskQueue<'task, CAPACITY>>,
+}
+
+impl<'task, const CAPACITY: usize> WaitQueue<'task, CAPACITY> {
+  /// Construct an empty wait queue.
+  pub const fn new() -> Self {
+    assert!(CAPACITY > 0);
+
+    WaitQueue {
+      tasks: SpinLock::new(TaskQueue::new()),
+    }
+  }
+
+  /// Block a task and park it in the queue.
+  ///
+  /// # Parameters
+  ///
+  /// * `task` - The task to block.
+  ///
+  /// # Description
+  ///
+  /// The task must be ready or running. See `TaskState::can_transition_to()`.
+  ///
+  /// # Returns
+  ///
+  /// Ok if the task was parked, or the task if the queue is full. The task's
+  /// state is not changed if the queue is full.
+  pub fn block(&self, task: &'task mut Task) -> Result<(), &'task mut Task> {
+    self.with_tasks(|tasks| {
+      if tasks.is_full() {
+        return Err(task);
+      }
+
+      task.set_state(TaskState::Blocked);
+      let priority = task.get_priority();
+      tasks.push(task, priority);
+      Ok(())
+    })
+  }
+
+  /// Wake the next waiting task.
+  ///
+  /// # Parameters
+  ///
+  /// * `run_queue` - The run queue that receives the woken task.
+  ///
+  /// # Description
+  ///
+  /// The task is marked ready and queued on the run queue. If the run queue
+  /// has no space for the task, the task stays blocked and is parked again
+  /// behind the waiting tasks with the same or higher priority.
+  ///
+  /// # Returns
+  ///
+  /// True if a task was woken, false if no task is waiting or the run queue
+  /// has no space for the task.
+  pub fn wake_one_into<const CORES: usize, const RUN_CAPACITY: usize>(
+    &self,
+    run_queue: &mut RunQueue<'task, CORES, RUN_CAPACITY>,
+  ) -> bool {
+    self.with_tasks(|tasks| {
+      let Some((task, priority)) = tasks.remove(0) else {
+        return false;
+      };
+
+      task.set_state(TaskState::Ready);
+
+      match run_queue.enqueue(task) {
+        Ok(_) => true,
+        Err(task) => {
+          task.set_state(TaskState::Blocked);
+          tasks.push(task, priority);
+          false
+        }
+      }
+    })
+  }
+
+  /// Wake all waiting tasks.
+  ///
+  /// # Parameters
+  ///
+  /// * `run_queue` - The run queue that receives the woken tasks.
+  ///
+  /// # Description
+  ///
+  /// Stops at the first task the run queue has no space for. See
+  /// `wake_one_into()`.
+  ///
+  /// # Returns
+  ///
+  /// The number of tasks woken.
+  pub fn wake_all_into<const CORES: usize, const RUN_CAPACITY: usize>(
+    &self,
+    run_queue: &mut RunQueue<'task, CORES, RUN_CAPACITY>,
+  ) -> usize {
+    let mut woken = 0;
+
+    while self.wake_one_into(run_queue) {
+      woken += 1;
+    }
+
+    woken
+  }
+
+  /// Remove a parked task from the queue without queuing it to run.
+  ///
+  /// # Parameters
+  ///
+  /// * `task_id` - The identifier of the parked task.
+  ///
+  /// # Description
+  ///
+  /// The task is marked ready. Used when a task that is still parked finds
+  /// that the event it is waiting for has already happened, e.g. because the
+  /// run queue had no space for the task when it was woken.
+  ///
+  /// # Returns
+  ///
+  /// True if the task was parked in the queue, false otherwise.
+  pub fn cancel(&self, task_id: usize) -> bool {
+    self.with_tasks(|tasks| {
+      let Some(pos) = tasks.rposition(|task| task.get_task_id() == task_id) else {
+        return false;
+      };
+
+      let Some((task, _)) = tasks.remove(pos) else {
+        return false;
+      };
+
+      task.set_state(TaskState::Ready);
+      true
+    })
+  }
+
+  /// Get the number of waiting tasks.
+  pub fn get_waiting_count(&self) -> usize {
+    self.with_tasks(|tasks| tasks.len)
+  }
+
+  /// Access the waiting tasks with the lock held.
+  ///
+  /// # Parameters
+  ///
+  /// * `f` - The function to call with the waiting tasks.
+  ///
+  /// # Description
+  ///
+  /// Interrupts are masked while the lock is held so that an interrupt handler
+  /// on the same core cannot deadlock trying to wake a task.
+  ///
+  /// # Returns
+  ///
+  /// The result of `f`.
+  fn with_tasks<R>(&self, f: impl FnOnce(&mut TaskQueue<'task, CAPACITY>) -> R) -> R {
+    let irq_state = interrupts::save_and_mask_all_interrupts();
+    let result = f(&mut self.tasks.lock());
+    interrupts::restore_interrupt_state(irq_state);
+    result
+  }
+}
+
+impl<const CAPACITY: usize> WaitQueue<'static, CAPACITY> {
+  /// Block the current task and park it in the queue.
+  ///
+  /// # Description
+  ///
+  /// The current task requests a reschedule so that it is switched out. See
+  /// `Task::request_reschedule()`.
+  ///
+  /// # Returns
+  ///
+  /// True if the task was parked, false if the queue is full.
+  pub fn block_current(&self) -> bool {
+    match self.block(Task::get_current_task_mut()) {
+      Ok(_) => {
+        Task::get_current_task_mut().request_reschedule();
+        true
+      }
+      Err(_) => false,
+    }
+  }
+
+  /// Wake the next waiting task into the system run queue.
+  ///
+  /// # Description
+  ///
+  /// See `wake_one_into()`.
+  ///
+  /// # Returns
+  ///
+  /// True if a task was woken, false otherwise.
+  pub fn wake_one(&self) -> bool {
+    super::with_run_queue(|run_queue| self.wake_one_into(run_queue))
+  }
+
+  /// Wake all waiting tasks into the system run queue.
+  ///
+  /// # Description
+  ///
+  /// See `wake_all_into()`.
+  ///
+  /// # Returns
+  ///
+  /// The number of tasks woken.
+  pub fn wake_all(&self) -> usize {
+    super::with_run_queue(|run_queue| self.wake_all_into(run_queue))
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
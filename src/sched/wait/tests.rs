@@ -0,0 +1,150 @@
+//! Wait Queue Tests
+
+use super::WaitQueue;
+use crate::debug_print;
+use crate::sched::RunQueue;
+use crate::task::{MAX_PRIORITY, Task, TaskContext, TaskState};
+use crate::{check_eq, check_none, check_optional, execute_test, mark_fail, test};
+use core::array;
+
+/// Test wait queue capacity.
+const TEST_CAPACITY: usize = 4;
+
+/// Test run queue capacity.
+const TEST_RUN_CAPACITY: usize = 2;
+
+/// Number of test tasks.
+const TEST_TASKS: usize = 4;
+
+/// Test wait queue type.
+type TestWaitQueue<'task> = WaitQueue<'task, TEST_CAPACITY>;
+
+/// Test single-core run queue type.
+type TestRunQueue<'task> = RunQueue<'task, 1, TEST_RUN_CAPACITY>;
+
+/// Run wait queue tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_wake_one);
+  execute_test!(context, test_wake_all);
+  execute_test!(context, test_wake_full_run_queue);
+}
+
+/// Construct the test tasks. Task N has task identifier N.
+///
+/// # Returns
+///
+/// An array of unqueued tasks.
+fn make_tasks() -> [Task; TEST_TASKS] {
+  array::from_fn(|task_id| Task::new(task_id, TaskContext::default()))
+}
+
+/// Test that waking one task only returns that task to the run queue.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Two tasks block on the queue. Waking one task must return the first task
+/// to block to the run queue as a ready task, while the second task stays
+/// parked and cannot be dequeued.
+fn test_wake_one(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let wait_queue = TestWaitQueue::new();
+  let mut run_queue = TestRunQueue::new(1);
+  let (first, rest) = tasks.split_at_mut(1);
+
+  let blocked = wait_queue.block(&mut first[0]).is_ok();
+  check_eq!(context, blocked, true);
+  let blocked = wait_queue.block(&mut rest[0]).is_ok();
+  check_eq!(context, blocked, true);
+  check_eq!(context, wait_queue.get_waiting_count(), 2);
+
+  let woken = wait_queue.wake_one_into(&mut run_queue);
+  check_eq!(context, woken, true);
+  check_eq!(context, wait_queue.get_waiting_count(), 1);
+  check_eq!(context, run_queue.get_load(0), 1);
+
+  let Some(task) = run_queue.dequeue(0) else {
+    mark_fail!(context, "No task was woken.");
+    return;
+  };
+
+  check_eq!(context, task.get_task_id(), 0);
+  let matches = task.state() == TaskState::Ready;
+  check_eq!(context, matches, true);
+  check_none!(context, run_queue.dequeue(0));
+}
+
+/// Test that waking all tasks wakes them in priority order.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The second task to block has the highest priority, so it must be woken
+/// first. Waking with no waiting tasks does nothing.
+fn test_wake_all(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let wait_queue = TestWaitQueue::new();
+  let mut run_queue = TestRunQueue::new(1);
+  let (first, rest) = tasks.split_at_mut(1);
+
+  rest[0].set_priority(MAX_PRIORITY);
+  let blocked = wait_queue.block(&mut first[0]).is_ok();
+  check_eq!(context, blocked, true);
+  let blocked = wait_queue.block(&mut rest[0]).is_ok();
+  check_eq!(context, blocked, true);
+
+  let woken = wait_queue.wake_all_into(&mut run_queue);
+  check_eq!(context, woken, 2);
+  check_eq!(context, wait_queue.get_waiting_count(), 0);
+  let woken = wait_queue.wake_one_into(&mut run_queue);
+  check_eq!(context, woken, false);
+
+  check_optional!(context, run_queue.dequeue(0).map(|task| task.get_task_id()), 1);
+  check_optional!(context, run_queue.dequeue(0).map(|task| task.get_task_id()), 0);
+}
+
+/// Test waking tasks into a run queue without space.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The run queue only has space for two tasks, so the last task stays blocked
+/// in the wait queue.
+fn test_wake_full_run_queue(context: &mut test::TestContext) {
+  let mut tasks = make_tasks();
+  let wait_queue = TestWaitQueue::new();
+  let mut run_queue = TestRunQueue::new(1);
+
+  for task in tasks.iter_mut().take(TEST_RUN_CAPACITY + 1) {
+    let blocked = wait_queue.block(task).is_ok();
+    check_eq!(context, blocked, true);
+  }
+
+  let woken = wait_queue.wake_all_into(&mut run_queue);
+  check_eq!(context, woken, TEST_RUN_CAPACITY);
+  check_eq!(context, wait_queue.get_waiting_count(), 1);
+  check_eq!(context, run_queue.get_load(0), TEST_RUN_CAPACITY);
+
+  // The remaining task is still blocked, so it cannot run once there is space
+  // in the run queue.
+  check_optional!(context, run_queue.dequeue(0).map(|task| task.get_task_id()), 0);
+  check_optional!(context, run_queue.dequeue(0).map(|task| task.get_task_id()), 1);
+  check_none!(context, run_queue.dequeue(0));
+
+  let woken = wait_queue.wake_one_into(&mut run_queue);
+  check_eq!(context, woken, true);
+  check_optional!(context, run_queue.dequeue(0).map(|task| task.get_task_id()), TEST_RUN_CAPACITY);
+}
@@ -0,0 +1,177 @@
+//! Typed Addresses
+//!
+//! Physical and virtual addresses are distinct types so that one cannot be
+//! passed where the other is expected. Conversions between the two are always
+//! explicit and require the kernel segment base address.
+//!
+//! Arithmetic with a `usize` offset preserves the address type, and the
+//! difference between two addresses of the same type is a `usize`. Mixing the
+//! two types does not compile:
+//!
+//! ```compile_fail
+//! use propeller::support::addr::{PhysAddr, VirtAddr};
+//!
+//! let phys = PhysAddr::new(0x1000);
+//! let virt = VirtAddr::new(0x8000_1000);
+//! let _ = virt - phys;
+//! ```
+//!
+//! ```compile_fail
+//! use propeller::support::addr::{PhysAddr, VirtAddr};
+//!
+//! fn walk(_: VirtAddr) {}
+//!
+//! walk(PhysAddr::new(0x1000));
+//! ```
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::fmt;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// A physical address.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct PhysAddr(usize);
+
+/// A virtual address.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct VirtAddr(usize);
+
+impl PhysAddr {
+  /// Construct a physical address.
+  ///
+  /// # Parameters
+  ///
+  /// * `addr` - The raw physical address.
+  pub const fn new(addr: usize) -> Self {
+    PhysAddr(addr)
+  }
+
+  /// Get the raw physical address.
+  pub const fn as_usize(self) -> usize {
+    self.0
+  }
+
+  /// Convert a physical address in linear memory to a virtual address.
+  ///
+  /// # Parameters
+  ///
+  /// * `virtual_base` - The kernel segment base address.
+  ///
+  /// # Assumptions
+  ///
+  /// The physical address is in linear memory.
+  ///
+  /// # Returns
+  ///
+  /// The virtual address `PA + virtual base`.
+  pub const fn to_virt(self, virtual_base: usize) -> VirtAddr {
+    VirtAddr(self.0 + virtual_base)
+  }
+}
+
+impl VirtAddr {
+  /// Construct a virtual address.
+  ///
+  /// # Parameters
+  ///
+  /// * `addr` - The raw virtual address.
+  pub const fn new(addr: usize) -> Self {
+    VirtAddr(addr)
+  }
+
+  /// Get the raw virtual address.
+  pub const fn as_usize(self) -> usize {
+    self.0
+  }
+
+  /// Convert a virtual address in the linear map to a physical address.
+  ///
+  /// # Parameters
+  ///
+  /// * `virtual_base` - The kernel segment base address.
+  ///
+  /// # Assumptions
+  ///
+  /// The virtual address is in the kernel's linear map, i.e. it is greater
+  /// than or equal to the kernel segment base address.
+  ///
+  /// # Returns
+  ///
+  /// The physical address `VA - virtual base`.
+  pub const fn to_phys(self, virtual_base: usize) -> PhysAddr {
+    PhysAddr(self.0 - virtual_base)
+  }
+}
+
+/// Implements the offset arithmetic and formatting shared by both address
+/// types.
+macro_rules! impl_addr_ops {
+  ($addr:ident) => {
+    impl $addr {
+      /// Offset the address, wrapping at the end of the address space.
+      ///
+      /// # Parameters
+      ///
+      /// * `rhs` - The offset.
+      pub const fn wrapping_add(self, rhs: usize) -> Self {
+        $addr(self.0.wrapping_add(rhs))
+      }
+    }
+
+    impl Add<usize> for $addr {
+      type Output = Self;
+
+      fn add(self, rhs: usize) -> Self {
+        $addr(self.0 + rhs)
+      }
+    }
+
+    impl AddAssign<usize> for $addr {
+      fn add_assign(&mut self, rhs: usize) {
+        self.0 += rhs;
+      }
+    }
+
+    impl Sub<usize> for $addr {
+      type Output = Self;
+
+      fn sub(self, rhs: usize) -> Self {
+        $addr(self.0 - rhs)
+      }
+    }
+
+    impl SubAssign<usize> for $addr {
+      fn sub_assign(&mut self, rhs: usize) {
+        self.0 -= rhs;
+      }
+    }
+
+    impl Sub<$addr> for $addr {
+      type Output = usize;
+
+      fn sub(self, rhs: Self) -> usize {
+        self.0 - rhs.0
+      }
+    }
+
+    impl fmt::Display for $addr {
+      fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+      }
+    }
+  };
+}
+
+impl_addr_ops!(PhysAddr);
+impl_addr_ops!(VirtAddr);
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
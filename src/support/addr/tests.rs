@@ -0,0 +1,91 @@
+//! Typed Address Tests
+
+use super::{PhysAddr, VirtAddr};
+use crate::debug_print;
+use crate::{check_eq, execute_test, test};
+
+/// Test kernel segment base addresses for the 3/1 and 2/2 splits.
+const TEST_VIRTUAL_BASES: [usize; 2] = [0xc000_0000, 0x8000_0000];
+
+/// Test physical address.
+const TEST_PHYS: usize = 0x1234_5000;
+
+/// Run the typed address tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_round_trip);
+  execute_test!(context, test_offset_arithmetic);
+  execute_test!(context, test_ordering);
+}
+
+/// Test converting between physical and virtual addresses.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_round_trip(context: &mut test::TestContext) {
+  for virtual_base in TEST_VIRTUAL_BASES {
+    let phys = PhysAddr::new(TEST_PHYS);
+    let virt = phys.to_virt(virtual_base);
+    check_eq!(context, virt.as_usize(), TEST_PHYS + virtual_base);
+    check_eq!(context, virt.to_phys(virtual_base), phys);
+
+    let virt = VirtAddr::new(virtual_base + TEST_PHYS);
+    let phys = virt.to_phys(virtual_base);
+    check_eq!(context, phys.as_usize(), TEST_PHYS);
+    check_eq!(context, phys.to_virt(virtual_base), virt);
+  }
+}
+
+/// Test that offset arithmetic preserves the address type.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_offset_arithmetic(context: &mut test::TestContext) {
+  let base = PhysAddr::new(TEST_PHYS);
+  let mut phys = base + 0x1000;
+  check_eq!(context, phys, PhysAddr::new(TEST_PHYS + 0x1000));
+
+  phys += 0x1000;
+  check_eq!(context, phys - base, 0x2000);
+
+  phys -= 0x2000;
+  check_eq!(context, phys, base);
+  check_eq!(context, base - 0x1000, PhysAddr::new(TEST_PHYS - 0x1000));
+
+  let base = VirtAddr::new(TEST_VIRTUAL_BASES[0]);
+  let mut virt = base + TEST_PHYS;
+  check_eq!(context, virt - base, TEST_PHYS);
+
+  virt -= TEST_PHYS;
+  check_eq!(context, virt, base);
+
+  // Walking off the end of the address space wraps to 0.
+  let virt = VirtAddr::new(usize::MAX - 0xfff);
+  check_eq!(context, virt.wrapping_add(0x1000), VirtAddr::new(0));
+
+  // Offsetting before or after the conversion yields the same address.
+  let phys = PhysAddr::new(TEST_PHYS);
+  check_eq!(
+    context,
+    (phys + 0x800).to_virt(TEST_VIRTUAL_BASES[0]),
+    phys.to_virt(TEST_VIRTUAL_BASES[0]) + 0x800
+  );
+}
+
+/// Test address ordering.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_ordering(context: &mut test::TestContext) {
+  let lo = VirtAddr::new(TEST_PHYS);
+  let hi = lo + 1;
+  let ordered = lo < hi;
+  check_eq!(context, ordered, true);
+  check_eq!(context, lo.max(hi), hi);
+}
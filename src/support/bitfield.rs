@@ -0,0 +1,95 @@
+//! Bit Fields
+//!
+//! Hardware structures such as translation table descriptors pack several
+//! values into one word. A `BitField` names the position and width of one of
+//! those values so that it can be read and written without open-coding shifts
+//! and masks.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::support::bits;
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// A field of contiguous bits in a machine word.
+#[derive(Copy, Clone, PartialEq)]
+pub struct BitField {
+  shift: usize,
+  width: usize,
+}
+
+impl BitField {
+  /// Construct a bit field.
+  ///
+  /// # Parameters
+  ///
+  /// * `shift` - The position of the field's least-significant bit.
+  /// * `width` - The number of bits in the field.
+  ///
+  /// # Description
+  ///
+  /// Fields are intended to be constants, so an invalid field fails to
+  /// compile.
+  ///
+  /// # Assumptions
+  ///
+  /// The width is not zero and the field fits in a machine word.
+  pub const fn new(shift: usize, width: usize) -> Self {
+    assert!(width > 0 && width <= bits::WORD_BITS);
+    assert!(shift <= bits::WORD_BITS - width);
+
+    BitField { shift, width }
+  }
+
+  /// Get the position of the field's least-significant bit.
+  pub const fn get_shift(&self) -> usize {
+    self.shift
+  }
+
+  /// Get the number of bits in the field.
+  pub const fn get_width(&self) -> usize {
+    self.width
+  }
+
+  /// Get the mask for the field's bits in place.
+  pub const fn mask(&self) -> usize {
+    (usize::MAX >> (bits::WORD_BITS - self.width)) << self.shift
+  }
+
+  /// Read the field from a word.
+  ///
+  /// # Parameters
+  ///
+  /// * `word` - The word containing the field.
+  ///
+  /// # Returns
+  ///
+  /// The value of the field.
+  pub const fn get(&self, word: usize) -> usize {
+    (word & self.mask()) >> self.shift
+  }
+
+  /// Write the field in a word.
+  ///
+  /// # Parameters
+  ///
+  /// * `word` - The word containing the field.
+  /// * `val` - The new value of the field.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: Bits of the value that do not fit in the field are discarded.
+  ///
+  /// # Returns
+  ///
+  /// The word with the field replaced. Bits outside of the field are unchanged.
+  pub const fn set(&self, word: usize, val: usize) -> usize {
+    (word & !self.mask()) | ((val << self.shift) & self.mask())
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
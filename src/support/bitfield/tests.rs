@@ -0,0 +1,99 @@
+//! Bit Field Tests
+
+use super::BitField;
+use crate::debug_print;
+use crate::support::bits;
+use crate::{check_eq, execute_test, test};
+
+/// Hand-computed test cases: (shift, width, word, value, mask, word with the
+/// field set to the value).
+const TEST_CASES: [(usize, usize, usize, usize, usize, usize); 6] = [
+  (0, 1, 0x0, 0x1, 0x1, 0x1),
+  (0, 2, 0xffff_fffc, 0x3, 0x3, 0xffff_ffff),
+  (2, 3, 0x8000_1000, 0x5, 0x1c, 0x8000_1014),
+  (2, 3, 0x8000_101f, 0x1, 0x1c, 0x8000_1007),
+  (10, 1, 0x0040_0003, 0x1, 0x400, 0x0040_0403),
+  (12, 20, 0xabcd_e7ff, 0x12345, 0xffff_f000, 0x1234_57ff),
+];
+
+/// Run bit field tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_hand_computed);
+  execute_test!(context, test_round_trip);
+  execute_test!(context, test_truncation);
+  execute_test!(context, test_full_word);
+}
+
+/// Test fields against hand-computed masks and words.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_hand_computed(context: &mut test::TestContext) {
+  for (shift, width, word, val, mask, expected) in TEST_CASES {
+    let field = BitField::new(shift, width);
+
+    check_eq!(context, field.mask(), mask);
+    check_eq!(context, field.set(word, val), expected);
+    check_eq!(context, field.get(expected), val);
+    check_eq!(context, expected & !mask, word & !mask);
+  }
+}
+
+/// Test that setting a field and reading it back returns the same value.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Every value of a 3-bit field must round-trip without changing the bits
+/// outside of the field, whether those bits are all clear or all set.
+fn test_round_trip(context: &mut test::TestContext) {
+  let field = BitField::new(4, 3);
+
+  for word in [0, usize::MAX] {
+    for val in 0..(1 << field.get_width()) {
+      let set = field.set(word, val);
+
+      check_eq!(context, field.get(set), val);
+      check_eq!(context, set & !field.mask(), word & !field.mask());
+    }
+  }
+}
+
+/// Test that bits of a value that do not fit in a field are discarded.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_truncation(context: &mut test::TestContext) {
+  let field = BitField::new(8, 4);
+
+  check_eq!(context, field.set(0, 0x1f), 0xf00);
+  check_eq!(context, field.set(0x1000, 0x10), 0x1000);
+  check_eq!(context, field.get(field.set(0, usize::MAX)), 0xf);
+}
+
+/// Test fields at the top of the word and spanning the whole word.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_full_word(context: &mut test::TestContext) {
+  let top = BitField::new(bits::WORD_BITS - 1, 1);
+  let top_bit = 1usize << (bits::WORD_BITS - 1);
+  check_eq!(context, top.mask(), top_bit);
+  check_eq!(context, top.set(0, 1), top_bit);
+  check_eq!(context, top.get(usize::MAX), 1);
+
+  let word = BitField::new(0, bits::WORD_BITS);
+  check_eq!(context, word.mask(), usize::MAX);
+  check_eq!(context, word.set(0x1234, 0x5678), 0x5678);
+  check_eq!(context, word.get(0x1234), 0x1234);
+}
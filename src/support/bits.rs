@@ -8,10 +8,8 @@ mod tests;
 
 pub use crate::arch::bits::*;
 
-use crate::debug_print;
 #[cfg(feature = "module_tests")]
 use crate::test;
-use core::cmp;
 
 /// The number of bits in a machine word.
 pub const WORD_BITS: usize = usize::BITS as usize;
@@ -165,12 +163,12 @@ impl<const MAP_WORDS: usize> Bitmap<MAP_WORDS> {
   ///
   /// The number of bits the map can store will be capped to the size of the
   /// buffer.
-  pub fn new(bits: usize) -> Self {
+  pub const fn new(bits: usize) -> Self {
     let max_bits = MAP_WORDS << WORD_BIT_SHIFT;
 
     Self {
       bitmap: Self::BITMAP_INITIALIZER,
-      bits: cmp::min(bits, max_bits),
+      bits: if bits < max_bits { bits } else { max_bits },
     }
   }
 
@@ -370,9 +368,7 @@ impl<'a, const MAP_WORDS: usize> Iterator for BitmapIter<'a, MAP_WORDS> {
 }
 
 #[cfg(feature = "module_tests")]
-pub fn run_tests() {
-  let mut context = test::TestContext::new();
-  debug_print!(" bits:\n");
-  tests::run_bitmap_tests(&mut context);
-  debug_print!("  {} pass, {} fail\n", context.pass_count, context.fail_count);
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_log2_tests(context);
+  tests::run_bitmap_tests(context);
 }
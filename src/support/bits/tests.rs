@@ -14,6 +14,17 @@ const TEST_MAP_SIZE: usize = TEST_MAX_BITS >> WORD_BIT_SHIFT;
 /// The maximum number of bits to use.
 const TEST_BITS: usize = 32;
 
+/// Run the base-2 logarithm tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_log2_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_floor_log2);
+  execute_test!(context, test_ceil_log2);
+  execute_test!(context, test_ceil_log2_edges);
+}
+
 /// Run the Bitmap tests.
 ///
 /// # Parameters
@@ -29,6 +40,110 @@ pub fn run_bitmap_tests(context: &mut test::TestContext) {
   execute_test!(context, test_bit_iterator);
 }
 
+/// Reference floor base-2 log using the count leading zeros instruction.
+///
+/// # Parameters
+///
+/// * `n` - The number.
+fn reference_floor_log2(n: usize) -> usize {
+  if n == 0 {
+    return 0;
+  }
+
+  WORD_BITS - 1 - n.leading_zeros() as usize
+}
+
+/// Reference ceiling base-2 log using the count leading zeros instruction.
+///
+/// # Parameters
+///
+/// * `n` - The number.
+fn reference_ceil_log2(n: usize) -> usize {
+  if n <= 1 {
+    return 0;
+  }
+
+  WORD_BITS - (n - 1).leading_zeros() as usize
+}
+
+/// Test the floor base-2 log against the reference around every power of 2.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_floor_log2(context: &mut test::TestContext) {
+  for shift in 0..WORD_BITS {
+    let p = 1 << shift;
+
+    for n in [p - 1, p, p + 1, p | (p >> 1)] {
+      check_eq!(context, bits::floor_log2(n), reference_floor_log2(n));
+    }
+  }
+
+  check_eq!(context, bits::floor_log2(usize::MAX), WORD_BITS - 1);
+}
+
+/// Test the ceiling base-2 log against the reference around every power of 2.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Exact powers of 2 must not be rounded up, and every other number must be
+/// rounded up to the next power. The buddy allocator relies on this to select
+/// a block level.
+fn test_ceil_log2(context: &mut test::TestContext) {
+  for shift in 0..WORD_BITS {
+    let p = 1 << shift;
+    check_eq!(context, bits::ceil_log2(p), shift);
+
+    for n in [p - 1, p, p + 1, p | (p >> 1)] {
+      check_eq!(context, bits::ceil_log2(n), reference_ceil_log2(n));
+    }
+  }
+}
+
+/// Test the ceiling base-2 log edge cases.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The expected values are listed for both 32-bit and 64-bit words.
+fn test_ceil_log2_edges(context: &mut test::TestContext) {
+  #[cfg(target_pointer_width = "32")]
+  const EDGES: [(usize, usize); 8] = [
+    (0, 0),
+    (1, 0),
+    (2, 1),
+    (3, 2),
+    (0x7fff_ffff, 31),
+    (0x8000_0000, 31),
+    (0x8000_0001, 32),
+    (usize::MAX, 32),
+  ];
+
+  #[cfg(target_pointer_width = "64")]
+  const EDGES: [(usize, usize); 8] = [
+    (0, 0),
+    (1, 0),
+    (2, 1),
+    (3, 2),
+    (0x7fff_ffff_ffff_ffff, 63),
+    (0x8000_0000_0000_0000, 63),
+    (0x8000_0000_0000_0001, 64),
+    (usize::MAX, 64),
+  ];
+
+  for (n, expected) in EDGES {
+    check_eq!(context, bits::ceil_log2(n), expected);
+  }
+}
+
 /// Test construction of a Bitmap.
 ///
 /// # Parameters
@@ -1,7 +1,17 @@
 //! Device Tree Utilities
 //! https://devicetree-specification.readthedocs.io/en/stable/index.html
+//!
+//! All DTB integers are big-endian. The reader converts them from explicit byte
+//! sequences, so it does not depend on the host's byte order or on the blob's
+//! alignment.
+
+#[cfg(feature = "module_tests")]
+mod tests;
 
 use super::bits;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::cell::Cell;
 use core::{cmp, slice, str};
 
 const FDT_BEGIN_NODE: u32 = 0x1;
@@ -10,10 +20,20 @@ const FDT_PROP: u32 = 0x3;
 const FDT_NOOP: u32 = 0x4;
 const FDT_END: u32 = 0x9;
 const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_MIN_VERSION: u32 = 16;
+const FDT_VERSION: u32 = 17;
 const FDT_MAX_SIZE: usize = 64 * 1024 * 1024;
 const FDT_WORD_BITS: usize = u32::BITS as usize;
 const FDT_WORD_BYTES: usize = (u32::BITS / 8) as usize;
 const FDT_HEADER_SIZE: usize = FDT_WORD_BYTES * 8;
+const FDT_V16_HEADER_SIZE: usize = FDT_WORD_BYTES * 9;
+const FDT_V17_HEADER_SIZE: usize = FDT_WORD_BYTES * 10;
+const FDT_MEM_RSV_ENTRY_SIZE: usize = 16;
+const FDT_MEM_RSV_ALIGN: usize = 8;
+
+/// Number of property names cached by a reader. Scanners typically look up a
+/// handful of distinct property names over and over.
+const STRING_CACHE_SIZE: usize = 8;
 
 /// Error value for DTB operations.
 pub enum DtbError {
@@ -22,6 +42,7 @@ pub enum DtbError {
   UnknownProperty,
   UnknownValue,
   UnsupportedValue,
+  OutOfBounds,
 }
 
 /// A lightweight pointer to a location in a DTB that also provides methods to
@@ -30,6 +51,7 @@ pub enum DtbError {
 #[derive(Copy, Clone)]
 pub struct DtbCursor {
   loc: usize,
+  end: usize,
 }
 
 impl DtbCursor {
@@ -38,12 +60,42 @@ impl DtbCursor {
   /// # Parameters
   ///
   /// * `loc` - The current location of the cursor.
+  /// * `end` - The end of the block the cursor may move within.
   ///
   /// # Returns
   ///
   /// A new cursor.
-  fn new(loc: usize) -> Self {
-    DtbCursor { loc }
+  fn new(loc: usize, end: usize) -> Self {
+    DtbCursor { loc, end }
+  }
+
+  /// Align the cursor's location.
+  ///
+  /// # Parameters
+  ///
+  /// * `boundary` - The boundary on which to align the location.
+  ///
+  /// # Description
+  ///
+  /// If aligning the location would place the cursor past the end of its block,
+  /// the cursor is positioned at the end of the block and is no longer valid.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes the boundary is a power of 2.
+  pub fn align_to(&mut self, boundary: usize) {
+    let loc = cmp::min(self.loc, self.end);
+    self.loc = cmp::min(bits::align_up(loc, boundary), self.end);
+  }
+
+  /// Get the number of bytes remaining in the cursor's block.
+  ///
+  /// # Returns
+  ///
+  /// The number of bytes between the cursor's location and the end of the
+  /// block.
+  pub fn remaining(&self) -> usize {
+    self.end.saturating_sub(self.loc)
   }
 }
 
@@ -55,8 +107,9 @@ pub struct DtbPropertyHeader<'blob> {
 }
 
 /// Interface for a DTB scanner. `DtbReader::scan()` walks the DTB and calls
-/// `DtbScanner::scan_node()` for each node it encounters.
-pub trait DtbScanner {
+/// `DtbScanner::scan_node()` for each node it encounters. The `'blob` lifetime
+/// allows a scanner to keep slices that reference the DTB.
+pub trait DtbScanner<'blob> {
   /// Scan the current node.
   ///
   /// # Parameters
@@ -71,23 +124,115 @@ pub trait DtbScanner {
   /// scanning with the provided error.
   fn scan_node(
     &mut self,
-    reader: &DtbReader,
+    reader: &DtbReader<'blob>,
     name: &[u8],
     cursor: &DtbCursor,
   ) -> Result<bool, DtbError>;
 }
 
+/// A cached string block entry: the offset into the string block, the string,
+/// and the time the entry was last used.
+type StringCacheEntry<'blob> = (usize, &'blob [u8], usize);
+
+/// Small least-recently-used cache of string block lookups.
+///
+/// # Description
+///
+/// The cache uses interior mutability so that lookups can be cached through
+/// the shared reader references given to scanners.
+struct StringCache<'blob> {
+  entries: [Cell<Option<StringCacheEntry<'blob>>>; STRING_CACHE_SIZE],
+  clock: Cell<usize>,
+}
+
+impl<'blob> StringCache<'blob> {
+  /// Construct an empty cache.
+  const fn new() -> Self {
+    StringCache {
+      entries: [const { Cell::new(None) }; STRING_CACHE_SIZE],
+      clock: Cell::new(0),
+    }
+  }
+
+  /// Advance the cache clock.
+  ///
+  /// # Returns
+  ///
+  /// The new time.
+  fn tick(&self) -> usize {
+    let time = self.clock.get().wrapping_add(1);
+    self.clock.set(time);
+    time
+  }
+
+  /// Find a cached string.
+  ///
+  /// # Parameters
+  ///
+  /// * `str_offset` - The byte offset into the string block.
+  ///
+  /// # Returns
+  ///
+  /// The cached string, or None if the offset is not cached.
+  fn find(&self, str_offset: usize) -> Option<&'blob [u8]> {
+    for entry in &self.entries {
+      if let Some((offset, name, _)) = entry.get()
+        && offset == str_offset
+      {
+        entry.set(Some((offset, name, self.tick())));
+        return Some(name);
+      }
+    }
+
+    None
+  }
+
+  /// Add a string to the cache, evicting the least-recently-used entry if the
+  /// cache is full.
+  ///
+  /// # Parameters
+  ///
+  /// * `str_offset` - The byte offset into the string block.
+  /// * `name` - The string.
+  fn insert(&self, str_offset: usize, name: &'blob [u8]) {
+    let mut victim = &self.entries[0];
+    let mut oldest = usize::MAX;
+
+    for entry in &self.entries {
+      match entry.get() {
+        None => {
+          victim = entry;
+          break;
+        }
+
+        Some((_, _, time)) if time < oldest => {
+          victim = entry;
+          oldest = time;
+        }
+
+        _ => {}
+      }
+    }
+
+    victim.set(Some((str_offset, name, self.tick())));
+  }
+}
+
 /// DTB reader.
 pub struct DtbReader<'blob> {
   dtb: &'blob [u8],
   dt_struct_offset: usize,
   dt_strings_offset: usize,
-  _mem_rsv_map_offset: usize,
-  _version: u32,
-  _last_comp_version: u32,
+  mem_rsv_map_offset: usize,
+  version: u32,
+  last_comp_version: u32,
   _boot_cpuid_phys: u32,
-  _dt_strings_size: usize,
-  _dt_struct_size: usize,
+  dt_strings_size: usize,
+  dt_struct_size: usize,
+  dt_struct_end: usize,
+  string_cache: StringCache<'blob>,
+  #[cfg(feature = "module_tests")]
+  string_scans: Cell<usize>,
 }
 
 impl<'blob> DtbReader<'blob> {
@@ -105,14 +250,15 @@ impl<'blob> DtbReader<'blob> {
       return Err(DtbError::NotADtb);
     }
 
-    let dtb = blob as *const u32;
-    let magic = unsafe { u32::from_be(*dtb) };
+    // Only the magic and total size are read before the size is known.
+    let header = unsafe { slice::from_raw_parts(blob as *const u8, FDT_WORD_BYTES * 2) };
+    let magic = u32::from_be_bytes(header[..FDT_WORD_BYTES].try_into().unwrap());
 
     if magic != FDT_MAGIC {
       return Err(DtbError::NotADtb);
     }
 
-    let total_size = unsafe { u32::from_be(*dtb.add(1)) } as usize;
+    let total_size = u32::from_be_bytes(header[FDT_WORD_BYTES..].try_into().unwrap()) as usize;
 
     if total_size < FDT_HEADER_SIZE || total_size > FDT_MAX_SIZE {
       return Err(DtbError::InvalidDtb);
@@ -127,37 +273,120 @@ impl<'blob> DtbReader<'blob> {
   ///
   /// * `blob` - The pointer to the DTB blob.
   ///
+  /// # Description
+  ///
+  /// Validates the header before returning the reader. See `check_layout()`.
+  ///
   /// # Returns
   ///
-  /// A new DTB reader if the blob is a valid DTB, otherwise None.
+  /// A new DTB reader if the blob is a valid DTB, otherwise a DtbError value.
   pub fn new(blob: usize) -> Result<Self, DtbError> {
     let total_size = DtbReader::check_dtb(blob)?;
     let base_ptr = blob as *const u8;
-    let mut cursor = DtbCursor::new(FDT_WORD_BYTES * 2);
+    let mut cursor = DtbCursor::new(FDT_WORD_BYTES * 2, total_size);
     let mut dtb = DtbReader {
       dtb: unsafe { slice::from_raw_parts(base_ptr, total_size) },
       dt_struct_offset: 0,
       dt_strings_offset: 0,
-      _mem_rsv_map_offset: 0,
-      _version: 0,
-      _last_comp_version: 0,
+      mem_rsv_map_offset: 0,
+      version: 0,
+      last_comp_version: 0,
       _boot_cpuid_phys: 0,
-      _dt_strings_size: 0,
-      _dt_struct_size: 0,
+      dt_strings_size: 0,
+      dt_struct_size: 0,
+      dt_struct_end: total_size,
+      string_cache: StringCache::new(),
+      #[cfg(feature = "module_tests")]
+      string_scans: Cell::new(0),
     };
 
     dtb.dt_struct_offset = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)? as usize;
     dtb.dt_strings_offset = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)? as usize;
-    dtb._mem_rsv_map_offset = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)? as usize;
-    dtb._version = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)?;
-    dtb._last_comp_version = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)?;
+    dtb.mem_rsv_map_offset = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)? as usize;
+    dtb.version = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)?;
+    dtb.last_comp_version = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)?;
     dtb._boot_cpuid_phys = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)?;
-    dtb._dt_strings_size = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)? as usize;
-    dtb._dt_struct_size = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)? as usize;
+    dtb.dt_strings_size = dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)? as usize;
+
+    // Version 16 headers do not have the structure block size. Assume the
+    // structure block extends to the end of the DTB.
+    dtb.dt_struct_size = if dtb.version > FDT_MIN_VERSION {
+      dtb.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)? as usize
+    } else {
+      total_size.saturating_sub(dtb.dt_struct_offset)
+    };
+
+    dtb.check_layout()?;
+
+    // Bound all reads through cursors by the end of the structure block.
+    dtb.dt_struct_end = dtb.dt_struct_offset + dtb.dt_struct_size;
 
     Ok(dtb)
   }
 
+  /// Validate the header fields.
+  ///
+  /// # Description
+  ///
+  /// Verifies that:
+  ///
+  /// * The DTB version is at least 16 and the DTB is backwards compatible with
+  ///   version 17.
+  /// * The memory reservation block, the structure block, and the strings block
+  ///   are aligned, start after the header, and lie within the total size.
+  ///
+  /// # Returns
+  ///
+  /// Ok if the header is valid, otherwise `DtbError::InvalidDtb`.
+  fn check_layout(&self) -> Result<(), DtbError> {
+    if self.version < FDT_MIN_VERSION || self.last_comp_version > FDT_VERSION {
+      return Err(DtbError::InvalidDtb);
+    }
+
+    if !bits::is_aligned(self.mem_rsv_map_offset, FDT_MEM_RSV_ALIGN)
+      || !bits::is_aligned(self.dt_struct_offset, FDT_WORD_BYTES)
+    {
+      return Err(DtbError::InvalidDtb);
+    }
+
+    let header_size = if self.version > FDT_MIN_VERSION {
+      FDT_V17_HEADER_SIZE
+    } else {
+      FDT_V16_HEADER_SIZE
+    };
+
+    let blocks = [
+      (self.mem_rsv_map_offset, FDT_MEM_RSV_ENTRY_SIZE),
+      (self.dt_struct_offset, self.dt_struct_size),
+      (self.dt_strings_offset, self.dt_strings_size),
+    ];
+
+    for (offset, size) in blocks {
+      if offset < header_size || !self.is_block_in_bounds(offset, size) {
+        return Err(DtbError::InvalidDtb);
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Check that a block lies within the DTB.
+  ///
+  /// # Parameters
+  ///
+  /// * `offset` - The offset of the block.
+  /// * `size` - The size of the block.
+  ///
+  /// # Returns
+  ///
+  /// True if the block is within bounds, false otherwise.
+  fn is_block_in_bounds(&self, offset: usize, size: usize) -> bool {
+    match offset.checked_add(size) {
+      Some(end) => end <= self.dtb.len(),
+      None => false,
+    }
+  }
+
   /// Get a new cursor positioned at the start of the root node.
   ///
   /// # Returns
@@ -165,7 +394,7 @@ impl<'blob> DtbReader<'blob> {
   /// A new cursor positioned after the null-terminator of the node's name, or
   /// None if the DTB is invalid.
   pub fn get_root_node(&self) -> Option<DtbCursor> {
-    let mut cursor = DtbCursor::new(self.dt_struct_offset);
+    let mut cursor = DtbCursor::new(self.dt_struct_offset, self.dt_struct_end);
     let marker = self.get_u32(&mut cursor)?;
 
     if marker != FDT_BEGIN_NODE {
@@ -239,6 +468,109 @@ impl<'blob> DtbReader<'blob> {
     }
   }
 
+  /// Get the first child of the node pointed to by the cursor. If the node has
+  /// a child, the cursor returned will be positioned just after the child's
+  /// name.
+  ///
+  /// # Parameters
+  ///
+  /// * `cursor` - A cursor pointing to a node.
+  ///
+  /// # Assumptions
+  ///
+  /// The cursor is assumed to be positioned just after the null-terminator of
+  /// the node's name.
+  ///
+  /// # Returns
+  ///
+  /// A cursor if the node has a child, otherwise None.
+  pub fn get_first_child_node(&self, cursor: &DtbCursor) -> Option<DtbCursor> {
+    let mut tmp_cursor = *cursor;
+    _ = self.skip_node_properties(&mut tmp_cursor).ok()?;
+    self.get_node_at_level(&mut tmp_cursor)
+  }
+
+  /// Get the next sibling of the node pointed to by the cursor. If the node has
+  /// a next sibling, the cursor returned will be positioned just after the
+  /// sibling's name.
+  ///
+  /// # Parameters
+  ///
+  /// * `cursor` - A cursor pointing to a node.
+  ///
+  /// # Assumptions
+  ///
+  /// The cursor is assumed to be positioned just after the null-terminator of
+  /// the node's name.
+  ///
+  /// # Returns
+  ///
+  /// A cursor if the node has a next sibling, otherwise None.
+  pub fn get_next_sibling_node(&self, cursor: &DtbCursor) -> Option<DtbCursor> {
+    let mut tmp_cursor = *cursor;
+    let mut depth = 0;
+
+    // Walk past the end of the node, including all of its descendants.
+    loop {
+      _ = self.skip_node_properties(&mut tmp_cursor).ok()?;
+
+      loop {
+        let marker = self.get_u32(&mut tmp_cursor)?;
+
+        match marker {
+          FDT_BEGIN_NODE => {
+            depth += 1;
+            break;
+          }
+          FDT_END_NODE => {
+            if depth == 0 {
+              return self.get_node_at_level(&mut tmp_cursor);
+            }
+
+            depth -= 1;
+          }
+          FDT_NOOP => {}
+          _ => return None,
+        }
+      }
+
+      _ = self.get_null_terminated_u8_slice(&mut tmp_cursor)?;
+      self.skip_and_align(1, &mut tmp_cursor);
+    }
+  }
+
+  /// Get the node that begins at the cursor's position, if any.
+  ///
+  /// # Parameters
+  ///
+  /// * `cursor` - The cursor to advance.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes the cursor is positioned after a node's properties or after the
+  /// end of a node.
+  ///
+  /// # Returns
+  ///
+  /// A cursor positioned just after the node's name, or None if the cursor is
+  /// at the end of the parent node.
+  fn get_node_at_level(&self, cursor: &mut DtbCursor) -> Option<DtbCursor> {
+    loop {
+      let marker = self.get_u32(cursor)?;
+
+      match marker {
+        FDT_BEGIN_NODE => break,
+        FDT_NOOP => {}
+        _ => return None,
+      }
+    }
+
+    _ = self.get_null_terminated_u8_slice(cursor)?;
+    self.skip_and_align(1, cursor);
+
+    Some(*cursor)
+  }
+
   /// Skips a node's properties.
   ///
   /// # Parameters
@@ -269,11 +601,35 @@ impl<'blob> DtbReader<'blob> {
         _ => return Err(DtbError::InvalidDtb),
       }
 
-      let prop_size = self.get_u32(cursor).ok_or(DtbError::InvalidDtb)?;
-      self.skip_and_align(prop_size as usize + FDT_WORD_BYTES, cursor);
+      let prop_size = self.get_u32(cursor).ok_or(DtbError::InvalidDtb)? as usize;
+
+      // Skip the name offset and make sure the value does not run past the end
+      // of the structure block.
+      if !self.has_bytes(cursor, prop_size.saturating_add(FDT_WORD_BYTES)) {
+        return Err(DtbError::OutOfBounds);
+      }
+
+      self.skip_and_align(prop_size + FDT_WORD_BYTES, cursor);
     }
   }
 
+  /// Skip past a number of bytes without aligning the new location. Useful for
+  /// stepping over the null terminator between the strings of a string list.
+  ///
+  /// # Parameters
+  ///
+  /// * `skip_bytes` - The number of bytes to skip.
+  /// * `cursor` - The cursor to advance.
+  ///
+  /// # Description
+  ///
+  /// If skipping the specified number of bytes would place the cursor past the
+  /// end of the structure block, the cursor is positioned at the end of the
+  /// structure block and is no longer valid.
+  pub fn skip(&self, skip_bytes: usize, cursor: &mut DtbCursor) {
+    cursor.loc += cmp::min(cursor.remaining(), skip_bytes);
+  }
+
   /// Skip past a number of bytes and align the new location. Useful shortcut
   /// for skipping past a property, or the null terminator of a string, and any
   /// padding after.
@@ -286,20 +642,15 @@ impl<'blob> DtbReader<'blob> {
   /// # Description
   ///
   /// If skipping the specified number of bytes would place the cursor past the
-  /// end of the DTB, the cursor is positioned at the end of the DTB and is no
-  /// longer valid.
+  /// end of the structure block, the cursor is positioned at the end of the
+  /// structure block and is no longer valid.
   ///
   /// Otherwise, the cursor's position is updated by adding the number of bytes
   /// to the location and then aligning the new position on a DTB word boundary.
+  /// See `DtbCursor::align_to()`.
   pub fn skip_and_align(&self, skip_bytes: usize, cursor: &mut DtbCursor) {
-    let len = self.dtb.len();
-    let offset = cmp::min(len - cursor.loc, skip_bytes);
-
-    cursor.loc = if cursor.loc + offset > len - FDT_WORD_BYTES {
-      len
-    } else {
-      bits::align_up(cursor.loc + offset, FDT_WORD_BYTES)
-    };
+    cursor.loc += cmp::min(cursor.remaining(), skip_bytes);
+    cursor.align_to(FDT_WORD_BYTES);
   }
 
   /// Read a 32-bit integer from the DTB at the position pointed to by the
@@ -310,12 +661,16 @@ impl<'blob> DtbReader<'blob> {
   ///
   /// * `cursor` - Cursor pointing to the location to read.
   ///
+  /// # Description
+  ///
+  /// The integer is converted from big-endian to the host's byte order.
+  ///
   /// # Returns
   ///
   /// The 32-bit integer at the current position or None if there are not at
-  /// least 32-bits remaining in the DTB.
+  /// least 32-bits remaining in the structure block.
   pub fn get_u32(&self, cursor: &mut DtbCursor) -> Option<u32> {
-    if cursor.loc > self.dtb.len() - FDT_WORD_BYTES {
+    if !self.has_bytes(cursor, FDT_WORD_BYTES) {
       return None;
     }
 
@@ -330,12 +685,17 @@ impl<'blob> DtbReader<'blob> {
   ///
   /// * `cursor` - Cursor pointing to the location to read.
   ///
+  /// # Description
+  ///
+  /// The integer is converted from big-endian to the host's byte order. The
+  /// most significant word comes first.
+  ///
   /// # Returns
   ///
   /// The 64-bit integer at the current position or None if there are not at
-  /// least 64-bits remaining in the DTB.
+  /// least 64-bits remaining in the structure block.
   pub fn get_u64(&self, cursor: &mut DtbCursor) -> Option<u64> {
-    if cursor.loc > self.dtb.len() - (FDT_WORD_BYTES * 2) {
+    if !self.has_bytes(cursor, FDT_WORD_BYTES * 2) {
       return None;
     }
 
@@ -344,6 +704,24 @@ impl<'blob> DtbReader<'blob> {
     Some(upper | lower)
   }
 
+  /// Check if a number of bytes remain in the structure block after the
+  /// position pointed to by the cursor.
+  ///
+  /// # Parameters
+  ///
+  /// * `cursor` - Cursor pointing to the location to check.
+  /// * `count` - The number of bytes required.
+  ///
+  /// # Returns
+  ///
+  /// True if the bytes are available, false otherwise.
+  fn has_bytes(&self, cursor: &DtbCursor, count: usize) -> bool {
+    match cursor.loc.checked_add(count) {
+      Some(end) => end <= self.dt_struct_end,
+      None => false,
+    }
+  }
+
   /// Internal helper to read a 32-bit integer.
   ///
   /// # Parameters
@@ -354,6 +732,9 @@ impl<'blob> DtbReader<'blob> {
   ///
   /// Assumes that the caller has already verified that 32-bits remain after the
   /// position pointed to by the cursor.
+  ///
+  ///   NOTE: Always use `u32::from_be_bytes()` rather than `u32::from_be()` on
+  ///         a dereferenced pointer. The DTB may be unaligned.
   fn get_u32_unchecked(&self, cursor: &mut DtbCursor) -> u32 {
     let end_loc = cursor.loc + FDT_WORD_BYTES;
     let bytes: &[u8; FDT_WORD_BYTES] = self.dtb[cursor.loc..end_loc].try_into().unwrap();
@@ -362,6 +743,76 @@ impl<'blob> DtbReader<'blob> {
     ret
   }
 
+  /// Find a property of the node pointed to by the cursor.
+  ///
+  /// # Parameters
+  ///
+  /// * `cursor` - A cursor pointing to a node.
+  /// * `name` - The property name.
+  ///
+  /// # Description
+  ///
+  /// Convenient for one-off lookups. Scanners that read several properties of
+  /// the same node should walk the properties once with `get_next_property()`.
+  ///
+  /// # Assumptions
+  ///
+  /// The cursor is assumed to be positioned just after the null-terminator of
+  /// the node's name.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with a cursor positioned at the property's value and the size of
+  /// the value, or None if the node does not have the property.
+  pub fn get_property(&self, cursor: &DtbCursor, name: &[u8]) -> Option<(DtbCursor, usize)> {
+    let mut tmp_cursor = *cursor;
+
+    while let Some(header) = self.get_next_property(&mut tmp_cursor) {
+      if header.name.cmp(name) == cmp::Ordering::Equal {
+        return Some((tmp_cursor, header.size));
+      }
+
+      self.skip_and_align(header.size, &mut tmp_cursor);
+    }
+
+    None
+  }
+
+  /// Check if the node pointed to by the cursor is enabled.
+  ///
+  /// # Parameters
+  ///
+  /// * `cursor` - A cursor pointing to a node.
+  ///
+  /// # Description
+  ///
+  /// https://devicetree-specification.readthedocs.io/en/stable/devicetree-basics.html#status
+  ///
+  /// A node is enabled if it does not have a `status` property, or if the
+  /// status is "okay" or the legacy "ok". Any other status, e.g. "disabled" or
+  /// "fail", means the node must be ignored.
+  ///
+  /// # Assumptions
+  ///
+  /// The cursor is assumed to be positioned just after the null-terminator of
+  /// the node's name.
+  ///
+  /// # Returns
+  ///
+  /// True if the node is enabled, false otherwise.
+  pub fn node_is_enabled(&self, cursor: &DtbCursor) -> bool {
+    let Some((mut tmp_cursor, _)) = self.get_property(cursor, b"status") else {
+      return true;
+    };
+
+    match self.get_null_terminated_u8_slice(&mut tmp_cursor) {
+      Some(status) => {
+        status.cmp(b"okay") == cmp::Ordering::Equal || status.cmp(b"ok") == cmp::Ordering::Equal
+      }
+      None => false,
+    }
+  }
+
   /// Read the property header of the next property after the position pointed
   /// to by the cursor.
   ///
@@ -394,6 +845,15 @@ impl<'blob> DtbReader<'blob> {
       let name_offset = self.get_u32(cursor)? as usize;
       let name = self.get_slice_from_string_table(name_offset)?;
 
+      // If the property's value runs past the end of the structure block,
+      // rewind to the start of the property and treat it as the end of the
+      // node's properties. `scan()` will report the error when it attempts to
+      // skip the property.
+      if !self.has_bytes(cursor, size) {
+        cursor.loc -= FDT_WORD_BYTES * 3;
+        return None;
+      }
+
       return Some(DtbPropertyHeader {
         size,
         name_offset,
@@ -457,7 +917,7 @@ impl<'blob> DtbReader<'blob> {
 
     let pair_size = DtbReader::get_reg_pair_size(addr_cells, size_cells);
 
-    if cursor.loc > self.dtb.len() - pair_size {
+    if !self.has_bytes(cursor, pair_size) {
       return None;
     }
 
@@ -527,7 +987,7 @@ impl<'blob> DtbReader<'blob> {
 
     let count = DtbReader::get_range_size(child_addr_cells, parent_addr_cells, size_cells);
 
-    if cursor.loc > self.dtb.len() - count {
+    if !self.has_bytes(cursor, count) {
       return None;
     }
 
@@ -556,8 +1016,8 @@ impl<'blob> DtbReader<'blob> {
   /// Gets a null-terminated slice starting at the position pointed to by the
   /// cursor. The cursor will be advanced to the null-terminator. The caller
   /// should use `skip_and_align` to skip the 1-byte terminator and align the
-  /// cursor. If a null-terminator is not found before the end of the DTB, the
-  /// cursor will not be repositioned.
+  /// cursor. If a null-terminator is not found before the end of the structure
+  /// block, the cursor will not be repositioned.
   ///
   /// # Parameters
   ///
@@ -568,27 +1028,36 @@ impl<'blob> DtbReader<'blob> {
   /// A slice containing the bytes up to, but NOT including, the null-terminator
   /// if a null-terminated string was found, otherwise None.
   pub fn get_null_terminated_u8_slice(&self, cursor: &mut DtbCursor) -> Option<&'blob [u8]> {
-    let mut end = cursor.loc;
-    let len = self.dtb.len();
+    let slice = self.find_null_terminated_u8_slice(cursor.loc, self.dt_struct_end)?;
 
-    while end < len {
-      if self.dtb[end] == 0 {
-        break;
-      }
+    // Leave the cursor on the null terminator.
+    cursor.loc += slice.len();
+    Some(slice)
+  }
 
-      end += 1;
-    }
+  /// Internal helper to find a null-terminated slice within a block.
+  ///
+  /// # Parameters
+  ///
+  /// * `start` - The offset of the first byte of the string.
+  /// * `block_end` - The end of the block containing the string.
+  ///
+  /// # Returns
+  ///
+  /// A slice containing the bytes up to, but NOT including, the null-terminator
+  /// if a null-terminator was found before the end of the block, otherwise
+  /// None.
+  fn find_null_terminated_u8_slice(&self, start: usize, block_end: usize) -> Option<&'blob [u8]> {
+    let dtb: &'blob [u8] = self.dtb;
 
-    // We did not actually find a null-terminator, this is invalid.
-    if end == len {
+    if start >= block_end {
       return None;
     }
 
-    // Return a slice excluding the null-terminator and leave the cursor on the
-    // null terminator.
-    let loc = cursor.loc;
-    cursor.loc = end;
-    Some(&self.dtb[loc..end])
+    // We did not actually find a null-terminator, this is invalid.
+    let len = dtb[start..block_end].iter().position(|&b| b == 0)?;
+
+    Some(&dtb[start..start + len])
   }
 
   /// Get a slice from the specified position in the string table.
@@ -597,13 +1066,36 @@ impl<'blob> DtbReader<'blob> {
   ///
   /// * `str_offset` - The byte offset into the string table.
   ///
+  /// # Description
+  ///
+  /// Recently used strings are cached to avoid repeatedly scanning the string
+  /// block for the null-terminator of common property names.
+  ///
   /// # Returns
   ///
   /// A slice containing the string if a null-terminated string was found at the
-  /// specified offset, otherwise None.
+  /// specified offset within the strings block, otherwise None.
   pub fn get_slice_from_string_table(&self, str_offset: usize) -> Option<&'blob [u8]> {
-    let mut cursor = DtbCursor::new(self.dt_strings_offset + str_offset);
-    self.get_null_terminated_u8_slice(&mut cursor)
+    if let Some(name) = self.string_cache.find(str_offset) {
+      return Some(name);
+    }
+
+    #[cfg(feature = "module_tests")]
+    self.string_scans.set(self.string_scans.get() + 1);
+
+    let strings_end = self.dt_strings_offset + self.dt_strings_size;
+    let start = self.dt_strings_offset.checked_add(str_offset)?;
+    let name = self.find_null_terminated_u8_slice(start, strings_end)?;
+
+    self.string_cache.insert(str_offset, name);
+
+    Some(name)
+  }
+
+  /// Get the number of times the reader has scanned the string block.
+  #[cfg(feature = "module_tests")]
+  fn get_string_scan_count(&self) -> usize {
+    self.string_scans.get()
   }
 
   /// Walk the DTB using a custom node scanner.
@@ -615,8 +1107,8 @@ impl<'blob> DtbReader<'blob> {
   /// # Returns
   ///
   /// Ok if scanning succeeds, or an Error.
-  pub fn scan(&self, scanner: &mut impl DtbScanner) -> Result<(), DtbError> {
-    let mut cursor = DtbCursor::new(self.dt_struct_offset);
+  pub fn scan(&self, scanner: &mut impl DtbScanner<'blob>) -> Result<(), DtbError> {
+    let mut cursor = DtbCursor::new(self.dt_struct_offset, self.dt_struct_end);
     let marker = self.get_u32(&mut cursor).ok_or(DtbError::InvalidDtb)?;
 
     if marker != FDT_BEGIN_NODE {
@@ -650,3 +1142,8 @@ impl<'blob> DtbReader<'blob> {
     }
   }
 }
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
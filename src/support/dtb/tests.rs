@@ -0,0 +1,731 @@
+//! DTB Reader Tests
+
+use super::{DtbCursor, DtbError, DtbReader, DtbScanner};
+use crate::debug_print;
+use crate::test::dtb::{self, DtbBuilder};
+use crate::{check_eq, check_none, check_optional, execute_test, mark_fail, test};
+use core::{ptr, slice};
+
+/// Run the DTB reader tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_valid_blob);
+  execute_test!(context, test_truncated_blob);
+  execute_test!(context, test_version_mismatch);
+  execute_test!(context, test_block_bounds);
+  execute_test!(context, test_property_out_of_bounds);
+  execute_test!(context, test_unterminated_string);
+  execute_test!(context, test_string_table_bounds);
+  execute_test!(context, test_string_cache);
+  execute_test!(context, test_string_cache_eviction);
+  execute_test!(context, test_cursor_align);
+  execute_test!(context, test_cursor_align_at_end);
+  execute_test!(context, test_big_endian_values);
+  execute_test!(context, test_unaligned_blob);
+  execute_test!(context, test_get_property);
+  execute_test!(context, test_node_status);
+}
+
+/// Buffer for a copy of a test DTB at an arbitrary offset. The extra word
+/// leaves room to misalign the copy.
+static mut TEST_BLOB_COPY: [u32; (dtb::MAX_BLOB_SIZE / 4) + 1] = [0; (dtb::MAX_BLOB_SIZE / 4) + 1];
+
+/// Scanner that counts the nodes in a DTB.
+struct NodeCounter {
+  count: usize,
+}
+
+impl<'blob> DtbScanner<'blob> for NodeCounter {
+  /// See `DtbScanner::scan_node()`.
+  fn scan_node(
+    &mut self,
+    _reader: &DtbReader<'blob>,
+    _name: &[u8],
+    _cursor: &DtbCursor,
+  ) -> Result<bool, DtbError> {
+    self.count += 1;
+    Ok(true)
+  }
+}
+
+/// Scanner that reads every property in a DTB.
+struct PropertyWalker {
+  count: usize,
+}
+
+impl<'blob> DtbScanner<'blob> for PropertyWalker {
+  /// See `DtbScanner::scan_node()`.
+  fn scan_node(
+    &mut self,
+    reader: &DtbReader<'blob>,
+    _name: &[u8],
+    cursor: &DtbCursor,
+  ) -> Result<bool, DtbError> {
+    let mut tmp_cursor = *cursor;
+
+    while let Some(header) = reader.get_next_property(&mut tmp_cursor) {
+      reader.skip_and_align(header.size, &mut tmp_cursor);
+      self.count += 1;
+    }
+
+    Ok(true)
+  }
+}
+
+/// Build a small, valid DTB.
+///
+/// # Parameters
+///
+/// * `builder` - The DTB builder.
+///
+/// # Returns
+///
+/// The address of the DTB.
+fn build_blob(builder: &mut DtbBuilder) -> usize {
+  builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1)
+    .begin_node("memory@0")
+    .prop_str("device_type", "memory")
+    .prop_cells("reg", &[0, 0x1000_0000])
+    .end_node()
+    .end_node()
+    .finish()
+}
+
+/// Check if the reader rejects a DTB as invalid.
+///
+/// # Parameters
+///
+/// * `blob` - The address of the DTB.
+///
+/// # Returns
+///
+/// True if `DtbReader::new()` returns `DtbError::InvalidDtb`, false otherwise.
+fn is_invalid(blob: usize) -> bool {
+  matches!(DtbReader::new(blob), Err(DtbError::InvalidDtb))
+}
+
+/// Test that a well-formed DTB is accepted.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_valid_blob(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = build_blob(&mut builder);
+
+  let reader = DtbReader::new(blob);
+  check_eq!(context, reader.is_ok(), true);
+
+  let root = reader.ok().and_then(|r| r.get_root_node());
+  check_eq!(context, root.is_some(), true);
+}
+
+/// Test that truncated DTBs are rejected.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Shrinking the total size cuts off the strings block, then the structure
+/// block, and finally the header itself.
+fn test_truncated_blob(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = build_blob(&mut builder);
+  let total_size = builder.get_header_field(dtb::HDR_TOTAL_SIZE);
+  let struct_offset = builder.get_header_field(dtb::HDR_STRUCT_OFFSET);
+
+  // Cut off the last byte of the strings block.
+  builder.set_header_field(dtb::HDR_TOTAL_SIZE, total_size - 1);
+  check_eq!(context, is_invalid(blob), true);
+
+  // Cut off the structure block.
+  builder.set_header_field(dtb::HDR_TOTAL_SIZE, struct_offset + 4);
+  check_eq!(context, is_invalid(blob), true);
+
+  // Cut off part of the header.
+  builder.set_header_field(dtb::HDR_TOTAL_SIZE, 16);
+  check_eq!(context, is_invalid(blob), true);
+
+  builder.set_header_field(dtb::HDR_TOTAL_SIZE, total_size);
+  check_eq!(context, DtbReader::new(blob).is_ok(), true);
+}
+
+/// Test that DTBs with unsupported versions are rejected.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_version_mismatch(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = build_blob(&mut builder);
+
+  // Versions prior to 16 are not supported.
+  builder.set_header_field(dtb::HDR_VERSION, 15);
+  check_eq!(context, is_invalid(blob), true);
+
+  // The DTB is not backwards compatible with version 17.
+  builder.set_header_field(dtb::HDR_VERSION, 18);
+  builder.set_header_field(dtb::HDR_LAST_COMP_VERSION, 18);
+  check_eq!(context, is_invalid(blob), true);
+
+  // Version 16 headers do not have a structure block size; the field is
+  // ignored.
+  builder.set_header_field(dtb::HDR_VERSION, 16);
+  builder.set_header_field(dtb::HDR_LAST_COMP_VERSION, 16);
+  builder.set_header_field(dtb::HDR_STRUCT_SIZE, 0xffff_ffff);
+  check_eq!(context, DtbReader::new(blob).is_ok(), true);
+}
+
+/// Test that blocks outside of the DTB are rejected.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_block_bounds(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = build_blob(&mut builder);
+  let total_size = builder.get_header_field(dtb::HDR_TOTAL_SIZE);
+  let struct_offset = builder.get_header_field(dtb::HDR_STRUCT_OFFSET);
+  let struct_size = builder.get_header_field(dtb::HDR_STRUCT_SIZE);
+  let strings_offset = builder.get_header_field(dtb::HDR_STRINGS_OFFSET);
+  let strings_size = builder.get_header_field(dtb::HDR_STRINGS_SIZE);
+  let mem_rsv_offset = builder.get_header_field(dtb::HDR_MEM_RSV_OFFSET);
+
+  // Structure block extends past the end of the DTB.
+  builder.set_header_field(dtb::HDR_STRUCT_SIZE, total_size - struct_offset + 4);
+  check_eq!(context, is_invalid(blob), true);
+  builder.set_header_field(dtb::HDR_STRUCT_SIZE, struct_size);
+
+  // Structure block overlaps the header.
+  builder.set_header_field(dtb::HDR_STRUCT_OFFSET, 8);
+  check_eq!(context, is_invalid(blob), true);
+  builder.set_header_field(dtb::HDR_STRUCT_OFFSET, struct_offset);
+
+  // Strings block size overflows.
+  builder.set_header_field(dtb::HDR_STRINGS_SIZE, 0xffff_ffff);
+  check_eq!(context, is_invalid(blob), true);
+  builder.set_header_field(dtb::HDR_STRINGS_SIZE, strings_size);
+
+  // Strings block starts past the end of the DTB.
+  builder.set_header_field(dtb::HDR_STRINGS_OFFSET, total_size + 4);
+  check_eq!(context, is_invalid(blob), true);
+  builder.set_header_field(dtb::HDR_STRINGS_OFFSET, strings_offset);
+
+  // Memory reservation block is past the end of the DTB.
+  builder.set_header_field(dtb::HDR_MEM_RSV_OFFSET, total_size);
+  check_eq!(context, is_invalid(blob), true);
+
+  // Memory reservation block is misaligned.
+  builder.set_header_field(dtb::HDR_MEM_RSV_OFFSET, mem_rsv_offset + 4);
+  check_eq!(context, is_invalid(blob), true);
+  builder.set_header_field(dtb::HDR_MEM_RSV_OFFSET, mem_rsv_offset);
+
+  check_eq!(context, DtbReader::new(blob).is_ok(), true);
+}
+
+/// Test a property whose declared length runs off the end of the structure
+/// block.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_property_out_of_bounds(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .begin_node("node")
+    .prop_with_size("reg", 0x100, &[0; 8])
+    .end_node()
+    .end_node()
+    .finish();
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  // Scanning must stop at the bad property.
+  let mut counter = NodeCounter { count: 0 };
+  let ret = reader.scan(&mut counter);
+  check_eq!(context, matches!(ret, Err(DtbError::OutOfBounds)), true);
+  check_eq!(context, counter.count, 2);
+
+  let Some(cursor) = reader.get_root_node() else {
+    mark_fail!(context, "Failed to find the root node.");
+    return;
+  };
+
+  let Some(mut cursor) = reader.find_child_node(&cursor, "node") else {
+    mark_fail!(context, "Failed to find the child node.");
+    return;
+  };
+
+  // The property header is not returned and the cursor is left at the start
+  // of the property.
+  let loc = cursor.loc;
+  check_eq!(context, reader.get_next_property(&mut cursor).is_none(), true);
+  check_eq!(context, cursor.loc, loc);
+}
+
+/// Test that strings are bounded by the end of the structure block.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Shrinks the structure block so that it ends in the middle of a string
+/// property value. The null-terminator still exists in memory, but is outside
+/// of the structure block.
+fn test_unterminated_string(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_str("bootargs", "console=ttyAMA0")
+    .end_node()
+    .finish();
+
+  // Root node marker, empty root node name, and the property header.
+  let value_offset = 4 + 4 + 12;
+  let struct_end = value_offset + 4;
+  builder.set_header_field(dtb::HDR_STRUCT_SIZE, struct_end as u32);
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  let Some(mut cursor) = reader.get_root_node() else {
+    mark_fail!(context, "Failed to find the root node.");
+    return;
+  };
+
+  // The property value runs past the structure block.
+  check_eq!(context, reader.get_next_property(&mut cursor).is_none(), true);
+
+  // Read the property header manually and attempt to read the value.
+  for _ in 0..3 {
+    _ = reader.get_u32(&mut cursor);
+  }
+
+  let loc = cursor.loc;
+  check_eq!(context, reader.get_null_terminated_u8_slice(&mut cursor).is_none(), true);
+  check_eq!(context, cursor.loc, loc);
+
+  // Only one word of the value is within the structure block.
+  check_eq!(context, reader.get_u32(&mut cursor).is_some(), true);
+  check_eq!(context, reader.get_u32(&mut cursor).is_none(), true);
+  check_eq!(
+    context,
+    reader
+      .get_u64(&mut DtbCursor::new(loc, cursor.end))
+      .is_none(),
+    true
+  );
+}
+
+/// Test that property names are bounded by the strings block.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_string_table_bounds(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = build_blob(&mut builder);
+  let strings_size = builder.get_header_field(dtb::HDR_STRINGS_SIZE) as usize;
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  let matches = reader.get_slice_from_string_table(0) == Some("#address-cells".as_bytes());
+  check_eq!(context, matches, true);
+  check_eq!(context, reader.get_slice_from_string_table(strings_size).is_none(), true);
+  check_eq!(context, reader.get_slice_from_string_table(usize::MAX).is_none(), true);
+
+  // Shrink the strings block so that the first name is not terminated.
+  builder.set_header_field(dtb::HDR_STRINGS_SIZE, 4);
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  check_eq!(context, reader.get_slice_from_string_table(0).is_none(), true);
+}
+
+/// Test that repeated property name lookups do not rescan the string block.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Without the cache, every property read scans the string block. With the
+/// cache, the string block is only scanned once per distinct name.
+fn test_string_cache(context: &mut test::TestContext) {
+  const NODE_COUNT: u32 = 6;
+
+  let mut builder = DtbBuilder::new();
+  builder
+    .begin_node("")
+    .prop_u32("#address-cells", 1)
+    .prop_u32("#size-cells", 1);
+
+  for i in 0..NODE_COUNT {
+    builder
+      .begin_node("memory")
+      .prop_str("device_type", "memory")
+      .prop_cells("reg", &[i * 0x1000_0000, 0x1000_0000])
+      .end_node();
+  }
+
+  let blob = builder.end_node().finish();
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  let mut walker = PropertyWalker { count: 0 };
+  check_eq!(context, reader.scan(&mut walker).is_ok(), true);
+
+  // Before: one scan per property. After: one scan per distinct name.
+  check_eq!(context, walker.count, 2 + (NODE_COUNT as usize * 2));
+  check_eq!(context, reader.get_string_scan_count(), 4);
+
+  // Walking the DTB again is served entirely from the cache.
+  check_eq!(context, reader.scan(&mut walker).is_ok(), true);
+  check_eq!(context, reader.get_string_scan_count(), 4);
+}
+
+/// Test that the least-recently-used name is evicted when the cache is full.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_string_cache_eviction(context: &mut test::TestContext) {
+  const NAMES: [&str; super::STRING_CACHE_SIZE + 1] =
+    ["p0", "p1", "p2", "p3", "p4", "p5", "p6", "p7", "p8"];
+
+  let mut builder = DtbBuilder::new();
+  builder.begin_node("");
+
+  for name in NAMES {
+    builder.prop_u32(name, 0);
+  }
+
+  let blob = builder.end_node().finish();
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  // Each name is 3 bytes including the null-terminator.
+  for i in 0..NAMES.len() {
+    _ = reader.get_slice_from_string_table(i * 3);
+  }
+
+  check_eq!(context, reader.get_string_scan_count(), NAMES.len());
+
+  // The most recent name is still cached.
+  let matches = reader.get_slice_from_string_table(8 * 3) == Some("p8".as_bytes());
+  check_eq!(context, matches, true);
+  check_eq!(context, reader.get_string_scan_count(), NAMES.len());
+
+  // The first name was evicted by the last.
+  let matches = reader.get_slice_from_string_table(0) == Some("p0".as_bytes());
+  check_eq!(context, matches, true);
+  check_eq!(context, reader.get_string_scan_count(), NAMES.len() + 1);
+}
+
+/// Test aligning a cursor within the structure block.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_cursor_align(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = build_blob(&mut builder);
+  let struct_size = builder.get_header_field(dtb::HDR_STRUCT_SIZE) as usize;
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  let Some(mut cursor) = reader.get_root_node() else {
+    mark_fail!(context, "Failed to find the root node.");
+    return;
+  };
+
+  // The root node's marker and empty name occupy the first two words.
+  let remaining = cursor.remaining();
+  check_eq!(context, remaining, struct_size - 8);
+
+  // An aligned cursor does not move.
+  cursor.align_to(4);
+  check_eq!(context, cursor.remaining(), remaining);
+
+  cursor.loc += 1;
+  cursor.align_to(4);
+  check_eq!(context, cursor.remaining(), remaining - 4);
+
+  cursor.loc += 1;
+  cursor.align_to(8);
+  check_eq!(context, cursor.loc & 0x7, 0);
+}
+
+/// Test that aligning a cursor near the end of a blob clamps at the end.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_cursor_align_at_end(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = build_blob(&mut builder);
+  let struct_offset = builder.get_header_field(dtb::HDR_STRUCT_OFFSET) as usize;
+  let struct_size = builder.get_header_field(dtb::HDR_STRUCT_SIZE) as usize;
+  let struct_end = struct_offset + struct_size;
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  let Some(mut cursor) = reader.get_root_node() else {
+    mark_fail!(context, "Failed to find the root node.");
+    return;
+  };
+
+  // Move to the last byte of the structure block, then align past the end.
+  cursor.loc = struct_end - 1;
+  check_eq!(context, cursor.remaining(), 1);
+
+  cursor.align_to(0x1000);
+  check_eq!(context, cursor.loc, struct_end);
+  check_eq!(context, cursor.remaining(), 0);
+  check_none!(context, reader.get_u32(&mut cursor));
+
+  // Aligning at the end is a no-op.
+  cursor.align_to(4);
+  check_eq!(context, cursor.loc, struct_end);
+
+  // Skipping from near the end also clamps.
+  cursor.loc = struct_end - 6;
+  reader.skip_and_align(5, &mut cursor);
+  check_eq!(context, cursor.loc, struct_end);
+
+  // A block that does not end on the alignment boundary.
+  let mut cursor = DtbCursor::new(struct_offset, struct_offset + 6);
+  cursor.loc += 5;
+  cursor.align_to(4);
+  check_eq!(context, cursor.loc, struct_offset + 6);
+  check_eq!(context, cursor.remaining(), 0);
+}
+
+/// Test reading big-endian integers.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The values are written as raw bytes, most significant byte first, so the
+/// expected values do not depend on the host's byte order.
+fn test_big_endian_values(context: &mut test::TestContext) {
+  const UPPER: u32 = 0x1234_5678;
+  const LOWER: u32 = 0x9abc_def0;
+  const VALUE: u64 = ((UPPER as u64) << 32) | (LOWER as u64);
+
+  let mut builder = DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_bytes("val", &[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0])
+    .end_node()
+    .finish();
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  let Some((cursor, _)) = reader
+    .get_root_node()
+    .and_then(|r| reader.get_property(&r, b"val"))
+  else {
+    mark_fail!(context, "Failed to find the property.");
+    return;
+  };
+
+  let mut tmp_cursor = cursor;
+  check_optional!(context, reader.get_u32(&mut tmp_cursor), UPPER);
+  check_optional!(context, reader.get_u32(&mut tmp_cursor), LOWER);
+
+  let mut tmp_cursor = cursor;
+  check_optional!(context, reader.get_u64(&mut tmp_cursor), VALUE);
+
+  let mut tmp_cursor = cursor;
+  let pair = reader.get_reg_pair(1, 1, &mut tmp_cursor);
+  check_optional!(context, pair.map(|(addr, _)| addr), UPPER as u64);
+  check_optional!(context, pair.map(|(_, size)| size), LOWER as u64);
+}
+
+/// Test reading a DTB that is not aligned on a word boundary.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_unaligned_blob(context: &mut test::TestContext) {
+  const VALUE: u32 = 0xd00d_f00d;
+
+  let mut builder = DtbBuilder::new();
+  let blob = builder
+    .begin_node("")
+    .prop_u32("val", VALUE)
+    .end_node()
+    .finish();
+
+  let Ok(size) = DtbReader::check_dtb(blob) else {
+    mark_fail!(context, "Failed to check the DTB.");
+    return;
+  };
+
+  let copy = unsafe { ptr::addr_of_mut!(TEST_BLOB_COPY).as_mut().unwrap() };
+  let copy = unsafe { slice::from_raw_parts_mut(copy.as_mut_ptr() as *mut u8, copy.len() * 4) };
+  let src = unsafe { slice::from_raw_parts(blob as *const u8, size) };
+  copy[1..size + 1].copy_from_slice(src);
+
+  let Ok(reader) = DtbReader::new(copy[1..].as_ptr() as usize) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  let Some((mut cursor, _)) = reader
+    .get_root_node()
+    .and_then(|r| reader.get_property(&r, b"val"))
+  else {
+    mark_fail!(context, "Failed to find the property.");
+    return;
+  };
+
+  check_optional!(context, reader.get_u32(&mut cursor), VALUE);
+}
+
+/// Test looking up properties by name.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Only the node's own properties are searched, not its children's, and a
+/// prefix of a property name does not match.
+fn test_get_property(context: &mut test::TestContext) {
+  let mut builder = DtbBuilder::new();
+  let blob = build_blob(&mut builder);
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  let Some(root) = reader.get_root_node() else {
+    mark_fail!(context, "Failed to find the root node.");
+    return;
+  };
+
+  let Some((mut cursor, size)) = reader.get_property(&root, b"#size-cells") else {
+    mark_fail!(context, "Failed to find the property.");
+    return;
+  };
+
+  check_eq!(context, size, 4);
+  check_optional!(context, reader.get_u32(&mut cursor), 1);
+
+  check_none!(context, reader.get_property(&root, b"reg"));
+  check_none!(context, reader.get_property(&root, b"#size"));
+  check_none!(context, reader.get_property(&root, b"missing"));
+
+  let Some(memory) = reader.find_child_node(&root, "memory@0") else {
+    mark_fail!(context, "Failed to find the child node.");
+    return;
+  };
+
+  let Some((mut cursor, size)) = reader.get_property(&memory, b"reg") else {
+    mark_fail!(context, "Failed to find the property.");
+    return;
+  };
+
+  check_eq!(context, size, 8);
+  check_optional!(context, reader.get_u32(&mut cursor), 0);
+  check_optional!(context, reader.get_u32(&mut cursor), 0x1000_0000);
+  check_none!(context, reader.get_property(&memory, b"#address-cells"));
+}
+
+/// Test checking whether nodes are enabled.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_node_status(context: &mut test::TestContext) {
+  const NODES: [(&str, Option<&str>, bool); 6] = [
+    ("none", None, true),
+    ("okay", Some("okay"), true),
+    ("ok", Some("ok"), true),
+    ("disabled", Some("disabled"), false),
+    ("fail", Some("fail"), false),
+    ("fail-sss", Some("fail-sss"), false),
+  ];
+
+  let mut builder = DtbBuilder::new();
+  builder.begin_node("");
+
+  for (name, status, _) in NODES {
+    builder.begin_node(name);
+
+    if let Some(status) = status {
+      builder.prop_str("status", status);
+    }
+
+    builder.prop_u32("reg", 0).end_node();
+  }
+
+  let blob = builder.end_node().finish();
+
+  let Ok(reader) = DtbReader::new(blob) else {
+    mark_fail!(context, "Failed to create the reader.");
+    return;
+  };
+
+  let Some(root) = reader.get_root_node() else {
+    mark_fail!(context, "Failed to find the root node.");
+    return;
+  };
+
+  check_eq!(context, reader.node_is_enabled(&root), true);
+
+  for (name, _, enabled) in NODES {
+    let Some(node) = reader.find_child_node(&root, name) else {
+      mark_fail!(context, "Failed to find the child node.");
+      continue;
+    };
+
+    check_eq!(context, reader.node_is_enabled(&node), enabled);
+  }
+}
@@ -0,0 +1,111 @@
+//! Memory-Mapped I/O Registers
+//!
+//! Drivers access device registers through an `Mmio` block rather than
+//! open-coding volatile accesses at computed addresses. Every access uses a
+//! barrier so that device accesses are observed in program order:
+//!
+//! * A read is followed by a barrier so that later accesses cannot pass it.
+//! * A write is preceded by a barrier so that it cannot pass earlier accesses.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use crate::arch::sync;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::ptr;
+
+/// A block of 32-bit device registers.
+#[derive(Copy, Clone)]
+pub struct Mmio {
+  base: usize,
+  barrier: fn(),
+}
+
+impl Mmio {
+  /// Construct a register block.
+  ///
+  /// # Parameters
+  ///
+  /// * `base` - The base virtual address of the registers.
+  ///
+  /// # Assumptions
+  ///
+  /// The range is mapped as device memory and the offsets used with the block
+  /// are 4-byte aligned and within the range.
+  pub const fn new(base: usize) -> Self {
+    Mmio {
+      base,
+      barrier: sync::dmb,
+    }
+  }
+
+  /// Construct a register block with a custom barrier.
+  ///
+  /// # Parameters
+  ///
+  /// * `base` - The base virtual address of the registers.
+  /// * `barrier` - The function called in place of a memory barrier.
+  ///
+  /// # Description
+  ///
+  /// Allows tests to back a register block with ordinary memory and observe
+  /// the barriers.
+  #[cfg(feature = "module_tests")]
+  pub const fn with_barrier(base: usize, barrier: fn()) -> Self {
+    Mmio { base, barrier }
+  }
+
+  /// Get the base virtual address of the registers.
+  pub const fn get_base(&self) -> usize {
+    self.base
+  }
+
+  /// Read a register.
+  ///
+  /// # Parameters
+  ///
+  /// * `offset` - The register offset.
+  ///
+  /// # Returns
+  ///
+  /// The value of the register.
+  pub fn read32(&self, offset: usize) -> u32 {
+    let val = unsafe { ptr::read_volatile((self.base + offset) as *const u32) };
+    (self.barrier)();
+    val
+  }
+
+  /// Write to a register.
+  ///
+  /// # Parameters
+  ///
+  /// * `offset` - The register offset.
+  /// * `val` - The value to write.
+  pub fn write32(&self, offset: usize, val: u32) {
+    (self.barrier)();
+    unsafe { ptr::write_volatile((self.base + offset) as *mut u32, val) };
+  }
+
+  /// Modify bits in a register.
+  ///
+  /// # Parameters
+  ///
+  /// * `offset` - The register offset.
+  /// * `mask` - The bits to modify.
+  /// * `val` - The new value of the bits. Bits outside of the mask are ignored.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: The read and write are separate accesses. The caller must prevent
+  ///         concurrent modifications of the register.
+  pub fn modify32(&self, offset: usize, mask: u32, val: u32) {
+    let old = self.read32(offset);
+    self.write32(offset, (old & !mask) | (val & mask));
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
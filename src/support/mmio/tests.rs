@@ -0,0 +1,123 @@
+//! Memory-Mapped I/O Register Tests
+
+use super::Mmio;
+use crate::debug_print;
+use crate::{check_eq, execute_test, test};
+
+/// Number of registers in the backing buffer.
+const REGISTER_COUNT: usize = 4;
+
+/// Initial register values for the read and write test.
+const TEST_REGISTERS: [u32; REGISTER_COUNT] = [0x1111_1111, 0x2222_2222, 0x3333_3333, 0x4444_4444];
+
+/// Value written by the read and write test.
+const TEST_VALUE: u32 = 0xdead_beef;
+
+/// Initial register value for the modify test.
+const TEST_MODIFY_INITIAL: u32 = 0xffff_0000;
+
+/// Number of times `count_barrier()` has run.
+static mut BARRIER_COUNT: usize = 0;
+
+/// Run MMIO register tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_read_write);
+  execute_test!(context, test_modify);
+  execute_test!(context, test_barriers);
+}
+
+/// Count the number of barriers.
+fn count_barrier() {
+  unsafe { BARRIER_COUNT += 1 };
+}
+
+/// Construct a register block backed by a buffer.
+///
+/// # Parameters
+///
+/// * `regs` - The backing buffer.
+fn make_block(regs: &mut [u32; REGISTER_COUNT]) -> Mmio {
+  Mmio::with_barrier(regs.as_mut_ptr() as usize, count_barrier)
+}
+
+/// Test reading and writing registers.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Offsets are in bytes, and a write must only change the register at its
+/// offset.
+fn test_read_write(context: &mut test::TestContext) {
+  let mut regs = TEST_REGISTERS;
+  let block = make_block(&mut regs);
+
+  check_eq!(context, block.read32(0), TEST_REGISTERS[0]);
+  check_eq!(context, block.read32(0xc), TEST_REGISTERS[3]);
+
+  block.write32(0x8, TEST_VALUE);
+  check_eq!(context, block.read32(0x8), TEST_VALUE);
+  check_eq!(context, block.read32(0x4), TEST_REGISTERS[1]);
+  check_eq!(context, block.read32(0xc), TEST_REGISTERS[3]);
+
+  check_eq!(context, regs[2], TEST_VALUE);
+}
+
+/// Test modifying bits in a register.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Only the masked bits may change, and bits in the value outside of the mask
+/// must be ignored.
+fn test_modify(context: &mut test::TestContext) {
+  let mut regs = [TEST_MODIFY_INITIAL, 0, 0, 0];
+  let block = make_block(&mut regs);
+
+  block.modify32(0, 0x0000_ff00, 0x1234_5678);
+  let val = block.read32(0);
+  check_eq!(context, val, TEST_MODIFY_INITIAL | 0x5600);
+
+  block.modify32(0, 0xf000_0000, 0);
+  let val = block.read32(0);
+  check_eq!(context, val, (TEST_MODIFY_INITIAL & 0x0fff_ffff) | 0x5600);
+
+  block.modify32(0, 0, u32::MAX);
+  let val = block.read32(0);
+  check_eq!(context, val, (TEST_MODIFY_INITIAL & 0x0fff_ffff) | 0x5600);
+  check_eq!(context, block.read32(0x4), 0);
+}
+
+/// Test the barriers issued by each access.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Reads and writes each issue one barrier. A modification is a read followed
+/// by a write, so it issues two.
+fn test_barriers(context: &mut test::TestContext) {
+  let mut regs = [0; REGISTER_COUNT];
+  let block = make_block(&mut regs);
+
+  unsafe { BARRIER_COUNT = 0 };
+  _ = block.read32(0);
+  check_eq!(context, unsafe { BARRIER_COUNT }, 1);
+
+  block.write32(0, 1);
+  check_eq!(context, unsafe { BARRIER_COUNT }, 2);
+
+  block.modify32(0, 1, 0);
+  check_eq!(context, unsafe { BARRIER_COUNT }, 4);
+}
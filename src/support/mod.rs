@@ -1,10 +1,15 @@
 //! Support Module
 
+pub mod addr;
+pub mod bitfield;
 pub mod bits;
 pub mod debug;
 pub mod dtb;
 pub mod hash;
 pub mod hash_map;
+pub mod mmio;
 pub mod print;
+#[cfg(feature = "module_tests")]
+pub mod rand;
 pub mod range;
 pub mod range_set;
@@ -0,0 +1,105 @@
+//! Pseudo-Random Number Generation
+//!
+//! The generators are deterministic and NOT suitable for cryptographic use.
+//! They are only intended for randomized tests, where a constant seed makes a
+//! failing sequence reproducible, so the module is only built with the
+//! `module_tests` feature.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// Xorshift64 generator.
+///
+/// https://www.jstatsoft.org/article/view/v008i14
+pub struct Xorshift64 {
+  state: u64,
+}
+
+impl Xorshift64 {
+  /// Xorshift cannot leave the all-zero state, so a zero seed is replaced.
+  const ZERO_SEED_REPLACEMENT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+  /// Construct a new generator.
+  ///
+  /// # Parameters
+  ///
+  /// * `seed` - The initial state. A seed of 0 is replaced with a non-zero
+  ///   constant.
+  pub const fn new(seed: u64) -> Self {
+    Xorshift64 {
+      state: if seed == 0 {
+        Self::ZERO_SEED_REPLACEMENT
+      } else {
+        seed
+      },
+    }
+  }
+
+  /// Get the next value in the sequence.
+  pub const fn next_u64(&mut self) -> u64 {
+    let mut x = self.state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    self.state = x;
+    x
+  }
+
+  /// Get the next value in the sequence reduced to a range.
+  ///
+  /// # Parameters
+  ///
+  /// * `bound` - The exclusive upper bound.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: The value is reduced with a modulo, so it is slightly biased
+  ///         toward lower values if `bound` is not a power of 2.
+  ///
+  /// # Returns
+  ///
+  /// A value in the range [0, `bound`), or 0 if `bound` is 0.
+  pub const fn next_below(&mut self, bound: usize) -> usize {
+    if bound == 0 {
+      return 0;
+    }
+
+    (self.next_u64() % bound as u64) as usize
+  }
+
+  /// Get the next value in the sequence reduced to an inclusive range.
+  ///
+  /// # Parameters
+  ///
+  /// * `lo` - The inclusive lower bound.
+  /// * `hi` - The inclusive upper bound.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: Panics if `lo` is greater than `hi`. See `next_below()` for the
+  ///         bias toward lower values.
+  ///
+  /// # Returns
+  ///
+  /// A value in the range [`lo`, `hi`].
+  pub const fn next_range(&mut self, lo: u64, hi: u64) -> u64 {
+    assert!(lo <= hi);
+
+    let value = self.next_u64();
+
+    // The span of [0, u64::MAX] does not fit in a u64, but every value is in
+    // range.
+    match (hi - lo).checked_add(1) {
+      Some(span) => lo + value % span,
+      None => value,
+    }
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
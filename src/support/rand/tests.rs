@@ -0,0 +1,151 @@
+//! Pseudo-Random Number Generation Tests
+
+use super::Xorshift64;
+use crate::debug_print;
+use crate::{check_eq, check_gteq, check_lt, check_lteq, check_neq, execute_test, test};
+
+/// Run the pseudo-random number generation tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_known_sequence);
+  execute_test!(context, test_reproducible);
+  execute_test!(context, test_zero_seed);
+  execute_test!(context, test_next_below);
+  execute_test!(context, test_next_range);
+}
+
+/// Test the sequence generated from a seed of 1.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first value is also generated at compile time.
+fn test_known_sequence(context: &mut test::TestContext) {
+  const EXPECTED: [u64; 3] = [0x4082_2041, 0x1000_4106_0c01_1441, 0x9b1e_842f_6e86_2629];
+  const FIRST: u64 = Xorshift64::new(1).next_u64();
+
+  let mut rng = Xorshift64::new(1);
+
+  for exp in EXPECTED {
+    check_eq!(context, rng.next_u64(), exp);
+  }
+
+  check_eq!(context, FIRST, EXPECTED[0]);
+}
+
+/// Test that generators with the same seed produce the same sequence.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_reproducible(context: &mut test::TestContext) {
+  const SEED: u64 = 0xfeed_face_cafe_beef;
+
+  let mut first = Xorshift64::new(SEED);
+  let mut second = Xorshift64::new(SEED);
+  let mut other = Xorshift64::new(SEED + 1);
+  let mut differs = false;
+
+  for _ in 0..64 {
+    let value = first.next_u64();
+    check_eq!(context, value, second.next_u64());
+    differs |= value != other.next_u64();
+  }
+
+  check_eq!(context, differs, true);
+}
+
+/// Test that a zero seed does not leave the generator stuck at zero.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_zero_seed(context: &mut test::TestContext) {
+  let mut rng = Xorshift64::new(0);
+  let mut expected = Xorshift64::new(Xorshift64::ZERO_SEED_REPLACEMENT);
+
+  for _ in 0..16 {
+    let value = rng.next_u64();
+    check_neq!(context, value, 0);
+    check_eq!(context, value, expected.next_u64());
+  }
+}
+
+/// Test reducing values to a range.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Every value in a small range should appear within a reasonable number of
+/// draws, and no value may reach the bound. A bound of 0 always yields 0.
+fn test_next_below(context: &mut test::TestContext) {
+  const BOUND: usize = 7;
+
+  let mut rng = Xorshift64::new(0x1234_5678);
+  let mut seen = [false; BOUND];
+
+  for _ in 0..256 {
+    let value = rng.next_below(BOUND);
+    check_lt!(context, value, BOUND);
+
+    if value < BOUND {
+      seen[value] = true;
+    }
+  }
+
+  for value_seen in seen {
+    check_eq!(context, value_seen, true);
+  }
+
+  check_eq!(context, rng.next_below(0), 0);
+}
+
+/// Test reducing values to an inclusive range.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Values must stay within the bounds, including a range of a single value and
+/// the full range of a u64. Both bounds of a small range should appear within
+/// a reasonable number of draws.
+fn test_next_range(context: &mut test::TestContext) {
+  const LO: u64 = 100;
+  const HI: u64 = 103;
+
+  let mut rng = Xorshift64::new(0x8765_4321);
+  let mut lo_seen = false;
+  let mut hi_seen = false;
+
+  for _ in 0..256 {
+    let value = rng.next_range(LO, HI);
+    check_gteq!(context, value, LO);
+    check_lteq!(context, value, HI);
+    lo_seen |= value == LO;
+    hi_seen |= value == HI;
+  }
+
+  check_eq!(context, lo_seen, true);
+  check_eq!(context, hi_seen, true);
+
+  for _ in 0..16 {
+    check_eq!(context, rng.next_range(HI, HI), HI);
+    check_eq!(context, rng.next_range(0, 0), 0);
+    check_eq!(context, rng.next_range(u64::MAX, u64::MAX), u64::MAX);
+  }
+
+  let mut full = Xorshift64::new(0x8765_4321);
+  let mut expected = Xorshift64::new(0x8765_4321);
+  check_eq!(context, full.next_range(0, u64::MAX), expected.next_u64());
+}
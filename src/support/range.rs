@@ -33,6 +33,16 @@ impl<TagType> Range<TagType>
 where
   TagType: Copy,
 {
+  /// Check if the range is empty.
+  ///
+  /// # Description
+  ///
+  /// Empty ranges cannot be compared, excluded, or split. Callers should skip
+  /// empty ranges before using them.
+  pub fn is_empty(&self) -> bool {
+    self.size == 0
+  }
+
   /// Compare two ranges.
   ///
   /// # Parameters
@@ -41,9 +51,9 @@ where
   ///
   /// # Returns
   ///
-  /// A range ordering or None if the either range is invalid.
+  /// A range ordering or None if either range is empty.
   pub fn cmp(&self, rhs: &Self) -> Option<RangeOrdering> {
-    if self.size == 0 || rhs.size == 0 {
+    if self.is_empty() || rhs.is_empty() {
       return None;
     }
 
@@ -81,6 +91,20 @@ where
     }
   }
 
+  /// Check if two ranges overlap.
+  ///
+  /// # Parameters
+  ///
+  /// * `rhs` - The range to check against.
+  ///
+  /// # Returns
+  ///
+  /// True if the ranges share at least one value, false if they are disjoint
+  /// or either range is invalid.
+  pub fn overlaps(&self, rhs: &Self) -> bool {
+    !matches!(self.cmp(rhs), None | Some(RangeOrdering::Less) | Some(RangeOrdering::Greater))
+  }
+
   /// Splits a range using an exclusion range.
   ///
   /// # Parameters
@@ -204,7 +228,7 @@ where
   ///
   /// A tuple with the resulting range(s) of the split. See description.
   pub fn split(&self, at: usize) -> Result<(Option<Self>, Option<Self>), ()> {
-    if self.size == 0 {
+    if self.is_empty() {
       return Err(());
     }
 
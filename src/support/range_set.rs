@@ -1,6 +1,20 @@
 //! Range Set Utilities
 
+#[cfg(feature = "module_tests")]
+mod tests;
+
 use super::range::{Range, RangeOrdering};
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// Error value for RangeSet operations.
+#[derive(Debug)]
+pub enum RangeSetError {
+  /// A range in the set could not be compared with the operand range.
+  InvalidRange,
+  /// The set does not have room for the ranges produced by the operation.
+  SetFull,
+}
 
 /// Fixed-size, ordered set of Ranges.
 #[derive(Copy, Clone)]
@@ -10,6 +24,7 @@ where
 {
   ranges: [Range<TagType>; SET_SIZE],
   count: usize,
+  dropped: usize,
 }
 
 impl<const SET_SIZE: usize, TagType> RangeSet<SET_SIZE, TagType>
@@ -25,12 +40,14 @@ where
         size: 0,
       }; SET_SIZE],
       count: 0,
+      dropped: 0,
     }
   }
 
-  /// Clear the range set.
+  /// Clear the range set and the dropped range count.
   pub fn clear(&mut self) {
     self.count = 0;
+    self.dropped = 0;
   }
 
   /// Check if the set is empty.
@@ -43,11 +60,47 @@ where
     self.count
   }
 
+  /// Get the maximum number of ranges the set can hold.
+  pub fn capacity(&self) -> usize {
+    SET_SIZE
+  }
+
+  /// Check if the set is full.
+  pub fn is_full(&self) -> bool {
+    self.count >= SET_SIZE
+  }
+
+  /// Get the number of ranges that could not be inserted because the set was
+  /// full.
+  pub fn get_dropped_count(&self) -> usize {
+    self.dropped
+  }
+
   /// Access the ranges.
   pub fn get_ranges(&self) -> &[Range<TagType>] {
     &self.ranges[..self.count]
   }
 
+  /// Get the total size covered by the ranges in the set.
+  ///
+  /// # Description
+  ///
+  /// The sum saturates at `usize::MAX` rather than overflowing. Ranges covering
+  /// nearly the entire address space would otherwise overflow a simple sum.
+  ///
+  ///   NOTE: The total does not account for overlap. Call `trim_ranges()` first
+  ///         if the set may contain overlapping ranges.
+  ///
+  /// # Returns
+  ///
+  /// The sum of the range sizes.
+  pub fn total_size(&self) -> usize {
+    self
+      .get_ranges()
+      .iter()
+      .fold(0, |total, range| total.saturating_add(range.size))
+  }
+
   /// Insert a new range in to the set ordered by base.
   ///
   /// # Parameters
@@ -59,15 +112,20 @@ where
   /// Ranges with the same base are ordered from first to last inserted. Ranges
   /// with a size of zero or a size that would overflow are ignored.
   ///
+  /// If the set is full, the range is counted as dropped so that callers that
+  /// do not check the return value can still detect the loss. See
+  /// `get_dropped_count()`.
+  ///
   /// # Returns
   ///
   /// True if able to insert the new range, false otherwise.
   pub fn insert_range(&mut self, range: Range<TagType>) -> bool {
-    if self.count >= SET_SIZE {
+    if self.is_full() {
+      self.dropped += 1;
       return false;
     }
 
-    if range.size == 0 || (usize::MAX - range.size) + 1 < range.base {
+    if !Self::is_insertable(&range) {
       return false;
     }
 
@@ -92,13 +150,47 @@ where
   /// # Parameters
   ///
   /// * `excl` - The range to exclude.
-  pub fn exclude_range(&mut self, excl: &Range<TagType>) {
+  ///
+  /// # Description
+  ///
+  /// Excluding a range from the middle of a range in the set splits that range
+  /// in two. An exclusion splits each range in the set at most once, so the
+  /// worst case is that the number of ranges doubles. If the set does not have
+  /// overlapping ranges, the exclusion splits at most one range and the set
+  /// grows by at most one range.
+  ///
+  /// The number of splits is counted before modifying the set. If the set does
+  /// not have room for the new ranges, the set is left unmodified.
+  ///
+  /// Excluding an empty range does not modify the set.
+  ///
+  /// # Returns
+  ///
+  /// Ok if the range was excluded, or an error if the set is unmodified.
+  pub fn exclude_range(&mut self, excl: &Range<TagType>) -> Result<(), RangeSetError> {
+    if excl.is_empty() {
+      return Ok(());
+    }
+
+    let mut splits = 0;
+
+    for range in self.get_ranges() {
+      let split = range.exclude(excl).or(Err(RangeSetError::InvalidRange))?;
+
+      if split.0.is_some() && split.1.is_some() {
+        splits += 1;
+      }
+    }
+
+    if splits > SET_SIZE - self.count {
+      return Err(RangeSetError::SetFull);
+    }
+
     let mut i = 0usize;
 
     while i < self.count {
-      let Ok(split) = self.ranges[i].exclude(excl) else {
-        return;
-      };
+      // The ranges were checked above.
+      let split = self.ranges[i].exclude(excl).unwrap();
 
       // If the first element is valid, the current range can simply be
       // replaced.
@@ -112,13 +204,11 @@ where
       if let Some(b) = split.1 {
         if split.0.is_none() {
           self.ranges[i] = b;
-        } else if self.count < SET_SIZE {
+        } else {
           self.ranges.copy_within(i..self.count, i + 1);
           self.ranges[i + 1] = b;
           self.count += 1;
           i += 1;
-        } else {
-          panic!("Could not split range; set is full.");
         }
       }
 
@@ -134,6 +224,8 @@ where
     }
 
     self.trim_empty_ranges();
+
+    Ok(())
   }
 
   /// Combines ranges as necessary to ensure ranges do not overlap and removes
@@ -143,12 +235,26 @@ where
     self.trim_empty_ranges();
   }
 
+  /// Check if a range may be inserted in to a set.
+  ///
+  /// # Parameters
+  ///
+  /// * `range` - The range to check.
+  ///
+  /// # Returns
+  ///
+  /// False if the range has a size of zero or a size that would overflow, true
+  /// otherwise.
+  fn is_insertable(range: &Range<TagType>) -> bool {
+    !range.is_empty() && (usize::MAX - range.size) + 1 >= range.base
+  }
+
   /// Removes empty ranges from the set.
   fn trim_empty_ranges(&mut self) {
     let mut i = 0usize;
 
     while i < self.count {
-      if self.ranges[i].size > 0 {
+      if !self.ranges[i].is_empty() {
         i += 1;
         continue;
       }
@@ -167,17 +273,20 @@ where
     let mut i = 0usize;
 
     while i < self.count - 1 {
-      // Just unwrap the comparison. The interface ensures the ranges within the
-      // set are valid.
+      // Just unwrap the comparison. The interface never inserts empty ranges
+      // and removes any empty ranges left by an exclusion, so the ranges within
+      // the set are always comparable.
       match self.ranges[i].cmp(&self.ranges[i + 1]).unwrap() {
         RangeOrdering::Equal | RangeOrdering::Superset => {
           // This range contains the next range, remove the next range.
           self.ranges.copy_within((i + 2)..self.count, i + 1);
+          self.count -= 1;
         }
 
         RangeOrdering::Subset => {
           // The next range contains this range, remove this range.
           self.ranges.copy_within((i + 1)..self.count, i);
+          self.count -= 1;
         }
 
         RangeOrdering::LessEqual | RangeOrdering::GreaterEqual => {
@@ -187,6 +296,7 @@ where
           self.ranges[i].size =
             (self.ranges[i + 1].base + self.ranges[i + 1].size) - self.ranges[i].base;
           self.ranges.copy_within((i + 2)..self.count, i + 1);
+          self.count -= 1;
         }
 
         // No overlap, move ahead.
@@ -195,3 +305,107 @@ where
     }
   }
 }
+
+impl<const SET_SIZE: usize, TagType> RangeSet<SET_SIZE, TagType>
+where
+  TagType: Copy + PartialEq,
+{
+  /// Insert a new range in to the set, merging it with ranges that have the
+  /// same tag.
+  ///
+  /// # Parameters
+  ///
+  /// * `range` - The new range to add to the set.
+  ///
+  /// # Description
+  ///
+  /// The new range absorbs every range in the set with the same tag that
+  /// overlaps it or is adjacent to it, and the merged range is inserted ordered
+  /// by base. A merged range may in turn touch further ranges, so merging
+  /// repeats until no more ranges touch the merged range. Ranges with different
+  /// tags are never merged.
+  ///
+  /// Ranges with a size of zero or a size that would overflow are ignored. If
+  /// the set is full and the range does not merge with an existing range, the
+  /// range is counted as dropped. See `insert_range()`.
+  ///
+  /// # Returns
+  ///
+  /// True if able to insert or merge the new range, false otherwise.
+  pub fn insert_range_coalescing(&mut self, range: Range<TagType>) -> bool {
+    if !Self::is_insertable(&range) {
+      return false;
+    }
+
+    let mut merged = range;
+
+    while let Some(i) = self
+      .get_ranges()
+      .iter()
+      .position(|other| Self::touches(&merged, other))
+    {
+      merged = Self::union(&merged, &self.ranges[i]);
+      self.ranges.copy_within((i + 1)..self.count, i);
+      self.count -= 1;
+    }
+
+    self.insert_range(merged)
+  }
+
+  /// Check if two ranges have the same tag and overlap or are adjacent.
+  ///
+  /// # Parameters
+  ///
+  /// * `a` - The first range.
+  /// * `b` - The second range.
+  ///
+  /// # Description
+  ///
+  /// The ranges are compared by their last addresses so that a range ending at
+  /// the top of the address space does not overflow. Ranges that would merge in
+  /// to a range covering the entire address space are not merged since the
+  /// size would overflow.
+  ///
+  /// # Returns
+  ///
+  /// True if the ranges may be merged, false otherwise.
+  fn touches(a: &Range<TagType>, b: &Range<TagType>) -> bool {
+    let a_last = a.base + (a.size - 1);
+    let b_last = b.base + (b.size - 1);
+
+    a.tag == b.tag
+      && b.base <= a_last.saturating_add(1)
+      && a.base <= b_last.saturating_add(1)
+      && a_last.max(b_last) - a.base.min(b.base) < usize::MAX
+  }
+
+  /// Combine two touching ranges.
+  ///
+  /// # Parameters
+  ///
+  /// * `a` - The first range.
+  /// * `b` - The second range.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes the ranges touch. See `touches()`.
+  ///
+  /// # Returns
+  ///
+  /// A range with the first range's tag covering both ranges.
+  fn union(a: &Range<TagType>, b: &Range<TagType>) -> Range<TagType> {
+    let base = a.base.min(b.base);
+    let last = (a.base + (a.size - 1)).max(b.base + (b.size - 1));
+
+    Range {
+      tag: a.tag,
+      base,
+      size: (last - base) + 1,
+    }
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,510 @@
+//! Range Set Tests
+
+use super::{RangeSet, RangeSetError};
+use crate::debug_print;
+use crate::support::range::Range;
+use crate::{check_eq, execute_test, test};
+
+/// The test set size.
+const TEST_SET_SIZE: usize = 8;
+
+/// Run the RangeSet tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_total_size);
+  execute_test!(context, test_total_size_saturation);
+  execute_test!(context, test_exclude_split);
+  execute_test!(context, test_exclude_full_set);
+  execute_test!(context, test_exclude_nearly_full_set);
+  execute_test!(context, test_exclude_invalid_range);
+  execute_test!(context, test_dropped_count);
+  execute_test!(context, test_empty_ranges);
+  execute_test!(context, test_trim_overlapping);
+  execute_test!(context, test_coalesce_adjacent);
+  execute_test!(context, test_coalesce_overlapping);
+  execute_test!(context, test_coalesce_full_set);
+}
+
+/// Check that a set's ranges match the expected (base, size) pairs.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `set` - The set to check.
+/// * `expected` - The expected ranges.
+fn check_ranges(
+  context: &mut test::TestContext,
+  set: &RangeSet<TEST_SET_SIZE, ()>,
+  expected: &[(usize, usize)],
+) {
+  check_eq!(context, set.len(), expected.len());
+
+  for (range, (base, size)) in set.get_ranges().iter().zip(expected) {
+    check_eq!(context, range.base, *base);
+    check_eq!(context, range.size, *size);
+  }
+}
+
+/// Test the total size of a set of ranges.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_total_size(context: &mut test::TestContext) {
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+  check_eq!(context, set.total_size(), 0);
+
+  set.insert_range(Range {
+    tag: (),
+    base: 0x1000,
+    size: 0x1000,
+  });
+  check_eq!(context, set.total_size(), 0x1000);
+
+  set.insert_range(Range {
+    tag: (),
+    base: 0x10_0000,
+    size: 0x2_0000,
+  });
+  set.insert_range(Range {
+    tag: (),
+    base: 0x8000,
+    size: 0x3000,
+  });
+  check_eq!(context, set.len(), 3);
+  check_eq!(context, set.total_size(), 0x2_4000);
+
+  set.clear();
+  check_eq!(context, set.total_size(), 0);
+}
+
+/// Test that the total size saturates rather than overflowing.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Each range covers half of the address space; a naive sum of the two would
+/// wrap around to zero.
+fn test_total_size_saturation(context: &mut test::TestContext) {
+  let half = (usize::MAX >> 1) + 1;
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+
+  set.insert_range(Range {
+    tag: (),
+    base: 0,
+    size: half,
+  });
+  set.insert_range(Range {
+    tag: (),
+    base: half,
+    size: half,
+  });
+  check_eq!(context, set.len(), 2);
+  check_eq!(context, set.total_size(), usize::MAX);
+
+  set.insert_range(Range {
+    tag: (),
+    base: 0x1000,
+    size: 0x1000,
+  });
+  check_eq!(context, set.len(), 3);
+  check_eq!(context, set.total_size(), usize::MAX);
+}
+
+/// Test excluding a range from the middle of a range.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_exclude_split(context: &mut test::TestContext) {
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+
+  set.insert_range(Range {
+    tag: (),
+    base: 0x1_0000,
+    size: 0x1_0000,
+  });
+
+  let excl = Range {
+    tag: (),
+    base: 0x1_4000,
+    size: 0x1000,
+  };
+
+  check_eq!(context, set.exclude_range(&excl).is_ok(), true);
+  check_ranges(context, &set, &[(0x1_0000, 0x4000), (0x1_5000, 0xb000)]);
+}
+
+/// Test that splitting a range in a full set fails without modifying the set.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_exclude_full_set(context: &mut test::TestContext) {
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+  let mut expected = [(0usize, 0usize); TEST_SET_SIZE];
+
+  for (i, pair) in expected.iter_mut().enumerate() {
+    *pair = (i * 0x2_0000, 0x1_0000);
+
+    set.insert_range(Range {
+      tag: (),
+      base: pair.0,
+      size: pair.1,
+    });
+  }
+
+  // Trimming the end of a range does not require a split.
+  let excl = Range {
+    tag: (),
+    base: 0xc000,
+    size: 0x8000,
+  };
+
+  check_eq!(context, set.exclude_range(&excl).is_ok(), true);
+  expected[0].1 = 0xc000;
+  check_ranges(context, &set, &expected);
+
+  // Excluding from the middle of a range requires a split.
+  let excl = Range {
+    tag: (),
+    base: 0x2_4000,
+    size: 0x1000,
+  };
+
+  let full = matches!(set.exclude_range(&excl), Err(RangeSetError::SetFull));
+  check_eq!(context, full, true);
+  check_ranges(context, &set, &expected);
+}
+
+/// Test that a set with room for one split fails to exclude a range that
+/// splits two overlapping ranges.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first two ranges overlap, so the exclusion splits both and requires two
+/// free entries.
+fn test_exclude_nearly_full_set(context: &mut test::TestContext) {
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+  let mut expected = [(0usize, 0usize); TEST_SET_SIZE - 1];
+
+  for (i, pair) in expected.iter_mut().enumerate() {
+    *pair = match i {
+      0 => (0x0, 0x1_0000),
+      1 => (0x1000, 0x1_0000),
+      _ => (i * 0x2_0000, 0x1_0000),
+    };
+
+    set.insert_range(Range {
+      tag: (),
+      base: pair.0,
+      size: pair.1,
+    });
+  }
+
+  let excl = Range {
+    tag: (),
+    base: 0x4000,
+    size: 0x1000,
+  };
+
+  let full = matches!(set.exclude_range(&excl), Err(RangeSetError::SetFull));
+  check_eq!(context, full, true);
+  check_ranges(context, &set, &expected);
+
+  // A single split still fits.
+  let excl = Range {
+    tag: (),
+    base: 0x4_4000,
+    size: 0x1000,
+  };
+
+  check_eq!(context, set.exclude_range(&excl).is_ok(), true);
+  check_eq!(context, set.len(), TEST_SET_SIZE);
+}
+
+/// Test that an invalid exclusion range fails without modifying the set.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_exclude_invalid_range(context: &mut test::TestContext) {
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+
+  set.insert_range(Range {
+    tag: (),
+    base: 0x1_0000,
+    size: 0x1_0000,
+  });
+
+  let excl = Range {
+    tag: (),
+    base: 0x1_4000,
+    size: 0,
+  };
+
+  let invalid = matches!(set.exclude_range(&excl), Err(RangeSetError::InvalidRange));
+  check_eq!(context, invalid, true);
+  check_ranges(context, &set, &[(0x1_0000, 0x1_0000)]);
+}
+
+/// Test that inserting into a full set reports the dropped ranges.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_dropped_count(context: &mut test::TestContext) {
+  const EXTRA_RANGES: usize = 3;
+
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+  check_eq!(context, set.capacity(), TEST_SET_SIZE);
+
+  for i in 0..(TEST_SET_SIZE + EXTRA_RANGES) {
+    let inserted = set.insert_range(Range {
+      tag: (),
+      base: i * 0x2000,
+      size: 0x1000,
+    });
+
+    check_eq!(context, inserted, i < TEST_SET_SIZE);
+  }
+
+  check_eq!(context, set.is_full(), true);
+  check_eq!(context, set.len(), TEST_SET_SIZE);
+  check_eq!(context, set.get_dropped_count(), EXTRA_RANGES);
+
+  // Invalid ranges are not counted as dropped.
+  set.clear();
+  check_eq!(context, set.get_dropped_count(), 0);
+
+  set.insert_range(Range {
+    tag: (),
+    base: 0x1000,
+    size: 0,
+  });
+  check_eq!(context, set.get_dropped_count(), 0);
+}
+
+/// Test that empty ranges never enter a set.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Inserting an empty range must be ignored by both insertion methods, and
+/// excluding an empty range must leave the set unmodified rather than fail the
+/// comparison with the ranges in the set.
+fn test_empty_ranges(context: &mut test::TestContext) {
+  let empty = Range {
+    tag: (),
+    base: 0x2000,
+    size: 0,
+  };
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+
+  check_eq!(context, empty.is_empty(), true);
+
+  set.insert_range(Range {
+    tag: (),
+    base: 0x1000,
+    size: 0x2000,
+  });
+  check_eq!(context, set.insert_range(empty), false);
+  check_eq!(context, set.insert_range_coalescing(empty), false);
+  check_eq!(context, set.exclude_range(&empty).is_ok(), true);
+  check_ranges(context, &set, &[(0x1000, 0x2000)]);
+
+  set.trim_ranges();
+  check_ranges(context, &set, &[(0x1000, 0x2000)]);
+}
+
+/// Test combining overlapping ranges.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Duplicate ranges, a range containing the next range, and partially
+/// overlapping ranges must each reduce the set by one range. Adjacent ranges
+/// do not overlap and are left separate.
+fn test_trim_overlapping(context: &mut test::TestContext) {
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+
+  for (base, size) in [
+    (0x1000, 0x2000),
+    (0x1000, 0x2000),
+    (0x2000, 0x2000),
+    (0x8000, 0x1000),
+    (0x7800, 0x2000),
+    (0xa000, 0x1000),
+  ] {
+    set.insert_range(Range {
+      tag: (),
+      base,
+      size,
+    });
+  }
+
+  set.trim_ranges();
+  check_ranges(context, &set, &[(0x1000, 0x3000), (0x7800, 0x2000), (0xa000, 0x1000)]);
+}
+
+/// Test that many adjacent ranges collapse to a single range.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The ranges are inserted out of order, and there are more ranges than the
+/// set can hold. The final range fills a gap between two merged ranges, so it
+/// must join them. A range ending at the top of the address space must merge
+/// without overflowing.
+fn test_coalesce_adjacent(context: &mut test::TestContext) {
+  const RANGE_COUNT: usize = TEST_SET_SIZE * 4;
+
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+
+  for i in (0..RANGE_COUNT).rev().filter(|i| *i != RANGE_COUNT / 2) {
+    let inserted = set.insert_range_coalescing(Range {
+      tag: (),
+      base: 0x1_0000 + i * 0x1000,
+      size: 0x1000,
+    });
+    check_eq!(context, inserted, true);
+  }
+
+  check_ranges(
+    context,
+    &set,
+    &[
+      (0x1_0000, (RANGE_COUNT / 2) * 0x1000),
+      (0x1_0000 + (RANGE_COUNT / 2 + 1) * 0x1000, (RANGE_COUNT / 2 - 1) * 0x1000),
+    ],
+  );
+
+  set.insert_range_coalescing(Range {
+    tag: (),
+    base: 0x1_0000 + (RANGE_COUNT / 2) * 0x1000,
+    size: 0x1000,
+  });
+  check_ranges(context, &set, &[(0x1_0000, RANGE_COUNT * 0x1000)]);
+  check_eq!(context, set.get_dropped_count(), 0);
+
+  set.clear();
+  set.insert_range_coalescing(Range {
+    tag: (),
+    base: usize::MAX - 0xfff,
+    size: 0x1000,
+  });
+  set.insert_range_coalescing(Range {
+    tag: (),
+    base: usize::MAX - 0x1fff,
+    size: 0x1000,
+  });
+  check_ranges(context, &set, &[(usize::MAX - 0x1fff, 0x2000)]);
+}
+
+/// Test coalescing overlapping ranges and ranges with different tags.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A range overlapping two ranges with the same tag must merge with both, but
+/// ranges with a different tag must remain separate even if they overlap.
+fn test_coalesce_overlapping(context: &mut test::TestContext) {
+  let mut set = RangeSet::<TEST_SET_SIZE, u32>::new(0);
+
+  for (tag, base, size) in [
+    (1, 0x1000, 0x2000),
+    (1, 0x5000, 0x2000),
+    (2, 0x2000, 0x4000),
+  ] {
+    let inserted = set.insert_range_coalescing(Range { tag, base, size });
+    check_eq!(context, inserted, true);
+  }
+
+  check_eq!(context, set.len(), 3);
+
+  let merged = set.insert_range_coalescing(Range {
+    tag: 1,
+    base: 0x2800,
+    size: 0x3000,
+  });
+  check_eq!(context, merged, true);
+
+  let ranges = set.get_ranges();
+  check_eq!(context, ranges.len(), 2);
+  check_eq!(context, ranges[0].tag, 1);
+  check_eq!(context, ranges[0].base, 0x1000);
+  check_eq!(context, ranges[0].size, 0x6000);
+  check_eq!(context, ranges[1].tag, 2);
+  check_eq!(context, ranges[1].base, 0x2000);
+  check_eq!(context, ranges[1].size, 0x4000);
+}
+
+/// Test coalescing in to a full set.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A range that merges with an existing range must be accepted even when the
+/// set is full, but a range that does not merge must be dropped.
+fn test_coalesce_full_set(context: &mut test::TestContext) {
+  let mut set = RangeSet::<TEST_SET_SIZE, ()>::new(());
+
+  for i in 0..TEST_SET_SIZE {
+    set.insert_range_coalescing(Range {
+      tag: (),
+      base: i * 0x2000,
+      size: 0x1000,
+    });
+  }
+
+  check_eq!(context, set.is_full(), true);
+
+  let merged = set.insert_range_coalescing(Range {
+    tag: (),
+    base: 0x1000,
+    size: 0x1000,
+  });
+  check_eq!(context, merged, true);
+  check_eq!(context, set.len(), TEST_SET_SIZE - 1);
+  check_eq!(context, set.get_ranges()[0].size, 0x3000);
+
+  set.insert_range_coalescing(Range {
+    tag: (),
+    base: TEST_SET_SIZE * 0x2000 + 0x1000,
+    size: 0x1000,
+  });
+  check_eq!(context, set.is_full(), true);
+
+  let dropped = set.insert_range_coalescing(Range {
+    tag: (),
+    base: TEST_SET_SIZE * 0x4000,
+    size: 0x1000,
+  });
+  check_eq!(context, dropped, false);
+  check_eq!(context, set.get_dropped_count(), 1);
+}
@@ -0,0 +1,148 @@
+//! Core Synchronization Barrier
+//!
+//! A barrier holds a fixed number of participating cores at a known point
+//! until all of them arrive, then releases them together. Each release starts
+//! a new generation, so the same barrier may be reused for the next
+//! synchronization point.
+//!
+//!   NOTE: Barriers spin rather than block because they are used during boot,
+//!         before the scheduler is running. A barrier must not be used by more
+//!         than one task on the same core.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::SpinLock;
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::hint;
+
+/// Barrier state protected by the barrier's spin lock.
+struct BarrierState {
+  /// The number of participating cores.
+  count: usize,
+
+  /// The number of cores that have arrived in the current generation.
+  arrived: usize,
+
+  /// The current generation. Incremented each time the barrier releases the
+  /// participating cores.
+  generation: usize,
+}
+
+/// A reusable barrier for a fixed number of cores.
+pub struct Barrier {
+  state: SpinLock<BarrierState>,
+}
+
+impl Barrier {
+  /// Construct a new barrier.
+  ///
+  /// # Parameters
+  ///
+  /// * `count` - The number of participating cores.
+  pub const fn new(count: usize) -> Self {
+    assert!(count > 0);
+
+    Barrier {
+      state: SpinLock::new(BarrierState {
+        count,
+        arrived: 0,
+        generation: 0,
+      }),
+    }
+  }
+
+  /// Get the number of participating cores.
+  pub fn get_count(&self) -> usize {
+    self.state.lock().count
+  }
+
+  /// Set the number of participating cores.
+  ///
+  /// # Parameters
+  ///
+  /// * `count` - The number of participating cores, e.g. the online core
+  ///   count once the core configuration is known.
+  ///
+  /// # Assumptions
+  ///
+  /// Assumes no cores are waiting at the barrier.
+  pub fn set_count(&self, count: usize) {
+    assert!(count > 0);
+
+    let mut state = self.state.lock();
+    assert!(state.arrived == 0);
+    state.count = count;
+  }
+
+  /// Get the number of cores waiting at the barrier.
+  pub fn get_arrived_count(&self) -> usize {
+    self.state.lock().arrived
+  }
+
+  /// Spin until all participating cores arrive at the barrier.
+  ///
+  /// # Returns
+  ///
+  /// True for the last core to arrive, which released the other cores, and
+  /// false for the other cores.
+  pub fn wait(&self) -> bool {
+    let (generation, released) = self.arrive();
+
+    if released {
+      return true;
+    }
+
+    while !self.is_released(generation) {
+      hint::spin_loop();
+    }
+
+    false
+  }
+
+  /// Arrive at the barrier.
+  ///
+  /// # Description
+  ///
+  /// The last core to arrive resets the arrival count and starts the next
+  /// generation, releasing the cores waiting on the current generation.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the generation the core arrived in and whether the core
+  /// released the barrier.
+  fn arrive(&self) -> (usize, bool) {
+    let mut state = self.state.lock();
+    let generation = state.generation;
+
+    state.arrived += 1;
+
+    if state.arrived < state.count {
+      return (generation, false);
+    }
+
+    state.arrived = 0;
+    state.generation = generation.wrapping_add(1);
+
+    (generation, true)
+  }
+
+  /// Check if the barrier has released a generation.
+  ///
+  /// # Parameters
+  ///
+  /// * `generation` - The generation a core arrived in.
+  ///
+  /// # Returns
+  ///
+  /// True if the cores in the generation may proceed, false otherwise.
+  fn is_released(&self, generation: usize) -> bool {
+    self.state.lock().generation != generation
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
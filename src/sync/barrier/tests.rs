@@ -0,0 +1,84 @@
+//! Core Synchronization Barrier Tests
+
+use super::Barrier;
+use crate::debug_print;
+use crate::{check_eq, execute_test, test};
+
+/// Number of simulated cores.
+const TEST_CORES: usize = 4;
+
+/// Run barrier tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_arrival);
+  execute_test!(context, test_set_count);
+}
+
+/// Test that no simulated core proceeds until all cores arrive.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Each simulated core arrives in turn and checks whether it may proceed in
+/// place of spinning. None of the cores may proceed until the last core
+/// arrives, and only the last core releases the barrier. The barrier is then
+/// reused for a second generation, and the cores from the first generation
+/// must remain released.
+fn test_arrival(context: &mut test::TestContext) {
+  let barrier = Barrier::new(TEST_CORES);
+  let mut generations = [0; TEST_CORES];
+
+  for _ in 0..2 {
+    for core in 0..TEST_CORES - 1 {
+      let (generation, released) = barrier.arrive();
+      generations[core] = generation;
+      check_eq!(context, released, false);
+      check_eq!(context, barrier.get_arrived_count(), core + 1);
+
+      for waiting in generations.iter().take(core + 1) {
+        check_eq!(context, barrier.is_released(*waiting), false);
+      }
+    }
+
+    let (generation, released) = barrier.arrive();
+    generations[TEST_CORES - 1] = generation;
+    check_eq!(context, released, true);
+    check_eq!(context, barrier.get_arrived_count(), 0);
+
+    for waiting in generations {
+      check_eq!(context, waiting, generations[0]);
+      check_eq!(context, barrier.is_released(waiting), true);
+    }
+  }
+}
+
+/// Test resizing a barrier.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A barrier for a single core must release the core immediately.
+fn test_set_count(context: &mut test::TestContext) {
+  let barrier = Barrier::new(TEST_CORES);
+
+  barrier.set_count(1);
+  check_eq!(context, barrier.get_count(), 1);
+  check_eq!(context, barrier.wait(), true);
+  check_eq!(context, barrier.wait(), true);
+
+  barrier.set_count(2);
+  let (generation, released) = barrier.arrive();
+  check_eq!(context, released, false);
+  check_eq!(context, barrier.is_released(generation), false);
+  check_eq!(context, barrier.wait(), true);
+  check_eq!(context, barrier.is_released(generation), true);
+}
@@ -0,0 +1,120 @@
+//! One-Shot Completion Primitive
+//!
+//! A completion signals a one-time event, such as a secondary core reaching the
+//! kernel. Tasks that wait before the event block on the completion's wait
+//! queue. Completing the event wakes all of the waiting tasks and latches the
+//! completed state, so tasks that wait after the event return immediately.
+//!
+//!   NOTE: Only `complete()` and `is_completed()` may be used from interrupt
+//!         handlers.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::SpinLock;
+use crate::sched::{self, WaitQueue};
+use crate::task::{Task, TaskState};
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// The maximum number of tasks that may block on a completion at the same
+/// time. Additional tasks spin until the event completes.
+pub const MAX_COMPLETION_WAITERS: usize = 16;
+
+/// A one-shot event.
+pub struct Completion {
+  /// The completed state. The spin lock is held while parking a task so that a
+  /// task cannot park after the event completes.
+  completed: SpinLock<bool>,
+
+  /// The tasks blocked on the completion.
+  waiters: WaitQueue<'static, MAX_COMPLETION_WAITERS>,
+}
+
+impl Completion {
+  /// Construct a new, incomplete event.
+  pub const fn new() -> Self {
+    Completion {
+      completed: SpinLock::new(false),
+      waiters: WaitQueue::new(),
+    }
+  }
+
+  /// Block until the event completes.
+  ///
+  /// # Description
+  ///
+  /// Returns immediately if the event has already completed. Otherwise, the
+  /// current task blocks on the completion and yields its core until it is
+  /// woken. See `sched::yield_now()`.
+  pub fn wait(&self) {
+    while !self.check_or_block(Task::get_current_task_mut()) {
+      sched::yield_now();
+    }
+  }
+
+  /// Complete the event and wake all waiting tasks.
+  ///
+  /// # Description
+  ///
+  /// Completing the event more than once has no further effect other than
+  /// waking any tasks that could not be woken the first time because the run
+  /// queue was full.
+  pub fn complete(&self) {
+    let mut completed = self.completed.lock();
+    *completed = true;
+    _ = self.waiters.wake_all();
+  }
+
+  /// Check if the event has completed.
+  pub fn is_completed(&self) -> bool {
+    *self.completed.lock()
+  }
+
+  /// Get the number of tasks blocked on the completion.
+  pub fn get_waiting_count(&self) -> usize {
+    self.waiters.get_waiting_count()
+  }
+
+  /// Check if the event has completed or block a task on the completion.
+  ///
+  /// # Parameters
+  ///
+  /// * `task` - The waiting task.
+  ///
+  /// # Description
+  ///
+  /// A task that is still blocked on the completion keeps waiting. If the wait
+  /// queue is full, the task is not blocked and spins instead.
+  ///
+  /// If the event completed, but the task could not be woken because the run
+  /// queue had no space for it, the task is still parked. The task is removed
+  /// from the wait queue and marked ready before it returns so that it is not
+  /// woken again later. See `WaitQueue::cancel()`.
+  ///
+  /// # Returns
+  ///
+  /// True if the event has completed, false if the task must wait.
+  fn check_or_block(&self, task: &'static mut Task) -> bool {
+    let completed = self.completed.lock();
+
+    if *completed {
+      if task.state() == TaskState::Blocked {
+        _ = self.waiters.cancel(task.get_task_id());
+      }
+
+      return true;
+    }
+
+    if task.state() != TaskState::Blocked {
+      _ = self.waiters.block(task);
+    }
+
+    false
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
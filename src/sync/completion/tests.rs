@@ -0,0 +1,160 @@
+//! One-Shot Completion Tests
+
+use super::Completion;
+use crate::debug_print;
+use crate::sched;
+use crate::task::{Task, TaskContext, TaskState, affinity};
+use crate::{check_eq, check_optional, execute_test, test};
+use core::ptr;
+
+/// Number of test tasks. Test task N has task identifier N + 1.
+const TEST_TASK_COUNT: usize = 2;
+
+/// The test tasks. The tasks must be static to block on a completion.
+static mut TEST_TASKS: [Task; TEST_TASK_COUNT] = [
+  Task::new(1, TaskContext::default()),
+  Task::new(2, TaskContext::default()),
+];
+
+/// Run completion tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_wait_before_complete);
+  execute_test!(context, test_complete_before_wait);
+  execute_test!(context, test_check_while_parked);
+}
+
+/// Get a test task.
+///
+/// # Parameters
+///
+/// * `idx` - The test task index.
+fn get_test_task(idx: usize) -> &'static mut Task {
+  unsafe { &mut ptr::addr_of_mut!(TEST_TASKS).as_mut().unwrap()[idx] }
+}
+
+/// Reset the test tasks.
+///
+/// # Description
+///
+/// The tasks may only run on core 0 so that woken tasks are queued on core 0.
+fn reset_test_tasks() {
+  for idx in 0..TEST_TASK_COUNT {
+    let task = get_test_task(idx);
+    *task = Task::new(idx + 1, TaskContext::default());
+    task.set_affinity(Some(&affinity::single(0)));
+  }
+}
+
+/// Dequeue the next woken task from core 0 of the system run queue.
+///
+/// # Returns
+///
+/// The task identifier, or None if there are no ready tasks on core 0.
+fn dequeue_woken() -> Option<usize> {
+  sched::get_run_queue()
+    .lock()
+    .dequeue(0)
+    .map(|task| task.get_task_id())
+}
+
+/// Test tasks that wait before the event completes.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Both tasks must be parked as blocked tasks, and waiting again must not park
+/// a task twice. Completing the event must wake both tasks, and both must then
+/// see the completed event.
+fn test_wait_before_complete(context: &mut test::TestContext) {
+  let event = Completion::new();
+
+  reset_test_tasks();
+
+  check_eq!(context, event.check_or_block(get_test_task(0)), false);
+  check_eq!(context, event.check_or_block(get_test_task(1)), false);
+  check_eq!(context, event.check_or_block(get_test_task(1)), false);
+  check_eq!(context, event.get_waiting_count(), 2);
+  check_eq!(context, event.is_completed(), false);
+
+  for idx in 0..TEST_TASK_COUNT {
+    let matches = get_test_task(idx).state() == TaskState::Blocked;
+    check_eq!(context, matches, true);
+  }
+
+  event.complete();
+  check_eq!(context, event.is_completed(), true);
+  check_eq!(context, event.get_waiting_count(), 0);
+
+  for idx in 0..TEST_TASK_COUNT {
+    let matches = get_test_task(idx).state() == TaskState::Ready;
+    check_eq!(context, matches, true);
+    check_optional!(context, dequeue_woken(), idx + 1);
+    check_eq!(context, event.check_or_block(get_test_task(idx)), true);
+  }
+}
+
+/// Test tasks that wait after the event completes.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The completed state is latched, so the task must not block, and completing
+/// the event again must have no effect.
+fn test_complete_before_wait(context: &mut test::TestContext) {
+  let event = Completion::new();
+
+  reset_test_tasks();
+
+  event.complete();
+  check_eq!(context, event.check_or_block(get_test_task(0)), true);
+  check_eq!(context, event.get_waiting_count(), 0);
+  let matches = get_test_task(0).state() == TaskState::Ready;
+  check_eq!(context, matches, true);
+
+  event.complete();
+  check_eq!(context, event.is_completed(), true);
+  check_eq!(context, event.check_or_block(get_test_task(0)), true);
+  check_eq!(context, dequeue_woken().is_none(), true);
+}
+
+/// Test that a task still parked on a completed event leaves the wait queue
+/// when it sees the completed event.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The event is completed without waking the waiter, as if the run queue had
+/// no space for the waiter. The waiter must see the completed event, but it
+/// must no longer be parked or marked blocked, so that completing the event
+/// again cannot queue it.
+fn test_check_while_parked(context: &mut test::TestContext) {
+  let event = Completion::new();
+
+  reset_test_tasks();
+
+  check_eq!(context, event.check_or_block(get_test_task(0)), false);
+  check_eq!(context, event.get_waiting_count(), 1);
+
+  *event.completed.lock() = true;
+
+  check_eq!(context, event.check_or_block(get_test_task(0)), true);
+  check_eq!(context, event.get_waiting_count(), 0);
+  let matches = get_test_task(0).state() == TaskState::Ready;
+  check_eq!(context, matches, true);
+
+  event.complete();
+  check_eq!(context, dequeue_woken().is_none(), true);
+}
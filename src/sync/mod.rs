@@ -1,5 +1,22 @@
 //! Synchronization Primitives
 
+pub mod barrier;
+pub mod completion;
+pub mod mutex;
+pub mod semaphore;
 pub mod spin_lock;
 
+pub use barrier::*;
 pub use spin_lock::*;
+
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// Run the synchronization primitive tests.
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  barrier::run_tests(context);
+  completion::run_tests(context);
+  mutex::run_tests(context);
+  semaphore::run_tests(context);
+}
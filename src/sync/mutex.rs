@@ -0,0 +1,182 @@
+//! Blocking Mutex Primitive
+//!
+//! A mutex protects a wrapped object like a spin lock, but a task that finds
+//! the mutex locked blocks on the mutex's wait queue and yields its core rather
+//! than spinning. Unlocking the mutex wakes the next waiting task, which then
+//! tries to lock the mutex again.
+//!
+//!   NOTE: Mutexes must not be used from interrupt handlers.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::SpinLock;
+use crate::sched::{self, WaitQueue};
+use crate::task::{Task, TaskState};
+#[cfg(feature = "module_tests")]
+use crate::test;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut, Drop};
+
+/// The maximum number of tasks that may block on a mutex at the same time.
+/// Additional tasks spin until there is space.
+pub const MAX_MUTEX_WAITERS: usize = 16;
+
+/// Guard object for mutex ownership. A Mutex constructs a guard object when a
+/// task acquires the mutex. A task releases the mutex by dropping the guard
+/// object.
+pub struct MutexGuard<'lock, T> {
+  mutex: &'lock Mutex<T>,
+}
+
+impl<'lock, T> MutexGuard<'lock, T> {
+  /// Construct a guard object after acquiring a mutex.
+  ///
+  /// # Parameters
+  ///
+  /// * `mutex` - The acquired mutex.
+  fn new(mutex: &'lock Mutex<T>) -> Self {
+    MutexGuard { mutex }
+  }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+  /// Unlock on drop.
+  fn drop(&mut self) {
+    self.mutex.unlock();
+  }
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+  type Target = T;
+
+  /// Obtain a reference to the protected object.
+  fn deref(&self) -> &Self::Target {
+    unsafe { self.mutex.obj.get().as_ref().unwrap() }
+  }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+  /// Obtain a mutable reference to the protected object.
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    unsafe { self.mutex.obj.get().as_mut().unwrap() }
+  }
+}
+
+/// A mutex protects a wrapped object. A guard object must be obtained using
+/// the lock method to access the protected object.
+pub struct Mutex<T> {
+  /// The protected object. UnsafeCell is used to allow interior mutability.
+  obj: UnsafeCell<T>,
+
+  /// Whether a task owns the mutex. The spin lock is only held long enough to
+  /// check or change ownership and to park or wake a task, so that a task
+  /// cannot park after the owner has already woken the waiting tasks.
+  locked: SpinLock<bool>,
+
+  /// The tasks blocked on the mutex.
+  waiters: WaitQueue<'static, MAX_MUTEX_WAITERS>,
+}
+
+impl<T> Mutex<T> {
+  /// Construct a new mutex to protect the specified object.
+  pub const fn new(obj: T) -> Self {
+    Mutex {
+      obj: UnsafeCell::new(obj),
+      locked: SpinLock::new(false),
+      waiters: WaitQueue::new(),
+    }
+  }
+
+  /// Block to acquire the mutex.
+  ///
+  /// # Description
+  ///
+  /// If the mutex is locked, the current task blocks on the mutex and yields
+  /// its core until it is woken. See `sched::yield_now()`.
+  ///
+  /// # Returns
+  ///
+  /// A guard object upon acquiring the mutex.
+  pub fn lock(&self) -> MutexGuard<'_, T> {
+    loop {
+      if let Some(guard) = self.lock_or_block(Task::get_current_task_mut()) {
+        return guard;
+      }
+
+      sched::yield_now();
+    }
+  }
+
+  /// Attempt to acquire the mutex without blocking.
+  ///
+  /// # Returns
+  ///
+  /// A guard object upon acquiring the mutex, or None if the mutex is already
+  /// acquired by another task.
+  pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+    let mut locked = self.locked.lock();
+
+    if *locked {
+      return None;
+    }
+
+    *locked = true;
+    Some(MutexGuard::new(self))
+  }
+
+  /// Get the number of tasks blocked on the mutex.
+  pub fn get_waiting_count(&self) -> usize {
+    self.waiters.get_waiting_count()
+  }
+
+  /// Acquire the mutex for a task or block the task on the mutex.
+  ///
+  /// # Parameters
+  ///
+  /// * `task` - The task acquiring the mutex.
+  ///
+  /// # Description
+  ///
+  /// A task that is still blocked on the mutex keeps waiting. If the wait
+  /// queue is full, the task is not blocked and spins instead.
+  ///
+  /// If the mutex was unlocked, but the task could not be woken because the
+  /// run queue had no space for it, the task is still parked. The task is
+  /// removed from the wait queue and marked ready before it acquires the
+  /// mutex so that it is not woken again later. See `WaitQueue::cancel()`.
+  ///
+  /// # Returns
+  ///
+  /// A guard object if the task acquired the mutex, or None if it must wait.
+  fn lock_or_block(&self, task: &'static mut Task) -> Option<MutexGuard<'_, T>> {
+    let mut locked = self.locked.lock();
+
+    if !*locked {
+      if task.state() == TaskState::Blocked {
+        _ = self.waiters.cancel(task.get_task_id());
+      }
+
+      *locked = true;
+      return Some(MutexGuard::new(self));
+    }
+
+    if task.state() != TaskState::Blocked {
+      _ = self.waiters.block(task);
+    }
+
+    None
+  }
+
+  /// Release the mutex and wake the next waiting task.
+  fn unlock(&self) {
+    let mut locked = self.locked.lock();
+    *locked = false;
+    _ = self.waiters.wake_one();
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
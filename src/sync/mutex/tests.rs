@@ -0,0 +1,161 @@
+//! Blocking Mutex Tests
+
+use super::Mutex;
+use crate::debug_print;
+use crate::sched;
+use crate::task::{Task, TaskContext, TaskState, affinity};
+use crate::{check_eq, check_none, check_optional, execute_test, mark_fail, test};
+use core::{mem, ptr};
+
+/// Identifiers of the task that owns the mutex and the task that waits for it.
+const OWNER_TASK_ID: usize = 1;
+const WAITER_TASK_ID: usize = 2;
+
+/// The test tasks. The tasks must be static to block on a mutex.
+static mut TEST_TASKS: [Task; 2] = [
+  Task::new(OWNER_TASK_ID, TaskContext::default()),
+  Task::new(WAITER_TASK_ID, TaskContext::default()),
+];
+
+/// Run mutex tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_try_lock);
+  execute_test!(context, test_contention);
+  execute_test!(context, test_lock_while_parked);
+}
+
+/// Get a test task.
+///
+/// # Parameters
+///
+/// * `idx` - The test task index.
+fn get_test_task(idx: usize) -> &'static mut Task {
+  unsafe { &mut ptr::addr_of_mut!(TEST_TASKS).as_mut().unwrap()[idx] }
+}
+
+/// Test locking an uncontended mutex.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Changes made through a guard must be visible to the next guard, and the
+/// mutex must be locked until the guard is dropped.
+fn test_try_lock(context: &mut test::TestContext) {
+  let mutex = Mutex::new(0);
+
+  let Some(mut guard) = mutex.try_lock() else {
+    mark_fail!(context, "Failed to lock an unlocked mutex.");
+    return;
+  };
+
+  *guard = 42;
+  check_none!(context, mutex.try_lock().map(|_| ()));
+  drop(guard);
+
+  check_optional!(context, mutex.try_lock().map(|guard| *guard), 42);
+}
+
+/// Test that a task blocks on a locked mutex and is woken on release.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The waiter must be parked on the mutex as a blocked task rather than left
+/// runnable to spin, and trying again while the mutex is locked must not park
+/// it twice. Releasing the mutex must return the waiter to the system run
+/// queue as a ready task, and the waiter must then acquire the mutex.
+///
+/// The waiter may only run on core 0 so that it is woken into core 0's queue.
+fn test_contention(context: &mut test::TestContext) {
+  let mutex = Mutex::new(0);
+
+  *get_test_task(0) = Task::new(OWNER_TASK_ID, TaskContext::default());
+  *get_test_task(1) = Task::new(WAITER_TASK_ID, TaskContext::default());
+  get_test_task(1).set_affinity(Some(&affinity::single(0)));
+
+  let Some(mut guard) = mutex.lock_or_block(get_test_task(0)) else {
+    mark_fail!(context, "Failed to lock an unlocked mutex.");
+    return;
+  };
+
+  *guard = 1;
+
+  check_none!(context, mutex.lock_or_block(get_test_task(1)).map(|_| ()));
+  let matches = get_test_task(1).state() == TaskState::Blocked;
+  check_eq!(context, matches, true);
+  check_eq!(context, mutex.get_waiting_count(), 1);
+
+  check_none!(context, mutex.lock_or_block(get_test_task(1)).map(|_| ()));
+  check_eq!(context, mutex.get_waiting_count(), 1);
+
+  drop(guard);
+  check_eq!(context, mutex.get_waiting_count(), 0);
+  let matches = get_test_task(1).state() == TaskState::Ready;
+  check_eq!(context, matches, true);
+
+  let woken = sched::get_run_queue()
+    .lock()
+    .dequeue(0)
+    .map(|task| task.get_task_id());
+  check_optional!(context, woken, WAITER_TASK_ID);
+
+  check_optional!(context, mutex.lock_or_block(get_test_task(1)).map(|guard| *guard), 1);
+}
+
+/// Test that a task still parked on an unlocked mutex leaves the wait queue
+/// when it acquires the mutex.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The mutex is unlocked without waking the waiter, as if the run queue had
+/// no space for the waiter. The waiter must still acquire the mutex, but it
+/// must no longer be parked or marked blocked, so that a later wake cannot
+/// queue it a second time.
+fn test_lock_while_parked(context: &mut test::TestContext) {
+  let mutex = Mutex::new(0);
+
+  *get_test_task(0) = Task::new(OWNER_TASK_ID, TaskContext::default());
+  *get_test_task(1) = Task::new(WAITER_TASK_ID, TaskContext::default());
+  get_test_task(1).set_affinity(Some(&affinity::single(0)));
+
+  let Some(guard) = mutex.lock_or_block(get_test_task(0)) else {
+    mark_fail!(context, "Failed to lock an unlocked mutex.");
+    return;
+  };
+
+  check_none!(context, mutex.lock_or_block(get_test_task(1)).map(|_| ()));
+  check_eq!(context, mutex.get_waiting_count(), 1);
+
+  mem::forget(guard);
+  *mutex.locked.lock() = false;
+
+  let Some(guard) = mutex.lock_or_block(get_test_task(1)) else {
+    mark_fail!(context, "Failed to lock an unlocked mutex.");
+    return;
+  };
+
+  check_eq!(context, mutex.get_waiting_count(), 0);
+  let matches = get_test_task(1).state() == TaskState::Ready;
+  check_eq!(context, matches, true);
+
+  drop(guard);
+  let woken = sched::get_run_queue()
+    .lock()
+    .dequeue(0)
+    .map(|task| task.get_task_id());
+  check_none!(context, woken);
+}
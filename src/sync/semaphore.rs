@@ -0,0 +1,136 @@
+//! Counting Semaphore Primitive
+//!
+//! A semaphore counts the units of a resource that are available, such as the
+//! buffers in a bounded pool. Acquiring a unit decrements the count, and a task
+//! that finds the count at zero blocks on the semaphore's wait queue until a
+//! unit is released. Releasing a unit wakes the next waiting task, which then
+//! tries to acquire a unit again.
+//!
+//!   NOTE: Only `try_acquire()` and `release()` may be used from interrupt
+//!         handlers.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::SpinLock;
+use crate::sched::{self, WaitQueue};
+use crate::task::{Task, TaskState};
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// The maximum number of tasks that may block on a semaphore at the same time.
+/// Additional tasks spin until there is space.
+pub const MAX_SEMAPHORE_WAITERS: usize = 16;
+
+/// A counting semaphore.
+pub struct Semaphore {
+  /// The number of available units. The spin lock is only held long enough to
+  /// check or change the count and to park or wake a task, so that a task
+  /// cannot park after a unit has been released.
+  count: SpinLock<usize>,
+
+  /// The tasks blocked on the semaphore.
+  waiters: WaitQueue<'static, MAX_SEMAPHORE_WAITERS>,
+}
+
+impl Semaphore {
+  /// Construct a new semaphore.
+  ///
+  /// # Parameters
+  ///
+  /// * `count` - The number of units initially available.
+  pub const fn new(count: usize) -> Self {
+    Semaphore {
+      count: SpinLock::new(count),
+      waiters: WaitQueue::new(),
+    }
+  }
+
+  /// Block to acquire a unit.
+  ///
+  /// # Description
+  ///
+  /// If no units are available, the current task blocks on the semaphore and
+  /// yields its core until it is woken. See `sched::yield_now()`.
+  pub fn acquire(&self) {
+    while !self.acquire_or_block(Task::get_current_task_mut()) {
+      sched::yield_now();
+    }
+  }
+
+  /// Attempt to acquire a unit without blocking.
+  ///
+  /// # Returns
+  ///
+  /// True if a unit was acquired, false if no units are available.
+  pub fn try_acquire(&self) -> bool {
+    let mut count = self.count.lock();
+
+    if *count == 0 {
+      return false;
+    }
+
+    *count -= 1;
+    true
+  }
+
+  /// Release a unit and wake the next waiting task.
+  pub fn release(&self) {
+    let mut count = self.count.lock();
+    *count += 1;
+    _ = self.waiters.wake_one();
+  }
+
+  /// Get the number of available units.
+  pub fn get_count(&self) -> usize {
+    *self.count.lock()
+  }
+
+  /// Get the number of tasks blocked on the semaphore.
+  pub fn get_waiting_count(&self) -> usize {
+    self.waiters.get_waiting_count()
+  }
+
+  /// Acquire a unit for a task or block the task on the semaphore.
+  ///
+  /// # Parameters
+  ///
+  /// * `task` - The task acquiring a unit.
+  ///
+  /// # Description
+  ///
+  /// A task that is still blocked on the semaphore keeps waiting. If the wait
+  /// queue is full, the task is not blocked and spins instead.
+  ///
+  /// If a unit was released, but the task could not be woken because the run
+  /// queue had no space for it, the task is still parked. The task is removed
+  /// from the wait queue and marked ready before it acquires the unit so that
+  /// it is not woken again later. See `WaitQueue::cancel()`.
+  ///
+  /// # Returns
+  ///
+  /// True if the task acquired a unit, false if it must wait.
+  fn acquire_or_block(&self, task: &'static mut Task) -> bool {
+    let mut count = self.count.lock();
+
+    if *count > 0 {
+      if task.state() == TaskState::Blocked {
+        _ = self.waiters.cancel(task.get_task_id());
+      }
+
+      *count -= 1;
+      return true;
+    }
+
+    if task.state() != TaskState::Blocked {
+      _ = self.waiters.block(task);
+    }
+
+    false
+  }
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,174 @@
+//! Counting Semaphore Tests
+
+use super::Semaphore;
+use crate::debug_print;
+use crate::sched;
+use crate::task::{Task, TaskContext, TaskState, affinity};
+use crate::{check_eq, check_optional, execute_test, test};
+use core::ptr;
+
+/// Number of test tasks. Test task N has task identifier N + 1.
+const TEST_TASK_COUNT: usize = 3;
+
+/// The test tasks. The tasks must be static to block on a semaphore.
+static mut TEST_TASKS: [Task; TEST_TASK_COUNT] = [
+  Task::new(1, TaskContext::default()),
+  Task::new(2, TaskContext::default()),
+  Task::new(3, TaskContext::default()),
+];
+
+/// Run semaphore tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_count);
+  execute_test!(context, test_blocking);
+  execute_test!(context, test_acquire_while_parked);
+}
+
+/// Get a test task.
+///
+/// # Parameters
+///
+/// * `idx` - The test task index.
+fn get_test_task(idx: usize) -> &'static mut Task {
+  unsafe { &mut ptr::addr_of_mut!(TEST_TASKS).as_mut().unwrap()[idx] }
+}
+
+/// Reset the test tasks.
+///
+/// # Description
+///
+/// The tasks may only run on core 0 so that woken tasks are queued on core 0.
+fn reset_test_tasks() {
+  for idx in 0..TEST_TASK_COUNT {
+    let task = get_test_task(idx);
+    *task = Task::new(idx + 1, TaskContext::default());
+    task.set_affinity(Some(&affinity::single(0)));
+  }
+}
+
+/// Dequeue the next woken task from core 0 of the system run queue.
+///
+/// # Returns
+///
+/// The task identifier, or None if there are no ready tasks on core 0.
+fn dequeue_woken() -> Option<usize> {
+  sched::get_run_queue()
+    .lock()
+    .dequeue(0)
+    .map(|task| task.get_task_id())
+}
+
+/// Test counting acquired and released units.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_count(context: &mut test::TestContext) {
+  const UNITS: usize = 3;
+
+  let sem = Semaphore::new(UNITS);
+
+  for remaining in (0..UNITS).rev() {
+    check_eq!(context, sem.try_acquire(), true);
+    check_eq!(context, sem.get_count(), remaining);
+  }
+
+  check_eq!(context, sem.try_acquire(), false);
+  check_eq!(context, sem.get_count(), 0);
+
+  sem.release();
+  sem.release();
+  check_eq!(context, sem.get_count(), 2);
+  check_eq!(context, sem.try_acquire(), true);
+  check_eq!(context, sem.get_count(), 1);
+
+  let empty = Semaphore::new(0);
+  check_eq!(context, empty.try_acquire(), false);
+}
+
+/// Test that tasks block on an empty semaphore and are woken on release.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The first task takes the only unit, so the other two tasks block. Each
+/// release must wake exactly one task, in the order the tasks blocked, and the
+/// task that is still blocked must not be queued to run. A task that is still
+/// blocked must not be parked twice.
+fn test_blocking(context: &mut test::TestContext) {
+  let sem = Semaphore::new(1);
+
+  reset_test_tasks();
+
+  check_eq!(context, sem.acquire_or_block(get_test_task(0)), true);
+  check_eq!(context, sem.acquire_or_block(get_test_task(1)), false);
+  check_eq!(context, sem.acquire_or_block(get_test_task(2)), false);
+  check_eq!(context, sem.acquire_or_block(get_test_task(2)), false);
+  check_eq!(context, sem.get_waiting_count(), 2);
+  let matches = get_test_task(1).state() == TaskState::Blocked;
+  check_eq!(context, matches, true);
+  let matches = get_test_task(2).state() == TaskState::Blocked;
+  check_eq!(context, matches, true);
+
+  sem.release();
+  check_eq!(context, sem.get_waiting_count(), 1);
+  let matches = get_test_task(1).state() == TaskState::Ready;
+  check_eq!(context, matches, true);
+  let matches = get_test_task(2).state() == TaskState::Blocked;
+  check_eq!(context, matches, true);
+  check_optional!(context, dequeue_woken(), 2);
+  check_eq!(context, dequeue_woken().is_none(), true);
+
+  check_eq!(context, sem.acquire_or_block(get_test_task(1)), true);
+  check_eq!(context, sem.get_count(), 0);
+
+  sem.release();
+  check_eq!(context, sem.get_waiting_count(), 0);
+  check_optional!(context, dequeue_woken(), 3);
+
+  check_eq!(context, sem.acquire_or_block(get_test_task(2)), true);
+  check_eq!(context, sem.get_count(), 0);
+
+  sem.release();
+  check_eq!(context, sem.get_count(), 1);
+}
+
+/// Test that a task still parked on a semaphore leaves the wait queue when it
+/// acquires a unit.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A unit is released without waking the waiter, as if the run queue had no
+/// space for the waiter. The waiter must still acquire the unit, but it must
+/// no longer be parked or marked blocked, so that a later wake cannot queue it
+/// a second time.
+fn test_acquire_while_parked(context: &mut test::TestContext) {
+  let sem = Semaphore::new(0);
+
+  reset_test_tasks();
+
+  check_eq!(context, sem.acquire_or_block(get_test_task(0)), false);
+  check_eq!(context, sem.get_waiting_count(), 1);
+
+  *sem.count.lock() += 1;
+
+  check_eq!(context, sem.acquire_or_block(get_test_task(0)), true);
+  check_eq!(context, sem.get_count(), 0);
+  check_eq!(context, sem.get_waiting_count(), 0);
+  let matches = get_test_task(0).state() == TaskState::Ready;
+  check_eq!(context, matches, true);
+
+  sem.release();
+  check_eq!(context, dequeue_woken().is_none(), true);
+}
@@ -0,0 +1,122 @@
+//! Task Affinity Masks
+//!
+//! Helpers for building affinity masks without manipulating bits directly.
+//! Core indices outside of `MAX_CORES` are ignored.
+
+#[cfg(feature = "module_tests")]
+mod tests;
+
+use super::AffinityMask;
+use crate::arch::cpu::MAX_CORES;
+#[cfg(feature = "module_tests")]
+use crate::test;
+
+/// Construct a mask that permits a single core.
+///
+/// # Parameters
+///
+/// * `core_idx` - The core index.
+///
+/// # Returns
+///
+/// The new mask.
+pub fn single(core_idx: usize) -> AffinityMask {
+  from_cores(&[core_idx])
+}
+
+/// Construct a mask that permits every core.
+///
+/// # Returns
+///
+/// The new mask.
+pub fn all() -> AffinityMask {
+  let mut mask = AffinityMask::new(MAX_CORES);
+
+  for core_idx in 0..MAX_CORES {
+    mask.set_bit(core_idx);
+  }
+
+  mask
+}
+
+/// Construct a mask that permits a list of cores.
+///
+/// # Parameters
+///
+/// * `cores` - The core indices.
+///
+/// # Returns
+///
+/// The new mask.
+pub fn from_cores(cores: &[usize]) -> AffinityMask {
+  let mut mask = AffinityMask::new(MAX_CORES);
+
+  for &core_idx in cores {
+    mask.set_bit(core_idx);
+  }
+
+  mask
+}
+
+/// Construct a mask that permits the cores permitted by either mask.
+///
+/// # Parameters
+///
+/// * `a` - The first mask.
+/// * `b` - The second mask.
+///
+/// # Returns
+///
+/// The new mask.
+pub fn union(a: &AffinityMask, b: &AffinityMask) -> AffinityMask {
+  let mut mask = AffinityMask::new(MAX_CORES);
+
+  for core_idx in 0..MAX_CORES {
+    if is_set(a, core_idx) || is_set(b, core_idx) {
+      mask.set_bit(core_idx);
+    }
+  }
+
+  mask
+}
+
+/// Construct a mask that permits the cores permitted by both masks.
+///
+/// # Parameters
+///
+/// * `a` - The first mask.
+/// * `b` - The second mask.
+///
+/// # Returns
+///
+/// The new mask.
+pub fn intersect(a: &AffinityMask, b: &AffinityMask) -> AffinityMask {
+  let mut mask = AffinityMask::new(MAX_CORES);
+
+  for core_idx in 0..MAX_CORES {
+    if is_set(a, core_idx) && is_set(b, core_idx) {
+      mask.set_bit(core_idx);
+    }
+  }
+
+  mask
+}
+
+/// Check if a mask permits a core.
+///
+/// # Parameters
+///
+/// * `mask` - The mask.
+/// * `core_idx` - The core index.
+///
+/// # Returns
+///
+/// True if the core's bit is set, false if it is clear or outside the mask.
+fn is_set(mask: &AffinityMask, core_idx: usize) -> bool {
+  mask.test_bit(core_idx).unwrap_or(false)
+}
+
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+}
@@ -0,0 +1,97 @@
+//! Task Affinity Mask Tests
+
+use super::{all, from_cores, intersect, single, union};
+use crate::arch::cpu::MAX_CORES;
+use crate::debug_print;
+use crate::task::AffinityMask;
+use crate::{check_eq, execute_test, test};
+
+/// Run affinity mask tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_single);
+  execute_test!(context, test_all);
+  execute_test!(context, test_from_cores);
+  execute_test!(context, test_set_operations);
+}
+
+/// Check that a mask permits exactly the expected cores.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+/// * `mask` - The mask to check.
+/// * `expected` - The cores the mask must permit.
+fn check_cores(context: &mut test::TestContext, mask: &AffinityMask, expected: &[usize]) {
+  check_eq!(context, mask.len(), MAX_CORES);
+  check_eq!(context, mask.ones(), expected.len());
+
+  for core_idx in 0..MAX_CORES {
+    let permitted = mask.test_bit(core_idx).unwrap_or(false);
+    check_eq!(context, permitted, expected.contains(&core_idx));
+  }
+}
+
+/// Test constructing a single-core mask.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_single(context: &mut test::TestContext) {
+  check_cores(context, &single(0), &[0]);
+  check_cores(context, &single(MAX_CORES - 1), &[MAX_CORES - 1]);
+
+  // Out of range cores are ignored.
+  check_cores(context, &single(MAX_CORES), &[]);
+}
+
+/// Test constructing a mask that permits every core.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_all(context: &mut test::TestContext) {
+  let mask = all();
+  check_eq!(context, mask.len(), MAX_CORES);
+  check_eq!(context, mask.ones(), MAX_CORES);
+  check_eq!(context, mask.first_zero().is_none(), true);
+}
+
+/// Test constructing a mask from a list of cores.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Duplicate and out of range cores are ignored.
+fn test_from_cores(context: &mut test::TestContext) {
+  let last = MAX_CORES - 1;
+
+  check_cores(context, &from_cores(&[]), &[]);
+  check_cores(context, &from_cores(&[0, last, 0, MAX_CORES]), &[0, last]);
+}
+
+/// Test mask unions and intersections.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_set_operations(context: &mut test::TestContext) {
+  let last = MAX_CORES - 1;
+  let a = from_cores(&[0, last]);
+  let b = single(last);
+  let empty = from_cores(&[]);
+
+  check_cores(context, &union(&a, &b), &[0, last]);
+  check_cores(context, &union(&b, &empty), &[last]);
+  check_eq!(context, union(&empty, &all()).ones(), MAX_CORES);
+
+  check_cores(context, &intersect(&a, &b), &[last]);
+  check_cores(context, &intersect(&a, &empty), &[]);
+  check_cores(context, &intersect(&all(), &a), &[0, last]);
+}
@@ -1,13 +1,46 @@
 //! Task Management
 
+#[cfg(feature = "module_tests")]
+mod tests;
+
+pub mod affinity;
+
 pub use crate::arch::task::*;
 
+use crate::arch::cpu::MAX_CORES;
+use crate::arch::percpu;
 use crate::debug_print;
+use crate::sync::SpinLock;
+#[cfg(feature = "module_tests")]
+use crate::test;
 use core::ptr;
 
+/// The bootstrap task's identifier. Never allocated.
+pub const BOOTSTRAP_TASK_ID: usize = 0;
+
+/// The number of timer ticks a task runs before it is preempted.
+pub const DEFAULT_QUANTUM: usize = 10;
+
+/// The highest task priority. Priority 0 is the lowest.
+pub const MAX_PRIORITY: u8 = 3;
+
+/// The priority of a new task.
+pub const DEFAULT_PRIORITY: u8 = 1;
+
+/// The maximum number of released task identifiers the system allocator holds
+/// for recycling.
+const MAX_FREE_TASK_IDS: usize = 64;
+
+/// Task identifier allocator convenience type.
+pub type SystemTaskIdAllocator = TaskIdAllocator<MAX_FREE_TASK_IDS>;
+
 /// Re-initialization guard.
 static mut INITIALIZED: bool = false;
 
+/// The system task identifier allocator.
+static mut TASK_ID_ALLOCATOR: SpinLock<SystemTaskIdAllocator> =
+  SpinLock::new(TaskIdAllocator::new(BOOTSTRAP_TASK_ID + 1, true));
+
 /// The bootstrap task is a special task that exists only to provide a way to
 /// manage high memory mappings before the kernel allocators and scheduler are
 /// initialized. The bootstrap task will only be used by the primary core.
@@ -15,7 +48,148 @@ static mut INITIALIZED: bool = false;
 /// Once the kernel maps system memory, initializes the kernel allocators,
 /// initializes the scheduler, and enables the secondary cores, the bootstrap
 /// task will be replaced by the real init thread tasks.
-static mut BOOTSTRAP_TASK: Task = Task::new(0, TaskContext::default());
+static mut BOOTSTRAP_TASK: Task = Task::new(BOOTSTRAP_TASK_ID, TaskContext::default());
+
+/// The idle tasks that the secondary cores are running when they enter the
+/// scheduler. Index 0 is unused since the primary core runs the bootstrap task.
+static mut IDLE_TASKS: [Task; MAX_CORES] =
+  [const { Task::new(BOOTSTRAP_TASK_ID, TaskContext::default()) }; MAX_CORES];
+
+/// Allocates unique task identifiers. Identifiers are handed out from a
+/// monotonically increasing counter. If recycling is enabled, released
+/// identifiers are held in a free list of up to MAX_FREE entries and reused
+/// before the counter advances.
+pub struct TaskIdAllocator<const MAX_FREE: usize> {
+  first_id: usize,
+  next_id: usize,
+  recycle: bool,
+  free: [usize; MAX_FREE],
+  free_count: usize,
+}
+
+impl<const MAX_FREE: usize> TaskIdAllocator<MAX_FREE> {
+  /// Construct a new task identifier allocator.
+  ///
+  /// # Parameters
+  ///
+  /// * `first_id` - The first identifier to allocate.
+  /// * `recycle` - Reuse released identifiers.
+  pub const fn new(first_id: usize, recycle: bool) -> Self {
+    TaskIdAllocator {
+      first_id,
+      next_id: first_id,
+      recycle,
+      free: [0; MAX_FREE],
+      free_count: 0,
+    }
+  }
+
+  /// Allocate a task identifier.
+  ///
+  /// # Description
+  ///
+  /// Recycled identifiers are reused in last-in, first-out order. Otherwise,
+  /// the counter advances.
+  ///
+  /// The counter does not wrap around. `usize::MAX` is never allocated so that
+  /// the counter cannot return to identifiers that may still be in use.
+  ///
+  /// # Returns
+  ///
+  /// A unique task identifier, or None if the counter is exhausted and there
+  /// are no identifiers to recycle.
+  pub fn alloc(&mut self) -> Option<usize> {
+    if self.free_count > 0 {
+      self.free_count -= 1;
+      return Some(self.free[self.free_count]);
+    }
+
+    if self.next_id == usize::MAX {
+      return None;
+    }
+
+    let task_id = self.next_id;
+    self.next_id += 1;
+    Some(task_id)
+  }
+
+  /// Release a task identifier.
+  ///
+  /// # Parameters
+  ///
+  /// * `task_id` - The task identifier.
+  ///
+  /// # Description
+  ///
+  /// If recycling is disabled or the free list is full, the identifier is
+  /// retired and will not be allocated again. Identifiers that were never
+  /// allocated or have already been released are ignored.
+  pub fn free(&mut self, task_id: usize) {
+    if !self.recycle || self.free_count == MAX_FREE {
+      return;
+    }
+
+    if task_id < self.first_id || task_id >= self.next_id {
+      return;
+    }
+
+    if self.free[..self.free_count].contains(&task_id) {
+      return;
+    }
+
+    self.free[self.free_count] = task_id;
+    self.free_count += 1;
+  }
+
+  /// Get the number of identifiers waiting to be recycled.
+  pub fn get_free_count(&self) -> usize {
+    self.free_count
+  }
+}
+
+/// Task run states.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum TaskState {
+  /// The task is waiting to run.
+  Ready,
+  /// The task is running on a core.
+  Running,
+  /// The task is waiting for an event and may not run.
+  Blocked,
+  /// The task has exited and will never run again.
+  Dead,
+}
+
+impl TaskState {
+  /// Check if a task may move from this state to another state.
+  ///
+  /// # Parameters
+  ///
+  /// * `next` - The next state.
+  ///
+  /// # Description
+  ///
+  /// A ready task may start running, block before it runs, or be killed. A
+  /// running task may be switched out, block, or exit. A blocked task may only
+  /// become ready or be killed. A dead task may not move to any state.
+  ///
+  /// # Returns
+  ///
+  /// True if the transition is valid, false otherwise.
+  pub fn can_transition_to(self, next: TaskState) -> bool {
+    matches!(
+      (self, next),
+      (TaskState::Ready, TaskState::Running)
+        | (TaskState::Ready, TaskState::Blocked)
+        | (TaskState::Ready, TaskState::Dead)
+        | (TaskState::Running, TaskState::Ready)
+        | (TaskState::Running, TaskState::Blocked)
+        | (TaskState::Running, TaskState::Dead)
+        | (TaskState::Blocked, TaskState::Ready)
+        | (TaskState::Blocked, TaskState::Dead)
+    )
+  }
+}
 
 /// The architecture-independent task object.
 ///
@@ -23,7 +197,11 @@ static mut BOOTSTRAP_TASK: Task = Task::new(0, TaskContext::default());
 /// dependent operations.
 pub struct Task {
   task_id: usize,
+  state: TaskState,
   affinity: Option<AffinityMask>,
+  priority: u8,
+  quantum: usize,
+  reschedule: bool,
   context: TaskContext,
 }
 
@@ -36,28 +214,56 @@ impl Task {
   pub const fn new(task_id: usize, context: TaskContext) -> Self {
     Task {
       task_id,
+      state: TaskState::Ready,
       affinity: None,
+      priority: DEFAULT_PRIORITY,
+      quantum: DEFAULT_QUANTUM,
+      reschedule: false,
       context,
     }
   }
 
+  /// Construct a new task with the next available task identifier.
+  ///
+  /// # Parameters
+  ///
+  /// * `context` - The new task's architecture-dependent context.
+  ///
+  /// # Description
+  ///
+  /// The function will panic if the system has run out of task identifiers.
+  pub fn spawn(context: TaskContext) -> Self {
+    let task_id = get_task_id_allocator().lock().alloc();
+    Task::new(task_id.expect("Out of task identifiers."), context)
+  }
+
   /// Get a reference to the current task.
   pub fn get_current_task<'task>() -> &'task Task {
     Task::get_current_task_mut()
   }
 
   /// Get a mutable reference to the current task.
+  ///
+  /// # Description
+  ///
+  /// The current task is kept in the current core's data area. See
+  /// `percpu::CoreData`.
   pub fn get_current_task_mut<'task>() -> &'task mut Task {
-    unsafe { (get_current_task_addr() as *mut Task).as_mut().unwrap() }
+    let addr = percpu::this_core().get_current_task_addr();
+    unsafe { (addr as *mut Task).as_mut().unwrap() }
   }
 
-  /// Set the current task.
+  /// Set the current task on the current core.
   ///
   /// # Parameters
   ///
   /// * `task` - The task that will begin running.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: Interrupts must be disabled.
   pub fn set_current_task(task: &Task) {
-    unsafe { set_current_task_addr(task as *const _ as usize) };
+    percpu::this_core().set_current_task_addr(task as *const _ as usize);
   }
 
   /// Get the task identifier.
@@ -65,6 +271,25 @@ impl Task {
     self.task_id
   }
 
+  /// Get the task's run state.
+  pub fn state(&self) -> TaskState {
+    self.state
+  }
+
+  /// Move the task to a new run state.
+  ///
+  /// # Parameters
+  ///
+  /// * `state` - The new state.
+  ///
+  /// # Description
+  ///
+  /// Panics if the transition is not valid. See `TaskState::can_transition_to()`.
+  pub fn set_state(&mut self, state: TaskState) {
+    assert!(self.state.can_transition_to(state));
+    self.state = state;
+  }
+
   /// The task's core affinity mask.
   pub fn get_affinity(&self) -> Option<&AffinityMask> {
     match self.context.get_pin_mask() {
@@ -86,6 +311,108 @@ impl Task {
     }
   }
 
+  /// Check if the task is pinned to a core.
+  ///
+  /// # Description
+  ///
+  /// A task is pinned while it has local mappings to high memory or device
+  /// pages. See `map_page()` and `map_device_page()`.
+  pub fn is_pinned(&self) -> bool {
+    self.context.get_pin_mask().is_some()
+  }
+
+  /// Check if the task may run on a core.
+  ///
+  /// # Parameters
+  ///
+  /// * `core_idx` - The core index.
+  ///
+  /// # Description
+  ///
+  /// A pinned task may only run on the core it is pinned to regardless of its
+  /// affinity mask. The scheduler must never migrate a task to a core that is
+  /// not permitted. Migrating a pinned task would leave it dereferencing local
+  /// mappings that only exist on the original core.
+  ///
+  /// # Returns
+  ///
+  /// True if the task may run on the core, false otherwise.
+  pub fn can_run_on(&self, core_idx: usize) -> bool {
+    match self.get_affinity() {
+      Some(mask) => mask.test_bit(core_idx).unwrap_or(false),
+      None => true,
+    }
+  }
+
+  /// Get the task's priority.
+  pub fn get_priority(&self) -> u8 {
+    self.priority
+  }
+
+  /// Set the task's priority.
+  ///
+  /// # Parameters
+  ///
+  /// * `priority` - The new priority. Must not exceed `MAX_PRIORITY`.
+  ///
+  /// # Description
+  ///
+  /// A queued task keeps the priority it was queued with until it is queued
+  /// again.
+  pub fn set_priority(&mut self, priority: u8) {
+    assert!(priority <= MAX_PRIORITY);
+    self.priority = priority;
+  }
+
+  /// Get the number of timer ticks remaining in the task's quantum.
+  pub fn get_quantum(&self) -> usize {
+    self.quantum
+  }
+
+  /// Give the task a full quantum and clear its reschedule request.
+  ///
+  /// # Description
+  ///
+  /// Called by the scheduler when the task is switched in.
+  pub fn reset_quantum(&mut self) {
+    self.quantum = DEFAULT_QUANTUM;
+    self.reschedule = false;
+  }
+
+  /// Check if the task should be switched out.
+  pub fn needs_reschedule(&self) -> bool {
+    self.reschedule
+  }
+
+  /// Request that the task be switched out.
+  ///
+  /// # Description
+  ///
+  /// The request remains set until the quantum is reset.
+  pub fn request_reschedule(&mut self) {
+    self.reschedule = true;
+  }
+
+  /// Account for a timer tick while the task is running.
+  ///
+  /// # Description
+  ///
+  /// Decrements the task's quantum. When the quantum reaches zero, the task
+  /// requests a reschedule. The request remains set until the quantum is reset.
+  ///
+  /// # Returns
+  ///
+  /// True if the task should be switched out.
+  pub fn tick(&mut self) -> bool {
+    self.quantum = self.quantum.saturating_sub(1);
+
+    if self.quantum == 0 {
+      self.reschedule = true;
+    }
+
+    self.reschedule
+  }
+
   /// Get a reference to the task's architecture-dependent context.
   pub fn get_context(&self) -> &TaskContext {
     &self.context
@@ -108,9 +435,6 @@ impl Task {
   /// be the last page unmapped and vice versa for the last page mapped. Thread-
   /// local mappings should not be maintained beyond the current context.
   ///
-  /// The function will panic if no more pages can be added to the thread's
-  /// mapping table.
-  ///
   ///   NOTE: Only 32-bit architectures implement thread-local mapping, but this
   ///         interface should be used for architecture independence. On a
   ///         64-bit architecture, the compiler will optimize the map call down
@@ -118,9 +442,58 @@ impl Task {
   ///
   /// # Returns
   ///
-  /// The virtual address of the mapped page.
-  pub fn map_page(&mut self, page_addr: usize) -> usize {
-    self.context.map_page(page_addr)
+  /// The virtual address of the mapped page, or None if no more pages can be
+  /// added to the thread's mapping table. See `free_mapping_count()`.
+  pub fn map_page(&mut self, page_addr: usize) -> Option<usize> {
+    self.context.map_page(page_addr, false)
+  }
+
+  /// Maps a device page into the task's local mapping table.
+  ///
+  /// # Parameters
+  ///
+  /// * `page_addr` - The physical address of the device page to map.
+  ///
+  /// # Description
+  ///
+  /// Maps the page with device memory attributes so that a driver can
+  /// transiently access registers that are not otherwise mapped. The mapping
+  /// follows the same stack semantics as `map_page()` and is released with
+  /// `unmap_page()`. Device pages are never linearly mapped, so the task is
+  /// pinned to the current core until the mapping is released.
+  ///
+  ///   NOTE: Only 32-bit architectures implement thread-local mapping. On a
+  ///         64-bit architecture, the function panics.
+  ///
+  /// # Returns
+  ///
+  /// The virtual address of the mapped page, or None if no more pages can be
+  /// added to the thread's mapping table.
+  pub fn map_device_page(&mut self, page_addr: usize) -> Option<usize> {
+    self.context.map_page(page_addr, true)
+  }
+
+  /// Maps a page into the task's local mapping table for the lifetime of a
+  /// guard.
+  ///
+  /// # Parameters
+  ///
+  /// * `page_addr` - The physical address of the page to map.
+  ///
+  /// # Description
+  ///
+  /// See `map_page()`. The page is unmapped when the returned guard is dropped.
+  /// Guards declared in the same scope are dropped in reverse order, which
+  /// maintains the stack semantics of the local mappings without balancing
+  /// calls to `unmap_page()` by hand.
+  ///
+  /// # Returns
+  ///
+  /// A tuple with the virtual address of the mapped page and the guard, or None
+  /// if no more pages can be added to the thread's mapping table.
+  pub fn map_page_scoped(&mut self, page_addr: usize) -> Option<(usize, LocalMapping<'_>)> {
+    let page_vaddr = self.map_page(page_addr)?;
+    Some((page_vaddr, LocalMapping { task: self }))
   }
 
   /// Unmaps the last mapped page in the current task's local mapping table.
@@ -132,6 +505,47 @@ impl Task {
   pub fn unmap_page(&mut self) {
     self.context.unmap_page();
   }
+
+  /// Get the number of pages mapped into the task's local mappings.
+  ///
+  /// # Description
+  ///
+  /// A task with outstanding local mappings must not be migrated to another
+  /// core. The virtual addresses of the mappings are only valid on the core
+  /// that created them.
+  ///
+  ///   NOTE: Only 32-bit architectures implement thread-local mapping. On a
+  ///         64-bit architecture, the count is always zero.
+  pub fn mapped_page_count(&self) -> usize {
+    self.context.mapped_page_count()
+  }
+
+  /// Get the number of pages that can still be mapped into the task's local
+  /// mappings.
+  ///
+  /// # Description
+  ///
+  ///   NOTE: Only 32-bit architectures implement thread-local mapping. On a
+  ///         64-bit architecture, the count is always `usize::MAX`.
+  pub fn free_mapping_count(&self) -> usize {
+    self.context.free_mapping_count()
+  }
+}
+
+/// Unmaps a page mapped by `Task::map_page_scoped()` when dropped.
+///
+///   NOTE: Guards must be dropped in the reverse order they were created. Moving
+///         a guard out of its scope can break the stack semantics of the local
+///         mappings.
+pub struct LocalMapping<'task> {
+  task: &'task mut Task,
+}
+
+impl Drop for LocalMapping<'_> {
+  /// Unmap the page.
+  fn drop(&mut self) {
+    self.task.unmap_page();
+  }
 }
 
 /// Initialize the task module and the bootstrap task.
@@ -150,10 +564,71 @@ pub fn init() {
   // running task pointer on the primary core and map the task's local mapping
   // table into the kernel page tables.
   let task = unsafe { ptr::addr_of_mut!(BOOTSTRAP_TASK).as_mut().unwrap() };
-  *task = Task::new(0, init_bootstrap_context());
+  *task = Task::new(BOOTSTRAP_TASK_ID, init_bootstrap_context());
+  task.set_state(TaskState::Running);
 
   // Update the current task pointer.
   Task::set_current_task(task);
 
   debug_print!("task init complete.\n");
 }
+
+/// Initialize a secondary core's idle task.
+///
+/// # Parameters
+///
+/// * `core_idx` - The secondary core's index.
+///
+/// # Description
+///
+/// The idle task is given a task identifier the first time it is initialized
+/// and is moved to the running state.
+///
+///   NOTE: Idle tasks do not have local mapping tables and cannot map high
+///         memory pages.
+///
+/// # Returns
+///
+/// The idle task.
+pub fn init_idle_task(core_idx: usize) -> &'static mut Task {
+  assert!(core_idx > 0 && core_idx < MAX_CORES);
+
+  let task = unsafe { &mut ptr::addr_of_mut!(IDLE_TASKS).as_mut().unwrap()[core_idx] };
+
+  if task.get_task_id() == BOOTSTRAP_TASK_ID {
+    *task = Task::spawn(TaskContext::default());
+  }
+
+  if task.state() != TaskState::Running {
+    task.set_state(TaskState::Running);
+  }
+
+  task
+}
+
+/// Get the tasks running on each core.
+///
+/// # Description
+///
+/// Intended for debugging. Cores that have not started running a task are
+/// skipped. The tasks may have been switched out by the time they are read.
+///
+/// # Returns
+///
+/// An iterator over the current tasks in core index order.
+pub fn current_tasks() -> impl Iterator<Item = &'static Task> {
+  percpu::cores()
+    .filter_map(|core| unsafe { (core.get_current_task_addr() as *const Task).as_ref() })
+}
+
+/// Get the system task identifier allocator.
+pub fn get_task_id_allocator() -> &'static SpinLock<SystemTaskIdAllocator> {
+  unsafe { ptr::addr_of!(TASK_ID_ALLOCATOR).as_ref().unwrap() }
+}
+
+/// Run the task management tests.
+#[cfg(feature = "module_tests")]
+pub fn run_tests(context: &mut test::TestContext) {
+  tests::run_tests(context);
+  affinity::run_tests(context);
+}
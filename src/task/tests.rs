@@ -0,0 +1,319 @@
+//! Task Management Tests
+
+use super::{
+  AffinityMask, BOOTSTRAP_TASK_ID, Task, TaskContext, TaskIdAllocator, TaskState, current_tasks,
+  get_task_id_allocator,
+};
+use crate::arch::cpu::MAX_CORES;
+use crate::debug_print;
+use crate::{
+  check_eq, check_neq, check_no_panic, check_none, check_optional, check_panics, execute_test, test,
+};
+use core::ptr;
+
+/// Test free list capacity.
+const TEST_MAX_FREE: usize = 4;
+
+/// Test task identifier allocator type.
+type TestTaskIdAllocator = TaskIdAllocator<TEST_MAX_FREE>;
+
+/// Run task management tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut test::TestContext) {
+  execute_test!(context, test_monotonic_allocation);
+  execute_test!(context, test_recycling);
+  execute_test!(context, test_no_recycling);
+  execute_test!(context, test_exhaustion);
+  execute_test!(context, test_spawn);
+  execute_test!(context, test_affinity);
+  execute_test!(context, test_last_core_affinity);
+  execute_test!(context, test_current_tasks);
+  execute_test!(context, test_scoped_mapping);
+  execute_test!(context, test_legal_transitions);
+  execute_test!(context, test_illegal_transitions);
+}
+
+/// Test that identifiers are allocated in increasing order.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_monotonic_allocation(context: &mut test::TestContext) {
+  let mut allocator = TestTaskIdAllocator::new(1, true);
+
+  for expected in 1..=16 {
+    check_optional!(context, allocator.alloc(), expected);
+  }
+}
+
+/// Test reusing released identifiers.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Released identifiers are reused in last-in, first-out order before the
+/// counter advances. Identifiers that were never allocated, double releases,
+/// and releases beyond the free list capacity are ignored.
+fn test_recycling(context: &mut test::TestContext) {
+  let mut allocator = TestTaskIdAllocator::new(1, true);
+
+  for _ in 0..8 {
+    _ = allocator.alloc();
+  }
+
+  allocator.free(3);
+  allocator.free(5);
+  allocator.free(5);
+  allocator.free(0);
+  allocator.free(9);
+  check_eq!(context, allocator.get_free_count(), 2);
+
+  check_optional!(context, allocator.alloc(), 5);
+  check_optional!(context, allocator.alloc(), 3);
+  check_optional!(context, allocator.alloc(), 9);
+
+  for task_id in 1..=(TEST_MAX_FREE + 1) {
+    allocator.free(task_id);
+  }
+
+  check_eq!(context, allocator.get_free_count(), TEST_MAX_FREE);
+}
+
+/// Test that released identifiers are retired when recycling is disabled.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_no_recycling(context: &mut test::TestContext) {
+  let mut allocator = TestTaskIdAllocator::new(1, false);
+
+  check_optional!(context, allocator.alloc(), 1);
+  allocator.free(1);
+  check_eq!(context, allocator.get_free_count(), 0);
+  check_optional!(context, allocator.alloc(), 2);
+}
+
+/// Test that the counter does not wrap around.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Once the counter is exhausted, only recycled identifiers can be allocated.
+fn test_exhaustion(context: &mut test::TestContext) {
+  let mut allocator = TestTaskIdAllocator::new(usize::MAX - 2, true);
+
+  check_optional!(context, allocator.alloc(), usize::MAX - 2);
+  check_optional!(context, allocator.alloc(), usize::MAX - 1);
+  check_none!(context, allocator.alloc());
+
+  allocator.free(usize::MAX - 2);
+  check_optional!(context, allocator.alloc(), usize::MAX - 2);
+  check_none!(context, allocator.alloc());
+}
+
+/// Test spawning tasks with the system allocator.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_spawn(context: &mut test::TestContext) {
+  let first = Task::spawn(TaskContext::default());
+  let second = Task::spawn(TaskContext::default());
+  let first_id = first.get_task_id();
+  let second_id = second.get_task_id();
+
+  check_neq!(context, first_id, BOOTSTRAP_TASK_ID);
+  check_neq!(context, second_id, BOOTSTRAP_TASK_ID);
+  check_neq!(context, first_id, second_id);
+
+  // Return the identifiers so the test does not consume them.
+  let mut allocator = get_task_id_allocator().lock();
+  allocator.free(first_id);
+  allocator.free(second_id);
+}
+
+/// Test restricting the cores a task may run on with an affinity mask.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_affinity(context: &mut test::TestContext) {
+  const TEST_CORE: usize = 1;
+
+  let mut task = Task::new(BOOTSTRAP_TASK_ID, TaskContext::default());
+  check_eq!(context, task.is_pinned(), false);
+
+  for core_idx in 0..MAX_CORES {
+    check_eq!(context, task.can_run_on(core_idx), true);
+  }
+
+  let mut affinity = AffinityMask::new(MAX_CORES);
+  affinity.set_bit(TEST_CORE);
+  task.set_affinity(Some(&affinity));
+
+  for core_idx in 0..MAX_CORES {
+    check_eq!(context, task.can_run_on(core_idx), core_idx == TEST_CORE);
+  }
+
+  // Cores outside of the mask are never permitted.
+  check_eq!(context, task.can_run_on(MAX_CORES), false);
+}
+
+/// Test that the affinity mask can represent the last core.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_last_core_affinity(context: &mut test::TestContext) {
+  const LAST_CORE: usize = MAX_CORES - 1;
+
+  let mut affinity = AffinityMask::new(MAX_CORES);
+  check_eq!(context, affinity.len(), MAX_CORES);
+
+  affinity.set_bit(LAST_CORE);
+  check_optional!(context, affinity.test_bit(LAST_CORE), true);
+  check_none!(context, affinity.test_bit(MAX_CORES));
+  check_eq!(context, affinity.ones(), 1);
+
+  let mut task = Task::new(BOOTSTRAP_TASK_ID, TaskContext::default());
+  task.set_affinity(Some(&affinity));
+  check_eq!(context, task.can_run_on(LAST_CORE), true);
+  check_eq!(context, task.can_run_on(0), LAST_CORE == 0);
+}
+
+/// Test enumerating the tasks running on each core.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The tests run single-threaded on the primary core, so only the primary
+/// core's current task is expected.
+fn test_current_tasks(context: &mut test::TestContext) {
+  let current = Task::get_current_task();
+  let mut tasks = current_tasks();
+
+  let first_is_current = tasks.next().is_some_and(|task| ptr::eq(task, current));
+  check_eq!(context, first_is_current, true);
+
+  let remaining = tasks.count();
+  check_eq!(context, remaining, 0);
+}
+
+/// Test that dropping a scoped mapping unmaps the page.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Nested guards are dropped in reverse order, and the mapped page count
+/// returns to its original value. The expected counts are taken from manual
+/// mappings because only 32-bit architectures count local mappings.
+fn test_scoped_mapping(context: &mut test::TestContext) {
+  const TEST_PAGE_ADDR: usize = 0x3700_0000;
+
+  let task = Task::get_current_task_mut();
+  let virt_base = crate::arch::get_kernel_virtual_base();
+  let count = task.mapped_page_count();
+
+  _ = task.map_page(TEST_PAGE_ADDR);
+  let one_count = task.mapped_page_count();
+  _ = task.map_page(TEST_PAGE_ADDR);
+  let two_count = task.mapped_page_count();
+  task.unmap_page();
+  task.unmap_page();
+
+  {
+    let (page_vaddr, mut mapping) = task.map_page_scoped(TEST_PAGE_ADDR).unwrap();
+    check_eq!(context, page_vaddr, virt_base + TEST_PAGE_ADDR);
+    check_eq!(context, mapping.task.mapped_page_count(), one_count);
+
+    {
+      let (_, inner) = mapping.task.map_page_scoped(TEST_PAGE_ADDR).unwrap();
+      check_eq!(context, inner.task.mapped_page_count(), two_count);
+    }
+
+    check_eq!(context, mapping.task.mapped_page_count(), one_count);
+  }
+
+  check_eq!(context, task.mapped_page_count(), count);
+}
+
+/// Test moving a task through valid run states.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_legal_transitions(context: &mut test::TestContext) {
+  let mut task = Task::new(BOOTSTRAP_TASK_ID, TaskContext::default());
+  let matches = task.state() == TaskState::Ready;
+  check_eq!(context, matches, true);
+
+  let states = [
+    TaskState::Running,
+    TaskState::Ready,
+    TaskState::Blocked,
+    TaskState::Ready,
+    TaskState::Running,
+    TaskState::Blocked,
+    TaskState::Dead,
+  ];
+
+  for state in states {
+    check_no_panic!(context, || task.set_state(state));
+    let matches = task.state() == state;
+    check_eq!(context, matches, true);
+  }
+}
+
+/// Test that invalid run state transitions panic.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// A blocked task must become ready before it runs, a task may not move to
+/// the state it is already in, and a dead task may not move to any state. A
+/// rejected transition must not change the task's state.
+fn test_illegal_transitions(context: &mut test::TestContext) {
+  const STATES: [TaskState; 4] = [
+    TaskState::Ready,
+    TaskState::Running,
+    TaskState::Blocked,
+    TaskState::Dead,
+  ];
+
+  for state in STATES {
+    check_eq!(context, state.can_transition_to(state), false);
+    check_eq!(context, TaskState::Dead.can_transition_to(state), false);
+  }
+
+  check_eq!(context, TaskState::Blocked.can_transition_to(TaskState::Running), false);
+
+  let mut task = Task::new(BOOTSTRAP_TASK_ID, TaskContext::default());
+  task.set_state(TaskState::Blocked);
+  check_panics!(context, || task.set_state(TaskState::Running));
+  let matches = task.state() == TaskState::Blocked;
+  check_eq!(context, matches, true);
+
+  task.set_state(TaskState::Dead);
+  check_panics!(context, || task.set_state(TaskState::Running));
+  check_panics!(context, || task.set_state(TaskState::Ready));
+  let matches = task.state() == TaskState::Dead;
+  check_eq!(context, matches, true);
+}
@@ -0,0 +1,291 @@
+//! Test DTB Construction Utilities
+
+use crate::support::bits;
+
+/// DTB structure block markers.
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_END: u32 = 0x9;
+const FDT_MAGIC: u32 = 0xd00d_feed;
+
+/// Header size, version 17.
+const HEADER_SIZE: usize = 40;
+
+/// Size of the empty memory reservation block: a single terminating entry.
+const MEM_RSV_SIZE: usize = 16;
+
+/// The offset of the memory reservation block.
+const MEM_RSV_OFFSET: usize = HEADER_SIZE;
+
+/// The offset of the structure block.
+const STRUCT_OFFSET: usize = MEM_RSV_OFFSET + MEM_RSV_SIZE;
+
+/// Maximum size of the test DTB in bytes.
+pub const MAX_BLOB_SIZE: usize = 1024;
+
+/// Maximum size of the strings block in bytes.
+const MAX_STRINGS_SIZE: usize = 256;
+
+/// Header field byte offsets.
+pub const HDR_TOTAL_SIZE: usize = 4;
+pub const HDR_STRUCT_OFFSET: usize = 8;
+pub const HDR_STRINGS_OFFSET: usize = 12;
+pub const HDR_MEM_RSV_OFFSET: usize = 16;
+pub const HDR_VERSION: usize = 20;
+pub const HDR_LAST_COMP_VERSION: usize = 24;
+pub const HDR_STRINGS_SIZE: usize = 32;
+pub const HDR_STRUCT_SIZE: usize = 36;
+
+/// Builds a flattened DTB in memory for testing.
+///
+/// # Description
+///
+/// Nodes and properties are appended to the structure block in order. Property
+/// names are de-duplicated in the strings block like `dtc` does. Call
+/// `finish()` after the last node has been closed to write the header and the
+/// strings block.
+///
+///   NOTE: The builder is intended for small, hand-written trees. It panics if
+///         the blob or strings block overflows.
+#[repr(C, align(8))]
+pub struct DtbBuilder {
+  blob: [u8; MAX_BLOB_SIZE],
+  strings: [u8; MAX_STRINGS_SIZE],
+  struct_end: usize,
+  strings_len: usize,
+}
+
+impl DtbBuilder {
+  /// Construct a new, empty DTB builder.
+  pub const fn new() -> Self {
+    DtbBuilder {
+      blob: [0; MAX_BLOB_SIZE],
+      strings: [0; MAX_STRINGS_SIZE],
+      struct_end: STRUCT_OFFSET,
+      strings_len: 0,
+    }
+  }
+
+  /// Begin a new node.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The node name. The root node's name is empty.
+  pub fn begin_node(&mut self, name: &str) -> &mut Self {
+    self.put_u32(FDT_BEGIN_NODE);
+    self.put_padded_str(name);
+    self
+  }
+
+  /// End the current node.
+  pub fn end_node(&mut self) -> &mut Self {
+    self.put_u32(FDT_END_NODE);
+    self
+  }
+
+  /// Add a property with a list of 32-bit cells.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The property name.
+  /// * `cells` - The property value.
+  pub fn prop_cells(&mut self, name: &str, cells: &[u32]) -> &mut Self {
+    self.put_prop_header(name, cells.len() * 4);
+
+    for cell in cells {
+      self.put_u32(*cell);
+    }
+
+    self
+  }
+
+  /// Add a property with a 32-bit value.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The property name.
+  /// * `value` - The property value.
+  pub fn prop_u32(&mut self, name: &str, value: u32) -> &mut Self {
+    self.prop_cells(name, &[value])
+  }
+
+  /// Add a property with a null-terminated string value.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The property name.
+  /// * `value` - The property value without a null-terminator.
+  pub fn prop_str(&mut self, name: &str, value: &str) -> &mut Self {
+    self.put_prop_header(name, value.len() + 1);
+    self.put_padded_str(value);
+    self
+  }
+
+  /// Add a property with an arbitrary byte value.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The property name.
+  /// * `value` - The property value.
+  pub fn prop_bytes(&mut self, name: &str, value: &[u8]) -> &mut Self {
+    self.put_prop_header(name, value.len());
+    self.put_padded_bytes(value);
+    self
+  }
+
+  /// Add a property with a declared size that may differ from the size of the
+  /// value written. Useful for building invalid DTBs.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The property name.
+  /// * `size` - The declared size of the property value.
+  /// * `value` - The property value.
+  pub fn prop_with_size(&mut self, name: &str, size: usize, value: &[u8]) -> &mut Self {
+    self.put_prop_header(name, size);
+    self.put_padded_bytes(value);
+    self
+  }
+
+  /// Terminate the structure block and write the header and strings block.
+  ///
+  /// # Returns
+  ///
+  /// The address of the DTB. The address is only valid while the builder is not
+  /// moved.
+  pub fn finish(&mut self) -> usize {
+    self.put_u32(FDT_END);
+
+    let struct_size = self.struct_end - STRUCT_OFFSET;
+    let strings_offset = self.struct_end;
+    let total_size = strings_offset + self.strings_len;
+    assert!(total_size <= MAX_BLOB_SIZE);
+
+    self.blob[strings_offset..total_size].copy_from_slice(&self.strings[..self.strings_len]);
+
+    self.set_header_field(0, FDT_MAGIC);
+    self.set_header_field(HDR_TOTAL_SIZE, total_size as u32);
+    self.set_header_field(HDR_STRUCT_OFFSET, STRUCT_OFFSET as u32);
+    self.set_header_field(HDR_STRINGS_OFFSET, strings_offset as u32);
+    self.set_header_field(HDR_MEM_RSV_OFFSET, MEM_RSV_OFFSET as u32);
+    self.set_header_field(HDR_VERSION, 17);
+    self.set_header_field(HDR_LAST_COMP_VERSION, 16);
+    self.set_header_field(HDR_STRINGS_SIZE, self.strings_len as u32);
+    self.set_header_field(HDR_STRUCT_SIZE, struct_size as u32);
+
+    self.blob.as_ptr() as usize
+  }
+
+  /// Read a header field after `finish()`.
+  ///
+  /// # Parameters
+  ///
+  /// * `offset` - The byte offset of the header field.
+  ///
+  /// # Returns
+  ///
+  /// The field value.
+  pub fn get_header_field(&self, offset: usize) -> u32 {
+    u32::from_be_bytes(self.blob[offset..offset + 4].try_into().unwrap())
+  }
+
+  /// Overwrite a header field after `finish()`. Useful for building invalid
+  /// DTBs.
+  ///
+  /// # Parameters
+  ///
+  /// * `offset` - The byte offset of the header field.
+  /// * `value` - The new field value.
+  pub fn set_header_field(&mut self, offset: usize, value: u32) {
+    self.blob[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+  }
+
+  /// Append a property header to the structure block.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The property name.
+  /// * `size` - The size of the property value.
+  fn put_prop_header(&mut self, name: &str, size: usize) {
+    let name_offset = match self.find_string(name) {
+      Some(offset) => offset,
+      None => self.put_string(name),
+    };
+
+    self.put_u32(FDT_PROP);
+    self.put_u32(size as u32);
+    self.put_u32(name_offset as u32);
+  }
+
+  /// Find a property name in the strings block.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The property name.
+  ///
+  /// # Returns
+  ///
+  /// The offset of the name in the strings block, or None if not found.
+  fn find_string(&self, name: &str) -> Option<usize> {
+    let mut offset = 0;
+
+    for string in self.strings[..self.strings_len].split(|&b| b == 0) {
+      if string == name.as_bytes() && offset < self.strings_len {
+        return Some(offset);
+      }
+
+      offset += string.len() + 1;
+    }
+
+    None
+  }
+
+  /// Append a property name to the strings block.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The property name.
+  ///
+  /// # Returns
+  ///
+  /// The offset of the name in the strings block.
+  fn put_string(&mut self, name: &str) -> usize {
+    let offset = self.strings_len;
+    let end = offset + name.len() + 1;
+    assert!(end <= MAX_STRINGS_SIZE);
+
+    self.strings[offset..end - 1].copy_from_slice(name.as_bytes());
+    self.strings[end - 1] = 0;
+    self.strings_len = end;
+
+    offset
+  }
+
+  /// Append a 32-bit big-endian word to the structure block.
+  fn put_u32(&mut self, value: u32) {
+    self.put_padded_bytes(&value.to_be_bytes());
+  }
+
+  /// Append a null-terminated string to the structure block and pad to a word
+  /// boundary.
+  fn put_padded_str(&mut self, value: &str) {
+    let start = self.struct_end;
+    let end = start + value.len() + 1;
+    assert!(end <= MAX_BLOB_SIZE);
+
+    self.blob[start..end - 1].copy_from_slice(value.as_bytes());
+    self.blob[end - 1] = 0;
+    self.struct_end = bits::align_up(end, 4);
+  }
+
+  /// Append bytes to the structure block and pad to a word boundary.
+  fn put_padded_bytes(&mut self, value: &[u8]) {
+    let start = self.struct_end;
+    let end = start + value.len();
+    assert!(end <= MAX_BLOB_SIZE);
+
+    self.blob[start..end].copy_from_slice(value);
+    self.struct_end = bits::align_up(end, 4);
+  }
+}
@@ -1,10 +1,17 @@
 //! Basic Low-Level Module Testing Utilities
 
+pub mod dtb;
 pub mod memory;
+mod tests;
+pub mod tlb;
+
+use crate::{arch, debug_print, mm, sched, support, sync, task};
+use core::cmp;
 
 pub struct TestContext {
   pub pass_count: u32,
   pub fail_count: u32,
+  pub last_failure: Option<(&'static str, u32)>,
 }
 
 impl TestContext {
@@ -12,8 +19,259 @@ impl TestContext {
     TestContext {
       pass_count: 0,
       fail_count: 0,
+      last_failure: None,
+    }
+  }
+
+  /// Count a failed check.
+  ///
+  /// # Parameters
+  ///
+  /// * `file` - The file containing the check.
+  /// * `line` - The line number of the check.
+  ///
+  /// # Description
+  ///
+  /// The location is kept so that the most recent failure can be found even
+  /// when serial debug output is disabled.
+  pub fn record_failure(&mut self, file: &'static str, line: u32) {
+    self.fail_count += 1;
+    self.last_failure = Some((file, line));
+  }
+}
+
+/// The maximum number of suites kept by a summary.
+pub const MAX_SUITES: usize = 16;
+
+/// The number of suites printed in the slowest suites table.
+#[cfg(feature = "test_timing")]
+const SLOWEST_SUITE_COUNT: usize = 5;
+
+/// The results of a test suite.
+#[derive(Copy, Clone)]
+pub struct SuiteResult {
+  pub name: &'static str,
+  pub pass_count: u32,
+  pub fail_count: u32,
+  pub ticks: u64,
+}
+
+/// A summary of test suite results.
+pub struct TestSummary {
+  pub pass_count: u32,
+  pub fail_count: u32,
+  pub ticks: u64,
+  results: [SuiteResult; MAX_SUITES],
+  count: usize,
+}
+
+impl TestSummary {
+  /// Construct an empty summary.
+  pub fn new() -> Self {
+    TestSummary {
+      pass_count: 0,
+      fail_count: 0,
+      ticks: 0,
+      results: [SuiteResult {
+        name: "",
+        pass_count: 0,
+        fail_count: 0,
+        ticks: 0,
+      }; MAX_SUITES],
+      count: 0,
+    }
+  }
+
+  /// Record a suite's results.
+  ///
+  /// # Parameters
+  ///
+  /// * `name` - The suite name.
+  /// * `pass_count` - The number of checks that passed.
+  /// * `fail_count` - The number of checks that failed.
+  /// * `ticks` - The system count elapsed while the suite ran.
+  ///
+  /// # Description
+  ///
+  /// The results are always added to the totals, but only the first
+  /// `MAX_SUITES` suites are kept.
+  pub fn record(&mut self, name: &'static str, pass_count: u32, fail_count: u32, ticks: u64) {
+    self.pass_count += pass_count;
+    self.fail_count += fail_count;
+    self.ticks += ticks;
+
+    if self.count < MAX_SUITES {
+      self.results[self.count] = SuiteResult {
+        name,
+        pass_count,
+        fail_count,
+        ticks,
+      };
+      self.count += 1;
+    }
+  }
+
+  /// Get the kept suite results.
+  pub fn get_results(&self) -> &[SuiteResult] {
+    &self.results[..self.count]
+  }
+
+  /// Sort the kept suite results from slowest to fastest.
+  pub fn sort_slowest_first(&mut self) {
+    self.results[..self.count].sort_unstable_by_key(|r| cmp::Reverse(r.ticks));
+  }
+}
+
+/// A module's test suite.
+pub struct TestSuite {
+  pub name: &'static str,
+  pub run: fn(&mut TestContext),
+}
+
+/// Every module's test suite in the order the suites run.
+const TEST_SUITES: [TestSuite; 13] = [
+  TestSuite {
+    name: "arch",
+    run: arch::run_tests,
+  },
+  TestSuite {
+    name: "mm",
+    run: mm::run_tests,
+  },
+  TestSuite {
+    name: "sched",
+    run: sched::run_tests,
+  },
+  TestSuite {
+    name: "addr",
+    run: support::addr::run_tests,
+  },
+  TestSuite {
+    name: "bitfield",
+    run: support::bitfield::run_tests,
+  },
+  TestSuite {
+    name: "bits",
+    run: support::bits::run_tests,
+  },
+  TestSuite {
+    name: "dtb",
+    run: support::dtb::run_tests,
+  },
+  TestSuite {
+    name: "mmio",
+    run: support::mmio::run_tests,
+  },
+  TestSuite {
+    name: "rand",
+    run: support::rand::run_tests,
+  },
+  TestSuite {
+    name: "range_set",
+    run: support::range_set::run_tests,
+  },
+  TestSuite {
+    name: "sync",
+    run: sync::run_tests,
+  },
+  TestSuite {
+    name: "task",
+    run: task::run_tests,
+  },
+  TestSuite {
+    name: "test",
+    run: tests::run_tests,
+  },
+];
+
+/// Run every module's test suite.
+///
+/// # Parameters
+///
+/// * `context` - The test context that receives the total counts.
+pub fn run_all(context: &mut TestContext) {
+  _ = run_suites(context, &TEST_SUITES);
+}
+
+/// Run a list of test suites.
+///
+/// # Parameters
+///
+/// * `context` - The test context that receives the total counts.
+/// * `suites` - The test suites.
+///
+/// # Description
+///
+/// Each suite runs with its own context so that the suite's counts can be
+/// reported separately before they are added to the totals.
+///
+/// With the `test_timing` feature, each suite is timed and the slowest suites
+/// are printed after all of the suites have run.
+///
+/// # Returns
+///
+/// A summary of the suite results.
+fn run_suites(context: &mut TestContext, suites: &[TestSuite]) -> TestSummary {
+  let mut summary = TestSummary::new();
+
+  for suite in suites {
+    let mut suite_context = TestContext::new();
+
+    debug_print!(" {}:\n", suite.name);
+    let start = now_ticks();
+    (suite.run)(&mut suite_context);
+    let ticks = now_ticks().wrapping_sub(start);
+    debug_print!("  {} pass, {} fail\n", suite_context.pass_count, suite_context.fail_count);
+
+    summary.record(suite.name, suite_context.pass_count, suite_context.fail_count, ticks);
+
+    if suite_context.last_failure.is_some() {
+      context.last_failure = suite_context.last_failure;
     }
   }
+
+  context.pass_count += summary.pass_count;
+  context.fail_count += summary.fail_count;
+
+  #[cfg(feature = "test_timing")]
+  print_slowest_suites(&mut summary);
+
+  summary
+}
+
+/// Print the slowest suites in a summary.
+///
+/// # Parameters
+///
+/// * `summary` - The suite summary.
+#[cfg(feature = "test_timing")]
+fn print_slowest_suites(summary: &mut TestSummary) {
+  summary.sort_slowest_first();
+
+  debug_print!(" slowest suites ({} ticks total):\n", summary.ticks);
+
+  for result in summary.get_results().iter().take(SLOWEST_SUITE_COUNT) {
+    debug_print!(
+      "  {:<12} {:>12} ticks {:>6} pass {:>6} fail\n",
+      result.name,
+      result.ticks,
+      result.pass_count,
+      result.fail_count
+    );
+  }
+}
+
+/// Get the current system count for timing suites.
+///
+/// # Returns
+///
+/// The system count, or 0 without the `test_timing` feature.
+fn now_ticks() -> u64 {
+  #[cfg(feature = "test_timing")]
+  return arch::time::now_ticks();
+
+  #[cfg(not(feature = "test_timing"))]
+  return 0;
 }
 
 #[macro_export]
@@ -28,7 +286,7 @@ macro_rules! execute_test {
 macro_rules! check_eq {
   ($ctx:ident, $act:expr, $exp:expr) => {
     if $act != $exp {
-      $ctx.fail_count += 1;
+      $ctx.record_failure(file!(), line!());
       debug_print!("   FAIL: {} != {} ({} {})\n", $act, $exp, file!(), line!());
     } else {
       $ctx.pass_count += 1;
@@ -40,7 +298,7 @@ macro_rules! check_eq {
 macro_rules! check_neq {
   ($ctx:ident, $act:expr, $exp:expr) => {
     if $act == $exp {
-      $ctx.fail_count += 1;
+      $ctx.record_failure(file!(), line!());
       debug_print!("   FAIL: {} == {} ({} {})\n", $act, $exp, file!(), line!());
     } else {
       $ctx.pass_count += 1;
@@ -52,7 +310,7 @@ macro_rules! check_neq {
 macro_rules! check_lt {
   ($ctx:ident, $act:expr, $exp:expr) => {
     if $act >= $exp {
-      $ctx.fail_count += 1;
+      $ctx.record_failure(file!(), line!());
       debug_print!("   FAIL: {} >= {} ({} {})\n", $act, $exp, file!(), line!());
     } else {
       $ctx.pass_count += 1;
@@ -64,7 +322,7 @@ macro_rules! check_lt {
 macro_rules! check_lteq {
   ($ctx:ident, $act:expr, $exp:expr) => {
     if $act > $exp {
-      $ctx.fail_count += 1;
+      $ctx.record_failure(file!(), line!());
       debug_print!("   FAIL: {} > {} ({} {})\n", $act, $exp, file!(), line!());
     } else {
       $ctx.pass_count += 1;
@@ -76,7 +334,7 @@ macro_rules! check_lteq {
 macro_rules! check_gt {
   ($ctx:ident, $act:expr, $exp:expr) => {
     if $act <= $exp {
-      $ctx.fail_count += 1;
+      $ctx.record_failure(file!(), line!());
       debug_print!("   FAIL: {} <= {} ({} {})\n", $act, $exp, file!(), line!());
     } else {
       $ctx.pass_count += 1;
@@ -88,7 +346,7 @@ macro_rules! check_gt {
 macro_rules! check_gteq {
   ($ctx:ident, $act:expr, $exp:expr) => {
     if $act < $exp {
-      $ctx.fail_count += 1;
+      $ctx.record_failure(file!(), line!());
       debug_print!("   FAIL: {} < {} ({} {})\n", $act, $exp, file!(), line!());
     } else {
       $ctx.pass_count += 1;
@@ -100,7 +358,7 @@ macro_rules! check_gteq {
 macro_rules! check_not_none {
   ($ctx:ident, $act:expr) => {
     if $act.is_none() {
-      $ctx.fail_count += 1;
+      $ctx.record_failure(file!(), line!());
       debug_print!("   FAIL: {} is None ({} {})\n", stringify!($act), file!(), line!());
     } else {
       $ctx.pass_count += 1;
@@ -112,7 +370,7 @@ macro_rules! check_not_none {
 macro_rules! check_none {
   ($ctx:ident, $act:expr) => {
     if !$act.is_none() {
-      $ctx.fail_count += 1;
+      $ctx.record_failure(file!(), line!());
       debug_print!("   FAIL: {} is not None ({} {})\n", stringify!($act), file!(), line!());
     } else {
       $ctx.pass_count += 1;
@@ -128,7 +386,7 @@ macro_rules! check_optional {
     {
       $ctx.pass_count += 1;
     } else {
-      $ctx.fail_count += 1;
+      $ctx.record_failure(file!(), line!());
       debug_print!("   FAIL: {} != {} ({} {})\n", stringify!($act), $exp, file!(), line!());
     }
   };
@@ -137,7 +395,31 @@ macro_rules! check_optional {
 #[macro_export]
 macro_rules! mark_fail {
   ($ctx:ident, $msg:literal) => {
-    $ctx.fail_count += 1;
+    $ctx.record_failure(file!(), line!());
     debug_print!("   FAIL: {} ({} {})\n", $msg, file!(), line!());
   };
 }
+
+#[macro_export]
+macro_rules! check_panics {
+  ($ctx:ident, $f:expr) => {
+    if !$crate::arch::panic_guard::call_guarded(&mut $f) {
+      $ctx.record_failure(file!(), line!());
+      debug_print!("   FAIL: {} did not panic ({} {})\n", stringify!($f), file!(), line!());
+    } else {
+      $ctx.pass_count += 1;
+    }
+  };
+}
+
+#[macro_export]
+macro_rules! check_no_panic {
+  ($ctx:ident, $f:expr) => {
+    if $crate::arch::panic_guard::call_guarded(&mut $f) {
+      $ctx.record_failure(file!(), line!());
+      debug_print!("   FAIL: {} panicked ({} {})\n", stringify!($f), file!(), line!());
+    } else {
+      $ctx.pass_count += 1;
+    }
+  };
+}
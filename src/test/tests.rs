@@ -0,0 +1,221 @@
+//! Test Runner Tests
+
+use super::{MAX_SUITES, TEST_SUITES, TestContext, TestSuite, TestSummary, run_suites};
+use crate::arch;
+use crate::debug_print;
+use crate::support::range::Range;
+use crate::{
+  check_eq, check_no_panic, check_none, check_not_none, check_panics, execute_test, mark_fail,
+};
+
+/// Number of times each fake suite has run.
+static mut SUITE_RUNS: [u32; 3] = [0; 3];
+
+/// Run test runner tests.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+pub fn run_tests(context: &mut TestContext) {
+  execute_test!(context, test_run_suites);
+  execute_test!(context, test_unique_suites);
+  execute_test!(context, test_summary);
+  execute_test!(context, test_failure_location);
+  execute_test!(context, test_panic_guard);
+}
+
+/// Fake suite that passes two checks.
+fn fake_suite_a(context: &mut TestContext) {
+  unsafe { SUITE_RUNS[0] += 1 };
+  context.pass_count += 2;
+}
+
+/// Fake suite that passes one check and fails one check.
+fn fake_suite_b(context: &mut TestContext) {
+  unsafe { SUITE_RUNS[1] += 1 };
+  context.pass_count += 1;
+  context.fail_count += 1;
+}
+
+/// Fake suite without any checks.
+fn fake_suite_c(_context: &mut TestContext) {
+  unsafe { SUITE_RUNS[2] += 1 };
+}
+
+/// Test that each registered suite runs exactly once.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The fake suites only record that they ran and adjust their counts, so the
+/// totals must be the sum of the fake suites' counts. The runner's context
+/// starts with non-zero counts to verify the totals are added rather than
+/// overwritten.
+fn test_run_suites(context: &mut TestContext) {
+  let suites = [
+    TestSuite {
+      name: "fake_a",
+      run: fake_suite_a,
+    },
+    TestSuite {
+      name: "fake_b",
+      run: fake_suite_b,
+    },
+    TestSuite {
+      name: "fake_c",
+      run: fake_suite_c,
+    },
+  ];
+
+  let mut totals = TestContext::new();
+  totals.pass_count = 10;
+  totals.fail_count = 20;
+
+  unsafe { SUITE_RUNS = [0; 3] };
+  let summary = run_suites(&mut totals, &suites);
+
+  for runs in unsafe { SUITE_RUNS } {
+    check_eq!(context, runs, 1);
+  }
+
+  check_eq!(context, totals.pass_count, 13);
+  check_eq!(context, totals.fail_count, 21);
+
+  check_eq!(context, summary.pass_count, 3);
+  check_eq!(context, summary.fail_count, 1);
+  check_eq!(context, summary.get_results().len(), suites.len());
+
+  for (result, suite) in summary.get_results().iter().zip(suites.iter()) {
+    check_eq!(context, result.name, suite.name);
+  }
+}
+
+/// Test summarizing suite results.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The totals must include every recorded suite, even the suites recorded
+/// after the summary is full. Sorting must order the kept suites from slowest
+/// to fastest.
+fn test_summary(context: &mut TestContext) {
+  let mut summary = TestSummary::new();
+
+  summary.record("fast", 1, 0, 10);
+  summary.record("slow", 2, 1, 30);
+  summary.record("medium", 3, 2, 20);
+
+  check_eq!(context, summary.pass_count, 6);
+  check_eq!(context, summary.fail_count, 3);
+  check_eq!(context, summary.ticks, 60);
+
+  summary.sort_slowest_first();
+
+  let results = summary.get_results();
+  check_eq!(context, results.len(), 3);
+  check_eq!(context, results[0].name, "slow");
+  check_eq!(context, results[0].pass_count, 2);
+  check_eq!(context, results[0].fail_count, 1);
+  check_eq!(context, results[1].name, "medium");
+  check_eq!(context, results[2].name, "fast");
+
+  let mut summary = TestSummary::new();
+
+  for _ in 0..MAX_SUITES + 1 {
+    summary.record("suite", 1, 0, 1);
+  }
+
+  check_eq!(context, summary.get_results().len(), MAX_SUITES);
+  check_eq!(context, summary.pass_count, (MAX_SUITES + 1) as u32);
+  check_eq!(context, summary.ticks, (MAX_SUITES + 1) as u64);
+}
+
+/// Test that each module's suite is registered only once.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+fn test_unique_suites(context: &mut TestContext) {
+  for (idx, suite) in TEST_SUITES.iter().enumerate() {
+    let duplicates = TEST_SUITES[idx + 1..]
+      .iter()
+      .filter(|other| other.name == suite.name)
+      .count();
+    check_eq!(context, duplicates, 0);
+  }
+}
+
+/// Test that a failing check records its location.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// The checks run against a separate context so that the intentional failure
+/// is not counted against the test runner tests.
+fn test_failure_location(context: &mut TestContext) {
+  let mut checks = TestContext::new();
+
+  check_eq!(checks, 1, 1);
+  check_none!(context, checks.last_failure);
+
+  mark_fail!(checks, "Intentional failure");
+  let fail_line = line!() - 1;
+
+  check_eq!(context, checks.fail_count, 1);
+  check_not_none!(context, checks.last_failure);
+
+  let Some((file, line)) = checks.last_failure else {
+    return;
+  };
+
+  check_eq!(context, file, file!());
+  check_eq!(context, line, fail_line);
+}
+
+/// Test catching expected panics.
+///
+/// # Parameters
+///
+/// * `context` - The test context.
+///
+/// # Description
+///
+/// Comparing a zero-size range is specified to return None rather than panic.
+/// A failed assertion must be caught, and a guard nested inside another guard
+/// must catch the panic without resuming the outer guard.
+fn test_panic_guard(context: &mut TestContext) {
+  let empty = Range {
+    tag: (),
+    base: 0x1000,
+    size: 0,
+  };
+  let valid = Range {
+    tag: (),
+    base: 0x1000,
+    size: 0x1000,
+  };
+  let mut is_none = false;
+
+  check_no_panic!(context, || is_none = empty.cmp(&valid).is_none());
+  check_eq!(context, is_none, true);
+
+  let addr = 0x1001;
+  check_panics!(context, || assert_eq!(addr & 0xfff, 0));
+  check_no_panic!(context, || assert_eq!((addr - 1) & 0xfff, 0));
+
+  let mut inner_panicked = false;
+  check_no_panic!(context, || {
+    let mut inner = || assert_eq!(addr & 0xfff, 0);
+    inner_panicked = arch::panic_guard::call_guarded(&mut inner);
+  });
+  check_eq!(context, inner_panicked, true);
+}
@@ -0,0 +1,110 @@
+//! TLB Invalidation Recording Utilities
+
+use core::{cmp, ptr};
+
+/// Maximum number of invalidations to record.
+pub const MAX_RECORDS: usize = 16;
+
+/// A recorded invalidation: base virtual address, size, and whether or not the
+/// invalidation was broadcast to all cores.
+type Record = (usize, usize, bool);
+
+/// Recorded TLB invalidations.
+static mut RECORDS: [Record; MAX_RECORDS] = [(0, 0, false); MAX_RECORDS];
+
+/// The number of records. The count may exceed the maximum; only the first
+/// MAX_RECORDS invalidations are recorded.
+static mut RECORD_COUNT: usize = 0;
+
+/// Record a TLB invalidation.
+///
+/// # Parameters
+///
+/// * `virt` - The base virtual address of the invalidated range.
+/// * `size` - The size of the invalidated range.
+/// * `broadcast` - Whether or not the invalidation was broadcast to all cores.
+pub fn record_invalidation(virt: usize, size: usize, broadcast: bool) {
+  unsafe {
+    if RECORD_COUNT < MAX_RECORDS {
+      RECORDS[RECORD_COUNT] = (virt, size, broadcast);
+    }
+
+    RECORD_COUNT += 1;
+  }
+}
+
+/// Clear the recorded invalidations.
+pub fn clear_invalidations() {
+  unsafe {
+    RECORD_COUNT = 0;
+  }
+}
+
+/// Get the total number of invalidations since the last clear.
+pub fn get_invalidation_count() -> usize {
+  unsafe { RECORD_COUNT }
+}
+
+/// Check if a range was invalidated since the last clear.
+///
+/// # Parameters
+///
+/// * `virt` - The base virtual address of the range.
+/// * `size` - The size of the range.
+///
+/// # Returns
+///
+/// True if a recorded local or broadcast invalidation covers the range, false
+/// otherwise.
+pub fn was_invalidated(virt: usize, size: usize) -> bool {
+  find_invalidation(virt, size, |_| true)
+}
+
+/// Check if a range was invalidated on the current core only since the last
+/// clear.
+///
+/// # Parameters
+///
+/// * `virt` - The base virtual address of the range.
+/// * `size` - The size of the range.
+///
+/// # Returns
+///
+/// True if a recorded local invalidation covers the range, false otherwise.
+pub fn was_invalidated_local(virt: usize, size: usize) -> bool {
+  find_invalidation(virt, size, |broadcast| !broadcast)
+}
+
+/// Check if a range was invalidated on all cores since the last clear.
+///
+/// # Parameters
+///
+/// * `virt` - The base virtual address of the range.
+/// * `size` - The size of the range.
+///
+/// # Returns
+///
+/// True if a recorded broadcast invalidation covers the range, false otherwise.
+pub fn was_invalidated_broadcast(virt: usize, size: usize) -> bool {
+  find_invalidation(virt, size, |broadcast| broadcast)
+}
+
+/// Search for a recorded invalidation covering a range.
+///
+/// # Parameters
+///
+/// * `virt` - The base virtual address of the range.
+/// * `size` - The size of the range.
+/// * `filter` - Filter applied to the broadcast flag of each record.
+///
+/// # Returns
+///
+/// True if a matching record covers the range, false otherwise.
+fn find_invalidation(virt: usize, size: usize, filter: impl Fn(bool) -> bool) -> bool {
+  let count = cmp::min(unsafe { RECORD_COUNT }, MAX_RECORDS);
+  let records = unsafe { &*ptr::addr_of!(RECORDS) };
+
+  records[..count]
+    .iter()
+    .any(|&(base, len, broadcast)| filter(broadcast) && virt >= base && (virt - base) + size <= len)
+}